@@ -0,0 +1,330 @@
+//! Guest-side SDK for Webrana WASM plugins
+//!
+//! Mirrors the host's `PluginInput`/`PluginOutput` types, wraps the host
+//! functions, and provides the ABI glue through [`register_handler!`].
+
+use serde::{Deserialize, Serialize};
+
+pub use serde_json::{json, Value};
+
+/// Input passed to plugin execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginInput {
+    /// Name of the skill being called
+    pub action: String,
+    pub params: Value,
+    pub context: PluginContext,
+}
+
+/// Context available to plugins
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginContext {
+    pub working_dir: String,
+    pub project_type: Option<String>,
+    pub user_config: Value,
+}
+
+/// Output from plugin execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginOutput {
+    pub success: bool,
+    pub result: Value,
+    #[serde(default)]
+    pub logs: Vec<String>,
+    #[serde(default)]
+    pub artifacts: Vec<PluginArtifact>,
+    /// Images for the model to look at; vision models see them, others get
+    /// the caption and a saved file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageArtifact>,
+}
+
+/// A PNG, JPEG, GIF or WebP image, base64-encoded (at most 5 MB decoded)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageArtifact {
+    pub media_type: String,
+    pub data_base64: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+}
+
+/// Artifact produced by plugin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginArtifact {
+    pub name: String,
+    pub artifact_type: ArtifactType,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ArtifactType {
+    File,
+    Log,
+    Metric,
+    Event,
+}
+
+impl PluginOutput {
+    pub fn success(result: Value) -> Self {
+        Self {
+            success: true,
+            result,
+            logs: Vec::new(),
+            artifacts: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+
+    pub fn error(message: &str) -> Self {
+        Self {
+            success: false,
+            result: json!({ "error": message }),
+            logs: Vec::new(),
+            artifacts: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+
+    pub fn with_log(mut self, log: &str) -> Self {
+        self.logs.push(log.to_string());
+        self
+    }
+
+    pub fn with_image(mut self, media_type: &str, data_base64: &str, caption: Option<&str>) -> Self {
+        self.images.push(ImageArtifact {
+            media_type: media_type.to_string(),
+            data_base64: data_base64.to_string(),
+            caption: caption.map(str::to_string),
+        });
+        self
+    }
+}
+
+/// Error reported by a host function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostError(pub String);
+
+impl std::fmt::Display for HostError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for HostError {}
+
+/// Functions provided by the Webrana host. Paths are relative to the
+/// working directory; the file functions need `fs:read` / `fs:write`.
+pub mod host {
+    use super::HostError;
+
+    /// Add a line to the skill's logs
+    pub fn log(message: &str) {
+        imp::log(message)
+    }
+
+    pub fn read_file(path: &str) -> Result<String, HostError> {
+        imp::read_file(path)
+    }
+
+    pub fn write_file(path: &str, contents: &str) -> Result<(), HostError> {
+        imp::write_file(path, contents)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    mod imp {
+        use super::HostError;
+        use crate::abi;
+
+        #[link(wasm_import_module = "webrana")]
+        extern "C" {
+            #[link_name = "log"]
+            fn host_log(ptr: u32, len: u32);
+            #[link_name = "read_file"]
+            fn host_read_file(path_ptr: u32, path_len: u32) -> u64;
+            #[link_name = "write_file"]
+            fn host_write_file(path_ptr: u32, path_len: u32, data_ptr: u32, data_len: u32) -> u64;
+        }
+
+        pub fn log(message: &str) {
+            unsafe { host_log(message.as_ptr() as u32, message.len() as u32) }
+        }
+
+        pub fn read_file(path: &str) -> Result<String, HostError> {
+            let packed = unsafe { host_read_file(path.as_ptr() as u32, path.len() as u32) };
+            let reply = unsafe { abi::take(packed) };
+            match abi::host_reply(&reply)? {
+                serde_json::Value::String(contents) => Ok(contents),
+                other => Err(HostError(format!("Unexpected read_file reply: {}", other))),
+            }
+        }
+
+        pub fn write_file(path: &str, contents: &str) -> Result<(), HostError> {
+            let packed = unsafe {
+                host_write_file(
+                    path.as_ptr() as u32,
+                    path.len() as u32,
+                    contents.as_ptr() as u32,
+                    contents.len() as u32,
+                )
+            };
+            let reply = unsafe { abi::take(packed) };
+            abi::host_reply(&reply).map(|_| ())
+        }
+    }
+
+    /// Native fallbacks so plugin logic can be unit tested with `cargo test`
+    #[cfg(not(target_arch = "wasm32"))]
+    mod imp {
+        use super::HostError;
+
+        pub fn log(message: &str) {
+            eprintln!("[plugin] {}", message);
+        }
+
+        pub fn read_file(path: &str) -> Result<String, HostError> {
+            std::fs::read_to_string(path).map_err(|e| HostError(e.to_string()))
+        }
+
+        pub fn write_file(path: &str, contents: &str) -> Result<(), HostError> {
+            std::fs::write(path, contents).map_err(|e| HostError(e.to_string()))
+        }
+    }
+}
+
+/// ABI glue used by [`register_handler!`]. Buffers cross the boundary as
+/// `ptr << 32 | len`.
+#[doc(hidden)]
+pub mod abi {
+    use super::{HostError, PluginInput, PluginOutput};
+    use std::alloc::{alloc, dealloc as free, Layout};
+
+    pub fn pack(ptr: u32, len: u32) -> u64 {
+        ((ptr as u64) << 32) | len as u64
+    }
+
+    pub fn unpack(packed: u64) -> (u32, u32) {
+        ((packed >> 32) as u32, packed as u32)
+    }
+
+    /// Allocate `len` bytes for the host to fill
+    pub fn allocate(len: u32) -> *mut u8 {
+        if len == 0 {
+            return std::ptr::NonNull::dangling().as_ptr();
+        }
+        unsafe { alloc(Layout::from_size_align_unchecked(len as usize, 1)) }
+    }
+
+    /// Free a buffer from [`allocate`]
+    ///
+    /// # Safety
+    /// `ptr` and `len` must come from one `allocate` call.
+    pub unsafe fn deallocate(ptr: *mut u8, len: u32) {
+        if len != 0 {
+            free(ptr, Layout::from_size_align_unchecked(len as usize, 1));
+        }
+    }
+
+    /// Copy `bytes` into a fresh buffer and pack it for the host
+    pub fn give(bytes: &[u8]) -> u64 {
+        let ptr = allocate(bytes.len() as u32);
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len()) };
+        pack(ptr as usize as u32, bytes.len() as u32)
+    }
+
+    /// Take ownership of a packed buffer the host allocated through us
+    ///
+    /// # Safety
+    /// `packed` must describe a live buffer from [`allocate`].
+    pub unsafe fn take(packed: u64) -> Vec<u8> {
+        let (ptr, len) = unpack(packed);
+        let ptr = ptr as usize as *mut u8;
+        let bytes = std::slice::from_raw_parts(ptr, len as usize).to_vec();
+        deallocate(ptr, len);
+        bytes
+    }
+
+    /// Host replies are `{"ok": value}` or `{"error": message}`
+    pub fn host_reply(reply: &[u8]) -> Result<serde_json::Value, HostError> {
+        let mut value: serde_json::Value = serde_json::from_slice(reply)
+            .map_err(|e| HostError(format!("Invalid host reply: {}", e)))?;
+        if let Some(error) = value.get("error").and_then(|e| e.as_str()) {
+            return Err(HostError(error.to_string()));
+        }
+        Ok(value.get_mut("ok").map(serde_json::Value::take).unwrap_or_default())
+    }
+
+    /// Decode the input, run `handler` and encode its output
+    pub fn execute(input: &[u8], handler: fn(PluginInput) -> PluginOutput) -> Vec<u8> {
+        let output = match serde_json::from_slice::<PluginInput>(input) {
+            Ok(input) => handler(input),
+            Err(e) => PluginOutput::error(&format!("Invalid plugin input: {}", e)),
+        };
+        serde_json::to_vec(&output).unwrap_or_default()
+    }
+}
+
+/// Export `handler` as the plugin entry point, together with the
+/// allocation functions the host needs:
+///
+/// ```ignore
+/// webrana_plugin_sdk::register_handler!(handle);
+///
+/// fn handle(input: PluginInput) -> PluginOutput { ... }
+/// ```
+#[macro_export]
+macro_rules! register_handler {
+    ($handler:path) => {
+        #[no_mangle]
+        pub extern "C" fn webrana_alloc(len: u32) -> u32 {
+            $crate::abi::allocate(len) as usize as u32
+        }
+
+        /// # Safety
+        /// Only called by the host with a buffer from `webrana_alloc`.
+        #[no_mangle]
+        pub unsafe extern "C" fn webrana_dealloc(ptr: u32, len: u32) {
+            $crate::abi::deallocate(ptr as usize as *mut u8, len)
+        }
+
+        /// # Safety
+        /// Only called by the host with a buffer from `webrana_alloc`.
+        #[no_mangle]
+        pub unsafe extern "C" fn webrana_execute(ptr: u32, len: u32) -> u64 {
+            let input = $crate::abi::take($crate::abi::pack(ptr, len));
+            $crate::abi::give(&$crate::abi::execute(&input, $handler))
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo(input: PluginInput) -> PluginOutput {
+        PluginOutput::success(json!({ "action": input.action, "dir": input.context.working_dir }))
+            .with_log("echoed")
+    }
+
+    #[test]
+    fn test_execute_round_trip() {
+        let input = br#"{"action":"echo","params":{},"context":{"working_dir":"/w","project_type":null,"user_config":null}}"#;
+        let output: PluginOutput = serde_json::from_slice(&abi::execute(input, echo)).unwrap();
+        assert!(output.success);
+        assert_eq!(output.result, json!({ "action": "echo", "dir": "/w" }));
+        assert_eq!(output.logs, vec!["echoed"]);
+
+        let bad: PluginOutput = serde_json::from_slice(&abi::execute(b"{}", echo)).unwrap();
+        assert!(!bad.success);
+    }
+
+    #[test]
+    fn test_host_reply() {
+        assert_eq!(abi::host_reply(br#"{"ok":"text"}"#), Ok(json!("text")));
+        assert_eq!(abi::host_reply(br#"{"ok":null}"#), Ok(Value::Null));
+        assert_eq!(
+            abi::host_reply(br#"{"error":"fs:read not granted"}"#),
+            Err(HostError("fs:read not granted".to_string()))
+        );
+        assert_eq!(abi::unpack(abi::pack(7, 9)), (7, 9));
+    }
+}
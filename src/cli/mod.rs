@@ -1,5 +1,9 @@
 use clap::{Parser, Subcommand};
 
+use crate::llm;
+use crate::plugins;
+use crate::ui::OutputTemplate;
+
 #[derive(Parser)]
 #[command(name = "webrana")]
 #[command(author = "Webrana Team")]
@@ -9,10 +13,14 @@ pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
 
-    /// Enable verbose output
+    /// Enable verbose output (debug logs)
     #[arg(short, long, global = true)]
     pub verbose: bool,
 
+    /// Only show warnings and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
     /// Enable auto mode (no confirmation prompts)
     #[arg(short, long, global = true)]
     pub auto: bool,
@@ -24,6 +32,51 @@ pub struct Cli {
     /// Working directory for the agent
     #[arg(short = 'd', long, global = true)]
     pub workdir: Option<String>,
+
+    /// Mount the working directory read-only in the shell sandbox
+    #[arg(long, global = true)]
+    pub read_only: bool,
+
+    /// Compact the conversation when it reaches this share of the model's context window (e.g. 80%)
+    #[arg(long, global = true, value_name = "PERCENT")]
+    pub auto_compact_at: Option<String>,
+
+    /// Load environment variables from a .env file in the working directory (default: .env)
+    #[arg(long, global = true, value_name = "PATH", num_args = 0..=1, default_missing_value = ".env")]
+    pub env_file: Option<String>,
+
+    /// Plain output for logs: no colors, banner, emoji or spinners (automatic under CI)
+    #[arg(long, global = true, env = "WEBRANA_PLAIN", value_parser = clap::builder::FalseyValueParser::new())]
+    pub plain: bool,
+
+    /// Screen-reader-friendly output: text labels instead of emoji and symbols, sentences instead of spinners and tables
+    #[arg(long, global = true, env = "WEBRANA_A11Y", value_parser = clap::builder::FalseyValueParser::new())]
+    pub a11y: bool,
+
+    /// Air-gapped use: no update checks, status calls, registry lookups or telemetry export; LLM calls go to a local model only
+    #[arg(long, global = true, env = "WEBRANA_OFFLINE", value_parser = clap::builder::FalseyValueParser::new())]
+    pub offline: bool,
+
+    /// Don't print the startup banner (it always goes to stderr)
+    #[arg(long, global = true, env = "WEBRANA_NO_BANNER", value_parser = clap::builder::FalseyValueParser::new())]
+    pub no_banner: bool,
+
+    /// Reproducible mode: send this sampling seed (OpenAI, Ollama) with
+    /// temperature 0 and top_p 1, and bypass the response cache
+    #[arg(long, global = true, env = "WEBRANA_SEED", value_name = "N")]
+    pub seed: Option<u64>,
+
+    /// Sampling temperature for every request (overrides the --seed default of 0)
+    #[arg(long, global = true, value_name = "T")]
+    pub temperature: Option<f32>,
+
+    /// Nucleus sampling for every request (overrides the --seed default of 1)
+    #[arg(long, global = true, value_name = "P")]
+    pub top_p: Option<f32>,
+
+    /// Recall past sessions from every project, not just the current one
+    #[arg(long, global = true, env = "WEBRANA_GLOBAL_MEMORY", value_parser = clap::builder::FalseyValueParser::new())]
+    pub global_memory: bool,
 }
 
 #[derive(Subcommand)]
@@ -31,12 +84,37 @@ pub enum Commands {
     /// Start a chat session with a message
     Chat {
         /// The message to send
-        #[arg(required = true)]
-        message: String,
+        #[arg(required_unless_present = "script")]
+        message: Option<String>,
+
+        /// Send the turns of a YAML script in order, checking any expectations
+        #[arg(long, value_name = "FILE", conflicts_with = "message")]
+        script: Option<String>,
+
+        /// Wait for Enter before each scripted turn after the first
+        #[arg(long, requires = "script")]
+        interactive_between: bool,
+
+        /// Save the scripted conversation (.html for HTML, Markdown otherwise)
+        #[arg(long, value_name = "FILE", requires = "script")]
+        transcript: Option<String>,
 
         /// Enable auto mode for this chat
         #[arg(short, long)]
         auto: bool,
+
+        /// Only allow these skills (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        allow: Vec<String>,
+
+        /// Deny these skills (comma-separated, takes precedence over --allow)
+        #[arg(long, value_delimiter = ',')]
+        deny: Vec<String>,
+
+        /// Write newline-delimited JSON events to stdout as the turn runs
+        /// (start, attempt, delta, tool_call, tool_result, done or error)
+        #[arg(long, conflicts_with = "script", requires = "message")]
+        json_stream: bool,
     },
 
     /// One-shot query (supports pipe input: cat file | webrana ask "explain")
@@ -53,6 +131,26 @@ pub enum Commands {
         #[arg(long)]
         json: bool,
 
+        /// Write newline-delimited JSON events to stdout as the answer
+        /// streams (start, attempt, delta, tool_call, tool_result, done or error)
+        #[arg(long, conflicts_with_all = ["json", "print", "output_template", "rag", "compare"])]
+        json_stream: bool,
+
+        /// With --print: drop a leading persona clause such as
+        /// "As the Code Reviewer, " (config: strip_persona_prefix)
+        #[arg(long, requires = "print")]
+        clean: bool,
+
+        /// Format the output, e.g. '{model}: {response}'. Placeholders:
+        /// {query}, {response}, {tokens}, {model}, {cost}; '{{'/'}}' for braces
+        #[arg(long, value_name = "TEMPLATE", conflicts_with = "json")]
+        output_template: Option<OutputTemplate>,
+
+        /// Index the working directory, add matching code to the prompt and
+        /// list the cited sources after the answer
+        #[arg(long, conflicts_with = "output_template")]
+        rag: bool,
+
         /// Model to use (overrides default)
         #[arg(short, long)]
         model: Option<String>,
@@ -60,13 +158,31 @@ pub enum Commands {
         /// Provider to use (overrides default)
         #[arg(long)]
         provider: Option<String>,
+
+        /// Ask the prompt under two system prompt files with the same seed
+        /// and show both answers side by side
+        #[arg(long, num_args = 2, value_names = ["SYSTEM_A", "SYSTEM_B"], conflicts_with_all = ["rag", "output_template", "print"])]
+        compare: Option<Vec<String>>,
+
+        /// With --compare: show a unified diff of the answers instead of columns
+        #[arg(long, requires = "compare")]
+        diff: bool,
     },
 
     /// Run a task autonomously until completion
     Run {
         /// The task to execute
-        #[arg(required = true)]
-        task: String,
+        #[arg(required_unless_present = "task_file")]
+        task: Option<String>,
+
+        /// Read the task from a markdown file, with optional YAML front
+        /// matter: title, acceptance_criteria, constraints, out_of_scope
+        #[arg(long, value_name = "PATH", conflicts_with = "task")]
+        task_file: Option<std::path::PathBuf>,
+
+        /// Also check the task file's acceptance criteria every K iterations
+        #[arg(long, value_name = "K", requires = "task_file")]
+        assess_every: Option<usize>,
 
         /// Maximum iterations (default: 25)
         #[arg(short, long, default_value = "25")]
@@ -75,16 +191,80 @@ pub enum Commands {
         /// Skip dangerous operation confirmations
         #[arg(long)]
         yolo: bool,
+
+        /// Only allow these skills (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        allow: Vec<String>,
+
+        /// Deny these skills (comma-separated, takes precedence over --allow)
+        #[arg(long, value_delimiter = ',')]
+        deny: Vec<String>,
+
+        /// Only allow file writes matching this glob, e.g. 'src/**' (repeatable)
+        #[arg(long = "allow-paths", value_name = "GLOB")]
+        allow_paths: Vec<String>,
+
+        /// Reject file writes matching this glob (repeatable, takes precedence)
+        #[arg(long = "deny-paths", value_name = "GLOB")]
+        deny_paths: Vec<String>,
+
+        /// Step through the run: continue, skip, abort or edit each tool call
+        #[arg(long)]
+        confirm_each: bool,
+
+        /// Stop the run after this much wall-clock time, e.g. 30m or 2h
+        #[arg(long, value_name = "DURATION")]
+        deadline: Option<String>,
+
+        /// Time limit for every tool call, replacing skill_timeouts, e.g. 90s
+        #[arg(long, value_name = "DURATION")]
+        tool_timeout: Option<String>,
+
+        /// auto (default), none for text-only answers, or a skill name to
+        /// call it first
+        #[arg(long, value_name = "CHOICE", default_value = "auto")]
+        tool_choice: llm::ToolChoice,
     },
 
     /// List available agents
     Agents,
 
-    /// List available skills
-    Skills,
+    /// List available skills, run one directly, or export their schemas
+    Skills {
+        #[command(subcommand)]
+        command: Option<SkillsCommands>,
+    },
 
-    /// Show current configuration
-    Config,
+    /// Show configuration, or get/set/unset one key
+    Config {
+        #[command(subcommand)]
+        command: Option<ConfigCommands>,
+    },
+
+    /// Project memory file (WEBRANA.md) added to the system prompt
+    Memory {
+        #[command(subcommand)]
+        command: MemoryCommands,
+    },
+
+    /// Inspect the context of a saved session
+    Context {
+        #[command(subcommand)]
+        command: ContextCommands,
+    },
+
+    /// Search the conversation memory of past sessions
+    MemorySearch {
+        query: String,
+
+        /// Maximum number of results
+        #[arg(short, long, default_value_t = 5)]
+        limit: usize,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
     /// Crew management (custom AI personas)
     Crew {
@@ -118,13 +298,77 @@ pub enum Commands {
         /// Index the codebase before searching
         #[arg(long)]
         index: bool,
+
+        /// Only search files matching this glob (e.g. "src/auth", "*.rs")
+        #[arg(long, value_name = "GLOB")]
+        file_glob: Option<String>,
+
+        /// Only search files in this language (e.g. rust, python, or an extension)
+        #[arg(long)]
+        language: Option<String>,
+
+        /// Only search chunks overlapping a symbol whose name contains this
+        #[arg(long, value_name = "TEXT")]
+        symbol_contains: Option<String>,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+
+        /// Write results to this file (in --format) and print only a summary
+        #[arg(long, value_name = "PATH")]
+        save_output: Option<String>,
+    },
+
+    /// Find duplicated or near-duplicate code using embeddings
+    Dupes {
+        /// Directory to check (default: current directory)
+        #[arg(long)]
+        dir: Option<String>,
+
+        /// Minimum cosine similarity (0.0-1.0) for a pair to be reported
+        #[arg(short, long, default_value = "0.9")]
+        threshold: f32,
+
+        /// Report at most this many pairs
+        #[arg(short = 'n', long, default_value = "20")]
+        top: usize,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Embedding debug tools
+    #[command(hide = true)]
+    Embeddings {
+        #[command(subcommand)]
+        command: EmbeddingsCommands,
     },
 
     /// Index codebase for semantic search
     Index {
         /// Directory to index (default: current directory)
-        #[arg(short, long)]
+        #[arg(long)]
         dir: Option<String>,
+
+        /// Instead of indexing, write every stored chunk with its vector to
+        /// this file as JSON Lines
+        #[arg(long, value_name = "PATH")]
+        export: Option<String>,
+
+        /// With --export: read this Qdrant collection instead of the
+        /// project index (needs the `qdrant` feature)
+        #[arg(long, value_name = "COLLECTION", requires = "export")]
+        qdrant: Option<String>,
+
+        /// With --export: don't ask before writing a very large file
+        #[arg(short, long, requires = "export")]
+        yes: bool,
+
+        /// Print the counts and each file's outcome as JSON
+        #[arg(long, conflicts_with = "export")]
+        json: bool,
     },
 
     /// Scan for secrets and credentials in codebase
@@ -144,6 +388,67 @@ pub enum Commands {
         /// Fail with exit code 1 if secrets found
         #[arg(long)]
         fail_on_secrets: bool,
+
+        /// Fail with exit code 1 only if a finding is at or above this severity
+        /// (low, medium, high, critical); independent of --min-severity
+        #[arg(long, value_name = "SEVERITY")]
+        fail_on: Option<String>,
+
+        /// Always exit with code 0, even if secrets are found (report-only runs)
+        #[arg(long)]
+        exit_zero: bool,
+
+        /// Print nothing when no secrets are found (for pre-commit hooks)
+        #[arg(long)]
+        quiet_clean: bool,
+
+        /// Write results to this file (in --format) and print only a summary
+        #[arg(long, value_name = "PATH")]
+        save_output: Option<String>,
+
+        /// With --format json: write findings as a JSON array while scanning,
+        /// in walk order, with the summary on stderr
+        #[arg(long)]
+        stream: bool,
+
+        /// Scan the lines added by each commit of the current branch instead
+        /// of the working tree, reporting the commit that introduced each secret
+        #[arg(long)]
+        git_history: bool,
+
+        /// With --git-history: only commits after this ref (e.g. a tag or main)
+        #[arg(long, value_name = "REF", requires = "git_history")]
+        since: Option<String>,
+
+        /// Save progress to a checkpoint while scanning and skip the files an
+        /// interrupted earlier `--resume` scan already did
+        #[arg(long, conflicts_with_all = ["git_history", "stream"])]
+        resume: bool,
+    },
+
+    /// Scaffold a new project from a template
+    New {
+        /// Template (rust-cli, rust-axum-service, python-fastapi, node-ts-lib) or a description for the agent
+        template: String,
+
+        /// Project name and directory (default: template name)
+        name: Option<String>,
+
+        /// Template variable, repeatable (e.g. --var description="My tool")
+        #[arg(long = "var", value_name = "KEY=VALUE")]
+        vars: Vec<String>,
+
+        /// Generate into a non-empty directory
+        #[arg(long)]
+        force: bool,
+
+        /// List the files that would be created without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Skip the build/test/lint checklist after generation
+        #[arg(long)]
+        skip_checks: bool,
     },
 
     /// Plugin management commands
@@ -155,15 +460,93 @@ pub enum Commands {
     /// Show version and build information
     Version,
 
-    /// Check system requirements and configuration
-    Doctor,
+    /// Check system requirements, configuration and security posture
+    Doctor {
+        /// Print the checks as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Offer to fix failing checks (e.g. run a missing or stale secret scan)
+        #[arg(long, conflicts_with = "json")]
+        fix: bool,
+
+        /// Also test DNS, proxy and TLS to each provider, update and registry host
+        #[arg(long)]
+        check_network: bool,
+    },
 
     /// Check for updates
-    Update,
+    Update {
+        /// Download the release for this platform and replace the running binary
+        #[arg(long)]
+        install: bool,
+    },
+
+    /// Show a GitLab merge request or Bitbucket pull request: branches, changed files and discussions
+    Mr {
+        /// Merge/pull request URL
+        #[arg(required_unless_present = "number")]
+        url: Option<String>,
+
+        /// Merge request number in the origin remote's project
+        #[arg(long = "mr", value_name = "NUMBER", conflicts_with = "url")]
+        number: Option<u64>,
+
+        /// Print the review context as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
     /// Show Webrana API usage status
     Status,
 
+    /// Summarize recorded token usage and estimated cost
+    Usage {
+        /// Only count usage on or after this day (YYYY-MM-DD, UTC)
+        #[arg(long, value_name = "DATE")]
+        since: Option<String>,
+
+        /// Group by provider, model or day
+        #[arg(long, value_name = "GROUP", default_value = "day")]
+        by: String,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Summarize this machine's runs, sessions, cost, skills, secret scans
+    /// and security events, for a standup doc or a central rollup
+    Digest {
+        /// Period to cover, e.g. 7d, 24h or 2w
+        #[arg(long, value_name = "AGE", default_value = "7d")]
+        since: String,
+
+        /// Output format
+        #[arg(long, default_value = "markdown", value_parser = ["markdown", "json"])]
+        format: String,
+    },
+
+    /// Revert files changed by a run or session: all of them after a
+    /// summary, one with --file, or each in turn with --interactive
+    Undo {
+        /// Run to undo, or an earlier undo (default: the latest recorded)
+        #[arg(long, value_name = "ID", conflicts_with = "session")]
+        run: Option<String>,
+
+        /// Interactive session to undo
+        #[arg(long, value_name = "ID")]
+        session: Option<String>,
+
+        /// Only restore this file, as the run wrote it
+        #[arg(long, value_name = "PATH", conflicts_with = "interactive")]
+        file: Option<String>,
+
+        /// Show what undoing each file would change and ask
+        #[arg(long)]
+        interactive: bool,
+    },
+
     /// Login to Webrana API (re-register device)
     Login,
 
@@ -171,6 +554,151 @@ pub enum Commands {
     Logout,
 }
 
+#[derive(Subcommand)]
+pub enum SkillsCommands {
+    /// List available skills
+    List,
+
+    /// Invoke a skill directly with JSON arguments, without the LLM
+    #[command(alias = "test")]
+    Run {
+        /// Skill name
+        name: String,
+
+        /// JSON arguments
+        #[arg(long, default_value = "{}")]
+        args: String,
+
+        /// Output the result as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Don't ask before running skills that require confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Print every tool definition (built-in, plugin and MCP) as a provider's tools JSON
+    Export {
+        /// Tools array format: openai or anthropic
+        #[arg(long, default_value = "openai", value_parser = ["openai", "anthropic"])]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Show the current configuration
+    Show,
+
+    /// Print the value of a dotted key (e.g. shell.timeout_secs)
+    Get {
+        key: String,
+
+        /// Read the project file (.webrana/config.toml) instead of the effective value
+        #[arg(long)]
+        project: bool,
+    },
+
+    /// Set a dotted key, validated against the known keys
+    Set {
+        key: String,
+        value: String,
+
+        /// Write the project file (.webrana/config.toml) instead of the global one
+        #[arg(long)]
+        project: bool,
+    },
+
+    /// Remove a key from the config file
+    Unset {
+        key: String,
+
+        /// Edit the project file (.webrana/config.toml) instead of the global one
+        #[arg(long)]
+        project: bool,
+    },
+
+    /// Prompt for a value and store it age-encrypted (needs the `age` feature)
+    Encrypt {
+        key: String,
+
+        /// age public key to encrypt to (repeatable; default: encryption.recipients)
+        #[arg(long = "recipient", value_name = "AGE1...")]
+        recipients: Vec<String>,
+
+        /// Write the project file (.webrana/config.toml) instead of the global one
+        #[arg(long)]
+        project: bool,
+    },
+
+    /// List age recipients; adding or removing one re-encrypts the file's values
+    Recipients {
+        /// Recipient to add (repeatable)
+        #[arg(long, value_name = "AGE1...")]
+        add: Vec<String>,
+
+        /// Recipient to remove (repeatable)
+        #[arg(long, value_name = "AGE1...")]
+        remove: Vec<String>,
+
+        /// Use the project file (.webrana/config.toml) instead of the global one
+        #[arg(long)]
+        project: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ContextCommands {
+    /// Size of each message and what is trimmed first, as `/context` shows it
+    Stats {
+        /// Session file written by `/save`
+        #[arg(long, value_name = "FILE")]
+        session: std::path::PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MemoryCommands {
+    /// Print the project memory file (WEBRANA.md, else AGENTS.md)
+    Show,
+
+    /// Open the project memory file in $VISUAL or $EDITOR, creating it if needed
+    Edit,
+
+    /// Create WEBRANA.md with Build, Conventions and Gotchas headings
+    Init {
+        /// Replace an existing WEBRANA.md
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Forget past sessions in the conversation memory (this project's, unless --global-memory)
+    Prune {
+        /// Age of the sessions to forget, e.g. 90d, 12h or 2w
+        #[arg(long, value_name = "AGE")]
+        older_than: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum EmbeddingsCommands {
+    /// Embed two texts and print their cosine similarity
+    #[command(alias = "compare")]
+    Similarity {
+        a: String,
+        b: String,
+
+        /// Use the deterministic mock provider even if OPENAI_API_KEY is set
+        #[arg(long)]
+        mock: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum PluginCommands {
     /// List installed plugins
@@ -178,10 +706,21 @@ pub enum PluginCommands {
 
     /// Install a plugin from local path
     Install {
-        /// Path to plugin directory
+        /// Path to plugin directory, or a .wbp / .tar.gz made by `plugin pack`
         path: String,
     },
 
+    /// Bundle a plugin's manifest and entry point into one checksummed archive
+    #[command(alias = "export")]
+    Pack {
+        /// Installed plugin ID, or path to a plugin directory
+        plugin: String,
+
+        /// Archive to write (default: <id>-<version>.wbp)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
     /// Uninstall a plugin
     Uninstall {
         /// Plugin ID to uninstall
@@ -205,6 +744,29 @@ pub enum PluginCommands {
         /// Plugin ID
         plugin_id: String,
     },
+
+    /// Create a WASM plugin project (using webrana-plugin-sdk) in the current directory
+    New {
+        /// Plugin ID, also the project directory
+        name: String,
+
+        /// Name of the generated skill
+        #[arg(long, default_value = plugins::DEFAULT_SKILL)]
+        skill: String,
+    },
+
+    /// Run a skill of an installed plugin and print its result as JSON
+    Run {
+        /// Plugin ID
+        plugin_id: String,
+
+        /// Skill name
+        skill: String,
+
+        /// Skill parameters as a JSON object
+        #[arg(long, default_value = "{}")]
+        params: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -216,6 +778,18 @@ pub enum McpCommands {
         port: u16,
     },
 
+    /// Serve Webrana skills and the tools of the configured upstream servers
+    /// on one endpoint; upstream tools are named <server>__<tool>
+    Proxy {
+        /// Port to listen on
+        #[arg(short, long, default_value = "3000")]
+        port: u16,
+
+        /// Upstream server list (default: mcp.toml next to config.toml)
+        #[arg(long, value_name = "PATH")]
+        config: Option<String>,
+    },
+
     /// List connected MCP servers
     List,
 
@@ -304,6 +878,19 @@ pub enum CrewCommands {
     /// Clear active crew (use default agent)
     Clear,
 
+    /// Clone a crew member (or template) under a new ID
+    Clone {
+        /// Source crew ID or template name
+        src: String,
+
+        /// ID for the new crew
+        new_id: String,
+
+        /// Display name for the new crew
+        #[arg(long)]
+        name: Option<String>,
+    },
+
     /// Export crew to YAML
     Export {
         /// Crew ID
@@ -314,10 +901,15 @@ pub enum CrewCommands {
         output: Option<String>,
     },
 
-    /// Import crew from YAML file
+    /// Import crew from a YAML file or URL
     Import {
         /// YAML file path
-        file: String,
+        #[arg(required_unless_present = "url", conflicts_with = "url")]
+        file: Option<String>,
+
+        /// Fetch the YAML over https; gist page URLs are read raw
+        #[arg(long)]
+        url: Option<String>,
     },
 
     /// List available templates
@@ -0,0 +1,559 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{Array, Document, Item, Value};
+
+/// Which config file a command reads or writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigScope {
+    /// `~/.config/webrana/config.toml`
+    Global,
+    /// `.webrana/config.toml` in the working directory, layered over the global file
+    Project,
+}
+
+impl ConfigScope {
+    pub fn path(&self) -> Result<PathBuf> {
+        match self {
+            Self::Global => super::Settings::config_path(),
+            Self::Project => Ok(super::Settings::project_config_path()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyType {
+    Bool,
+    /// Non-negative integer
+    Integer,
+    Float { min: f64, max: f64 },
+    String,
+    Enum(&'static [&'static str]),
+    /// Comma-separated on the command line
+    StringList,
+    /// Never written by `config set`
+    Secret,
+}
+
+/// A settable config key. `*` matches any single name segment (a model name,
+/// a skill name).
+#[derive(Debug, Clone, Copy)]
+pub struct ConfigKey {
+    pub path: &'static str,
+    pub kind: KeyType,
+    /// May be set in the project file. Keys that weaken safety checks or
+    /// redirect credentials stay global so a cloned repo can't change them.
+    pub project: bool,
+}
+
+const fn key(path: &'static str, kind: KeyType, project: bool) -> ConfigKey {
+    ConfigKey { path, kind, project }
+}
+
+const PROVIDERS: &[&str] = &["anthropic", "openai", "openai_compatible", "ollama", "webrana", "replay"];
+
+pub const CONFIG_KEYS: &[ConfigKey] = &[
+    key("default_model", KeyType::String, true),
+    key("default_agent", KeyType::String, true),
+    key("load_dotenv", KeyType::Bool, false),
+    key("otel", KeyType::Bool, false),
+    key("auto_compact_at", KeyType::Float { min: 0.01, max: 1.0 }, true),
+    key("safety.confirm_file_write", KeyType::Bool, false),
+    key("safety.confirm_file_delete", KeyType::Bool, false),
+    key("safety.confirm_shell_execute", KeyType::Bool, false),
+    key("safety.allowed_commands", KeyType::StringList, false),
+    key("safety.blocked_paths", KeyType::StringList, false),
+    key("safety.egress_allowlist", KeyType::StringList, false),
+    key("shell.sandbox", KeyType::Enum(&["none", "docker", "podman"]), false),
+    key("shell.image", KeyType::String, false),
+    key("shell.read_only", KeyType::Bool, false),
+    key("shell.allow_network", KeyType::Bool, false),
+    key("shell.cpus", KeyType::String, true),
+    key("shell.memory", KeyType::String, true),
+    key("shell.fallback", KeyType::Enum(&["deny", "host"]), false),
+    key("shell.timeout_secs", KeyType::Integer, true),
+    key("rate_limit.requests_per_minute", KeyType::Integer, false),
+    key("rate_limit.background_share", KeyType::Float { min: 0.0, max: 1.0 }, false),
+    key("tool_output.keep_colors", KeyType::Bool, true),
+    key("tool_output.ephemeral", KeyType::Bool, true),
+    key("tool_output.keep_iterations", KeyType::Integer, true),
+    key("show_provenance", KeyType::Bool, true),
+    key("strip_persona_prefix", KeyType::Bool, true),
+    key("strict_prompt_variables", KeyType::Bool, true),
+    key("http.ca_bundle", KeyType::String, false),
+    key("http.ca_replace", KeyType::Bool, false),
+    key("http.client_cert", KeyType::String, false),
+    key("http.client_key", KeyType::String, false),
+    key("http.insecure_skip_verify", KeyType::Bool, false),
+    key("encryption.recipients", KeyType::StringList, true),
+    key("vcs.gitlab_url", KeyType::String, false),
+    key("vcs.gitlab_token", KeyType::Secret, false),
+    key("vcs.bitbucket_api_url", KeyType::String, false),
+    key("vcs.bitbucket_token", KeyType::Secret, false),
+    key("sampling.seed", KeyType::Integer, true),
+    key("sampling.temperature", KeyType::Float { min: 0.0, max: 2.0 }, true),
+    key("sampling.top_p", KeyType::Float { min: 0.0, max: 1.0 }, true),
+    key("conversation_memory.enabled", KeyType::Bool, true),
+    key("conversation_memory.global", KeyType::Bool, false),
+    key("conversation_memory.min_score", KeyType::Float { min: 0.0, max: 1.0 }, true),
+    key("conversation_memory.max_chars", KeyType::Integer, true),
+    key("embeddings.provider", KeyType::Enum(&["openai", "ollama", "mock"]), false),
+    key("embeddings.model", KeyType::String, true),
+    key("embeddings.dimension", KeyType::Integer, true),
+    key("embeddings.base_url", KeyType::String, false),
+    key("context_budget.system", KeyType::Float { min: 0.0, max: 1.0 }, true),
+    key("context_budget.rag", KeyType::Float { min: 0.0, max: 1.0 }, true),
+    key("context_budget.conversation", KeyType::Float { min: 0.0, max: 1.0 }, true),
+    key("ui.banner", KeyType::Enum(&["full", "compact", "off"]), true),
+    key("ui.warm_start_commits", KeyType::Integer, true),
+    key("run.dirty_tree", KeyType::Enum(&["warn", "block", "stash", "branch"]), true),
+    key("run.stuck_after", KeyType::Integer, true),
+    key("skill_timeouts.default_secs", KeyType::Integer, true),
+    key("skill_timeouts.skills.*", KeyType::Integer, true),
+    key("models.*.provider", KeyType::Enum(PROVIDERS), false),
+    key("models.*.model", KeyType::String, false),
+    key("models.*.base_url", KeyType::String, false),
+    key("models.*.api_key_env", KeyType::String, false),
+    key("models.*.api_key", KeyType::Secret, false),
+    key("models.*.temperature", KeyType::Float { min: 0.0, max: 2.0 }, false),
+    key("models.*.max_tokens", KeyType::Integer, false),
+    key("models.*.context_window", KeyType::Integer, false),
+    key("models.*.fallback", KeyType::StringList, false),
+    key("models.*.edit_format", KeyType::Enum(&["search_replace", "unified_diff"]), false),
+    key("models.*.max_tools", KeyType::Integer, false),
+    key("models.*.max_tool_bytes", KeyType::Integer, false),
+    key("models.*.vision", KeyType::Bool, false),
+];
+
+fn matches_pattern(pattern: &str, key: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('.').collect();
+    let key: Vec<&str> = key.split('.').collect();
+    pattern.len() == key.len()
+        && pattern
+            .iter()
+            .zip(&key)
+            .all(|(p, k)| !k.is_empty() && (*p == "*" || p == k))
+}
+
+/// Find the schema entry for `key`; unknown keys get a "did you mean" hint
+pub fn lookup_key(key: &str) -> Result<&'static ConfigKey> {
+    if let Some(found) = CONFIG_KEYS.iter().find(|k| matches_pattern(k.path, key)) {
+        return Ok(found);
+    }
+
+    let segments: Vec<&str> = key.split('.').collect();
+    let suggestion = CONFIG_KEYS
+        .iter()
+        .map(|k| {
+            // Fill wildcards from the key so `models.claude.temprature` suggests
+            // `models.claude.temperature`
+            let candidate: Vec<&str> = k
+                .path
+                .split('.')
+                .enumerate()
+                .map(|(i, p)| match (p, segments.get(i)) {
+                    ("*", Some(s)) => *s,
+                    (p, _) => p,
+                })
+                .collect();
+            let candidate = candidate.join(".");
+            (edit_distance(key, &candidate), candidate)
+        })
+        .min_by_key(|(distance, candidate)| (!candidate.starts_with(key), *distance))
+        .filter(|(distance, candidate)| {
+            candidate.starts_with(key) || *distance <= (key.len() / 3).max(3)
+        });
+
+    match suggestion {
+        Some((_, candidate)) => {
+            anyhow::bail!("Unknown config key '{}'. Did you mean '{}'?", key, candidate)
+        }
+        None => anyhow::bail!("Unknown config key '{}'", key),
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+impl ConfigKey {
+    /// Parse and validate a command-line value for this key
+    pub fn parse_value(&self, key: &str, raw: &str) -> Result<Value> {
+        let raw = raw.trim();
+        let value = match self.kind {
+            KeyType::Bool => match raw {
+                "true" => Value::from(true),
+                "false" => Value::from(false),
+                _ => anyhow::bail!("'{}' must be true or false, got '{}'", key, raw),
+            },
+            KeyType::Integer => {
+                let n: u32 = raw.parse().map_err(|_| {
+                    anyhow::anyhow!("'{}' must be a non-negative integer, got '{}'", key, raw)
+                })?;
+                Value::from(n as i64)
+            }
+            KeyType::Float { min, max } => {
+                let n: f64 = raw
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("'{}' must be a number, got '{}'", key, raw))?;
+                if !(min..=max).contains(&n) {
+                    anyhow::bail!("'{}' must be between {} and {}, got {}", key, min, max, raw);
+                }
+                Value::from(n)
+            }
+            KeyType::String => {
+                if raw.is_empty() {
+                    anyhow::bail!("'{}' must not be empty (use `config unset` to remove it)", key);
+                }
+                Value::from(raw)
+            }
+            KeyType::Enum(allowed) => {
+                if !allowed.contains(&raw) {
+                    anyhow::bail!(
+                        "Invalid value '{}' for '{}': expected one of {}",
+                        raw,
+                        key,
+                        allowed.join(", ")
+                    );
+                }
+                Value::from(raw)
+            }
+            KeyType::StringList => {
+                let mut array = Array::new();
+                for item in raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                    array.push(item);
+                }
+                Value::Array(array)
+            }
+            KeyType::Secret if key.starts_with("models.") => anyhow::bail!(
+                "Refusing to store '{}' in plain text. Put the key in an environment variable \
+                 and run `webrana config set {}_env <VAR_NAME>` instead",
+                key,
+                key
+            ),
+            KeyType::Secret => anyhow::bail!(
+                "Refusing to store '{}' in plain text. Use `webrana config encrypt {}` or its \
+                 environment variable instead",
+                key,
+                key
+            ),
+        };
+        Ok(value)
+    }
+}
+
+/// A config file edited in place, keeping comments and unrelated tables
+pub struct ConfigFile {
+    path: PathBuf,
+    doc: Document,
+}
+
+impl ConfigFile {
+    /// Open `path`; a missing file starts empty and is created on `save`
+    pub fn load(path: &Path) -> Result<Self> {
+        let doc = if path.exists() {
+            fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?
+                .parse::<Document>()
+                .with_context(|| format!("Failed to parse {}", path.display()))?
+        } else {
+            Document::new()
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            doc,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.item(key)?.as_value().map(display_value)
+    }
+
+    /// A string value without TOML quoting
+    pub fn get_str(&self, key: &str) -> Option<String> {
+        self.item(key)?.as_str().map(str::to_string)
+    }
+
+    /// The strings of an array value, e.g. `encryption.recipients`
+    pub fn get_list(&self, key: &str) -> Vec<String> {
+        self.item(key)
+            .and_then(|item| item.as_array())
+            .map(|array| array.iter().filter_map(|v| v.as_str()).map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+
+    /// Keys whose values are encrypted (`enc:age1:...`)
+    pub fn encrypted_keys(&self) -> Vec<String> {
+        let table: toml::Table = toml::from_str(&self.doc.to_string()).unwrap_or_default();
+        flatten(&table)
+            .into_iter()
+            .filter(|(_, value)| value.as_str().is_some_and(super::is_encrypted))
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    fn item(&self, key: &str) -> Option<&Item> {
+        let mut item = self.doc.as_item();
+        for segment in key.split('.') {
+            item = item.as_table_like()?.get(segment)?;
+        }
+        Some(item)
+    }
+
+    /// Set `key`, creating parent tables. Returns the previous value.
+    pub fn set(&mut self, key: &str, mut value: Value) -> Result<Option<String>> {
+        let (parents, last) = split_key(key);
+        let mut table = self.doc.as_table_mut() as &mut dyn toml_edit::TableLike;
+        for segment in parents {
+            let entry = table.entry(segment).or_insert_with(|| {
+                let mut new = toml_edit::Table::new();
+                new.set_implicit(true);
+                Item::Table(new)
+            });
+            table = entry
+                .as_table_like_mut()
+                .with_context(|| format!("'{}' in '{}' is not a table", segment, key))?;
+        }
+
+        let old = table.get(last).and_then(|item| item.as_value()).cloned();
+        if let Some(old) = &old {
+            // Keep the spacing and trailing comment of the line being replaced
+            *value.decor_mut() = old.decor().clone();
+        }
+        // Replace in place so the key keeps its leading comments
+        match table.get_mut(last) {
+            Some(item) => *item = Item::Value(value),
+            None => {
+                table.insert(last, Item::Value(value));
+            }
+        }
+        Ok(old.as_ref().map(display_value))
+    }
+
+    /// Set `key` to an array of strings
+    pub fn set_list(&mut self, key: &str, items: &[String]) -> Result<Option<String>> {
+        let array: Array = items.iter().map(String::as_str).collect();
+        self.set(key, Value::Array(array))
+    }
+
+    /// Remove `key`. Returns the removed value.
+    pub fn unset(&mut self, key: &str) -> Option<String> {
+        let (parents, last) = split_key(key);
+        let mut table = self.doc.as_table_mut() as &mut dyn toml_edit::TableLike;
+        for segment in parents {
+            table = table.get_mut(segment)?.as_table_like_mut()?;
+        }
+        table.remove(last).and_then(|item| item.as_value().map(display_value))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, self.doc.to_string())
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+fn split_key(key: &str) -> (Vec<&str>, &str) {
+    let mut segments: Vec<&str> = key.split('.').collect();
+    let last = segments.pop().unwrap_or_default();
+    (segments, last)
+}
+
+fn display_value(value: &Value) -> String {
+    let mut value = value.clone();
+    value.decor_mut().clear();
+    value.to_string()
+}
+
+/// Validate and write one key. Returns the old and new values.
+pub fn set_value(
+    scope: ConfigScope,
+    path: &Path,
+    key: &str,
+    raw: &str,
+) -> Result<(Option<String>, String)> {
+    let schema = lookup_key(key)?;
+    let value = schema.parse_value(key, raw)?;
+    if scope == ConfigScope::Project && !schema.project {
+        anyhow::bail!("'{}' can only be set in the global config (drop --project)", key);
+    }
+
+    let mut file = ConfigFile::load(path)?;
+    let new = display_value(&value);
+    let old = file.set(key, value)?;
+    file.save()?;
+    Ok((old, new))
+}
+
+/// Remove one key. Returns the removed value, if it was set.
+pub fn unset_value(path: &Path, key: &str) -> Result<Option<String>> {
+    lookup_key(key)?;
+    let mut file = ConfigFile::load(path)?;
+    let old = file.unset(key);
+    if old.is_some() {
+        file.save()?;
+    }
+    Ok(old)
+}
+
+/// Dotted keys and values of every leaf in a parsed TOML table
+pub fn flatten(table: &toml::Table) -> Vec<(String, toml::Value)> {
+    let mut out = Vec::new();
+    for (name, value) in table {
+        match value {
+            toml::Value::Table(inner) => {
+                for (key, value) in flatten(inner) {
+                    out.push((format!("{}.{}", name, key), value));
+                }
+            }
+            value => out.push((name.clone(), value.clone())),
+        }
+    }
+    out
+}
+
+/// Set a dotted key in a TOML value tree, creating tables as needed
+pub fn insert_path(root: &mut toml::Value, key: &str, value: toml::Value) {
+    let (parents, last) = split_key(key);
+    let mut current = root;
+    for segment in parents {
+        let toml::Value::Table(table) = current else {
+            return;
+        };
+        current = table
+            .entry(segment)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    }
+    if let toml::Value::Table(table) = current {
+        table.insert(last.to_string(), value);
+    }
+}
+
+/// Value of a dotted key in a TOML value tree
+pub fn get_path<'a>(root: &'a toml::Value, key: &str) -> Option<&'a toml::Value> {
+    key.split('.').try_fold(root, |value, segment| value.get(segment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"# Webrana settings
+default_model = "claude" # the usual one
+
+[models.claude]
+# Anthropic key comes from the environment
+provider = "anthropic"
+model = "claude-sonnet-4-20250514"
+api_key_env = "ANTHROPIC_API_KEY"
+temperature = 0.7
+
+[custom_plugin]
+# Not a webrana key; must survive edits
+endpoint = "http://localhost:9000"
+"#;
+
+    #[test]
+    fn test_round_trip_keeps_comments() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("config.toml");
+        fs::write(&path, CONFIG).unwrap();
+
+        let (old, new) = set_value(ConfigScope::Global, &path, "default_model", "gpt").unwrap();
+        assert_eq!((old.as_deref(), new.as_str()), (Some("\"claude\""), "\"gpt\""));
+        let (old, _) = set_value(ConfigScope::Global, &path, "models.claude.temperature", "0.2")
+            .unwrap();
+        assert_eq!(old.as_deref(), Some("0.7"));
+        let (old, _) = set_value(ConfigScope::Global, &path, "shell.timeout_secs", "300").unwrap();
+        assert_eq!(old, None);
+        set_value(ConfigScope::Global, &path, "skill_timeouts.skills.run_tests", "60").unwrap();
+        set_value(ConfigScope::Global, &path, "models.claude.fallback", "openai:gpt-4o, ollama")
+            .unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# Webrana settings\ndefault_model = \"gpt\" # the usual one\n"));
+        assert!(content.contains("# Anthropic key comes from the environment\n"));
+        assert!(content.contains("temperature = 0.2\n"));
+        assert!(content.contains("[custom_plugin]\n# Not a webrana key; must survive edits\n"));
+        assert!(content.contains("[shell]\ntimeout_secs = 300\n"));
+
+        let file = ConfigFile::load(&path).unwrap();
+        assert_eq!(file.get("skill_timeouts.skills.run_tests").as_deref(), Some("60"));
+        assert_eq!(
+            file.get("models.claude.fallback").as_deref(),
+            Some("[\"openai:gpt-4o\", \"ollama\"]")
+        );
+
+        assert_eq!(unset_value(&path, "shell.timeout_secs").unwrap().as_deref(), Some("300"));
+        assert_eq!(unset_value(&path, "shell.timeout_secs").unwrap(), None);
+
+        // Still a valid config
+        let settings: super::super::Settings =
+            toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(settings.default_model, "gpt");
+        assert_eq!(settings.models["claude"].temperature, 0.2);
+        assert_eq!(settings.models["claude"].fallback, vec!["openai:gpt-4o", "ollama"]);
+    }
+
+    #[test]
+    fn test_set_creates_missing_file() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join(".webrana").join("config.toml");
+        set_value(ConfigScope::Project, &path, "shell.cpus", "2").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[shell]\ncpus = \"2\"\n"
+        );
+    }
+
+    #[test]
+    fn test_validation_failures() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("config.toml");
+        fs::write(&path, CONFIG).unwrap();
+        let set = |key: &str, value: &str| {
+            set_value(ConfigScope::Global, &path, key, value)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            set("shell.sandbox", "kvm"),
+            "Invalid value 'kvm' for 'shell.sandbox': expected one of none, docker, podman"
+        );
+        assert_eq!(
+            set("shell.timeout", "5"),
+            "Unknown config key 'shell.timeout'. Did you mean 'shell.timeout_secs'?"
+        );
+        assert_eq!(
+            set("models.claude.temprature", "0.1"),
+            "Unknown config key 'models.claude.temprature'. \
+             Did you mean 'models.claude.temperature'?"
+        );
+        assert_eq!(set("telemetry.endpoint", "x"), "Unknown config key 'telemetry.endpoint'");
+        assert!(set("shell.timeout_secs", "-1").contains("non-negative integer"));
+        assert!(set("auto_compact_at", "1.5").contains("between"));
+        assert!(set("otel", "yes").contains("true or false"));
+        assert!(set("models.claude.api_key", "sk-ant-123").contains("models.claude.api_key_env"));
+
+        let err = set_value(ConfigScope::Project, &path, "safety.confirm_shell_execute", "false")
+            .unwrap_err();
+        assert!(err.to_string().contains("only be set in the global config"));
+
+        // Nothing was written
+        assert_eq!(fs::read_to_string(&path).unwrap(), CONFIG);
+    }
+}
@@ -0,0 +1,353 @@
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+
+use super::ConfigFile;
+
+/// Marks a config value as an age-encrypted blob: the prefix followed by
+/// the body of the value's ASCII armor
+pub const ENCRYPTED_PREFIX: &str = "enc:age1:";
+
+/// Identity (an `AGE-SECRET-KEY-...` line) or path to an identity file;
+/// checked before the default file
+pub const IDENTITY_ENV: &str = "WEBRANA_AGE_IDENTITY";
+
+/// Recipients that `config encrypt` and `config recipients` keep in step
+pub const RECIPIENTS_KEY: &str = "encryption.recipients";
+
+const ARMOR_BEGIN: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+const ARMOR_END: &str = "-----END AGE ENCRYPTED FILE-----";
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// `~/.config/webrana/age/keys.txt`
+pub fn default_identity_file() -> Result<PathBuf> {
+    let config = super::Settings::config_path()?;
+    Ok(config.with_file_name("age").join("keys.txt"))
+}
+
+/// Decrypts `enc:` values while config is loaded. Identities are read on
+/// the first encrypted value, so configs without any need no keys.
+#[derive(Default)]
+pub struct Decrypter {
+    #[cfg(feature = "age")]
+    identities: Option<imp::Identities>,
+}
+
+impl Decrypter {
+    /// Decrypt every encrypted string in `root` in place
+    pub fn decrypt_tree(&mut self, root: &mut toml::Value) -> Result<()> {
+        self.decrypt_at("", root)
+    }
+
+    fn decrypt_at(&mut self, path: &str, value: &mut toml::Value) -> Result<()> {
+        let child = |name: &str| {
+            if path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", path, name)
+            }
+        };
+        match value {
+            toml::Value::String(text) if is_encrypted(text) => *text = self.decrypt(path, text)?,
+            toml::Value::Table(table) => {
+                for (name, value) in table.iter_mut() {
+                    self.decrypt_at(&child(name), value)?;
+                }
+            }
+            toml::Value::Array(items) => {
+                for (i, value) in items.iter_mut().enumerate() {
+                    self.decrypt_at(&child(&i.to_string()), value)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Plaintext of the encrypted value at `key`. Errors name the key and
+    /// the identities tried, never the ciphertext.
+    #[cfg(feature = "age")]
+    pub fn decrypt(&mut self, key: &str, value: &str) -> Result<String> {
+        if self.identities.is_none() {
+            self.identities = Some(imp::Identities::load()?);
+        }
+        self.identities.as_ref().unwrap().decrypt(key, value)
+    }
+
+    #[cfg(not(feature = "age"))]
+    pub fn decrypt(&mut self, key: &str, _value: &str) -> Result<String> {
+        bail!(
+            "'{}' is encrypted, but this webrana was built without age support \
+             (rebuild with `--features age`)",
+            key
+        )
+    }
+}
+
+/// Encrypt `plaintext` to every recipient (`age1...` public keys)
+#[cfg(feature = "age")]
+pub fn encrypt(plaintext: &str, recipients: &[String]) -> Result<String> {
+    imp::encrypt(plaintext, recipients)
+}
+
+#[cfg(not(feature = "age"))]
+pub fn encrypt(_plaintext: &str, _recipients: &[String]) -> Result<String> {
+    bail!("This webrana was built without age support (rebuild with `--features age`)")
+}
+
+/// A value typed at a hidden prompt, or one line of piped stdin
+#[cfg(feature = "age")]
+pub fn read_secret(prompt: &str) -> Result<String> {
+    let value = if atty::is(atty::Stream::Stdin) {
+        rpassword::prompt_password(prompt)?
+    } else {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        line
+    };
+    let value = value.trim_end_matches(['\r', '\n']).to_string();
+    if value.is_empty() {
+        bail!("No value entered");
+    }
+    Ok(value)
+}
+
+#[cfg(not(feature = "age"))]
+pub fn read_secret(_prompt: &str) -> Result<String> {
+    bail!("This webrana was built without age support (rebuild with `--features age`)")
+}
+
+/// Whether this build can encrypt and decrypt config values
+pub const AGE_SUPPORTED: bool = cfg!(feature = "age");
+
+/// Encrypt `plaintext` to `recipients` and store it under `key` in `file`.
+/// The plaintext is never written.
+pub fn encrypt_into(file: &mut ConfigFile, key: &str, plaintext: &str, recipients: &[String]) -> Result<()> {
+    let value = encrypt(plaintext, recipients)?;
+    file.set(key, toml_edit::Value::from(value))?;
+    Ok(())
+}
+
+/// Re-encrypt every encrypted value in `file` to `recipients`, decrypting
+/// with the local identities. Returns the keys that were re-encrypted.
+pub fn reencrypt(file: &mut ConfigFile, recipients: &[String]) -> Result<Vec<String>> {
+    let mut decrypter = Decrypter::default();
+    let keys = file.encrypted_keys();
+    for key in &keys {
+        let Some(current) = file.get_str(key) else {
+            continue;
+        };
+        let plaintext = decrypter.decrypt(key, &current)?;
+        encrypt_into(file, key, &plaintext, recipients)?;
+    }
+    Ok(keys)
+}
+
+/// The armor of an `enc:` value, for the age reader
+fn to_armor(value: &str) -> Result<String> {
+    let body = value.strip_prefix(ENCRYPTED_PREFIX).context("not an encrypted value")?;
+    if body.is_empty() || !body.bytes().all(|b| b.is_ascii_alphanumeric() || b"+/=".contains(&b)) {
+        bail!("malformed encrypted value");
+    }
+    let mut armor = format!("{}\n", ARMOR_BEGIN);
+    for line in body.as_bytes().chunks(64) {
+        armor.push_str(std::str::from_utf8(line)?);
+        armor.push('\n');
+    }
+    armor.push_str(ARMOR_END);
+    armor.push('\n');
+    Ok(armor)
+}
+
+/// An `enc:` value from age's armored output
+fn from_armor(armor: &str) -> String {
+    let body: String = armor
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("-----"))
+        .collect();
+    format!("{}{}", ENCRYPTED_PREFIX, body)
+}
+
+#[cfg(feature = "age")]
+mod imp {
+    use anyhow::{bail, Context, Result};
+    use age::armor::{ArmoredReader, ArmoredWriter, Format};
+    use std::io::{Read, Write};
+    use std::str::FromStr;
+
+    use super::{default_identity_file, from_armor, to_armor, IDENTITY_ENV};
+
+    pub struct Identities {
+        keys: Vec<Box<dyn age::Identity>>,
+        /// Where keys were looked for, for error messages
+        tried: Vec<String>,
+    }
+
+    impl Identities {
+        /// From `WEBRANA_AGE_IDENTITY`, then the default identity file
+        pub fn load() -> Result<Self> {
+            let mut identities = Self { keys: Vec::new(), tried: Vec::new() };
+            if let Ok(value) = std::env::var(IDENTITY_ENV) {
+                let value = value.trim();
+                if value.starts_with("AGE-SECRET-KEY-") {
+                    identities.add(&format!("${}", IDENTITY_ENV), value)?;
+                } else {
+                    let path = crate::util::http::expand_path(value);
+                    let content = std::fs::read_to_string(&path)
+                        .with_context(|| format!("Failed to read ${} ({})", IDENTITY_ENV, path.display()))?;
+                    identities.add(&format!("${} ({})", IDENTITY_ENV, path.display()), &content)?;
+                }
+            }
+            let default = default_identity_file()?;
+            match std::fs::read_to_string(&default) {
+                Ok(content) => identities.add(&default.display().to_string(), &content)?,
+                Err(_) => identities.tried.push(format!("{} (not found)", default.display())),
+            }
+            Ok(identities)
+        }
+
+        pub fn from_keys(source: &str, content: &str) -> Result<Self> {
+            let mut identities = Self { keys: Vec::new(), tried: Vec::new() };
+            identities.add(source, content)?;
+            Ok(identities)
+        }
+
+        fn add(&mut self, source: &str, content: &str) -> Result<()> {
+            let file = age::IdentityFile::from_buffer(content.as_bytes())
+                .and_then(|file| file.into_identities().map_err(std::io::Error::other))
+                .with_context(|| format!("Invalid age identities in {}", source))?;
+            self.keys.extend(file);
+            self.tried.push(source.to_string());
+            Ok(())
+        }
+
+        pub fn decrypt(&self, key: &str, value: &str) -> Result<String> {
+            let tried = if self.tried.is_empty() { "none".to_string() } else { self.tried.join(", ") };
+            let armor = to_armor(value).with_context(|| format!("Failed to decrypt '{}'", key))?;
+            let result = age::Decryptor::new(ArmoredReader::new(armor.as_bytes()))
+                .and_then(|decryptor| decryptor.decrypt(self.keys.iter().map(|k| k.as_ref() as &dyn age::Identity)));
+            let mut reader = match result {
+                Ok(reader) => reader,
+                Err(age::DecryptError::NoMatchingKeys) => bail!(
+                    "Failed to decrypt '{}': none of the age identities match (tried {}). \
+                     Ask a teammate to add your recipient with `webrana config recipients --add`",
+                    key,
+                    tried
+                ),
+                Err(e) => bail!("Failed to decrypt '{}': {} (tried {})", key, e, tried),
+            };
+            let mut plaintext = String::new();
+            reader
+                .read_to_string(&mut plaintext)
+                .map_err(|_| anyhow::anyhow!("Failed to decrypt '{}': corrupt ciphertext", key))?;
+            Ok(plaintext)
+        }
+    }
+
+    pub fn encrypt(plaintext: &str, recipients: &[String]) -> Result<String> {
+        if recipients.is_empty() {
+            bail!("No recipients to encrypt to");
+        }
+        let recipients = recipients
+            .iter()
+            .map(|r| age::x25519::Recipient::from_str(r.trim()).map_err(|e| anyhow::anyhow!("Invalid recipient '{}': {}", r, e)))
+            .collect::<Result<Vec<_>>>()?;
+        let encryptor =
+            age::Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn age::Recipient))?;
+
+        let mut armor = Vec::new();
+        let writer = ArmoredWriter::wrap_output(&mut armor, Format::AsciiArmor)?;
+        let mut stream = encryptor.wrap_output(writer)?;
+        stream.write_all(plaintext.as_bytes())?;
+        stream.finish()?.finish()?;
+        Ok(from_armor(&String::from_utf8(armor)?))
+    }
+}
+
+#[cfg(all(test, feature = "age"))]
+mod tests {
+    use super::imp::Identities;
+    use super::*;
+    use age::secrecy::ExposeSecret;
+
+    fn keypair() -> (String, String) {
+        let identity = age::x25519::Identity::generate();
+        (identity.to_string().expose_secret().to_string(), identity.to_public().to_string())
+    }
+
+    #[test]
+    fn test_round_trip_to_several_recipients() {
+        let (alice_key, alice) = keypair();
+        let (bob_key, bob) = keypair();
+
+        let value = encrypt("gw-team-key-123", &[alice, bob]).unwrap();
+        assert!(is_encrypted(&value), "{}", value);
+        assert!(!value.contains('\n') && !value.contains("gw-team-key"));
+
+        for key in [alice_key, bob_key] {
+            let identities = Identities::from_keys("test", &key).unwrap();
+            assert_eq!(identities.decrypt("models.gateway.api_key", &value).unwrap(), "gw-team-key-123");
+        }
+    }
+
+    #[test]
+    fn test_wrong_identity_names_key_and_sources() {
+        let (_, alice) = keypair();
+        let (mallory_key, _) = keypair();
+        let value = encrypt("gw-team-key-123", &[alice]).unwrap();
+
+        let identities = Identities::from_keys("/tmp/mallory.txt", &mallory_key).unwrap();
+        let err = identities.decrypt("models.gateway.api_key", &value).unwrap_err().to_string();
+        assert!(err.contains("'models.gateway.api_key'"), "{}", err);
+        assert!(err.contains("tried /tmp/mallory.txt"), "{}", err);
+        let body = value.strip_prefix(ENCRYPTED_PREFIX).unwrap();
+        assert!(!err.contains(&body[..16]) && !err.contains("gw-team-key"), "{}", err);
+
+        let err = identities.decrypt("models.gateway.api_key", "enc:age1:not*base64").unwrap_err();
+        assert!(format!("{:#}", err).contains("malformed"));
+    }
+
+    // One test owns WEBRANA_AGE_IDENTITY so tests don't race on it
+    #[test]
+    fn test_config_values_decrypt_from_env_identity() {
+        let (key, recipient) = keypair();
+        let value = encrypt("s3cret", std::slice::from_ref(&recipient)).unwrap();
+        std::env::set_var(IDENTITY_ENV, &key);
+
+        let mut tree: toml::Value =
+            toml::from_str(&format!("[models.gateway]\napi_key = \"{}\"\nmodel = \"m\"\n", value)).unwrap();
+        Decrypter::default().decrypt_tree(&mut tree).unwrap();
+        assert_eq!(tree["models"]["gateway"]["api_key"].as_str(), Some("s3cret"));
+        assert_eq!(tree["models"]["gateway"]["model"].as_str(), Some("m"));
+
+        // A project file may supply an encrypted key, but not a plain one
+        let project = format!("[models.claude]\napi_key = \"{}\"\nbase_url = \"https://evil\"\n", value);
+        let settings = crate::config::Settings::default().with_project_config(&project).unwrap();
+        assert_eq!(settings.models["claude"].api_key.as_deref(), Some("s3cret"));
+        assert_eq!(settings.models["claude"].base_url, None);
+        let plain = crate::config::Settings::default()
+            .with_project_config("[models.claude]\napi_key = \"sk-plain\"\n")
+            .unwrap();
+        assert_eq!(plain.models["claude"].api_key, None);
+
+        // Re-encrypting to a new recipient keeps the file free of plaintext
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("config.toml");
+        std::fs::write(&path, &project).unwrap();
+        let (bob_key, bob) = keypair();
+        let mut file = ConfigFile::load(&path).unwrap();
+        assert_eq!(reencrypt(&mut file, &[recipient, bob]).unwrap(), vec!["models.claude.api_key"]);
+        file.save().unwrap();
+        std::env::remove_var(IDENTITY_ENV);
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(!written.contains("s3cret"), "{}", written);
+        let reencrypted = ConfigFile::load(&path).unwrap().get_str("models.claude.api_key").unwrap();
+        assert_ne!(reencrypted, value);
+        let bob = Identities::from_keys("bob", &bob_key).unwrap();
+        assert_eq!(bob.decrypt("models.claude.api_key", &reencrypted).unwrap(), "s3cret");
+    }
+}
@@ -1,4 +1,20 @@
+mod edit;
+mod encrypted;
 mod settings;
 
 #[allow(unused_imports)]
-pub use settings::{AgentConfig, ModelConfig, Settings};
+pub use settings::{
+    AgentConfig, BannerMode, ContextBudgetSettings, ConversationMemorySettings, DirtyTreePolicy, EditFormat, EmbeddingSettings, EncryptionSettings, HttpSettings, ModelConfig, RateLimitSettings, RunSettings, SamplingSettings, SandboxFallback,
+    SandboxKind, Settings, ShellConfig, SkillTimeouts, ToolBudget,
+    ToolOutputSettings, UiSettings, VcsSettings,
+};
+#[allow(unused_imports)]
+pub use edit::{
+    get_path, lookup_key, set_value, unset_value, ConfigFile, ConfigKey, ConfigScope, KeyType,
+    CONFIG_KEYS,
+};
+#[allow(unused_imports)]
+pub use encrypted::{
+    default_identity_file, encrypt, encrypt_into, is_encrypted, read_secret, reencrypt, Decrypter,
+    AGE_SUPPORTED, ENCRYPTED_PREFIX, IDENTITY_ENV, RECIPIENTS_KEY,
+};
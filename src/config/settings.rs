@@ -2,7 +2,10 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::encrypted::{is_encrypted, Decrypter};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
@@ -20,6 +23,82 @@ pub struct Settings {
 
     #[serde(default)]
     pub safety: SafetyConfig,
+
+    #[serde(default)]
+    pub shell: ShellConfig,
+
+    /// Request budget shared by everything using one provider key
+    #[serde(default)]
+    pub rate_limit: RateLimitSettings,
+
+    /// How long each skill call may run before it is cancelled
+    #[serde(default)]
+    pub skill_timeouts: SkillTimeouts,
+
+    /// Autonomous run behaviour
+    #[serde(default)]
+    pub run: RunSettings,
+
+    /// Load `.env` from the working directory at startup (same as `--env-file`)
+    #[serde(default)]
+    pub load_dotenv: bool,
+
+    /// Export traces over OTLP (needs the `otel` build feature and OTEL_EXPORTER_OTLP_ENDPOINT)
+    #[serde(default)]
+    pub otel: bool,
+
+    /// Compact the conversation when it uses this fraction of the model's context window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_compact_at: Option<f32>,
+
+    /// How tool results are cleaned before they are shown and sent
+    #[serde(default)]
+    pub tool_output: ToolOutputSettings,
+
+    /// End each chat answer with the model and provider that produced it
+    #[serde(default = "default_true")]
+    pub show_provenance: bool,
+
+    /// Drop a leading "As the Code Reviewer, ..." clause from `ask --print`
+    /// answers (same as `--clean`)
+    #[serde(default)]
+    pub strip_persona_prefix: bool,
+
+    /// Refuse to start when a crew or agent prompt uses an unknown `{{variable}}`
+    #[serde(default)]
+    pub strict_prompt_variables: bool,
+
+    /// TLS settings for every outbound HTTP request
+    #[serde(default)]
+    pub http: HttpSettings,
+
+    /// Who `enc:` config values are encrypted to
+    #[serde(default)]
+    pub encryption: EncryptionSettings,
+
+    /// GitLab and Bitbucket access for merge request context
+    #[serde(default)]
+    pub vcs: VcsSettings,
+
+    /// Sampling parameters sent with every request (`--seed`, `--temperature`, `--top-p`)
+    #[serde(default)]
+    pub sampling: SamplingSettings,
+
+    /// Embedded summaries of past chat sessions, recalled in new ones
+    #[serde(default)]
+    pub conversation_memory: ConversationMemorySettings,
+
+    /// Where embeddings for indexing, search and recall come from
+    #[serde(default)]
+    pub embeddings: EmbeddingSettings,
+
+    /// Shares of the context window for the system prompt, retrieved code and conversation
+    #[serde(default)]
+    pub context_budget: ContextBudgetSettings,
+
+    /// Terminal presentation
+    #[serde(default)]
+    pub ui: UiSettings,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +114,97 @@ pub struct ModelConfig {
 
     #[serde(default = "default_max_tokens")]
     pub max_tokens: u32,
+
+    /// Context window in tokens (guessed from the provider when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<usize>,
+
+    /// Tried in order when this model hard-fails: configured model names
+    /// or `provider:model` (e.g. `openai:gpt-4o`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<String>,
+
+    /// Edit format the tool guidance steers this model towards (guessed
+    /// from the provider when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edit_format: Option<EditFormat>,
+
+    /// Most tools sent with one request (guessed from the provider when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tools: Option<usize>,
+
+    /// Most bytes of serialized tool definitions sent with one request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tool_bytes: Option<usize>,
+
+    /// Whether the model accepts images (guessed from the model name when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vision: Option<bool>,
+}
+
+/// Model-name prefixes of models that accept images
+const VISION_MODELS: &[&str] = &[
+    "claude-3",
+    "claude-sonnet-4",
+    "claude-opus-4",
+    "gpt-4o",
+    "gpt-4.1",
+    "gpt-4-turbo",
+    "llava",
+    "llama3.2-vision",
+];
+
+/// How many tool definitions a model is sent per request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolBudget {
+    pub max_tools: usize,
+    pub max_bytes: usize,
+}
+
+/// How the model is asked to change existing files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EditFormat {
+    /// `edit_file` with an exact search string
+    SearchReplace,
+    /// `apply_patch` with a unified diff
+    UnifiedDiff,
+}
+
+impl ModelConfig {
+    pub fn context_window(&self) -> usize {
+        self.context_window.unwrap_or(match self.provider.as_str() {
+            "anthropic" => 200_000,
+            "openai" => 128_000,
+            _ => 8_192,
+        })
+    }
+
+    pub fn edit_format(&self) -> EditFormat {
+        self.edit_format.unwrap_or(match self.provider.as_str() {
+            "openai" => EditFormat::UnifiedDiff,
+            _ => EditFormat::SearchReplace,
+        })
+    }
+
+    /// Local models lose track of long tool lists much sooner than hosted ones
+    pub fn tool_budget(&self) -> ToolBudget {
+        let (max_tools, max_bytes) = match self.provider.as_str() {
+            "ollama" => (16, 24_000),
+            _ => (128, 200_000),
+        };
+        ToolBudget {
+            max_tools: self.max_tools.unwrap_or(max_tools),
+            max_bytes: self.max_tool_bytes.unwrap_or(max_bytes),
+        }
+    }
+
+    /// Images from tool results are attached for vision models, and saved
+    /// to disk behind a text placeholder for the rest
+    pub fn vision(&self) -> bool {
+        self.vision
+            .unwrap_or_else(|| VISION_MODELS.iter().any(|prefix| self.model.starts_with(prefix)))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,8 +235,469 @@ pub struct SafetyConfig {
 
     #[serde(default)]
     pub blocked_paths: Vec<String>,
+
+    /// Hosts web_fetch, MCP over HTTP and plugins may connect to
+    /// (`*.docs.rs`, `wiki.example.com:443`, `10.0.0.5`); empty allows any
+    /// public host. LLM providers are not affected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub egress_allowlist: Vec<String>,
+}
+
+impl SafetyConfig {
+    /// An allow-list of commands or blocked paths is configured; the
+    /// confirmation switches alone don't count
+    pub fn is_policy(&self) -> bool {
+        !self.allowed_commands.is_empty() || !self.blocked_paths.is_empty()
+    }
+}
+
+/// Where `execute_command` runs commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxKind {
+    /// Run directly on the host
+    #[default]
+    None,
+    Docker,
+    Podman,
+}
+
+/// What to do when the sandbox runtime is missing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxFallback {
+    /// Refuse to run Medium/High risk commands
+    #[default]
+    Deny,
+    /// Run them on the host instead
+    Host,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitSettings {
+    #[serde(default = "default_requests_per_minute")]
+    pub requests_per_minute: u32,
+
+    /// Share of the budget background work (indexing) may use; chat gets the rest
+    #[serde(default = "default_background_share")]
+    pub background_share: f32,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: default_requests_per_minute(),
+            background_share: default_background_share(),
+        }
+    }
+}
+
+fn default_requests_per_minute() -> u32 {
+    300
+}
+
+fn default_background_share() -> f32 {
+    0.3
+}
+
+/// What an autonomous run does when it starts on uncommitted changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DirtyTreePolicy {
+    /// List the dirty paths and continue
+    #[default]
+    Warn,
+    /// Refuse to start
+    Block,
+    /// Stash the changes (untracked files included) before starting
+    Stash,
+    /// Switch to a new `webrana/run-<id>` branch before starting
+    Branch,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolOutputSettings {
+    /// Keep ANSI colors in the tool output echoed to the terminal. The
+    /// model always gets plain text.
+    #[serde(default)]
+    pub keep_colors: bool,
+
+    /// Shrink tool results to a one-line record after `keep_iterations`
+    /// agent iterations, unless the final answer cites them
+    #[serde(default = "default_true")]
+    pub ephemeral: bool,
+
+    #[serde(default = "default_keep_iterations")]
+    pub keep_iterations: usize,
+}
+
+impl Default for ToolOutputSettings {
+    fn default() -> Self {
+        Self {
+            keep_colors: false,
+            ephemeral: true,
+            keep_iterations: default_keep_iterations(),
+        }
+    }
+}
+
+fn default_keep_iterations() -> usize {
+    crate::memory::working_set::DEFAULT_KEEP_ITERATIONS
+}
+
+/// Custom CA and client certificate for outbound HTTPS. Paths may use `~`
+/// and `${VAR}`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct HttpSettings {
+    /// PEM bundle of extra trusted CAs (e.g. a proxy that re-signs TLS)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+
+    /// Trust only `ca_bundle`, not the built-in roots
+    #[serde(default)]
+    pub ca_replace: bool,
+
+    /// PEM client certificate for mTLS, used with `client_key`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<String>,
+
+    /// Unencrypted PEM private key of `client_cert`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<String>,
+
+    /// Accept any server certificate. Refused while a safety policy is set.
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+}
+
+/// Recipients for `webrana config encrypt`, usually set in the project file
+/// so the whole team can decrypt shared values
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct EncryptionSettings {
+    /// age public keys (`age1...`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recipients: Vec<String>,
+}
+
+/// Merge request APIs. Tokens may be `enc:` values; without them
+/// `GITLAB_TOKEN` and `BITBUCKET_TOKEN` are used.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct VcsSettings {
+    /// Self-hosted GitLab root, also when served under a path
+    /// (default: the host of the merge request URL or origin remote)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gitlab_url: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gitlab_token: Option<String>,
+
+    /// Bitbucket REST base (default: https://api.bitbucket.org/2.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitbucket_api_url: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitbucket_token: Option<String>,
+}
+
+/// Overrides of the providers' sampling defaults; unset fields are not sent.
+/// A seed makes a run reproducible: temperature 0 and top_p 1 unless set.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+pub struct SamplingSettings {
+    /// Sent as OpenAI's `seed` and Ollama's `options.seed`; other providers ignore it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+}
+
+impl SamplingSettings {
+    /// What requests carry: with a seed, temperature and top_p default to 0 and 1
+    pub fn resolved(&self) -> Self {
+        match self.seed {
+            Some(_) => Self {
+                seed: self.seed,
+                temperature: self.temperature.or(Some(0.0)),
+                top_p: self.top_p.or(Some(1.0)),
+            },
+            None => *self,
+        }
+    }
+}
+
+/// Opt-in long-term memory of chat sessions (the `webrana-memory` index)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationMemorySettings {
+    /// Store each finished REPL session and recall related ones
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Recall sessions from every project, not just this one (same as `--global-memory`)
+    #[serde(default)]
+    pub global: bool,
+
+    /// Minimum similarity for a past session to be recalled
+    #[serde(default = "default_memory_min_score")]
+    pub min_score: f32,
+
+    /// Cap on the recalled-memory block added to a turn, in characters
+    #[serde(default = "default_memory_max_chars")]
+    pub max_chars: usize,
+}
+
+impl Default for ConversationMemorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            global: false,
+            min_score: default_memory_min_score(),
+            max_chars: default_memory_max_chars(),
+        }
+    }
+}
+
+/// Embedding provider. Unset, a local (Ollama) default model embeds with
+/// Ollama too, and anything else with OpenAI when OPENAI_API_KEY is set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmbeddingSettings {
+    /// `openai`, `ollama` or `mock`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+
+    /// Embedding model (text-embedding-3-small for OpenAI, nomic-embed-text for Ollama)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Size of the model's vectors, needed with `model`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimension: Option<usize>,
+
+    /// API base URL; for Ollama, the default model's when that is an Ollama one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
 }
 
+/// How the context window is split when a request would overflow it.
+/// Regions over their share are cut first, retrieved code before the
+/// oldest conversation; the system prompt is only warned about.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ContextBudgetSettings {
+    #[serde(default = "default_budget_system")]
+    pub system: f32,
+
+    #[serde(default = "default_budget_rag")]
+    pub rag: f32,
+
+    #[serde(default = "default_budget_conversation")]
+    pub conversation: f32,
+}
+
+impl Default for ContextBudgetSettings {
+    fn default() -> Self {
+        Self {
+            system: default_budget_system(),
+            rag: default_budget_rag(),
+            conversation: default_budget_conversation(),
+        }
+    }
+}
+
+fn default_budget_system() -> f32 {
+    0.3
+}
+
+fn default_budget_rag() -> f32 {
+    0.2
+}
+
+fn default_budget_conversation() -> f32 {
+    0.5
+}
+
+fn default_memory_min_score() -> f32 {
+    0.35
+}
+
+fn default_memory_max_chars() -> usize {
+    1_500
+}
+
+/// What is printed when a session starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BannerMode {
+    /// The ASCII logo, then the session header
+    Full,
+    /// One or two lines: model, crew, directory, flags, tools
+    #[default]
+    Compact,
+    Off,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiSettings {
+    #[serde(default)]
+    pub banner: BannerMode,
+
+    /// Recent commits in the REPL's warm-start block; 0 turns the block off
+    #[serde(default = "default_warm_start_commits")]
+    pub warm_start_commits: usize,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        Self {
+            banner: BannerMode::default(),
+            warm_start_commits: default_warm_start_commits(),
+        }
+    }
+}
+
+fn default_warm_start_commits() -> usize {
+    5
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSettings {
+    #[serde(default)]
+    pub dirty_tree: DirtyTreePolicy,
+
+    /// Identical (or near-identical) responses in a row before the model is
+    /// told it is repeating itself; 0 turns the check off
+    #[serde(default = "default_stuck_after")]
+    pub stuck_after: usize,
+}
+
+impl Default for RunSettings {
+    fn default() -> Self {
+        Self {
+            dirty_tree: DirtyTreePolicy::default(),
+            stuck_after: default_stuck_after(),
+        }
+    }
+}
+
+fn default_stuck_after() -> usize {
+    3
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkillTimeouts {
+    /// Limit for skills without an override, in seconds (0 = no limit)
+    #[serde(default = "default_skill_timeout")]
+    pub default_secs: u64,
+
+    /// Per-skill overrides by skill name, in seconds (0 = no limit)
+    #[serde(default = "default_skill_overrides")]
+    pub skills: HashMap<String, u64>,
+}
+
+impl Default for SkillTimeouts {
+    fn default() -> Self {
+        Self {
+            default_secs: default_skill_timeout(),
+            skills: default_skill_overrides(),
+        }
+    }
+}
+
+impl SkillTimeouts {
+    /// The same limit for every skill, overrides included (`run --tool-timeout`)
+    pub fn uniform(limit: Duration) -> Self {
+        Self {
+            default_secs: limit.as_secs().max(1),
+            skills: HashMap::new(),
+        }
+    }
+
+    /// Time limit for one call of `name`, or None when unlimited
+    pub fn for_skill(&self, name: &str) -> Option<Duration> {
+        let secs = self.skills.get(name).copied().unwrap_or(self.default_secs);
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+
+    /// Effective limits of the skills with an override
+    pub fn timeouts(&self) -> HashMap<String, Duration> {
+        self.skills
+            .keys()
+            .filter_map(|name| self.for_skill(name).map(|limit| (name.clone(), limit)))
+            .collect()
+    }
+}
+
+fn default_skill_timeout() -> u64 {
+    120
+}
+
+/// Commands and test runs have their own (600s) shell timeout; leave room for it
+fn default_skill_overrides() -> HashMap<String, u64> {
+    HashMap::from([
+        ("execute_command".to_string(), 900),
+        ("run_tests".to_string(), 900),
+        ("build_project".to_string(), 900),
+        ("test_project".to_string(), 900),
+        ("lint_project".to_string(), 900),
+    ])
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShellConfig {
+    #[serde(default)]
+    pub sandbox: SandboxKind,
+
+    /// Container image (default: chosen from the detected project type)
+    #[serde(default)]
+    pub image: Option<String>,
+
+    /// Mount the working directory read-only
+    #[serde(default)]
+    pub read_only: bool,
+
+    /// Allow skill calls to request network access with `network: true`
+    #[serde(default)]
+    pub allow_network: bool,
+
+    #[serde(default = "default_cpus")]
+    pub cpus: String,
+
+    #[serde(default = "default_memory")]
+    pub memory: String,
+
+    #[serde(default)]
+    pub fallback: SandboxFallback,
+
+    /// Kill commands after this many seconds (0 = no limit)
+    #[serde(default = "default_shell_timeout")]
+    pub timeout_secs: u64,
+}
+
+impl Default for ShellConfig {
+    fn default() -> Self {
+        Self {
+            sandbox: SandboxKind::None,
+            image: None,
+            read_only: false,
+            allow_network: false,
+            cpus: default_cpus(),
+            memory: default_memory(),
+            fallback: SandboxFallback::Deny,
+            timeout_secs: default_shell_timeout(),
+        }
+    }
+}
+
+fn default_cpus() -> String {
+    "2".to_string()
+}
+fn default_memory() -> String {
+    "2g".to_string()
+}
+fn default_shell_timeout() -> u64 {
+    600
+}
 fn default_temperature() -> f32 {
     0.7
 }
@@ -90,6 +721,12 @@ impl Default for Settings {
                 model: "claude-sonnet-4-20250514".to_string(),
                 temperature: 0.7,
                 max_tokens: 4096,
+                context_window: None,
+                fallback: Vec::new(),
+                edit_format: None,
+                max_tools: None,
+                max_tool_bytes: None,
+                vision: None,
             },
         );
         models.insert(
@@ -102,6 +739,12 @@ impl Default for Settings {
                 model: "gpt-4o".to_string(),
                 temperature: 0.7,
                 max_tokens: 4096,
+                context_window: None,
+                fallback: Vec::new(),
+                edit_format: None,
+                max_tools: None,
+                max_tool_bytes: None,
+                vision: None,
             },
         );
         models.insert(
@@ -114,6 +757,12 @@ impl Default for Settings {
                 model: "llama3".to_string(),
                 temperature: 0.7,
                 max_tokens: 4096,
+                context_window: None,
+                fallback: Vec::new(),
+                edit_format: None,
+                max_tools: None,
+                max_tool_bytes: None,
+                vision: None,
             },
         );
 
@@ -136,26 +785,144 @@ impl Default for Settings {
             default_model: "claude".to_string(),
             default_agent: "nexus".to_string(),
             safety: SafetyConfig::default(),
+            shell: ShellConfig::default(),
+            rate_limit: RateLimitSettings::default(),
+            skill_timeouts: SkillTimeouts::default(),
+            run: RunSettings::default(),
+            load_dotenv: false,
+            otel: false,
+            auto_compact_at: None,
+            tool_output: ToolOutputSettings::default(),
+            show_provenance: true,
+            strip_persona_prefix: false,
+            strict_prompt_variables: false,
+            http: HttpSettings::default(),
+            encryption: EncryptionSettings::default(),
+            vcs: VcsSettings::default(),
+            sampling: SamplingSettings::default(),
+            conversation_memory: ConversationMemorySettings::default(),
+            embeddings: EmbeddingSettings::default(),
+            context_budget: ContextBudgetSettings::default(),
+            ui: UiSettings::default(),
         }
     }
 }
 
 impl Settings {
-    pub fn load() -> Result<Self> {
-        dotenvy::dotenv().ok();
+    /// Context window of the default model, in tokens
+    pub fn context_window(&self) -> usize {
+        self.models
+            .get(&self.default_model)
+            .map(|m| m.context_window())
+            .unwrap_or(8_192)
+    }
+
+    /// Tool budget of the default model
+    pub fn tool_budget(&self) -> ToolBudget {
+        self.models
+            .get(&self.default_model)
+            .map(|m| m.tool_budget())
+            .unwrap_or(ToolBudget {
+                max_tools: 128,
+                max_bytes: 200_000,
+            })
+    }
+
+    /// Edit format of the default model
+    pub fn edit_format(&self) -> EditFormat {
+        self.models
+            .get(&self.default_model)
+            .map(|m| m.edit_format())
+            .unwrap_or(EditFormat::SearchReplace)
+    }
 
+    pub fn load() -> Result<Self> {
         let config_path = Self::config_path()?;
 
-        if config_path.exists() {
+        let settings = if config_path.exists() {
             let content = fs::read_to_string(&config_path).context("Failed to read config file")?;
-            let settings: Settings =
-                toml::from_str(&content).context("Failed to parse config file")?;
-            Ok(settings)
+            let mut value: toml::Value = toml::from_str(&content).context("Failed to parse config file")?;
+            // Decrypted values only live in memory; config edits work on the file text
+            Decrypter::default().decrypt_tree(&mut value)?;
+            let settings: Settings = value.try_into().context("Failed to parse config file")?;
+            settings
         } else {
             let settings = Settings::default();
             settings.save()?;
-            Ok(settings)
+            settings
+        };
+
+        let project_path = Self::project_config_path();
+        if project_path.exists() {
+            let content = fs::read_to_string(&project_path)
+                .with_context(|| format!("Failed to read {}", project_path.display()))?;
+            return settings
+                .with_project_config(&content)
+                .with_context(|| format!("Failed to apply {}", project_path.display()));
         }
+        Ok(settings)
+    }
+
+    /// Project config, layered over the global one by `load`
+    pub fn project_config_path() -> PathBuf {
+        PathBuf::from(".webrana").join("config.toml")
+    }
+
+    /// Overlay project config `content`. Keys that may only be set globally
+    /// (safety, sandbox, models) are ignored with a warning, except that an
+    /// encrypted `models.*.api_key` may supply a shared key for a model.
+    pub fn with_project_config(self, content: &str) -> Result<Self> {
+        let overlay: toml::Table = toml::from_str(content)?;
+        let mut merged = toml::Value::try_from(&self)?;
+        let mut decrypter = Decrypter::default();
+        for (key, mut value) in super::edit::flatten(&overlay) {
+            let encrypted = value.as_str().is_some_and(is_encrypted);
+            match super::edit::lookup_key(&key) {
+                Ok(schema) if schema.project || (encrypted && schema.kind == super::KeyType::Secret) => {
+                    if let (toml::Value::String(text), true) = (&mut value, encrypted) {
+                        *text = decrypter.decrypt(&key, text)?;
+                    }
+                    super::edit::insert_path(&mut merged, &key, value)
+                }
+                Ok(_) => tracing::warn!("Ignoring '{}' in project config: global-only key", key),
+                Err(e) => tracing::warn!("Ignoring project config: {}", e),
+            }
+        }
+        Ok(merged.try_into()?)
+    }
+
+    /// `load_dotenv` of the global config file. Read ahead of `load`, so
+    /// that `.env` is in the environment before the settings that use it
+    /// (e.g. the identity for encrypted values).
+    pub fn dotenv_enabled() -> Result<bool> {
+        let config_path = Self::config_path()?;
+        if !config_path.exists() {
+            return Ok(false);
+        }
+        let content = fs::read_to_string(&config_path).context("Failed to read config file")?;
+        let table: toml::Table = toml::from_str(&content).context("Failed to parse config file")?;
+        Ok(table.get("load_dotenv").and_then(toml::Value::as_bool).unwrap_or(false))
+    }
+
+    /// Load variables from a `.env` file without overriding ones already set.
+    ///
+    /// An explicit `path` must exist; otherwise `.env` in the working directory
+    /// is loaded if present. Returns the file that was loaded.
+    pub fn load_env_file(path: Option<&Path>) -> Result<Option<PathBuf>> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let default = PathBuf::from(".env");
+                if !default.exists() {
+                    return Ok(None);
+                }
+                default
+            }
+        };
+
+        dotenvy::from_path(&path)
+            .with_context(|| format!("Failed to load env file {}", path.display()))?;
+        Ok(Some(path))
     }
 
     pub fn save(&self) -> Result<()> {
@@ -180,6 +947,65 @@ impl Settings {
         self.models.get(name)
     }
 
+    /// The model to use without the network: the default model when it is
+    /// local, otherwise the first configured local one by name
+    pub fn local_model(&self) -> Option<&str> {
+        let is_local = |name: &str| {
+            self.models
+                .get(name)
+                .is_some_and(crate::util::connectivity::is_local_model)
+        };
+        if is_local(&self.default_model) {
+            return Some(&self.default_model);
+        }
+        let mut names: Vec<&String> = self.models.keys().filter(|name| is_local(name)).collect();
+        names.sort();
+        names.first().map(|name| name.as_str())
+    }
+
+    /// Resolve a fallback entry: a configured model name, or `provider:model`
+    /// using the first configured model of that provider for keys and URLs
+    pub fn resolve_fallback(&self, entry: &str) -> Option<ModelConfig> {
+        if let Some(config) = self.models.get(entry) {
+            return Some(config.clone());
+        }
+
+        let (provider, model) = entry.split_once(':')?;
+        let mut names: Vec<&String> = self.models.keys().collect();
+        names.sort();
+        let template = names
+            .into_iter()
+            .map(|name| &self.models[name])
+            .find(|m| m.provider == provider);
+
+        let mut config = match template {
+            Some(template) => template.clone(),
+            None => ModelConfig {
+                provider: provider.to_string(),
+                api_key: None,
+                api_key_env: match provider {
+                    "anthropic" => Some("ANTHROPIC_API_KEY".to_string()),
+                    "openai" => Some("OPENAI_API_KEY".to_string()),
+                    _ => None,
+                },
+                base_url: None,
+                model: String::new(),
+                temperature: default_temperature(),
+                max_tokens: default_max_tokens(),
+                context_window: None,
+                fallback: Vec::new(),
+                edit_format: None,
+                max_tools: None,
+                max_tool_bytes: None,
+                vision: None,
+            },
+        };
+        config.model = model.to_string();
+        config.context_window = None;
+        config.fallback = Vec::new();
+        Some(config)
+    }
+
     pub fn get_agent(&self, name: &str) -> Option<&AgentConfig> {
         self.agents.get(name)
     }
@@ -194,3 +1020,68 @@ impl Settings {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_config_overlay() {
+        let project = r#"
+default_model = "ollama"
+
+[shell]
+timeout_secs = 30
+sandbox = "none"
+
+[safety]
+confirm_shell_execute = false
+
+[skill_timeouts.skills]
+run_tests = 45
+"#;
+        let mut global = Settings::default();
+        global.shell.sandbox = SandboxKind::Docker;
+        global.safety.confirm_shell_execute = true;
+        let settings = global.with_project_config(project).unwrap();
+
+        assert_eq!(settings.default_model, "ollama");
+        assert_eq!(settings.shell.timeout_secs, 30);
+        assert_eq!(settings.skill_timeouts.skills["run_tests"], 45);
+        assert_eq!(settings.skill_timeouts.skills["execute_command"], 900);
+        // Global-only keys can't be weakened by a project file
+        assert_eq!(settings.shell.sandbox, SandboxKind::Docker);
+        assert!(settings.safety.confirm_shell_execute);
+    }
+
+    #[test]
+    fn test_project_config_cannot_set_risky_keys() {
+        let project = r#"
+load_dotenv = true
+
+[shell]
+image = "attacker/image"
+allow_network = true
+
+[http]
+insecure_skip_verify = true
+ca_bundle = "evil.pem"
+
+[safety]
+egress_allowlist = ["*"]
+
+[conversation_memory]
+global = true
+"#;
+        let global = Settings::default();
+        let settings = global.clone().with_project_config(project).unwrap();
+        assert_eq!(toml::Value::try_from(&settings).unwrap(), toml::Value::try_from(&global).unwrap());
+
+        for key in super::super::edit::CONFIG_KEYS {
+            let risky = ["safety.", "http.", "shell.sandbox", "shell.image", "shell.allow_network", "shell.read_only"]
+                .iter()
+                .any(|prefix| key.path.starts_with(prefix));
+            assert!(!(risky && key.project), "{} must be global-only", key.path);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// ============================================
+// WEBRANA CLI - Activity Journal
+// One line per finished autonomous run and interactive session, one file
+// per day, read back by `webrana digest`
+// ============================================
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::memory::utc_date;
+
+/// A finished run or session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Activity {
+    Run {
+        /// Unix seconds at the end of the run
+        time: u64,
+        task: String,
+        /// `completed`, `partially_completed`, `stalled`, `max_iterations`,
+        /// `deadline_exceeded`, `aborted` or `failed`
+        status: String,
+        duration_secs: u64,
+        /// Tool calls by skill name
+        #[serde(default)]
+        skills: BTreeMap<String, usize>,
+    },
+    Session {
+        time: u64,
+        turns: u64,
+        #[serde(default)]
+        skills: BTreeMap<String, usize>,
+        /// Working directory of the session
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        project: Option<String>,
+    },
+}
+
+impl Activity {
+    pub fn time(&self) -> u64 {
+        match self {
+            Activity::Run { time, .. } | Activity::Session { time, .. } => *time,
+        }
+    }
+
+    pub fn skills(&self) -> &BTreeMap<String, usize> {
+        match self {
+            Activity::Run { skills, .. } | Activity::Session { skills, .. } => skills,
+        }
+    }
+}
+
+/// Append-only JSON Lines files named `YYYY-MM-DD.jsonl`
+#[derive(Debug, Clone)]
+pub struct ActivityLog {
+    dir: PathBuf,
+}
+
+impl ActivityLog {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `<data_dir>/activity`
+    pub fn open_default() -> Option<Self> {
+        directories::ProjectDirs::from("dev", "webrana", "webrana-cli")
+            .map(|dirs| Self::new(dirs.data_dir().join("activity")))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Append `activity` to the file of its day in one write
+    pub fn record(&self, activity: &Activity) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let path = self.dir.join(format!("{}.jsonl", &utc_date(activity.time())[..10]));
+        let mut line = serde_json::to_string(activity)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Entries at or after `since` (unix seconds), oldest first, and the
+    /// number of unreadable lines
+    pub fn since(&self, since: u64) -> Result<(Vec<Activity>, usize)> {
+        let first_day = utc_date(since)[..10].to_string();
+        let mut files: Vec<PathBuf> = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str()?.strip_suffix(".jsonl"))
+                        .is_some_and(|day| day.len() == 10 && day >= first_day.as_str())
+                })
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", self.dir.display())),
+        };
+        files.sort();
+
+        let (mut entries, mut skipped) = (Vec::new(), 0);
+        for path in files {
+            let file = fs::File::open(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            for line in BufReader::new(file).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Activity>(&line) {
+                    Ok(entry) if entry.time() >= since => entries.push(entry),
+                    Ok(_) => {}
+                    Err(_) => skipped += 1,
+                }
+            }
+        }
+        entries.sort_by_key(Activity::time);
+        Ok((entries, skipped))
+    }
+}
+
+/// Record `activity` in the default journal. Failures only log: the
+/// journal must never fail a run.
+pub fn record(activity: Activity) {
+    let Some(log) = ActivityLog::open_default() else { return };
+    if let Err(e) = log.record(&activity) {
+        tracing::warn!("Could not record activity: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_read_since() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let log = ActivityLog::new(tmp.path().join("activity"));
+        let day = 86_400;
+        let run = |time| Activity::Run {
+            time,
+            task: "fix tests".to_string(),
+            status: "completed".to_string(),
+            duration_secs: 60,
+            skills: BTreeMap::from([("read_file".to_string(), 2)]),
+        };
+        log.record(&run(20_000 * day)).unwrap();
+        log.record(&Activity::Session { time: 20_001 * day + 5, turns: 3, skills: BTreeMap::new(), project: None }).unwrap();
+        log.record(&run(20_001 * day + 10)).unwrap();
+        let mut file = OpenOptions::new().append(true).open(log.dir().join("2024-10-05.jsonl")).unwrap();
+        file.write_all(b"{broken\n").unwrap();
+
+        let (entries, skipped) = log.since(20_001 * day).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(entries[0], Activity::Session { turns: 3, .. }));
+        assert_eq!(entries[1].skills()["read_file"], 2);
+        assert_eq!(skipped, 1);
+
+        let (entries, _) = ActivityLog::new(tmp.path().join("missing")).since(0).unwrap();
+        assert!(entries.is_empty());
+    }
+}
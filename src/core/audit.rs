@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::fs::{File, OpenOptions};
 use std::io::{BufWriter, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -37,10 +37,15 @@ pub enum AuditEventType {
     SessionEnd,
     SecurityViolation,
     SecretDetected,
+    PermissionRequested,
+    PermissionGranted,
+    PermissionDenied,
 
     // System operations
     ConfigChange,
     PluginLoaded,
+    PluginPermissionDenied,
+    EgressBlocked,
     SkillExecuted,
     IndexingStarted,
     IndexingCompleted,
@@ -162,6 +167,113 @@ impl Default for AuditConfig {
     }
 }
 
+/// Directory under the data dir holding `audit.log` and its rotations.
+/// Logging to file is enabled by creating it.
+pub const LOG_DIR: &str = "audit";
+pub const LOG_FILE: &str = "audit.log";
+/// `audit.log` is rotated to `audit.log.1` when it grows past this
+pub const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+/// Rotated files kept (`audit.log.1` ..= `audit.log.N`)
+pub const KEEP_ROTATED: usize = 5;
+
+/// `<data_dir>/audit`
+pub fn log_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(LOG_DIR)
+}
+
+/// The default data directory's audit dir, if it can be determined
+pub fn default_log_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("dev", "webrana", "webrana-cli").map(|dirs| log_dir(dirs.data_dir()))
+}
+
+impl AuditConfig {
+    /// Default config, writing to `<dir>/audit.log` when `dir` exists
+    pub fn in_dir(dir: &Path) -> Self {
+        Self {
+            log_file: dir.is_dir().then(|| dir.join(LOG_FILE)),
+            ..Default::default()
+        }
+    }
+}
+
+/// Shift `audit.log.N` to `audit.log.N+1` (dropping the oldest) and move
+/// `path` to `audit.log.1` once it reaches `max_bytes`
+pub fn rotate_if_needed(path: &Path, max_bytes: u64) -> std::io::Result<bool> {
+    let size = match std::fs::metadata(path) {
+        Ok(meta) => meta.len(),
+        Err(_) => return Ok(false),
+    };
+    if size < max_bytes {
+        return Ok(false);
+    }
+    let rotated = |n: usize| PathBuf::from(format!("{}.{}", path.display(), n));
+    let _ = std::fs::remove_file(rotated(KEEP_ROTATED));
+    for n in (1..KEEP_ROTATED).rev() {
+        if rotated(n).exists() {
+            std::fs::rename(rotated(n), rotated(n + 1))?;
+        }
+    }
+    std::fs::rename(path, rotated(1))?;
+    Ok(true)
+}
+
+/// `audit.log` and its rotations in `dir`
+pub fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let rotated_prefix = format!("{}.", LOG_FILE);
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name == LOG_FILE
+                || name
+                    .strip_prefix(&rotated_prefix)
+                    .is_some_and(|n| n.parse::<usize>().is_ok())
+        })
+        .map(|entry| entry.path())
+        .collect();
+    files.sort();
+    files
+}
+
+/// Timestamp and event type name of a line written by [`AuditEvent::to_log_line`]
+pub fn parse_log_line(line: &str) -> Option<(u64, &str)> {
+    let rest = line.strip_prefix('[')?;
+    let (timestamp, rest) = rest.split_once("] ")?;
+    let (_severity, rest) = rest.split_once(' ')?;
+    let (event_type, _) = rest.split_once(':')?;
+    Some((timestamp.parse().ok()?, event_type))
+}
+
+/// Start of a `--since` window ending at `now`, in unix seconds: an age
+/// like `7d`, `12h` or `2w`
+pub fn since_cutoff(since: &str, now: u64) -> Result<u64> {
+    Ok(now.saturating_sub(crate::memory::parse_age(since)?.as_secs()))
+}
+
+/// Count events of `types` logged at or after `since` across the audit
+/// files in `dir`. Returns counts in `types` order and the files read.
+pub fn count_events_since(dir: &Path, types: &[AuditEventType], since: u64) -> (Vec<usize>, usize) {
+    let names: Vec<String> = types.iter().map(|t| format!("{:?}", t)).collect();
+    let mut counts = vec![0; types.len()];
+    let files = log_files(dir);
+    for file in &files {
+        let Ok(content) = std::fs::read_to_string(file) else {
+            continue;
+        };
+        for (timestamp, event_type) in content.lines().filter_map(parse_log_line) {
+            if timestamp < since {
+                continue;
+            }
+            if let Some(i) = names.iter().position(|n| n == event_type) {
+                counts[i] += 1;
+            }
+        }
+    }
+    (counts, files.len())
+}
+
 /// Audit logger
 pub struct AuditLogger {
     config: AuditConfig,
@@ -173,6 +285,7 @@ pub struct AuditLogger {
 impl AuditLogger {
     pub fn new(config: AuditConfig) -> Result<Self> {
         let file_writer = if let Some(ref path) = config.log_file {
+            rotate_if_needed(path, MAX_LOG_BYTES)?;
             let file = OpenOptions::new()
                 .create(true)
                 .append(true)
@@ -409,7 +522,9 @@ impl AuditLogger {
 /// Global audit logger instance
 lazy_static::lazy_static! {
     pub static ref AUDIT: Arc<AuditLogger> = Arc::new(
-        AuditLogger::new(AuditConfig::default()).expect("Failed to create audit logger")
+        AuditLogger::new(default_log_dir().map(|dir| AuditConfig::in_dir(&dir)).unwrap_or_default())
+            .or_else(|_| AuditLogger::new(AuditConfig::default()))
+            .expect("Failed to create audit logger")
     );
 }
 
@@ -506,4 +621,26 @@ mod tests {
         assert!(AuditSeverity::Warning < AuditSeverity::Error);
         assert!(AuditSeverity::Error < AuditSeverity::Critical);
     }
+
+    #[test]
+    fn test_rotation_and_counting_across_files() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join(LOG_FILE);
+        let logger = AuditLogger::new(AuditConfig::in_dir(tmp.path())).unwrap();
+        logger.log_command_blocked("rm -rf /", "Dangerous command");
+        logger.log_security_violation("path escape", None);
+        drop(logger);
+
+        assert!(rotate_if_needed(&path, 1).unwrap());
+        std::fs::write(
+            &path,
+            "[100] WARN CommandBlocked: old\n[2000000000] ERROR SecurityViolation: new | {}\nnot a log line\n",
+        )
+        .unwrap();
+        std::fs::write(tmp.path().join("audit.log.bak"), "[2000000000] WARN CommandBlocked: x\n").unwrap();
+
+        let types = [AuditEventType::SecurityViolation, AuditEventType::CommandBlocked];
+        assert_eq!(count_events_since(tmp.path(), &types, 1000), (vec![2, 1], 2));
+        assert_eq!(parse_log_line("[7] INFO FileRead: a: b"), Some((7, "FileRead")));
+    }
 }
@@ -0,0 +1,96 @@
+// ============================================
+// WEBRANA CLI - External Binary Resolution
+// Finds git once per process, with a clear error when missing
+// ============================================
+
+use anyhow::{anyhow, Result};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Overrides the git lookup, e.g. for a portable install outside PATH
+pub const GIT_ENV: &str = "WEBRANA_GIT";
+
+static GIT: OnceLock<std::result::Result<PathBuf, String>> = OnceLock::new();
+
+/// Resolved path of the git executable, cached for the process
+pub fn git() -> Result<PathBuf> {
+    GIT.get_or_init(|| {
+        resolve_git(
+            std::env::var_os(GIT_ENV),
+            std::env::var_os("PATH"),
+            &std::env::current_dir().unwrap_or_default(),
+        )
+        .map_err(|e| e.to_string())
+    })
+    .clone()
+    .map_err(|e| anyhow!(e))
+}
+
+/// `std::process::Command` for the resolved git
+pub fn git_command() -> Result<std::process::Command> {
+    Ok(std::process::Command::new(git()?))
+}
+
+/// Look git up the way `which` does: an explicit override wins, then each
+/// entry of `path_var` (PATHEXT is honoured on Windows)
+pub fn resolve_git(
+    override_path: Option<OsString>,
+    path_var: Option<OsString>,
+    cwd: &Path,
+) -> Result<PathBuf> {
+    if let Some(path) = override_path.filter(|p| !p.is_empty()) {
+        let path = PathBuf::from(path);
+        return which::which_in(&path, None::<&str>, cwd).map_err(|_| {
+            anyhow!(
+                "{} is set to {}, but no executable was found there",
+                GIT_ENV,
+                path.display()
+            )
+        });
+    }
+    which::which_in("git", path_var, cwd).map_err(|_| {
+        anyhow!(
+            "git was not found on PATH. Install it (https://git-scm.com/downloads) or set {} to its full path",
+            GIT_ENV
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_git_is_a_clear_error() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let empty = tmp.path().join("bin");
+        std::fs::create_dir(&empty).unwrap();
+
+        let err = resolve_git(None, Some(empty.clone().into_os_string()), tmp.path()).unwrap_err();
+        assert!(err.to_string().starts_with("git was not found on PATH"), "{}", err);
+        assert!(err.to_string().contains(GIT_ENV));
+
+        let err = resolve_git(Some(empty.join("git").into_os_string()), None, tmp.path())
+            .unwrap_err();
+        assert!(err.to_string().contains("no executable was found there"), "{}", err);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolves_from_path_and_override() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::TempDir::new().unwrap();
+        let bin = tmp.path().join("tools");
+        std::fs::create_dir(&bin).unwrap();
+        let fake = bin.join("git");
+        std::fs::write(&fake, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let found = resolve_git(None, Some(bin.clone().into_os_string()), tmp.path()).unwrap();
+        assert_eq!(found, fake);
+        let found = resolve_git(Some(fake.clone().into_os_string()), None, tmp.path()).unwrap();
+        assert_eq!(found, fake);
+    }
+}
@@ -0,0 +1,239 @@
+// ============================================
+// WEBRANA CLI - Chat Scripts
+// A fixed sequence of user turns with optional checks on each answer
+// ============================================
+
+use anyhow::{anyhow, bail, Context, Result};
+use regex::Regex;
+use serde_yaml::Value;
+use std::fmt;
+use std::path::Path;
+
+/// `webrana chat --script` input
+#[derive(Debug, Clone)]
+pub struct ChatScript {
+    pub turns: Vec<ScriptTurn>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScriptTurn {
+    pub message: String,
+    pub expect: Vec<Expectation>,
+}
+
+/// Something the assistant's answer to a turn must (not) do
+#[derive(Debug, Clone)]
+pub enum Expectation {
+    /// Answer contains the text, ignoring case
+    Mentions(String),
+    /// Answer does not contain the text, ignoring case
+    Avoids(String),
+    /// Answer matches the regex
+    Matches(Regex),
+    /// The turn called this tool
+    CallsTool(String),
+}
+
+impl Expectation {
+    /// `answer` is the final response of the turn, `tool_calls` the
+    /// `name(arguments)` of each call made during it
+    pub fn is_met(&self, answer: &str, tool_calls: &[String]) -> bool {
+        match self {
+            Expectation::Mentions(text) => answer.to_lowercase().contains(&text.to_lowercase()),
+            Expectation::Avoids(text) => !answer.to_lowercase().contains(&text.to_lowercase()),
+            Expectation::Matches(regex) => regex.is_match(answer),
+            Expectation::CallsTool(name) => tool_calls
+                .iter()
+                .any(|call| call.split('(').next() == Some(name.as_str())),
+        }
+    }
+
+    fn parse(value: &Value) -> Result<Self> {
+        if let Value::String(text) = value {
+            return Ok(Expectation::Mentions(text.clone()));
+        }
+        let Value::Mapping(map) = value else {
+            bail!("an expectation is a string or a one-key map (mentions, not, regex, tool)");
+        };
+        let [(key, value)] = map.iter().collect::<Vec<_>>()[..] else {
+            bail!("an expectation map has exactly one key");
+        };
+        let text = value
+            .as_str()
+            .ok_or_else(|| anyhow!("the value of '{}' must be a string", key.as_str().unwrap_or("?")))?
+            .to_string();
+        match key.as_str() {
+            Some("mentions") => Ok(Expectation::Mentions(text)),
+            Some("not") => Ok(Expectation::Avoids(text)),
+            Some("regex") => Ok(Expectation::Matches(
+                Regex::new(&text).with_context(|| format!("invalid regex '{}'", text))?,
+            )),
+            Some("tool") => Ok(Expectation::CallsTool(text)),
+            _ => bail!("unknown expectation {:?}; use mentions, not, regex or tool", key),
+        }
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expectation::Mentions(text) => write!(f, "mentions \"{}\"", text),
+            Expectation::Avoids(text) => write!(f, "does not mention \"{}\"", text),
+            Expectation::Matches(regex) => write!(f, "matches /{}/", regex.as_str()),
+            Expectation::CallsTool(name) => write!(f, "calls {}", name),
+        }
+    }
+}
+
+/// An expectation a turn did not meet
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnmetExpectation {
+    /// From 1
+    pub turn: usize,
+    pub expectation: String,
+}
+
+impl fmt::Display for UnmetExpectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "turn {}: assistant {}", self.turn, self.expectation)
+    }
+}
+
+impl ChatScript {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read chat script {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid chat script {}", path.display()))
+    }
+
+    /// A list of turns, or a map with a `turns` list. A turn is the message
+    /// itself, or a map with `message` and an optional `expect` list.
+    pub fn parse(text: &str) -> Result<Self> {
+        let document: Value = serde_yaml::from_str(text)?;
+        let turns = match &document {
+            Value::Sequence(turns) => turns,
+            Value::Mapping(map) => map
+                .get(&Value::from("turns"))
+                .and_then(Value::as_sequence)
+                .context("expected a 'turns' list")?,
+            _ => bail!("expected a list of turns"),
+        };
+        if turns.is_empty() {
+            bail!("the script has no turns");
+        }
+        let turns = turns
+            .iter()
+            .enumerate()
+            .map(|(i, turn)| ScriptTurn::parse(turn).with_context(|| format!("Turn {}", i + 1)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { turns })
+    }
+
+    pub fn has_expectations(&self) -> bool {
+        self.turns.iter().any(|turn| !turn.expect.is_empty())
+    }
+}
+
+impl ScriptTurn {
+    fn parse(value: &Value) -> Result<Self> {
+        let (message, expect) = match value {
+            Value::String(message) => (message.clone(), Vec::new()),
+            Value::Mapping(map) => {
+                let message = map
+                    .get(&Value::from("message"))
+                    .context("missing 'message'")?
+                    .as_str()
+                    .context("'message' must be a string")?
+                    .to_string();
+                let expect = match map.get(&Value::from("expect")) {
+                    None => Vec::new(),
+                    Some(Value::Sequence(items)) => items
+                        .iter()
+                        .enumerate()
+                        .map(|(j, item)| Expectation::parse(item).with_context(|| format!("expectation {}", j + 1)))
+                        .collect::<Result<Vec<_>>>()?,
+                    Some(single) => vec![Expectation::parse(single).context("expectation 1")?],
+                };
+                (message, expect)
+            }
+            _ => bail!("a turn is a message string or a map with 'message'"),
+        };
+        if message.trim().is_empty() {
+            bail!("the message is empty");
+        }
+        Ok(Self { message, expect })
+    }
+
+    /// The expectations `answer` and `tool_calls` do not meet, described
+    pub fn unmet(&self, turn: usize, answer: &str, tool_calls: &[String]) -> Vec<UnmetExpectation> {
+        self.expect
+            .iter()
+            .filter(|expectation| !expectation.is_met(answer, tool_calls))
+            .map(|expectation| UnmetExpectation {
+                turn,
+                expectation: expectation.to_string(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_script_forms() {
+        let script = ChatScript::parse(
+            "turns:\n  - Summarize README.md\n  - message: Which license?\n    expect:\n      - MIT\n      - not: GPL\n      - regex: '(?i)licen[cs]e'\n      - tool: read_file\n",
+        )
+        .unwrap();
+        assert_eq!(script.turns.len(), 2);
+        assert_eq!(script.turns[0].message, "Summarize README.md");
+        assert!(script.turns[0].expect.is_empty());
+        let described: Vec<String> = script.turns[1].expect.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            described,
+            vec!["mentions \"MIT\"", "does not mention \"GPL\"", "matches /(?i)licen[cs]e/", "calls read_file"]
+        );
+        assert!(script.has_expectations());
+
+        let list = ChatScript::parse("- hello\n- message: bye\n  expect: ciao\n").unwrap();
+        assert_eq!(list.turns[1].expect.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_turn() {
+        let err = |text: &str| format!("{:#}", ChatScript::parse(text).unwrap_err());
+        assert!(err("- ok\n- expect: [x]\n").starts_with("Turn 2: missing 'message'"), "{}", err("- ok\n- expect: [x]\n"));
+        assert!(err("- ok\n- ok\n- message: hi\n  expect:\n    - regex: '('\n").starts_with("Turn 3: expectation 1: invalid regex"));
+        assert!(err("- message: hi\n  expect: [{bogus: x}]\n").starts_with("Turn 1: expectation 1: unknown expectation"));
+        assert!(err("- [1, 2]\n").starts_with("Turn 1: a turn is"));
+        assert!(err("- '  '\n").starts_with("Turn 1: the message is empty"));
+        assert_eq!(err("turns: []\n"), "the script has no turns");
+    }
+
+    #[test]
+    fn test_expectation_evaluation() {
+        let turn = ChatScript::parse(
+            "- message: q\n  expect: [mit, {not: gpl}, {regex: '^The'}, {tool: read_file}]\n",
+        )
+        .unwrap()
+        .turns
+        .remove(0);
+
+        let calls = vec![r#"read_file({"path":"LICENSE"})"#.to_string()];
+        assert!(turn.unmet(1, "The project is MIT licensed.", &calls).is_empty());
+
+        let unmet = turn.unmet(2, "It uses the GPL.", &[r#"read_file_lines({})"#.to_string()]);
+        let described: Vec<String> = unmet.iter().map(|u| u.to_string()).collect();
+        assert_eq!(
+            described,
+            vec![
+                "turn 2: assistant mentions \"mit\"",
+                "turn 2: assistant does not mention \"gpl\"",
+                "turn 2: assistant matches /^The/",
+                "turn 2: assistant calls read_file",
+            ]
+        );
+    }
+}
@@ -0,0 +1,568 @@
+// ============================================
+// WEBRANA CLI - Activity Digest
+// What webrana did on this machine over a window: runs, sessions, tokens
+// and cost, skills, secret scans and security events, from local stores
+// ============================================
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::activity::{Activity, ActivityLog};
+use super::audit::{self, AuditEventType};
+use super::orchestrator::format_duration;
+use super::secrets::ScanStamp;
+use crate::llm::{UsageGroup, UsageLedger, UsageSummary};
+use crate::memory::utc_date;
+
+/// Longest runs listed
+pub const TOP_TASKS: usize = 5;
+/// Most used skills listed
+pub const TOP_SKILLS: usize = 10;
+
+/// Audit events that count as security events
+const SECURITY_EVENTS: [AuditEventType; 6] = [
+    AuditEventType::CommandBlocked,
+    AuditEventType::SecurityViolation,
+    AuditEventType::PermissionDenied,
+    AuditEventType::FileAccessDenied,
+    AuditEventType::EgressBlocked,
+    AuditEventType::PluginPermissionDenied,
+];
+
+/// Where the digest reads from
+#[derive(Debug, Clone)]
+pub struct Sources {
+    /// Holds `activity/`, `usage/` and `audit/`
+    pub data_dir: PathBuf,
+    /// Project whose secret scans are reported
+    pub project: PathBuf,
+}
+
+impl Sources {
+    /// The default data directory and `project`
+    pub fn default_for(project: &Path) -> Option<Self> {
+        directories::ProjectDirs::from("dev", "webrana", "webrana-cli").map(|dirs| Self {
+            data_dir: dirs.data_dir().to_path_buf(),
+            project: project.to_path_buf(),
+        })
+    }
+}
+
+/// A section's data, or why there is none
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Tracked<T> {
+    Data(T),
+    NotTracked { not_tracked: String },
+}
+
+impl<T> Tracked<T> {
+    fn not_tracked(reason: impl Into<String>) -> Self {
+        Tracked::NotTracked { not_tracked: reason.into() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunSummary {
+    pub task: String,
+    pub status: String,
+    pub duration_secs: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RunStats {
+    pub total: usize,
+    pub succeeded: usize,
+    /// Stalled, out of iterations or past the deadline
+    pub stalled: usize,
+    /// Errors and runs aborted by the user
+    pub failed: usize,
+    /// Longest first, at most [`TOP_TASKS`]
+    pub longest: Vec<RunSummary>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SessionStats {
+    pub sessions: usize,
+    pub turns: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelUsage {
+    /// `model (provider)`
+    pub model: String,
+    pub requests: usize,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    /// USD over the requests with a known price
+    pub cost: f64,
+    pub unpriced: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkillCount {
+    pub skill: String,
+    pub calls: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScanPoint {
+    /// Unix seconds
+    pub time: u64,
+    pub total_secrets: usize,
+    pub critical: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SecurityStats {
+    pub total: usize,
+    /// Audit event type name to count, only types that occurred
+    pub by_type: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Digest {
+    /// Unix seconds
+    pub since: u64,
+    pub until: u64,
+    pub runs: Tracked<RunStats>,
+    pub sessions: Tracked<SessionStats>,
+    pub usage: Tracked<Vec<ModelUsage>>,
+    pub skills: Tracked<Vec<SkillCount>>,
+    pub secret_scans: Tracked<Vec<ScanPoint>>,
+    pub security_events: Tracked<SecurityStats>,
+}
+
+/// Runs by outcome and the longest ones
+pub fn run_stats(activity: &[Activity]) -> RunStats {
+    let mut stats = RunStats::default();
+    let mut runs = Vec::new();
+    for entry in activity {
+        let Activity::Run { task, status, duration_secs, .. } = entry else { continue };
+        stats.total += 1;
+        match status.as_str() {
+            "completed" => stats.succeeded += 1,
+            "partially_completed" | "stalled" | "max_iterations" | "deadline_exceeded" => stats.stalled += 1,
+            _ => stats.failed += 1,
+        }
+        runs.push(RunSummary {
+            task: task.clone(),
+            status: status.clone(),
+            duration_secs: *duration_secs,
+        });
+    }
+    runs.sort_by_key(|run| std::cmp::Reverse(run.duration_secs));
+    runs.truncate(TOP_TASKS);
+    stats.longest = runs;
+    stats
+}
+
+pub fn session_stats(activity: &[Activity]) -> SessionStats {
+    let mut stats = SessionStats::default();
+    for entry in activity {
+        if let Activity::Session { turns, .. } = entry {
+            stats.sessions += 1;
+            stats.turns += turns;
+        }
+    }
+    stats
+}
+
+/// Skill calls over runs and sessions, most used first, ties by name
+pub fn top_skills(activity: &[Activity]) -> Vec<SkillCount> {
+    let mut totals: BTreeMap<&str, usize> = BTreeMap::new();
+    for (skill, calls) in activity.iter().flat_map(Activity::skills) {
+        *totals.entry(skill).or_default() += calls;
+    }
+    let mut skills: Vec<SkillCount> = totals
+        .into_iter()
+        .map(|(skill, calls)| SkillCount { skill: skill.to_string(), calls })
+        .collect();
+    skills.sort_by_key(|skill| std::cmp::Reverse(skill.calls));
+    skills.truncate(TOP_SKILLS);
+    skills
+}
+
+/// Per-model rows of a usage summary grouped by model
+pub fn usage_by_model(summary: &UsageSummary) -> Vec<ModelUsage> {
+    summary
+        .groups
+        .iter()
+        .map(|(model, totals)| ModelUsage {
+            model: model.clone(),
+            requests: totals.requests,
+            input_tokens: totals.input_tokens,
+            output_tokens: totals.output_tokens,
+            cost: totals.cost,
+            unpriced: totals.unpriced,
+        })
+        .collect()
+}
+
+/// Scans at or after `since`, oldest first
+pub fn scan_trend(stamps: &[ScanStamp], since: u64) -> Vec<ScanPoint> {
+    stamps
+        .iter()
+        .filter(|stamp| stamp.time >= since)
+        .map(|stamp| ScanPoint {
+            time: stamp.time,
+            total_secrets: stamp.total_secrets,
+            critical: stamp.critical(),
+        })
+        .collect()
+}
+
+/// Security events in the audit files of `dir` at or after `since`
+pub fn security_stats(dir: &Path, since: u64) -> SecurityStats {
+    let (counts, _) = audit::count_events_since(dir, &SECURITY_EVENTS, since);
+    let mut stats = SecurityStats::default();
+    for (event, count) in SECURITY_EVENTS.iter().zip(counts) {
+        if count > 0 {
+            stats.total += count;
+            stats.by_type.insert(format!("{:?}", event), count);
+        }
+    }
+    stats
+}
+
+/// Aggregate every source over `since..=now`. A source that does not exist
+/// is reported as not tracked; one that can't be read is an error.
+pub fn build(sources: &Sources, since: u64, now: u64) -> Result<Digest> {
+    let activity_log = ActivityLog::new(sources.data_dir.join("activity"));
+    let (runs, sessions, skills) = if activity_log.dir().is_dir() {
+        let (activity, skipped) = activity_log.since(since)?;
+        if skipped > 0 {
+            tracing::warn!("{} unreadable activity line(s) skipped", skipped);
+        }
+        (
+            Tracked::Data(run_stats(&activity)),
+            Tracked::Data(session_stats(&activity)),
+            Tracked::Data(top_skills(&activity)),
+        )
+    } else {
+        let reason = "no runs or sessions recorded on this machine yet";
+        (Tracked::not_tracked(reason), Tracked::not_tracked(reason), Tracked::not_tracked(reason))
+    };
+
+    let ledger = UsageLedger::new(sources.data_dir.join("usage"));
+    let usage = if ledger.dir().is_dir() {
+        Tracked::Data(usage_by_model(&ledger.summarize_after(since, UsageGroup::Model)?))
+    } else {
+        Tracked::not_tracked("no usage ledger; requests are recorded from the first model call on")
+    };
+
+    let stamps = match ScanStamp::history(&sources.project)? {
+        Some(stamps) => Some(stamps),
+        // Scans from before the history was kept left only the last stamp
+        None => ScanStamp::load(&sources.project)?.map(|stamp| vec![stamp]),
+    };
+    let secret_scans = match stamps {
+        Some(stamps) => Tracked::Data(scan_trend(&stamps, since)),
+        None => Tracked::not_tracked("`webrana scan` has not been run in this project"),
+    };
+
+    let audit_dir = audit::log_dir(&sources.data_dir);
+    let security_events = if audit_dir.is_dir() {
+        Tracked::Data(security_stats(&audit_dir, since))
+    } else {
+        Tracked::not_tracked(format!("audit logging is off; create {} to turn it on", audit_dir.display()))
+    };
+
+    Ok(Digest {
+        since,
+        until: now,
+        runs,
+        sessions,
+        usage,
+        skills,
+        secret_scans,
+        security_events,
+    })
+}
+
+impl Digest {
+    /// Markdown for a standup doc
+    pub fn render_markdown(&self) -> String {
+        let mut out = format!(
+            "# Webrana digest\n\n{} to {}\n",
+            utc_date(self.since),
+            utc_date(self.until)
+        );
+
+        out.push_str("\n## Runs\n\n");
+        section(&mut out, &self.runs, |out, runs| {
+            out.push_str(&format!(
+                "{} run{}: {} succeeded, {} stalled, {} failed\n",
+                runs.total,
+                plural(runs.total),
+                runs.succeeded,
+                runs.stalled,
+                runs.failed
+            ));
+            if !runs.longest.is_empty() {
+                out.push_str("\nLongest:\n\n");
+                for (i, run) in runs.longest.iter().enumerate() {
+                    out.push_str(&format!(
+                        "{}. {} ({}, {})\n",
+                        i + 1,
+                        run.task.lines().next().unwrap_or_default(),
+                        run.status.replace('_', " "),
+                        format_duration(Duration::from_secs(run.duration_secs))
+                    ));
+                }
+            }
+        });
+
+        out.push_str("\n## Sessions\n\n");
+        section(&mut out, &self.sessions, |out, sessions| {
+            out.push_str(&format!(
+                "{} session{}, {} turn{}\n",
+                sessions.sessions,
+                plural(sessions.sessions),
+                sessions.turns,
+                plural(sessions.turns as usize)
+            ));
+        });
+
+        out.push_str("\n## Tokens and cost\n\n");
+        section(&mut out, &self.usage, |out, models| {
+            if models.is_empty() {
+                out.push_str("No requests\n");
+                return;
+            }
+            out.push_str("| Model | Requests | Input | Output | Cost |\n|---|---:|---:|---:|---:|\n");
+            for model in models {
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} | ${:.4}{} |\n",
+                    model.model,
+                    model.requests,
+                    model.input_tokens,
+                    model.output_tokens,
+                    model.cost,
+                    if model.unpriced > 0 { format!(" ({} unpriced)", model.unpriced) } else { String::new() }
+                ));
+            }
+            let sum = |f: fn(&ModelUsage) -> usize| models.iter().map(f).sum::<usize>();
+            out.push_str(&format!(
+                "| **Total** | {} | {} | {} | ${:.4} |\n",
+                sum(|m| m.requests),
+                sum(|m| m.input_tokens),
+                sum(|m| m.output_tokens),
+                models.iter().map(|m| m.cost).sum::<f64>()
+            ));
+        });
+
+        out.push_str("\n## Top skills\n\n");
+        section(&mut out, &self.skills, |out, skills| {
+            if skills.is_empty() {
+                out.push_str("No skill calls\n");
+                return;
+            }
+            out.push_str("| Skill | Calls |\n|---|---:|\n");
+            for skill in skills {
+                out.push_str(&format!("| {} | {} |\n", skill.skill, skill.calls));
+            }
+        });
+
+        out.push_str("\n## Secret scans\n\n");
+        section(&mut out, &self.secret_scans, |out, scans| {
+            let (Some(first), Some(last)) = (scans.first(), scans.last()) else {
+                out.push_str("No scans in this period\n");
+                return;
+            };
+            out.push_str(&format!(
+                "{} scan{}: {} → {} secret{} ({} critical in the latest)\n",
+                scans.len(),
+                plural(scans.len()),
+                first.total_secrets,
+                last.total_secrets,
+                plural(last.total_secrets),
+                last.critical
+            ));
+        });
+
+        out.push_str("\n## Security events\n\n");
+        section(&mut out, &self.security_events, |out, security| {
+            if security.total == 0 {
+                out.push_str("None\n");
+                return;
+            }
+            let by_type: Vec<String> =
+                security.by_type.iter().map(|(event, count)| format!("{} {}", count, event)).collect();
+            out.push_str(&format!(
+                "{} event{}: {}\n",
+                security.total,
+                plural(security.total),
+                by_type.join(", ")
+            ));
+        });
+
+        out
+    }
+}
+
+fn section<T>(out: &mut String, tracked: &Tracked<T>, render: impl FnOnce(&mut String, &T)) {
+    match tracked {
+        Tracked::Data(data) => render(out, data),
+        Tracked::NotTracked { not_tracked } => out.push_str(&format!("_Not tracked: {}_\n", not_tracked)),
+    }
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::AuditEvent;
+    use crate::llm::{TokenUsage, UsageEntry};
+    use std::collections::HashMap;
+
+    const SNAPSHOT: &str = "src/core/snapshots/digest.md";
+
+    /// 2026-10-16 12:00 UTC
+    const NOW: u64 = 1_792_152_000;
+    const DAY: u64 = 86_400;
+
+    fn run(time: u64, task: &str, status: &str, duration_secs: u64, skills: &[(&str, usize)]) -> Activity {
+        Activity::Run {
+            time,
+            task: task.to_string(),
+            status: status.to_string(),
+            duration_secs,
+            skills: skills.iter().map(|(name, n)| (name.to_string(), *n)).collect(),
+        }
+    }
+
+    /// A data dir and project with every store filled in, plus entries
+    /// from before the window that must not count
+    fn fabricate(root: &Path) -> Sources {
+        let sources = Sources {
+            data_dir: root.join("data"),
+            project: root.join("project"),
+        };
+
+        let activity = ActivityLog::new(sources.data_dir.join("activity"));
+        for entry in [
+            run(NOW - 30 * DAY, "ancient task", "completed", 9_999, &[("shell", 50)]),
+            run(NOW - 6 * DAY, "Fix the flaky parser test", "completed", 754, &[("read_file", 6), ("edit_file", 2)]),
+            run(NOW - 4 * DAY, "Upgrade tokio\nand its friends", "stalled", 1_830, &[("shell", 4)]),
+            run(NOW - 3 * DAY, "Write release notes", "max_iterations", 95, &[("read_file", 3)]),
+            run(NOW - 2 * DAY, "Refactor the config loader", "failed", 40, &[]),
+            Activity::Session { time: NOW - 5 * DAY, turns: 12, skills: [("search_files".to_string(), 4)].into(), project: None },
+            Activity::Session { time: NOW - DAY, turns: 3, skills: [("read_file".to_string(), 1)].into(), project: None },
+        ] {
+            activity.record(&entry).unwrap();
+        }
+
+        let ledger = UsageLedger::new(sources.data_dir.join("usage"));
+        let usage = |input_tokens, output_tokens| TokenUsage { input_tokens, output_tokens };
+        for entry in [
+            UsageEntry::new(NOW - 40 * DAY, "anthropic", "claude-sonnet-4-20250514", usage(1_000_000, 0), false),
+            UsageEntry::new(NOW - 5 * DAY, "anthropic", "claude-sonnet-4-20250514", usage(120_000, 8_000), false),
+            UsageEntry::new(NOW - 2 * DAY, "anthropic", "claude-sonnet-4-20250514", usage(80_000, 4_000), false),
+            UsageEntry::new(NOW - DAY, "ollama", "llama3", usage(5_000, 1_000), true),
+        ] {
+            ledger.record(&entry).unwrap();
+        }
+
+        let stamp = |time, total_secrets, critical| ScanStamp {
+            time,
+            total_secrets,
+            files_with_secrets: total_secrets,
+            by_severity: HashMap::from([("Critical".to_string(), critical)]),
+        };
+        for scan in [stamp(NOW - 20 * DAY, 9, 4), stamp(NOW - 6 * DAY, 5, 2), stamp(NOW - DAY, 1, 0)] {
+            scan.save(&sources.project).unwrap();
+        }
+
+        let audit_dir = audit::log_dir(&sources.data_dir);
+        std::fs::create_dir_all(&audit_dir).unwrap();
+        let event = |time, event_type| {
+            let mut event = AuditEvent::new(event_type, crate::core::AuditSeverity::Warning, "test");
+            event.timestamp = time;
+            event.to_log_line()
+        };
+        let lines = [
+            event(NOW - 10 * DAY, AuditEventType::CommandBlocked),
+            event(NOW - 3 * DAY, AuditEventType::CommandBlocked),
+            event(NOW - 2 * DAY, AuditEventType::CommandBlocked),
+            event(NOW - 2 * DAY, AuditEventType::SecurityViolation),
+            event(NOW - DAY, AuditEventType::CommandExecuted),
+        ];
+        std::fs::write(audit_dir.join(audit::LOG_FILE), lines.join("\n")).unwrap();
+
+        sources
+    }
+
+    #[test]
+    fn test_aggregation() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let sources = fabricate(tmp.path());
+        let since = audit::since_cutoff("7d", NOW).unwrap();
+        let digest = build(&sources, since, NOW).unwrap();
+
+        let Tracked::Data(runs) = &digest.runs else { panic!("runs not tracked") };
+        assert_eq!((runs.total, runs.succeeded, runs.stalled, runs.failed), (4, 1, 2, 1));
+        assert_eq!(runs.longest[0].task, "Upgrade tokio\nand its friends");
+        assert_eq!(runs.longest.len(), 4);
+
+        assert_eq!(digest.sessions, Tracked::Data(SessionStats { sessions: 2, turns: 15 }));
+
+        let Tracked::Data(skills) = &digest.skills else { panic!("skills not tracked") };
+        let skills: Vec<(&str, usize)> = skills.iter().map(|s| (s.skill.as_str(), s.calls)).collect();
+        assert_eq!(skills, [("read_file", 10), ("search_files", 4), ("shell", 4), ("edit_file", 2)]);
+
+        let Tracked::Data(usage) = &digest.usage else { panic!("usage not tracked") };
+        assert_eq!(usage.len(), 2);
+        assert_eq!((usage[0].requests, usage[0].input_tokens), (2, 200_000));
+
+        let Tracked::Data(scans) = &digest.secret_scans else { panic!("scans not tracked") };
+        assert_eq!(scans.iter().map(|s| s.total_secrets).collect::<Vec<_>>(), [5, 1]);
+
+        let Tracked::Data(security) = &digest.security_events else { panic!("audit not tracked") };
+        assert_eq!(security.total, 3);
+        assert_eq!(security.by_type["CommandBlocked"], 2);
+    }
+
+    #[test]
+    fn test_missing_sources_are_not_tracked() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let sources = Sources {
+            data_dir: tmp.path().join("data"),
+            project: tmp.path().join("project"),
+        };
+        let digest = build(&sources, NOW - 7 * DAY, NOW).unwrap();
+        assert!(matches!(digest.runs, Tracked::NotTracked { .. }));
+        assert!(matches!(digest.usage, Tracked::NotTracked { .. }));
+        assert!(matches!(digest.secret_scans, Tracked::NotTracked { .. }));
+        assert!(matches!(digest.security_events, Tracked::NotTracked { .. }));
+
+        let markdown = digest.render_markdown();
+        assert_eq!(markdown.matches("_Not tracked: ").count(), 6);
+        let json = serde_json::to_value(&digest).unwrap();
+        assert!(json["runs"]["not_tracked"].is_string());
+    }
+
+    #[test]
+    fn test_markdown_snapshot() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let sources = fabricate(tmp.path());
+        let markdown = build(&sources, NOW - 7 * DAY, NOW).unwrap().render_markdown();
+
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::create_dir_all("src/core/snapshots").unwrap();
+            std::fs::write(SNAPSHOT, &markdown).unwrap();
+        }
+        let expected = std::fs::read_to_string(SNAPSHOT).expect("run with UPDATE_SNAPSHOTS=1 to create");
+        assert_eq!(markdown, expected);
+    }
+}
@@ -0,0 +1,265 @@
+// ============================================
+// WEBRANA CLI - Pre-Run Repository Check
+// Keeps the agent's changes apart from uncommitted work
+// ============================================
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::config::DirtyTreePolicy;
+use crate::skills::run_git_command;
+
+/// Dirty paths listed in the warning before the rest are summarized
+const MAX_LISTED_PATHS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DirtyTreeAction {
+    /// Nothing uncommitted
+    Clean,
+    /// Dirty paths were listed and the run went ahead
+    Warned,
+    /// Changes were stashed; `sha` identifies the stash commit
+    Stashed { message: String, sha: String },
+    /// The run switched to a new branch
+    Branched { branch: String },
+}
+
+/// Repository state at run start, for the run report
+#[derive(Debug, Clone)]
+pub struct RepoCheck {
+    /// None in a repository without commits
+    pub start_sha: Option<String>,
+    pub start_branch: Option<String>,
+    pub dirty: Vec<String>,
+    pub action: DirtyTreeAction,
+}
+
+impl RepoCheck {
+    /// Prominent warning listing the dirty paths
+    pub fn dirty_warning(&self) -> String {
+        let mut out = format!(
+            "Working tree has uncommitted changes ({} paths). The agent's edits will be mixed with them:\n",
+            self.dirty.len()
+        );
+        for path in self.dirty.iter().take(MAX_LISTED_PATHS) {
+            out.push_str(&format!("  {}\n", path));
+        }
+        if self.dirty.len() > MAX_LISTED_PATHS {
+            out.push_str(&format!("  ... and {} more\n", self.dirty.len() - MAX_LISTED_PATHS));
+        }
+        out.push_str("Set run.dirty_tree to block, stash or branch to change this.");
+        out
+    }
+
+    /// Lines for the end-of-run report
+    pub fn report(&self) -> String {
+        let start = match (&self.start_sha, &self.start_branch) {
+            (Some(sha), Some(branch)) => format!("Started at {} on {}", short(sha), branch),
+            (Some(sha), None) => format!("Started at {}", short(sha)),
+            (None, _) => "Started in a repository without commits".to_string(),
+        };
+        let action = match &self.action {
+            DirtyTreeAction::Clean => "Working tree was clean".to_string(),
+            DirtyTreeAction::Warned => format!(
+                "Working tree had {} uncommitted paths; they are mixed with the agent's changes",
+                self.dirty.len()
+            ),
+            DirtyTreeAction::Stashed { message, sha } => format!(
+                "Your {} uncommitted paths were stashed as '{}'.\n\
+                 Restore them with: git stash apply {}\n\
+                 Then remove the stash: git stash drop \"$(git stash list | grep -m1 '{}' | cut -d: -f1)\"",
+                self.dirty.len(),
+                message,
+                sha,
+                message
+            ),
+            DirtyTreeAction::Branched { branch } => format!(
+                "The run worked on branch {}; go back with: git checkout {}",
+                branch,
+                self.start_branch.as_deref().unwrap_or("-")
+            ),
+        };
+        format!("{}\n{}", start, action)
+    }
+}
+
+fn short(sha: &str) -> &str {
+    &sha[..sha.len().min(12)]
+}
+
+/// Check `dir` before a run and apply `policy` to uncommitted changes.
+/// Returns None outside a git repository. `confirm` is asked before
+/// stashing or branching; a refusal cancels the run.
+pub async fn check_repo(
+    dir: &Path,
+    policy: DirtyTreePolicy,
+    run_id: &str,
+    confirm: &mut dyn FnMut(&str) -> bool,
+) -> Result<Option<RepoCheck>> {
+    let cwd = dir.to_str();
+    if run_git_command(&["rev-parse", "--is-inside-work-tree"], cwd)
+        .await
+        .is_err()
+    {
+        return Ok(None);
+    }
+
+    let start_sha = run_git_command(&["rev-parse", "HEAD"], cwd)
+        .await
+        .ok()
+        .map(|s| s.trim().to_string());
+    let start_branch = run_git_command(&["rev-parse", "--abbrev-ref", "HEAD"], cwd)
+        .await
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|b| b != "HEAD");
+    let dirty: Vec<String> = run_git_command(&["status", "--porcelain", "--untracked-files=all"], cwd)
+        .await?
+        .lines()
+        .filter(|l| l.len() > 3)
+        .map(|l| l[3..].to_string())
+        .collect();
+
+    let mut check = RepoCheck {
+        start_sha,
+        start_branch,
+        dirty,
+        action: DirtyTreeAction::Clean,
+    };
+    if check.dirty.is_empty() {
+        return Ok(Some(check));
+    }
+
+    check.action = match policy {
+        DirtyTreePolicy::Warn => DirtyTreeAction::Warned,
+        DirtyTreePolicy::Block => anyhow::bail!(
+            "Refusing to start: {}\nCommit or stash them first (run.dirty_tree = block)",
+            check.dirty_warning().lines().next().unwrap_or_default()
+        ),
+        DirtyTreePolicy::Stash => {
+            let message = format!("webrana run {}", run_id);
+            if !confirm(&format!(
+                "Stash {} uncommitted paths as '{}' before the run?",
+                check.dirty.len(),
+                message
+            )) {
+                anyhow::bail!("Run cancelled: uncommitted changes were not stashed");
+            }
+            if check.start_sha.is_none() {
+                anyhow::bail!("Cannot stash in a repository without commits");
+            }
+            run_git_command(&["stash", "push", "--include-untracked", "-m", &message], cwd).await?;
+            let sha = run_git_command(&["rev-parse", "stash@{0}"], cwd).await?;
+            DirtyTreeAction::Stashed {
+                message,
+                sha: sha.trim().to_string(),
+            }
+        }
+        DirtyTreePolicy::Branch => {
+            let branch = format!("webrana/run-{}", run_id);
+            if !confirm(&format!("Create and switch to branch '{}' for this run?", branch)) {
+                anyhow::bail!("Run cancelled: branch '{}' was not created", branch);
+            }
+            run_git_command(&["checkout", "-q", "-b", &branch], cwd).await?;
+            DirtyTreeAction::Branched { branch }
+        }
+    };
+    Ok(Some(check))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    async fn git(dir: &Path, args: &[&str]) -> String {
+        run_git_command(args, dir.to_str()).await.unwrap()
+    }
+
+    /// Repo with one commit, a modified file and an untracked file
+    async fn dirty_repo() -> tempfile::TempDir {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path();
+        git(dir, &["init", "-q", "-b", "main"]).await;
+        fs::write(dir.join("lib.rs"), "fn a() {}\n").unwrap();
+        git(dir, &["add", "."]).await;
+        git(dir, &["-c", "user.name=t", "-c", "user.email=t@t", "commit", "-qm", "init"]).await;
+        fs::write(dir.join("lib.rs"), "fn b() {}\n").unwrap();
+        fs::write(dir.join("notes.txt"), "mine").unwrap();
+        tmp
+    }
+
+    async fn check(dir: &Path, policy: DirtyTreePolicy, answer: bool) -> Result<Option<RepoCheck>> {
+        check_repo(dir, policy, "42-abc", &mut |_| answer).await
+    }
+
+    #[tokio::test]
+    async fn test_non_git_and_clean_trees() {
+        let plain = tempfile::TempDir::new().unwrap();
+        assert!(check(plain.path(), DirtyTreePolicy::Block, true).await.unwrap().is_none());
+
+        let repo = dirty_repo().await;
+        git(repo.path(), &["checkout", "-q", "--", "lib.rs"]).await;
+        fs::remove_file(repo.path().join("notes.txt")).unwrap();
+        let result = check(repo.path(), DirtyTreePolicy::Block, true).await.unwrap().unwrap();
+        assert_eq!(result.action, DirtyTreeAction::Clean);
+        assert_eq!(result.start_branch.as_deref(), Some("main"));
+        let head = git(repo.path(), &["rev-parse", "HEAD"]).await;
+        assert_eq!(result.start_sha.as_deref(), Some(head.trim()));
+        assert!(result.report().starts_with(&format!("Started at {} on main", &head[..12])));
+    }
+
+    #[tokio::test]
+    async fn test_warn_and_block() {
+        let repo = dirty_repo().await;
+        let warned = check(repo.path(), DirtyTreePolicy::Warn, true).await.unwrap().unwrap();
+        assert_eq!(warned.action, DirtyTreeAction::Warned);
+        assert_eq!(warned.dirty, vec!["lib.rs", "notes.txt"]);
+        let warning = warned.dirty_warning();
+        assert!(warning.contains("uncommitted changes (2 paths)"));
+        assert!(warning.contains("  notes.txt\n"));
+
+        let err = check(repo.path(), DirtyTreePolicy::Block, true).await.unwrap_err();
+        assert!(err.to_string().starts_with("Refusing to start: Working tree has uncommitted"));
+        // Nothing was touched
+        assert_eq!(fs::read_to_string(repo.path().join("lib.rs")).unwrap(), "fn b() {}\n");
+    }
+
+    #[tokio::test]
+    async fn test_stash_with_restore_instructions() {
+        let repo = dirty_repo().await;
+        assert!(check(repo.path(), DirtyTreePolicy::Stash, false).await.is_err());
+        assert!(repo.path().join("notes.txt").exists());
+
+        let stashed = check(repo.path(), DirtyTreePolicy::Stash, true).await.unwrap().unwrap();
+        let DirtyTreeAction::Stashed { message, sha } = &stashed.action else {
+            panic!("expected a stash, got {:?}", stashed.action);
+        };
+        assert_eq!(message, "webrana run 42-abc");
+        assert!(git(repo.path(), &["status", "--porcelain"]).await.is_empty());
+        assert!(git(repo.path(), &["stash", "list"]).await.contains("webrana run 42-abc"));
+
+        let report = stashed.report();
+        assert!(report.contains("Your 2 uncommitted paths were stashed as 'webrana run 42-abc'."));
+        assert!(report.contains(&format!("Restore them with: git stash apply {}", sha)));
+
+        // The instructions work
+        git(repo.path(), &["stash", "apply", sha]).await;
+        assert_eq!(fs::read_to_string(repo.path().join("notes.txt")).unwrap(), "mine");
+    }
+
+    #[tokio::test]
+    async fn test_branch() {
+        let repo = dirty_repo().await;
+        assert!(check(repo.path(), DirtyTreePolicy::Branch, false).await.is_err());
+
+        let branched = check(repo.path(), DirtyTreePolicy::Branch, true).await.unwrap().unwrap();
+        assert_eq!(
+            branched.action,
+            DirtyTreeAction::Branched { branch: "webrana/run-42-abc".to_string() }
+        );
+        let current = git(repo.path(), &["rev-parse", "--abbrev-ref", "HEAD"]).await;
+        assert_eq!(current.trim(), "webrana/run-42-abc");
+        assert!(branched.report().contains("go back with: git checkout main"));
+    }
+}
@@ -0,0 +1,638 @@
+// ============================================
+// WEBRANA CLI - Doctor Checks
+// System and security checks reported by `webrana doctor`
+// ============================================
+
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::path::Path;
+
+use super::audit::{self, AuditEventType};
+use super::binaries;
+use super::secrets::{ScanStamp, SCAN_STAMP_FILE};
+use crate::config::{HttpSettings, SandboxFallback, SandboxKind, Settings};
+use crate::crew::CrewManager;
+use crate::memory::utc_date;
+use crate::util::{http, unix_now};
+use crate::util::netcheck::{self, HostCheck};
+
+pub const SYSTEM: &str = "System";
+pub const SECURITY: &str = "Security";
+pub const NETWORK: &str = "Network";
+
+/// Window for audit event counts, and the age after which a scan is stale
+pub const SECURITY_WINDOW_SECS: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Ok => "OK",
+            Self::Warn => "WARN",
+            Self::Fail => "FAIL",
+        }
+    }
+
+    /// The word a screen reader announces for this status
+    pub fn spoken(&self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Warn => "warning",
+            Self::Fail => "failed",
+        }
+    }
+}
+
+/// Action `doctor --fix` can take for a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DoctorFix {
+    /// Run a secret scan of the working directory
+    RunScan,
+    /// Set aside unreadable crew files (`CrewManager::repair`)
+    RepairCrews,
+}
+
+/// One line of `webrana doctor` output
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub section: &'static str,
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<DoctorFix>,
+}
+
+impl CheckResult {
+    fn new(section: &'static str, name: &str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            section,
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn with_fix(mut self, fix: DoctorFix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+/// Configuration, API keys, git and the plugins directory
+pub fn system_checks(settings: &Settings, data_dir: Option<&Path>) -> Vec<CheckResult> {
+    let mut checks = Vec::new();
+
+    checks.push(if settings.get_model(&settings.default_model).is_some() {
+        CheckResult::new(SYSTEM, "Configuration", CheckStatus::Ok, format!("model: {}", settings.default_model))
+    } else {
+        CheckResult::new(SYSTEM, "Configuration", CheckStatus::Warn, "no default model")
+    });
+
+    for (name, var) in [("OpenAI API Key", "OPENAI_API_KEY"), ("Anthropic API Key", "ANTHROPIC_API_KEY")] {
+        checks.push(if std::env::var(var).is_ok() {
+            CheckResult::new(SYSTEM, name, CheckStatus::Ok, "")
+        } else {
+            CheckResult::new(SYSTEM, name, CheckStatus::Warn, format!("{} not set", var))
+        });
+    }
+
+    checks.push(match binaries::git() {
+        Ok(git) => match std::process::Command::new(&git).arg("--version").output() {
+            Ok(output) => CheckResult::new(
+                SYSTEM,
+                "Git",
+                CheckStatus::Ok,
+                format!("{} ({})", String::from_utf8_lossy(&output.stdout).trim(), git.display()),
+            ),
+            Err(e) => CheckResult::new(SYSTEM, "Git", CheckStatus::Fail, format!("{}: {}", git.display(), e)),
+        },
+        Err(e) => CheckResult::new(SYSTEM, "Git", CheckStatus::Fail, e.to_string()),
+    });
+
+    checks.push(match data_dir.map(|dir| dir.join("plugins")) {
+        Some(dir) if dir.exists() => {
+            CheckResult::new(SYSTEM, "Plugins directory", CheckStatus::Ok, dir.display().to_string())
+        }
+        Some(dir) => CheckResult::new(
+            SYSTEM,
+            "Plugins directory",
+            CheckStatus::Ok,
+            format!("will be created: {}", dir.display()),
+        ),
+        None => CheckResult::new(SYSTEM, "Plugins directory", CheckStatus::Warn, "using .webrana/plugins"),
+    });
+
+    if let Some(dir) = data_dir.map(|dir| dir.join("crew")).filter(|dir| dir.is_dir()) {
+        checks.push(crew_check(&dir));
+    }
+
+    let now = unix_now();
+    checks.extend(tls_checks(&settings.http, now));
+
+    checks
+}
+
+/// Crew files that don't parse and a stale active pointer, left for `--fix`
+fn crew_check(dir: &Path) -> CheckResult {
+    match CrewManager::inspect(dir.to_path_buf()) {
+        Ok(manager) if manager.warnings().is_empty() => {
+            CheckResult::new(SYSTEM, "Crew storage", CheckStatus::Ok, format!("{} crew(s)", manager.count()))
+        }
+        Ok(manager) => {
+            CheckResult::new(SYSTEM, "Crew storage", CheckStatus::Warn, manager.warnings().join("; "))
+                .with_fix(DoctorFix::RepairCrews)
+        }
+        Err(e) => CheckResult::new(SYSTEM, "Crew storage", CheckStatus::Fail, format!("{}: {:#}", dir.display(), e)),
+    }
+}
+
+/// The `[http]` CA bundle and client certificate: readable, parseable and
+/// not expired. `now` is unix seconds.
+fn tls_checks(settings: &HttpSettings, now: u64) -> Vec<CheckResult> {
+    let mut checks = Vec::new();
+    let soon = now + 30 * 86_400;
+
+    for (name, path) in [("HTTP CA bundle", &settings.ca_bundle), ("HTTP client certificate", &settings.client_cert)] {
+        let Some(path) = path else {
+            continue;
+        };
+        let path = http::expand_path(path);
+        let certs = std::fs::read(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|pem| inspect_pem(&pem));
+        checks.push(match certs {
+            Ok(certs) => {
+                let status = if certs.iter().any(|c| c.not_after < now) {
+                    CheckStatus::Fail
+                } else if certs.iter().any(|c| c.not_after < soon) {
+                    CheckStatus::Warn
+                } else {
+                    CheckStatus::Ok
+                };
+                let detail = certs
+                    .iter()
+                    .map(|c| format!("{} (expires {})", c.subject, utc_date(c.not_after)))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                CheckResult::new(SYSTEM, name, status, detail)
+            }
+            Err(e) => CheckResult::new(SYSTEM, name, CheckStatus::Fail, format!("{}: {:#}", path.display(), e)),
+        });
+    }
+
+    checks.push(match http::client_builder(settings) {
+        Err(e) => CheckResult::new(SYSTEM, "HTTP TLS", CheckStatus::Fail, format!("{:#}", e)),
+        Ok(_) if settings.insecure_skip_verify => CheckResult::new(
+            SYSTEM,
+            "HTTP TLS",
+            CheckStatus::Warn,
+            "certificate verification is disabled (http.insecure_skip_verify)",
+        ),
+        Ok(_) => {
+            let roots = match (&settings.ca_bundle, settings.ca_replace) {
+                (None, _) => "system trust store",
+                (Some(_), false) => "system trust store + CA bundle",
+                (Some(_), true) => "CA bundle only",
+            };
+            let identity = if settings.client_cert.is_some() { ", client certificate" } else { "" };
+            CheckResult::new(SYSTEM, "HTTP TLS", CheckStatus::Ok, format!("{}{}", roots, identity))
+        }
+    });
+    checks
+}
+
+/// Subject and expiry of one certificate
+#[derive(Debug, Clone, PartialEq)]
+struct CertSummary {
+    /// e.g. "CN=Corp Root CA, O=Corp"
+    subject: String,
+    /// Unix seconds
+    not_after: u64,
+}
+
+/// Summaries of the certificates in a PEM file
+fn inspect_pem(pem: &[u8]) -> anyhow::Result<Vec<CertSummary>> {
+    use anyhow::Context;
+
+    let ders = rustls_pemfile::certs(&mut &pem[..]).context("Invalid PEM")?;
+    if ders.is_empty() {
+        anyhow::bail!("no certificates found");
+    }
+    ders.iter()
+        .map(|der| {
+            let (_, cert) = x509_parser::parse_x509_certificate(der)
+                .map_err(|e| anyhow::anyhow!("Unreadable certificate: {}", e))?;
+            Ok(CertSummary {
+                subject: cert.subject().to_string(),
+                not_after: cert.validity().not_after.timestamp().max(0) as u64,
+            })
+        })
+        .collect()
+}
+
+/// Audit log, recent security events, safety policy, sandboxing and the
+/// project's last secret scan. `now` is unix seconds.
+pub fn security_checks(
+    settings: &Settings,
+    data_dir: Option<&Path>,
+    project: &Path,
+    now: u64,
+) -> Vec<CheckResult> {
+    let mut checks = audit_checks(data_dir, now);
+    checks.push(policy_check(settings));
+    checks.push(sandbox_check(settings));
+    checks.extend(scan_checks(project, now));
+    checks
+}
+
+fn audit_checks(data_dir: Option<&Path>, now: u64) -> Vec<CheckResult> {
+    let Some(dir) = data_dir.map(audit::log_dir) else {
+        return vec![CheckResult::new(
+            SECURITY,
+            "Audit log",
+            CheckStatus::Warn,
+            "could not determine the data directory",
+        )];
+    };
+    if !dir.is_dir() {
+        return vec![
+            CheckResult::new(
+                SECURITY,
+                "Audit log",
+                CheckStatus::Warn,
+                format!("disabled (create {} to enable)", dir.display()),
+            ),
+            CheckResult::new(SECURITY, "Security events (7d)", CheckStatus::Warn, "unknown: audit log disabled"),
+        ];
+    }
+
+    let path = dir.join(audit::LOG_FILE);
+    let log = match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(_) => CheckResult::new(SECURITY, "Audit log", CheckStatus::Ok, format!("enabled ({})", path.display())),
+        Err(e) => CheckResult::new(
+            SECURITY,
+            "Audit log",
+            CheckStatus::Fail,
+            format!("{} is not writable: {}", path.display(), e),
+        ),
+    };
+
+    let types = [AuditEventType::SecurityViolation, AuditEventType::CommandBlocked];
+    let (counts, files) = audit::count_events_since(&dir, &types, now.saturating_sub(SECURITY_WINDOW_SECS));
+    let status = if counts.iter().all(|&n| n == 0) {
+        CheckStatus::Ok
+    } else {
+        CheckStatus::Warn
+    };
+    let events = CheckResult::new(
+        SECURITY,
+        "Security events (7d)",
+        status,
+        format!(
+            "{} SecurityViolation, {} CommandBlocked ({} audit file{})",
+            counts[0],
+            counts[1],
+            files,
+            if files == 1 { "" } else { "s" }
+        ),
+    );
+    vec![log, events]
+}
+
+/// The `[safety]` section of the global config acts as the policy file
+fn policy_check(settings: &Settings) -> CheckResult {
+    let safety = &settings.safety;
+    let mut restricted = Vec::new();
+    if safety.confirm_file_write {
+        restricted.push("file writes (confirm)".to_string());
+    }
+    if safety.confirm_file_delete {
+        restricted.push("file deletes (confirm)".to_string());
+    }
+    if safety.confirm_shell_execute {
+        restricted.push("shell (confirm)".to_string());
+    }
+    if !safety.allowed_commands.is_empty() {
+        restricted.push(format!("shell ({} allowed commands)", safety.allowed_commands.len()));
+    }
+    if !safety.blocked_paths.is_empty() {
+        restricted.push(format!("paths ({} blocked)", safety.blocked_paths.len()));
+    }
+
+    let source = match Settings::config_path() {
+        Ok(path) if path.exists() => format!("{} [safety]", path.display()),
+        _ => "built-in defaults".to_string(),
+    };
+    if restricted.is_empty() {
+        CheckResult::new(SECURITY, "Safety policy", CheckStatus::Warn, format!("{}: no restrictions", source))
+    } else {
+        CheckResult::new(
+            SECURITY,
+            "Safety policy",
+            CheckStatus::Ok,
+            format!("{}: restricts {}", source, restricted.join(", ")),
+        )
+    }
+}
+
+fn sandbox_check(settings: &Settings) -> CheckResult {
+    let shell = &settings.shell;
+    let runtime = match shell.sandbox {
+        SandboxKind::None => {
+            return CheckResult::new(
+                SECURITY,
+                "Shell sandbox",
+                CheckStatus::Warn,
+                "disabled: commands run on the host (set shell.sandbox = \"docker\" or \"podman\")",
+            )
+        }
+        SandboxKind::Docker => "docker",
+        SandboxKind::Podman => "podman",
+    };
+    let fallback = match shell.fallback {
+        SandboxFallback::Deny => "deny",
+        SandboxFallback::Host => "host",
+    };
+    CheckResult::new(
+        SECURITY,
+        "Shell sandbox",
+        CheckStatus::Ok,
+        format!(
+            "{} ({} workdir, network {}, fallback: {})",
+            runtime,
+            if shell.read_only { "read-only" } else { "writable" },
+            if shell.allow_network { "allowed" } else { "off" },
+            fallback
+        ),
+    )
+}
+
+fn scan_checks(project: &Path, now: u64) -> Vec<CheckResult> {
+    let stamp = match ScanStamp::load(project) {
+        Ok(Some(stamp)) => stamp,
+        Ok(None) => {
+            return vec![
+                CheckResult::new(SECURITY, "Last secret scan", CheckStatus::Warn, "no scan recorded for this project")
+                    .with_fix(DoctorFix::RunScan),
+                CheckResult::new(SECURITY, "Critical findings", CheckStatus::Warn, "unknown: no scan recorded"),
+            ]
+        }
+        Err(e) => {
+            return vec![CheckResult::new(
+                SECURITY,
+                "Last secret scan",
+                CheckStatus::Warn,
+                format!("unreadable {}: {}", SCAN_STAMP_FILE, e),
+            )
+            .with_fix(DoctorFix::RunScan)]
+        }
+    };
+
+    let age = now.saturating_sub(stamp.time);
+    let summary = format!(
+        "{} ago: {} finding{} in {} file{}",
+        format_age(age),
+        stamp.total_secrets,
+        if stamp.total_secrets == 1 { "" } else { "s" },
+        stamp.files_with_secrets,
+        if stamp.files_with_secrets == 1 { "" } else { "s" }
+    );
+    let last = if age > SECURITY_WINDOW_SECS {
+        CheckResult::new(SECURITY, "Last secret scan", CheckStatus::Warn, format!("stale, {}", summary))
+            .with_fix(DoctorFix::RunScan)
+    } else {
+        CheckResult::new(SECURITY, "Last secret scan", CheckStatus::Ok, summary)
+    };
+
+    let critical = match stamp.critical() {
+        0 => CheckResult::new(SECURITY, "Critical findings", CheckStatus::Ok, "none in last scan"),
+        n => CheckResult::new(
+            SECURITY,
+            "Critical findings",
+            CheckStatus::Fail,
+            format!("{} in last scan (run `webrana scan --min-severity critical`)", n),
+        ),
+    };
+    vec![last, critical]
+}
+
+pub(crate) fn format_age(secs: u64) -> String {
+    match secs {
+        s if s < 60 * 60 => format!("{}m", s / 60),
+        s if s < 24 * 60 * 60 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
+}
+
+/// DNS, proxy, TCP and TLS to every provider, update and registry host
+/// (`doctor --check-network`), all at once
+pub async fn network_checks(settings: &Settings) -> Vec<CheckResult> {
+    if http::is_offline() {
+        return vec![CheckResult::new(NETWORK, "Network", CheckStatus::Warn, "skipped in offline mode")];
+    }
+    let env = |name: &str| std::env::var(name).ok();
+    let targets = netcheck::targets(settings);
+    let checks = targets.iter().map(|(label, url)| netcheck::check_host(label, url, &settings.http, env));
+    futures::future::join_all(checks).await.iter().map(network_check).collect()
+}
+
+fn network_check(host: &HostCheck) -> CheckResult {
+    let name = format!("{} ({}:{})", host.label, host.host, host.port);
+    let via = host.proxy.as_ref().map(|p| format!(" via proxy {}", p)).unwrap_or_default();
+    match (host.failure, &host.error) {
+        (None, _) => CheckResult::new(
+            NETWORK,
+            &name,
+            CheckStatus::Ok,
+            format!("reachable in {} ms{}", host.latency_ms.unwrap_or_default(), via),
+        ),
+        (Some(kind), error) => CheckResult::new(
+            NETWORK,
+            &name,
+            CheckStatus::Fail,
+            format!("{} failure{}: {}", kind.label(), via, error.as_deref().unwrap_or_default()),
+        ),
+    }
+}
+
+/// Text report grouped by section
+pub fn render_text(checks: &[CheckResult]) -> String {
+    render_text_with(checks, crate::ui::is_a11y())
+}
+
+/// Text report; `accessible` leads each line with its status word
+pub fn render_text_with(checks: &[CheckResult], accessible: bool) -> String {
+    let mut out = String::new();
+    let mut section = "";
+    for check in checks {
+        if check.section != section {
+            section = check.section;
+            out.push_str(&format!("\n{}\n", section));
+        }
+        if accessible {
+            out.push_str(&format!("  {}: {}", check.status.spoken(), check.name));
+            if !check.detail.is_empty() {
+                out.push_str(&format!(", {}", check.detail));
+            }
+        } else {
+            out.push_str(&format!("  {}... {}", check.name, check.status.label()));
+            if !check.detail.is_empty() {
+                out.push_str(&format!(" ({})", check.detail));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const NOW: u64 = 2_000_000_000;
+
+    fn find<'a>(checks: &'a [CheckResult], name: &str) -> &'a CheckResult {
+        checks.iter().find(|c| c.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_network_check_lines() {
+        use crate::util::netcheck::FailureKind;
+
+        let mut host = HostCheck {
+            label: "model openai".to_string(),
+            host: "api.openai.com".to_string(),
+            port: 443,
+            proxy: Some("proxy.corp:8080".to_string()),
+            latency_ms: Some(84),
+            failure: None,
+            error: None,
+        };
+        let ok = network_check(&host);
+        assert_eq!((ok.section, ok.status), (NETWORK, CheckStatus::Ok));
+        assert_eq!(ok.name, "model openai (api.openai.com:443)");
+        assert_eq!(ok.detail, "reachable in 84 ms via proxy proxy.corp:8080");
+
+        host.proxy = None;
+        host.failure = Some(FailureKind::Dns);
+        host.error = Some("could not resolve api.openai.com".to_string());
+        let failed = network_check(&host);
+        assert_eq!(failed.status, CheckStatus::Fail);
+        assert_eq!(failed.detail, "DNS failure: could not resolve api.openai.com");
+    }
+
+    #[test]
+    fn test_security_checks() {
+        let data = tempfile::TempDir::new().unwrap();
+        let project = tempfile::TempDir::new().unwrap();
+        let mut settings = Settings::default();
+
+        let checks = security_checks(&settings, Some(data.path()), project.path(), NOW);
+        assert_eq!(find(&checks, "Audit log").status, CheckStatus::Warn);
+        assert_eq!(find(&checks, "Shell sandbox").status, CheckStatus::Warn);
+        assert_eq!(find(&checks, "Last secret scan").fix, Some(DoctorFix::RunScan));
+
+        let dir = audit::log_dir(data.path());
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("audit.log.1"),
+            format!("[{}] CRITICAL SecurityViolation: escape\n[100] WARN CommandBlocked: old\n", NOW - 60),
+        )
+        .unwrap();
+        let stamp = ScanStamp {
+            time: NOW - 3 * 3600,
+            total_secrets: 3,
+            files_with_secrets: 2,
+            by_severity: HashMap::from([("Critical".to_string(), 1), ("Low".to_string(), 2)]),
+        };
+        stamp.save(project.path()).unwrap();
+        settings.shell.sandbox = SandboxKind::Docker;
+        settings.shell.read_only = true;
+
+        let checks = security_checks(&settings, Some(data.path()), project.path(), NOW);
+        assert_eq!(find(&checks, "Audit log").status, CheckStatus::Ok);
+        let events = find(&checks, "Security events (7d)");
+        assert_eq!(events.detail, "1 SecurityViolation, 0 CommandBlocked (2 audit files)");
+        assert!(find(&checks, "Shell sandbox").detail.starts_with("docker (read-only workdir"));
+        let last = find(&checks, "Last secret scan");
+        assert_eq!((last.status, last.detail.as_str()), (CheckStatus::Ok, "3h ago: 3 findings in 2 files"));
+        assert_eq!(find(&checks, "Critical findings").status, CheckStatus::Fail);
+
+        let checks = security_checks(&settings, Some(data.path()), project.path(), NOW + 8 * 86400);
+        let last = find(&checks, "Last secret scan");
+        assert_eq!(last.status, CheckStatus::Warn);
+        assert!(last.detail.starts_with("stale, 8d ago"));
+        assert_eq!(last.fix, Some(DoctorFix::RunScan));
+
+        let text = render_text_with(&checks, false);
+        assert!(text.contains("\nSecurity\n  Audit log... OK (enabled ("));
+    }
+
+    #[test]
+    fn test_accessible_report() {
+        let checks = vec![
+            CheckResult::new(SYSTEM, "Config file", CheckStatus::Ok, "~/.config/webrana/config.toml"),
+            CheckResult::new(SYSTEM, "git", CheckStatus::Fail, "not found on PATH"),
+            CheckResult::new(SECURITY, "Audit log", CheckStatus::Warn, "disabled"),
+            CheckResult::new(SECURITY, "Shell sandbox", CheckStatus::Ok, ""),
+        ];
+        assert_eq!(
+            render_text_with(&checks, true),
+            "\nSystem\n  ok: Config file, ~/.config/webrana/config.toml\n  failed: git, not found on PATH\n\
+             \nSecurity\n  warning: Audit log, disabled\n  ok: Shell sandbox\n"
+        );
+    }
+
+    #[test]
+    fn test_inspect_pem() {
+        let pem = std::fs::read(format!("{}/tests/fixtures/tls/ca.pem", env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let certs = inspect_pem(&pem).unwrap();
+        assert_eq!(certs.len(), 1);
+        assert_eq!(certs[0].subject, "CN=Webrana Test CA, O=Webrana Tests");
+        assert!(utc_date(certs[0].not_after).starts_with("2126-"));
+        assert!(inspect_pem(b"not a certificate").is_err());
+    }
+
+    #[test]
+    fn test_tls_checks() {
+        let fixture = |name: &str| Some(format!("{}/tests/fixtures/tls/{}", env!("CARGO_MANIFEST_DIR"), name));
+
+        let checks = tls_checks(&HttpSettings::default(), NOW);
+        assert_eq!(checks.len(), 1);
+        assert_eq!(find(&checks, "HTTP TLS").detail, "system trust store");
+
+        let mut http = HttpSettings {
+            ca_bundle: fixture("ca.pem"),
+            client_cert: fixture("client.pem"),
+            client_key: fixture("client.key"),
+            ..Default::default()
+        };
+        let checks = tls_checks(&http, NOW);
+        let bundle = find(&checks, "HTTP CA bundle");
+        assert_eq!(bundle.status, CheckStatus::Ok);
+        assert!(bundle.detail.starts_with("CN=Webrana Test CA, O=Webrana Tests (expires 2126-"), "{}", bundle.detail);
+        assert!(find(&checks, "HTTP client certificate").detail.contains("CN=webrana-test-client"));
+        assert_eq!(find(&checks, "HTTP TLS").detail, "system trust store + CA bundle, client certificate");
+
+        // Long after the fixtures expire
+        let checks = tls_checks(&http, 5_000_000_000);
+        assert_eq!(find(&checks, "HTTP CA bundle").status, CheckStatus::Fail);
+
+        http.client_key = fixture("client-encrypted.key");
+        http.ca_bundle = fixture("missing.pem");
+        let checks = tls_checks(&http, NOW);
+        assert_eq!(find(&checks, "HTTP CA bundle").status, CheckStatus::Fail);
+        assert_eq!(find(&checks, "HTTP TLS").status, CheckStatus::Fail);
+    }
+}
@@ -0,0 +1,327 @@
+// ============================================
+// WEBRANA CLI - Lifecycle Hooks
+// Runs project commands from .webrana/hooks.toml
+// ============================================
+
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::safety::{CommandRisk, InputSanitizer};
+use crate::config::Settings;
+use crate::skills::{shell_join, ExecuteCommandSkill};
+use crate::util::sha256_hex;
+
+/// Location of the hooks file, relative to the project root
+pub const HOOKS_FILE: &str = ".webrana/hooks.toml";
+
+/// Hooks files the user agreed to run: canonical project path -> SHA-256 of
+/// the file they reviewed
+fn trust_store() -> Option<PathBuf> {
+    directories::ProjectDirs::from("dev", "webrana", "webrana-cli")
+        .map(|dirs| dirs.data_dir().join("hooks-trust.json"))
+}
+
+fn read_trust(store: &Path) -> BTreeMap<String, String> {
+    fs::read_to_string(store)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn project_key(root: &Path) -> String {
+    root.canonicalize()
+        .unwrap_or_else(|_| root.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Lifecycle points at which hooks run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    /// Before an autonomous run starts
+    PreRun,
+    /// After a file is written or edited
+    PostEdit,
+    /// After an autonomous run finishes
+    PostRun,
+}
+
+impl HookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::PreRun => "pre_run",
+            HookEvent::PostEdit => "post_edit",
+            HookEvent::PostRun => "post_run",
+        }
+    }
+}
+
+/// Contents of `.webrana/hooks.toml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HooksConfig {
+    #[serde(default)]
+    pub pre_run: Vec<String>,
+    #[serde(default)]
+    pub post_edit: Vec<String>,
+    #[serde(default)]
+    pub post_run: Vec<String>,
+}
+
+impl HooksConfig {
+    pub fn commands(&self, event: HookEvent) -> &[String] {
+        match event {
+            HookEvent::PreRun => &self.pre_run,
+            HookEvent::PostEdit => &self.post_edit,
+            HookEvent::PostRun => &self.post_run,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pre_run.is_empty() && self.post_edit.is_empty() && self.post_run.is_empty()
+    }
+}
+
+/// Outcome of a single hook command
+#[derive(Debug, Clone)]
+pub struct HookResult {
+    pub command: String,
+    pub success: bool,
+    pub output: String,
+}
+
+/// Runs configured hook commands with the changed paths as arguments,
+/// through the shell skill's checks, sandbox and timeout
+#[derive(Debug, Clone)]
+pub struct HookRunner {
+    config: HooksConfig,
+    root: PathBuf,
+    shell_access: bool,
+    /// SHA-256 of the hooks file; None for hooks configured in code
+    digest: Option<String>,
+    trust_store: Option<PathBuf>,
+    /// Hooks from the project's file only run once the user trusts them
+    trusted: bool,
+}
+
+impl HookRunner {
+    pub fn new(config: HooksConfig, root: impl Into<PathBuf>) -> Self {
+        Self {
+            config,
+            root: root.into(),
+            shell_access: true,
+            digest: None,
+            trust_store: None,
+            trusted: true,
+        }
+    }
+
+    /// Load hooks from `<root>/.webrana/hooks.toml` (empty if the file is missing)
+    pub fn load(root: &Path) -> Result<Self> {
+        Self::load_with_store(root, trust_store())
+    }
+
+    /// `load`, checking trust against `store`
+    pub fn load_with_store(root: &Path, store: Option<PathBuf>) -> Result<Self> {
+        let path = root.join(HOOKS_FILE);
+        if !path.exists() {
+            return Ok(Self::new(HooksConfig::default(), root));
+        }
+        let content = fs::read_to_string(&path)?;
+        let config: HooksConfig = toml::from_str(&content)
+            .with_context(|| format!("Invalid hooks file: {}", path.display()))?;
+        let digest = sha256_hex(&content);
+        let trusted = config.is_empty()
+            || store
+                .as_deref()
+                .and_then(|store| read_trust(store).remove(&project_key(root)))
+                .is_some_and(|trusted| trusted == digest);
+        Ok(Self {
+            digest: Some(digest),
+            trust_store: store,
+            trusted,
+            ..Self::new(config, root)
+        })
+    }
+
+    /// The project's hooks file is new or changed since the user last
+    /// agreed to run it
+    pub fn needs_trust(&self) -> bool {
+        !self.trusted
+    }
+
+    /// Record that the user trusts this project's hooks file as it is now
+    pub fn trust(&mut self) -> Result<()> {
+        if let (Some(store), Some(digest)) = (&self.trust_store, &self.digest) {
+            let mut trusted = read_trust(store);
+            trusted.insert(project_key(&self.root), digest.clone());
+            if let Some(parent) = store.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(store, serde_json::to_string_pretty(&trusted)?)?;
+        }
+        self.trusted = true;
+        Ok(())
+    }
+
+    /// The configured commands, one `event: command` per line
+    pub fn describe(&self) -> String {
+        [HookEvent::PreRun, HookEvent::PostEdit, HookEvent::PostRun]
+            .iter()
+            .flat_map(|event| {
+                self.config
+                    .commands(*event)
+                    .iter()
+                    .map(move |command| format!("  {}: {}", event.as_str(), command))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Disable all hooks when the active crew has no shell access
+    pub fn with_shell_access(mut self, allowed: bool) -> Self {
+        self.shell_access = allowed;
+        self
+    }
+
+    pub fn config(&self) -> &HooksConfig {
+        &self.config
+    }
+
+    /// Run every hook registered for `event`
+    pub async fn run(&self, event: HookEvent, paths: &[String], settings: &Settings) -> Vec<HookResult> {
+        if !self.shell_access || !self.trusted {
+            return Vec::new();
+        }
+
+        let sanitizer = InputSanitizer::with_default();
+        let mut results = Vec::new();
+        for command in self.config.commands(event) {
+            let result = match sanitizer.validate_command(command) {
+                Err(e) => Self::refused(command, &e.to_string()),
+                Ok(risk @ (CommandRisk::High(_) | CommandRisk::Blocked(_))) => {
+                    Self::refused(command, &risk.description())
+                }
+                Ok(_) => self.run_command(command, paths, settings).await,
+            };
+            results.push(result);
+        }
+        results
+    }
+
+    fn refused(command: &str, reason: &str) -> HookResult {
+        HookResult {
+            command: command.to_string(),
+            success: false,
+            output: format!("Hook refused by safety check: {}", reason),
+        }
+    }
+
+    async fn run_command(&self, command: &str, paths: &[String], settings: &Settings) -> HookResult {
+        // Paths are quoted so the shell never re-parses them
+        let full = if paths.is_empty() {
+            command.to_string()
+        } else {
+            format!("{} {}", command, shell_join(paths))
+        };
+        let root = self.root.to_string_lossy();
+        match ExecuteCommandSkill::new().run(&full, Some(&root), false, settings).await {
+            Ok(run) => HookResult {
+                command: command.to_string(),
+                success: run.success,
+                output: run.output.trim().to_string(),
+            },
+            Err(e) => HookResult {
+                command: command.to_string(),
+                success: false,
+                output: format!("Failed to run hook: {}", e),
+            },
+        }
+    }
+}
+
+/// Summarize failed hooks for the agent, or None if all succeeded
+pub fn failure_report(event: HookEvent, results: &[HookResult]) -> Option<String> {
+    let failures: Vec<String> = results
+        .iter()
+        .filter(|r| !r.success)
+        .map(|r| format!("`{}` failed:\n{}", r.command, r.output))
+        .collect();
+
+    if failures.is_empty() {
+        None
+    } else {
+        Some(format!("[{} hook]\n{}", event.as_str(), failures.join("\n")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_hooks_file() {
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join(".webrana")).unwrap();
+        fs::write(
+            tmp.path().join(HOOKS_FILE),
+            "post_edit = [\"rustfmt\"]\npost_run = [\"cargo test\"]\n",
+        )
+        .unwrap();
+
+        let runner = HookRunner::load(tmp.path()).unwrap();
+        assert_eq!(runner.config().post_edit, vec!["rustfmt"]);
+        assert!(runner.config().pre_run.is_empty());
+
+        let empty = HookRunner::load(&tmp.path().join("missing")).unwrap();
+        assert!(empty.config().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_risky_hooks_refused() {
+        let tmp = TempDir::new().unwrap();
+        let settings = Settings::default();
+        let config = HooksConfig {
+            pre_run: vec!["sudo make install".to_string()],
+            ..Default::default()
+        };
+
+        let results = HookRunner::new(config.clone(), tmp.path())
+            .run(HookEvent::PreRun, &[], &settings)
+            .await;
+        assert_eq!(results.len(), 1);
+        assert!(!results[0].success);
+        assert!(failure_report(HookEvent::PreRun, &results).unwrap().contains("pre_run"));
+
+        let no_shell = HookRunner::new(config, tmp.path()).with_shell_access(false);
+        assert!(no_shell.run(HookEvent::PreRun, &[], &settings).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_project_hooks_run_once_trusted() {
+        let tmp = TempDir::new().unwrap();
+        let store = tmp.path().join("data").join("hooks-trust.json");
+        fs::create_dir_all(tmp.path().join(".webrana")).unwrap();
+        fs::write(tmp.path().join(HOOKS_FILE), "post_edit = [\"sudo rm -rf /\"]\n").unwrap();
+
+        let mut runner = HookRunner::load_with_store(tmp.path(), Some(store.clone())).unwrap();
+        assert!(runner.needs_trust());
+        assert!(runner.describe().contains("post_edit: sudo rm -rf /"));
+        // Nothing runs, not even the safety check
+        let settings = Settings::default();
+        assert!(runner.run(HookEvent::PostEdit, &[], &settings).await.is_empty());
+
+        runner.trust().unwrap();
+        assert!(!runner.needs_trust());
+        let reloaded = HookRunner::load_with_store(tmp.path(), Some(store.clone())).unwrap();
+        assert!(!reloaded.needs_trust());
+        assert_eq!(reloaded.run(HookEvent::PostEdit, &[], &settings).await.len(), 1);
+
+        // A changed file is asked about again
+        fs::write(tmp.path().join(HOOKS_FILE), "post_edit = [\"make lint\"]\n").unwrap();
+        assert!(HookRunner::load_with_store(tmp.path(), Some(store)).unwrap().needs_trust());
+    }
+}
@@ -0,0 +1,89 @@
+// ============================================
+// WEBRANA CLI - Log Verbosity
+// --quiet / --verbose mapped onto tracing and console output
+// ============================================
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Output level selected by the global `--quiet` / `--verbose` flags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Warnings and errors only
+    Quiet,
+    #[default]
+    Normal,
+    /// Debug logs too
+    Verbose,
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+impl Verbosity {
+    pub fn from_flags(verbose: bool, quiet: bool) -> Self {
+        match (verbose, quiet) {
+            (_, true) => Verbosity::Quiet,
+            (true, false) => Verbosity::Verbose,
+            (false, false) => Verbosity::Normal,
+        }
+    }
+
+    /// `EnvFilter` directive for this level
+    pub fn directive(&self) -> &'static str {
+        match self {
+            Verbosity::Quiet => "webrana=warn",
+            Verbosity::Normal => "webrana=info",
+            Verbosity::Verbose => "webrana=debug",
+        }
+    }
+
+    /// Process-wide level, read by the console
+    pub fn current() -> Self {
+        match CURRENT.load(Ordering::Relaxed) {
+            0 => Verbosity::Quiet,
+            2 => Verbosity::Verbose,
+            _ => Verbosity::Normal,
+        }
+    }
+
+    pub fn set_current(self) {
+        CURRENT.store(self as u8, Ordering::Relaxed);
+    }
+}
+
+/// Log filter directive: an explicit, non-empty `RUST_LOG` wins over the flags
+pub fn filter_directive(verbosity: Verbosity, rust_log: Option<&str>) -> String {
+    match rust_log.map(str::trim) {
+        Some(env) if !env.is_empty() => env.to_string(),
+        _ => verbosity.directive().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_directive() {
+        let cases = [
+            (false, false, None, "webrana=info"),
+            (true, false, None, "webrana=debug"),
+            (false, true, None, "webrana=warn"),
+            // --quiet wins if both somehow get through
+            (true, true, None, "webrana=warn"),
+            (true, false, Some("webrana=trace"), "webrana=trace"),
+            (false, true, Some("debug"), "debug"),
+            (true, false, Some("  "), "webrana=debug"),
+        ];
+        for (verbose, quiet, rust_log, expected) in cases {
+            let verbosity = Verbosity::from_flags(verbose, quiet);
+            assert_eq!(
+                filter_directive(verbosity, rust_log),
+                expected,
+                "verbose={} quiet={} RUST_LOG={:?}",
+                verbose,
+                quiet,
+                rust_log
+            );
+        }
+    }
+}
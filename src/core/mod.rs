@@ -1,22 +1,59 @@
+pub mod activity;
 mod agent;
 pub mod audit;
+pub mod binaries;
+pub mod chat_script;
+pub mod digest;
+pub mod dirty_tree;
+pub mod doctor;
+pub mod hooks;
+pub mod logging;
 pub mod metrics;
 mod orchestrator;
+pub mod prompt_vars;
 pub mod rate_limit;
 mod safety;
+pub mod scan_checkpoint;
+pub mod scrub;
+pub mod secret_history;
 pub mod secrets;
+pub mod setup;
+pub mod stuck;
+pub mod task_brief;
+pub mod telemetry;
+pub mod undo;
 pub mod updater;
+pub mod warm_start;
 
 #[allow(unused_imports)]
 pub use agent::Agent;
 #[allow(unused_imports)]
+pub use chat_script::{ChatScript, Expectation, ScriptTurn, UnmetExpectation};
+#[allow(unused_imports)]
 pub use audit::{AuditConfig, AuditEvent, AuditEventType, AuditLogger, AuditSeverity, AUDIT};
 #[allow(unused_imports)]
+pub use hooks::{HookEvent, HookResult, HookRunner, HooksConfig};
+#[allow(unused_imports)]
+pub use logging::Verbosity;
+#[allow(unused_imports)]
 pub use metrics::{Metrics, MetricsSummary, TimingStats, METRICS};
-pub use orchestrator::Orchestrator;
+pub use orchestrator::{saved_context_report, strip_persona_prefix, write_print_response, Orchestrator};
+#[allow(unused_imports)]
+pub use prompt_vars::{PromptVariables, PROMPT_VARIABLES};
+#[allow(unused_imports)]
+pub use rate_limit::{
+    shared_limiter, Priority, RateLimitConfig, RateLimiter, SharedLimitConfig, SharedRateLimiter,
+    API_LIMITER, CMD_LIMITER, FILE_LIMITER, LLM_LIMITER,
+};
 #[allow(unused_imports)]
-pub use rate_limit::{RateLimitConfig, RateLimiter, API_LIMITER, CMD_LIMITER, FILE_LIMITER, LLM_LIMITER};
+pub use safety::{
+    glob_match, resolve_existing, CommandRisk, ConfirmationPrompt, InputSanitizer, SecurityConfig,
+    StepAborted, StepDecision, Stepper,
+};
 #[allow(unused_imports)]
-pub use safety::{CommandRisk, ConfirmationPrompt, InputSanitizer, SecurityConfig};
+pub use secrets::{
+    scan_fails, DetectedSecret, ScanStamp, ScanSummary, ScannerConfig, SecretScanner, SecretSeverity,
+    SecretType,
+};
 #[allow(unused_imports)]
-pub use secrets::{DetectedSecret, ScanSummary, ScannerConfig, SecretScanner, SecretSeverity, SecretType};
+pub use secret_history::{scan_git_history, HistoryFinding, HistoryScan};
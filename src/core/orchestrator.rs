@@ -1,13 +1,132 @@
 use anyhow::Result;
 use colored::Colorize;
+use std::collections::HashSet;
 use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::Instrument;
 
-use crate::config::Settings;
+use super::activity::{self, Activity, ActivityLog};
+use super::chat_script::{ChatScript, UnmetExpectation};
+use super::dirty_tree::{self, DirtyTreeAction, RepoCheck};
+use super::hooks::{self, HookEvent, HookRunner};
+use super::metrics::METRICS;
+use super::safety::{ConfirmationPrompt, StepAborted, Stepper};
+use super::scrub::scrub_tool_output;
+use super::setup;
+use super::prompt_vars::PromptVariables;
+use super::stuck::{StuckDetector, StuckVerdict, CORRECTIVE_MESSAGE};
+use super::task_brief::{self, CriterionVerdict};
+use super::undo::{ChangeJournal, JournalKind};
+use super::warm_start;
+use super::Verbosity;
+use crate::config::{SandboxFallback, Settings};
 use crate::crew::{Crew, CrewManager};
-use crate::llm::{LlmClient, Message};
-use crate::memory::Context;
-use crate::skills::SkillRegistry;
-use crate::ui::Console;
+use crate::llm::events::{self, StreamEvent};
+use crate::llm::{
+    pricing, CitedAnswer, LlmClient, Message, RagContext, Refused, ResponseProvenance, RetrievedChunk, ToolChoice,
+    LIST_MORE_TOOLS, REFUSALS_METRIC,
+};
+use crate::memory::{
+    default_index_path, prompt_section, recall_block, render_html, render_transcript, utc_date,
+    AutoCompact, CompactionRecord, Context, ContextBudget, ContextConfig, ConversationMemory, Demand, ExportMeta,
+    HtmlOptions, LlmSummarizer, Recollection, SessionInfo, DEFAULT_BUDGET_CHARS, DEFAULT_KEEP_LAST,
+    SUMMARY_HEADER,
+};
+use crate::scaffold::WorkdirBoundary;
+use crate::skills::{
+    edit_guidance, inspect_environment, project_index_path, new_run_id, run_dir, runtime_available, runtime_binary, Confirmer,
+    Skill, SkillPermissions, SkillRegistry, TaskList,
+};
+use crate::mcp::McpRegistry;
+use crate::plugins::{plugin_skills, PluginManager};
+use crate::ui::header::abbreviate_home;
+use crate::ui::plain::{self, Mode};
+use crate::ui::{Console, SessionHeader, TemplateValues};
+use crate::util::unix_now;
+
+/// Rough cap on the open-task reminder added to each iteration's prompt
+const TASK_SUMMARY_BUDGET: usize = 1_500;
+
+/// Past-session entries considered for each recall
+const RECALL_TOP_K: usize = 5;
+
+/// Told to the model when every tracked task is done but it has not said
+/// TASK_COMPLETE
+const TASKS_DONE_CHECK: &str = "Every task in your task list is done. If the whole task is finished, \
+respond with TASK_COMPLETE; otherwise add what remains with task_add and continue.";
+
+/// Sent once after the model declines an autonomous run's request
+const REFUSAL_ADJUSTMENT: &str = "Your last response declined the request. If part of the task \
+can be done within your usage policy, continue with that part and say what you are leaving out; \
+otherwise explain briefly why the task cannot be done.";
+
+/// Conversation memory and what this session has already been reminded of
+struct Recall {
+    memory: ConversationMemory,
+    offered: HashSet<String>,
+}
+
+/// Acceptance criteria of a `run --task-file` run and the latest verdicts
+struct Acceptance {
+    criteria: Vec<String>,
+    /// Also assess every this many iterations, finishing once all are met
+    every: Option<usize>,
+    verdicts: std::sync::Mutex<Vec<CriterionVerdict>>,
+}
+
+/// A finished `chat --script` run
+pub struct ScriptRun {
+    pub context: Context,
+    pub unmet: Vec<UnmetExpectation>,
+}
+
+/// How an autonomous run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Completed,
+    /// The model finished but not every acceptance criterion was met
+    PartiallyCompleted,
+    /// Stopped by the user under `--confirm-each`
+    Aborted,
+    MaxIterations,
+    /// The model kept repeating itself after being told to change approach
+    Stalled,
+    /// `run --deadline` passed
+    DeadlineExceeded,
+    /// The model declined the task on content-policy grounds, even after
+    /// being asked to adjust
+    Refused,
+}
+
+impl RunStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RunStatus::Completed => "completed",
+            RunStatus::PartiallyCompleted => "partially completed",
+            RunStatus::Aborted => "aborted",
+            RunStatus::MaxIterations => "maximum iterations reached",
+            RunStatus::Stalled => "stalled",
+            RunStatus::DeadlineExceeded => "deadline exceeded",
+            RunStatus::Refused => "refused by the model",
+        }
+    }
+
+    /// Name in the activity journal
+    pub fn key(&self) -> &'static str {
+        match self {
+            RunStatus::Completed => "completed",
+            RunStatus::PartiallyCompleted => "partially_completed",
+            RunStatus::Aborted => "aborted",
+            RunStatus::MaxIterations => "max_iterations",
+            RunStatus::Stalled => "stalled",
+            RunStatus::DeadlineExceeded => "deadline_exceeded",
+            RunStatus::Refused => "refused",
+        }
+    }
+}
 
 pub struct Orchestrator {
     settings: Settings,
@@ -17,25 +136,125 @@ pub struct Orchestrator {
     console: Console,
     auto_mode: bool,
     active_crew: Option<Crew>,
+    /// Retrieved code injected into `ask_cited` prompts
+    rag: Option<RagContext>,
+    /// WEBRANA.md section of the system prompt, loaded at session start
+    project_memory: Option<String>,
+    /// Toolchains and resources, inspected at session start
+    environment: String,
+    /// Past sessions, when `conversation_memory.enabled`
+    recall: Option<tokio::sync::Mutex<Recall>>,
+    /// Wall-clock limit of an autonomous run (`run --deadline`)
+    deadline: Option<Duration>,
+    /// `run --task-file` acceptance criteria
+    acceptance: Option<Acceptance>,
+    /// Startup summary; tool count and flags are filled in when shown
+    header: SessionHeader,
 }
 
 impl Orchestrator {
-    pub async fn new(settings: Settings, auto_mode: bool) -> Result<Self> {
+    pub async fn new(mut settings: Settings, auto_mode: bool) -> Result<Self> {
+        let console = Console::new();
+        if let Err(e) = setup::check_providers(&settings).await {
+            if !auto_mode && atty::is(atty::Stream::Stdin) {
+                offer_starter_config(&console);
+            }
+            return Err(e);
+        }
         let llm = LlmClient::new(&settings).await?;
         let context = Context::new();
-        let skills = SkillRegistry::new();
-        let console = Console::new();
 
         // Load active crew if any
-        let active_crew = CrewManager::new()
+        let mut active_crew = CrewManager::new()
             .ok()
             .and_then(|manager| manager.active().cloned());
 
+        let variables = PromptVariables::detect(&std::env::current_dir()?).await;
+        resolve_prompt_variables(&mut settings, active_crew.as_mut(), &variables)?;
+
+        // Startup check for the shell sandbox runtime
+        if let Some(runtime) = runtime_binary(settings.shell.sandbox) {
+            if !runtime_available(settings.shell.sandbox) {
+                match settings.shell.fallback {
+                    SandboxFallback::Deny => console.warn(&format!(
+                        "Sandbox runtime '{}' not found: commands above low risk will be refused",
+                        runtime
+                    )),
+                    SandboxFallback::Host => console.warn(&format!(
+                        "Sandbox runtime '{}' not found: commands will run on the host",
+                        runtime
+                    )),
+                }
+            }
+        }
+
+        // Project hooks only run when the crew may use the shell
+        let shell_access = active_crew
+            .as_ref()
+            .map(|crew| crew.permissions.shell_access)
+            .unwrap_or(true);
+        let mut hooks = HookRunner::load(&std::env::current_dir()?)?.with_shell_access(shell_access);
+        // A repo's hooks file runs commands on every edit: ask once per
+        // version of it before they run
+        if hooks.needs_trust() {
+            if !auto_mode && atty::is(atty::Stream::Stdin) {
+                console.warn(&format!("{} wants to run these commands:\n{}", hooks::HOOKS_FILE, hooks.describe()));
+                if ConfirmationPrompt::confirm("Trust these hooks for this project?") {
+                    if let Err(e) = hooks.trust() {
+                        console.warn(&format!("Could not record the trusted hooks: {}", e));
+                    }
+                }
+            }
+            if hooks.needs_trust() {
+                console.warn(&format!(
+                    "Hooks in {} are not trusted and will not run; start webrana interactively to review them",
+                    hooks::HOOKS_FILE
+                ));
+            }
+        }
+        let mut skills = SkillRegistry::new().with_hooks(hooks);
+        let plugin_manager = PluginManager::default_manager().ok();
+        let crew_permissions = active_crew.as_ref().map(|crew| &crew.permissions);
+        for skill in plugin_manager
+            .iter()
+            .flat_map(|manager| plugin_skills(manager, crew_permissions))
+        {
+            // Same rule as `skills export`: built-in names win
+            let name = skill.definition().name;
+            if skills.get(&name).is_some() {
+                tracing::warn!("Skipping plugin tool '{}': the name is already taken", name);
+                continue;
+            }
+            skills.register(Box::new(skill));
+        }
         if let Some(ref crew) = active_crew {
-            console.info(&format!("Active crew: {}", crew.name));
+            skills.restrict_to_crew(crew);
+        }
+        // Permission requests need someone to answer them
+        if !auto_mode && atty::is(atty::Stream::Stdin) {
+            let confirmer: Confirmer = Arc::new(ConfirmationPrompt::confirm);
+            skills.set_confirmer(Some(confirmer));
         }
 
-        Ok(Self {
+        let recall = if settings.conversation_memory.enabled {
+            open_recall(&console, &settings)
+        } else {
+            None
+        };
+
+        let cwd = std::env::current_dir()?;
+        let header = SessionHeader {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            models: llm.model_chain(),
+            crew: active_crew.as_ref().map(|crew| crew.name.clone()),
+            directory: abbreviate_home(&cwd, dirs::home_dir().as_deref()),
+            mcp_servers: enabled_mcp_servers(),
+            plugins: plugin_manager.map_or(0, |manager| manager.list_enabled().len()),
+            semantic_index: project_index_path(&cwd).is_some_and(|path| path.is_file()),
+            ..Default::default()
+        };
+
+        let mut orchestrator = Self {
             settings,
             llm,
             context,
@@ -43,12 +262,112 @@ impl Orchestrator {
             console,
             auto_mode,
             active_crew,
-        })
+            rag: None,
+            project_memory: None,
+            environment: inspect_environment(&std::env::current_dir()?).await.summary(),
+            recall,
+            deadline: None,
+            acceptance: None,
+            header,
+        };
+        // Leave out what the crew or agent prompt already says
+        let (_, base_prompt) = orchestrator.get_system_prompt();
+        orchestrator.project_memory =
+            prompt_section(&[std::env::current_dir()?], &base_prompt, DEFAULT_BUDGET_CHARS);
+        Ok(orchestrator)
+    }
+
+    /// The session header as of now: tools left after `--allow`/`--deny`,
+    /// auto and read-only flags
+    pub fn session_header(&self) -> SessionHeader {
+        SessionHeader {
+            auto: self.auto_mode,
+            read_only: self.settings.shell.read_only,
+            tools: self.skills.list().len(),
+            ..self.header.clone()
+        }
+    }
+
+    /// Layer per-run `--allow`/`--deny` lists over the crew's skill permissions
+    pub fn with_skill_overrides(mut self, allow: &[String], deny: &[String]) -> Result<Self> {
+        for name in allow.iter().chain(deny) {
+            if self.skills.get(name).is_none() {
+                anyhow::bail!("Unknown skill '{}'", name);
+            }
+        }
+        self.skills
+            .restrict(&SkillPermissions::new(allow.iter().cloned(), deny.iter().cloned()));
+        Ok(self)
+    }
+
+    /// Restrict file writes to paths matching `allow` and not `deny` (globs)
+    pub fn with_write_paths(mut self, allow: Vec<String>, deny: Vec<String>) -> Self {
+        self.skills.set_write_paths(allow, deny);
+        self
+    }
+
+    /// Inject chunks retrieved from `rag` into `ask_cited` prompts
+    pub fn with_rag(mut self, rag: RagContext) -> Self {
+        self.rag = Some(rag);
+        self
+    }
+
+    /// Ask `stepper` before every tool call (`run --confirm-each`)
+    pub fn with_stepper(mut self, stepper: Stepper) -> Self {
+        self.skills.set_stepper(Some(stepper));
+        self
+    }
+
+    /// Stop autonomous runs after `deadline` of wall-clock time
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Have the model check `criteria` when it finishes, and every `every`
+    /// iterations if set
+    pub fn with_acceptance_criteria(mut self, criteria: Vec<String>, every: Option<usize>) -> Self {
+        if !criteria.is_empty() {
+            self.acceptance = Some(Acceptance { criteria, every, verdicts: Default::default() });
+        }
+        self
+    }
+
+    /// Steer the model's tool use (`run --tool-choice`)
+    pub fn with_tool_choice(mut self, choice: ToolChoice) -> Result<Self> {
+        if let ToolChoice::Tool(name) = &choice {
+            if self.skills.get(name).is_none() {
+                anyhow::bail!("Unknown skill '{}' for --tool-choice", name);
+            }
+        }
+        self.llm = self.llm.with_tool_choice(choice);
+        Ok(self)
+    }
+
+    /// Confine file writes to `boundary` for the rest of this session
+    pub fn with_boundary(mut self, boundary: WorkdirBoundary) -> Self {
+        self.skills.set_boundary(boundary);
+        self
+    }
+
+    /// System prompt for turns that may call tools, with the edit-format
+    /// guidance for the active model
+    fn tool_system_prompt(&self) -> (String, String) {
+        let (name, mut prompt) = self.get_system_prompt();
+        if self.skills.is_allowed("apply_patch") && self.skills.is_allowed("edit_file") {
+            prompt.push_str("\n\n");
+            prompt.push_str(edit_guidance(self.settings.edit_format()));
+        }
+        if !self.environment.is_empty() {
+            prompt.push_str("\n\n");
+            prompt.push_str(&self.environment);
+        }
+        (name, prompt)
     }
 
     /// Get the effective system prompt (crew or default agent)
     fn get_system_prompt(&self) -> (String, String) {
-        if let Some(ref crew) = self.active_crew {
+        let (name, mut prompt) = if let Some(ref crew) = self.active_crew {
             (crew.name.clone(), crew.effective_system_prompt())
         } else {
             let agent = self
@@ -56,54 +375,130 @@ impl Orchestrator {
                 .get_agent(&self.settings.default_agent)
                 .expect("Default agent not found");
             (agent.name.clone(), agent.system_prompt.clone())
+        };
+        if let Some(project_memory) = &self.project_memory {
+            prompt.push_str("\n\n");
+            prompt.push_str(project_memory);
         }
+        (name, prompt)
     }
 
     pub async fn chat(&self, message: &str) -> Result<()> {
+        let repo = if self.auto_mode {
+            self.check_repo(&new_run_id()).await?
+        } else {
+            None
+        };
+
         self.console.user_message(message);
 
-        let (name, system_prompt) = self.get_system_prompt();
+        let (name, system_prompt) = self.tool_system_prompt();
+        let system_prompt = self.with_recall(system_prompt, message, &[]).await;
 
         println!(
             "\n{} {}",
             format!("[{}]", name).green().bold(),
-            "━".repeat(50).dimmed()
+            plain::rule("━", 50).dimmed()
         );
 
-        let response = self
-            .llm
-            .chat_with_tools(
-                &system_prompt,
-                self.context.get_messages(),
-                message,
-                &self.skills,
-            )
-            .await?;
+        let turn = async {
+            let response = self
+                .llm
+                .chat_with_tools(
+                    &system_prompt,
+                    self.context.get_messages(),
+                    message,
+                    &self.skills,
+                )
+                .await?;
+            self.print_provenance();
 
-        // Execute any tool calls
-        for tool_call in &response.tool_calls {
-            println!("\n{} {}", "[TOOL]".magenta(), tool_call.name.cyan());
+            // Execute any tool calls
+            for tool_call in &response.tool_calls {
+                println!("\n{} {}", "[TOOL]".magenta(), tool_call.name.cyan());
 
-            let result = self
-                .skills
-                .execute(&tool_call.name, &tool_call.arguments, &self.settings)
-                .await;
+                if tool_call.name == LIST_MORE_TOOLS {
+                    let loaded = self.llm.list_more_tools(&tool_call.arguments, &self.skills, message);
+                    println!("{}", loaded.dimmed());
+                    continue;
+                }
 
-            match result {
-                Ok(output) => println!("{}", output.dimmed()),
-                Err(e) => println!("{}", format!("Error: {}", e).red()),
+                let result = self
+                    .skills
+                    .execute(&tool_call.name, &tool_call.arguments, &self.settings)
+                    .await;
+
+                let keep_colors = self.settings.tool_output.keep_colors;
+                match result {
+                    Ok(output) => {
+                        let output = scrub_tool_output(&tool_call.name, &output, keep_colors);
+                        println!("{}", output.display.dimmed())
+                    }
+                    Err(e) => {
+                        let error = scrub_tool_output(&tool_call.name, &format!("Error: {}", e), keep_colors);
+                        println!("{}", error.display.red())
+                    }
+                }
             }
+
+            Ok(())
+        };
+
+        let result = turn
+            .instrument(tracing::info_span!("orchestrator.turn", turn = 1u64))
+            .await;
+        if let Some(repo) = &repo {
+            println!("\n{}", repo.report());
         }
+        result
+    }
+
+    /// One tool-using turn whose output goes out as `llm::events`: a
+    /// `start` event, then deltas and tool events as they happen. The
+    /// caller ends the stream with `events::finish`.
+    pub async fn chat_events(&self, message: &str) -> Result<()> {
+        let model = self.settings.get_model(&self.settings.default_model);
+        events::emit(&StreamEvent::Start {
+            model: model.map_or(self.settings.default_model.as_str(), |m| m.model.as_str()),
+            provider: model.map_or("", |m| m.provider.as_str()),
+        });
 
+        let (_name, system_prompt) = self.tool_system_prompt();
+        let system_prompt = self.with_recall(system_prompt, message, &[]).await;
+        let mut history = self.context.get_messages().to_vec();
+        self.llm
+            .chat_with_tools_loop(&system_prompt, &mut history, message, &self.skills)
+            .instrument(tracing::info_span!("orchestrator.turn", turn = 1u64))
+            .await?;
         Ok(())
     }
 
+    /// Recent git activity since the last session in this directory, unless
+    /// `ui.warm_start_commits` is 0 or this is not a repository
+    async fn warm_start(&self) -> Option<String> {
+        let commits = self.settings.ui.warm_start_commits;
+        if commits == 0 {
+            return None;
+        }
+        let dir = std::env::current_dir().ok()?;
+        let now = unix_now();
+        let last = ActivityLog::open_default()
+            .and_then(|log| warm_start::last_session(&log, &dir.display().to_string(), now));
+        let snapshot = warm_start::gather(&dir, warm_start::window_start(last, now), commits).await?;
+        Some(warm_start::summarize(&snapshot, now, last, warm_start::MAX_CHARS))
+    }
+
     pub async fn repl(&self) -> Result<()> {
         self.console
             .info("Starting interactive mode. Type 'exit' to quit.\n");
 
-        let (name, system_prompt) = self.get_system_prompt();
-        
+        let (name, mut system_prompt) = self.tool_system_prompt();
+        if let Some(block) = self.warm_start().await {
+            self.console.warm_start(&block);
+            system_prompt.push_str("\n\n");
+            system_prompt.push_str(&block);
+        }
+
         self.console.info(&format!(
             "Model: {} | {}: {}\n",
             self.settings.default_model.cyan(),
@@ -111,27 +506,104 @@ impl Orchestrator {
             name.cyan()
         ));
 
-        let mut history: Vec<Message> = Vec::new();
+        let mut context = Context::with_config(ContextConfig::unbounded());
+        let mut auto_compact = self.settings.auto_compact_at.map(AutoCompact::new);
+        let mut turn: u64 = 0;
+        let session_id = new_run_id();
+        if let Ok(root) = std::env::current_dir() {
+            self.skills.start_journal(ChangeJournal::new(&root, &session_id, JournalKind::Session));
+        }
 
         loop {
-            print!("\n{} ", "▶".cyan().bold());
+            print!("\n{} ", Mode::current().marker("▶", "You:").cyan().bold());
             io::stdout().flush()?;
 
             let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
-            let input = input.trim();
+            // End of input (Ctrl-D) ends the session like `exit`
+            let input = match io::stdin().read_line(&mut input)? {
+                0 => "exit",
+                _ => input.trim(),
+            };
 
             if input.is_empty() {
                 continue;
             }
 
+            if let Some(arg) = input.strip_prefix("/compact") {
+                let keep_last = match arg.trim() {
+                    "" => DEFAULT_KEEP_LAST,
+                    n => match n.parse() {
+                        Ok(n) => n,
+                        Err(_) => {
+                            self.console.error("Usage: /compact [keep_last_n]");
+                            continue;
+                        }
+                    },
+                };
+                if let Err(e) = self.compact_context(&mut context, keep_last, false).await {
+                    self.console.error(&format!("Compaction failed: {}", e));
+                }
+                continue;
+            }
+
+            if let Some(arg) = input.strip_prefix("/export") {
+                let mut words: Vec<&str> = arg.split_whitespace().collect();
+                let redact = words.contains(&"--redact");
+                words.retain(|w| *w != "--redact");
+                let [path] = words[..] else {
+                    self.console.error("Usage: /export <file.html> [--redact]");
+                    continue;
+                };
+                match self.export_html(&context, Path::new(path), redact) {
+                    Ok(()) => self.console.success(&format!("Conversation exported to {}", path)),
+                    Err(e) => self.console.error(&format!("Export failed: {}", e)),
+                }
+                continue;
+            }
+
+            if let Some(arg) = input.strip_prefix("/save ").or_else(|| input.strip_prefix("/load ")) {
+                let path = Path::new(arg.trim());
+                if input.starts_with("/save") {
+                    match context.save(path) {
+                        Ok(()) => self.console.success(&format!("Session saved to {}", path.display())),
+                        Err(e) => self.console.error(&format!("Save failed: {:#}", e)),
+                    }
+                } else {
+                    match context.load(path) {
+                        Ok(()) => self.console.success(&format!("Loaded {} messages", context.len())),
+                        Err(e) => self.console.error(&format!("Load failed: {:#}", e)),
+                    }
+                }
+                continue;
+            }
+
             match input.to_lowercase().as_str() {
+                "/grants" => {
+                    self.print_grants();
+                    continue;
+                }
+                "/context" => {
+                    self.print_context(&context);
+                    continue;
+                }
+                "/status" => {
+                    self.console.print_session_header(&self.session_header());
+                    continue;
+                }
                 "exit" | "quit" | "q" => {
+                    self.remember_session(&session_id, &context).await;
+                    self.finish_journal("--session");
+                    activity::record(Activity::Session {
+                        time: unix_now(),
+                        turns: turn,
+                        skills: self.llm.skill_uses(),
+                        project: std::env::current_dir().ok().map(|dir| dir.display().to_string()),
+                    });
                     self.console.info("Goodbye!");
                     break;
                 }
                 "clear" | "reset" => {
-                    history.clear();
+                    context.clear();
                     self.console.info("Context cleared.");
                     continue;
                 }
@@ -149,7 +621,11 @@ impl Orchestrator {
                 }
                 "history" => {
                     println!("\n{}", "Conversation History:".bold().underline());
-                    for (i, msg) in history.iter().enumerate() {
+                    for (i, msg) in context.get_messages().iter().enumerate() {
+                        if msg.content.starts_with(SUMMARY_HEADER) {
+                            let rule = plain::rule("─", 2);
+                            println!("  {}", format!("{} compacted {}", rule, rule).trim().dimmed());
+                        }
                         let role = match msg.role {
                             crate::llm::Role::User => "USER".blue(),
                             crate::llm::Role::Assistant => "ASSISTANT".green(),
@@ -158,6 +634,16 @@ impl Orchestrator {
                         let preview: String = msg.content.chars().take(100).collect();
                         println!("  {}. [{}] {}...", i + 1, role, preview);
                     }
+                    for record in context.compactions() {
+                        println!(
+                            "  {} {} messages summarized, ~{} → ~{} tokens{}",
+                            "compaction:".dimmed(),
+                            record.summarized,
+                            record.tokens_before,
+                            record.tokens_after,
+                            if record.automatic { " (auto)" } else { "" }
+                        );
+                    }
                     continue;
                 }
                 _ => {}
@@ -166,52 +652,408 @@ impl Orchestrator {
             println!(
                 "\n{} {}",
                 format!("[{}]", name).green().bold(),
-                "━".repeat(50).dimmed()
+                plain::rule("━", 50).dimmed()
             );
 
+            self.auto_compact(&mut auto_compact, &mut context).await;
+
+            // Kept in the history so later turns still see it
+            if let Some(block) = self.recall(input, &[]).await {
+                context.add_system_message(&block);
+            }
+
             // Use the tool loop for multi-turn tool usage
+            turn += 1;
+            let mut history = context.take_messages();
             match self
                 .llm
                 .chat_with_tools_loop(&system_prompt, &mut history, input, &self.skills)
+                .instrument(tracing::info_span!("orchestrator.turn", turn))
                 .await
             {
                 Ok(response) => {
+                    self.print_provenance();
                     // Response already streamed, just add to history
                     if !response.is_empty() {
-                        history.push(Message::assistant(&response));
+                        history.push(Message::assistant(&response).stamped());
                     }
                 }
+                Err(e) if e.is::<Refused>() => self.console.warn(&e.to_string()),
                 Err(e) => {
                     self.console.error(&format!("Error: {}", e));
                 }
             }
+            context.set_messages(history);
         }
 
         Ok(())
     }
 
+    /// Send each turn of `script` as if typed into the REPL, checking each
+    /// answer against the turn's expectations. With `interactive_between`
+    /// the user presses Enter before every turn after the first.
+    pub async fn run_script(&self, script: &ChatScript, interactive_between: bool) -> Result<ScriptRun> {
+        let (name, system_prompt) = self.tool_system_prompt();
+        let mut context = Context::with_config(ContextConfig::unbounded());
+        let mut auto_compact = self.settings.auto_compact_at.map(AutoCompact::new);
+        let mut unmet = Vec::new();
+        let total = script.turns.len();
+
+        for (i, turn) in script.turns.iter().enumerate() {
+            let number = i + 1;
+            if interactive_between && i > 0 {
+                print!("\n{} ", format!("Press Enter for turn {}/{} (q to stop)", number, total).dimmed());
+                io::stdout().flush()?;
+                let mut input = String::new();
+                io::stdin().read_line(&mut input)?;
+                if input.trim().eq_ignore_ascii_case("q") {
+                    self.console.info(&format!("Stopped before turn {}", number));
+                    break;
+                }
+            }
+
+            let rule = plain::rule("━", 2);
+            println!("\n{}", format!("{} Turn {}/{} {}", rule, number, total, rule).trim().cyan().bold());
+            self.console.user_message(&turn.message);
+            println!(
+                "\n{} {}",
+                format!("[{}]", name).green().bold(),
+                plain::rule("━", 50).dimmed()
+            );
+
+            self.auto_compact(&mut auto_compact, &mut context).await;
+            if let Some(block) = self.recall(&turn.message, &[]).await {
+                context.add_system_message(&block);
+            }
+
+            let mut history = context.take_messages();
+            let result = self
+                .llm
+                .chat_with_tools_loop(&system_prompt, &mut history, &turn.message, &self.skills)
+                .instrument(tracing::info_span!("orchestrator.turn", turn = number as u64))
+                .await;
+            if let Ok(response) = &result {
+                if !response.is_empty() {
+                    history.push(Message::assistant(response).stamped());
+                }
+            }
+            context.set_messages(history);
+
+            // A failed turn meets nothing, but the script goes on
+            let answer = match result {
+                Ok(response) => {
+                    self.print_provenance();
+                    response
+                }
+                Err(e) => {
+                    self.console.error(&format!("Error: {}", e));
+                    String::new()
+                }
+            };
+            for missed in turn.unmet(number, &answer, &self.llm.take_tool_calls()) {
+                self.console.warn(&format!("Expected: {}", missed));
+                unmet.push(missed);
+            }
+        }
+
+        Ok(ScriptRun { context, unmet })
+    }
+
+    /// Write `context` as HTML for `.html`/`.htm` paths, Markdown otherwise
+    pub fn save_transcript(&self, context: &Context, path: &Path) -> Result<()> {
+        let html = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm"));
+        if html {
+            return self.export_html(context, path, false);
+        }
+        let text = render_transcript(context.get_messages(), &self.export_meta(context), &export_options(false));
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
     pub async fn run_autonomous(
         &self,
         task: &str,
         max_iterations: usize,
         yolo: bool,
     ) -> Result<()> {
-        let (_name, system_prompt) = self.get_system_prompt();
+        // Let run_tests pick only the tests affected by files edited in this run
+        self.skills.clear_edited_files();
+        self.skills.set_auto_select_tests(true);
+
+        let run_id = new_run_id();
+        let repo = self.check_repo(&run_id).await?;
+
+        if let Some(report) = self.run_hooks(HookEvent::PreRun, &[]).await {
+            anyhow::bail!("{}", report);
+        }
+
+        let started = Instant::now();
+        let result = self.run_iterations(task, max_iterations, yolo, &run_id).await;
+
+        self.run_hooks(HookEvent::PostRun, &self.skills.edited_files()).await;
+        self.print_run_report(repo.as_ref(), result.as_ref().ok().copied());
+        activity::record(Activity::Run {
+            time: unix_now(),
+            task: task.to_string(),
+            status: result.as_ref().map_or("failed", RunStatus::key).to_string(),
+            duration_secs: started.elapsed().as_secs(),
+            skills: self.llm.skill_uses(),
+        });
+
+        result.map(|_| ())
+    }
+
+    /// Apply the `run.dirty_tree` policy before an autonomous run. Stashing
+    /// and branching ask first unless in auto mode.
+    async fn check_repo(&self, run_id: &str) -> Result<Option<RepoCheck>> {
+        let auto = self.auto_mode;
+        let check = dirty_tree::check_repo(
+            &std::env::current_dir()?,
+            self.settings.run.dirty_tree,
+            run_id,
+            &mut |question| auto || ConfirmationPrompt::confirm(question),
+        )
+        .await?;
+
+        if let Some(check) = &check {
+            match &check.action {
+                DirtyTreeAction::Clean => {}
+                DirtyTreeAction::Warned => self.console.warn(&check.dirty_warning()),
+                DirtyTreeAction::Stashed { message, .. } => self.console.info(&format!(
+                    "Stashed {} uncommitted paths as '{}'",
+                    check.dirty.len(),
+                    message
+                )),
+                DirtyTreeAction::Branched { branch } => {
+                    self.console.info(&format!("Switched to branch {}", branch))
+                }
+            }
+        }
+        Ok(check)
+    }
+
+    /// Summarize all but pinned messages and the last `keep_last` turns of
+    /// `context`. Call before large planned operations to free up the window.
+    pub async fn compact_context(
+        &self,
+        context: &mut Context,
+        keep_last: usize,
+        automatic: bool,
+    ) -> Result<Option<CompactionRecord>> {
+        let summarizer = LlmSummarizer::new(&self.llm);
+        let record = context.compact(keep_last, &summarizer, automatic).await?;
+        match &record {
+            Some(record) => self.console.info(&format!(
+                "Compacted {} messages: ~{} → ~{} tokens",
+                record.summarized, record.tokens_before, record.tokens_after
+            )),
+            None => self.console.info("Nothing to compact."),
+        }
+        Ok(record)
+    }
+
+    /// Compact once each time usage crosses the `auto_compact_at` threshold
+    async fn auto_compact(&self, auto: &mut Option<AutoCompact>, context: &mut Context) {
+        let Some(auto) = auto else { return };
+        if auto.check(context.estimated_tokens(), self.settings.context_window()) {
+            self.console.info(&format!(
+                "Context above {:.0}% of the model window, compacting...",
+                auto.threshold() * 100.0
+            ));
+            if let Err(e) = self.compact_context(context, DEFAULT_KEEP_LAST, true).await {
+                self.console.warn(&format!("Auto-compaction failed: {}", e));
+            }
+        }
+    }
+
+    /// Past sessions related to `message` that this session has not been
+    /// reminded of yet, as a budget-capped block. Entries repeating `code`
+    /// (chunks already in the prompt) are left out.
+    async fn recall(&self, message: &str, code: &[String]) -> Option<String> {
+        let mut recall = self.recall.as_ref()?.lock().await;
+        let config = &self.settings.conversation_memory;
+        let scope = (!config.global)
+            .then(|| std::env::current_dir().ok().map(|dir| dir.display().to_string()))
+            .flatten();
+        let found = match recall
+            .memory
+            .search(message, scope.as_deref(), RECALL_TOP_K, config.min_score)
+            .await
+        {
+            Ok(found) => found,
+            Err(e) => {
+                tracing::warn!("Conversation memory search failed: {}", e);
+                return None;
+            }
+        };
+        let fresh: Vec<Recollection> = found
+            .into_iter()
+            .filter(|memory| !recall.offered.contains(&memory.id))
+            .collect();
+        let (block, ids) = recall_block(&fresh, code, config.max_chars);
+        recall.offered.extend(ids);
+        (!block.is_empty()).then_some(block)
+    }
+
+    /// `system_prompt` followed by the `recall` block, if any
+    async fn with_recall(&self, system_prompt: String, message: &str, code: &[String]) -> String {
+        match self.recall(message, code).await {
+            Some(block) => format!("{}\n\n{}", system_prompt, block),
+            None => system_prompt,
+        }
+    }
+
+    /// Summarize the finished session into the conversation memory
+    async fn remember_session(&self, session_id: &str, context: &Context) {
+        let Some(recall) = &self.recall else { return };
+        let session = SessionInfo {
+            id: session_id.to_string(),
+            project: std::env::current_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+            ended: unix_now(),
+        };
+        let summarizer = LlmSummarizer::new(&self.llm);
+        let mut recall = recall.lock().await;
+        let stored = match recall.memory.remember(&session, context.get_messages(), &summarizer).await {
+            Ok(stored) => recall.memory.save().map(|_| stored),
+            Err(e) => Err(e),
+        };
+        match stored {
+            Ok(0) => {}
+            Ok(_) => self.console.info(&format!("Session {} saved to conversation memory", session_id)),
+            Err(e) => self.console.warn(&format!("Could not save this session to conversation memory: {}", e)),
+        }
+    }
 
-        let mut history: Vec<Message> = Vec::new();
+    /// Run project hooks for `event`, printing and returning any failures
+    async fn run_hooks(&self, event: HookEvent, paths: &[String]) -> Option<String> {
+        let runner = self.skills.hooks()?;
+        let report = hooks::failure_report(event, &runner.run(event, paths, &self.settings).await);
+        if let Some(ref report) = report {
+            self.console.error(report);
+        }
+        report
+    }
 
-        let enhanced_task = format!(
+    async fn run_iterations(
+        &self,
+        task: &str,
+        max_iterations: usize,
+        yolo: bool,
+        run_id: &str,
+    ) -> Result<RunStatus> {
+        let mut context = Context::with_config(ContextConfig::unbounded());
+        let root = std::env::current_dir()?;
+        self.skills.start_journal(ChangeJournal::new(&root, run_id, JournalKind::Run));
+        let status = self.iterate(task, max_iterations, yolo, run_id, &mut context).await;
+        self.finish_journal("--run");
+        Ok(self.check_acceptance(status?, &context).await)
+    }
+
+    /// Stop recording changed files and say how to undo them
+    fn finish_journal(&self, flag: &str) {
+        if let Some(manifest) = self.skills.finish_journal() {
+            self.console.info(&format!(
+                "{} file(s) changed; `webrana undo {} {}` reverts them",
+                manifest.files.len(),
+                flag,
+                manifest.id
+            ));
+        }
+    }
+
+    /// Judge the acceptance criteria against a run that ended with
+    /// `status`, downgrading a completed run that missed any
+    async fn check_acceptance(&self, status: RunStatus, context: &Context) -> RunStatus {
+        let Some(acceptance) = &self.acceptance else { return status };
+        if matches!(status, RunStatus::Aborted | RunStatus::DeadlineExceeded | RunStatus::Refused) {
+            return status;
+        }
+        // Already judged all met by the periodic check that ended the run
+        {
+            let verdicts = acceptance.verdicts.lock().unwrap();
+            if status == RunStatus::Completed && !verdicts.is_empty() && task_brief::all_met(&verdicts) {
+                return status;
+            }
+        }
+        match self.assess(acceptance, context).await {
+            Some(verdicts) => final_status(status, &verdicts),
+            None => status,
+        }
+    }
+
+    /// Ask the model for verdicts and keep them for the run report
+    async fn assess(&self, acceptance: &Acceptance, context: &Context) -> Option<Vec<CriterionVerdict>> {
+        println!("{}", "Checking acceptance criteria...".dimmed());
+        match task_brief::assess(&self.llm, context.get_messages(), &acceptance.criteria).await {
+            Ok(verdicts) => {
+                *acceptance.verdicts.lock().unwrap() = verdicts.clone();
+                Some(verdicts)
+            }
+            Err(e) => {
+                self.console.warn(&format!("Could not check the acceptance criteria: {:#}", e));
+                None
+            }
+        }
+    }
+
+    async fn iterate(
+        &self,
+        task: &str,
+        max_iterations: usize,
+        yolo: bool,
+        run_id: &str,
+        context: &mut Context,
+    ) -> Result<RunStatus> {
+        let (_name, system_prompt) = self.tool_system_prompt();
+        let mut stuck = StuckDetector::new(self.settings.run.stuck_after);
+
+        let mut auto_compact = self.settings.auto_compact_at.map(AutoCompact::new);
+
+        let tasks_enabled = self.skills.is_allowed("task_add");
+        self.skills
+            .tasks()
+            .start_run(&run_dir(&std::env::current_dir()?, run_id));
+
+        let mut enhanced_task = format!(
             "{}\n\nIMPORTANT: You are running in autonomous mode. \
             Work step by step until the task is FULLY complete. \
             After each action, evaluate progress and continue until done. \
             When finished, respond with 'TASK_COMPLETE' on a new line.",
             task
         );
+        if tasks_enabled {
+            enhanced_task.push_str(
+                " Record sub-tasks with task_add and keep their status current with task_update.",
+            );
+        }
 
         println!("\n{} {}", "[TASK]".yellow().bold(), task.white());
-        println!("{}", "━".repeat(60).dimmed());
+        println!("{}", plain::rule("━", 60).dimmed());
 
+        let started = Instant::now();
+        let mut deadline_warned = false;
+        let mut adjusted_after_refusal = false;
+        // Done count of the task list when the model was asked to confirm it
+        let mut tasks_checked: Option<usize> = None;
         for iteration in 1..=max_iterations {
+            if let Some(deadline) = self.deadline {
+                let elapsed = started.elapsed();
+                if elapsed >= deadline && iteration > 1 {
+                    let when = format!("after iteration {}", iteration - 1);
+                    print_run_summary(RunMark::Deadline, &deadline_summary(deadline, &when, elapsed));
+                    return Ok(RunStatus::DeadlineExceeded);
+                }
+                let left = deadline.saturating_sub(elapsed);
+                if !deadline_warned && left <= deadline_warning(deadline) {
+                    self.console.warn(&format!("{} left before the run's deadline", format_duration(left)));
+                    deadline_warned = true;
+                }
+            }
             println!(
                 "\n{} {}/{}",
                 "[ITERATION]".blue().bold(),
@@ -219,33 +1061,115 @@ impl Orchestrator {
                 max_iterations.to_string().dimmed()
             );
 
-            let prompt = if iteration == 1 {
-                enhanced_task.clone()
-            } else {
-                "Continue working on the task. If complete, respond with TASK_COMPLETE.".to_string()
-            };
+            let tasks = self.skills.tasks().snapshot();
+            if Verbosity::current() == Verbosity::Verbose && !tasks.tasks.is_empty() {
+                println!("{}", tasks.render().trim_end().dimmed());
+            }
+            let prompt = iteration_prompt(iteration, &enhanced_task, &tasks);
 
-            match self
+            self.auto_compact(&mut auto_compact, context).await;
+
+            let mut history = context.take_messages();
+            let turn = self
                 .llm
                 .chat_with_tools_loop(&system_prompt, &mut history, &prompt, &self.skills)
-                .await
-            {
+                .instrument(tracing::info_span!("orchestrator.iteration", iteration = iteration as u64));
+            // The deadline also cuts off a model request or tool call in flight
+            let result = match self.deadline {
+                Some(deadline) => {
+                    match tokio::time::timeout(deadline.saturating_sub(started.elapsed()), turn).await {
+                        Ok(result) => result,
+                        Err(_) => {
+                            print_run_summary(
+                                RunMark::Deadline,
+                                &deadline_summary(
+                                    deadline,
+                                    &format!("during iteration {}", iteration),
+                                    started.elapsed(),
+                                ),
+                            );
+                            return Ok(RunStatus::DeadlineExceeded);
+                        }
+                    }
+                }
+                None => turn.await,
+            };
+            if let Ok(response) = &result {
+                if !response.is_empty() {
+                    history.push(Message::assistant(response).stamped());
+                }
+            }
+            context.set_messages(history);
+
+            match result {
                 Ok(response) => {
-                    if !response.is_empty() {
-                        history.push(Message::assistant(&response));
-
-                        // Check for task completion
-                        if response.contains("TASK_COMPLETE") {
-                            println!("\n{}", "━".repeat(60).green());
-                            println!(
-                                "{} Task completed in {} iterations",
-                                "✓".green().bold(),
-                                iteration.to_string().cyan()
+                    // Check for task completion
+                    let tasks = self.skills.tasks().snapshot();
+                    if run_complete(&response, &tasks, tasks_checked) {
+                        print_run_summary(
+                            RunMark::Completed,
+                            &format!("Task completed in {} iterations", iteration.to_string().cyan()),
+                        );
+                        return Ok(RunStatus::Completed);
+                    }
+                    if tasks.all_done() {
+                        tasks_checked = Some(tasks.done_count());
+                        context.add_system_message(TASKS_DONE_CHECK);
+                    }
+
+                    match stuck.observe(&response, &self.llm.take_tool_calls()) {
+                        StuckVerdict::Progress => {}
+                        StuckVerdict::Correct => {
+                            self.console.warn("The model is repeating itself; asking it to change approach");
+                            context.add_system_message(CORRECTIVE_MESSAGE);
+                        }
+                        StuckVerdict::Stalled => {
+                            print_run_summary(
+                                RunMark::Warning,
+                                &format!(
+                                    "Run stalled at iteration {}: the model kept giving the same response",
+                                    iteration
+                                ),
                             );
-                            return Ok(());
+                            return Ok(RunStatus::Stalled);
+                        }
+                    }
+
+                    if let Some(acceptance) = self.acceptance.as_ref().filter(|a| {
+                        a.every.is_some_and(|every| every > 0 && iteration % every == 0) && iteration < max_iterations
+                    }) {
+                        if let Some(verdicts) = self.assess(acceptance, context).await {
+                            if task_brief::all_met(&verdicts) {
+                                print_run_summary(
+                                    RunMark::Completed,
+                                    &format!(
+                                        "All acceptance criteria met after {} iterations",
+                                        iteration.to_string().cyan()
+                                    ),
+                                );
+                                return Ok(RunStatus::Completed);
+                            }
+                            context.add_system_message(&unmet_reminder(&verdicts));
                         }
                     }
                 }
+                Err(e) if e.is::<StepAborted>() => {
+                    print_run_summary(RunMark::Warning, &format!("Run aborted at iteration {}", iteration));
+                    return Ok(RunStatus::Aborted);
+                }
+                // Retrying the same request gets the same answer, even with --yolo
+                Err(e) if e.is::<Refused>() => {
+                    self.console.warn(&e.to_string());
+                    if adjusted_after_refusal {
+                        print_run_summary(
+                            RunMark::Warning,
+                            &format!("Run stopped at iteration {}: the model declined the task", iteration),
+                        );
+                        return Ok(RunStatus::Refused);
+                    }
+                    context.add_system_message(REFUSAL_ADJUSTMENT);
+                    adjusted_after_refusal = true;
+                }
                 Err(e) => {
                     self.console
                         .error(&format!("Error in iteration {}: {}", iteration, e));
@@ -256,36 +1180,139 @@ impl Orchestrator {
             }
         }
 
-        println!("\n{}", "━".repeat(60).yellow());
-        println!(
-            "{} Reached maximum iterations ({})",
-            "⚠".yellow().bold(),
-            max_iterations
+        print_run_summary(
+            RunMark::Warning,
+            &format!("Reached maximum iterations ({})", max_iterations),
         );
 
+        Ok(RunStatus::MaxIterations)
+    }
+
+    /// Outcome, starting repository state and done vs remaining tasks of
+    /// the run
+    fn print_run_report(&self, repo: Option<&RepoCheck>, status: Option<RunStatus>) {
+        if let Some(status) = status {
+            println!("\n{} {}", "Status:".bold(), status.label());
+        }
+        let refusals = METRICS.get_counter(REFUSALS_METRIC);
+        if refusals > 0 {
+            println!("{} {}", "Refusals:".bold(), refusals);
+        }
+        if let Some(acceptance) = &self.acceptance {
+            let verdicts = acceptance.verdicts.lock().unwrap();
+            if !verdicts.is_empty() {
+                print!("\n{}", task_brief::render_verdicts(&verdicts));
+            }
+        }
+        if let Some(note) = self.llm.last_provenance().and_then(|p| p.seed_note()) {
+            println!("{} {}", "Reproducible:".bold(), note);
+        }
+        if let Some(repo) = repo {
+            println!("\n{}", repo.report());
+        }
+        let tasks = self.skills.tasks();
+        if let Some(report) = tasks.snapshot().report() {
+            println!("\n{}", report);
+            if let Some(path) = tasks.path().filter(|p| p.exists()) {
+                println!("{}", format!("Saved to {}", path.display()).dimmed());
+            }
+        }
+    }
+
+    /// Session-only exceptions granted through `request_permission`
+    fn print_grants(&self) {
+        let grants = self.skills.escalation().grants();
+        if grants.is_empty() {
+            self.console.info("No permission grants in this session.");
+            return;
+        }
+        println!("\n{}", "Permission grants (this session only):".bold().underline());
+        let bullet = Mode::current().marker("•", "-");
+        for grant in grants {
+            println!("  {} {}", bullet, grant.describe());
+        }
+    }
+
+    /// Provider and model behind the last answer
+    pub fn last_provenance(&self) -> Option<ResponseProvenance> {
+        self.llm.last_provenance()
+    }
+
+    /// Dim "— model via provider" line after an answer (`show_provenance`)
+    pub fn print_provenance(&self) {
+        if !self.settings.show_provenance {
+            return;
+        }
+        if let Some(provenance) = self.llm.last_provenance() {
+            println!("{}", provenance.suffix().dimmed());
+        }
+    }
+
+    /// Context usage and each message's size, marking the one that goes first
+    fn print_context(&self, context: &Context) {
+        print!("{}", context_report(context, self.settings.context_window(), Mode::current()));
+    }
+
+    /// Write `context` as a self-contained HTML page
+    fn export_html(&self, context: &Context, path: &Path, redact: bool) -> Result<()> {
+        let html = render_html(context.get_messages(), &self.export_meta(context), &export_options(redact));
+        std::fs::write(path, html)?;
         Ok(())
     }
 
+    fn export_meta(&self, context: &Context) -> ExportMeta {
+        let (provider, model) = match self.settings.get_model(&self.settings.default_model) {
+            Some(config) => (config.provider.as_str(), config.model.clone()),
+            None => ("", self.settings.default_model.clone()),
+        };
+        // Same chars/4 estimate as the llm.request span
+        let (mut input_tokens, mut output_tokens) = (0, 0);
+        for message in context.get_messages() {
+            match message.role {
+                crate::llm::Role::Assistant => output_tokens += message.content.len() / 4,
+                _ => input_tokens += message.content.len() / 4,
+            }
+        }
+        let secs = unix_now();
+
+        ExportMeta {
+            date: utc_date(secs),
+            cost: pricing::estimate_cost(provider, &model, input_tokens, output_tokens),
+            model,
+            crew: self.active_crew.as_ref().map(|crew| crew.name.clone()),
+            tokens: input_tokens + output_tokens,
+        }
+    }
+
     fn print_help(&self) {
         println!("\n{}", "WEBRANA COMMANDS".bold().underline());
-        println!("{}", "─".repeat(40));
+        println!("{}", plain::rule("─", 40));
         println!("  {}  - Exit the REPL", "exit, quit, q".cyan());
         println!("  {}  - Clear conversation history", "clear, reset".cyan());
         println!("  {}      - List available skills", "skills".cyan());
         println!("  {}      - List available agents", "agents".cyan());
         println!("  {}     - Show conversation history", "history".cyan());
+        println!("  {} - Summarize all but the last N turns", "/compact [N]".cyan());
+        println!("  {}     - Show permissions granted to the crew", "/grants".cyan());
+        println!("  {}    - Show context size and what is trimmed next", "/context".cyan());
+        println!("  {}     - Show model, crew, directory and tools", "/status".cyan());
+        println!("  {} - Save the conversation as HTML", "/export <file> [--redact]".cyan());
+        println!("  {} - Save the session as JSON", "/save <file>".cyan());
+        println!("  {} - Continue a session saved with /save", "/load <file>".cyan());
         println!("  {}    - Show this help", "help, ?".cyan());
         println!();
         println!("{}", "TIPS".bold().underline());
-        println!("  • Just type your request and press Enter");
-        println!("  • The agent can read/write files, run commands");
-        println!("  • Use Ctrl+C to interrupt streaming");
+        let bullet = Mode::current().marker("•", "-");
+        println!("  {} Just type your request and press Enter", bullet);
+        println!("  {} The agent can read/write files, run commands", bullet);
+        println!("  {} Use Ctrl+C to interrupt streaming", bullet);
         println!();
     }
 
     /// Simple ask method that returns response as string (for pipe/print mode)
     pub async fn ask_simple(&self, message: &str) -> Result<String> {
         let (_name, system_prompt) = self.get_system_prompt();
+        let system_prompt = self.with_recall(system_prompt, message, &[]).await;
         
         let response = self
             .llm
@@ -294,4 +1321,1110 @@ impl Orchestrator {
         
         Ok(response)
     }
+
+    /// Ask with retrieved code in the prompt and resolve the answer's `[n]`
+    /// citations. Without a RAG context this is `ask_simple` with no sources.
+    pub async fn ask_cited(&self, message: &str) -> Result<CitedAnswer> {
+        let Some(rag) = &self.rag else {
+            let response = self.ask_simple(message).await?;
+            return Ok(CitedAnswer::resolve(&response, &[]));
+        };
+        let (_name, system_prompt) = self.get_system_prompt();
+        let budget = ContextBudget::from_settings(&self.settings);
+        let (system_prompt, chunks, warnings) = augment_within_budget(rag, &budget, &system_prompt, message).await?;
+        for warning in &warnings {
+            tracing::warn!("{}", warning);
+        }
+        let code: Vec<String> = chunks.iter().map(|chunk| chunk.content.clone()).collect();
+        let system_prompt = self.with_recall(system_prompt, message, &code).await;
+        answer_cited(&self.llm, &system_prompt, message, &chunks).await
+    }
+
+    /// Ask like `ask_simple`, also returning the model and estimated usage
+    /// for `--output-template`. `query` is what the user typed; `prompt` may
+    /// include piped input.
+    pub async fn ask_with_usage(&self, query: &str, prompt: &str) -> Result<TemplateValues> {
+        let (_name, system_prompt) = self.get_system_prompt();
+        let system_prompt = self.with_recall(system_prompt, query, &[]).await;
+        ask_values(&self.llm, &self.settings, &system_prompt, query, prompt).await
+    }
+}
+
+/// Add retrieved code to `system_prompt`, as much as the context budget
+/// leaves beside the prompt and `message`. Also returns the budget warnings.
+async fn augment_within_budget(
+    rag: &RagContext,
+    budget: &ContextBudget,
+    system_prompt: &str,
+    message: &str,
+) -> Result<(String, Vec<RetrievedChunk>, Vec<String>)> {
+    let allocation = budget.allocate(Demand {
+        system: system_prompt.len(),
+        rag: rag.max_context_chars(),
+        conversation: message.len(),
+    });
+    let (prompt, chunks) = rag.augment_within(message, system_prompt, allocation.rag).await?;
+    Ok((prompt, chunks, allocation.warnings))
+}
+
+/// Ask with `chunks` already in `system_prompt` and resolve the citations
+async fn answer_cited(
+    llm: &LlmClient,
+    system_prompt: &str,
+    message: &str,
+    chunks: &[RetrievedChunk],
+) -> Result<CitedAnswer> {
+    let response = llm.chat(system_prompt, &[], message).await?;
+    let answer = CitedAnswer::resolve(&response, chunks);
+    if !answer.unknown.is_empty() {
+        tracing::debug!(
+            "Answer cites sources that were not provided (hallucinated): {:?}",
+            answer.unknown
+        );
+    }
+    Ok(answer)
+}
+
+/// Export options relative to the working directory and home
+fn export_options(redact: bool) -> HtmlOptions {
+    HtmlOptions {
+        redact,
+        project_root: std::env::current_dir().ok(),
+        home_dir: dirs::home_dir(),
+        ..Default::default()
+    }
+}
+
+/// MCP servers enabled in `mcp.toml`, for the session header
+fn enabled_mcp_servers() -> usize {
+    McpRegistry::default_config_path()
+        .and_then(|path| McpRegistry::load_config(&path))
+        .map_or(0, |config| config.servers.values().filter(|server| server.enabled).count())
+}
+
+/// Offer to replace a never-edited config with the commented starter one
+fn offer_starter_config(console: &Console) {
+    let Ok(path) = Settings::config_path() else { return };
+    if !setup::is_untouched_default(&path) {
+        return;
+    }
+    let prompt = format!(
+        "No model provider is configured. Write a commented starter config to {}?",
+        path.display()
+    );
+    if !console.confirm(&prompt) {
+        return;
+    }
+    let written = setup::starter_config().and_then(|content| {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(std::fs::write(&path, content)?)
+    });
+    match written {
+        Ok(()) => console.success(&format!("Wrote {}", path.display())),
+        Err(e) => console.error(&format!("Could not write {}: {}", path.display(), e)),
+    }
+}
+
+/// Open the conversation memory index, warning instead of failing the session
+fn open_recall(console: &Console, settings: &Settings) -> Option<tokio::sync::Mutex<Recall>> {
+    let path = default_index_path()?;
+    let (provider, mock) = crate::embeddings::default_provider(settings);
+    if let Some(reason) = mock {
+        console.warn(&format!("{}, conversation memory uses mock embeddings", reason));
+    }
+    match ConversationMemory::open(&path, provider) {
+        Ok(memory) => Some(tokio::sync::Mutex::new(Recall {
+            memory,
+            offered: HashSet::new(),
+        })),
+        Err(e) => {
+            console.warn(&format!("Conversation memory is off: {}", e));
+            None
+        }
+    }
+}
+
+/// Substitute `{{variables}}` in the crew's prompt, or in the default
+/// agent's when no crew is active
+fn resolve_prompt_variables(
+    settings: &mut Settings,
+    crew: Option<&mut Crew>,
+    variables: &PromptVariables,
+) -> Result<()> {
+    let strict = settings.strict_prompt_variables;
+    let prompt = match crew {
+        Some(crew) => &mut crew.system_prompt,
+        None => match settings.agents.get_mut(&settings.default_agent) {
+            Some(agent) => &mut agent.system_prompt,
+            None => return Ok(()),
+        },
+    };
+    *prompt = variables.render(prompt, strict)?;
+    Ok(())
+}
+
+async fn ask_values(
+    llm: &LlmClient,
+    settings: &Settings,
+    system_prompt: &str,
+    query: &str,
+    prompt: &str,
+) -> Result<TemplateValues> {
+    let response = llm.chat(system_prompt, &[], prompt).await?;
+    if response.trim().is_empty() {
+        anyhow::bail!("the model returned an empty response");
+    }
+
+    // Same chars/4 estimate as the llm.request span
+    let input_tokens = (system_prompt.len() + prompt.len()) / 4;
+    let output_tokens = response.len() / 4;
+    // Priced by whoever answered, which may be a fallback
+    let (provider, model) = match (llm.last_provenance(), settings.get_model(&settings.default_model)) {
+        (Some(provenance), _) => (provenance.provider, provenance.model),
+        (None, Some(config)) => (config.provider.clone(), config.model.clone()),
+        (None, None) => (String::new(), settings.default_model.clone()),
+    };
+
+    Ok(TemplateValues {
+        query: query.to_string(),
+        cost: pricing::estimate_cost(&provider, &model, input_tokens, output_tokens),
+        response,
+        tokens: input_tokens + output_tokens,
+        model,
+    })
+}
+
+/// Warn once this much time is left: a fifth of the deadline, at most 5 minutes
+fn deadline_warning(deadline: Duration) -> Duration {
+    (deadline / 5).min(Duration::from_secs(300))
+}
+
+/// `/context` for a session saved with `/save` (`webrana context stats`)
+pub fn saved_context_report(path: &Path, window: usize, mode: Mode) -> Result<String> {
+    let mut context = Context::with_config(ContextConfig::unbounded());
+    context.load(path)?;
+    Ok(context_report(&context, window, mode))
+}
+
+/// Context usage against a `window`-token model and each message's size,
+/// marking the one that goes first
+fn context_report(context: &Context, window: usize, mode: Mode) -> String {
+    let stats = context.stats();
+    let limit = |n: usize| {
+        if n == usize::MAX {
+            "none".to_string()
+        } else {
+            n.to_string()
+        }
+    };
+    let mut out = format!("\n{}\n", "Context:".bold().underline());
+    out.push_str(&format!("  Messages: {} ({} pinned)\n", stats.message_count, stats.pinned_count));
+    out.push_str(&format!(
+        "  Size: {} chars, ~{} tokens ({:.1}% of the {}-token window)\n",
+        stats.total_chars,
+        stats.estimated_tokens,
+        stats.estimated_tokens as f64 * 100.0 / window.max(1) as f64,
+        window
+    ));
+    out.push_str(&format!(
+        "  Durable: {} chars; tool results: {} chars ({} in full, {} dropped)\n",
+        stats.durable_chars, stats.working_chars, stats.working_results, stats.evicted_results
+    ));
+    out.push_str(&format!(
+        "  Limits: {} messages, {} chars\n",
+        limit(stats.max_messages),
+        limit(stats.max_chars)
+    ));
+
+    // Unbounded contexts aren't trimmed, but /compact summarizes oldest first
+    let unbounded = stats.max_messages == usize::MAX && stats.max_chars == usize::MAX;
+    let next = context.next_to_trim();
+    for (i, size) in context.message_sizes().iter().enumerate() {
+        if mode.a11y {
+            let role = match size.role {
+                crate::llm::Role::User => "user",
+                crate::llm::Role::Assistant => "assistant",
+                crate::llm::Role::System => "system",
+            };
+            let status = if size.pinned {
+                "pinned"
+            } else if Some(i) == next {
+                if unbounded { "compacted first" } else { "trimmed next" }
+            } else {
+                ""
+            };
+            out.push_str(&plain::record(
+                &format!("  Message {}", i + 1),
+                &[
+                    ("  role", role.to_string()),
+                    ("  size", format!("{} chars, about {} tokens", size.chars, size.estimated_tokens())),
+                    ("  status", status.to_string()),
+                ],
+            ));
+            continue;
+        }
+        let role = match size.role {
+            crate::llm::Role::User => "user".blue(),
+            crate::llm::Role::Assistant => "assistant".green(),
+            crate::llm::Role::System => "system".yellow(),
+        };
+        let flag = if size.pinned {
+            " pinned".cyan().to_string()
+        } else if Some(i) == next {
+            if unbounded { " ← compacted first" } else { " ← trimmed next" }.red().to_string()
+        } else {
+            String::new()
+        };
+        out.push_str(&format!(
+            "  {:>3}. {:<9} {:>7} chars ~{:>6} tokens{}\n",
+            i + 1,
+            role,
+            size.chars,
+            size.estimated_tokens(),
+            flag
+        ));
+    }
+    out
+}
+
+/// How an autonomous run ended, for the line that closes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunMark {
+    Completed,
+    Warning,
+    Deadline,
+}
+
+/// The rule and line closing an autonomous run, with a word in place of
+/// the symbol in plain and accessible output
+fn run_summary(mark: RunMark, text: &str, mode: Mode) -> String {
+    let (glyph, label) = match mark {
+        RunMark::Completed => ("✓", "completed:"),
+        RunMark::Warning => ("⚠", "warning:"),
+        RunMark::Deadline => ("⏱", "deadline:"),
+    };
+    let (rule, marker) = (mode.rule("━", 60), mode.marker(glyph, label));
+    let (rule, marker) = match mark {
+        RunMark::Completed => (rule.green(), marker.green().bold()),
+        _ => (rule.yellow(), marker.yellow().bold()),
+    };
+    if mode.a11y {
+        format!("\n{} {}", marker, text)
+    } else {
+        format!("\n{}\n{} {}", rule, marker, text)
+    }
+}
+
+fn print_run_summary(mark: RunMark, text: &str) {
+    println!("{}", run_summary(mark, text, Mode::current()));
+}
+
+/// Why a run stopped at its deadline, e.g. "during iteration 3"
+fn deadline_summary(deadline: Duration, when: &str, elapsed: Duration) -> String {
+    format!(
+        "Deadline of {} exceeded {} ({} elapsed); stopping the run",
+        format_duration(deadline),
+        when,
+        format_duration(elapsed)
+    )
+}
+
+/// `1h5m`, `2m30s`, `45s`
+pub(super) fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3_600, secs % 3_600 / 60, secs % 60);
+    let mut out = String::new();
+    if hours > 0 {
+        out.push_str(&format!("{}h", hours));
+    }
+    if minutes > 0 {
+        out.push_str(&format!("{}m", minutes));
+    }
+    if seconds > 0 || out.is_empty() {
+        out.push_str(&format!("{}s", seconds));
+    }
+    out
+}
+
+/// Prompt for one autonomous iteration. Later iterations carry a reminder
+/// of the open tasks so the model doesn't lose track of them.
+fn iteration_prompt(iteration: usize, enhanced_task: &str, tasks: &TaskList) -> String {
+    if iteration == 1 {
+        return enhanced_task.to_string();
+    }
+    let mut prompt =
+        "Continue working on the task. If complete, respond with TASK_COMPLETE.".to_string();
+    if let Some(summary) = tasks.open_summary(TASK_SUMMARY_BUDGET) {
+        prompt.push_str("\n\n");
+        prompt.push_str(&summary);
+    }
+    prompt
+}
+
+/// The model said TASK_COMPLETE, or its non-empty task list is all done and
+/// has not grown since it was asked to confirm that (`checked`, the done
+/// count then)
+fn run_complete(response: &str, tasks: &TaskList, checked: Option<usize>) -> bool {
+    response.contains("TASK_COMPLETE") || (tasks.all_done() && checked == Some(tasks.done_count()))
+}
+
+/// A completed run that missed any acceptance criterion only partially
+/// completed; other outcomes stand
+fn final_status(status: RunStatus, verdicts: &[CriterionVerdict]) -> RunStatus {
+    if status == RunStatus::Completed && !task_brief::all_met(verdicts) {
+        RunStatus::PartiallyCompleted
+    } else {
+        status
+    }
+}
+
+/// Told to the model after a periodic check finds criteria still unmet
+fn unmet_reminder(verdicts: &[CriterionVerdict]) -> String {
+    let mut reminder = String::from("These acceptance criteria are not met yet:\n");
+    for verdict in verdicts.iter().filter(|v| !v.met) {
+        reminder.push_str(&format!("- {} ({})\n", verdict.criterion, verdict.evidence));
+    }
+    reminder
+}
+
+/// Write an `ask --print` response: the content with trailing whitespace
+/// trimmed to one newline. A failed call or an empty response is reported on
+/// `err` instead, and nothing is written to `out`. Returns the exit code.
+pub fn write_print_response(result: Result<String>, out: &mut dyn Write, err: &mut dyn Write) -> i32 {
+    match result {
+        Ok(response) if !response.trim().is_empty() => {
+            if writeln!(out, "{}", response.trim_end()).is_err() {
+                return 1;
+            }
+            0
+        }
+        Ok(_) => {
+            let _ = writeln!(err, "Error: the model returned an empty response");
+            1
+        }
+        Err(e) => {
+            let _ = writeln!(err, "Error: {:#}", e);
+            1
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    /// "As the Code Reviewer, " before the model speaks for itself. The role
+    /// must end in a role noun and the clause be followed by "I", "my",
+    /// "let me" or "here", so "As a result, ..." and advice such as "As a
+    /// developer, you ..." never match. Group 1 is the clause to drop.
+    static ref PERSONA_PREFIX: regex::Regex = regex::Regex::new(
+        r"(?i)^(\s*(?:as|speaking as|acting as) (?:the|your|a|an|this) (?:[a-z][\w-]* ){0,3}(?:reviewer|engineer|architect|assistant|auditor|expert|analyst|developer|writer|specialist|agent|consultant|tester|lead|advisor|designer|scientist|maintainer|mentor|ai|model),\s+)(?-i:I\b|I'|[Mm]y\b|[Ll]et me\b|[Hh]ere\b)"
+    )
+    .unwrap();
+}
+
+/// `response` without a leading persona clause ("As the Code Reviewer, I
+/// think ..." becomes "I think ..."), for `ask --print --clean`. Anything
+/// else passes through unchanged.
+pub fn strip_persona_prefix(response: &str) -> String {
+    let Some(clause) = PERSONA_PREFIX.captures(response).and_then(|caps| caps.get(1)) else {
+        return response.to_string();
+    };
+    let mut rest = response[clause.end()..].chars();
+    match rest.next() {
+        Some(first) => first.to_uppercase().chain(rest).collect(),
+        None => response.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{ChatResponse, Provider, RefusalInfo, RefusalKind, ToolCall, ToolDefinition};
+    use crate::skills::{Skill, SkillDefinition, TaskPriority, TaskStatus};
+    use async_trait::async_trait;
+
+    struct FixedProvider(&'static str);
+
+    #[async_trait]
+    impl Provider for FixedProvider {
+        async fn chat(
+            &self,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            Ok(ChatResponse {
+                content: self.0.to_string(),
+                tool_calls: Vec::new(),
+                stop_reason: None,
+                refusal: None,
+                provenance: Default::default(),
+            })
+        }
+
+        async fn chat_stream(
+            &self,
+            messages: Vec<Message>,
+            tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            self.chat(messages, tools).await
+        }
+
+        fn name(&self) -> &str {
+            "fixed"
+        }
+    }
+
+    async fn print_mode(content: &'static str) -> (i32, String, String) {
+        let provider: Arc<dyn Provider> = Arc::new(FixedProvider(content));
+        let llm = LlmClient::from_provider(provider, &Settings::default());
+        let result = llm.chat("system", &[], "question").await;
+
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let code = write_print_response(result, &mut out, &mut err);
+        (
+            code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_print_empty_response_fails() {
+        let (code, out, err) = print_mode("  \n").await;
+        assert_eq!(code, 1);
+        assert_eq!(out, "");
+        assert!(err.contains("empty response"));
+
+        let mut out = Vec::new();
+        let code = write_print_response(Err(anyhow::anyhow!("boom")), &mut out, &mut Vec::new());
+        assert_eq!((code, out.len()), (1, 0));
+    }
+
+    #[test]
+    fn test_strip_persona_prefix() {
+        assert_eq!(
+            strip_persona_prefix("As the Code Reviewer, I found two issues in parse().\n\n1. ..."),
+            "I found two issues in parse().\n\n1. ..."
+        );
+        assert_eq!(
+            strip_persona_prefix("As your security auditor, here is what stands out:\n- unchecked input"),
+            "Here is what stands out:\n- unchecked input"
+        );
+        assert_eq!(strip_persona_prefix("Speaking as an AI, my advice is to retry."), "My advice is to retry.");
+
+        // Real content passes through untouched
+        for answer in [
+            "As a result, the cache is never invalidated.",
+            "As a developer, you should pin the toolchain.",
+            "As the Code Reviewer noted, the lock is held too long.",
+            "As an example, here is a loop:\n```rust\nfor x in xs {}\n```",
+            "The answer is 42.",
+            "",
+        ] {
+            assert_eq!(strip_persona_prefix(answer), answer);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_print_response_is_clean() {
+        let (code, out, err) = print_mode("The answer is 42.\n\n").await;
+        assert_eq!(code, 0);
+        assert_eq!(out, "The answer is 42.\n");
+        assert_eq!(err, "");
+    }
+
+    /// Replays scripted responses and records the tool names and system
+    /// prompt of every request
+    struct RecordingProvider {
+        script: std::sync::Mutex<Vec<ChatResponse>>,
+        requests: std::sync::Mutex<Vec<(String, Vec<String>)>>,
+    }
+
+    #[async_trait]
+    impl Provider for RecordingProvider {
+        async fn chat(
+            &self,
+            messages: Vec<Message>,
+            tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            let names = tools.unwrap_or_default().into_iter().map(|t| t.name).collect();
+            self.requests.lock().unwrap().push((messages[0].content.clone(), names));
+            Ok(self.script.lock().unwrap().remove(0))
+        }
+
+        async fn chat_stream(
+            &self,
+            messages: Vec<Message>,
+            tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            self.chat(messages, tools).await
+        }
+
+        fn name(&self) -> &str {
+            "recording"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_more_tools_swaps_in_dropped_tool() {
+        let mut settings = Settings::default();
+        let default_model = settings.default_model.clone();
+        // The core tools, the meta-tool and one more
+        settings.models.get_mut(&default_model).unwrap().max_tools = Some(9);
+
+        let provider = Arc::new(RecordingProvider {
+            script: std::sync::Mutex::new(vec![
+                ChatResponse {
+                    content: String::new(),
+                    tool_calls: vec![crate::llm::ToolCall {
+                        id: "call_1".to_string(),
+                        name: LIST_MORE_TOOLS.to_string(),
+                        arguments: serde_json::json!({"query": "show commit logs"}),
+                    }],
+                    stop_reason: None,
+                    refusal: None,
+                    provenance: Default::default(),
+                },
+                ChatResponse {
+                    content: "done".to_string(),
+                    tool_calls: Vec::new(),
+                    stop_reason: None,
+                    refusal: None,
+                    provenance: Default::default(),
+                },
+            ]),
+            requests: Default::default(),
+        });
+        let llm = LlmClient::from_provider(provider.clone(), &settings);
+        let skills = SkillRegistry::new();
+
+        let mut history = Vec::new();
+        let answer = llm
+            .chat_with_tools_loop("system", &mut history, "refactor the parser", &skills)
+            .await
+            .unwrap();
+        assert_eq!(answer, "done");
+
+        let requests = provider.requests.lock().unwrap();
+        let (first_prompt, first_tools) = &requests[0];
+        assert_eq!(first_tools.len(), 9);
+        assert!(!first_tools.contains(&"git_log".to_string()));
+        assert!(first_tools.contains(&LIST_MORE_TOOLS.to_string()));
+        assert!(first_tools.contains(&"edit_file".to_string()));
+        assert!(first_prompt.contains("more tools are available but not loaded"));
+
+        let (_, second_tools) = &requests[1];
+        assert_eq!(second_tools.len(), 9);
+        assert!(second_tools.contains(&"git_log".to_string()));
+        assert!(history
+            .iter()
+            .any(|m| m.content.contains("call_1") && m.content.contains("- git_log: Show commit logs")));
+    }
+
+    #[test]
+    fn test_unmet_criteria_downgrade_completed_run() {
+        let verdict = |met| CriterionVerdict { criterion: "tests pass".to_string(), met, evidence: String::new() };
+        assert_eq!(final_status(RunStatus::Completed, &[verdict(true)]), RunStatus::Completed);
+        assert_eq!(
+            final_status(RunStatus::Completed, &[verdict(true), verdict(false)]),
+            RunStatus::PartiallyCompleted
+        );
+        assert_eq!(final_status(RunStatus::MaxIterations, &[verdict(false)]), RunStatus::MaxIterations);
+        assert_eq!(RunStatus::PartiallyCompleted.key(), "partially_completed");
+    }
+
+    /// Finishes at once, then judges the second criterion unmet
+    struct SelfAssessingProvider;
+
+    #[async_trait]
+    impl Provider for SelfAssessingProvider {
+        async fn chat(
+            &self,
+            messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            let assessing = messages.last().is_some_and(|m| m.content.contains("Acceptance criteria:\n"));
+            let content = if assessing {
+                r#"{"criteria": [{"criterion": "bug fixed", "met": true, "evidence": "parser.rs changed"},
+                    {"criterion": "test added", "met": false, "evidence": "no new test"}]}"#
+            } else {
+                "Fixed the parser.\nTASK_COMPLETE"
+            };
+            Ok(ChatResponse {
+                content: content.to_string(),
+                tool_calls: Vec::new(),
+                stop_reason: None,
+                refusal: None,
+                provenance: Default::default(),
+            })
+        }
+
+        async fn chat_stream(
+            &self,
+            messages: Vec<Message>,
+            tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            self.chat(messages, tools).await
+        }
+
+        fn name(&self) -> &str {
+            "self-assessing"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_acceptance_check_after_completion() {
+        let settings = Settings::default();
+        let provider: Arc<dyn Provider> = Arc::new(SelfAssessingProvider);
+        let orchestrator = Orchestrator {
+            llm: LlmClient::from_provider(provider, &settings),
+            settings,
+            context: Context::new(),
+            skills: SkillRegistry::new(),
+            console: Console::new(),
+            auto_mode: true,
+            active_crew: None,
+            rag: None,
+            project_memory: None,
+            environment: String::new(),
+            recall: None,
+            deadline: None,
+            acceptance: None,
+            header: SessionHeader::default(),
+        }
+        .with_acceptance_criteria(vec!["bug fixed".to_string(), "test added".to_string()], None);
+
+        let status = orchestrator.run_iterations("fix the parser", 5, false, "test-run").await.unwrap();
+        assert_eq!(status, RunStatus::PartiallyCompleted);
+        let verdicts = orchestrator.acceptance.as_ref().unwrap().verdicts.lock().unwrap().clone();
+        assert_eq!(verdicts.iter().map(|v| v.met).collect::<Vec<_>>(), [true, false]);
+        assert_eq!(verdicts[1].evidence, "no new test");
+    }
+
+    #[tokio::test]
+    async fn test_repeating_model_stalls_the_run() {
+        let settings = Settings::default();
+        let provider: Arc<dyn Provider> = Arc::new(FixedProvider("Let me look at the config first."));
+        let orchestrator = Orchestrator {
+            llm: LlmClient::from_provider(provider, &settings),
+            settings,
+            context: Context::new(),
+            skills: SkillRegistry::new(),
+            console: Console::new(),
+            auto_mode: true,
+            active_crew: None,
+            rag: None,
+            project_memory: None,
+            environment: String::new(),
+            recall: None,
+            deadline: None,
+            acceptance: None,
+            header: SessionHeader::default(),
+        };
+
+        // Three repeats get the correction, the fourth stops the run
+        let status = orchestrator.run_iterations("fix the bug", 10, false, "test-run").await.unwrap();
+        assert_eq!(status, RunStatus::Stalled);
+    }
+
+    /// Declines every request, counting them
+    struct RefusingProvider(std::sync::atomic::AtomicUsize);
+
+    #[async_trait]
+    impl Provider for RefusingProvider {
+        async fn chat(
+            &self,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(ChatResponse {
+                content: "I can't help with that.".to_string(),
+                tool_calls: Vec::new(),
+                stop_reason: Some("refusal".to_string()),
+                refusal: Some(RefusalInfo {
+                    kind: RefusalKind::StopReason,
+                    message: "I can't help with that.".to_string(),
+                }),
+                provenance: Default::default(),
+            })
+        }
+
+        async fn chat_stream(
+            &self,
+            messages: Vec<Message>,
+            tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            self.chat(messages, tools).await
+        }
+
+        fn name(&self) -> &str {
+            "refusing"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refusing_model_stops_the_run_after_one_adjustment() {
+        let settings = Settings::default();
+        let provider = Arc::new(RefusingProvider(Default::default()));
+        let orchestrator = Orchestrator {
+            llm: LlmClient::from_provider(provider.clone(), &settings),
+            settings,
+            context: Context::new(),
+            skills: SkillRegistry::new(),
+            console: Console::new(),
+            auto_mode: true,
+            active_crew: None,
+            rag: None,
+            project_memory: None,
+            environment: String::new(),
+            recall: None,
+            deadline: None,
+            acceptance: None,
+            header: SessionHeader::default(),
+        };
+
+        // Not retried even with --yolo: one request, one adjusted request
+        let status = orchestrator.run_iterations("write the exploit", 10, true, "test-run").await.unwrap();
+        assert_eq!(status, RunStatus::Refused);
+        assert_eq!(status.key(), "refused");
+        assert_eq!(provider.0.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert!(METRICS.get_counter(REFUSALS_METRIC) >= 2);
+    }
+
+    /// Asks for the `slow` tool every turn
+    struct SlowToolProvider;
+
+    #[async_trait]
+    impl Provider for SlowToolProvider {
+        async fn chat(
+            &self,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            Ok(ChatResponse {
+                content: "Running the slow check".to_string(),
+                tool_calls: vec![ToolCall {
+                    id: "call_1".to_string(),
+                    name: "slow".to_string(),
+                    arguments: serde_json::json!({}),
+                }],
+                stop_reason: None,
+                refusal: None,
+                provenance: Default::default(),
+            })
+        }
+
+        async fn chat_stream(
+            &self,
+            messages: Vec<Message>,
+            tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            self.chat(messages, tools).await
+        }
+
+        fn name(&self) -> &str {
+            "slow-tool"
+        }
+    }
+
+    struct SlowSkill;
+
+    #[async_trait]
+    impl Skill for SlowSkill {
+        fn definition(&self) -> SkillDefinition {
+            SkillDefinition {
+                name: "slow".to_string(),
+                description: "Takes ten minutes".to_string(),
+                parameters: serde_json::json!({"type": "object", "properties": {}}),
+                requires_confirmation: false,
+                parallel_safe: false,
+            }
+        }
+
+        async fn execute(&self, _args: &serde_json::Value, _settings: &Settings) -> Result<String> {
+            tokio::time::sleep(Duration::from_secs(600)).await;
+            Ok("done".to_string())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deadline_stops_run_during_slow_tool() {
+        let settings = Settings {
+            skill_timeouts: crate::config::SkillTimeouts::uniform(Duration::from_secs(3_600)),
+            ..Default::default()
+        };
+        let provider: Arc<dyn Provider> = Arc::new(SlowToolProvider);
+        let mut skills = SkillRegistry::new();
+        skills.register(Box::new(SlowSkill));
+        let orchestrator = Orchestrator {
+            llm: LlmClient::from_provider(provider, &settings),
+            settings,
+            context: Context::new(),
+            skills,
+            console: Console::new(),
+            auto_mode: true,
+            active_crew: None,
+            rag: None,
+            project_memory: None,
+            environment: String::new(),
+            recall: None,
+            deadline: None,
+            acceptance: None,
+            header: SessionHeader::default(),
+        }
+        .with_deadline(Duration::from_secs(30));
+
+        let started = Instant::now();
+        let status = orchestrator.run_iterations("check everything", 10, false, "test-run").await.unwrap();
+        assert_eq!(status, RunStatus::DeadlineExceeded);
+        assert_eq!(status.label(), "deadline exceeded");
+        // Cut off at the deadline, not when the tool finishes
+        assert!(started.elapsed() < Duration::from_secs(31), "{:?}", started.elapsed());
+    }
+
+    #[test]
+    fn test_deadline_summary() {
+        assert_eq!(
+            deadline_summary(Duration::from_secs(1_800), "during iteration 3", Duration::from_secs(1_802)),
+            "Deadline of 30m exceeded during iteration 3 (30m2s elapsed); stopping the run"
+        );
+        assert_eq!(format_duration(Duration::from_secs(3_900)), "1h5m");
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+        assert_eq!(deadline_warning(Duration::from_secs(600)), Duration::from_secs(120));
+        assert_eq!(deadline_warning(Duration::from_secs(7_200)), Duration::from_secs(300));
+        let uniform = crate::config::SkillTimeouts::uniform(Duration::from_secs(90));
+        assert_eq!(uniform.for_skill("shell_execute"), Some(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn test_saved_session_context_report() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("session.json");
+        let mut context = Context::with_config(ContextConfig::unbounded());
+        context.add_pinned_message(Message::system("rules"));
+        context.add_user_message("how do retries work?");
+        context.add_assistant_message(&"They back off. ".repeat(20));
+        context.save(&path).unwrap();
+
+        let report = saved_context_report(&path, 1_000, Mode { plain: false, a11y: true }).unwrap();
+        assert!(report.contains("Messages: 3 (1 pinned)"), "{}", report);
+        assert!(report.contains("Limits: none messages, none chars"), "{}", report);
+        assert!(report.contains("  Message 1\n    role: system\n"), "{}", report);
+        assert!(report.contains("    size: 300 chars, about 75 tokens\n"), "{}", report);
+        let second = report.split("  Message 2\n").nth(1).unwrap();
+        assert!(second.starts_with("    role: user\n    size: 20 chars, about 5 tokens\n    status: compacted first\n"));
+
+        assert!(saved_context_report(&tmp.path().join("missing.json"), 1_000, Mode::default()).is_err());
+    }
+
+    #[test]
+    fn test_run_summary_in_accessible_mode() {
+        let a11y = Mode { plain: false, a11y: true };
+        let stalled = run_summary(RunMark::Warning, "Run stalled at iteration 4", a11y);
+        assert!(stalled.contains("warning:") && stalled.contains("Run stalled at iteration 4"), "{}", stalled);
+        let deadline = run_summary(RunMark::Deadline, "Deadline of 30m exceeded", a11y);
+        assert!(deadline.contains("deadline:"), "{}", deadline);
+        for line in [&stalled, &deadline] {
+            assert!(!line.contains('⚠') && !line.contains('⏱') && !line.contains('━'), "{}", line);
+        }
+
+        let done = run_summary(RunMark::Completed, "Task completed in 2 iterations", Mode::default());
+        assert!(done.contains('✓') && done.contains(&"━".repeat(60)), "{}", done);
+    }
+
+    #[test]
+    fn test_iteration_prompt_reminds_open_tasks() {
+        let mut tasks = TaskList::default();
+        assert_eq!(iteration_prompt(1, "Do the thing", &tasks), "Do the thing");
+        assert_eq!(
+            iteration_prompt(2, "Do the thing", &tasks),
+            "Continue working on the task. If complete, respond with TASK_COMPLETE."
+        );
+
+        tasks.add("Write parser", TaskPriority::Normal).unwrap();
+        tasks.add("Add tests", TaskPriority::Normal).unwrap();
+        tasks.update("t1", TaskStatus::Done, None).unwrap();
+        let prompt = iteration_prompt(3, "Do the thing", &tasks);
+        assert!(prompt.contains("Open tasks (1 remaining, 1 done)"));
+        assert!(prompt.contains("- t2 [pending] Add tests"));
+        assert!(!prompt.contains("Write parser"));
+        assert!(!run_complete("Still working", &tasks, None));
+
+        tasks.update("t2", TaskStatus::Done, None).unwrap();
+        assert!(!iteration_prompt(4, "Do the thing", &tasks).contains("Open tasks"));
+        // All done is not enough until the model was asked and added nothing
+        assert!(!run_complete("Still working", &tasks, None));
+        assert!(run_complete("Still working", &tasks, Some(2)));
+        tasks.add("Update docs", TaskPriority::Normal).unwrap();
+        tasks.update("t3", TaskStatus::Done, None).unwrap();
+        assert!(!run_complete("Still working", &tasks, Some(2)));
+        assert!(run_complete("TASK_COMPLETE", &TaskList::default(), None));
+        assert!(!run_complete("Still working", &TaskList::default(), Some(0)));
+    }
+
+    #[tokio::test]
+    async fn test_output_template_renders_mock_call() {
+        let settings = Settings::default();
+        let provider: Arc<dyn Provider> = Arc::new(FixedProvider("Paris\n"));
+        let llm = LlmClient::from_provider(provider, &settings);
+
+        let values = ask_values(&llm, &settings, "system", "capital?", "capital?")
+            .await
+            .unwrap();
+        let template = crate::ui::OutputTemplate::parse("{model}: {response} ({tokens} tokens)")
+            .unwrap();
+        assert_eq!(
+            template.render(&values),
+            "claude-sonnet-4-20250514: Paris (4 tokens)"
+        );
+        assert_eq!(values.cost, pricing::estimate_cost("anthropic", &values.model, 3, 1));
+
+        let provider: Arc<dyn Provider> = Arc::new(FixedProvider(" "));
+        let llm = LlmClient::from_provider(provider, &settings);
+        assert!(ask_values(&llm, &settings, "system", "q", "q").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_system_prompt_leaves_out_retrieved_code() {
+        use crate::config::ContextBudgetSettings;
+        use crate::embeddings::MockEmbeddingProvider;
+        use crate::llm::{Document, RagConfig};
+
+        let config = RagConfig { top_k: 2, min_score: -1.0, ..Default::default() };
+        let mut rag = RagContext::new(Arc::new(MockEmbeddingProvider::new(32)), config);
+        rag.add_documents(vec![Document::new("src/a.rs:chunk:0", "fn retry() {}".repeat(40))])
+            .await
+            .unwrap();
+        // 4,000 characters of window
+        let budget = ContextBudget::new(ContextBudgetSettings::default(), 1_200, 200);
+
+        let (prompt, chunks, warnings) = augment_within_budget(&rag, &budget, "rules", "retries?").await.unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert!(prompt.contains("fn retry()"));
+        assert!(!warnings.iter().any(|w| w.contains("system prompt")), "{:?}", warnings);
+
+        let huge = "r".repeat(3_990);
+        let (prompt, chunks, warnings) = augment_within_budget(&rag, &budget, &huge, "retries?").await.unwrap();
+        assert!(chunks.is_empty());
+        assert_eq!(prompt, huge);
+        assert!(warnings[0].starts_with("The system prompt is ~997 tokens, over its"), "{:?}", warnings);
+        assert!(warnings.iter().any(|w| w.starts_with("Retrieved code cut from")), "{:?}", warnings);
+    }
+
+    #[tokio::test]
+    async fn test_cited_answer_sources() {
+        use crate::embeddings::MockEmbeddingProvider;
+        use crate::llm::{Document, RagConfig};
+
+        let config = RagConfig {
+            top_k: 2,
+            min_score: -1.0,
+            ..Default::default()
+        };
+        let mut rag = RagContext::new(Arc::new(MockEmbeddingProvider::new(32)), config);
+        rag.add_documents(vec![
+            Document::new("src/a.rs:chunk:0", "fn retry() {}")
+                .with_metadata("file", "src/a.rs")
+                .with_metadata("start_line", "10")
+                .with_metadata("end_line", "20"),
+            Document::new("src/b.rs:chunk:3", "fn backoff() {}")
+                .with_metadata("file", "src/b.rs")
+                .with_metadata("start_line", "40")
+                .with_metadata("end_line", "52"),
+        ])
+        .await
+        .unwrap();
+        let retrieved = rag.retrieve("how do retries work?").await.unwrap();
+
+        let settings = Settings::default();
+        let provider: Arc<dyn Provider> =
+            Arc::new(FixedProvider("Retries back off [2].\n\n```\nv[1]\n```\nAlso [9]."));
+        let llm = LlmClient::from_provider(provider, &settings);
+        let budget = ContextBudget::from_settings(&settings);
+        let (prompt, chunks, warnings) = augment_within_budget(&rag, &budget, "system", "how do retries work?")
+            .await
+            .unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(chunks.len(), 2);
+        let answer = answer_cited(&llm, &prompt, "how do retries work?", &chunks).await.unwrap();
+
+        assert_eq!(answer.unknown, vec![9]);
+        let sources = serde_json::to_value(&answer.sources).unwrap();
+        let cited = &retrieved[1];
+        assert_eq!(
+            sources,
+            serde_json::json!([{
+                "number": 2,
+                "id": cited.id,
+                "file": cited.file_path,
+                "start_line": cited.start_line,
+                "end_line": cited.end_line,
+                "score": cited.score,
+            }])
+        );
+        assert!(answer.with_footer().ends_with(&format!(
+            "Sources:\n  [2] {}:{}-{}",
+            cited.file_path.as_ref().unwrap(),
+            cited.start_line.unwrap(),
+            cited.end_line.unwrap()
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_prompt_variables_in_crew_prompt() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("Cargo.toml"), "[package]\nname = \"fixture\"\n").unwrap();
+        let variables = PromptVariables::detect(tmp.path()).await;
+
+        let mut settings = Settings::default();
+        let mut crew = crate::crew::CrewTemplate::CodeReviewer.create();
+        crew.system_prompt = "Review this {{project_type}} code. Ticket: {{ticket}}".to_string();
+        resolve_prompt_variables(&mut settings, Some(&mut crew), &variables).unwrap();
+        assert!(crew
+            .effective_system_prompt()
+            .ends_with("Review this rust code. Ticket: {{ticket}}"));
+
+        // Without a crew the default agent's prompt is used; strict rejects unknown names
+        let agent = settings.default_agent.clone();
+        settings.agents.get_mut(&agent).unwrap().system_prompt = "Work in {{cwd}} on {{ticket}}".to_string();
+        settings.strict_prompt_variables = true;
+        let err = resolve_prompt_variables(&mut settings, None, &variables).unwrap_err();
+        assert!(err.to_string().contains("{{ticket}}"), "{}", err);
+        settings.strict_prompt_variables = false;
+        resolve_prompt_variables(&mut settings, None, &variables).unwrap();
+        assert_eq!(
+            settings.agents[&agent].system_prompt,
+            format!("Work in {} on {{{{ticket}}}}", tmp.path().display())
+        );
+    }
+
+    #[test]
+    fn test_no_provider_gives_guided_setup() {
+        use crate::util::connectivity;
+
+        fn ollama_down(_: &str, _: u16) -> bool {
+            false
+        }
+        let _guard = connectivity::TEST_PROBE_LOCK.lock().unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        connectivity::set_probe(Some(ollama_down));
+
+        let mut settings = Settings::default();
+        for model in settings.models.values_mut() {
+            model.api_key_env = model.api_key_env.as_ref().map(|_| "WEBRANA_TEST_UNSET_KEY".to_string());
+        }
+        let result = runtime.block_on(Orchestrator::new(settings.clone(), true));
+        let err = result.err().expect("orchestrator built without a provider");
+        assert!(err.downcast_ref::<setup::NoProviderConfigured>().is_some(), "{:#}", err);
+        let message = err.to_string();
+        for step in ["ANTHROPIC_API_KEY", "ollama serve", "webrana login"] {
+            assert!(message.contains(step), "{}", message);
+        }
+
+        // A single key is enough
+        settings.models.get_mut("gpt").unwrap().api_key = Some("sk-test".to_string());
+        assert!(runtime.block_on(setup::check_providers(&settings)).is_ok());
+        connectivity::set_probe(None);
+    }
 }
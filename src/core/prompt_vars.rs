@@ -0,0 +1,125 @@
+// ============================================
+// WEBRANA CLI - System Prompt Variables
+// {{project_type}}, {{git_branch}}, {{date}} and {{cwd}} in crew/agent prompts
+// ============================================
+
+use anyhow::Result;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::indexer::ProjectDetector;
+use crate::memory::utc_date;
+use crate::skills::run_git_command;
+use crate::util::unix_now;
+
+/// Variables resolved at startup
+pub const PROMPT_VARIABLES: &[&str] = &["project_type", "git_branch", "date", "cwd"];
+
+fn placeholder() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").unwrap())
+}
+
+/// Values substituted into system prompts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PromptVariables {
+    values: BTreeMap<String, String>,
+}
+
+impl PromptVariables {
+    /// Resolve every variable for `dir`. Outside a git repository the
+    /// branch is "none"; an undetected project type is "unknown".
+    pub async fn detect(dir: &Path) -> Self {
+        let project_type = ProjectDetector::new(dir)
+            .detect()
+            .map(|info| info.project_type.as_str())
+            .unwrap_or("unknown");
+        let cwd = dir.to_string_lossy();
+        let git_branch = run_git_command(&["rev-parse", "--abbrev-ref", "HEAD"], Some(&cwd))
+            .await
+            .map(|branch| branch.trim().to_string())
+            .unwrap_or_else(|_| "none".to_string());
+        let secs = unix_now();
+        let date = utc_date(secs);
+
+        Self::default()
+            .with("project_type", project_type)
+            .with("git_branch", &git_branch)
+            .with("date", date.split(' ').next().unwrap_or_default())
+            .with("cwd", &cwd)
+    }
+
+    pub fn with(mut self, name: &str, value: &str) -> Self {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// Replace `{{name}}` placeholders. Unknown names are left as written,
+    /// or are an error when `strict`.
+    pub fn render(&self, template: &str, strict: bool) -> Result<String> {
+        if strict {
+            let mut unknown: Vec<&str> = placeholder()
+                .captures_iter(template)
+                .filter_map(|caps| caps.get(1))
+                .map(|name| name.as_str())
+                .filter(|name| !self.values.contains_key(*name))
+                .collect();
+            unknown.dedup();
+            if !unknown.is_empty() {
+                anyhow::bail!(
+                    "Unknown prompt variable{} {} (available: {})",
+                    if unknown.len() > 1 { "s" } else { "" },
+                    unknown.iter().map(|n| format!("{{{{{}}}}}", n)).collect::<Vec<_>>().join(", "),
+                    self.values.keys().cloned().collect::<Vec<_>>().join(", ")
+                );
+            }
+        }
+
+        Ok(placeholder()
+            .replace_all(template, |caps: &regex::Captures| match self.values.get(&caps[1]) {
+                Some(value) => value.clone(),
+                None => caps[0].to_string(),
+            })
+            .into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_known_and_unknown() {
+        let vars = PromptVariables::default()
+            .with("project_type", "rust")
+            .with("git_branch", "main");
+        let rendered = vars
+            .render("A {{project_type}} project on {{ git_branch }}; {{ticket}} stays", false)
+            .unwrap();
+        assert_eq!(rendered, "A rust project on main; {{ticket}} stays");
+
+        let err = vars.render("{{ticket}} and {{owner}}", true).unwrap_err().to_string();
+        assert!(err.contains("Unknown prompt variables {{ticket}}, {{owner}}"), "{}", err);
+        assert!(err.contains("available: git_branch, project_type"), "{}", err);
+        assert_eq!(vars.render("{{project_type}}", true).unwrap(), "rust");
+    }
+
+    #[tokio::test]
+    async fn test_detect_outside_git() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("go.mod"), "module example.com/fixture\n").unwrap();
+
+        let vars = PromptVariables::detect(tmp.path()).await;
+        assert_eq!(vars.get("project_type"), Some("go"));
+        assert_eq!(vars.get("git_branch"), Some("none"));
+        assert_eq!(vars.get("cwd"), Some(tmp.path().to_string_lossy().as_ref()));
+        assert_eq!(vars.get("date").unwrap().len(), "2025-01-01".len());
+        assert!(PROMPT_VARIABLES.iter().all(|name| vars.get(name).is_some()));
+    }
+}
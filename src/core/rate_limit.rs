@@ -5,7 +5,8 @@
 // ============================================
 
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
 /// Rate limiter using token bucket algorithm
@@ -238,6 +239,246 @@ lazy_static::lazy_static! {
     pub static ref CMD_LIMITER: RateLimiter = RateLimiter::new(RateLimitConfig::commands());
 }
 
+// ============================================
+// Shared provider limiter
+// One async budget per (provider, credential), shared by chat turns and
+// background work such as indexing
+// ============================================
+
+/// Who is asking: interactive turns are served first, background work yields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// Chat/ask turns and retrieval for them
+    Interactive,
+    /// Indexing and other work nobody is waiting on
+    Background,
+}
+
+/// Budget of a shared provider limiter
+#[derive(Debug, Clone)]
+pub struct SharedLimitConfig {
+    pub requests_per_minute: f64,
+    /// Requests that may be issued back to back
+    pub burst: f64,
+    /// Share of the budget background work may use (0.3 = 70/30 split)
+    pub background_share: f64,
+    /// How long a 429 keeps the budget shrunk
+    pub backoff: Duration,
+}
+
+impl Default for SharedLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 300.0,
+            burst: 20.0,
+            background_share: 0.3,
+            backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Shortest sleep between acquisition attempts
+const MIN_WAIT: Duration = Duration::from_millis(10);
+
+/// Lowest fraction of the budget repeated 429s can shrink it to
+const MIN_THROTTLE: f64 = 0.1;
+
+struct SharedState {
+    /// Whole budget, used by both priorities
+    tokens: f64,
+    /// Background's share; background acquisitions draw from both
+    background_tokens: f64,
+    last_refill: tokio::time::Instant,
+    pending_interactive: usize,
+    /// Multiplier on the refill rate, lowered by 429s
+    throttle: f64,
+    throttled_until: Option<tokio::time::Instant>,
+    waited_interactive: Duration,
+    waited_background: Duration,
+}
+
+/// Async token bucket shared by everything calling one provider with one key
+pub struct SharedRateLimiter {
+    config: SharedLimitConfig,
+    state: Mutex<SharedState>,
+}
+
+impl SharedRateLimiter {
+    pub fn new(config: SharedLimitConfig) -> Self {
+        let state = SharedState {
+            tokens: config.burst.max(1.0),
+            background_tokens: Self::background_capacity(&config),
+            last_refill: tokio::time::Instant::now(),
+            pending_interactive: 0,
+            throttle: 1.0,
+            throttled_until: None,
+            waited_interactive: Duration::ZERO,
+            waited_background: Duration::ZERO,
+        };
+        Self {
+            config,
+            state: Mutex::new(state),
+        }
+    }
+
+    fn background_capacity(config: &SharedLimitConfig) -> f64 {
+        (config.burst * config.background_share).max(1.0)
+    }
+
+    fn refill(&self, state: &mut SharedState) {
+        let now = tokio::time::Instant::now();
+        if state.throttled_until.is_some_and(|until| now >= until) {
+            state.throttle = 1.0;
+            state.throttled_until = None;
+        }
+
+        let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+        let rate = self.config.requests_per_minute / 60.0 * state.throttle;
+        state.tokens = (state.tokens + elapsed * rate).min(self.config.burst.max(1.0));
+        state.background_tokens = (state.background_tokens
+            + elapsed * rate * self.config.background_share)
+            .min(Self::background_capacity(&self.config));
+        state.last_refill = now;
+    }
+
+    /// Take one token, or how long to wait before trying again
+    fn try_take(&self, state: &mut SharedState, priority: Priority) -> Option<Duration> {
+        self.refill(state);
+        let rate = self.config.requests_per_minute / 60.0 * state.throttle;
+        let deficit = |tokens: f64, rate: f64| {
+            Duration::from_secs_f64(((1.0 - tokens) / rate.max(f64::EPSILON)).max(0.0))
+        };
+
+        match priority {
+            Priority::Interactive => {
+                if state.tokens >= 1.0 {
+                    state.tokens -= 1.0;
+                    return None;
+                }
+                Some(deficit(state.tokens, rate))
+            }
+            Priority::Background => {
+                if state.pending_interactive == 0
+                    && state.tokens >= 1.0
+                    && state.background_tokens >= 1.0
+                {
+                    state.tokens -= 1.0;
+                    state.background_tokens -= 1.0;
+                    return None;
+                }
+                Some(
+                    deficit(state.tokens, rate).max(deficit(
+                        state.background_tokens,
+                        rate * self.config.background_share,
+                    )),
+                )
+            }
+        }
+    }
+
+    /// Wait for permission to send one request; returns the time spent waiting
+    pub async fn acquire(&self, priority: Priority) -> Duration {
+        let start = tokio::time::Instant::now();
+        let _pending = (priority == Priority::Interactive).then(|| PendingInteractive::new(self));
+
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                self.try_take(&mut state, priority)
+            };
+            match wait {
+                None => break,
+                Some(wait) => tokio::time::sleep(wait.max(MIN_WAIT)).await,
+            }
+        }
+
+        let waited = start.elapsed();
+        let mut state = self.state.lock().unwrap();
+        match priority {
+            Priority::Interactive => state.waited_interactive += waited,
+            Priority::Background => state.waited_background += waited,
+        }
+        waited
+    }
+
+    /// Record a 429 from any consumer: the budget is emptied and its refill
+    /// rate halved until `backoff` passes without another one
+    pub fn report_rate_limited(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state);
+        state.throttle = (state.throttle * 0.5).max(MIN_THROTTLE);
+        state.tokens = state.tokens.min(0.0);
+        state.background_tokens = state.background_tokens.min(0.0);
+        state.throttled_until = Some(tokio::time::Instant::now() + self.config.backoff);
+        tracing::warn!(
+            "Provider rate limited (429); shared budget reduced to {:.0}%",
+            state.throttle * 100.0
+        );
+    }
+
+    /// Total time callers of `priority` have waited for permits
+    pub fn throttled_for(&self, priority: Priority) -> Duration {
+        let state = self.state.lock().unwrap();
+        match priority {
+            Priority::Interactive => state.waited_interactive,
+            Priority::Background => state.waited_background,
+        }
+    }
+
+    /// Current fraction of the configured budget (below 1.0 after a 429)
+    pub fn throttle_factor(&self) -> f64 {
+        self.state.lock().unwrap().throttle
+    }
+}
+
+/// Marks an interactive acquisition as pending until dropped, so background
+/// acquisitions yield to it (also when the waiting future is cancelled)
+struct PendingInteractive<'a> {
+    limiter: &'a SharedRateLimiter,
+}
+
+impl<'a> PendingInteractive<'a> {
+    fn new(limiter: &'a SharedRateLimiter) -> Self {
+        limiter.state.lock().unwrap().pending_interactive += 1;
+        Self { limiter }
+    }
+}
+
+impl Drop for PendingInteractive<'_> {
+    fn drop(&mut self) {
+        self.limiter.state.lock().unwrap().pending_interactive -= 1;
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref SHARED_LIMIT_CONFIG: RwLock<SharedLimitConfig> = RwLock::new(SharedLimitConfig::default());
+    static ref SHARED_LIMITERS: Mutex<HashMap<String, Arc<SharedRateLimiter>>> = Mutex::new(HashMap::new());
+}
+
+/// Budget used for shared limiters created from now on
+pub fn configure_shared_limits(config: SharedLimitConfig) {
+    *SHARED_LIMIT_CONFIG.write().unwrap() = config;
+}
+
+/// The limiter for `provider` used with `credential`. Clients holding the
+/// same key share one budget; the key itself is only kept as a hash.
+pub fn shared_limiter(provider: &str, credential: &str) -> Arc<SharedRateLimiter> {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    credential.hash(&mut hasher);
+    let key = format!("{}:{:016x}", provider, hasher.finish());
+
+    SHARED_LIMITERS
+        .lock()
+        .unwrap()
+        .entry(key)
+        .or_insert_with(|| {
+            Arc::new(SharedRateLimiter::new(
+                SHARED_LIMIT_CONFIG.read().unwrap().clone(),
+            ))
+        })
+        .clone()
+}
+
 /// Result of rate limit check
 #[derive(Debug, Clone)]
 pub enum RateLimitResult {
@@ -355,4 +596,77 @@ mod tests {
         limiter.reset("test");
         assert!(limiter.try_acquire("test"));
     }
+
+    fn shared(requests_per_minute: f64) -> Arc<SharedRateLimiter> {
+        Arc::new(SharedRateLimiter::new(SharedLimitConfig {
+            requests_per_minute,
+            burst: 1.0,
+            background_share: 0.3,
+            backoff: Duration::from_secs(60),
+        }))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_interactive_served_before_queued_background() {
+        let limiter = shared(60.0);
+        limiter.acquire(Priority::Interactive).await;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for i in 0..3 {
+            let (limiter, order) = (limiter.clone(), order.clone());
+            tasks.push(tokio::spawn(async move {
+                limiter.acquire(Priority::Background).await;
+                order.lock().unwrap().push(format!("background {}", i));
+            }));
+        }
+
+        // Arrives while the background requests are already queued
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let (l, o) = (limiter.clone(), order.clone());
+        tasks.push(tokio::spawn(async move {
+            l.acquire(Priority::Interactive).await;
+            o.lock().unwrap().push("interactive".to_string());
+        }));
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+        let order = order.lock().unwrap();
+        assert_eq!(order.len(), 4);
+        assert_eq!(order[0], "interactive");
+        assert!(limiter.throttled_for(Priority::Background) > Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limited_response_shrinks_budget() {
+        let limiter = shared(120.0);
+        let issued_within = |limiter: Arc<SharedRateLimiter>, window: Duration| async move {
+            let deadline = tokio::time::Instant::now() + window;
+            let mut issued = 0;
+            while tokio::time::timeout_at(deadline, limiter.acquire(Priority::Interactive))
+                .await
+                .is_ok()
+            {
+                issued += 1;
+            }
+            issued
+        };
+
+        let before = issued_within(limiter.clone(), Duration::from_secs(10)).await;
+        limiter.report_rate_limited();
+        assert_eq!(limiter.throttle_factor(), 0.5);
+        let after = issued_within(limiter.clone(), Duration::from_secs(10)).await;
+
+        assert!(before >= 20, "before: {}", before);
+        assert!(after <= before / 2 + 1, "before: {}, after: {}", before, after);
+    }
+
+    #[test]
+    fn test_shared_limiter_keyed_by_credential() {
+        let a = shared_limiter("openai", "sk-one");
+        assert!(Arc::ptr_eq(&a, &shared_limiter("openai", "sk-one")));
+        assert!(!Arc::ptr_eq(&a, &shared_limiter("openai", "sk-two")));
+        assert!(!Arc::ptr_eq(&a, &shared_limiter("anthropic", "sk-one")));
+    }
 }
@@ -5,7 +5,9 @@
 
 use anyhow::{anyhow, Result};
 use std::collections::HashSet;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+
+use crate::util::diff::{unified, DiffStyle};
 
 /// Security configuration for Webrana CLI
 #[derive(Debug, Clone)]
@@ -30,6 +32,12 @@ pub struct SecurityConfig {
 
     /// Require confirmation for destructive operations
     pub require_confirmation: bool,
+
+    /// Globs (relative to `working_dir`) that file writes must match; empty allows all
+    pub write_allow: Vec<String>,
+
+    /// Globs that file writes must not match; takes precedence over `write_allow`
+    pub write_deny: Vec<String>,
 }
 
 impl Default for SecurityConfig {
@@ -80,6 +88,8 @@ impl Default for SecurityConfig {
             sensitive_files,
             max_file_size: 10 * 1024 * 1024, // 10MB
             require_confirmation: true,
+            write_allow: Vec::new(),
+            write_deny: Vec::new(),
         }
     }
 }
@@ -109,12 +119,12 @@ impl InputSanitizer {
             self.config.working_dir.join(path)
         };
 
-        // Canonicalize to resolve .. and symlinks
-        let canonical = absolute.canonicalize().unwrap_or_else(|_| absolute.clone());
+        // Resolve .. and symlinks, also for a file not created yet
+        let canonical = resolve_existing(&absolute);
 
         // Check if within working directory (unless global access allowed)
         if !self.config.allow_global_access {
-            if !canonical.starts_with(&self.config.working_dir) {
+            if !canonical.starts_with(resolve_existing(&self.config.working_dir)) {
                 return Err(anyhow!(
                     "Access denied: path '{}' is outside working directory",
                     path.display()
@@ -136,6 +146,41 @@ impl InputSanitizer {
         Ok(canonical)
     }
 
+    /// Validate a path about to be written or deleted against the
+    /// `write_allow`/`write_deny` globs, on top of `validate_path`
+    pub fn validate_write_path(&self, path: &str) -> Result<PathBuf> {
+        let resolved = self.validate_path(path)?;
+        let relative = resolved
+            .strip_prefix(resolve_existing(&self.config.working_dir))
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .map_err(|_| anyhow!("Write denied: '{}' is outside the working directory", path))?;
+
+        if let Some(glob) = self
+            .config
+            .write_deny
+            .iter()
+            .find(|g| glob_match(g, &relative))
+        {
+            return Err(anyhow!(
+                "Write denied: '{}' matches --deny-paths '{}'",
+                relative,
+                glob
+            ));
+        }
+
+        if !self.config.write_allow.is_empty()
+            && !self.config.write_allow.iter().any(|g| glob_match(g, &relative))
+        {
+            return Err(anyhow!(
+                "Write denied: '{}' is outside --allow-paths ({})",
+                relative,
+                self.config.write_allow.join(", ")
+            ));
+        }
+
+        Ok(resolved)
+    }
+
     /// Validate a shell command
     pub fn validate_command(&self, command: &str) -> Result<CommandRisk> {
         let command_lower = command.to_lowercase();
@@ -364,8 +409,13 @@ impl ConfirmationPrompt {
     pub fn confirm(message: &str) -> bool {
         use std::io::{self, Write};
 
-        print!("\n⚠️  {} [y/N]: ", message);
-        io::stdout().flush().unwrap();
+        // Keep stdout clean while it carries `--json-stream` events
+        if crate::llm::events::is_active() {
+            eprint!("\n⚠️  {} [y/N]: ", message);
+        } else {
+            print!("\n⚠️  {} [y/N]: ", message);
+            io::stdout().flush().unwrap();
+        }
 
         let mut input = String::new();
         io::stdin().read_line(&mut input).unwrap();
@@ -392,6 +442,204 @@ impl ConfirmationPrompt {
     pub fn confirm_delete(path: &str) -> bool {
         Self::confirm(&format!("DELETE file: {}? This cannot be undone", path))
     }
+
+    /// The change a `write_file` or `edit_file` call would make, as a
+    /// unified diff against the file on disk
+    pub fn write_preview(tool: &str, arguments: &serde_json::Value, style: DiffStyle) -> Option<String> {
+        let path = arguments.get("path")?.as_str()?;
+        let current = std::fs::read_to_string(path);
+        let (old, new) = match tool {
+            "write_file" => {
+                let content = arguments.get("content")?.as_str()?;
+                (current.unwrap_or_default(), content.to_string())
+            }
+            "edit_file" => {
+                let search = arguments.get("search")?.as_str()?;
+                let replace = arguments.get("replace")?.as_str()?;
+                let current = current.ok()?;
+                if !current.contains(search) {
+                    return Some(format!("{}: search string not found", path));
+                }
+                let edited = current.replace(search, replace);
+                (current, edited)
+            }
+            _ => return None,
+        };
+        let diff = unified(&old, &new, path, style);
+        Some(if diff.is_empty() {
+            format!("{}: no changes", path)
+        } else {
+            diff.trim_end().to_string()
+        })
+    }
+
+    /// Show a tool call and ask what to do with it (`run --confirm-each`)
+    pub fn step(tool: &str, arguments: &serde_json::Value) -> StepDecision {
+        let stdin = std::io::stdin();
+        Self::step_with(&mut stdin.lock(), &mut std::io::stdout(), tool, arguments)
+    }
+
+    /// [`step`](Self::step) over any input and output. End of input aborts.
+    pub fn step_with(
+        input: &mut dyn std::io::BufRead,
+        out: &mut dyn std::io::Write,
+        tool: &str,
+        arguments: &serde_json::Value,
+    ) -> StepDecision {
+        match Self::write_preview(tool, arguments, DiffStyle::terminal()) {
+            Some(preview) => {
+                let _ = writeln!(out, "\n⏸  Next tool call: {}\n{}", tool, preview);
+            }
+            None => {
+                let pretty = serde_json::to_string_pretty(arguments).unwrap_or_default();
+                let _ = writeln!(out, "\n⏸  Next tool call: {}\n{}", tool, pretty);
+            }
+        }
+
+        loop {
+            let _ = write!(out, "[c]ontinue / [s]kip / [a]bort / [e]dit: ");
+            let _ = out.flush();
+            let mut line = String::new();
+            if input.read_line(&mut line).unwrap_or(0) == 0 {
+                return StepDecision::Abort;
+            }
+            match line.trim().to_lowercase().as_str() {
+                "c" | "continue" | "" => return StepDecision::Continue,
+                "s" | "skip" => return StepDecision::Skip,
+                "a" | "abort" => return StepDecision::Abort,
+                "e" | "edit" => {
+                    let _ = write!(out, "New arguments as one line of JSON: ");
+                    let _ = out.flush();
+                    let mut json = String::new();
+                    if input.read_line(&mut json).unwrap_or(0) == 0 {
+                        return StepDecision::Abort;
+                    }
+                    match serde_json::from_str(json.trim()) {
+                        Ok(edited) => return StepDecision::Edit(edited),
+                        Err(e) => {
+                            let _ = writeln!(out, "Invalid JSON: {}", e);
+                        }
+                    }
+                }
+                other => {
+                    let _ = writeln!(out, "Unknown choice '{}'", other);
+                }
+            }
+        }
+    }
+}
+
+/// What the user chose for one tool call under `run --confirm-each`
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepDecision {
+    Continue,
+    /// Don't run the call; the model is told it was skipped
+    Skip,
+    /// Stop the run
+    Abort,
+    /// Run the call with these arguments instead
+    Edit(serde_json::Value),
+}
+
+impl StepDecision {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Continue => "continue",
+            Self::Skip => "skip",
+            Self::Abort => "abort",
+            Self::Edit(_) => "edit",
+        }
+    }
+}
+
+/// Asked before each tool call with the tool name and arguments
+pub type Stepper = std::sync::Arc<dyn Fn(&str, &serde_json::Value) -> StepDecision + Send + Sync>;
+
+/// Error returned when the user aborts a step-through run
+#[derive(Debug)]
+pub struct StepAborted;
+
+impl std::fmt::Display for StepAborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Run aborted by user")
+    }
+}
+
+impl std::error::Error for StepAborted {}
+
+/// Match a `/`-separated relative path against a glob. `*` and `?` stay
+/// within one segment, `**` spans segments, and a pattern without wildcards
+/// also matches everything below it (`src` is `src/**`).
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+    // A trailing `/**` (explicit or implied) also covers the directory itself
+    let (body, subtree) = match pattern.strip_suffix("/**") {
+        Some(body) => (body, true),
+        None if !pattern.contains(['*', '?']) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut regex = String::from("^");
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    if subtree {
+        regex.push_str("(?:/.*)?");
+    }
+    regex.push('$');
+
+    regex::Regex::new(&regex)
+        .map(|re| re.is_match(path))
+        .unwrap_or(false)
+}
+
+/// Lexically resolve `.` and `..`, for paths that don't exist yet
+/// `path` with `..` removed and symlinks followed for the part that
+/// already exists, so a file about to be created under a symlinked
+/// directory resolves to where it will really be written
+pub fn resolve_existing(path: &Path) -> PathBuf {
+    let normalized = normalize(path);
+    let existing = normalized
+        .ancestors()
+        .find(|p| p.exists())
+        .and_then(|p| p.canonicalize().ok().map(|c| (p.to_path_buf(), c)));
+    match existing {
+        Some((prefix, canonical)) => match normalized.strip_prefix(&prefix) {
+            // Joining an empty rest would add a trailing separator
+            Ok(rest) if rest.as_os_str().is_empty() => canonical,
+            Ok(rest) => canonical.join(rest),
+            Err(_) => normalized,
+        },
+        None => normalized,
+    }
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
 }
 
 #[cfg(test)]
@@ -432,4 +680,101 @@ mod tests {
         assert!(sanitizer.validate_path("/etc/passwd").is_err());
         assert!(sanitizer.validate_path("~/.ssh/id_rsa").is_err());
     }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("src/**", "src/x.rs"));
+        assert!(glob_match("src/**", "src/a/b/c.rs"));
+        assert!(glob_match("src", "src/x.rs"));
+        assert!(glob_match("**/*.rs", "x.rs"));
+        assert!(glob_match("**/*.rs", "src/a/x.rs"));
+        assert!(glob_match("tests/*.rs", "tests/cli_test.rs"));
+        assert!(!glob_match("tests/*.rs", "tests/data/x.rs"));
+        assert!(!glob_match("src/**", "README.md"));
+        assert!(!glob_match("src/**", "srcx/main.rs"));
+    }
+
+    #[test]
+    fn test_write_path_allow_and_deny() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = SecurityConfig {
+            working_dir: dir.path().canonicalize().unwrap(),
+            write_allow: vec!["src/**".to_string()],
+            write_deny: vec!["src/generated/**".to_string()],
+            ..Default::default()
+        };
+        let sanitizer = InputSanitizer::new(config);
+
+        assert!(sanitizer.validate_write_path("src/x.rs").is_ok());
+        let err = sanitizer.validate_write_path("README.md").unwrap_err();
+        assert!(err.to_string().contains("outside --allow-paths (src/**)"));
+        // Deny wins over allow
+        let err = sanitizer.validate_write_path("src/generated/api.rs").unwrap_err();
+        assert!(err.to_string().contains("--deny-paths"));
+        // `..` can't be used to step out of the allowed tree
+        assert!(sanitizer.validate_write_path("src/../README.md").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_path_follows_symlinked_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("src"), dir.path().join("alias")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("escape")).unwrap();
+        let config = SecurityConfig {
+            working_dir: dir.path().canonicalize().unwrap(),
+            write_deny: vec!["src/**".to_string()],
+            ..Default::default()
+        };
+        let sanitizer = InputSanitizer::new(config);
+
+        // A new file under a link to src/ is still in src/
+        let err = sanitizer.validate_write_path("alias/new.rs").unwrap_err();
+        assert!(err.to_string().contains("'src/new.rs' matches --deny-paths"), "{}", err);
+        // A link out of the project doesn't pass the working-dir check
+        let err = sanitizer.validate_write_path("escape/new.rs").unwrap_err();
+        assert!(err.to_string().contains("outside working directory"), "{}", err);
+        assert!(sanitizer.validate_write_path("docs/new.md").is_ok());
+    }
+
+    #[test]
+    fn test_step_prompt_reads_decisions() {
+        let args = serde_json::json!({"path": "src/main.rs"});
+        let step = |script: &str| {
+            let mut out = Vec::new();
+            let decision = ConfirmationPrompt::step_with(&mut script.as_bytes(), &mut out, "read_file", &args);
+            (decision, String::from_utf8(out).unwrap())
+        };
+
+        let (decision, out) = step("c\n");
+        assert_eq!(decision, StepDecision::Continue);
+        assert!(out.contains("Next tool call: read_file"));
+        assert!(out.contains("\"path\": \"src/main.rs\""));
+        assert_eq!(step("x\ns\n").0, StepDecision::Skip);
+        assert_eq!(step("abort\n").0, StepDecision::Abort);
+        assert_eq!(step("").0, StepDecision::Abort);
+
+        let (decision, out) = step("e\nnot json\ne\n{\"path\": \"README.md\"}\n");
+        assert_eq!(decision, StepDecision::Edit(serde_json::json!({"path": "README.md"})));
+        assert!(out.contains("Invalid JSON"));
+    }
+
+    #[test]
+    fn test_write_preview_is_a_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let args = serde_json::json!({"path": path, "search": "two", "replace": "three"});
+        let preview = ConfirmationPrompt::write_preview("edit_file", &args, DiffStyle::default()).unwrap();
+        assert!(preview.contains("\n-two\n+three"), "{}", preview);
+
+        let args = serde_json::json!({"path": path, "content": "one\ntwo\n"});
+        let preview = ConfirmationPrompt::write_preview("write_file", &args, DiffStyle::default()).unwrap();
+        assert!(preview.ends_with("no changes"));
+        assert!(ConfirmationPrompt::write_preview("read_file", &args, DiffStyle::default()).is_none());
+    }
 }
@@ -0,0 +1,260 @@
+// ============================================
+// WEBRANA CLI - Scan Checkpoints
+// `scan --resume`: which files a directory scan has done and what it found
+// so far, saved as it goes so an interrupted scan picks up where it stopped
+// ============================================
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::secrets::{DetectedSecret, SecretScanner};
+use crate::util::hash;
+
+/// Save after this many newly scanned files...
+pub const CHECKPOINT_EVERY: usize = 200;
+
+/// ...or this long since the last save, whichever comes first
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Progress of one directory scan
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanCheckpoint {
+    /// Of the scanner config and the file set; see [`fingerprint`]
+    pub fingerprint: String,
+    /// Paths relative to the scanned directory
+    pub scanned: BTreeSet<String>,
+    pub findings: Vec<DetectedSecret>,
+}
+
+impl ScanCheckpoint {
+    /// The checkpoint at `path` if it matches `fingerprint`; Err(stale) when
+    /// one exists for a different config or file set
+    pub fn load(path: &Path, fingerprint: &str) -> Result<Option<Self>, Stale> {
+        let Ok(text) = std::fs::read_to_string(path) else { return Ok(None) };
+        match serde_json::from_str::<Self>(&text) {
+            Ok(checkpoint) if checkpoint.fingerprint == fingerprint => Ok(Some(checkpoint)),
+            _ => Err(Stale),
+        }
+    }
+
+    /// Replace the file at `path` in one rename, so an interruption never
+    /// leaves half a checkpoint
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, serde_json::to_vec(self)?)
+            .with_context(|| format!("Failed to write {}", partial.display()))?;
+        std::fs::rename(&partial, path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// A checkpoint for another config or file set, which is discarded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stale;
+
+/// Where the checkpoint of scanning `dir` lives: the system temp directory,
+/// one file per scanned directory
+pub fn default_path(dir: &Path) -> PathBuf {
+    std::env::temp_dir().join(format!("webrana-scan-{}.json", hash::dir_key(dir)))
+}
+
+/// Changes with the scanner version and config, and with any file added,
+/// removed, resized or modified
+pub fn fingerprint(scanner: &SecretScanner, dir: &Path, files: &[PathBuf]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION"));
+    hasher.update(format!("{:?}", scanner.config()));
+    for file in files {
+        let meta = std::fs::metadata(file).ok();
+        let modified = meta
+            .as_ref()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_nanos());
+        hasher.update(format!(
+            "\n{}\t{}\t{}",
+            relative(dir, file),
+            meta.map_or(0, |m| m.len()),
+            modified
+        ));
+    }
+    hash::hex(&hasher.finalize())
+}
+
+fn relative(dir: &Path, file: &Path) -> String {
+    file.strip_prefix(dir).unwrap_or(file).to_string_lossy().into_owned()
+}
+
+/// How a checkpointed scan started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeFrom {
+    /// No checkpoint
+    Start,
+    /// A checkpoint for another config or file set was discarded
+    Stale,
+    /// Picked up with this many files already scanned
+    Checkpoint(usize),
+}
+
+/// `scan_directory` that skips files recorded in the checkpoint at
+/// `checkpoint_path` and saves progress as it goes. `on_file(done, total)`
+/// runs after each file and may stop the scan by failing; the checkpoint is
+/// saved first. The checkpoint is removed once the scan finishes.
+pub fn scan_directory_resumable(
+    scanner: &SecretScanner,
+    dir: &Path,
+    checkpoint_path: &Path,
+    on_start: &mut dyn FnMut(ResumeFrom),
+    on_file: &mut dyn FnMut(usize, usize) -> Result<()>,
+) -> Result<Vec<DetectedSecret>> {
+    let files = scanner.list_files(dir)?;
+    let fingerprint = fingerprint(scanner, dir, &files);
+    let mut checkpoint = match ScanCheckpoint::load(checkpoint_path, &fingerprint) {
+        Ok(Some(checkpoint)) => {
+            on_start(ResumeFrom::Checkpoint(checkpoint.scanned.len()));
+            checkpoint
+        }
+        Ok(None) => {
+            on_start(ResumeFrom::Start);
+            ScanCheckpoint { fingerprint, ..Default::default() }
+        }
+        Err(Stale) => {
+            on_start(ResumeFrom::Stale);
+            ScanCheckpoint { fingerprint, ..Default::default() }
+        }
+    };
+
+    let (mut unsaved, mut last_save) = (0, Instant::now());
+    for file in &files {
+        let key = relative(dir, file);
+        if checkpoint.scanned.contains(&key) {
+            continue;
+        }
+        match scanner.scan_file(file) {
+            Ok(found) => checkpoint.findings.extend(found),
+            Err(e) => tracing::debug!("Failed to scan {}: {}", file.display(), e),
+        }
+        checkpoint.scanned.insert(key);
+        unsaved += 1;
+        if unsaved >= CHECKPOINT_EVERY || last_save.elapsed() >= CHECKPOINT_INTERVAL {
+            checkpoint.save(checkpoint_path)?;
+            (unsaved, last_save) = (0, Instant::now());
+        }
+        if let Err(e) = on_file(checkpoint.scanned.len(), files.len()) {
+            checkpoint.save(checkpoint_path)?;
+            return Err(e);
+        }
+    }
+
+    let _ = std::fs::remove_file(checkpoint_path);
+    let mut findings = checkpoint.findings;
+    findings.sort_by_key(|s| std::cmp::Reverse(s.severity));
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ScannerConfig;
+
+    fn fixture() -> tempfile::TempDir {
+        let tmp = tempfile::TempDir::new().unwrap();
+        for i in 0..12 {
+            let dir = tmp.path().join(format!("pkg{}", i % 3));
+            std::fs::create_dir_all(&dir).unwrap();
+            let content = if i % 2 == 0 {
+                format!("API_KEY = \"sk-{:0>30}\"\npassword = \"hunter{:04}\"\n", i, i)
+            } else {
+                format!("fn f{}() {{}}\n", i)
+            };
+            std::fs::write(dir.join(format!("file{}.rs", i)), content).unwrap();
+        }
+        tmp
+    }
+
+    fn keys(findings: &[DetectedSecret]) -> Vec<String> {
+        let mut keys: Vec<String> = findings
+            .iter()
+            .map(|s| format!("{}:{}:{}:{:?}", s.file, s.line, s.column, s.secret_type))
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_interrupted_scan_resumes_to_same_findings() {
+        let tmp = fixture();
+        let scanner = SecretScanner::new(ScannerConfig::default());
+        let uninterrupted = scanner.scan_directory(tmp.path()).unwrap();
+        assert!(!uninterrupted.is_empty());
+
+        let state = tempfile::TempDir::new().unwrap();
+        let checkpoint = state.path().join("checkpoint/scan.json");
+        let interrupted = scan_directory_resumable(&scanner, tmp.path(), &checkpoint, &mut |_| {}, &mut |done, _| {
+            if done == 5 {
+                anyhow::bail!("interrupted")
+            }
+            Ok(())
+        });
+        assert!(interrupted.is_err());
+        let saved: ScanCheckpoint = serde_json::from_str(&std::fs::read_to_string(&checkpoint).unwrap()).unwrap();
+        assert_eq!(saved.scanned.len(), 5);
+
+        let (mut started, mut rescanned) = (None, 0);
+        let resumed = scan_directory_resumable(
+            &scanner,
+            tmp.path(),
+            &checkpoint,
+            &mut |from| started = Some(from),
+            &mut |_, _| {
+                rescanned += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(started, Some(ResumeFrom::Checkpoint(5)));
+        assert_eq!(rescanned, 7);
+        assert_eq!(keys(&resumed), keys(&uninterrupted));
+        assert!(!checkpoint.exists());
+    }
+
+    #[test]
+    fn test_checkpoint_invalidated_by_config_or_file_change() {
+        let tmp = fixture();
+        let scanner = SecretScanner::new(ScannerConfig::default());
+        let state = tempfile::TempDir::new().unwrap();
+        let checkpoint = state.path().join("scan.json");
+        let interrupt = &mut |done: usize, _: usize| if done == 3 { anyhow::bail!("stop") } else { Ok(()) };
+        let mut started = None;
+
+        assert!(scan_directory_resumable(&scanner, tmp.path(), &checkpoint, &mut |_| {}, interrupt).is_err());
+        let stricter = SecretScanner::new(ScannerConfig {
+            min_severity: crate::core::SecretSeverity::High,
+            ..Default::default()
+        });
+        scan_directory_resumable(&stricter, tmp.path(), &checkpoint, &mut |from| started = Some(from), &mut |_, _| {
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(started, Some(ResumeFrom::Stale));
+
+        assert!(scan_directory_resumable(&scanner, tmp.path(), &checkpoint, &mut |_| {}, interrupt).is_err());
+        std::fs::write(tmp.path().join("pkg0/new.env"), "SECRET=abcdefghijklmnopqrstuv\n").unwrap();
+        let findings = scan_directory_resumable(
+            &scanner,
+            tmp.path(),
+            &checkpoint,
+            &mut |from| started = Some(from),
+            &mut |_, _| Ok(()),
+        )
+        .unwrap();
+        assert_eq!(started, Some(ResumeFrom::Stale));
+        assert_eq!(keys(&findings), keys(&scanner.scan_directory(tmp.path()).unwrap()));
+    }
+}
@@ -0,0 +1,237 @@
+// ============================================
+// WEBRANA CLI - Tool Output Scrubbing
+// Terminal escapes and control characters removed before tool results
+// reach the model or are echoed to the terminal
+// ============================================
+
+use super::audit::{AuditEvent, AuditEventType, AuditSeverity, AUDIT};
+
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{07}';
+
+/// Sequences that have no business in tool output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Suspicious {
+    /// OSC 0/1/2: window or icon title change
+    TitleChange,
+    /// Any other OSC or DCS/PM/APC string (clipboard, palette, ...)
+    OperatingSystemCommand,
+    /// CSI other than SGR colors, or a bare escape: cursor movement,
+    /// screen clearing, terminal resets
+    CursorControl,
+    /// C0 (other than tab/newline/carriage return) or C1 control
+    ControlCharacter,
+}
+
+impl Suspicious {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Suspicious::TitleChange => "title change",
+            Suspicious::OperatingSystemCommand => "OSC sequence",
+            Suspicious::CursorControl => "cursor/screen control",
+            Suspicious::ControlCharacter => "control character",
+        }
+    }
+}
+
+/// One tool result in its two cleaned forms
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scrubbed {
+    /// Plain text for the model (and the history the compactor measures)
+    pub model: String,
+    /// Text to echo; keeps SGR colors when asked to
+    pub display: String,
+    /// Kinds of dangerous sequences found, sorted, without duplicates
+    pub suspicious: Vec<Suspicious>,
+}
+
+impl Scrubbed {
+    pub fn is_suspicious(&self) -> bool {
+        !self.suspicious.is_empty()
+    }
+}
+
+/// Clean decoded `text`. SGR color codes are removed from the model text,
+/// and from the display text unless `keep_colors`. OSC-8 hyperlinks keep
+/// their visible text. Everything else that drives the terminal is
+/// removed, and stray control characters are escaped as `\u{..}`, in both.
+pub fn scrub(text: &str, keep_colors: bool) -> Scrubbed {
+    let mut model = String::with_capacity(text.len());
+    let mut display = String::with_capacity(text.len());
+    let mut suspicious = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ESC => match chars.peek().copied() {
+                Some('[') => {
+                    chars.next();
+                    let mut sequence = String::from("\u{1b}[");
+                    let mut final_byte = None;
+                    for next in chars.by_ref() {
+                        sequence.push(next);
+                        if ('\u{40}'..='\u{7e}').contains(&next) {
+                            final_byte = Some(next);
+                            break;
+                        }
+                    }
+                    if final_byte == Some('m') {
+                        if keep_colors {
+                            display.push_str(&sequence);
+                        }
+                    } else {
+                        suspicious.push(Suspicious::CursorControl);
+                    }
+                }
+                Some(']') => {
+                    chars.next();
+                    let body = read_string(&mut chars);
+                    let command = body.split(';').next().unwrap_or_default();
+                    match command {
+                        // Hyperlink open/close; the link text follows as plain text
+                        "8" => {}
+                        "0" | "1" | "2" => suspicious.push(Suspicious::TitleChange),
+                        _ => suspicious.push(Suspicious::OperatingSystemCommand),
+                    }
+                }
+                Some('P' | 'X' | '^' | '_') => {
+                    chars.next();
+                    read_string(&mut chars);
+                    suspicious.push(Suspicious::OperatingSystemCommand);
+                }
+                _ => {
+                    // Two-character escapes (ESC c, ESC 7, ...)
+                    chars.next();
+                    suspicious.push(Suspicious::CursorControl);
+                }
+            },
+            '\t' | '\n' | '\r' => {
+                model.push(c);
+                display.push(c);
+            }
+            c if c.is_control() => {
+                let escaped = format!("\\u{{{:x}}}", c as u32);
+                model.push_str(&escaped);
+                display.push_str(&escaped);
+                suspicious.push(Suspicious::ControlCharacter);
+            }
+            c => {
+                model.push(c);
+                display.push(c);
+            }
+        }
+    }
+
+    suspicious.sort();
+    suspicious.dedup();
+    Scrubbed {
+        model,
+        display,
+        suspicious,
+    }
+}
+
+/// Read an OSC/DCS body up to its terminator (BEL or ESC \)
+fn read_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut body = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            BEL => break,
+            ESC if chars.peek() == Some(&'\\') => {
+                chars.next();
+                break;
+            }
+            c => body.push(c),
+        }
+    }
+    body
+}
+
+/// [`scrub`] a tool result, auditing and logging anything suspicious
+pub fn scrub_tool_output(tool: &str, text: &str, keep_colors: bool) -> Scrubbed {
+    let scrubbed = scrub(text, keep_colors);
+    if scrubbed.is_suspicious() {
+        let kinds: Vec<&str> = scrubbed.suspicious.iter().map(|s| s.label()).collect();
+        tracing::debug!("Removed {} from the output of {}", kinds.join(", "), tool);
+        AUDIT.log(
+            AuditEvent::new(
+                AuditEventType::SecurityViolation,
+                AuditSeverity::Warning,
+                format!("Terminal control sequences in {} output", tool),
+            )
+            .with_details(serde_json::json!({ "tool": tool, "sequences": kinds })),
+        );
+    }
+    scrubbed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SGR: &str = "\u{1b}[1m\u{1b}[32m   Compiling\u{1b}[0m webrana v0.4.0\n";
+    const OSC8: &str = "see \u{1b}]8;;https://example.com/docs\u{1b}\\the docs\u{1b}]8;;\u{1b}\\ here";
+    const TITLE: &str = "line 1\u{1b}]0;pwned\u{07}\nline 2";
+    const C1: &str = "before\u{9b}2Jafter";
+
+    #[test]
+    fn test_sgr_colors() {
+        let stripped = scrub(SGR, false);
+        assert_eq!(stripped.model, "   Compiling webrana v0.4.0\n");
+        assert_eq!(stripped.display, stripped.model);
+        assert!(!stripped.is_suspicious());
+
+        let kept = scrub(SGR, true);
+        assert_eq!(kept.model, "   Compiling webrana v0.4.0\n");
+        assert_eq!(kept.display, SGR);
+    }
+
+    #[test]
+    fn test_osc8_hyperlink_keeps_text() {
+        let scrubbed = scrub(OSC8, true);
+        assert_eq!(scrubbed.model, "see the docs here");
+        assert_eq!(scrubbed.display, "see the docs here");
+        assert!(!scrubbed.is_suspicious());
+    }
+
+    #[test]
+    fn test_title_change_and_cursor_control() {
+        let scrubbed = scrub(TITLE, true);
+        assert_eq!(scrubbed.model, "line 1\nline 2");
+        assert_eq!(scrubbed.display, "line 1\nline 2");
+        assert_eq!(scrubbed.suspicious, vec![Suspicious::TitleChange]);
+
+        let scrubbed = scrub("ok\u{1b}[2J\u{1b}[H\u{1b}cdone\u{07}\u{07}", true);
+        assert_eq!(scrubbed.display, "okdone\\u{7}\\u{7}");
+        assert_eq!(
+            scrubbed.suspicious,
+            vec![Suspicious::CursorControl, Suspicious::ControlCharacter]
+        );
+    }
+
+    #[test]
+    fn test_c1_control_is_escaped() {
+        let scrubbed = scrub(C1, true);
+        assert_eq!(scrubbed.model, "before\\u{9b}2Jafter");
+        assert_eq!(scrubbed.display, scrubbed.model);
+        assert_eq!(scrubbed.suspicious, vec![Suspicious::ControlCharacter]);
+    }
+
+    #[test]
+    fn test_plain_text_untouched() {
+        let text = "tab\tseparated\r\nunicode: héllo ✓\n";
+        let scrubbed = scrub(text, false);
+        assert_eq!(scrubbed.model, text);
+        assert_eq!(scrubbed.display, text);
+    }
+
+    #[test]
+    fn test_suspicious_output_is_audited() {
+        let scrubbed = scrub_tool_output("scrub_test_tool", TITLE, false);
+        assert!(scrubbed.is_suspicious());
+        assert!(AUDIT
+            .events_by_type(AuditEventType::SecurityViolation)
+            .iter()
+            .any(|e| e.message == "Terminal control sequences in scrub_test_tool output"));
+    }
+}
@@ -0,0 +1,257 @@
+// ============================================
+// WEBRANA CLI - Secret History Scan
+// Finds secrets in the lines each commit added, back to where they came in
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::Stdio;
+
+use super::binaries;
+use super::secrets::{render_report, DetectedSecret, ScanSummary, SecretScanner, SecretType};
+
+/// Starts each commit header line of the `git log` output (`%x00`)
+const COMMIT_MARK: char = '\0';
+
+/// A secret and the commit that introduced it
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryFinding {
+    pub commit: String,
+    pub author: String,
+    /// Author date, ISO 8601
+    pub date: String,
+    /// `file` and `line` are in the introducing commit's version of the file
+    #[serde(flatten)]
+    pub secret: DetectedSecret,
+}
+
+impl HistoryFinding {
+    /// `abc1234 by Alice on 2026-01-02`
+    pub fn introduced(&self) -> String {
+        format!(
+            "{} by {} on {}",
+            &self.commit[..self.commit.len().min(7)],
+            self.author,
+            self.date.split('T').next().unwrap_or_default()
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HistoryScan {
+    /// Commits whose diffs were read
+    pub commits: usize,
+    /// Oldest introduction first
+    pub findings: Vec<HistoryFinding>,
+}
+
+struct CommitInfo {
+    hash: String,
+    author: String,
+    date: String,
+}
+
+/// Scan the lines added by every commit reachable from HEAD, oldest first,
+/// or only those after `since` (a ref). Merge commits add nothing of their
+/// own and are skipped. A secret is reported once, at the commit that first
+/// added it, however often it is re-added or moved later.
+pub fn scan_git_history(scanner: &SecretScanner, repo: &Path, since: Option<&str>) -> Result<HistoryScan> {
+    let range = match since {
+        Some(since) => format!("{}..HEAD", since),
+        None => "HEAD".to_string(),
+    };
+    let mut child = binaries::git_command()?
+        .arg("-C")
+        .arg(repo)
+        .args(["-c", "core.quotePath=false", "log", "--reverse", "--no-color", "--no-ext-diff"])
+        .args(["--no-textconv", "-M", "--unified=0", "-p"])
+        .arg("--format=%x00%H%x1f%an%x1f%aI")
+        .arg(&range)
+        .arg("--")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute git log")?;
+
+    let mut scan = HistoryScan::default();
+    let mut seen: HashSet<(SecretType, String)> = HashSet::new();
+    let mut commit: Option<CommitInfo> = None;
+    // Path being added to, and the new-file number of the next added line
+    let mut file: Option<String> = None;
+    let mut next_line = 0;
+    let mut in_hunk = false;
+
+    let mut reader = BufReader::new(child.stdout.take().context("git log has no output")?);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        let raw = String::from_utf8_lossy(&buf);
+        let line = raw.trim_end_matches(['\n', '\r']);
+
+        if let Some(header) = line.strip_prefix(COMMIT_MARK) {
+            let mut parts = header.split('\x1f');
+            commit = Some(CommitInfo {
+                hash: parts.next().unwrap_or_default().to_string(),
+                author: parts.next().unwrap_or_default().to_string(),
+                date: parts.next().unwrap_or_default().to_string(),
+            });
+            scan.commits += 1;
+            file = None;
+            in_hunk = false;
+        } else if line.starts_with("diff --git ") {
+            file = None;
+            in_hunk = false;
+        } else if let Some(hunk) = line.strip_prefix("@@ ") {
+            next_line = hunk_start(hunk).unwrap_or(1);
+            in_hunk = true;
+        } else if !in_hunk {
+            if let Some(path) = line.strip_prefix("+++ ") {
+                file = new_path(path).filter(|path| scanner.wants_path(Path::new(path)));
+            }
+        } else if let Some(added) = line.strip_prefix('+') {
+            if let (Some(path), Some(info)) = (&file, &commit) {
+                for (secret, value) in scanner.scan_line(added, path, next_line) {
+                    if seen.insert((secret.secret_type, normalized(&value))) {
+                        scan.findings.push(HistoryFinding {
+                            commit: info.hash.clone(),
+                            author: info.author.clone(),
+                            date: info.date.clone(),
+                            secret,
+                        });
+                    }
+                }
+            }
+            next_line += 1;
+        }
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        let mut stderr = String::new();
+        if let Some(mut err) = child.stderr.take() {
+            let _ = err.read_to_string(&mut stderr);
+        }
+        bail!("Git error: {}", stderr.trim());
+    }
+    Ok(scan)
+}
+
+/// A match without quotes and spaces, so `KEY = "x"` re-added as `KEY='x'`
+/// counts as the same secret
+fn normalized(value: &str) -> String {
+    value.chars().filter(|c| !c.is_whitespace() && *c != '"' && *c != '\'').collect()
+}
+
+/// First new-file line of a hunk: `-12,3 +14,2 @@ fn x()` gives 14
+fn hunk_start(hunk: &str) -> Option<usize> {
+    let new = hunk.split_whitespace().find_map(|part| part.strip_prefix('+'))?;
+    new.split(',').next()?.parse().ok()
+}
+
+/// Path of the `+++` side, None for a deleted file
+fn new_path(path: &str) -> Option<String> {
+    let path = path.trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')).unwrap_or(path);
+    Some(path.strip_prefix("b/").unwrap_or(path).to_string())
+}
+
+/// Human-readable `webrana scan --git-history` report
+pub fn history_text_report(scan: &HistoryScan, summary: &ScanSummary, accessible: bool) -> String {
+    if scan.findings.is_empty() {
+        return format!("No secrets found in {} commit(s)\n", scan.commits);
+    }
+    let findings: Vec<(&DetectedSecret, Option<String>)> = scan
+        .findings
+        .iter()
+        .map(|finding| (&finding.secret, Some(finding.introduced())))
+        .collect();
+    render_report(
+        &format!("{} secrets found in {} commit(s) of history", scan.findings.len(), scan.commits),
+        &findings,
+        summary,
+        accessible,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ScannerConfig;
+    use std::fs;
+
+    const KEY: &str = "sk-abcdefghijklmnopqrstuvwxyz123456";
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = binaries::git_command()
+            .unwrap()
+            .args(["-c", "user.name=Alice", "-c", "user.email=alice@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn commit(dir: &Path, message: &str) -> String {
+        git(dir, &["add", "-A"]);
+        git(dir, &["commit", "-qm", message, "--date=2026-01-02T10:00:00+00:00"]);
+        git(dir, &["rev-parse", "HEAD"])
+    }
+
+    #[test]
+    fn test_secret_found_at_introducing_commit() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path();
+        git(dir, &["init", "-q", "-b", "main"]);
+        fs::write(dir.join("README.md"), "# App\n").unwrap();
+        commit(dir, "init");
+        fs::write(dir.join("config.py"), format!("DEBUG = True\nOPENAI_API_KEY = \"{}\"\n", KEY)).unwrap();
+        let introduced = commit(dir, "add config");
+        // Reformatting re-adds the line; the key still dates from the commit above
+        fs::write(dir.join("config.py"), format!("DEBUG = False\nOPENAI_API_KEY = '{}'\n", KEY)).unwrap();
+        commit(dir, "tweak config");
+        fs::write(dir.join("config.py"), "DEBUG = False\nOPENAI_API_KEY = os.environ[\"OPENAI_API_KEY\"]\n").unwrap();
+        commit(dir, "read key from env");
+
+        let scanner = SecretScanner::new(ScannerConfig::default());
+        assert!(scanner.scan_directory(dir).unwrap().is_empty());
+
+        let scan = scan_git_history(&scanner, dir, None).unwrap();
+        assert_eq!(scan.commits, 4);
+        // The key line also matches the generic pattern; both date from `introduced`
+        assert!(scan.findings.iter().all(|f| f.commit == introduced), "{:?}", scan.findings);
+        let openai: Vec<_> = scan.findings.iter().filter(|f| f.secret.secret_type == SecretType::OpenAIKey).collect();
+        assert_eq!(openai.len(), 1);
+        let finding = openai[0];
+        assert_eq!(finding.author, "Alice");
+        assert!(finding.date.starts_with("2026-01-02"));
+        assert_eq!((finding.secret.file.as_str(), finding.secret.line), ("config.py", 2));
+        assert!(!serde_json::to_string(finding).unwrap().contains(KEY));
+
+        let report = history_text_report(&scan, &ScanSummary::from_secrets(std::slice::from_ref(&finding.secret)), false);
+        assert!(report.contains(&format!("Introduced: {} by Alice on 2026-01-02", &introduced[..7])));
+
+        // Only the removal comes after HEAD~1
+        let recent = scan_git_history(&scanner, dir, Some("HEAD~1")).unwrap();
+        assert_eq!((recent.commits, recent.findings.len()), (1, 0));
+    }
+
+    #[test]
+    fn test_hunk_and_path_parsing() {
+        assert_eq!(hunk_start("-12,3 +14,2 @@ fn x()"), Some(14));
+        assert_eq!(hunk_start("-0,0 +1 @@"), Some(1));
+        assert_eq!(new_path("b/src/main.rs").as_deref(), Some("src/main.rs"));
+        assert_eq!(new_path("\"b/with space.env\"").as_deref(), Some("with space.env"));
+        assert_eq!(new_path("/dev/null"), None);
+    }
+}
@@ -0,0 +1,109 @@
+// ============================================
+// WEBRANA CLI - First-Run Setup
+// Detects when no model can be used at all and explains how to get one,
+// instead of failing deep inside a provider with a low-level error
+// ============================================
+
+use anyhow::Result;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::config::{ModelConfig, Settings};
+use crate::llm::webrana::WebranaProvider;
+use crate::util::connectivity;
+
+/// No configured model has an API key, a Webrana login or a running Ollama
+#[derive(Debug)]
+pub struct NoProviderConfigured {
+    pub config_path: Option<PathBuf>,
+}
+
+impl fmt::Display for NoProviderConfigured {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "No model provider is configured. To get started, pick one:")?;
+        writeln!(f)?;
+        writeln!(f, "  1. Set an API key:     export ANTHROPIC_API_KEY=...  (or OPENAI_API_KEY=...)")?;
+        writeln!(f, "  2. Run a local model:  ollama serve && ollama pull llama3, then")?;
+        writeln!(f, "                         webrana config set default_model ollama")?;
+        writeln!(f, "  3. Use the free tier:  webrana login, then add a model with provider = \"webrana\"")?;
+        writeln!(f, "                         and make it default_model")?;
+        match &self.config_path {
+            Some(path) => write!(f, "\nModels are configured in {}", path.display()),
+            None => write!(f, "\nThen run webrana again."),
+        }
+    }
+}
+
+impl std::error::Error for NoProviderConfigured {}
+
+/// Whether `model` could serve a request: it has a key, a login, or its
+/// local server answers
+async fn model_usable(model: &ModelConfig, settings: &Settings) -> bool {
+    match model.provider.as_str() {
+        "anthropic" | "openai" | "openai_compatible" => settings.get_api_key(model).is_some(),
+        "webrana" => WebranaProvider::get_credentials().is_some(),
+        "replay" => true,
+        "ollama" => match connectivity::provider_endpoint(model)
+            .as_ref()
+            .and_then(connectivity::probe_target)
+        {
+            Some((host, port)) => connectivity::reachable(&host, port).await,
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// Ok when at least one configured model is usable. Models with keys are
+/// checked first so a running setup never waits on an Ollama probe.
+pub async fn check_providers(settings: &Settings) -> Result<()> {
+    let mut models: Vec<&ModelConfig> = settings.models.values().collect();
+    models.sort_by_key(|model| model.provider == "ollama");
+    for model in models {
+        if model_usable(model, settings).await {
+            return Ok(());
+        }
+    }
+    Err(NoProviderConfigured {
+        config_path: Settings::config_path().ok(),
+    }
+    .into())
+}
+
+/// The default config with the setup choices explained, written over a
+/// config that was never edited
+pub fn starter_config() -> Result<String> {
+    let header = "\
+# Webrana starter config
+#
+# default_model picks the model sessions use:
+#   \"claude\"  Anthropic; needs ANTHROPIC_API_KEY in the environment
+#   \"gpt\"     OpenAI; needs OPENAI_API_KEY
+#   \"ollama\"  a local model; needs `ollama serve` and `ollama pull llama3`
+# For the free tier run `webrana login` and add a model with provider = \"webrana\".
+# A key can also go in the model's api_key (see `webrana config encrypt`).
+
+";
+    Ok(format!("{}{}", header, toml::to_string_pretty(&Settings::default())?))
+}
+
+/// Whether the file at `path` is still the config written on first run
+pub fn is_untouched_default(path: &Path) -> bool {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return true;
+    };
+    toml::to_string_pretty(&Settings::default()).is_ok_and(|default| default == content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starter_config_parses() {
+        let content = starter_config().unwrap();
+        let settings: Settings = toml::from_str(&content).unwrap();
+        assert_eq!(settings.default_model, Settings::default().default_model);
+        assert!(content.starts_with("# Webrana starter config"));
+    }
+}
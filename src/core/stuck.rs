@@ -0,0 +1,220 @@
+// ============================================
+// WEBRANA CLI - Stuck Model Detection
+// Notices when the autonomous loop gets the same response turn after turn
+// ============================================
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+/// Responses at least this similar (Jaccard over word shingles) count as
+/// repeats, catching trivially reworded ones
+pub const REPEAT_SIMILARITY: f64 = 0.95;
+
+/// Added to the conversation once, when the streak is first reached
+pub const CORRECTIVE_MESSAGE: &str = "You appear to be repeating yourself. \
+    Summarize what is blocking you, then try a different approach.";
+
+/// Words per shingle
+const SHINGLE: usize = 3;
+
+/// What to do after one response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StuckVerdict {
+    /// Carry on
+    Progress,
+    /// Repeated `threshold` times: inject [`CORRECTIVE_MESSAGE`]
+    Correct,
+    /// Still repeating after the correction: stop the run
+    Stalled,
+}
+
+/// Normalized form of one response
+#[derive(Debug, Clone)]
+struct Fingerprint {
+    hash: u64,
+    shingles: HashSet<u64>,
+}
+
+impl Fingerprint {
+    fn new(text: &str, tool_calls: &[String]) -> Self {
+        let normalized = normalize(text, tool_calls);
+        // Case and punctuation don't matter for similarity
+        let lowered = normalized.to_lowercase();
+        let words: Vec<&str> = lowered
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect();
+        let shingles = words
+            .windows(SHINGLE.min(words.len()).max(1))
+            .map(|window| hash(&window.join(" ")))
+            .collect();
+        Self {
+            hash: hash(&normalized),
+            shingles,
+        }
+    }
+
+    fn repeats(&self, other: &Fingerprint) -> bool {
+        self.hash == other.hash || jaccard(&self.shingles, &other.shingles) >= REPEAT_SIMILARITY
+    }
+}
+
+/// Whitespace-collapsed text followed by the sorted tool call signatures
+pub fn normalize(text: &str, tool_calls: &[String]) -> String {
+    let mut calls = tool_calls.to_vec();
+    calls.sort();
+    let mut normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    for call in calls {
+        normalized.push_str(" \u{1f}");
+        normalized.push_str(&call.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+    normalized
+}
+
+fn hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / a.union(b).count() as f64
+}
+
+/// Tracks the streak of repeated responses in one run. The correction is
+/// given once; a repeat right after it, or a later streak, stalls the run.
+#[derive(Debug, Clone)]
+pub struct StuckDetector {
+    threshold: usize,
+    last: Option<Fingerprint>,
+    streak: usize,
+    corrected: bool,
+    /// The previous verdict was [`StuckVerdict::Correct`]
+    awaiting: bool,
+}
+
+impl StuckDetector {
+    /// Flag `threshold` consecutive repeats (at least 2); 0 turns detection off
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold: if threshold == 0 { 0 } else { threshold.max(2) },
+            last: None,
+            streak: 0,
+            corrected: false,
+            awaiting: false,
+        }
+    }
+
+    /// Record one response: its final text and the `name(arguments)`
+    /// signatures of the tool calls it made
+    pub fn observe(&mut self, text: &str, tool_calls: &[String]) -> StuckVerdict {
+        if self.threshold == 0 {
+            return StuckVerdict::Progress;
+        }
+
+        let fingerprint = Fingerprint::new(text, tool_calls);
+        let repeat = self.last.as_ref().is_some_and(|last| fingerprint.repeats(last));
+        self.streak = if repeat { self.streak + 1 } else { 1 };
+        self.last = Some(fingerprint);
+
+        if std::mem::take(&mut self.awaiting) && repeat {
+            return StuckVerdict::Stalled;
+        }
+        if self.streak >= self.threshold {
+            if self.corrected {
+                return StuckVerdict::Stalled;
+            }
+            self.corrected = true;
+            self.awaiting = true;
+            return StuckVerdict::Correct;
+        }
+        StuckVerdict::Progress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(detector: &mut StuckDetector, responses: &[(&str, &[&str])]) -> Vec<StuckVerdict> {
+        responses
+            .iter()
+            .map(|(text, calls)| {
+                let calls: Vec<String> = calls.iter().map(|c| c.to_string()).collect();
+                detector.observe(text, &calls)
+            })
+            .collect()
+    }
+
+    /// A long response with each word distinct
+    fn long_response(changed: Option<usize>) -> String {
+        (0..120)
+            .map(|i| if Some(i) == changed { "reworded".to_string() } else { format!("step{}", i) })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn test_exact_repeats_correct_then_stall() {
+        use StuckVerdict::*;
+        let mut detector = StuckDetector::new(3);
+        let same: (&str, &[&str]) = ("Let me check   the file.", &["read_file({\"path\":\"a.rs\"})"]);
+        let verdicts = run(&mut detector, &[same, ("Let me check the file.\n", same.1), same, same]);
+        assert_eq!(verdicts, vec![Progress, Progress, Correct, Stalled]);
+    }
+
+    #[test]
+    fn test_correction_is_injected_once() {
+        use StuckVerdict::*;
+        let mut detector = StuckDetector::new(3);
+        let a: (&str, &[&str]) = ("same answer", &[]);
+        let b: (&str, &[&str]) = ("something new", &[]);
+        let verdicts = run(&mut detector, &[a, a, a, b, b, b]);
+        // The model moved on after the correction, but a second streak stops the run
+        assert_eq!(verdicts, vec![Progress, Progress, Correct, Progress, Progress, Stalled]);
+    }
+
+    #[test]
+    fn test_near_repeats_count() {
+        use StuckVerdict::*;
+        let mut detector = StuckDetector::new(3);
+        let original = long_response(None);
+        let reworded = long_response(Some(60));
+        let verdicts = run(
+            &mut detector,
+            &[(&original, &[]), (&reworded, &[]), (&original.to_uppercase(), &[])],
+        );
+        assert_eq!(verdicts, vec![Progress, Progress, Correct]);
+
+        // Half the words changed is a different response
+        let mut detector = StuckDetector::new(2);
+        let half: String = original.split(' ').take(60).collect::<Vec<_>>().join(" ");
+        assert_eq!(run(&mut detector, &[(&original, &[]), (&half, &[])]), vec![Progress, Progress]);
+    }
+
+    #[test]
+    fn test_different_tool_calls_are_progress() {
+        let mut detector = StuckDetector::new(2);
+        let verdicts = run(
+            &mut detector,
+            &[
+                ("Reading.", &["read_file({\"path\":\"a.rs\"})"]),
+                ("Reading.", &["read_file({\"path\":\"b.rs\"})"]),
+                ("Reading.", &["read_file({\"path\":\"c.rs\"})"]),
+            ],
+        );
+        assert!(verdicts.iter().all(|v| *v == StuckVerdict::Progress));
+
+        // Call order doesn't matter
+        let mut detector = StuckDetector::new(2);
+        detector.observe("x", &["a()".to_string(), "b()".to_string()]);
+        assert_eq!(detector.observe("x", &["b()".to_string(), "a()".to_string()]), StuckVerdict::Correct);
+
+        let mut off = StuckDetector::new(0);
+        assert!(run(&mut off, &[("a", &[]), ("a", &[]), ("a", &[])]).iter().all(|v| *v == StuckVerdict::Progress));
+    }
+}
@@ -0,0 +1,288 @@
+// ============================================
+// WEBRANA CLI - Task Files
+// `run --task-file`: a markdown task with optional YAML front matter
+// listing acceptance criteria, constraints and what is out of scope
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::llm::{LlmClient, Message};
+
+/// Front matter keys of a task file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FrontMatter {
+    title: Option<String>,
+    acceptance_criteria: Vec<String>,
+    constraints: Vec<String>,
+    out_of_scope: Vec<String>,
+}
+
+/// A task read from a file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskBrief {
+    pub title: Option<String>,
+    /// Markdown after the front matter
+    pub body: String,
+    pub acceptance_criteria: Vec<String>,
+    pub constraints: Vec<String>,
+    pub out_of_scope: Vec<String>,
+}
+
+impl TaskBrief {
+    /// Parse a task file. Without a leading `---` line the whole text is
+    /// the task.
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+            return Ok(Self { body: text.trim().to_string(), ..Default::default() });
+        };
+        let (yaml, body) = match rest.find("\n---") {
+            Some(end) => {
+                let after = &rest[end + 4..];
+                if !(after.is_empty() || after.starts_with('\n') || after.starts_with("\r\n")) {
+                    bail!("Front matter must end with a '---' line");
+                }
+                (&rest[..end], after)
+            }
+            None if rest.starts_with("---") => ("", &rest[3..]),
+            None => bail!("Front matter is not closed with a '---' line"),
+        };
+        let front: FrontMatter = if yaml.trim().is_empty() {
+            FrontMatter::default()
+        } else {
+            serde_yaml::from_str(yaml).context("Invalid front matter")?
+        };
+        let brief = Self {
+            title: front.title.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()),
+            body: body.trim().to_string(),
+            acceptance_criteria: front.acceptance_criteria,
+            constraints: front.constraints,
+            out_of_scope: front.out_of_scope,
+        };
+        if brief.title.is_none() && brief.body.is_empty() {
+            bail!("Task file has no title and no task text");
+        }
+        Ok(brief)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid task file {}", path.display()))
+    }
+
+    /// The task as sent to the model, each front matter list under its
+    /// own heading
+    pub fn prompt(&self) -> String {
+        let mut out = String::new();
+        if let Some(title) = &self.title {
+            out.push_str(&format!("# {}\n\n", title));
+        }
+        if !self.body.is_empty() {
+            out.push_str(&self.body);
+            out.push_str("\n\n");
+        }
+        for (heading, items) in [
+            ("Acceptance criteria (all must be met)", &self.acceptance_criteria),
+            ("Constraints", &self.constraints),
+            ("Out of scope (do not do these)", &self.out_of_scope),
+        ] {
+            if items.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("## {}\n", heading));
+            for item in items {
+                out.push_str(&format!("- {}\n", item));
+            }
+            out.push('\n');
+        }
+        out.trim_end().to_string()
+    }
+}
+
+/// The model's judgment of one acceptance criterion
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CriterionVerdict {
+    pub criterion: String,
+    pub met: bool,
+    #[serde(default)]
+    pub evidence: String,
+}
+
+#[derive(Deserialize)]
+struct Assessment {
+    criteria: Vec<CriterionVerdict>,
+}
+
+const ASSESSMENT_SYSTEM: &str = "You review the work of an autonomous coding agent. \
+    Judge only from what the conversation shows was actually done. \
+    Answer with JSON only, no prose and no code fences.";
+
+/// Instructions asking for one verdict per criterion
+pub fn assessment_prompt(criteria: &[String]) -> String {
+    let mut prompt = String::from(
+        "For each acceptance criterion below, decide whether the work so far meets it. \
+        Reply with exactly this JSON shape, one entry per criterion in the same order:\n\
+        {\"criteria\": [{\"criterion\": \"...\", \"met\": true, \"evidence\": \"file, command output or change that shows it\"}]}\n\n\
+        Acceptance criteria:\n",
+    );
+    for (i, criterion) in criteria.iter().enumerate() {
+        prompt.push_str(&format!("{}. {}\n", i + 1, criterion));
+    }
+    prompt
+}
+
+/// Read the model's answer to [`assessment_prompt`]. Verdicts line up with
+/// `criteria` by position; a criterion the answer leaves out counts as unmet.
+pub fn parse_assessment(response: &str, criteria: &[String]) -> Result<Vec<CriterionVerdict>> {
+    let start = response.find('{').context("Assessment has no JSON object")?;
+    let end = response.rfind('}').filter(|end| *end > start).context("Assessment has no JSON object")?;
+    let assessment: Assessment =
+        serde_json::from_str(&response[start..=end]).context("Assessment is not in the expected shape")?;
+
+    Ok(criteria
+        .iter()
+        .enumerate()
+        .map(|(i, criterion)| match assessment.criteria.get(i) {
+            Some(verdict) => CriterionVerdict { criterion: criterion.clone(), ..verdict.clone() },
+            None => CriterionVerdict {
+                criterion: criterion.clone(),
+                met: false,
+                evidence: "not assessed".to_string(),
+            },
+        })
+        .collect())
+}
+
+/// Ask the model to judge `criteria` against the run so far
+pub async fn assess(llm: &LlmClient, history: &[Message], criteria: &[String]) -> Result<Vec<CriterionVerdict>> {
+    let response = llm.chat(ASSESSMENT_SYSTEM, history, &assessment_prompt(criteria)).await?;
+    parse_assessment(&response, criteria)
+}
+
+pub fn all_met(verdicts: &[CriterionVerdict]) -> bool {
+    verdicts.iter().all(|v| v.met)
+}
+
+/// Criteria section of the run report
+pub fn render_verdicts(verdicts: &[CriterionVerdict]) -> String {
+    let met = verdicts.iter().filter(|v| v.met).count();
+    let mut out = format!("Acceptance criteria: {}/{} met\n", met, verdicts.len());
+    for verdict in verdicts {
+        let mark = if verdict.met { "✓" } else { "✗" };
+        out.push_str(&format!("  {} {}\n", mark, verdict.criterion));
+        if !verdict.evidence.is_empty() {
+            out.push_str(&format!("      {}\n", verdict.evidence));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::llm::{ChatResponse, Provider, ToolDefinition};
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_parse_front_matter() {
+        let brief = TaskBrief::parse(
+            "---\ntitle: Fix the parser\nacceptance_criteria:\n  - cargo test passes\n  - no new clippy warnings\n\
+            constraints:\n  - keep the public API\nout_of_scope: [\"docs\"]\n---\n\nThe parser drops trailing commas.\n",
+        )
+        .unwrap();
+        assert_eq!(brief.title.as_deref(), Some("Fix the parser"));
+        assert_eq!(brief.body, "The parser drops trailing commas.");
+        assert_eq!(brief.acceptance_criteria, ["cargo test passes", "no new clippy warnings"]);
+        assert_eq!(brief.constraints, ["keep the public API"]);
+        assert_eq!(brief.out_of_scope, ["docs"]);
+
+        let prompt = brief.prompt();
+        assert!(prompt.starts_with("# Fix the parser\n\nThe parser drops trailing commas.\n\n"));
+        assert!(prompt.contains("## Acceptance criteria (all must be met)\n- cargo test passes\n- no new clippy warnings\n"));
+        assert!(prompt.contains("## Constraints\n- keep the public API\n"));
+        assert!(prompt.ends_with("## Out of scope (do not do these)\n- docs"));
+    }
+
+    #[test]
+    fn test_parse_without_front_matter() {
+        let text = "# Upgrade tokio\n\nBump it and fix what breaks.\n---\nNot front matter.\n";
+        let brief = TaskBrief::parse(text).unwrap();
+        assert_eq!(brief.body, text.trim());
+        assert!(brief.acceptance_criteria.is_empty());
+        assert_eq!(brief.prompt(), text.trim());
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_front_matter() {
+        assert!(TaskBrief::parse("---\ntitle: x\n").unwrap_err().to_string().contains("not closed"));
+        let typo = TaskBrief::parse("---\nacceptance-criteria: [a]\n---\ntask\n").unwrap_err();
+        assert!(format!("{:#}", typo).contains("unknown field"));
+        assert!(TaskBrief::parse("---\n---\n").is_err());
+        assert_eq!(TaskBrief::parse("---\n---\ntask").unwrap().body, "task");
+    }
+
+    #[test]
+    fn test_parse_assessment_lines_up_criteria() {
+        let criteria = vec!["tests pass".to_string(), "README updated".to_string()];
+        let verdicts = parse_assessment(
+            "Here you go:\n```json\n{\"criteria\": [{\"criterion\": \"tests\", \"met\": true, \"evidence\": \"cargo test: 12 passed\"}]}\n```",
+            &criteria,
+        )
+        .unwrap();
+        assert_eq!(verdicts[0], CriterionVerdict {
+            criterion: "tests pass".to_string(),
+            met: true,
+            evidence: "cargo test: 12 passed".to_string(),
+        });
+        assert!(!verdicts[1].met);
+        assert!(!all_met(&verdicts));
+        assert!(parse_assessment("all good!", &criteria).is_err());
+    }
+
+    /// Answers every request with the same text
+    struct Answer(&'static str);
+
+    #[async_trait]
+    impl Provider for Answer {
+        async fn chat(&self, _messages: Vec<Message>, _tools: Option<Vec<ToolDefinition>>) -> Result<ChatResponse> {
+            Ok(ChatResponse {
+                content: self.0.to_string(),
+                tool_calls: Vec::new(),
+                stop_reason: None,
+                refusal: None,
+                provenance: Default::default(),
+            })
+        }
+
+        async fn chat_stream(&self, messages: Vec<Message>, tools: Option<Vec<ToolDefinition>>) -> Result<ChatResponse> {
+            self.chat(messages, tools).await
+        }
+
+        fn name(&self) -> &str {
+            "answer"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_assess_asks_the_model() {
+        let settings = Settings::default();
+        let llm = LlmClient::from_provider(
+            Arc::new(Answer(
+                r#"{"criteria": [{"criterion": "a", "met": true, "evidence": "done"}, {"criterion": "b", "met": false, "evidence": "no test added"}]}"#,
+            )),
+            &settings,
+        );
+        let criteria = vec!["a".to_string(), "b".to_string()];
+        let verdicts = assess(&llm, &[Message::user("do a and b")], &criteria).await.unwrap();
+        assert_eq!(verdicts.iter().map(|v| v.met).collect::<Vec<_>>(), [true, false]);
+        let report = render_verdicts(&verdicts);
+        assert!(report.starts_with("Acceptance criteria: 1/2 met\n"));
+        assert!(report.contains("✗ b\n      no test added"));
+    }
+}
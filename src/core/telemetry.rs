@@ -0,0 +1,240 @@
+// ============================================
+// WEBRANA CLI - OpenTelemetry Trace Export
+// OTLP exporter layered onto `tracing`
+// ============================================
+//
+// Spans are regular `tracing` spans (command, orchestrator turn/iteration,
+// llm.request, skill.execute, index.build, rag.retrieve). With the `otel`
+// feature and an OTLP endpoint they are also exported; otherwise everything
+// here compiles to no-ops.
+
+pub const DEFAULT_SERVICE_NAME: &str = "webrana-cli";
+
+/// Whether export is requested: the Settings toggle plus an endpoint in
+/// `OTEL_EXPORTER_OTLP_ENDPOINT`
+pub fn export_requested(enabled: bool) -> bool {
+    enabled
+        && std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+            .map(|v| !v.trim().is_empty())
+            .unwrap_or(false)
+}
+
+/// Service name from `OTEL_SERVICE_NAME`, defaulting to `webrana-cli`
+pub fn service_name() -> String {
+    std::env::var("OTEL_SERVICE_NAME")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string())
+}
+
+#[cfg(feature = "otel")]
+mod otlp {
+    use lazy_static::lazy_static;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+    use opentelemetry_sdk::Resource;
+    use std::sync::Mutex;
+    use tracing::Subscriber;
+    use tracing_opentelemetry::OpenTelemetryLayer;
+    use tracing_subscriber::registry::LookupSpan;
+
+    lazy_static! {
+        static ref PROVIDER: Mutex<Option<TracerProvider>> = Mutex::new(None);
+    }
+
+    /// OTLP (gRPC) exporting layer, or None when export isn't requested
+    pub fn layer<S>(enabled: bool) -> Option<OpenTelemetryLayer<S, Tracer>>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        if !super::export_requested(enabled) {
+            return None;
+        }
+
+        let exporter = match opentelemetry_otlp::SpanExporter::builder().with_tonic().build() {
+            Ok(exporter) => exporter,
+            Err(e) => {
+                eprintln!("Warning: OTLP exporter disabled: {}", e);
+                return None;
+            }
+        };
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+            .with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                super::service_name(),
+            )]))
+            .build();
+
+        Some(layer_for(provider))
+    }
+
+    /// Layer exporting through `provider`, which `shutdown` will flush
+    pub fn layer_for<S>(provider: TracerProvider) -> OpenTelemetryLayer<S, Tracer>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let tracer = provider.tracer("webrana");
+        *PROVIDER.lock().unwrap() = Some(provider);
+        tracing_opentelemetry::layer().with_tracer(tracer)
+    }
+
+    pub fn shutdown() {
+        if let Some(provider) = PROVIDER.lock().unwrap().take() {
+            provider.force_flush();
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Warning: OTLP exporter shutdown failed: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+#[allow(unused_imports)]
+pub use otlp::{layer, layer_for};
+
+/// Flush and stop the exporter. Safe to call more than once.
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    otlp::shutdown();
+}
+
+/// `std::process::exit` that flushes pending spans first
+pub fn exit(code: i32) -> ! {
+    shutdown();
+    std::process::exit(code)
+}
+
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::llm::{ChatResponse, LlmClient, Message, Provider, ToolCall, ToolDefinition};
+    use crate::skills::SkillRegistry;
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use opentelemetry::Value;
+    use opentelemetry_sdk::export::trace::SpanData;
+    use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tracing::Instrument;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Asks for one tool call, then answers
+    struct ScriptedProvider {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Provider for ScriptedProvider {
+        async fn chat(
+            &self,
+            messages: Vec<Message>,
+            tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            self.chat_stream(messages, tools).await
+        }
+
+        async fn chat_stream(
+            &self,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            let tool_calls = if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                vec![ToolCall {
+                    id: "call_1".to_string(),
+                    name: "read_file".to_string(),
+                    arguments: serde_json::json!({"path": "/nonexistent/webrana-otel-test"}),
+                }]
+            } else {
+                Vec::new()
+            };
+            Ok(ChatResponse {
+                content: "Done reading.".to_string(),
+                tool_calls,
+                stop_reason: None,
+                refusal: None,
+                provenance: Default::default(),
+            })
+        }
+
+        fn name(&self) -> &str {
+            "scripted"
+        }
+    }
+
+    fn attr(span: &SpanData, key: &str) -> Option<Value> {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| kv.value.clone())
+    }
+
+    #[tokio::test]
+    async fn test_single_turn_chat_spans() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry().with(layer_for(provider));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let settings = Settings::default();
+        let llm = LlmClient::from_provider(
+            Arc::new(ScriptedProvider {
+                calls: AtomicUsize::new(0),
+            }),
+            &settings,
+        );
+        let skills = SkillRegistry::new();
+        let mut history = Vec::new();
+
+        let command = tracing::info_span!("webrana.command", command = "chat");
+        let turn = tracing::info_span!(parent: &command, "orchestrator.turn", turn = 1u64);
+        llm.chat_with_tools_loop("system prompt", &mut history, "secret prompt text", &skills)
+            .instrument(turn)
+            .await
+            .unwrap();
+        drop(command);
+
+        // Simple exporter: spans are exported as they close (shutdown would clear them)
+        let spans = exporter.get_finished_spans().unwrap();
+        let find = |name: &str| spans.iter().filter(|s| s.name == name).collect::<Vec<_>>();
+
+        let root = find("webrana.command");
+        let turn = find("orchestrator.turn");
+        let requests = find("llm.request");
+        let skills = find("skill.execute");
+        assert_eq!((root.len(), turn.len(), requests.len(), skills.len()), (1, 1, 2, 1));
+
+        // command -> turn -> {llm.request, skill.execute}, all in one trace
+        let trace_id = root[0].span_context.trace_id();
+        assert_eq!(turn[0].parent_span_id, root[0].span_context.span_id());
+        for span in requests.iter().chain(&skills) {
+            assert_eq!(span.span_context.trace_id(), trace_id);
+            assert_eq!(span.parent_span_id, turn[0].span_context.span_id());
+        }
+
+        let request = requests[0];
+        assert_eq!(attr(request, "provider"), Some(Value::from("scripted")));
+        assert_eq!(attr(request, "model"), Some(Value::from("claude-sonnet-4-20250514")));
+        assert!(attr(request, "input_tokens").is_some());
+        assert!(attr(request, "output_tokens").is_some());
+        assert!(attr(request, "latency_ms").is_some());
+        assert_eq!(attr(request, "tool_calls").map(|v| v.as_str().into_owned()), Some("1".into()));
+
+        assert_eq!(attr(skills[0], "skill"), Some(Value::from("read_file")));
+        assert_eq!(attr(skills[0], "success"), Some(Value::Bool(false)));
+        assert!(attr(skills[0], "duration_ms").is_some());
+
+        // Prompt text never ends up in attributes
+        for span in &spans {
+            for kv in &span.attributes {
+                assert!(!kv.value.as_str().contains("secret prompt text"));
+            }
+        }
+    }
+}
@@ -0,0 +1,542 @@
+// ============================================
+// WEBRANA CLI - Change Journal & Undo
+// The files each run or session changed, with their content from before
+// the first change, so `webrana undo` can put any of them back
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::skills::run_dir;
+use crate::util::diff::{self, DiffStyle};
+use crate::util::{sha256_hex, unix_now};
+
+/// Manifest of one journal, next to its `blobs/` in the run directory
+pub const MANIFEST_FILE: &str = "changes.json";
+
+/// What recorded the changes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalKind {
+    Run,
+    Session,
+    /// `webrana undo` of the journal `of`
+    Undo { of: String },
+}
+
+impl JournalKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JournalKind::Run => "run",
+            JournalKind::Session => "session",
+            JournalKind::Undo { .. } => "undo",
+        }
+    }
+}
+
+/// One changed file. Hashes are SHA-256 of the content; None means the
+/// file did not exist.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChange {
+    /// As the skill was given it, relative to the project root unless absolute
+    pub path: String,
+    /// Content before the first change, kept under `blobs/`
+    pub before: Option<String>,
+    /// Content after the latest write, so an unfinished journal still
+    /// tells later edits apart
+    #[serde(default)]
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeManifest {
+    pub id: String,
+    #[serde(flatten)]
+    pub kind: JournalKind,
+    /// Unix seconds
+    pub started: u64,
+    #[serde(default)]
+    pub finished: Option<u64>,
+    /// In order of first change
+    pub files: Vec<FileChange>,
+}
+
+/// Hash of the file at `path`, None when it doesn't exist
+fn file_hash(path: &Path) -> Option<String> {
+    fs::read(path).ok().map(sha256_hex)
+}
+
+/// Records the files a run or session changes under
+/// `.webrana/runs/<id>/`. Nothing is written until the first change.
+#[derive(Debug)]
+pub struct ChangeJournal {
+    root: PathBuf,
+    dir: PathBuf,
+    manifest: Mutex<ChangeManifest>,
+}
+
+impl ChangeJournal {
+    pub fn new(root: &Path, id: &str, kind: JournalKind) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            dir: run_dir(root, id),
+            manifest: Mutex::new(ChangeManifest {
+                id: id.to_string(),
+                kind,
+                started: unix_now(),
+                finished: None,
+                files: Vec::new(),
+            }),
+        }
+    }
+
+    /// Keep the current content of each of `paths` not changed before,
+    /// ahead of a write to them
+    pub fn before_write(&self, paths: &[String]) -> Result<()> {
+        let mut manifest = self.manifest.lock().unwrap();
+        let mut added = false;
+        for path in paths {
+            if manifest.files.iter().any(|f| &f.path == path) {
+                continue;
+            }
+            let before = match fs::read(self.root.join(path)) {
+                Ok(bytes) => Some(self.store_blob(&bytes)?),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path)),
+            };
+            manifest.files.push(FileChange { path: path.clone(), before, after: None });
+            added = true;
+        }
+        if added {
+            self.save(&manifest)?;
+        }
+        Ok(())
+    }
+
+    /// Note what each of `paths` is right after a write to them
+    pub fn after_write(&self, paths: &[String]) -> Result<()> {
+        let mut manifest = self.manifest.lock().unwrap();
+        let mut changed = false;
+        for change in manifest.files.iter_mut().filter(|f| paths.contains(&f.path)) {
+            change.after = file_hash(&self.root.join(&change.path));
+            changed = true;
+        }
+        if changed {
+            self.save(&manifest)?;
+        }
+        Ok(())
+    }
+
+    /// Note what each file ended up as and drop the ones left as they were.
+    /// Returns the manifest, None if nothing changed.
+    pub fn finish(&self) -> Result<Option<ChangeManifest>> {
+        let mut manifest = self.manifest.lock().unwrap();
+        if manifest.files.is_empty() {
+            return Ok(None);
+        }
+        for change in &mut manifest.files {
+            change.after = file_hash(&self.root.join(&change.path));
+        }
+        manifest.files.retain(|change| change.before != change.after);
+        manifest.finished = Some(unix_now());
+        self.save(&manifest)?;
+        Ok(Some(manifest.clone()))
+    }
+
+    fn store_blob(&self, bytes: &[u8]) -> Result<String> {
+        let hash = sha256_hex(bytes);
+        let path = self.dir.join("blobs").join(&hash);
+        if !path.exists() {
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(&path, bytes).with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(hash)
+    }
+
+    fn save(&self, manifest: &ChangeManifest) -> Result<()> {
+        fs::create_dir_all(&self.dir).with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let path = self.dir.join(MANIFEST_FILE);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(manifest)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// The journal `id` under `root`, or the latest one when `id` is None
+pub fn load(root: &Path, id: Option<&str>) -> Result<ChangeManifest> {
+    let id = match id {
+        Some(id) => id.to_string(),
+        None => latest(root)?.context("No recorded changes to undo")?,
+    };
+    let path = run_dir(root, &id).join(MANIFEST_FILE);
+    let text = fs::read_to_string(&path).with_context(|| format!("No recorded changes for '{}'", id))?;
+    serde_json::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Id of the most recent journal; run ids sort by time
+fn latest(root: &Path) -> Result<Option<String>> {
+    let runs = root.join(".webrana").join("runs");
+    let entries = match fs::read_dir(&runs) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", runs.display())),
+    };
+    Ok(entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join(MANIFEST_FILE).is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .max())
+}
+
+/// What undoing does to one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UndoAction {
+    /// Put back the content from before the run
+    Restore,
+    /// The run created it: move it to the trash
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoItem {
+    pub path: String,
+    pub action: UndoAction,
+    /// Changed since the run finished, e.g. by hand; undoing would lose that
+    pub modified_since: bool,
+}
+
+/// Files of `manifest` that undoing would change; files already back to
+/// their old content are left out
+pub fn plan(root: &Path, manifest: &ChangeManifest) -> Vec<UndoItem> {
+    manifest
+        .files
+        .iter()
+        .filter_map(|change| {
+            let current = file_hash(&root.join(&change.path));
+            if current == change.before {
+                return None;
+            }
+            Some(UndoItem {
+                path: change.path.clone(),
+                action: if change.before.is_some() { UndoAction::Restore } else { UndoAction::Delete },
+                modified_since: current != change.after,
+            })
+        })
+        .collect()
+}
+
+/// Which files to undo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    /// Everything, after one confirmation of the summary
+    All,
+    /// One file, as recorded
+    File(String),
+    /// Step through the files, confirming each
+    Interactive,
+}
+
+/// A question `undo` asks before changing anything
+#[derive(Debug)]
+pub enum Ask<'a> {
+    /// Undo all of these?
+    All(&'a [UndoItem]),
+    /// Undo this one, which changes it by `diff`?
+    File { item: &'a UndoItem, diff: &'a str },
+    /// Overwrite changes made since the run finished?
+    Overwrite(&'a UndoItem),
+}
+
+/// The unified diff undoing `item` would apply
+pub fn undo_diff(root: &Path, manifest: &ChangeManifest, item: &UndoItem, style: DiffStyle) -> String {
+    let current = fs::read(root.join(&item.path)).unwrap_or_default();
+    let before = manifest
+        .files
+        .iter()
+        .find(|f| f.path == item.path)
+        .and_then(|f| f.before.as_ref())
+        .and_then(|hash| fs::read(run_dir(root, &manifest.id).join("blobs").join(hash)).ok())
+        .unwrap_or_default();
+    diff::unified(&String::from_utf8_lossy(&current), &String::from_utf8_lossy(&before), &item.path, style)
+}
+
+/// Undo `selection` of the journal `manifest` as the journal `undo_id`,
+/// so the undo can be undone in turn. `ask` answers each question. Returns
+/// the undo's manifest, None if nothing was undone.
+pub fn undo(
+    root: &Path,
+    manifest: &ChangeManifest,
+    selection: &Selection,
+    undo_id: &str,
+    ask: &mut dyn FnMut(Ask) -> Result<bool>,
+) -> Result<Option<ChangeManifest>> {
+    for change in &manifest.files {
+        inside_root(root, &change.path)?;
+    }
+    let mut items = plan(root, manifest);
+    match selection {
+        Selection::File(path) => {
+            if !manifest.files.iter().any(|f| &f.path == path) {
+                bail!("{} was not changed by {} {}", path, manifest.kind.label(), manifest.id);
+            }
+            items.retain(|item| &item.path == path);
+        }
+        Selection::All if !items.is_empty() => {
+            if !ask(Ask::All(&items))? {
+                return Ok(None);
+            }
+        }
+        Selection::All => {}
+        Selection::Interactive => {
+            let mut chosen = Vec::new();
+            for item in items {
+                let diff = undo_diff(root, manifest, &item, DiffStyle::terminal());
+                if ask(Ask::File { item: &item, diff: &diff })? {
+                    chosen.push(item);
+                }
+            }
+            items = chosen;
+        }
+    }
+
+    let journal = ChangeJournal::new(root, undo_id, JournalKind::Undo { of: manifest.id.clone() });
+    let blobs = run_dir(root, &manifest.id).join("blobs");
+    for item in &items {
+        if item.modified_since && !ask(Ask::Overwrite(item))? {
+            continue;
+        }
+        journal.before_write(std::slice::from_ref(&item.path))?;
+        let path = inside_root(root, &item.path)?;
+        match item.action {
+            UndoAction::Restore => {
+                let change = manifest.files.iter().find(|f| f.path == item.path).unwrap();
+                let blob = blobs.join(change.before.as_ref().unwrap());
+                let content = fs::read(&blob).with_context(|| format!("Missing backup of {}", item.path))?;
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(&path, content).with_context(|| format!("Failed to restore {}", item.path))?;
+            }
+            UndoAction::Delete => trash(root, undo_id, &item.path)?,
+        }
+        journal.after_write(std::slice::from_ref(&item.path))?;
+    }
+    journal.finish()
+}
+
+/// `path` joined to `root`, refused when it leads outside: a manifest
+/// checked into the repository could otherwise name `../` or absolute paths
+fn inside_root(root: &Path, path: &str) -> Result<PathBuf> {
+    let mut full = PathBuf::new();
+    for component in root.join(path).components() {
+        match component {
+            Component::ParentDir => {
+                full.pop();
+            }
+            Component::CurDir => {}
+            other => full.push(other),
+        }
+    }
+    // A symlink on the way could lead out as well
+    let real_root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let real = full.ancestors().find_map(|dir| dir.canonicalize().ok());
+    if !full.starts_with(root) || full == root || real.is_some_and(|real| !real.starts_with(&real_root)) {
+        bail!("{} is outside {}; not undoing it", path, root.display());
+    }
+    Ok(full)
+}
+
+/// Move `path` under `.webrana/trash/<undo_id>/` rather than deleting it
+fn trash(root: &Path, undo_id: &str, path: &str) -> Result<()> {
+    let source = inside_root(root, path)?;
+    let target = root.join(".webrana").join("trash").join(undo_id).join(source.strip_prefix(root)?);
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::rename(&source, &target).with_context(|| format!("Failed to move {} to the trash", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tree changed by the fabricated run `1700000000-aaaaaa`: `a.txt`
+    /// edited, `b.txt` edited, `new.txt` created
+    fn fixture() -> (tempfile::TempDir, ChangeManifest) {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let root = tmp.path();
+        fs::write(root.join("a.txt"), "a before\n").unwrap();
+        fs::write(root.join("b.txt"), "b before\n").unwrap();
+
+        let journal = ChangeJournal::new(root, "1700000000-aaaaaa", JournalKind::Run);
+        let paths = ["a.txt".to_string(), "b.txt".to_string(), "new.txt".to_string()];
+        journal.before_write(&paths).unwrap();
+        fs::write(root.join("a.txt"), "a after\n").unwrap();
+        fs::write(root.join("b.txt"), "b after\n").unwrap();
+        fs::write(root.join("new.txt"), "created\n").unwrap();
+        journal.after_write(&paths).unwrap();
+        // Written twice: the first content is what's kept
+        journal.before_write(&paths[..1]).unwrap();
+        fs::write(root.join("a.txt"), "a final\n").unwrap();
+        journal.after_write(&paths[..1]).unwrap();
+        let manifest = journal.finish().unwrap().unwrap();
+        (tmp, manifest)
+    }
+
+    fn read(root: &Path, path: &str) -> String {
+        fs::read_to_string(root.join(path)).unwrap()
+    }
+
+    #[test]
+    fn test_full_undo() {
+        let (tmp, manifest) = fixture();
+        let root = tmp.path();
+        assert_eq!(load(root, None).unwrap(), manifest);
+
+        let mut asked = Vec::new();
+        let undone = undo(root, &manifest, &Selection::All, "1700000100-bbbbbb", &mut |ask| {
+            asked.push(format!("{:?}", ask));
+            Ok(true)
+        })
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(asked.len(), 1, "one summary confirmation: {:?}", asked);
+        assert_eq!(read(root, "a.txt"), "a before\n");
+        assert_eq!(read(root, "b.txt"), "b before\n");
+        assert!(!root.join("new.txt").exists());
+        assert_eq!(read(root, ".webrana/trash/1700000100-bbbbbb/new.txt"), "created\n");
+        assert_eq!(undone.kind, JournalKind::Undo { of: manifest.id.clone() });
+        assert_eq!(undone.files.len(), 3);
+        assert!(plan(root, &manifest).is_empty());
+
+        // Declining the summary changes nothing
+        let (tmp, manifest) = fixture();
+        let declined = undo(tmp.path(), &manifest, &Selection::All, "u", &mut |_| Ok(false)).unwrap();
+        assert!(declined.is_none());
+        assert_eq!(read(tmp.path(), "a.txt"), "a final\n");
+    }
+
+    #[test]
+    fn test_selective_undo() {
+        let (tmp, manifest) = fixture();
+        let root = tmp.path();
+        undo(root, &manifest, &Selection::File("b.txt".into()), "1700000100-bbbbbb", &mut |_| {
+            panic!("a clean single file needs no confirmation")
+        })
+        .unwrap();
+        assert_eq!(read(root, "a.txt"), "a final\n");
+        assert_eq!(read(root, "b.txt"), "b before\n");
+        assert!(root.join("new.txt").exists());
+        assert!(undo(root, &manifest, &Selection::File("c.txt".into()), "x", &mut |_| Ok(true)).is_err());
+
+        // Interactive: shown each diff, takes only the first
+        let mut shown = Vec::new();
+        undo(root, &manifest, &Selection::Interactive, "1700000200-cccccc", &mut |ask| match ask {
+            Ask::File { item, diff } => {
+                shown.push((item.path.clone(), diff.to_string()));
+                Ok(shown.len() == 1)
+            }
+            other => panic!("unexpected {:?}", other),
+        })
+        .unwrap();
+        assert_eq!(shown.len(), 2);
+        assert_eq!(shown[0].0, "a.txt");
+        assert!(shown[0].1.contains("-a final") && shown[0].1.contains("+a before"), "{}", shown[0].1);
+        assert_eq!(read(root, "a.txt"), "a before\n");
+        assert!(root.join("new.txt").exists());
+    }
+
+    #[test]
+    fn test_modified_since_needs_extra_confirmation() {
+        let (tmp, manifest) = fixture();
+        let root = tmp.path();
+        fs::write(root.join("b.txt"), "b edited by hand\n").unwrap();
+
+        let items = plan(root, &manifest);
+        let modified: Vec<_> = items.iter().filter(|i| i.modified_since).map(|i| i.path.as_str()).collect();
+        assert_eq!(modified, ["b.txt"]);
+
+        let mut overwrites = Vec::new();
+        undo(root, &manifest, &Selection::All, "1700000100-bbbbbb", &mut |ask| match ask {
+            Ask::Overwrite(item) => {
+                overwrites.push(item.path.clone());
+                Ok(false)
+            }
+            _ => Ok(true),
+        })
+        .unwrap();
+        assert_eq!(overwrites, ["b.txt"]);
+        assert_eq!(read(root, "b.txt"), "b edited by hand\n");
+        assert_eq!(read(root, "a.txt"), "a before\n");
+
+        // Confirmed, the manual edit goes
+        undo(root, &manifest, &Selection::File("b.txt".into()), "1700000200-cccccc", &mut |_| Ok(true)).unwrap();
+        assert_eq!(read(root, "b.txt"), "b before\n");
+    }
+
+    #[test]
+    fn test_unfinished_journal_still_detects_later_edits() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let root = tmp.path();
+        fs::write(root.join("a.txt"), "a before\n").unwrap();
+        // A session ended by Ctrl-C never finishes its journal
+        let journal = ChangeJournal::new(root, "1700000000-aaaaaa", JournalKind::Session);
+        let paths = ["a.txt".to_string(), "new.txt".to_string()];
+        journal.before_write(&paths).unwrap();
+        fs::write(root.join("a.txt"), "a after\n").unwrap();
+        fs::write(root.join("new.txt"), "created\n").unwrap();
+        journal.after_write(&paths).unwrap();
+        drop(journal);
+
+        fs::write(root.join("a.txt"), "a edited by hand\n").unwrap();
+        let manifest = load(root, None).unwrap();
+        assert_eq!(manifest.finished, None);
+        let modified: Vec<_> = plan(root, &manifest).into_iter().map(|i| (i.path, i.modified_since)).collect();
+        assert_eq!(modified, [("a.txt".to_string(), true), ("new.txt".to_string(), false)]);
+    }
+
+    #[test]
+    fn test_paths_outside_root_are_refused() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let root = tmp.path().join("project");
+        fs::create_dir(&root).unwrap();
+        fs::write(tmp.path().join("outside.txt"), "keep me\n").unwrap();
+        let outside = tmp.path().join("outside.txt").display().to_string();
+        for path in ["../outside.txt", outside.as_str(), "sub/../../outside.txt"] {
+            let manifest = ChangeManifest {
+                id: "1700000000-aaaaaa".to_string(),
+                kind: JournalKind::Run,
+                started: 0,
+                finished: Some(1),
+                files: vec![FileChange { path: path.to_string(), before: None, after: None }],
+            };
+            let err = undo(&root, &manifest, &Selection::All, "u", &mut |_| Ok(true)).unwrap_err();
+            assert!(err.to_string().contains("is outside"), "{}: {}", path, err);
+            assert_eq!(read(tmp.path(), "outside.txt"), "keep me\n");
+        }
+        assert_eq!(inside_root(&root, "./src/../a.txt").unwrap(), root.join("a.txt"));
+    }
+
+    #[test]
+    fn test_undo_of_undo() {
+        let (tmp, manifest) = fixture();
+        let root = tmp.path();
+        undo(root, &manifest, &Selection::All, "1700000100-bbbbbb", &mut |_| Ok(true)).unwrap();
+
+        let undone = load(root, None).unwrap();
+        assert_eq!(undone.id, "1700000100-bbbbbb");
+        undo(root, &undone, &Selection::All, "1700000200-cccccc", &mut |ask| {
+            assert!(matches!(ask, Ask::All(items) if items.iter().all(|i| !i.modified_since)));
+            Ok(true)
+        })
+        .unwrap();
+        assert_eq!(read(root, "a.txt"), "a final\n");
+        assert_eq!(read(root, "b.txt"), "b after\n");
+        assert_eq!(read(root, "new.txt"), "created\n");
+    }
+}
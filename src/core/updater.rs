@@ -6,8 +6,11 @@
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
-const GITHUB_API_URL: &str = "https://api.github.com/repos/webranaai/webrana-cli/releases/latest";
+use crate::util::sha256_hex;
+
+pub const GITHUB_API_URL: &str = "https://api.github.com/repos/webranaai/webrana-cli/releases/latest";
 const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Release information from GitHub
@@ -39,10 +42,19 @@ pub enum UpdateStatus {
         release_notes: Option<String>,
     },
     CheckFailed(String),
+    /// Not checked: `--offline`
+    Offline,
 }
 
 /// Check for updates from GitHub releases
 pub async fn check_for_updates() -> UpdateStatus {
+    check_for_updates_with(crate::util::http::is_offline()).await
+}
+
+async fn check_for_updates_with(offline: bool) -> UpdateStatus {
+    if offline {
+        return UpdateStatus::Offline;
+    }
     match fetch_latest_release().await {
         Ok(release) => {
             let latest = release.tag_name.trim_start_matches('v');
@@ -65,7 +77,7 @@ pub async fn check_for_updates() -> UpdateStatus {
 
 /// Fetch latest release from GitHub API
 async fn fetch_latest_release() -> Result<ReleaseInfo> {
-    let client = reqwest::Client::new();
+    let client = crate::util::http::client()?;
 
     let response = client
         .get(GITHUB_API_URL)
@@ -135,6 +147,175 @@ pub fn get_platform_download_url(release: &ReleaseInfo) -> Option<&ReleaseAsset>
     None
 }
 
+/// Download the platform asset of the latest release and swap it in for
+/// the running executable. Returns the installed version.
+pub async fn install_latest() -> Result<String> {
+    install_latest_with(crate::util::http::is_offline()).await
+}
+
+async fn install_latest_with(offline: bool) -> Result<String> {
+    crate::util::http::check_online(offline, "Installing an update")?;
+    let release = fetch_latest_release().await?;
+    let latest = release.tag_name.trim_start_matches('v').to_string();
+    let asset = get_platform_download_url(&release)
+        .with_context(|| format!("No release asset for this platform; see {}", release.html_url))?;
+    let name = asset.name.to_lowercase();
+    if [".tar.gz", ".tgz", ".zip", ".tar.xz"].iter().any(|ext| name.ends_with(ext)) {
+        anyhow::bail!(
+            "{} is an archive; download it from {} and replace the binary by hand",
+            asset.name,
+            release.html_url
+        );
+    }
+
+    // Fail closed: without a published checksum nothing is installed
+    let checksums = checksum_asset(&release, &asset.name).with_context(|| {
+        format!(
+            "The release publishes no SHA-256 checksum for {}; download it from {} and verify it by hand",
+            asset.name, release.html_url
+        )
+    })?;
+    let checksums = String::from_utf8_lossy(&download(&checksums.browser_download_url).await?).to_string();
+    let expected = expected_sha256(&checksums, &asset.name)
+        .with_context(|| format!("No SHA-256 for {} in the release checksums", asset.name))?;
+
+    let bytes = download(&asset.browser_download_url).await?;
+    verify_sha256(&bytes, &expected).with_context(|| format!("Refusing to install {}", asset.name))?;
+
+    let target = std::env::current_exe().context("Cannot locate the running executable")?;
+    let staged = sibling(&target, "new");
+    std::fs::write(&staged, &bytes).with_context(|| format!("Writing {}", staged.display()))?;
+    replace_executable(&staged, &target, ReplaceStrategy::current())?;
+    Ok(latest)
+}
+
+async fn download(url: &str) -> Result<Vec<u8>> {
+    Ok(crate::util::http::client()?
+        .get(url)
+        .header("User-Agent", format!("webrana-cli/{}", CURRENT_VERSION))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .context("Failed to download the release")?
+        .bytes()
+        .await
+        .context("Failed to download the release")?
+        .to_vec())
+}
+
+/// The release asset with the checksum of `name`: `<name>.sha256`, else a
+/// `SHA256SUMS`-style list covering every asset
+fn checksum_asset<'a>(release: &'a ReleaseInfo, name: &str) -> Option<&'a ReleaseAsset> {
+    let own = format!("{}.sha256", name.to_lowercase());
+    release
+        .assets
+        .iter()
+        .find(|a| a.name.to_lowercase() == own)
+        .or_else(|| {
+            release.assets.iter().find(|a| {
+                matches!(
+                    a.name.to_lowercase().as_str(),
+                    "sha256sums" | "sha256sums.txt" | "checksums.txt" | "checksums.sha256"
+                )
+            })
+        })
+}
+
+/// The hex digest for `name` in `sha256sum` output (`<hex>  <name>` or
+/// `<hex> *<name>`), or a lone digest as in `<name>.sha256`
+fn expected_sha256(checksums: &str, name: &str) -> Option<String> {
+    let is_digest = |hex: &str| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit());
+    let entries: Vec<(&str, Option<&str>)> = checksums
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            Some((parts.next()?, parts.next().map(|n| n.trim_start_matches('*'))))
+        })
+        .filter(|(hex, _)| is_digest(hex))
+        .collect();
+    entries
+        .iter()
+        .find(|(_, file)| *file == Some(name))
+        .or_else(|| (entries.len() == 1 && entries[0].1.is_none()).then(|| &entries[0]))
+        .map(|(hex, _)| hex.to_lowercase())
+}
+
+fn verify_sha256(bytes: &[u8], expected: &str) -> Result<()> {
+    let actual = sha256_hex(bytes);
+    if actual != expected.to_lowercase() {
+        anyhow::bail!("SHA-256 mismatch: expected {}, downloaded file has {}", expected, actual);
+    }
+    Ok(())
+}
+
+/// How the running executable is swapped for a new one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaceStrategy {
+    /// Rename the new file over the old one (Unix: the running process
+    /// keeps its open inode)
+    RenameOver,
+    /// Windows cannot overwrite or delete a running .exe, but can rename
+    /// it: move it aside to `<exe>.old`, move the new one in, and delete
+    /// the old one on the next start ([`cleanup_replaced_binary`])
+    MoveAside,
+}
+
+impl ReplaceStrategy {
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            Self::MoveAside
+        } else {
+            Self::RenameOver
+        }
+    }
+}
+
+/// `<exe>.<suffix>` next to `target`, so renames stay on one filesystem
+fn sibling(target: &Path, suffix: &str) -> PathBuf {
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    target.with_file_name(name)
+}
+
+/// Replace `target` with `new_binary` (which must be on the same filesystem)
+pub fn replace_executable(new_binary: &Path, target: &Path, strategy: ReplaceStrategy) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(target).map(|m| m.permissions().mode()).unwrap_or(0o755);
+        std::fs::set_permissions(new_binary, std::fs::Permissions::from_mode(mode | 0o111))?;
+    }
+
+    match strategy {
+        ReplaceStrategy::RenameOver => std::fs::rename(new_binary, target)
+            .with_context(|| format!("Cannot replace {}", target.display())),
+        ReplaceStrategy::MoveAside => {
+            let old = sibling(target, "old");
+            // A leftover from an earlier update may still be around
+            let _ = std::fs::remove_file(&old);
+            std::fs::rename(target, &old)
+                .with_context(|| format!("Cannot move {} aside", target.display()))?;
+            if let Err(e) = std::fs::rename(new_binary, target) {
+                let _ = std::fs::rename(&old, target);
+                return Err(e).with_context(|| format!("Cannot replace {}", target.display()));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Delete the `<exe>.old` left behind by a [`ReplaceStrategy::MoveAside`]
+/// update; called at startup, once the old process has exited
+pub fn cleanup_replaced_binary() {
+    if let Ok(exe) = std::env::current_exe() {
+        let old = sibling(&exe, "old");
+        if old.exists() {
+            let _ = std::fs::remove_file(old);
+        }
+    }
+}
+
 /// Format update message for display
 pub fn format_update_message(status: &UpdateStatus) -> String {
     match status {
@@ -165,6 +346,9 @@ pub fn format_update_message(status: &UpdateStatus) -> String {
         UpdateStatus::CheckFailed(error) => {
             format!("Update check failed: {}", error)
         }
+        UpdateStatus::Offline => {
+            format!("Webrana CLI v{}; update check skipped in offline mode.", CURRENT_VERSION)
+        }
     }
 }
 
@@ -181,6 +365,74 @@ mod tests {
         assert!(!is_newer_version("0.3.0", "0.4.0"));
     }
 
+    #[tokio::test]
+    async fn test_offline_skips_update_check() {
+        let status = check_for_updates_with(true).await;
+        let install = install_latest_with(true).await;
+
+        assert!(matches!(status, UpdateStatus::Offline), "{:?}", status);
+        assert!(format_update_message(&status).contains("skipped in offline mode"));
+        let err = install.unwrap_err().to_string();
+        assert!(err.contains("Installing an update needs the network"), "{}", err);
+    }
+
+    #[test]
+    fn test_replace_executable() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let target = tmp.path().join("webrana.exe");
+
+        for strategy in [ReplaceStrategy::RenameOver, ReplaceStrategy::MoveAside] {
+            std::fs::write(&target, "old").unwrap();
+            let staged = sibling(&target, "new");
+            std::fs::write(&staged, "new").unwrap();
+
+            replace_executable(&staged, &target, strategy).unwrap();
+            assert_eq!(std::fs::read_to_string(&target).unwrap(), "new");
+            assert!(!staged.exists());
+            let old = tmp.path().join("webrana.exe.old");
+            assert_eq!(old.exists(), strategy == ReplaceStrategy::MoveAside);
+            let _ = std::fs::remove_file(old);
+        }
+
+        // A failed move restores the original executable
+        std::fs::write(&target, "old").unwrap();
+        let missing = tmp.path().join("missing");
+        assert!(replace_executable(&missing, &target, ReplaceStrategy::MoveAside).is_err());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "old");
+    }
+
+    #[test]
+    fn test_release_checksums() {
+        let asset = |name: &str| ReleaseAsset {
+            name: name.to_string(),
+            browser_download_url: format!("https://example.com/{}", name),
+            size: 1,
+        };
+        let mut release = ReleaseInfo {
+            tag_name: "v9.9.9".to_string(),
+            name: "9.9.9".to_string(),
+            html_url: "https://example.com/release".to_string(),
+            published_at: String::new(),
+            body: None,
+            assets: vec![asset("webrana-linux-x86_64"), asset("SHA256SUMS")],
+        };
+        assert_eq!(checksum_asset(&release, "webrana-linux-x86_64").unwrap().name, "SHA256SUMS");
+        release.assets.push(asset("webrana-linux-x86_64.sha256"));
+        assert_eq!(checksum_asset(&release, "webrana-linux-x86_64").unwrap().name, "webrana-linux-x86_64.sha256");
+        release.assets.retain(|a| a.name == "webrana-linux-x86_64");
+        assert!(checksum_asset(&release, "webrana-linux-x86_64").is_none());
+
+        let digest = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        let sums = format!("{}  webrana-macos-arm64\n{} *webrana-linux-x86_64\n", "0".repeat(64), digest);
+        assert_eq!(expected_sha256(&sums, "webrana-linux-x86_64").as_deref(), Some(digest));
+        assert_eq!(expected_sha256(&format!("{}\n", digest), "anything").as_deref(), Some(digest));
+        assert!(expected_sha256(&sums, "webrana-windows-x86_64.exe").is_none());
+
+        assert!(verify_sha256(b"hello", digest).is_ok());
+        let err = verify_sha256(b"tampered", digest).unwrap_err().to_string();
+        assert!(err.starts_with("SHA-256 mismatch"), "{}", err);
+    }
+
     #[test]
     fn test_version_parsing() {
         assert!(is_newer_version("1.0.0", "0.4.0-alpha"));
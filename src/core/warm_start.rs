@@ -0,0 +1,278 @@
+// ============================================
+// WEBRANA CLI - REPL Warm Start
+// What happened in the repository since the last session here: recent
+// commits, branch position and any merge or rebase underway
+// ============================================
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::core::activity::{Activity, ActivityLog};
+use crate::core::doctor::format_age;
+use crate::skills::run_git_command;
+
+pub const HEADING: &str = "## Since you last worked here";
+
+/// Longest the block may grow; commits are left out past it
+pub const MAX_CHARS: usize = 1_200;
+
+/// Each git call; a slow repository just means no warm start
+const GIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Without an earlier session, commits of the last week
+const FALLBACK_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// How far back the activity journal is searched for the last session
+const LOOKBACK_SECS: u64 = 90 * 24 * 60 * 60;
+
+/// Raw git output the block is built from
+#[derive(Debug, Clone, Default)]
+pub struct GitSnapshot {
+    /// `git status --porcelain=v2 --branch`
+    pub status: String,
+    /// `git log --format=%h%x1f%s%x1f%an%x1f%ct`
+    pub log: String,
+    pub rebasing: bool,
+    pub merging: bool,
+}
+
+/// End of the last session in `project`, from the activity journal
+pub fn last_session(log: &ActivityLog, project: &str, now: u64) -> Option<u64> {
+    let (entries, _) = log.since(now.saturating_sub(LOOKBACK_SECS)).ok()?;
+    entries.into_iter().rev().find_map(|entry| match entry {
+        Activity::Session { time, project: Some(p), .. } if p == project => Some(time),
+        _ => None,
+    })
+}
+
+/// Start of the commit window: the last session, or a week back
+pub fn window_start(last_session: Option<u64>, now: u64) -> u64 {
+    last_session.unwrap_or(now.saturating_sub(FALLBACK_SECS))
+}
+
+/// Gather the snapshot of `dir`; None outside a git repository or when
+/// git is too slow
+pub async fn gather(dir: &Path, since: u64, commits: usize) -> Option<GitSnapshot> {
+    let cwd = dir.to_str()?;
+    let git = |args: Vec<String>| async move {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        tokio::time::timeout(GIT_TIMEOUT, run_git_command(&args, Some(cwd))).await.ok()?.ok()
+    };
+
+    let git_dir = git(vec!["rev-parse".into(), "--git-dir".into()]).await?;
+    let git_dir = dir.join(git_dir.trim());
+    let status = git(vec!["status".into(), "--porcelain=v2".into(), "--branch".into()]).await?;
+    // Fails in a repository without commits
+    let log = git(vec![
+        "log".into(),
+        format!("--since=@{}", since),
+        format!("-n{}", commits),
+        "--format=%h%x1f%s%x1f%an%x1f%ct".into(),
+    ])
+    .await
+    .unwrap_or_default();
+
+    Some(GitSnapshot {
+        status,
+        log,
+        rebasing: git_dir.join("rebase-merge").is_dir() || git_dir.join("rebase-apply").is_dir(),
+        merging: git_dir.join("MERGE_HEAD").is_file(),
+    })
+}
+
+/// The labeled block for the prompt and the REPL start, within `max_chars`
+pub fn summarize(snapshot: &GitSnapshot, now: u64, last_session: Option<u64>, max_chars: usize) -> String {
+    let (mut head, mut oid, mut upstream, mut ab) = (None, None, None, None);
+    let mut conflicts = Vec::new();
+    for line in snapshot.status.lines() {
+        if let Some(value) = line.strip_prefix("# branch.head ") {
+            head = Some(value);
+        } else if let Some(value) = line.strip_prefix("# branch.oid ") {
+            oid = Some(value);
+        } else if let Some(value) = line.strip_prefix("# branch.upstream ") {
+            upstream = Some(value);
+        } else if let Some(value) = line.strip_prefix("# branch.ab ") {
+            ab = Some(value);
+        } else if line.starts_with("u ") {
+            if let Some(path) = line.splitn(11, ' ').nth(10) {
+                conflicts.push(path);
+            }
+        }
+    }
+
+    let mut lines = vec![HEADING.to_string()];
+    let short = |oid: &str| oid.chars().take(7).collect::<String>();
+    let branch = match (head, oid) {
+        (Some("(detached)"), Some(oid)) => format!("Detached HEAD at {}", short(oid)),
+        (Some("(detached)"), None) => "Detached HEAD".to_string(),
+        (Some(name), Some("(initial)")) => format!("Branch {} (no commits yet)", name),
+        (Some(name), _) => format!("Branch {}", name),
+        (None, _) => "Branch unknown".to_string(),
+    };
+    let position = match (head, upstream, ab) {
+        (Some("(detached)"), ..) => String::new(),
+        (_, None, _) => ", no upstream".to_string(),
+        (_, Some(upstream), ab) => {
+            let (ahead, behind) = ab.map_or((0, 0), parse_ab);
+            match (ahead, behind) {
+                (0, 0) => format!(", up to date with {}", upstream),
+                (a, 0) => format!(", {} ahead of {}", a, upstream),
+                (0, b) => format!(", {} behind {}", b, upstream),
+                (a, b) => format!(", {} ahead and {} behind {}", a, b, upstream),
+            }
+        }
+    };
+    lines.push(format!("{}{}", branch, position));
+
+    let underway = match (snapshot.rebasing, snapshot.merging) {
+        (true, _) => Some("Rebase in progress"),
+        (false, true) => Some("Merge in progress"),
+        _ if !conflicts.is_empty() => Some("Unresolved conflicts"),
+        _ => None,
+    };
+    if let Some(state) = underway {
+        lines.push(match conflicts.len() {
+            0 => state.to_string(),
+            n => format!("{}: {} conflicted file{} ({})", state, n, if n == 1 { "" } else { "s" }, conflicts.join(", ")),
+        });
+    }
+
+    let commits: Vec<String> = snapshot
+        .log
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\x1f');
+            let (sha, subject, author) = (fields.next()?, fields.next()?, fields.next()?);
+            let time: u64 = fields.next()?.trim().parse().ok()?;
+            Some(format!("- {} {} ({}, {} ago)", sha, subject, author, format_age(now.saturating_sub(time))))
+        })
+        .collect();
+    lines.push(match (commits.is_empty(), last_session) {
+        (true, Some(_)) => "No commits since the last session".to_string(),
+        (true, None) => "No commits in the last 7 days".to_string(),
+        (false, Some(time)) => format!("Commits since the last session {} ago:", format_age(now.saturating_sub(time))),
+        (false, None) => "Commits in the last 7 days:".to_string(),
+    });
+
+    let mut out = lines.join("\n");
+    for (i, commit) in commits.iter().enumerate() {
+        if out.len() + commit.len() + 1 > max_chars {
+            out.push_str(&format!("\n- … {} more", commits.len() - i));
+            break;
+        }
+        out.push('\n');
+        out.push_str(commit);
+    }
+    out
+}
+
+/// `+3 -1` → (3, 1)
+fn parse_ab(ab: &str) -> (u64, u64) {
+    let mut parts = ab.split_whitespace().map(|n| n.trim_start_matches(['+', '-']).parse().unwrap_or(0));
+    (parts.next().unwrap_or(0), parts.next().unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn log(entries: &[(&str, &str, &str, u64)]) -> String {
+        entries
+            .iter()
+            .map(|(sha, subject, author, age)| format!("{}\x1f{}\x1f{}\x1f{}\n", sha, subject, author, NOW - age))
+            .collect()
+    }
+
+    #[test]
+    fn test_summary_on_a_tracking_branch() {
+        let snapshot = GitSnapshot {
+            status: "# branch.oid 4f2a9c1e0b\n# branch.head main\n# branch.upstream origin/main\n# branch.ab +2 -1\n1 .M N... 100644 100644 100644 a b src/lib.rs\n".into(),
+            log: log(&[("4f2a9c1", "Fix parser", "Ana", 3 * 3600), ("9e8d7c6", "Add tests", "Ben", 2 * 86_400)]),
+            ..Default::default()
+        };
+        let summary = summarize(&snapshot, NOW, Some(NOW - 3 * 86_400), MAX_CHARS);
+        assert_eq!(
+            summary,
+            "## Since you last worked here\n\
+             Branch main, 2 ahead and 1 behind origin/main\n\
+             Commits since the last session 3d ago:\n\
+             - 4f2a9c1 Fix parser (Ana, 3h ago)\n\
+             - 9e8d7c6 Add tests (Ben, 2d ago)"
+        );
+
+        // Over the budget the oldest commits are counted, not listed
+        let summary = summarize(&snapshot, NOW, None, 150);
+        assert!(summary.ends_with("- 4f2a9c1 Fix parser (Ana, 3h ago)\n- … 1 more"), "{}", summary);
+        assert!(summary.contains("Commits in the last 7 days:"));
+    }
+
+    #[test]
+    fn test_summary_with_detached_head() {
+        let snapshot = GitSnapshot {
+            status: "# branch.oid 4f2a9c1e0b\n# branch.head (detached)\n".into(),
+            ..Default::default()
+        };
+        let summary = summarize(&snapshot, NOW, None, MAX_CHARS);
+        assert!(summary.contains("\nDetached HEAD at 4f2a9c1\n"), "{}", summary);
+        assert!(summary.ends_with("No commits in the last 7 days"));
+    }
+
+    #[test]
+    fn test_summary_with_rebase_in_progress() {
+        let snapshot = GitSnapshot {
+            status: "# branch.oid 4f2a9c1e0b\n# branch.head (detached)\n\
+                     u UU N... 100644 100644 100644 100644 a b c src/main.rs\n\
+                     u AA N... 100644 100644 100644 100644 a b c docs/my notes.md\n"
+                .into(),
+            rebasing: true,
+            ..Default::default()
+        };
+        let summary = summarize(&snapshot, NOW, Some(NOW - 600), MAX_CHARS);
+        assert!(
+            summary.contains("Rebase in progress: 2 conflicted files (src/main.rs, docs/my notes.md)"),
+            "{}",
+            summary
+        );
+        assert!(summary.ends_with("No commits since the last session"));
+
+        let merging = GitSnapshot { rebasing: false, merging: true, ..snapshot };
+        assert!(summarize(&merging, NOW, None, MAX_CHARS).contains("Merge in progress: 2 conflicted files"));
+    }
+
+    #[test]
+    fn test_summary_without_upstream() {
+        let snapshot = GitSnapshot {
+            status: "# branch.oid 4f2a9c1e0b\n# branch.head feature/x\n".into(),
+            log: log(&[("4f2a9c1", "WIP", "Ana", 120)]),
+            ..Default::default()
+        };
+        let summary = summarize(&snapshot, NOW, None, MAX_CHARS);
+        assert!(summary.contains("\nBranch feature/x, no upstream\n"), "{}", summary);
+        assert!(summary.ends_with("- 4f2a9c1 WIP (Ana, 2m ago)"));
+    }
+
+    #[test]
+    fn test_window_starts_at_last_session_in_project() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let log = ActivityLog::new(tmp.path());
+        let session = |time, project: &str| Activity::Session {
+            time,
+            turns: 1,
+            skills: Default::default(),
+            project: Some(project.to_string()),
+        };
+        log.record(&session(NOW - 86_400, "/work/a")).unwrap();
+        log.record(&session(NOW - 3_600, "/work/b")).unwrap();
+
+        assert_eq!(last_session(&log, "/work/a", NOW), Some(NOW - 86_400));
+        assert_eq!(window_start(last_session(&log, "/work/c", NOW), NOW), NOW - FALLBACK_SECS);
+    }
+
+    #[tokio::test]
+    async fn test_gather_skips_non_git_directories() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        assert!(gather(tmp.path(), 0, 5).await.is_none());
+    }
+}
@@ -1,10 +1,88 @@
 //! Crew Manager - Create, list, and manage crew members
 
 use super::{Crew, CrewTemplate};
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// File naming the active crew, inside the crew directory
+const ACTIVE_FILE: &str = ".active";
+
+/// Locked while a process changes the crew directory. The file stays;
+/// only the OS lock on it is taken and released.
+const LOCK_FILE: &str = ".lock";
+
+/// How long to wait for another process to release the lock
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Exclusive hold on a crew directory, released on drop, or by the OS when
+/// the holding process dies
+struct DirLock {
+    _file: fs::File,
+}
+
+impl DirLock {
+    fn acquire(dir: &Path) -> Result<Self> {
+        let path = dir.join(LOCK_FILE);
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to lock {}", dir.display()))?;
+        let started = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Self { _file: file }),
+                Err(fs::TryLockError::WouldBlock) => {
+                    if started.elapsed() > LOCK_TIMEOUT {
+                        anyhow::bail!(
+                            "Crew directory {} is locked by another webrana process",
+                            dir.display()
+                        );
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(fs::TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("Failed to lock {}", dir.display()))
+                }
+            }
+        }
+    }
+}
+
+/// Write via a synced sibling temp file and rename, so readers never see a
+/// partial file and a crash leaves the old or the new content
+fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .context("Invalid file path")?;
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+    let written = fs::File::create(&tmp).and_then(|mut file| {
+        file.write_all(content.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&tmp, path)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// What `CrewManager::repair` did
+#[derive(Debug, Default)]
+pub struct RepairReport {
+    /// Unparseable crew files, renamed to `.broken`, with the parse error
+    pub broken: Vec<(PathBuf, String)>,
+    /// Active crew pointer removed because its crew is gone
+    pub cleared_active: Option<String>,
+    /// Crews loaded after the repair
+    pub crews: usize,
+}
 
 /// Manages crew members (custom AI personas)
 pub struct CrewManager {
@@ -16,6 +94,12 @@ pub struct CrewManager {
     
     /// Currently active crew
     active_crew: Option<String>,
+
+    /// Problems found while loading: unreadable crew files, a stale active pointer
+    warnings: Vec<String>,
+
+    /// Only report problems, never touch the directory (`inspect`)
+    read_only: bool,
 }
 
 impl CrewManager {
@@ -36,6 +120,8 @@ impl CrewManager {
             crew_dir,
             crews: HashMap::new(),
             active_crew: None,
+            warnings: Vec::new(),
+            read_only: false,
         };
 
         // Load existing crews
@@ -44,6 +130,20 @@ impl CrewManager {
         Ok(manager)
     }
 
+    /// Load `crew_dir` to see what is wrong with it, without creating it or
+    /// clearing a stale active pointer (`doctor` without `--fix`)
+    pub fn inspect(crew_dir: PathBuf) -> Result<Self> {
+        let mut manager = Self {
+            crew_dir,
+            crews: HashMap::new(),
+            active_crew: None,
+            warnings: Vec::new(),
+            read_only: true,
+        };
+        manager.load_all()?;
+        Ok(manager)
+    }
+
     /// Get default crew directory
     fn default_crew_dir() -> Result<PathBuf> {
         let dir = directories::ProjectDirs::from("dev", "webrana", "webrana-cli")
@@ -52,30 +152,41 @@ impl CrewManager {
         Ok(dir)
     }
 
-    /// Load all crews from disk
+    /// Load all crews from disk. Unparseable files are skipped with a
+    /// warning; an active pointer to a missing crew is cleared.
     fn load_all(&mut self) -> Result<()> {
+        self.crews.clear();
+        self.active_crew = None;
         if !self.crew_dir.exists() {
             return Ok(());
         }
 
-        for entry in fs::read_dir(&self.crew_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.extension().map(|e| e == "yaml" || e == "yml").unwrap_or(false) {
-                if let Ok(crew) = self.load_crew_file(&path) {
+        for path in self.crew_files()? {
+            match self.load_crew_file(&path) {
+                Ok(crew) => {
                     self.crews.insert(crew.id.clone(), crew);
                 }
+                Err(e) => self.warn(format!(
+                    "Skipping unreadable crew file {}: {} (run `webrana doctor --fix` to set it aside)",
+                    path.display(),
+                    e
+                )),
             }
         }
 
         // Load active crew from state file
-        let state_file = self.crew_dir.join(".active");
-        if state_file.exists() {
-            if let Ok(active) = fs::read_to_string(&state_file) {
-                let active = active.trim().to_string();
-                if self.crews.contains_key(&active) {
-                    self.active_crew = Some(active);
+        if let Some(active) = self.read_active() {
+            if self.crews.contains_key(&active) {
+                self.active_crew = Some(active);
+            } else {
+                self.warn(format!("Active crew '{}' no longer exists; using the default agent", active));
+                if self.read_only {
+                    return Ok(());
+                }
+                let _lock = DirLock::acquire(&self.crew_dir)?;
+                // Another process may have just pointed it somewhere valid
+                if self.read_active().as_deref() == Some(active.as_str()) {
+                    let _ = fs::remove_file(self.crew_dir.join(ACTIVE_FILE));
                 }
             }
         }
@@ -83,6 +194,53 @@ impl CrewManager {
         Ok(())
     }
 
+    fn warn(&mut self, message: String) {
+        tracing::warn!("{}", message);
+        self.warnings.push(message);
+    }
+
+    /// Problems found by the last load
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// `*.yaml`/`*.yml` files in the crew directory
+    fn crew_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.crew_dir)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            if !name.starts_with('.') && path.extension().is_some_and(|e| e == "yaml" || e == "yml") {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// The crew ID in the active pointer file, if any
+    fn read_active(&self) -> Option<String> {
+        fs::read_to_string(self.crew_dir.join(ACTIVE_FILE))
+            .ok()
+            .map(|active| active.trim().to_string())
+            .filter(|active| !active.is_empty())
+    }
+
+    fn crew_path(&self, id: &str) -> PathBuf {
+        self.crew_dir.join(format!("{}.yaml", id))
+    }
+
+    /// Whether a crew file on disk (usually `<id>.yaml`) defines `id`
+    fn on_disk(&self, id: &str) -> Result<bool> {
+        if self.crew_path(id).exists() {
+            return Ok(true);
+        }
+        Ok(self
+            .crew_files()?
+            .iter()
+            .any(|path| self.load_crew_file(path).is_ok_and(|crew| crew.id == id)))
+    }
+
     /// Load a single crew file
     fn load_crew_file(&self, path: &Path) -> Result<Crew> {
         let content = fs::read_to_string(path)?;
@@ -92,33 +250,46 @@ impl CrewManager {
 
     /// Save a crew to disk
     fn save_crew(&self, crew: &Crew) -> Result<()> {
-        let path = self.crew_dir.join(format!("{}.yaml", crew.id));
         let content = serde_yaml::to_string(crew)?;
-        fs::write(path, content)?;
-        Ok(())
+        write_atomic(&self.crew_path(&crew.id), &content)
     }
 
-    /// Create a new crew member
-    pub fn create(&mut self, crew: Crew) -> Result<()> {
-        if self.crews.contains_key(&crew.id) {
+    /// Save a crew that must not exist yet, here or on disk (another
+    /// process may have created it since we loaded)
+    fn insert_new(&mut self, crew: &Crew) -> Result<()> {
+        let _lock = DirLock::acquire(&self.crew_dir)?;
+        if self.crews.contains_key(&crew.id) || self.crew_path(&crew.id).exists() {
             return Err(anyhow!("Crew '{}' already exists", crew.id));
         }
 
-        self.save_crew(&crew)?;
-        self.crews.insert(crew.id.clone(), crew);
+        self.save_crew(crew)?;
+        self.crews.insert(crew.id.clone(), crew.clone());
         Ok(())
     }
 
+    /// Create a new crew member
+    pub fn create(&mut self, crew: Crew) -> Result<()> {
+        self.insert_new(&crew)
+    }
+
     /// Create from template
     pub fn create_from_template(&mut self, template: CrewTemplate) -> Result<Crew> {
         let crew = template.create();
-        
-        if self.crews.contains_key(&crew.id) {
-            return Err(anyhow!("Crew '{}' already exists", crew.id));
-        }
+        self.insert_new(&crew)?;
+        Ok(crew)
+    }
 
-        self.save_crew(&crew)?;
-        self.crews.insert(crew.id.clone(), crew.clone());
+    /// Clone an existing crew (or built-in template) under a new ID
+    pub fn clone_crew(&mut self, src: &str, new_id: &str, name: Option<&str>) -> Result<Crew> {
+        let source = match self.crews.get(src) {
+            Some(crew) => crew.clone(),
+            None => CrewTemplate::from_name(src)
+                .map(|template| template.create())
+                .ok_or_else(|| anyhow!("Crew '{}' not found", src))?,
+        };
+
+        let crew = source.fork(new_id, name);
+        self.insert_new(&crew)?;
         Ok(crew)
     }
 
@@ -137,52 +308,81 @@ impl CrewManager {
         self.crews.values().collect()
     }
 
-    /// Delete a crew
+    /// Delete a crew, clearing the active pointer if it named this crew
     pub fn delete(&mut self, id: &str) -> Result<bool> {
-        if let Some(_crew) = self.crews.remove(id) {
-            let path = self.crew_dir.join(format!("{}.yaml", id));
-            if path.exists() {
-                fs::remove_file(path)?;
-            }
-            
-            // Clear active if it was this crew
-            if self.active_crew.as_deref() == Some(id) {
-                self.active_crew = None;
-                let state_file = self.crew_dir.join(".active");
-                let _ = fs::remove_file(state_file);
-            }
-            
-            Ok(true)
-        } else {
-            Ok(false)
+        let _lock = DirLock::acquire(&self.crew_dir)?;
+        let path = self.crew_path(id);
+        let known = self.crews.remove(id).is_some();
+        let on_disk = path.exists();
+        if on_disk {
+            fs::remove_file(path)?;
         }
+
+        if self.active_crew.as_deref() == Some(id) {
+            self.active_crew = None;
+        }
+        if self.read_active().as_deref() == Some(id) {
+            let _ = fs::remove_file(self.crew_dir.join(ACTIVE_FILE));
+        }
+
+        Ok(known || on_disk)
     }
 
     /// Set active crew
-    pub fn set_active(&mut self, id: &str) -> Result<()> {
+    pub fn set_active(&mut self, id: &str) -> Result<&Crew> {
+        let _lock = DirLock::acquire(&self.crew_dir)?;
+        // Deleted by another process since we loaded
+        if !self.on_disk(id)? {
+            self.crews.remove(id);
+        }
         if !self.crews.contains_key(id) {
             return Err(anyhow!("Crew '{}' not found", id));
         }
 
+        write_atomic(&self.crew_dir.join(ACTIVE_FILE), id)?;
         self.active_crew = Some(id.to_string());
-        
-        // Persist active state
-        let state_file = self.crew_dir.join(".active");
-        fs::write(state_file, id)?;
-        
-        Ok(())
+        Ok(&self.crews[id])
     }
 
     /// Clear active crew
     pub fn clear_active(&mut self) -> Result<()> {
+        let _lock = DirLock::acquire(&self.crew_dir)?;
         self.active_crew = None;
-        let state_file = self.crew_dir.join(".active");
+        let state_file = self.crew_dir.join(ACTIVE_FILE);
         if state_file.exists() {
             fs::remove_file(state_file)?;
         }
         Ok(())
     }
 
+    /// Set aside crew files that don't parse (renamed to `<file>.broken`),
+    /// drop an active pointer to a missing crew, and reload
+    pub fn repair(&mut self) -> Result<RepairReport> {
+        let mut report = RepairReport::default();
+        {
+            let _lock = DirLock::acquire(&self.crew_dir)?;
+            for path in self.crew_files()? {
+                if let Err(e) = self.load_crew_file(&path) {
+                    let mut broken = path.clone().into_os_string();
+                    broken.push(".broken");
+                    fs::rename(&path, &broken)?;
+                    report.broken.push((PathBuf::from(broken), e.to_string()));
+                }
+            }
+            if let Some(active) = self.read_active() {
+                if !self.on_disk(&active)? {
+                    fs::remove_file(self.crew_dir.join(ACTIVE_FILE))?;
+                    report.cleared_active = Some(active);
+                }
+            }
+        }
+
+        self.warnings.clear();
+        self.load_all()?;
+        report.crews = self.crews.len();
+        Ok(report)
+    }
+
     /// Get active crew
     pub fn active(&self) -> Option<&Crew> {
         self.active_crew.as_ref().and_then(|id| self.crews.get(id))
@@ -195,6 +395,7 @@ impl CrewManager {
 
     /// Update a crew
     pub fn update(&mut self, crew: Crew) -> Result<()> {
+        let _lock = DirLock::acquire(&self.crew_dir)?;
         if !self.crews.contains_key(&crew.id) {
             return Err(anyhow!("Crew '{}' not found", crew.id));
         }
@@ -213,14 +414,15 @@ impl CrewManager {
 
     /// Import crew from YAML string
     pub fn import(&mut self, yaml: &str) -> Result<Crew> {
-        let crew: Crew = serde_yaml::from_str(yaml)?;
-        
-        if self.crews.contains_key(&crew.id) {
-            return Err(anyhow!("Crew '{}' already exists", crew.id));
-        }
+        self.import_from(yaml, None)
+    }
 
-        self.save_crew(&crew)?;
-        self.crews.insert(crew.id.clone(), crew.clone());
+    /// Import crew from YAML string, recording where it came from
+    pub fn import_from(&mut self, yaml: &str, source: Option<&str>) -> Result<Crew> {
+        let mut crew: Crew = serde_yaml::from_str(yaml)?;
+        crew.validate()?;
+        crew.source = source.map(str::to_string);
+        self.insert_new(&crew)?;
         Ok(crew)
     }
 
@@ -241,6 +443,8 @@ impl Default for CrewManager {
             crew_dir: PathBuf::from(".webrana/crew"),
             crews: HashMap::new(),
             active_crew: None,
+            warnings: Vec::new(),
+            read_only: false,
         })
     }
 }
@@ -283,6 +487,31 @@ mod tests {
         assert!(manager.get("code-reviewer").is_some());
     }
 
+    #[test]
+    fn test_clone_crew() {
+        let tmp = TempDir::new().unwrap();
+        let mut manager = CrewManager::with_dir(tmp.path().to_path_buf()).unwrap();
+
+        // Clone straight from a template
+        let clone = manager.clone_crew("code-reviewer", "my-reviewer", None).unwrap();
+        let template = CrewTemplate::CodeReviewer.create();
+        assert_eq!(clone.id, "my-reviewer");
+        assert_ne!(clone.id, template.id);
+        assert_eq!(clone.system_prompt, template.system_prompt);
+        assert!(tmp.path().join("my-reviewer.yaml").exists());
+
+        // Clone an existing crew with a new name
+        let second = manager
+            .clone_crew("my-reviewer", "second", Some("Second Reviewer"))
+            .unwrap();
+        assert_eq!(second.name, "Second Reviewer");
+        assert_eq!(second.system_prompt, clone.system_prompt);
+
+        // Existing target and unknown source are errors
+        assert!(manager.clone_crew("code-reviewer", "my-reviewer", None).is_err());
+        assert!(manager.clone_crew("does-not-exist", "other", None).is_err());
+    }
+
     #[test]
     fn test_import_export() {
         let tmp = TempDir::new().unwrap();
@@ -300,4 +529,99 @@ mod tests {
         manager2.import(&yaml).unwrap();
         assert!(manager2.get("export-test").is_some());
     }
+
+    #[test]
+    fn test_truncated_yaml_is_skipped_and_repaired() {
+        let tmp = TempDir::new().unwrap();
+        let mut manager = CrewManager::with_dir(tmp.path().to_path_buf()).unwrap();
+        manager.create(Crew::new("good", "Good", "Fine", "Prompt")).unwrap();
+        manager.create(Crew::new("cut", "Cut", "Truncated", "Prompt")).unwrap();
+        let cut = tmp.path().join("cut.yaml");
+        let yaml = fs::read_to_string(&cut).unwrap();
+        fs::write(&cut, &yaml[..yaml.len() / 3]).unwrap();
+
+        let mut reloaded = CrewManager::with_dir(tmp.path().to_path_buf()).unwrap();
+        assert_eq!(reloaded.count(), 1);
+        assert!(reloaded.get("good").is_some());
+        assert_eq!(reloaded.warnings().len(), 1);
+        assert!(reloaded.warnings()[0].contains("cut.yaml"));
+
+        let report = reloaded.repair().unwrap();
+        assert_eq!(report.broken.len(), 1);
+        assert_eq!(report.broken[0].0, tmp.path().join("cut.yaml.broken"));
+        assert_eq!(report.crews, 1);
+        assert!(!cut.exists());
+        assert!(reloaded.warnings().is_empty());
+        assert!(CrewManager::with_dir(tmp.path().to_path_buf()).unwrap().warnings().is_empty());
+    }
+
+    #[test]
+    fn test_stale_active_pointer_is_cleared() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(tmp.path().join(ACTIVE_FILE), "gone\n").unwrap();
+
+        let mut manager = CrewManager::with_dir(tmp.path().to_path_buf()).unwrap();
+        assert!(manager.active().is_none());
+        assert!(manager.warnings()[0].contains("'gone'"));
+        assert!(!tmp.path().join(ACTIVE_FILE).exists());
+        assert!(manager.set_active("gone").is_err());
+
+        // Inspecting reports a stale pointer but leaves it for `repair`
+        fs::write(tmp.path().join(ACTIVE_FILE), "gone\n").unwrap();
+        let inspected = CrewManager::inspect(tmp.path().to_path_buf()).unwrap();
+        assert!(inspected.warnings()[0].contains("'gone'"));
+        assert!(tmp.path().join(ACTIVE_FILE).exists());
+        assert_eq!(manager.repair().unwrap().cleared_active.as_deref(), Some("gone"));
+
+        // Deleting the active crew clears the pointer
+        manager.create(Crew::new("here", "Here", "Present", "Prompt")).unwrap();
+        assert_eq!(manager.set_active("here").unwrap().name, "Here");
+        manager.delete("here").unwrap();
+        assert!(!tmp.path().join(ACTIVE_FILE).exists());
+
+        // Another process deleted the crew this manager still has loaded
+        manager.create(Crew::new("here", "Here", "Present", "Prompt")).unwrap();
+        fs::remove_file(tmp.path().join("here.yaml")).unwrap();
+        assert!(manager.set_active("here").is_err());
+        assert!(manager.get("here").is_none());
+    }
+
+    #[test]
+    fn test_concurrent_create_and_delete() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path().to_path_buf();
+
+        let workers: Vec<_> = (0..2)
+            .map(|worker| {
+                let dir = dir.clone();
+                std::thread::spawn(move || {
+                    let mut manager = CrewManager::with_dir(dir).unwrap();
+                    let mut created_shared = 0;
+                    for i in 0..20 {
+                        let id = format!("w{}-{}", worker, i);
+                        manager.create(Crew::new(&id, &id, "Worker crew", "Prompt")).unwrap();
+                        if i % 2 == 0 {
+                            assert!(manager.delete(&id).unwrap());
+                        }
+                        if manager.create(Crew::new("shared", "Shared", "Both try", "Prompt")).is_ok() {
+                            created_shared += 1;
+                        }
+                    }
+                    created_shared
+                })
+            })
+            .collect();
+        let created: usize = workers.into_iter().map(|w| w.join().unwrap()).sum();
+        assert_eq!(created, 1);
+
+        let manager = CrewManager::with_dir(dir.clone()).unwrap();
+        assert!(manager.warnings().is_empty());
+        assert_eq!(manager.count(), 21);
+        let leftovers: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with('.') && name != LOCK_FILE)
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+    }
 }
@@ -5,6 +5,7 @@
 
 mod persona;
 mod manager;
+pub mod remote;
 
 pub use persona::{Crew, CrewConfig, CrewPermissions, CrewTemplate};
 pub use manager::CrewManager;
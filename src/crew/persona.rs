@@ -1,5 +1,6 @@
 //! Crew Persona Definition
 
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
@@ -41,6 +42,10 @@ pub struct Crew {
     /// Creation timestamp
     #[serde(default)]
     pub created_at: Option<String>,
+
+    /// Where an imported crew came from (file path or URL)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
 }
 
 fn default_version() -> String {
@@ -111,6 +116,32 @@ fn default_true() -> bool {
     true
 }
 
+impl CrewPermissions {
+    /// One `Name: value` line per permission, as `crew show` lists them
+    pub fn summary(&self) -> Vec<String> {
+        let skills = |set: &HashSet<String>| {
+            let mut names: Vec<&str> = set.iter().map(String::as_str).collect();
+            names.sort_unstable();
+            names.join(", ")
+        };
+        let mut lines = vec![
+            format!("Shell: {}", self.shell_access),
+            format!("File Read: {}", self.file_read),
+            format!("File Write: {}", self.file_write),
+            format!("Network: {}", self.network_access),
+        ];
+        lines.push(if self.allowed_skills.is_empty() {
+            "Skills: all".to_string()
+        } else {
+            format!("Skills: {}", skills(&self.allowed_skills))
+        });
+        if !self.denied_skills.is_empty() {
+            lines.push(format!("Denied Skills: {}", skills(&self.denied_skills)));
+        }
+        lines
+    }
+}
+
 impl Default for CrewPermissions {
     fn default() -> Self {
         Self {
@@ -128,7 +159,7 @@ impl Crew {
     /// Create a new crew member
     pub fn new(id: &str, name: &str, description: &str, system_prompt: &str) -> Self {
         Self {
-            id: id.to_lowercase().replace(' ', "-"),
+            id: Self::normalize_id(id),
             name: name.to_string(),
             description: description.to_string(),
             system_prompt: system_prompt.to_string(),
@@ -138,7 +169,50 @@ impl Crew {
             author: None,
             version: "1.0.0".to_string(),
             created_at: Some(chrono_lite()),
+            source: None,
+        }
+    }
+
+    /// Normalize a crew ID (lowercase, spaces replaced with dashes)
+    pub fn normalize_id(id: &str) -> String {
+        id.to_lowercase().replace(' ', "-")
+    }
+
+    /// Create an editable copy of this crew under a new ID
+    pub fn fork(&self, new_id: &str, name: Option<&str>) -> Crew {
+        let mut crew = self.clone();
+        crew.id = Self::normalize_id(new_id);
+        if let Some(name) = name {
+            crew.name = name.to_string();
         }
+        crew.version = default_version();
+        crew.created_at = Some(chrono_lite());
+        crew.source = None;
+        crew
+    }
+
+    /// Check a crew loaded from outside (an import) before installing it
+    pub fn validate(&self) -> Result<()> {
+        if self.id.is_empty() {
+            bail!("Crew has no id");
+        }
+        if self.id != Self::normalize_id(&self.id)
+            || !self.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("Invalid crew id '{}': use lowercase letters, digits, '-' and '_'", self.id);
+        }
+        if self.name.trim().is_empty() {
+            bail!("Crew '{}' has no name", self.id);
+        }
+        if self.system_prompt.trim().is_empty() {
+            bail!("Crew '{}' has no system_prompt", self.id);
+        }
+        if let Some(temperature) = self.config.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                bail!("Crew '{}' has temperature {} outside 0.0-2.0", self.id, temperature);
+            }
+        }
+        Ok(())
     }
 
     /// Check if a skill is allowed
@@ -208,6 +282,7 @@ Prioritize issues by severity: Critical > High > Medium > Low."#.to_string(),
                 author: Some("Webrana Team".to_string()),
                 version: "1.0.0".to_string(),
                 created_at: Some(chrono_lite()),
+                source: None,
             },
             
             CrewTemplate::BugHunter => Crew {
@@ -233,6 +308,7 @@ Use systematic debugging approaches. Always verify fixes don't introduce new iss
                 author: Some("Webrana Team".to_string()),
                 version: "1.0.0".to_string(),
                 created_at: Some(chrono_lite()),
+                source: None,
             },
 
             CrewTemplate::DocWriter => Crew {
@@ -260,6 +336,7 @@ Use markdown formatting. Include code examples. Write for your audience level."#
                 author: Some("Webrana Team".to_string()),
                 version: "1.0.0".to_string(),
                 created_at: Some(chrono_lite()),
+                source: None,
             },
 
             CrewTemplate::Refactorer => Crew {
@@ -286,6 +363,7 @@ Always ensure tests pass after refactoring. Make small, incremental changes."#.t
                 author: Some("Webrana Team".to_string()),
                 version: "1.0.0".to_string(),
                 created_at: Some(chrono_lite()),
+                source: None,
             },
 
             CrewTemplate::TestEngineer => Crew {
@@ -311,6 +389,7 @@ Follow testing best practices. Use appropriate assertions. Test behavior, not im
                 author: Some("Webrana Team".to_string()),
                 version: "1.0.0".to_string(),
                 created_at: Some(chrono_lite()),
+                source: None,
             },
 
             CrewTemplate::SecurityAuditor => Crew {
@@ -339,6 +418,7 @@ Report findings with severity levels. Provide remediation guidance."#.to_string(
                 author: Some("Webrana Team".to_string()),
                 version: "1.0.0".to_string(),
                 created_at: Some(chrono_lite()),
+                source: None,
             },
 
             CrewTemplate::DevOpsEngineer => Crew {
@@ -364,6 +444,7 @@ Follow infrastructure-as-code principles. Prioritize security and reliability."#
                 author: Some("Webrana Team".to_string()),
                 version: "1.0.0".to_string(),
                 created_at: Some(chrono_lite()),
+                source: None,
             },
         }
     }
@@ -409,6 +490,19 @@ fn chrono_lite() -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_permissions_summary() {
+        let permissions = CrewPermissions {
+            denied_skills: ["write_file".to_string(), "delete_file".to_string()].into(),
+            shell_access: false,
+            ..Default::default()
+        };
+        let summary = permissions.summary();
+        assert_eq!(summary[0], "Shell: false");
+        assert_eq!(summary[4], "Skills: all");
+        assert_eq!(summary[5], "Denied Skills: delete_file, write_file");
+    }
+
     #[test]
     fn test_crew_creation() {
         let crew = Crew::new(
@@ -448,6 +542,18 @@ mod tests {
         assert!(!reviewer.permissions.file_write); // Read-only
     }
 
+    #[test]
+    fn test_fork() {
+        let mut reviewer = CrewTemplate::CodeReviewer.create();
+        reviewer.version = "2.3.0".to_string();
+
+        let fork = reviewer.fork("My Reviewer", Some("Mine"));
+        assert_eq!(fork.id, "my-reviewer");
+        assert_eq!(fork.name, "Mine");
+        assert_eq!(fork.version, "1.0.0");
+        assert_eq!(fork.system_prompt, reviewer.system_prompt);
+    }
+
     #[test]
     fn test_template_lookup() {
         assert_eq!(CrewTemplate::from_name("reviewer"), Some(CrewTemplate::CodeReviewer));
@@ -0,0 +1,235 @@
+//! Remote crews - fetch a crew definition shared by URL or gist
+
+use anyhow::{bail, Context, Result};
+use url::Url;
+
+use crate::util::{connectivity, http};
+
+/// Largest crew definition we download
+pub const MAX_CREW_BYTES: usize = 256 * 1024;
+
+const MAX_REDIRECTS: usize = 5;
+
+/// Content types a crew YAML may be served as. Raw file hosts (gists,
+/// GitHub raw) use text/plain.
+const YAML_TYPES: &[&str] = &[
+    "application/yaml",
+    "application/x-yaml",
+    "text/yaml",
+    "text/x-yaml",
+    "text/plain",
+    "application/octet-stream",
+];
+
+/// `https://gist.github.com/<user>/<id>` serves an HTML page; its raw
+/// content lives under gist.githubusercontent.com
+pub fn raw_url(url: &Url) -> Url {
+    if url.host_str() != Some("gist.github.com") {
+        return url.clone();
+    }
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+    match segments[..] {
+        [user, id] => Url::parse(&format!("https://gist.githubusercontent.com/{}/{}/raw", user, id))
+            .unwrap_or_else(|_| url.clone()),
+        _ => url.clone(),
+    }
+}
+
+/// A crew grants its own permissions, so it must not be swapped in transit:
+/// only https is fetched, plus plain http to this machine
+fn check_scheme(url: &Url) -> Result<()> {
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if connectivity::is_loopback(url) => Ok(()),
+        "http" => bail!("Refusing to fetch a crew over plain http: {}; use an https URL", url),
+        _ => bail!("Only https URLs can be imported, got {}", url),
+    }
+}
+
+/// Download a crew YAML. Redirects are followed (up to 5), each hop held to
+/// https; anything but a 200 with a YAML or plain-text body under
+/// `MAX_CREW_BYTES` is an error. Returns the YAML and the URL it was finally
+/// served from.
+pub async fn fetch(url: &str) -> Result<(String, String)> {
+    http::ensure_online("crew import --url")?;
+    let mut url = Url::parse(url.trim()).with_context(|| format!("Invalid URL '{}'", url))?;
+    url = raw_url(&url);
+    let client = http::configured_builder()?
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+
+    for _ in 0..=MAX_REDIRECTS {
+        check_scheme(&url)?;
+        let mut response = client
+            .get(url.clone())
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch {}", url))?;
+        let status = response.status();
+
+        if status.is_redirection() {
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .with_context(|| format!("{} redirected ({}) without a Location", url, status))?;
+            url = url.join(location)?;
+            continue;
+        }
+        if status != reqwest::StatusCode::OK {
+            bail!("{} returned {}", url, status);
+        }
+
+        let kind = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(';').next().unwrap_or_default().trim().to_ascii_lowercase());
+        if let Some(kind) = &kind {
+            if !YAML_TYPES.contains(&kind.as_str()) {
+                bail!("{} is {}, not a crew YAML file; link to the raw file instead", url, kind);
+            }
+        }
+        if response.content_length().is_some_and(|len| len as usize > MAX_CREW_BYTES) {
+            bail!("{} is larger than {} KB", url, MAX_CREW_BYTES / 1024);
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() > MAX_CREW_BYTES {
+                bail!("{} is larger than {} KB", url, MAX_CREW_BYTES / 1024);
+            }
+        }
+        let yaml = String::from_utf8(body).with_context(|| format!("{} is not UTF-8 text", url))?;
+        // An untyped body still has to look like YAML, not a web page
+        if kind.is_none() && yaml.trim_start().starts_with('<') {
+            bail!("{} looks like HTML, not a crew YAML file", url);
+        }
+        return Ok((yaml, url.to_string()));
+    }
+    bail!("{} redirected more than {} times", url, MAX_REDIRECTS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crew::CrewManager;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const CREW_YAML: &str =
+        "id: shared-reviewer\nname: Shared Reviewer\ndescription: From a URL\nsystem_prompt: Review carefully.\n";
+
+    /// Serve `(path, status, content type, body)` routes; unknown paths 404
+    async fn serve(routes: Vec<(&'static str, &'static str, &'static str, String)>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let head = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let (status, headers, body) = match routes.iter().find(|(p, ..)| *p == path) {
+                    Some((_, status, headers, body)) => (*status, *headers, body.clone()),
+                    None => ("404 Not Found", "content-type: text/plain", "missing".to_string()),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\n{}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    headers,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+        format!("http://127.0.0.1:{}", port)
+    }
+
+    #[tokio::test]
+    async fn test_import_from_url() {
+        let base = serve(vec![
+            ("/crew.yaml", "200 OK", "content-type: application/x-yaml", CREW_YAML.to_string()),
+            ("/moved", "302 Found", "location: /crew.yaml", String::new()),
+        ])
+        .await;
+
+        let (yaml, served_from) = fetch(&format!("{}/moved", base)).await.unwrap();
+        assert_eq!(served_from, format!("{}/crew.yaml", base));
+
+        let tmp = TempDir::new().unwrap();
+        let mut manager = CrewManager::with_dir(tmp.path().to_path_buf()).unwrap();
+        let url = format!("{}/moved", base);
+        let crew = manager.import_from(&yaml, Some(&url)).unwrap();
+        assert_eq!(crew.id, "shared-reviewer");
+
+        // The source survives a reload
+        let reloaded = CrewManager::with_dir(tmp.path().to_path_buf()).unwrap();
+        assert_eq!(reloaded.get("shared-reviewer").unwrap().source.as_deref(), Some(url.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_html_and_errors_rejected() {
+        let big = format!("{}description: {}\n", CREW_YAML, "x".repeat(MAX_CREW_BYTES));
+        let base = serve(vec![
+            (
+                "/page",
+                "200 OK",
+                "content-type: text/html; charset=utf-8",
+                "<html><body>crew</body></html>".to_string(),
+            ),
+            ("/big.yaml", "200 OK", "content-type: text/yaml", big),
+            ("/loop", "301 Moved Permanently", "location: /loop", String::new()),
+        ])
+        .await;
+
+        let err = |path: &str| {
+            let url = format!("{}{}", base, path);
+            async move { fetch(&url).await.unwrap_err().to_string() }
+        };
+        let html = err("/page").await;
+        assert!(html.contains("is text/html, not a crew YAML file"), "{}", html);
+        assert!(err("/gone.yaml").await.ends_with("returned 404 Not Found"));
+        assert!(err("/big.yaml").await.contains("larger than 256 KB"));
+        assert!(err("/loop").await.contains("redirected more than 5 times"));
+        assert!(fetch("ftp://example.com/crew.yaml").await.unwrap_err().to_string().contains("Only https"));
+    }
+
+    #[test]
+    fn test_plain_http_refused_off_this_machine() {
+        let check = |url: &str| check_scheme(&Url::parse(url).unwrap());
+        assert!(check("https://example.com/crew.yaml").is_ok());
+        assert!(check("http://127.0.0.1:8080/crew.yaml").is_ok());
+        assert!(check("http://localhost/crew.yaml").is_ok());
+        let err = check("http://example.com/crew.yaml").unwrap_err().to_string();
+        assert!(err.contains("plain http"), "{}", err);
+    }
+
+    #[test]
+    fn test_gist_raw_url() {
+        let raw = |url: &str| raw_url(&Url::parse(url).unwrap()).to_string();
+        assert_eq!(
+            raw("https://gist.github.com/ada/0123abcd"),
+            "https://gist.githubusercontent.com/ada/0123abcd/raw"
+        );
+        assert_eq!(raw("https://example.com/crew.yaml"), "https://example.com/crew.yaml");
+    }
+
+    #[test]
+    fn test_invalid_crew_refused() {
+        let tmp = TempDir::new().unwrap();
+        let mut manager = CrewManager::with_dir(tmp.path().to_path_buf()).unwrap();
+        let err = manager
+            .import_from("id: Bad Id!\nname: x\ndescription: y\nsystem_prompt: z\n", Some("https://x"))
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid crew id"), "{}", err);
+        assert!(manager.import("id: ok\nname: Ok\ndescription: y\nsystem_prompt: ''\n").is_err());
+        assert_eq!(manager.count(), 0);
+    }
+}
@@ -0,0 +1,103 @@
+// ============================================
+// WEBRANA CLI - Embedding Export
+// Stored chunks with their vectors as JSON Lines, for analysis in other
+// tools
+// ============================================
+
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Write;
+
+use super::StoredEmbedding;
+
+/// Exports estimated above this size ask before writing
+pub const LARGE_EXPORT_BYTES: u64 = 512 * 1024 * 1024;
+
+/// One line of an export
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportRecord<'a> {
+    pub id: &'a str,
+    pub file: Option<&'a str>,
+    /// 1-based, inclusive
+    pub start_line: Option<usize>,
+    /// 1-based, inclusive
+    pub end_line: Option<usize>,
+    pub text: &'a str,
+    pub vector: &'a [f32],
+}
+
+impl<'a> ExportRecord<'a> {
+    pub fn new(stored: &'a StoredEmbedding) -> Self {
+        let number = |key: &str| stored.metadata.get(key).and_then(|v| v.parse::<usize>().ok());
+        Self {
+            id: &stored.id,
+            file: stored.metadata.get("file").map(String::as_str),
+            // Stored start lines are 0-based, end lines exclusive
+            start_line: number("start_line").map(|line| line + 1),
+            end_line: number("end_line"),
+            text: &stored.text,
+            vector: &stored.embedding,
+        }
+    }
+}
+
+/// Rough size of `count` exported chunks of `dimension`-wide vectors
+/// averaging `text_bytes` of text: about 12 bytes per written float
+pub fn estimated_size(count: usize, dimension: usize, text_bytes: usize) -> u64 {
+    (count * (dimension * 12 + text_bytes + 120)) as u64
+}
+
+/// Write one JSON object per chunk to `out`, returning how many were written
+pub fn write_jsonl<'a>(
+    chunks: impl IntoIterator<Item = &'a StoredEmbedding>,
+    out: &mut dyn Write,
+) -> Result<usize> {
+    let mut written = 0;
+    for stored in chunks {
+        serde_json::to_writer(&mut *out, &ExportRecord::new(stored))?;
+        out.write_all(b"\n")?;
+        written += 1;
+    }
+    out.flush()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::EmbeddingStore;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_jsonl_export_one_record_per_chunk() {
+        let mut store = EmbeddingStore::new(4);
+        for i in 0..3 {
+            store.add(StoredEmbedding {
+                id: format!("src/lib.rs:{}", i),
+                text: format!("fn f{}() {{}}", i),
+                embedding: vec![i as f32, 0.5, -0.25, 1.0],
+                metadata: HashMap::from([
+                    ("file".to_string(), "src/lib.rs".to_string()),
+                    ("start_line".to_string(), (i * 10).to_string()),
+                    ("end_line".to_string(), (i * 10 + 10).to_string()),
+                ]),
+            });
+        }
+
+        let mut out = Vec::new();
+        assert_eq!(write_jsonl(store.iter(), &mut out).unwrap(), 3);
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        for line in &lines {
+            assert_eq!(line["vector"].as_array().unwrap().len(), store.dimension());
+        }
+        assert_eq!(lines[1]["id"], "src/lib.rs:1");
+        assert_eq!(lines[1]["file"], "src/lib.rs");
+        assert_eq!((&lines[1]["start_line"], &lines[1]["end_line"]), (&11.into(), &20.into()));
+        assert_eq!(lines[2]["text"], "fn f2() {}");
+    }
+}
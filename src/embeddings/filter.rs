@@ -0,0 +1,125 @@
+// ============================================
+// WEBRANA CLI - Chunk Metadata Filter
+// Scopes semantic search by file, language or symbol
+// ============================================
+
+use std::collections::HashMap;
+
+use crate::core::glob_match;
+
+/// Restricts which chunks a search may return. Empty fields match anything.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataFilter {
+    /// Glob over the chunk's file. It is tried against the path and each of
+    /// its trailing sub-paths, so `src/auth` and `*.rs` work from any index root.
+    pub file_glob: Option<String>,
+    /// Language name (`rust`) or file extension (`rs`)
+    pub language: Option<String>,
+    /// Case-insensitive substring of a symbol the chunk overlaps
+    pub symbol_contains: Option<String>,
+}
+
+impl MetadataFilter {
+    pub fn is_empty(&self) -> bool {
+        self.file_glob.is_none() && self.language.is_none() && self.symbol_contains.is_none()
+    }
+
+    /// Language as stored in chunk metadata
+    pub fn language_key(&self) -> Option<String> {
+        self.language.as_deref().map(|lang| {
+            language_for_extension(lang)
+                .map(str::to_string)
+                .unwrap_or_else(|| lang.to_lowercase())
+        })
+    }
+
+    pub fn matches(&self, metadata: &HashMap<String, String>) -> bool {
+        let field = |key: &str| metadata.get(key).map(String::as_str).unwrap_or("");
+
+        if let Some(language) = self.language_key() {
+            if field("language") != language {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.file_glob {
+            if !glob_matches_any_suffix(pattern, field("file")) {
+                return false;
+            }
+        }
+        if let Some(needle) = &self.symbol_contains {
+            let needle = needle.to_lowercase();
+            if !field("symbols")
+                .split(',')
+                .any(|symbol| symbol.to_lowercase().contains(&needle))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn glob_matches_any_suffix(pattern: &str, file: &str) -> bool {
+    let file = file.trim_start_matches("./");
+    glob_match(pattern, file)
+        || file
+            .match_indices('/')
+            .any(|(i, _)| glob_match(pattern, &file[i + 1..]))
+}
+
+/// Language name for a file extension, as recorded on indexed chunks
+pub fn language_for_extension(ext: &str) -> Option<&'static str> {
+    Some(match ext.to_lowercase().as_str() {
+        "rs" => "rust",
+        "py" | "ipynb" => "python",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "go" => "go",
+        "java" => "java",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "hpp" => "cpp",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "kt" => "kotlin",
+        "scala" => "scala",
+        "md" => "markdown",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "txt" => "text",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(file: &str, language: &str, symbols: &str) -> HashMap<String, String> {
+        HashMap::from([
+            ("file".to_string(), file.to_string()),
+            ("language".to_string(), language.to_string()),
+            ("symbols".to_string(), symbols.to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_filter_fields() {
+        let login = chunk("/repo/src/auth/login.rs", "rust", "Session::new,login");
+        let readme = chunk("/repo/README.md", "markdown", "");
+
+        assert!(MetadataFilter::default().matches(&readme));
+
+        let rust = MetadataFilter { language: Some("rs".into()), ..Default::default() };
+        assert!(rust.matches(&login) && !rust.matches(&readme));
+
+        let auth = MetadataFilter { file_glob: Some("src/auth".into()), ..Default::default() };
+        assert!(auth.matches(&login) && !auth.matches(&readme));
+        let md = MetadataFilter { file_glob: Some("*.md".into()), ..Default::default() };
+        assert!(md.matches(&readme) && !md.matches(&login));
+
+        let session = MetadataFilter { symbol_contains: Some("session".into()), ..Default::default() };
+        assert!(session.matches(&login) && !session.matches(&readme));
+    }
+}
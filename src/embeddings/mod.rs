@@ -4,13 +4,19 @@
 // Created by: SYNAPSE (Team Beta)
 // ============================================
 
+pub mod export;
+mod filter;
 mod provider;
 mod store;
 
 #[cfg(feature = "qdrant")]
 mod qdrant;
 
-pub use provider::{EmbeddingProvider, MockEmbeddingProvider, OpenAIEmbeddings};
+use anyhow::Result;
+use serde::Serialize;
+
+pub use filter::{language_for_extension, MetadataFilter};
+pub use provider::{default_provider, EmbeddingProvider, MockEmbeddingProvider, OpenAIEmbeddings};
 pub use store::{EmbeddingStore, SearchResult, StoredEmbedding};
 
 #[cfg(feature = "qdrant")]
@@ -36,6 +42,34 @@ pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     dot_product / (norm_a * norm_b)
 }
 
+/// Cosine similarity of two texts embedded by one provider
+#[derive(Debug, Clone, Serialize)]
+pub struct TextSimilarity {
+    pub similarity: f32,
+    pub dimension: usize,
+    pub model: String,
+}
+
+/// Embed `a` and `b` in one batch and compare them (for tuning search)
+pub async fn compare_texts(
+    provider: &dyn EmbeddingProvider,
+    a: &str,
+    b: &str,
+) -> Result<TextSimilarity> {
+    let vectors = provider.embed_batch(&[a.to_string(), b.to_string()]).await?;
+    let [va, vb] = vectors.as_slice() else {
+        anyhow::bail!("Expected 2 embeddings, got {}", vectors.len());
+    };
+    if va.len() != vb.len() {
+        anyhow::bail!("Embedding dimensions differ ({} vs {})", va.len(), vb.len());
+    }
+    Ok(TextSimilarity {
+        similarity: cosine_similarity(va, vb),
+        dimension: va.len(),
+        model: provider.model_name().to_string(),
+    })
+}
+
 /// Normalize a vector to unit length
 pub fn normalize(v: &mut [f32]) {
     let norm: f32 = v.iter().map(|x| x * x).sum::<f32>().sqrt();
@@ -74,6 +108,18 @@ mod tests {
         assert!((sim + 1.0).abs() < 0.0001);
     }
 
+    #[tokio::test]
+    async fn test_compare_texts_with_mock() {
+        let provider = MockEmbeddingProvider::new(64);
+        let same = compare_texts(&provider, "fn parse()", "fn parse()").await.unwrap();
+        assert!((same.similarity - 1.0).abs() < 0.0001);
+        assert_eq!(same.dimension, 64);
+        assert_eq!(same.model, "mock-embedding");
+
+        let different = compare_texts(&provider, "fn parse()", "struct Config").await.unwrap();
+        assert!(different.similarity < 0.9999);
+    }
+
     #[test]
     fn test_normalize() {
         let mut v = vec![3.0, 4.0];
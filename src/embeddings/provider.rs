@@ -7,8 +7,13 @@
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
 
 use super::Embedding;
+use crate::config::Settings;
+use crate::core::rate_limit::{shared_limiter, Priority, SharedRateLimiter};
+use crate::util::connectivity;
 
 /// Trait for embedding providers
 #[async_trait]
@@ -30,6 +35,11 @@ pub trait EmbeddingProvider: Send + Sync {
 
     /// Get the model name
     fn model_name(&self) -> &str;
+
+    /// Time batch (background) requests have spent waiting on rate limits
+    fn throttled(&self) -> Duration {
+        Duration::ZERO
+    }
 }
 
 /// OpenAI Embeddings Provider
@@ -38,18 +48,32 @@ pub struct OpenAIEmbeddings {
     model: String,
     dimension: usize,
     base_url: Option<String>,
+    limiter: Arc<SharedRateLimiter>,
+    /// `--offline` when created; requests fail instead of connecting
+    offline: bool,
 }
 
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
 impl OpenAIEmbeddings {
     pub fn new(api_key: String) -> Self {
+        // Same budget as chat requests made with this key
+        let limiter = shared_limiter(DEFAULT_BASE_URL, &api_key);
         Self {
             api_key,
             model: "text-embedding-3-small".to_string(),
             dimension: 1536,
             base_url: None,
+            limiter,
+            offline: crate::util::http::is_offline(),
         }
     }
 
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
     pub fn with_model(mut self, model: &str, dimension: usize) -> Self {
         self.model = model.to_string();
         self.dimension = dimension;
@@ -58,8 +82,52 @@ impl OpenAIEmbeddings {
 
     pub fn with_base_url(mut self, url: &str) -> Self {
         self.base_url = Some(url.to_string());
+        self.limiter = shared_limiter(url, &self.api_key);
         self
     }
+
+    async fn request(&self, texts: &[String], priority: Priority) -> Result<Vec<Embedding>> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        crate::util::http::check_online(self.offline, "OpenAI embeddings")?;
+
+        let base_url = self.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL);
+        let url = format!("{}/embeddings", base_url);
+
+        let request = EmbeddingRequest {
+            model: self.model.clone(),
+            input: texts.to_vec(),
+        };
+
+        self.limiter.acquire(priority).await;
+
+        let client = crate::util::http::client()?;
+        let response = client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to send embedding request")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                self.limiter.report_rate_limited();
+            }
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Embedding API error ({}): {}", status, body);
+        }
+
+        let result: EmbeddingResponse = response
+            .json()
+            .await
+            .context("Failed to parse embedding response")?;
+
+        Ok(result.data.into_iter().map(|d| d.embedding).collect())
+    }
 }
 
 #[derive(Serialize)]
@@ -80,44 +148,88 @@ struct EmbeddingData {
 
 #[async_trait]
 impl EmbeddingProvider for OpenAIEmbeddings {
+    /// Batches come from indexing, so they queue behind interactive requests
     async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Embedding>> {
-        if texts.is_empty() {
-            return Ok(vec![]);
+        self.request(texts, Priority::Background).await
+    }
+
+    /// Single texts are search queries made while someone waits
+    async fn embed(&self, text: &str) -> Result<Embedding> {
+        self.request(&[text.to_string()], Priority::Interactive)
+            .await?
+            .into_iter()
+            .next()
+            .context("No embedding returned")
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn throttled(&self) -> Duration {
+        self.limiter.throttled_for(Priority::Background)
+    }
+}
+
+/// Ollama embeddings (`/api/embed`), for local models
+pub struct OllamaEmbeddings {
+    base_url: String,
+    model: String,
+    dimension: usize,
+}
+
+const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+
+impl OllamaEmbeddings {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: "nomic-embed-text".to_string(),
+            dimension: 768,
         }
+    }
 
-        let base_url = self
-            .base_url
-            .as_deref()
-            .unwrap_or("https://api.openai.com/v1");
-        let url = format!("{}/embeddings", base_url);
+    pub fn with_model(mut self, model: &str, dimension: usize) -> Self {
+        self.model = model.to_string();
+        self.dimension = dimension;
+        self
+    }
+}
 
-        let request = EmbeddingRequest {
-            model: self.model.clone(),
-            input: texts.to_vec(),
-        };
+#[derive(Deserialize)]
+struct OllamaEmbedResponse {
+    embeddings: Vec<Embedding>,
+}
 
-        let client = reqwest::Client::new();
+#[async_trait]
+impl EmbeddingProvider for OllamaEmbeddings {
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Embedding>> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        let client = crate::util::http::client()?;
         let response = client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&serde_json::json!({"model": self.model, "input": texts}))
             .send()
             .await
-            .context("Failed to send embedding request")?;
+            .with_context(|| format!("Failed to reach Ollama at {}", self.base_url))?;
 
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("Embedding API error ({}): {}", status, body);
+            anyhow::bail!("Ollama embedding error ({}): {}", status, body);
         }
 
-        let result: EmbeddingResponse = response
+        let result: OllamaEmbedResponse = response
             .json()
             .await
-            .context("Failed to parse embedding response")?;
-
-        Ok(result.data.into_iter().map(|d| d.embedding).collect())
+            .context("Failed to parse Ollama embedding response")?;
+        Ok(result.embeddings)
     }
 
     fn dimension(&self) -> usize {
@@ -129,6 +241,65 @@ impl EmbeddingProvider for OpenAIEmbeddings {
     }
 }
 
+/// Embeddings for indexing, search and recall, from `settings.embeddings`.
+/// Unset, a default model served by Ollama embeds with Ollama, and anything
+/// else with OpenAI when OPENAI_API_KEY is set, else the mock provider.
+/// Offline mode uses the mock unless the provider is local. The second value
+/// says why the mock was chosen.
+pub fn default_provider(settings: &Settings) -> (Arc<dyn EmbeddingProvider>, Option<&'static str>) {
+    default_provider_with(settings, crate::util::http::is_offline())
+}
+
+fn default_provider_with(
+    settings: &Settings,
+    offline: bool,
+) -> (Arc<dyn EmbeddingProvider>, Option<&'static str>) {
+    let config = &settings.embeddings;
+    let default_model = settings.get_model(&settings.default_model);
+    let provider = match (&config.provider, default_model) {
+        (Some(provider), _) => provider.as_str(),
+        (None, Some(model)) if model.provider == "ollama" => "ollama",
+        (None, _) => "openai",
+    };
+    let mock = || Arc::new(MockEmbeddingProvider::new(384));
+
+    match provider {
+        "mock" => (mock(), Some("embeddings.provider is mock")),
+        "ollama" => {
+            let base_url = config
+                .base_url
+                .clone()
+                .or_else(|| default_model.filter(|m| m.provider == "ollama").and_then(|m| m.base_url.clone()))
+                .unwrap_or_else(|| OLLAMA_BASE_URL.to_string());
+            let local = url::Url::parse(&base_url).is_ok_and(|url| connectivity::is_loopback(&url));
+            if offline && !local {
+                return (mock(), Some("offline mode"));
+            }
+            let mut ollama = OllamaEmbeddings::new(&base_url);
+            if let (Some(model), Some(dimension)) = (&config.model, config.dimension) {
+                ollama = ollama.with_model(model, dimension);
+            }
+            (Arc::new(ollama), None)
+        }
+        _ => {
+            if offline {
+                return (mock(), Some("offline mode"));
+            }
+            let Ok(key) = std::env::var("OPENAI_API_KEY") else {
+                return (mock(), Some("OPENAI_API_KEY not set"));
+            };
+            let mut openai = OpenAIEmbeddings::new(key);
+            if let Some(base_url) = &config.base_url {
+                openai = openai.with_base_url(base_url);
+            }
+            if let (Some(model), Some(dimension)) = (&config.model, config.dimension) {
+                openai = openai.with_model(model, dimension);
+            }
+            (Arc::new(openai), None)
+        }
+    }
+}
+
 /// Mock embedding provider for testing
 pub struct MockEmbeddingProvider {
     dimension: usize,
@@ -171,6 +342,49 @@ impl EmbeddingProvider for MockEmbeddingProvider {
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_offline_embeddings_make_no_requests() {
+        // Any connection to this server is a failure
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+        listener.set_nonblocking(true).unwrap();
+        let openai = OpenAIEmbeddings::new("sk-test".to_string())
+            .with_base_url(&url)
+            .with_offline(true);
+
+        let single = openai.embed("query").await;
+        let batch = openai.embed_batch(&["a".to_string(), "b".to_string()]).await;
+        let (fallback, reason) = default_provider_with(&Settings::default(), true);
+
+        assert!(single.unwrap_err().to_string().contains("OpenAI embeddings needs the network"));
+        assert!(batch.is_err());
+        assert_eq!((fallback.model_name(), reason), ("mock-embedding", Some("offline mode")));
+        assert!(listener.accept().is_err(), "offline embeddings reached the server");
+    }
+
+    #[tokio::test]
+    async fn test_local_default_model_embeds_with_ollama() {
+        let server = crate::vcs::mock::MockServer::start().await;
+        server.route("POST", "/api/embed", &[], serde_json::json!({"embeddings": [[0.5, -0.5], [1.0, 0.0]]}));
+        let mut settings = Settings {
+            default_model: "ollama".to_string(),
+            ..Default::default()
+        };
+        settings.models.get_mut("ollama").unwrap().base_url = Some(server.url());
+
+        let (provider, reason) = default_provider(&settings);
+        assert_eq!((provider.model_name(), reason), ("nomic-embed-text", None));
+        let embeddings = provider.embed_batch(&["a".to_string(), "b".to_string()]).await.unwrap();
+        assert_eq!(embeddings, vec![vec![0.5, -0.5], vec![1.0, 0.0]]);
+        let request = &server.requests()[0];
+        assert_eq!(request.json(), serde_json::json!({"model": "nomic-embed-text", "input": ["a", "b"]}));
+
+        // An explicit provider wins over the default model's
+        settings.embeddings.provider = Some("mock".to_string());
+        let (provider, reason) = default_provider(&settings);
+        assert_eq!((provider.model_name(), reason), ("mock-embedding", Some("embeddings.provider is mock")));
+    }
+
     #[tokio::test]
     async fn test_mock_provider() {
         let provider = MockEmbeddingProvider::new(384);
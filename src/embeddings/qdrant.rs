@@ -11,12 +11,13 @@ use qdrant_client::prelude::*;
 use qdrant_client::qdrant::{
     vectors_config::Config, CreateCollection, Distance, PointStruct, SearchPoints,
     VectorParams, VectorsConfig, Filter, Condition, FieldCondition, Match,
-    value::Kind, Value as QdrantValue,
+    value::Kind, ScoredPoint, Value as QdrantValue, PointId, RetrievedPoint, ScrollPoints,
+    vectors_output::VectorsOptions,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use super::StoredEmbedding;
+use super::{MetadataFilter, StoredEmbedding};
 
 /// Qdrant vector store configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -169,41 +170,7 @@ impl QdrantStore {
         let results = search_result
             .result
             .into_iter()
-            .map(|point| {
-                let payload = point.payload;
-                
-                let id = payload
-                    .get("id")
-                    .and_then(|v| match &v.kind {
-                        Some(Kind::StringValue(s)) => Some(s.clone()),
-                        _ => None,
-                    })
-                    .unwrap_or_default();
-
-                let text = payload
-                    .get("text")
-                    .and_then(|v| match &v.kind {
-                        Some(Kind::StringValue(s)) => Some(s.clone()),
-                        _ => None,
-                    })
-                    .unwrap_or_default();
-
-                let mut metadata: HashMap<String, String> = HashMap::new();
-                for (key, value) in payload {
-                    if key != "id" && key != "text" {
-                        if let Some(Kind::StringValue(s)) = value.kind {
-                            metadata.insert(key, s);
-                        }
-                    }
-                }
-
-                SearchResult {
-                    id,
-                    text,
-                    score: point.score,
-                    metadata,
-                }
-            })
+            .map(to_search_result)
             .collect();
 
         Ok(results)
@@ -217,23 +184,7 @@ impl QdrantStore {
         top_k: usize,
     ) -> Result<Vec<SearchResult>> {
         let filter = Filter {
-            must: vec![Condition {
-                condition_one_of: Some(
-                    qdrant_client::qdrant::condition::ConditionOneOf::Field(
-                        FieldCondition {
-                            key: "file".to_string(),
-                            r#match: Some(Match {
-                                match_value: Some(
-                                    qdrant_client::qdrant::r#match::MatchValue::Keyword(
-                                        file_path.to_string(),
-                                    ),
-                                ),
-                            }),
-                            ..Default::default()
-                        },
-                    ),
-                ),
-            }],
+            must: vec![keyword_condition("file", file_path)],
             ..Default::default()
         };
 
@@ -253,46 +204,79 @@ impl QdrantStore {
         let results = search_result
             .result
             .into_iter()
-            .map(|point| {
-                let payload = point.payload;
-                
-                let id = payload
-                    .get("id")
-                    .and_then(|v| match &v.kind {
-                        Some(Kind::StringValue(s)) => Some(s.clone()),
-                        _ => None,
-                    })
-                    .unwrap_or_default();
-
-                let text = payload
-                    .get("text")
-                    .and_then(|v| match &v.kind {
-                        Some(Kind::StringValue(s)) => Some(s.clone()),
-                        _ => None,
-                    })
-                    .unwrap_or_default();
-
-                let mut metadata: HashMap<String, String> = HashMap::new();
-                for (key, value) in payload {
-                    if key != "id" && key != "text" {
-                        if let Some(Kind::StringValue(s)) = value.kind {
-                            metadata.insert(key, s);
-                        }
-                    }
-                }
-
-                SearchResult {
-                    id,
-                    text,
-                    score: point.score,
-                    metadata,
-                }
-            })
+            .map(to_search_result)
             .collect();
 
         Ok(results)
     }
 
+    /// Search chunks passing `filter`. The language is matched by Qdrant;
+    /// file globs and symbols are checked here on an enlarged candidate set.
+    pub async fn search_filtered(
+        &self,
+        query_vector: &[f32],
+        filter: &MetadataFilter,
+        top_k: usize,
+        min_score: f32,
+    ) -> Result<Vec<SearchResult>> {
+        let must: Vec<Condition> = filter
+            .language_key()
+            .map(|language| keyword_condition("language", &language))
+            .into_iter()
+            .collect();
+        let checked_here = filter.file_glob.is_some() || filter.symbol_contains.is_some();
+        let limit = if checked_here { top_k * POST_FILTER_FACTOR } else { top_k };
+
+        let search_result = self
+            .client
+            .search_points(&SearchPoints {
+                collection_name: self.config.collection_name.clone(),
+                vector: query_vector.to_vec(),
+                limit: limit as u64,
+                score_threshold: Some(min_score),
+                filter: (!must.is_empty()).then(|| Filter {
+                    must,
+                    ..Default::default()
+                }),
+                with_payload: Some(true.into()),
+                ..Default::default()
+            })
+            .await
+            .context("Failed to search points")?;
+
+        Ok(search_result
+            .result
+            .into_iter()
+            .map(to_search_result)
+            .filter(|result| filter.matches(&result.metadata))
+            .take(top_k)
+            .collect())
+    }
+
+    /// One page of up to `limit` stored points with their vectors, starting
+    /// at `offset`, and the offset of the next page if there is one
+    pub async fn scroll(
+        &self,
+        offset: Option<PointId>,
+        limit: u32,
+    ) -> Result<(Vec<StoredEmbedding>, Option<PointId>)> {
+        let page = self
+            .client
+            .scroll(&ScrollPoints {
+                collection_name: self.config.collection_name.clone(),
+                offset,
+                limit: Some(limit),
+                with_payload: Some(true.into()),
+                with_vectors: Some(true.into()),
+                ..Default::default()
+            })
+            .await
+            .context("Failed to scroll points")?;
+
+        let points = page.result.into_iter().map(to_stored_embedding).collect();
+        Ok((points, page.next_page_offset))
+    }
+
     /// Get collection info
     pub async fn info(&self) -> Result<CollectionInfo> {
         let info = self
@@ -341,6 +325,80 @@ pub struct SearchResult {
     pub metadata: HashMap<String, String>,
 }
 
+/// Candidates fetched per wanted result when part of a filter is applied
+/// after the Qdrant query
+const POST_FILTER_FACTOR: usize = 10;
+
+fn keyword_condition(key: &str, value: &str) -> Condition {
+    Condition {
+        condition_one_of: Some(qdrant_client::qdrant::condition::ConditionOneOf::Field(
+            FieldCondition {
+                key: key.to_string(),
+                r#match: Some(Match {
+                    match_value: Some(qdrant_client::qdrant::r#match::MatchValue::Keyword(
+                        value.to_string(),
+                    )),
+                }),
+                ..Default::default()
+            },
+        )),
+    }
+}
+
+/// The `id` and `text` fields of a payload and its other string fields
+fn split_payload(payload: HashMap<String, QdrantValue>) -> (String, String, HashMap<String, String>) {
+    let id = payload
+        .get("id")
+        .and_then(|v| match &v.kind {
+            Some(Kind::StringValue(s)) => Some(s.clone()),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let text = payload
+        .get("text")
+        .and_then(|v| match &v.kind {
+            Some(Kind::StringValue(s)) => Some(s.clone()),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let mut metadata: HashMap<String, String> = HashMap::new();
+    for (key, value) in payload {
+        if key != "id" && key != "text" {
+            if let Some(Kind::StringValue(s)) = value.kind {
+                metadata.insert(key, s);
+            }
+        }
+    }
+
+    (id, text, metadata)
+}
+
+fn to_search_result(point: ScoredPoint) -> SearchResult {
+    let (id, text, metadata) = split_payload(point.payload);
+    SearchResult {
+        id,
+        text,
+        score: point.score,
+        metadata,
+    }
+}
+
+fn to_stored_embedding(point: RetrievedPoint) -> StoredEmbedding {
+    let (id, text, metadata) = split_payload(point.payload);
+    let embedding = match point.vectors.and_then(|v| v.vectors_options) {
+        Some(VectorsOptions::Vector(vector)) => vector.data,
+        _ => Vec::new(),
+    };
+    StoredEmbedding {
+        id,
+        text,
+        embedding,
+        metadata,
+    }
+}
+
 /// Collection info
 #[derive(Debug)]
 pub struct CollectionInfo {
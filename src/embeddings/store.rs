@@ -10,7 +10,7 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
-use super::{cosine_similarity, Embedding};
+use super::{cosine_similarity, Embedding, MetadataFilter};
 
 /// Stored embedding with metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -102,6 +102,16 @@ impl EmbeddingStore {
 
     /// Search for similar embeddings
     pub fn search(&self, query_embedding: &[f32], top_k: usize) -> Vec<SearchResult> {
+        self.search_filtered(query_embedding, top_k, &MetadataFilter::default())
+    }
+
+    /// Search only among embeddings whose metadata passes `filter`
+    pub fn search_filtered(
+        &self,
+        query_embedding: &[f32],
+        top_k: usize,
+        filter: &MetadataFilter,
+    ) -> Vec<SearchResult> {
         if query_embedding.len() != self.dimension {
             return vec![];
         }
@@ -109,6 +119,7 @@ impl EmbeddingStore {
         let mut results: Vec<_> = self
             .embeddings
             .iter()
+            .filter(|emb| filter.matches(&emb.metadata))
             .map(|emb| {
                 let score = cosine_similarity(query_embedding, &emb.embedding);
                 SearchResult {
@@ -140,6 +151,22 @@ impl EmbeddingStore {
             .collect()
     }
 
+    /// Every pair of stored embeddings at least `min_score` similar, most
+    /// similar first
+    pub fn similar_pairs(&self, min_score: f32) -> Vec<(&StoredEmbedding, &StoredEmbedding, f32)> {
+        let mut pairs = Vec::new();
+        for (i, a) in self.embeddings.iter().enumerate() {
+            for b in &self.embeddings[i + 1..] {
+                let score = cosine_similarity(&a.embedding, &b.embedding);
+                if score >= min_score {
+                    pairs.push((a, b, score));
+                }
+            }
+        }
+        pairs.sort_by(|x, y| y.2.partial_cmp(&x.2).unwrap_or(std::cmp::Ordering::Equal));
+        pairs
+    }
+
     /// Get embedding by ID
     pub fn get(&self, id: &str) -> Option<&StoredEmbedding> {
         self.id_index.get(id).map(|&idx| &self.embeddings[idx])
@@ -158,6 +185,23 @@ impl EmbeddingStore {
         }
     }
 
+    /// Keep only the embeddings `keep` accepts; returns how many were removed
+    pub fn retain(&mut self, mut keep: impl FnMut(&StoredEmbedding) -> bool) -> usize {
+        let before = self.embeddings.len();
+        self.embeddings.retain(|emb| keep(emb));
+        self.rebuild_index();
+        before - self.embeddings.len()
+    }
+
+    /// Change an embedding's id, returning it for further updates
+    pub fn rename(&mut self, id: &str, new_id: &str) -> Option<&mut StoredEmbedding> {
+        let idx = self.id_index.remove(id)?;
+        self.id_index.insert(new_id.to_string(), idx);
+        let embedding = &mut self.embeddings[idx];
+        embedding.id = new_id.to_string();
+        Some(embedding)
+    }
+
     /// Rebuild the ID index
     fn rebuild_index(&mut self) {
         self.id_index.clear();
@@ -176,6 +220,11 @@ impl EmbeddingStore {
         self.embeddings.is_empty()
     }
 
+    /// Stored embeddings in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = &StoredEmbedding> {
+        self.embeddings.iter()
+    }
+
     /// Get store dimension
     pub fn dimension(&self) -> usize {
         self.dimension
@@ -249,6 +298,22 @@ mod tests {
         assert_eq!(results[0].id, "doc1");
     }
 
+    #[test]
+    fn test_similar_pairs() {
+        let mut store = EmbeddingStore::new(3);
+        store.add(create_test_embedding("doc1", vec![1.0, 0.0, 0.0]));
+        store.add(create_test_embedding("doc2", vec![0.0, 1.0, 0.0]));
+        store.add(create_test_embedding("doc3", vec![0.9, 0.1, 0.0]));
+        store.add(create_test_embedding("doc4", vec![1.0, 0.01, 0.0]));
+
+        let pairs: Vec<_> = store
+            .similar_pairs(0.95)
+            .into_iter()
+            .map(|(a, b, _)| (a.id.as_str(), b.id.as_str()))
+            .collect();
+        assert_eq!(pairs, vec![("doc1", "doc4"), ("doc3", "doc4"), ("doc1", "doc3")]);
+    }
+
     #[test]
     fn test_store_remove() {
         let mut store = EmbeddingStore::new(3);
@@ -0,0 +1,189 @@
+use anyhow::Result;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::io;
+use std::path::Path;
+
+/// How a text file is stored on disk, so edits can be saved back the same way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextEncoding {
+    pub encoding: &'static Encoding,
+    pub bom: bool,
+}
+
+impl Default for TextEncoding {
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8,
+            bom: false,
+        }
+    }
+}
+
+impl TextEncoding {
+    /// Plain UTF-8 without a BOM, i.e. what `read_to_string` already handles
+    pub fn is_utf8(&self) -> bool {
+        self.encoding == UTF_8 && !self.bom
+    }
+
+    /// e.g. `Shift_JIS`, `UTF-16LE with BOM`
+    pub fn label(&self) -> String {
+        if self.bom {
+            format!("{} with BOM", self.encoding.name())
+        } else {
+            self.encoding.name().to_string()
+        }
+    }
+
+    /// Encode `text` back into this encoding, BOM included. Fails instead of
+    /// substituting characters the encoding can't represent.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        let bom: &[u8] = match (self.bom, self.encoding) {
+            (false, _) => &[],
+            (true, e) if e == UTF_16LE => &[0xFF, 0xFE],
+            (true, e) if e == UTF_16BE => &[0xFE, 0xFF],
+            (true, _) => &[0xEF, 0xBB, 0xBF],
+        };
+        let mut out = bom.to_vec();
+
+        // encoding_rs only encodes UTF-16 as UTF-8, so do those by hand
+        if self.encoding == UTF_16LE {
+            out.extend(text.encode_utf16().flat_map(|u| u.to_le_bytes()));
+        } else if self.encoding == UTF_16BE {
+            out.extend(text.encode_utf16().flat_map(|u| u.to_be_bytes()));
+        } else {
+            let (bytes, _, unmappable) = self.encoding.encode(text);
+            if unmappable {
+                anyhow::bail!(
+                    "Content has characters that {} cannot represent; pass convert_to_utf8: true to save as UTF-8",
+                    self.encoding.name()
+                );
+            }
+            out.extend_from_slice(&bytes);
+        }
+        Ok(out)
+    }
+}
+
+/// File content decoded to UTF-8, with the encoding it was stored in
+#[derive(Debug, Clone)]
+pub struct DecodedText {
+    pub text: String,
+    pub encoding: TextEncoding,
+}
+
+/// Detect the encoding of `bytes` and decode them: BOM first, then UTF-8,
+/// then a statistical guess for legacy encodings (Latin-1, Shift-JIS, ...).
+/// Returns None for binary or undecodable content.
+pub fn decode(bytes: &[u8]) -> Option<DecodedText> {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let text = encoding
+            .decode_without_bom_handling_and_without_replacement(&bytes[bom_len..])?
+            .into_owned();
+        return Some(DecodedText {
+            text,
+            encoding: TextEncoding {
+                encoding,
+                bom: true,
+            },
+        });
+    }
+
+    if looks_binary(bytes) {
+        return None;
+    }
+
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Some(DecodedText {
+            text: text.to_string(),
+            encoding: TextEncoding::default(),
+        });
+    }
+
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(bytes, true);
+    let encoding = detector.guess(None, false);
+    let text = encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)?
+        .into_owned();
+    Some(DecodedText {
+        text,
+        encoding: TextEncoding {
+            encoding,
+            bom: false,
+        },
+    })
+}
+
+/// NUL bytes, or more than 1% control characters that text files don't use
+fn looks_binary(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return true;
+    }
+    let controls = bytes
+        .iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b)) || b == 0x7f)
+        .count();
+    controls * 100 > bytes.len()
+}
+
+/// Read and decode a text file. Binary files fail like `fs::read_to_string`
+/// does on invalid UTF-8.
+pub fn read_text(path: &Path) -> io::Result<DecodedText> {
+    let bytes = std::fs::read(path)?;
+    decode(&bytes).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        )
+    })
+}
+
+/// `fs::read_to_string` that also accepts non-UTF-8 text files
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    read_text(path).map(|decoded| decoded.text)
+}
+
+/// Write `text` in `encoding`
+pub fn write_text(path: &Path, text: &str, encoding: TextEncoding) -> Result<()> {
+    std::fs::write(path, encoding.encode(text)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_encodings() {
+        let latin1 = b"caf\xe9 na\xefve r\xe9sum\xe9 \xe0 la cr\xe8me br\xfbl\xe9e\n";
+        let decoded = decode(latin1).unwrap();
+        assert_eq!(decoded.text, "café naïve résumé à la crème brûlée\n");
+        assert_eq!(decoded.encoding.encode(&decoded.text).unwrap(), latin1);
+
+        // "日本語のテキストです。" in Shift-JIS
+        let sjis = b"\x93\xfa\x96\x7b\x8c\xea\x82\xcc\x83\x65\x83\x4c\x83\x58\x83\x67\x82\xc5\x82\xb7\x81\x42";
+        let decoded = decode(sjis).unwrap();
+        assert_eq!(decoded.encoding.encoding, encoding_rs::SHIFT_JIS);
+        assert_eq!(decoded.text, "日本語のテキストです。");
+
+        let utf16 = [0xFF, 0xFE, b'h', 0, b'i', 0];
+        let decoded = decode(&utf16).unwrap();
+        assert_eq!(decoded.text, "hi");
+        assert_eq!(decoded.encoding.label(), "UTF-16LE with BOM");
+        assert_eq!(decoded.encoding.encode("hi").unwrap(), utf16);
+
+        assert!(decode("plain ✓".as_bytes()).unwrap().encoding.is_utf8());
+        assert!(decode(b"\x7fELF\x02\x01\x01\x00\x00").is_none());
+        assert!(decode(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0x00]).is_none());
+    }
+
+    #[test]
+    fn test_encode_rejects_unmappable() {
+        let latin1 = TextEncoding {
+            encoding: encoding_rs::WINDOWS_1252,
+            bom: false,
+        };
+        assert!(latin1.encode("naïve").is_ok());
+        assert!(latin1.encode("日本").is_err());
+    }
+}
@@ -57,32 +57,38 @@ impl FileIndex {
         Self::default()
     }
 
-    pub fn build(entries: Vec<FileEntry>) -> Self {
+    /// Index entries as they arrive, e.g. from `FileWalker::walk_stream`
+    pub fn build(entries: impl IntoIterator<Item = FileEntry>) -> Self {
         let mut index = Self::new();
-        index.total_size = entries.iter().map(|e| e.size).sum();
-        index.code_files = entries
-            .iter()
-            .filter(|e| e.file_type == FileType::Code)
-            .count();
-        index.config_files = entries
-            .iter()
-            .filter(|e| e.file_type == FileType::Config)
-            .count();
-
-        for (i, entry) in entries.iter().enumerate() {
-            if let Some(ext) = &entry.extension {
-                index.by_extension.entry(ext.clone()).or_default().push(i);
-            }
+        for entry in entries {
+            index.add(entry);
+        }
+        index
+    }
 
-            index
-                .by_type
-                .entry(entry.file_type.as_str().to_string())
-                .or_default()
-                .push(i);
+    pub fn add(&mut self, entry: FileEntry) {
+        let i = self.entries.len();
+        self.total_size += entry.size;
+        match entry.file_type {
+            FileType::Code => self.code_files += 1,
+            FileType::Config => self.config_files += 1,
+            _ => {}
         }
+        if let Some(ext) = &entry.extension {
+            self.by_extension.entry(ext.clone()).or_default().push(i);
+        }
+        self.by_type
+            .entry(entry.file_type.as_str().to_string())
+            .or_default()
+            .push(i);
+        self.entries.push(entry);
+    }
 
-        index.entries = entries;
-        index
+    /// Order the entries by path, for listings and the tree
+    pub fn sort(&mut self) {
+        let mut entries = std::mem::take(&mut self.entries);
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        *self = Self::build(entries);
     }
 
     pub fn get_code_files(&self) -> Vec<&FileEntry> {
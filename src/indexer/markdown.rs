@@ -0,0 +1,135 @@
+use std::path::Path;
+
+/// Kind of a markdown block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    Prose,
+    Code,
+}
+
+#[derive(Debug, Clone)]
+pub struct MarkdownBlock {
+    pub kind: BlockKind,
+    /// Fence language of a code block (```rust), lowercased
+    pub language: Option<String>,
+    /// Zero-based line of the block's first text line in the file
+    pub start_line: usize,
+    /// Block text without the fence lines
+    pub text: String,
+}
+
+/// Open fence: at most 3 spaces of indent, then 3+ backticks or tildes
+fn fence(line: &str) -> Option<(char, usize, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = &line[indent..];
+    let marker = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = rest.chars().take_while(|c| *c == marker).count();
+    if len < 3 {
+        return None;
+    }
+    let info = rest[len..].trim();
+    // Backtick fences can't have backticks in the info string
+    if marker == '`' && info.contains('`') {
+        return None;
+    }
+    Some((marker, len, info))
+}
+
+/// Language named by a fence info string: `rust`, `{.python}`, `js title="x"`
+fn fence_language(info: &str) -> Option<String> {
+    let word = info.split_whitespace().next()?;
+    let word = word.trim_matches(|c| c == '{' || c == '}' || c == '.').to_ascii_lowercase();
+    (!word.is_empty()).then_some(word)
+}
+
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("md") || e.eq_ignore_ascii_case("markdown"))
+}
+
+/// Split markdown into prose and fenced code blocks, in file order. Blank
+/// prose between fences is dropped; an unclosed fence runs to the end.
+pub fn parse(content: &str) -> Vec<MarkdownBlock> {
+    let mut blocks = Vec::new();
+    let mut current = MarkdownBlock {
+        kind: BlockKind::Prose,
+        language: None,
+        start_line: 0,
+        text: String::new(),
+    };
+    let mut open: Option<(char, usize)> = None;
+
+    fn finish(block: MarkdownBlock, blocks: &mut Vec<MarkdownBlock>) {
+        if block.kind == BlockKind::Code || !block.text.trim().is_empty() {
+            blocks.push(block);
+        }
+    }
+
+    for (line_num, line) in content.lines().enumerate() {
+        match (open, fence(line)) {
+            (None, Some((marker, len, info))) => {
+                let next = MarkdownBlock {
+                    kind: BlockKind::Code,
+                    language: fence_language(info),
+                    start_line: line_num + 1,
+                    text: String::new(),
+                };
+                finish(std::mem::replace(&mut current, next), &mut blocks);
+                open = Some((marker, len));
+            }
+            (Some((marker, len)), Some((closing, closing_len, "")))
+                if closing == marker && closing_len >= len =>
+            {
+                let next = MarkdownBlock {
+                    kind: BlockKind::Prose,
+                    language: None,
+                    start_line: line_num + 1,
+                    text: String::new(),
+                };
+                finish(std::mem::replace(&mut current, next), &mut blocks);
+                open = None;
+            }
+            _ => {
+                current.text.push_str(line);
+                current.text.push('\n');
+            }
+        }
+    }
+    finish(current, &mut blocks);
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fences() {
+        let doc = "# Setup\n\nInstall it:\n\n```bash\ncargo install webrana\n```\n\n~~~{.python}\nprint('```')\n~~~\n````\nno language\n````\nDone.\n";
+        let blocks = parse(doc);
+        let summary: Vec<_> = blocks
+            .iter()
+            .map(|b| (b.kind, b.language.as_deref(), b.start_line))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (BlockKind::Prose, None, 0),
+                (BlockKind::Code, Some("bash"), 5),
+                (BlockKind::Code, Some("python"), 9),
+                (BlockKind::Code, None, 12),
+                (BlockKind::Prose, None, 14),
+            ]
+        );
+        assert_eq!(blocks[1].text, "cargo install webrana\n");
+        assert_eq!(blocks[2].text, "print('```')\n");
+
+        // An unclosed fence keeps the rest of the file
+        let open = parse("text\n```rust\nfn main() {}\n");
+        assert_eq!(open[1].text, "fn main() {}\n");
+    }
+}
@@ -1,9 +1,18 @@
 pub mod detector;
+pub mod encoding;
 pub mod index;
+pub mod markdown;
+pub mod notebook;
 pub mod walker;
 
 #[allow(unused_imports)]
 pub use detector::{ProjectDetector, ProjectInfo, ProjectType};
 #[allow(unused_imports)]
+pub use encoding::{DecodedText, TextEncoding};
+#[allow(unused_imports)]
 pub use index::{FileEntry, FileIndex, FileType};
+#[allow(unused_imports)]
+pub use markdown::{BlockKind, MarkdownBlock};
+#[allow(unused_imports)]
+pub use notebook::{CellKind, Notebook, NotebookCell};
 pub use walker::FileWalker;
@@ -0,0 +1,126 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::Path;
+
+/// Kind of a Jupyter notebook cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellKind {
+    Code,
+    Markdown,
+    Raw,
+}
+
+#[derive(Debug, Clone)]
+pub struct NotebookCell {
+    /// Zero-based position of the cell in the notebook
+    pub index: usize,
+    pub kind: CellKind,
+    pub source: String,
+}
+
+/// Cell sources of a `.ipynb` file; outputs are dropped
+#[derive(Debug, Clone, Default)]
+pub struct Notebook {
+    pub cells: Vec<NotebookCell>,
+    pub language: Option<String>,
+}
+
+impl Notebook {
+    pub fn is_notebook(path: &Path) -> bool {
+        path.extension().map(|e| e == "ipynb").unwrap_or(false)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let json: Value = serde_json::from_str(content).context("Invalid notebook JSON")?;
+
+        let language = json
+            .pointer("/metadata/kernelspec/language")
+            .or_else(|| json.pointer("/metadata/language_info/name"))
+            .and_then(|v| v.as_str())
+            .map(String::from);
+
+        let cells = json
+            .get("cells")
+            .and_then(|c| c.as_array())
+            .context("Notebook has no cells array")?
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| {
+                let kind = match cell.get("cell_type").and_then(|t| t.as_str()) {
+                    Some("code") => CellKind::Code,
+                    Some("markdown") => CellKind::Markdown,
+                    _ => CellKind::Raw,
+                };
+                NotebookCell {
+                    index,
+                    kind,
+                    source: source_text(cell.get("source")),
+                }
+            })
+            .collect();
+
+        Ok(Self { cells, language })
+    }
+
+    /// Code and markdown cells
+    pub fn text_cells(&self) -> impl Iterator<Item = &NotebookCell> {
+        self.cells
+            .iter()
+            .filter(|c| matches!(c.kind, CellKind::Code | CellKind::Markdown))
+    }
+
+    /// Code and markdown sources joined with `# %% [cell N]` markers, for indexing
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for cell in self.text_cells() {
+            let kind = if cell.kind == CellKind::Markdown { " markdown" } else { "" };
+            out.push_str(&format!("# %% [cell {}]{}\n", cell.index + 1, kind));
+            out.push_str(&cell.source);
+            if !cell.source.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+/// `source` is either a string or a list of lines
+fn source_text(source: Option<&Value>) -> String {
+    match source {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(|l| l.as_str()).collect(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTEBOOK: &str = r##"{
+        "metadata": {"kernelspec": {"language": "python"}},
+        "cells": [
+            {"cell_type": "markdown", "source": ["# Title\n", "Intro"]},
+            {"cell_type": "code", "source": "import os\nprint(os.getcwd())",
+             "outputs": [{"data": {"image/png": "iVBORw0KGgo="}}]},
+            {"cell_type": "raw", "source": "raw text"}
+        ]
+    }"##;
+
+    #[test]
+    fn test_parse_notebook() {
+        let nb = Notebook::parse(NOTEBOOK).unwrap();
+        assert_eq!(nb.language.as_deref(), Some("python"));
+        assert_eq!(nb.cells.len(), 3);
+        assert_eq!(nb.cells[0].source, "# Title\nIntro");
+        assert_eq!(nb.cells[1].kind, CellKind::Code);
+
+        let text = nb.to_text();
+        assert!(text.contains("# %% [cell 1] markdown\n# Title"));
+        assert!(text.contains("# %% [cell 2]\nimport os"));
+        assert!(!text.contains("iVBORw0KGgo"));
+        assert!(!text.contains("raw text"));
+
+        assert!(Notebook::parse("not json").is_err());
+    }
+}
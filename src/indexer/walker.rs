@@ -1,14 +1,27 @@
 use anyhow::Result;
+use ignore::{DirEntry, WalkBuilder, WalkState};
 use std::collections::HashSet;
-use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::thread;
 
 use super::index::{FileEntry, FileType};
 
+/// Deepest directory level read below the root
+const MAX_DEPTH: usize = 20;
+
+/// Entries `walk_stream` may have waiting before its threads pause
+const STREAM_BUFFER: usize = 1024;
+
+/// Upper bound on `walk_stream` worker threads
+const MAX_THREADS: usize = 8;
+
+#[derive(Clone)]
 pub struct FileWalker {
     root: PathBuf,
-    ignore_patterns: Vec<String>,
-    default_ignores: HashSet<String>,
+    /// Honor `.gitignore` files (the root's and nested ones)
+    gitignore: bool,
+    default_ignores: Arc<HashSet<String>>,
 }
 
 impl FileWalker {
@@ -28,123 +41,101 @@ impl FileWalker {
 
         Self {
             root: root.as_ref().to_path_buf(),
-            ignore_patterns: Vec::new(),
-            default_ignores,
+            gitignore: false,
+            default_ignores: Arc::new(default_ignores),
         }
     }
 
+    /// Skip what `.gitignore` files below the root exclude, with full
+    /// gitignore syntax (globs, negation, nested files)
     pub fn load_gitignore(&mut self) -> Result<()> {
-        let gitignore_path = self.root.join(".gitignore");
-        if gitignore_path.exists() {
-            let content = fs::read_to_string(&gitignore_path)?;
-            for line in content.lines() {
-                let line = line.trim();
-                if !line.is_empty() && !line.starts_with('#') {
-                    self.ignore_patterns.push(line.to_string());
-                }
-            }
-        }
+        self.gitignore = true;
         Ok(())
     }
 
     pub fn walk(&self) -> Result<Vec<FileEntry>> {
-        let mut entries = Vec::new();
-        self.walk_dir(&self.root, &mut entries, 0)?;
-        Ok(entries)
+        Ok(self
+            .builder()
+            .build()
+            .filter_map(|entry| self.file_entry(entry.ok()?))
+            .collect())
     }
 
-    fn walk_dir(&self, dir: &Path, entries: &mut Vec<FileEntry>, depth: usize) -> Result<()> {
-        if depth > 20 {
-            return Ok(());
-        }
-
-        let read_dir = match fs::read_dir(dir) {
-            Ok(rd) => rd,
-            Err(_) => return Ok(()),
-        };
-
-        for entry in read_dir.flatten() {
-            let path = entry.path();
-            let file_name = entry.file_name().to_string_lossy().to_string();
-
-            if self.should_ignore(&file_name, &path) {
-                continue;
-            }
-
-            let metadata = match entry.metadata() {
-                Ok(m) => m,
-                Err(_) => continue,
-            };
-
-            let relative_path = path
-                .strip_prefix(&self.root)
-                .unwrap_or(&path)
-                .to_string_lossy()
-                .to_string();
-
-            if metadata.is_dir() {
-                entries.push(FileEntry {
-                    path: relative_path.clone(),
-                    file_type: FileType::Directory,
-                    size: 0,
-                    extension: None,
-                });
-                self.walk_dir(&path, entries, depth + 1)?;
-            } else if metadata.is_file() {
-                let extension = path.extension().map(|e| e.to_string_lossy().to_string());
-                let file_type = Self::detect_file_type(&extension);
-
-                entries.push(FileEntry {
-                    path: relative_path,
-                    file_type,
-                    size: metadata.len(),
-                    extension,
-                });
-            }
-        }
-
-        Ok(())
+    /// Walk on several threads, yielding entries as directories are read
+    /// instead of after the whole tree. Yields the same entries as `walk`,
+    /// in no particular order. At most a bounded number of entries wait
+    /// unread; dropping the iterator stops the walk.
+    pub fn walk_stream(&self) -> impl Iterator<Item = FileEntry> {
+        let (tx, rx) = mpsc::sync_channel(STREAM_BUFFER);
+        let threads = thread::available_parallelism().map_or(1, |n| n.get()).min(MAX_THREADS);
+        let walker = self.clone();
+        let parallel = self.builder().threads(threads).build_parallel();
+        thread::spawn(move || {
+            parallel.run(|| {
+                let (walker, tx) = (walker.clone(), tx.clone());
+                Box::new(move |entry| {
+                    let Some(entry) = entry.ok().and_then(|entry| walker.file_entry(entry)) else {
+                        return WalkState::Continue;
+                    };
+                    match tx.send(entry) {
+                        Ok(()) => WalkState::Continue,
+                        Err(_) => WalkState::Quit,
+                    }
+                })
+            })
+        });
+        rx.into_iter()
     }
 
-    fn should_ignore(&self, name: &str, path: &Path) -> bool {
-        if name.starts_with('.') && name != ".env.example" {
-            return true;
-        }
+    /// Hidden files and the default ignores are skipped here, so their
+    /// directories are never read; `.gitignore` rules come from the walker
+    fn builder(&self) -> WalkBuilder {
+        let mut builder = WalkBuilder::new(&self.root);
+        builder
+            .standard_filters(false)
+            .git_ignore(self.gitignore)
+            .require_git(false)
+            .max_depth(Some(MAX_DEPTH + 1));
+        let default_ignores = self.default_ignores.clone();
+        builder.filter_entry(move |entry| {
+            entry.depth() == 0 || !should_ignore(&default_ignores, &entry.file_name().to_string_lossy())
+        });
+        builder
+    }
 
-        if self.default_ignores.contains(name) {
-            return true;
+    /// The entry for a walked file or directory; None for the root,
+    /// symlinks and anything unreadable
+    fn file_entry(&self, entry: DirEntry) -> Option<FileEntry> {
+        if entry.depth() == 0 {
+            return None;
         }
-
-        let relative = path
+        let path = entry.path();
+        let metadata = entry.metadata().ok()?;
+        let relative_path = path
             .strip_prefix(&self.root)
             .unwrap_or(path)
             .to_string_lossy()
             .to_string();
 
-        for pattern in &self.ignore_patterns {
-            if self.matches_pattern(&relative, pattern) || self.matches_pattern(name, pattern) {
-                return true;
-            }
+        if metadata.is_dir() {
+            Some(FileEntry {
+                path: relative_path,
+                file_type: FileType::Directory,
+                size: 0,
+                extension: None,
+            })
+        } else if metadata.is_file() {
+            let extension = path.extension().map(|e| e.to_string_lossy().to_string());
+            let file_type = Self::detect_file_type(&extension);
+            Some(FileEntry {
+                path: relative_path,
+                file_type,
+                size: metadata.len(),
+                extension,
+            })
+        } else {
+            None
         }
-
-        false
-    }
-
-    fn matches_pattern(&self, path: &str, pattern: &str) -> bool {
-        let pattern = pattern.trim_start_matches('/');
-        let pattern = pattern.trim_end_matches('/');
-
-        if pattern.contains('*') {
-            let parts: Vec<&str> = pattern.split('*').collect();
-            if parts.len() == 2 {
-                let (prefix, suffix) = (parts[0], parts[1]);
-                return path.starts_with(prefix) && path.ends_with(suffix);
-            }
-        }
-
-        path == pattern
-            || path.starts_with(&format!("{}/", pattern))
-            || path.ends_with(&format!("/{}", pattern))
     }
 
     fn detect_file_type(extension: &Option<String>) -> FileType {
@@ -178,14 +169,70 @@ impl FileWalker {
     }
 }
 
+fn should_ignore(default_ignores: &HashSet<String>, name: &str) -> bool {
+    (name.starts_with('.') && name != ".env.example") || default_ignores.contains(name)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
 
     #[test]
-    fn test_pattern_matching() {
-        let walker = FileWalker::new("/tmp");
-        assert!(walker.matches_pattern("node_modules/test", "node_modules"));
-        assert!(walker.matches_pattern("test.log", "*.log"));
+    fn test_gitignore_rules() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src/generated")).unwrap();
+        for file in ["keep.log", "debug.log", "src/lib.rs", "src/generated/out.rs", "src/generated/mod.rs"] {
+            fs::write(root.join(file), file).unwrap();
+        }
+        fs::write(root.join(".gitignore"), "*.log\n!keep.log\n").unwrap();
+        fs::write(root.join("src/generated/.gitignore"), "*.rs\n!mod.rs\n").unwrap();
+
+        let mut walker = FileWalker::new(root);
+        let mut paths: Vec<String> = walker.walk().unwrap().into_iter().map(|e| e.path).collect();
+        paths.sort();
+        assert_eq!(paths.len(), 7);
+
+        walker.load_gitignore().unwrap();
+        let mut paths: Vec<String> = walker.walk().unwrap().into_iter().map(|e| e.path).collect();
+        paths.sort();
+        assert_eq!(paths, ["keep.log", "src", "src/generated", "src/generated/mod.rs", "src/lib.rs"]);
+    }
+
+    #[test]
+    fn test_walk_stream_matches_walk() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let root = tmp.path();
+        for dir in ["src/parser/nested", "docs", "node_modules/pkg", ".git", "logs"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "Cargo.toml", "src/main.rs", "src/parser/mod.rs", "src/parser/nested/deep.rs",
+            "docs/guide.md", "node_modules/pkg/index.js", ".git/HEAD", ".env.example",
+            "logs/run.log", "debug.log",
+        ] {
+            fs::write(root.join(file), file).unwrap();
+        }
+        for i in 0..50 {
+            fs::write(root.join("src").join(format!("gen_{}.rs", i)), "fn x() {}").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "*.log\nlogs/\n").unwrap();
+
+        let mut walker = FileWalker::new(root);
+        walker.load_gitignore().unwrap();
+        let key = |e: &FileEntry| (e.path.clone(), e.file_type.as_str().to_string(), e.size);
+        let mut walked: Vec<_> = walker.walk().unwrap().iter().map(key).collect();
+        let mut streamed: Vec<_> = walker.walk_stream().map(|e| key(&e)).collect();
+        walked.sort();
+        streamed.sort();
+
+        assert_eq!(walked.len(), 4 + 6 + 50);
+        assert_eq!(streamed, walked);
+        assert!(streamed.iter().any(|(path, _, _)| path == "src/parser/nested/deep.rs"));
+        assert!(!streamed.iter().any(|(path, _, _)| path.contains("node_modules") || path.ends_with(".log")));
+
+        // Stopping early leaves the workers to wind down on their own
+        assert_eq!(walker.walk_stream().take(3).count(), 3);
     }
 }
@@ -38,7 +38,8 @@ impl ResponseCache {
         }
     }
 
-    /// Generate cache key from messages
+    /// Generate cache key from messages. Images are left out: a tool
+    /// result's text already names each image it carries.
     fn cache_key(messages: &[super::Message]) -> u64 {
         use std::collections::hash_map::DefaultHasher;
         let mut hasher = DefaultHasher::new();
@@ -0,0 +1,191 @@
+// ============================================
+// WEBRANA CLI - RAG Citations
+// Maps [n] markers in an answer back to the injected chunks
+// ============================================
+
+use serde::Serialize;
+
+use super::rag::RetrievedChunk;
+
+/// Instruction appended to the system prompt when chunks are injected
+pub const CITATION_INSTRUCTIONS: &str = "When a statement relies on one of the numbered \
+    snippets above, cite it with its marker, e.g. [1] or [2][3]. Only cite numbers that \
+    were provided.";
+
+/// A cited chunk, as listed in the footer and the JSON `sources` array
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Source {
+    pub number: usize,
+    pub id: String,
+    pub file: Option<String>,
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+    pub score: f32,
+}
+
+impl Source {
+    /// `file:start-end`, falling back to the chunk id
+    pub fn location(&self) -> String {
+        let name = self.file.as_deref().unwrap_or(&self.id);
+        match (self.start_line, self.end_line) {
+            (Some(start), Some(end)) => format!("{}:{}-{}", name, start, end),
+            _ => name.to_string(),
+        }
+    }
+}
+
+/// An answer with its citations resolved
+#[derive(Debug, Clone)]
+pub struct CitedAnswer {
+    pub text: String,
+    /// Cited chunks by number; uncited chunks are left out
+    pub sources: Vec<Source>,
+    /// Cited numbers that match no injected chunk
+    pub unknown: Vec<usize>,
+}
+
+impl CitedAnswer {
+    /// Resolve the markers in `text` against `chunks`, which were numbered
+    /// from 1 in the context block
+    pub fn resolve(text: &str, chunks: &[RetrievedChunk]) -> Self {
+        let mut sources = Vec::new();
+        let mut unknown = Vec::new();
+        for number in citation_markers(text) {
+            match number.checked_sub(1).and_then(|i| chunks.get(i)) {
+                Some(chunk) => sources.push(Source {
+                    number,
+                    id: chunk.id.clone(),
+                    file: chunk.file_path.clone(),
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    score: chunk.score,
+                }),
+                None => unknown.push(number),
+            }
+        }
+        sources.sort_by_key(|s| s.number);
+        unknown.sort_unstable();
+        Self {
+            text: text.to_string(),
+            sources,
+            unknown,
+        }
+    }
+
+    /// The answer followed by its sources footer, if any
+    pub fn with_footer(&self) -> String {
+        match self.footer() {
+            Some(footer) => format!("{}\n\n{}", self.text.trim_end(), footer),
+            None => self.text.clone(),
+        }
+    }
+
+    /// "Sources:" footer expanding each cited number, or None without citations
+    pub fn footer(&self) -> Option<String> {
+        if self.sources.is_empty() {
+            return None;
+        }
+        let mut footer = String::from("Sources:");
+        for source in &self.sources {
+            footer.push_str(&format!("\n  [{}] {}", source.number, source.location()));
+        }
+        Some(footer)
+    }
+}
+
+/// Distinct `[n]` markers in order of first use. Fenced code blocks and
+/// inline code are skipped, as are link texts like `[1](url)`.
+pub fn citation_markers(text: &str) -> Vec<usize> {
+    let mut markers = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m));
+        match (fence, marker) {
+            (None, Some(m)) => {
+                fence = Some(m);
+                continue;
+            }
+            (Some(open), Some(m)) if open == m => {
+                fence = None;
+                continue;
+            }
+            (Some(_), _) => continue,
+            (None, None) => {}
+        }
+
+        // Even segments between backticks are prose
+        for prose in line.split('`').step_by(2) {
+            let mut rest = prose;
+            while let Some(start) = rest.find('[') {
+                rest = &rest[start + 1..];
+                let Some(end) = rest.find(']') else { break };
+                let inner = &rest[..end];
+                let is_link = rest[end + 1..].starts_with('(');
+                if !inner.is_empty() && inner.bytes().all(|b| b.is_ascii_digit()) && !is_link {
+                    if let Ok(n) = inner.parse() {
+                        if !markers.contains(&n) {
+                            markers.push(n);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    markers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &str, file: &str, start: usize, end: usize, score: f32) -> RetrievedChunk {
+        RetrievedChunk {
+            id: id.to_string(),
+            content: String::new(),
+            score,
+            file_path: Some(file.to_string()),
+            start_line: Some(start),
+            end_line: Some(end),
+        }
+    }
+
+    #[test]
+    fn test_footer_with_valid_and_unknown_citations() {
+        let chunks = vec![
+            chunk("a:chunk:0", "src/core/orchestrator.rs", 120, 180, 0.91),
+            chunk("b:chunk:2", "src/llm/rag.rs", 93, 120, 0.74),
+            chunk("c:chunk:1", "src/main.rs", 1, 40, 0.52),
+        ];
+        let response = "Retries happen in the client [3]. The loop lives in the \
+                        orchestrator [1][3], and caching is in [7]. See [0] too.";
+
+        let answer = CitedAnswer::resolve(response, &chunks);
+        assert_eq!(answer.unknown, vec![0, 7]);
+        assert_eq!(
+            answer.footer().unwrap(),
+            "Sources:\n  [1] src/core/orchestrator.rs:120-180\n  [3] src/main.rs:1-40"
+        );
+        // The answer text is kept as written
+        assert_eq!(answer.text, response);
+
+        assert!(CitedAnswer::resolve("No citations here.", &chunks).footer().is_none());
+    }
+
+    #[test]
+    fn test_code_is_not_parsed_for_markers() {
+        let response = "Index with `items[2]` as in [2]:\n\
+                        ```rust\n\
+                        let first = items[1];\n\
+                        ```\n\
+                        ~~~\n\
+                        [4]\n\
+                        ~~~\n\
+                        See the [docs](https://example.com) and [5](https://x.y).";
+        assert_eq!(citation_markers(response), vec![2]);
+
+        // An unclosed fence hides the rest
+        assert!(citation_markers("```\n[1]\n").is_empty());
+    }
+}
@@ -1,76 +1,438 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
+use futures::StreamExt;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Instant;
+use tracing::Instrument;
 
 #[allow(unused_imports)]
 use super::providers::{
-    AnthropicProvider, ChatResponse, Message, OllamaProvider, OpenAIProvider, Provider, ToolCall,
-    ToolDefinition,
+    AnthropicProvider, ApiError, ChatResponse, Message, OllamaProvider, OpenAIProvider, Provider, Refused,
+    ResponseProvenance, TokenUsage, ToolCall, ToolChoice, ToolDefinition,
 };
+use super::replay::ReplayProvider;
+use super::usage::{UsageEntry, UsageLedger};
 use super::webrana::WebranaProvider;
 use super::cache::ResponseCache;
-use super::retry::{with_retry, RetryConfig};
-use crate::config::Settings;
-use crate::skills::SkillRegistry;
+use super::events::{self, StreamEvent};
+use super::images::{self, ImageArtifact, ARTIFACTS_DIR, MAX_IMAGES_PER_TURN};
+use super::retry::{should_fall_back, with_retry, RetryConfig};
+use super::tool_budget::{ToolSelection, ToolSelector, LIST_MORE_TOOLS};
+use crate::config::{ModelConfig, Settings};
+use crate::core::audit::{AuditEvent, AuditEventType, AuditSeverity, AUDIT};
+use crate::core::metrics::METRICS;
+use crate::core::scrub::scrub_tool_output;
+use crate::core::{StepAborted, StepDecision};
+use crate::memory::working_set::{self, WorkingSetPolicy};
+use crate::memory::ContextBudget;
+use crate::skills::{SkillOutput, SkillRegistry};
+use crate::util::{connectivity, unix_now};
+
+/// Read-only tool calls from one response run at most this many at a time
+const MAX_PARALLEL_TOOLS: usize = 4;
+
+/// Counter of responses the model declined on content-policy grounds
+pub const REFUSALS_METRIC: &str = "llm.refusals";
 
 pub struct LlmClient {
     provider: Arc<dyn Provider>,
+    /// Tried in order after a hard failure of `provider`: (provider, model)
+    fallbacks: Vec<(Arc<dyn Provider>, String)>,
     settings: Settings,
     cache: Arc<ResponseCache>,
     retry_config: RetryConfig,
+    /// Tool usage and `list_more_tools` requests of this session
+    tool_selector: std::sync::Mutex<ToolSelector>,
+    /// `name(arguments)` of each tool call made by the last tool loop
+    tool_calls: std::sync::Mutex<Vec<String>>,
+    /// Tool calls of this client's lifetime by skill name
+    skill_uses: std::sync::Mutex<std::collections::BTreeMap<String, usize>>,
+    /// Who answered the last request
+    provenance: std::sync::Mutex<Option<ResponseProvenance>>,
+    /// Where each request's tokens and cost are recorded
+    usage_ledger: Option<UsageLedger>,
+    /// Where tool result images go when the model can't view them
+    artifacts_dir: std::path::PathBuf,
+    /// Tool choice of the next tool request; a forced tool applies once
+    tool_choice: std::sync::Mutex<ToolChoice>,
+    /// Context budget warnings already logged, so each shows once
+    budget_warnings: std::sync::Mutex<std::collections::HashSet<String>>,
+    /// Context budgets of the fallback models, by model name. Other models
+    /// get the default model's.
+    budgets: std::collections::HashMap<String, ContextBudget>,
+}
+
+/// Build the provider for one model configuration
+async fn build_provider(model_config: &ModelConfig, settings: &Settings) -> Result<Arc<dyn Provider>> {
+    let api_key = settings.get_api_key(model_config);
+    let sampling = settings.sampling.resolved();
+
+    let provider: Arc<dyn Provider> = match model_config.provider.as_str() {
+        "anthropic" => {
+            let key = api_key
+                .context("Anthropic API key not found. Set ANTHROPIC_API_KEY env var.")?;
+            Arc::new(
+                AnthropicProvider::new(key, model_config.model.clone(), model_config.max_tokens)
+                    .with_sampling(sampling),
+            )
+        }
+        "openai" | "openai_compatible" => {
+            let key =
+                api_key.context("OpenAI API key not found. Set OPENAI_API_KEY env var.")?;
+            Arc::new(
+                OpenAIProvider::new(key, model_config.model.clone(), model_config.base_url.clone())
+                    .with_sampling(sampling),
+            )
+        }
+        "ollama" => {
+            let base_url = model_config
+                .base_url
+                .clone()
+                .unwrap_or_else(|| "http://localhost:11434".to_string());
+            Arc::new(OllamaProvider::new(base_url, model_config.model.clone()).with_sampling(sampling))
+        }
+        "webrana" => {
+            // Built-in Webrana API provider (free tier)
+            let webrana = WebranaProvider::new().await
+                .context("Failed to initialize Webrana provider. Try 'webrana login' to re-register.")?;
+            Arc::new(webrana)
+        }
+        "replay" => {
+            // The recorded responses file stands in for the server
+            let path = model_config
+                .base_url
+                .as_deref()
+                .context("The replay provider needs base_url set to a responses file")?;
+            Arc::new(ReplayProvider::load(std::path::Path::new(path))?)
+        }
+        _ => anyhow::bail!("Unknown provider: {}", model_config.provider),
+    };
+
+    if sampling.seed.is_some() && !supports_seed(&model_config.provider) {
+        tracing::warn!(
+            "The {} provider does not support --seed; only temperature and top_p are fixed",
+            model_config.provider
+        );
+    }
+
+    Ok(provider)
+}
+
+/// Providers whose APIs take a sampling seed
+fn supports_seed(provider: &str) -> bool {
+    matches!(provider, "openai" | "openai_compatible" | "ollama")
+}
+
+/// Settings with the model to use given the network. Offline, that is a
+/// local model, or an error right away when none is configured. Online, the
+/// provider (or its proxy) is probed so a dead network fails in seconds.
+async fn network_ready(settings: &Settings, offline: bool) -> Result<Settings> {
+    let mut settings = settings.clone();
+    if offline {
+        let local = settings.local_model().map(str::to_string).with_context(|| {
+            format!(
+                "Offline mode: model '{}' needs the network and no local model is configured. \
+                 Add an Ollama model under [models] in the config, or run without --offline.",
+                settings.default_model
+            )
+        })?;
+        if local != settings.default_model {
+            eprintln!("Offline: using local model '{}'", local);
+            settings.default_model = local;
+        }
+        return Ok(settings);
+    }
+
+    let endpoint = settings
+        .get_model(&settings.default_model)
+        .and_then(connectivity::provider_endpoint)
+        .filter(|url| !connectivity::is_loopback(url));
+    if let Some((host, port)) = endpoint.as_ref().and_then(connectivity::probe_target) {
+        if !connectivity::reachable(&host, port).await {
+            anyhow::bail!(
+                "Network unreachable ({}:{} did not answer) — retry with --offline to use local models only",
+                host,
+                port
+            );
+        }
+    }
+    Ok(settings)
 }
 
 impl LlmClient {
     pub async fn new(settings: &Settings) -> Result<Self> {
+        let offline = crate::util::http::is_offline();
+        let settings = &network_ready(settings, offline).await?;
         let model_config = settings
             .get_model(&settings.default_model)
             .context("Default model not found in configuration")?;
 
-        let api_key = settings.get_api_key(model_config);
-
-        let provider: Arc<dyn Provider> = match model_config.provider.as_str() {
-            "anthropic" => {
-                let key = api_key
-                    .context("Anthropic API key not found. Set ANTHROPIC_API_KEY env var.")?;
-                Arc::new(AnthropicProvider::new(
-                    key,
-                    model_config.model.clone(),
-                    model_config.max_tokens,
-                ))
-            }
-            "openai" | "openai_compatible" => {
-                let key =
-                    api_key.context("OpenAI API key not found. Set OPENAI_API_KEY env var.")?;
-                Arc::new(OpenAIProvider::new(
-                    key,
-                    model_config.model.clone(),
-                    model_config.base_url.clone(),
-                ))
-            }
-            "ollama" => {
-                let base_url = model_config
-                    .base_url
-                    .clone()
-                    .unwrap_or_else(|| "http://localhost:11434".to_string());
-                Arc::new(OllamaProvider::new(base_url, model_config.model.clone()))
+        let provider = build_provider(model_config, settings).await?;
+
+        // Fallbacks that can't be built (e.g. no API key) are skipped
+        let mut fallbacks = Vec::new();
+        let mut budgets = std::collections::HashMap::new();
+        for entry in &model_config.fallback {
+            let Some(config) = settings.resolve_fallback(entry) else {
+                tracing::warn!("Unknown fallback model '{}', skipping", entry);
+                continue;
+            };
+            if offline && !connectivity::is_local_model(&config) {
+                continue;
             }
-            "webrana" => {
-                // Built-in Webrana API provider (free tier)
-                let webrana = WebranaProvider::new().await
-                    .context("Failed to initialize Webrana provider. Try 'webrana login' to re-register.")?;
-                Arc::new(webrana)
+            match build_provider(&config, settings).await {
+                Ok(fallback) => {
+                    budgets.insert(config.model.clone(), ContextBudget::for_model(settings, &config));
+                    fallbacks.push((fallback, config.model.clone()));
+                }
+                Err(e) => tracing::warn!("Fallback '{}' unavailable: {}", entry, e),
             }
-            _ => anyhow::bail!("Unknown provider: {}", model_config.provider),
-        };
+        }
 
         Ok(Self {
             provider,
+            fallbacks,
             settings: settings.clone(),
             cache: Arc::new(ResponseCache::default()),
             retry_config: RetryConfig::default(),
+            tool_selector: Default::default(),
+            tool_calls: Default::default(),
+            skill_uses: Default::default(),
+            provenance: Default::default(),
+            usage_ledger: UsageLedger::open_default(),
+            artifacts_dir: ARTIFACTS_DIR.into(),
+            tool_choice: Default::default(),
+            budget_warnings: Default::default(),
+            budgets,
         })
     }
 
+    /// Create a client around an already constructed provider
+    pub fn from_provider(provider: Arc<dyn Provider>, settings: &Settings) -> Self {
+        Self {
+            provider,
+            fallbacks: Vec::new(),
+            settings: settings.clone(),
+            cache: Arc::new(ResponseCache::default()),
+            retry_config: RetryConfig::default(),
+            tool_selector: Default::default(),
+            tool_calls: Default::default(),
+            skill_uses: Default::default(),
+            provenance: Default::default(),
+            usage_ledger: None,
+            artifacts_dir: ARTIFACTS_DIR.into(),
+            tool_choice: Default::default(),
+            budget_warnings: Default::default(),
+            budgets: Default::default(),
+        }
+    }
+
+    /// Providers (with their model names) to try after the primary one fails
+    pub fn with_fallbacks(mut self, fallbacks: Vec<(Arc<dyn Provider>, String)>) -> Self {
+        self.fallbacks = fallbacks;
+        self
+    }
+
+    /// Record every request in `ledger`
+    pub fn with_usage_ledger(mut self, ledger: UsageLedger) -> Self {
+        self.usage_ledger = Some(ledger);
+        self
+    }
+
+    /// Save tool result images the model can't view under `dir`
+    pub fn with_artifacts_dir(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.artifacts_dir = dir.into();
+        self
+    }
+
+    /// Steer tool use: `None` keeps every request text-only, a named tool is
+    /// forced on the next tool request and the model decides after that
+    pub fn with_tool_choice(self, choice: ToolChoice) -> Self {
+        *self.tool_choice.lock().unwrap() = choice;
+        self
+    }
+
+    /// Choice for the next tool request, with `selection` made to offer a
+    /// forced tool the tool budget left out
+    fn next_tool_choice(&self, skill_registry: &SkillRegistry, selection: &mut ToolSelection) -> ToolChoice {
+        let mut current = self.tool_choice.lock().unwrap();
+        let choice = match &*current {
+            ToolChoice::Tool(_) => std::mem::take(&mut *current),
+            other => other.clone(),
+        };
+        if let ToolChoice::Tool(name) = &choice {
+            if !selection.tools.iter().any(|tool| &tool.name == name) {
+                if let Some(tool) = self.get_tool_definitions(skill_registry).into_iter().find(|tool| &tool.name == name) {
+                    selection.tools.push(tool);
+                }
+            }
+        }
+        choice
+    }
+
+    fn model_name(&self) -> &str {
+        self.settings
+            .get_model(&self.settings.default_model)
+            .map(|m| m.model.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Send a request to the primary provider, moving down the fallback
+    /// chain on hard failures (auth, 5xx, timeouts after retries)
+    async fn request(
+        &self,
+        messages: &[Message],
+        tools: Option<Vec<ToolDefinition>>,
+        choice: &ToolChoice,
+        stream: bool,
+        retry: bool,
+    ) -> Result<ChatResponse> {
+        let chain = std::iter::once((&self.provider, self.model_name()))
+            .chain(self.fallbacks.iter().map(|(p, m)| (p, m.as_str())));
+        let requested = messages;
+
+        // Retries and fallbacks each get an `attempt` event
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let mut last_error = None;
+        for (index, (provider, model)) in chain.enumerate() {
+            let messages = &self.fit_to_window(requested, index, model);
+            let started = Instant::now();
+            let call = || {
+                if stream {
+                    let attempt = attempts.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+                    events::begin_request(attempt, provider.name(), model);
+                }
+                let p = provider.clone();
+                let m = messages.to_vec();
+                let t = tools.clone();
+                async move { p.chat_with_choice(m, t, choice, stream).await }
+            };
+            let result = if retry {
+                self.traced(provider.as_ref(), model, messages, with_retry(&self.retry_config, call))
+                    .await
+            } else {
+                self.traced(provider.as_ref(), model, messages, call()).await
+            };
+
+            match result {
+                Ok(mut response) => {
+                    if index > 0 {
+                        tracing::info!("Response served by fallback provider {} ({})", provider.name(), model);
+                    }
+                    response.provenance = ResponseProvenance {
+                        provider: provider.name().to_string(),
+                        model: model.to_string(),
+                        cached: false,
+                        fallback_used: index > 0,
+                        attempt: (index + 1).min(u8::MAX as usize) as u8,
+                        latency_ms: started.elapsed().as_millis() as u64,
+                        seed: self.settings.sampling.seed,
+                        system_fingerprint: response.provenance.system_fingerprint.take(),
+                        usage: response.provenance.usage.take(),
+                    };
+                    *self.provenance.lock().unwrap() = Some(response.provenance.clone());
+                    let (usage, estimated) = request_usage(messages, &response);
+                    self.record_usage(&response.provenance, usage, estimated);
+                    if stream {
+                        events::end_request(&response.content, usage);
+                    }
+                    // Deterministic: neither a retry nor a fallback would answer differently
+                    if let Some(refusal) = response.refusal.take() {
+                        METRICS.increment(REFUSALS_METRIC);
+                        tracing::warn!("Provider {} ({}) refused the request ({})", provider.name(), model, refusal.kind.label());
+                        return Err(Refused(refusal).into());
+                    }
+                    return Ok(response);
+                }
+                Err(e) if index < self.fallbacks.len() && should_fall_back(&e) => {
+                    tracing::warn!("Provider {} ({}) failed: {}. Falling back...", provider.name(), model, e);
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No provider available")))
+    }
+
+    /// `messages` with the oldest conversation left out if the request would
+    /// overflow the window of `model`, the chain's `index`th; see
+    /// [`ContextBudget`]. Leaving messages out is shown every time, since
+    /// the history itself keeps them and the next request drops them again.
+    fn fit_to_window(&self, messages: &[Message], index: usize, model: &str) -> Vec<Message> {
+        let budget = match self.budgets.get(model) {
+            Some(budget) if index > 0 => *budget,
+            _ => ContextBudget::from_settings(&self.settings),
+        };
+        let mut fitted = messages.to_vec();
+        for warning in budget.fit_messages(&mut fitted) {
+            if self.budget_warnings.lock().unwrap().insert(warning.clone()) {
+                tracing::warn!("{}", warning);
+            }
+        }
+        let dropped = messages.len() - fitted.len();
+        if dropped > 0 {
+            let remedy = if self.settings.auto_compact_at.is_some() {
+                "/compact summarizes them instead"
+            } else {
+                "/compact or auto_compact_at summarizes them instead"
+            };
+            events::status(&format!(
+                "{} Left the {} oldest message(s) out of the request to {} to fit its context window; {}",
+                "[CONTEXT]".yellow(),
+                dropped,
+                model,
+                remedy
+            ));
+        }
+        fitted
+    }
+
+    /// Append the request to the usage ledger. Failures only log:
+    /// accounting must never fail a request.
+    fn record_usage(&self, provenance: &ResponseProvenance, usage: TokenUsage, estimated: bool) {
+        let Some(ledger) = &self.usage_ledger else { return };
+        let time = unix_now();
+        let entry = UsageEntry::new(time, &provenance.provider, &provenance.model, usage, estimated);
+        if let Err(e) = ledger.record(&entry) {
+            tracing::warn!("Could not record usage: {}", e);
+        }
+    }
+
+    /// Run a provider request inside an `llm.request` span. Only sizes and
+    /// timings are recorded, never prompt or response text.
+    async fn traced<F>(
+        &self,
+        provider: &dyn Provider,
+        model: &str,
+        messages: &[Message],
+        request: F,
+    ) -> Result<ChatResponse>
+    where
+        F: Future<Output = Result<ChatResponse>>,
+    {
+        let input_chars: usize = messages.iter().map(Message::chars).sum();
+        let span = tracing::info_span!(
+            "llm.request",
+            provider = provider.name(),
+            model = model,
+            input_tokens = (input_chars / 4) as u64,
+            output_tokens = tracing::field::Empty,
+            tool_calls = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+
+        let start = Instant::now();
+        let result = request.instrument(span.clone()).await;
+        span.record("latency_ms", start.elapsed().as_millis() as u64);
+        if let Ok(response) = &result {
+            span.record("output_tokens", (response.content.len() / 4) as u64);
+            span.record("tool_calls", response.tool_calls.len() as u64);
+        }
+        result
+    }
+
     /// Create client with custom cache and retry configuration
     pub async fn with_config(
         settings: &Settings,
@@ -103,24 +465,27 @@ impl LlmClient {
         messages.extend(history.iter().cloned());
         messages.push(Message::user(user_message));
 
-        // Check cache first
-        if let Some(cached) = self.cache.get(&messages) {
+        // Check cache first; seeded runs always ask the provider
+        let use_cache = self.settings.sampling.seed.is_none();
+        if let Some(cached) = self.cache.get(&messages).filter(|_| use_cache) {
             tracing::debug!("Cache hit for chat request");
+            *self.provenance.lock().unwrap() = Some(ResponseProvenance {
+                provider: self.provider.name().to_string(),
+                model: self.model_name().to_string(),
+                cached: true,
+                attempt: 1,
+                ..Default::default()
+            });
             return Ok(cached);
         }
 
         // Execute with retry logic
-        let provider = self.provider.clone();
-        let msgs = messages.clone();
-        let response = with_retry(&self.retry_config, || {
-            let p = provider.clone();
-            let m = msgs.clone();
-            async move { p.chat(m, None).await }
-        })
-        .await?;
+        let response = self.request(&messages, None, &ToolChoice::Auto, false, true).await?;
 
         // Cache the response
-        self.cache.set(&messages, response.content.clone());
+        if use_cache {
+            self.cache.set(&messages, response.content.clone());
+        }
 
         Ok(response.content)
     }
@@ -137,14 +502,7 @@ impl LlmClient {
 
         // Streaming responses are not cached (real-time output)
         // But we still use retry logic
-        let provider = self.provider.clone();
-        let msgs = messages.clone();
-        let response = with_retry(&self.retry_config, || {
-            let p = provider.clone();
-            let m = msgs.clone();
-            async move { p.chat_stream(m, None).await }
-        })
-        .await?;
+        let response = self.request(&messages, None, &ToolChoice::Auto, true, true).await?;
 
         Ok(response.content)
     }
@@ -156,23 +514,58 @@ impl LlmClient {
         user_message: &str,
         skill_registry: &SkillRegistry,
     ) -> Result<ChatResponse> {
-        let mut messages = vec![Message::system(system_prompt)];
+        let mut selection = self.select_tools(skill_registry, user_message);
+        let choice = self.next_tool_choice(skill_registry, &mut selection);
+        let mut messages = vec![Message::system(with_tool_note(system_prompt, &selection))];
         messages.extend(history.iter().cloned());
         messages.push(Message::user(user_message));
 
-        // Convert skills to tool definitions
-        let tools: Vec<ToolDefinition> = skill_registry
-            .list()
-            .iter()
-            .map(|skill| ToolDefinition {
-                name: skill.name.clone(),
-                description: skill.description.clone(),
-                input_schema: skill.parameters.clone(),
-            })
-            .collect();
+        self.request(&messages, Some(selection.tools), &choice, true, false).await
+    }
 
-        let response = self.provider.chat_stream(messages, Some(tools)).await?;
-        Ok(response)
+    /// Tools for a request about `message`, within the default model's
+    /// tool budget
+    pub fn select_tools(&self, skill_registry: &SkillRegistry, message: &str) -> ToolSelection {
+        self.tool_selector.lock().unwrap().select(
+            self.get_tool_definitions(skill_registry),
+            message,
+            self.settings.tool_budget(),
+        )
+    }
+
+    /// Answer a `list_more_tools` call made while working on `message`;
+    /// the matches are sent from the next request on
+    pub fn list_more_tools(&self, arguments: &serde_json::Value, skill_registry: &SkillRegistry, message: &str) -> String {
+        let selection = self.select_tools(skill_registry, message);
+        let query = arguments["query"].as_str().unwrap_or_default();
+        self.tool_selector.lock().unwrap().list_more(
+            query,
+            &self.get_tool_definitions(skill_registry),
+            &selection,
+        )
+    }
+
+    /// `provider/model` of the primary model, then of each fallback in order
+    pub fn model_chain(&self) -> Vec<String> {
+        let primary = self
+            .settings
+            .get_model(&self.settings.default_model)
+            .map(|config| config.model.as_str())
+            .unwrap_or(self.settings.default_model.as_str());
+        std::iter::once(format!("{}/{}", self.provider.name(), primary))
+            .chain(self.fallbacks.iter().map(|(provider, model)| format!("{}/{}", provider.name(), model)))
+            .collect()
+    }
+
+    /// Provider and model behind the last response, including cache hits.
+    /// For a tool loop this is the request that produced the final answer.
+    pub fn last_provenance(&self) -> Option<ResponseProvenance> {
+        self.provenance.lock().unwrap().clone()
+    }
+
+    /// Signatures of the tool calls made by the last `chat_with_tools_loop`
+    pub fn take_tool_calls(&self) -> Vec<String> {
+        std::mem::take(&mut *self.tool_calls.lock().unwrap())
     }
 
     pub async fn chat_with_tools_loop(
@@ -182,22 +575,15 @@ impl LlmClient {
         user_message: &str,
         skill_registry: &SkillRegistry,
     ) -> Result<String> {
-        history.push(Message::user(user_message));
+        history.push(Message::user(user_message).stamped());
+        self.tool_calls.lock().unwrap().clear();
 
-        let mut messages = vec![Message::system(system_prompt)];
+        let policy = WorkingSetPolicy::from_settings(&self.settings.tool_output);
+        let system_prompt = &with_working_set_note(system_prompt, &policy);
+        let mut selection = self.select_tools(skill_registry, user_message);
+        let mut messages = vec![Message::system(with_tool_note(system_prompt, &selection))];
         messages.extend(history.iter().cloned());
 
-        // Convert skills to tool definitions
-        let tools: Vec<ToolDefinition> = skill_registry
-            .list()
-            .iter()
-            .map(|skill| ToolDefinition {
-                name: skill.name.clone(),
-                description: skill.description.clone(),
-                input_schema: skill.parameters.clone(),
-            })
-            .collect();
-
         let max_iterations = 10;
         let mut iteration = 0;
         let mut final_content = String::new();
@@ -205,13 +591,13 @@ impl LlmClient {
         loop {
             iteration += 1;
             if iteration > max_iterations {
-                println!("\n[Max tool iterations reached]");
+                events::status("\n[Max tool iterations reached]");
                 break;
             }
 
+            let choice = self.next_tool_choice(skill_registry, &mut selection);
             let response = self
-                .provider
-                .chat_stream(messages.clone(), Some(tools.clone()))
+                .request(&messages, Some(selection.tools.clone()), &choice, true, false)
                 .await?;
             final_content = response.content.clone();
 
@@ -221,49 +607,222 @@ impl LlmClient {
             }
 
             // Add assistant message with tool calls
-            history.push(Message::assistant(&response.content));
+            history.push(
+                Message::assistant(&response.content)
+                    .with_tool_calls(response.tool_calls.clone())
+                    .stamped(),
+            );
+            let step = working_set::current_iteration(history);
+
+            // Under --confirm-each, show what the model intends before asking
+            if skill_registry.stepper().is_some() {
+                if !response.content.trim().is_empty() {
+                    events::status(&format!("\n{} {}", "[MODEL]".blue(), response.content.trim()));
+                }
+                let names: Vec<&str> = response.tool_calls.iter().map(|c| c.name.as_str()).collect();
+                events::status(&format!("{} {}", "[PLAN]".blue(), names.join(", ")));
+            }
+
+            // Execute each tool call. A run of read-only calls executes
+            // concurrently; calls with side effects go one at a time, in order.
+            let mut image_budget = MAX_IMAGES_PER_TURN;
+            let mut calls = response.tool_calls.iter().peekable();
+            while let Some(tool_call) = calls.next() {
+                self.record_call(tool_call);
+                if tool_call.name == LIST_MORE_TOOLS {
+                    let result = self.list_more_tools(&tool_call.arguments, skill_registry, user_message);
+                    events::emit(&StreamEvent::ToolResult {
+                        name: &tool_call.name,
+                        ok: true,
+                        summary: &events::summary(&result),
+                    });
+                    let message = Message::tool_result(tool_call, result);
+                    history.push(working_set::tag(message, tool_call, step));
+                    continue;
+                }
+
+                if skill_registry.stepper().is_none() && skill_registry.is_parallel_safe(&tool_call.name) {
+                    let mut batch = vec![tool_call];
+                    while let Some(next) = calls.next_if(|c| skill_registry.is_parallel_safe(&c.name)) {
+                        self.record_call(next);
+                        batch.push(next);
+                    }
+                    let names: Vec<&str> = batch.iter().map(|c| c.name.as_str()).collect();
+                    if batch.len() > 1 {
+                        events::status(&format!(
+                            "\n{} Executing {} tools in parallel: {}",
+                            "[TOOL]".magenta(),
+                            batch.len(),
+                            names.join(", ").cyan()
+                        ));
+                    } else {
+                        events::status(&format!("\n{} Executing tool: {}", "[TOOL]".magenta(), names[0].cyan()));
+                    }
+
+                    let results: Vec<Result<SkillOutput>> = futures::stream::iter(
+                        batch
+                            .iter()
+                            .map(|call| skill_registry.execute_output(&call.name, &call.arguments, &self.settings)),
+                    )
+                    .buffered(MAX_PARALLEL_TOOLS)
+                    .collect()
+                    .await;
+                    for (call, result) in batch.into_iter().zip(results) {
+                        let message = self.tool_result_message(call, result, &mut image_budget);
+                        history.push(working_set::tag(message, call, step));
+                    }
+                    continue;
+                }
+
+                let mut arguments = tool_call.arguments.clone();
+                if let Some(stepper) = skill_registry.stepper() {
+                    let decision = stepper(&tool_call.name, &tool_call.arguments);
+                    audit_step(&tool_call.name, &tool_call.arguments, &decision);
+                    match decision {
+                        StepDecision::Continue => {}
+                        StepDecision::Edit(edited) => arguments = edited,
+                        StepDecision::Skip => {
+                            events::emit(&StreamEvent::ToolResult {
+                                name: &tool_call.name,
+                                ok: false,
+                                summary: "Skipped by the user",
+                            });
+                            history.push(Message::tool_result(tool_call, "Skipped by the user"));
+                            continue;
+                        }
+                        StepDecision::Abort => return Err(StepAborted.into()),
+                    }
+                }
 
-            // Execute each tool call
-            for tool_call in &response.tool_calls {
-                println!(
+                events::status(&format!(
                     "\n{} Executing tool: {}",
                     "[TOOL]".magenta(),
                     tool_call.name.as_str().cyan()
-                );
+                ));
 
                 let result = skill_registry
-                    .execute(&tool_call.name, &tool_call.arguments, &self.settings)
+                    .execute_output(&tool_call.name, &arguments, &self.settings)
                     .await;
-
-                let result_str = match result {
-                    Ok(output) => {
-                        println!("{}", output.as_str().dimmed());
-                        output
-                    }
-                    Err(e) => {
-                        let err_msg = format!("Error: {}", e);
-                        println!("{}", err_msg.as_str().red());
-                        err_msg
-                    }
-                };
-
-                // Add tool result to messages
-                // For Anthropic, we need to format this as a user message with tool_result
-                let tool_result_msg = format!(
-                    "<tool_result tool_use_id=\"{}\">\n{}\n</tool_result>",
-                    tool_call.id, result_str
-                );
-                history.push(Message::user(&tool_result_msg));
+                let message = self.tool_result_message(tool_call, result, &mut image_budget);
+                history.push(working_set::tag(message, tool_call, step));
             }
 
-            // Update messages for next iteration
-            messages = vec![Message::system(system_prompt)];
+            // Results past their last iteration shrink to a one-line record
+            policy.evict(history);
+
+            // Update messages for next iteration; tools used or requested
+            // meanwhile may change the selection
+            selection = self.select_tools(skill_registry, user_message);
+            messages = vec![Message::system(with_tool_note(system_prompt, &selection))];
             messages.extend(history.iter().cloned());
         }
 
+        // Results the answer cites stay; the rest remain in the working set
+        working_set::settle(history, &final_content);
         Ok(final_content)
     }
 
+    /// Tool calls made so far by skill name
+    pub fn skill_uses(&self) -> std::collections::BTreeMap<String, usize> {
+        self.skill_uses.lock().unwrap().clone()
+    }
+
+    fn record_call(&self, tool_call: &ToolCall) {
+        events::emit(&StreamEvent::ToolCall { name: &tool_call.name, arguments: &tool_call.arguments });
+        *self.skill_uses.lock().unwrap().entry(tool_call.name.clone()).or_default() += 1;
+        self.tool_calls
+            .lock()
+            .unwrap()
+            .push(format!("{}({})", tool_call.name, tool_call.arguments));
+    }
+
+    /// Print the outcome of `tool_call` and wrap it for the next request.
+    /// Images use up `image_budget`, the attachments left this turn.
+    fn tool_result_message(
+        &self,
+        tool_call: &ToolCall,
+        result: Result<SkillOutput>,
+        image_budget: &mut usize,
+    ) -> Message {
+        self.tool_selector.lock().unwrap().record_use(&tool_call.name);
+        let keep_colors = self.settings.tool_output.keep_colors;
+        let (mut result_str, images) = match result {
+            Ok(output) => {
+                let text = scrub_tool_output(&tool_call.name, &output.text, keep_colors);
+                events::status(&text.display.as_str().dimmed().to_string());
+                events::emit(&StreamEvent::ToolResult {
+                    name: &tool_call.name,
+                    ok: true,
+                    summary: &events::summary(&text.model),
+                });
+                (text.model, output.images)
+            }
+            Err(e) => {
+                let err_msg = scrub_tool_output(&tool_call.name, &format!("Error: {}", e), keep_colors);
+                events::status(&err_msg.display.as_str().red().to_string());
+                events::emit(&StreamEvent::ToolResult {
+                    name: &tool_call.name,
+                    ok: false,
+                    summary: &events::summary(&err_msg.model),
+                });
+                (err_msg.model, Vec::new())
+            }
+        };
+
+        let (notes, attached) = self.place_images(tool_call, images, image_budget);
+        for note in &notes {
+            events::status(&note.as_str().dimmed().to_string());
+            result_str.push('\n');
+            result_str.push_str(note);
+        }
+
+        // For Anthropic, we need to format this as a user message with tool_result
+        Message::tool_result(tool_call, result_str).with_images(attached)
+    }
+
+    /// Split a tool's images into those attached for a vision model (while
+    /// the turn's budget lasts) and those saved to the artifacts directory.
+    /// Returns a line of text per image, and the attached ones.
+    fn place_images(
+        &self,
+        tool_call: &ToolCall,
+        images: Vec<ImageArtifact>,
+        budget: &mut usize,
+    ) -> (Vec<String>, Vec<ImageArtifact>) {
+        let vision = self
+            .settings
+            .get_model(&self.settings.default_model)
+            .is_some_and(ModelConfig::vision);
+        let id: String = tool_call
+            .id
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+
+        let mut notes = Vec::new();
+        let mut attached = Vec::new();
+        for (index, image) in images.into_iter().enumerate() {
+            if let Err(e) = image.validate() {
+                notes.push(format!("[image: {} — not shown: {}]", image.label(), e));
+                continue;
+            }
+            if vision && *budget > 0 {
+                *budget -= 1;
+                notes.push(format!("[image: {} — attached]", image.label()));
+                attached.push(image);
+                continue;
+            }
+            let why = if vision {
+                format!("over the limit of {} images per turn", MAX_IMAGES_PER_TURN)
+            } else {
+                "the current model can't view images".to_string()
+            };
+            let saved = image.save(&self.artifacts_dir, &format!("{}-{}-{}", tool_call.name, id, index + 1));
+            notes.push(images::placeholder(&image, &saved, &why));
+        }
+        (notes, attached)
+    }
+
     pub fn get_tool_definitions(&self, skill_registry: &SkillRegistry) -> Vec<ToolDefinition> {
         skill_registry
             .list()
@@ -276,3 +835,567 @@ impl LlmClient {
             .collect()
     }
 }
+
+/// Tokens of a request as the provider reported them, or estimated as
+/// chars/4 (the bool) when it reported none
+fn request_usage(messages: &[Message], response: &ChatResponse) -> (TokenUsage, bool) {
+    match response.provenance.usage {
+        Some(usage) => (usage, false),
+        None => {
+            let output_chars = response.content.len()
+                + response.tool_calls.iter().map(|c| c.name.len() + c.arguments.to_string().len()).sum::<usize>();
+            let usage = TokenUsage {
+                input_tokens: messages.iter().map(Message::chars).sum::<usize>() / 4,
+                output_tokens: output_chars / 4,
+            };
+            (usage, true)
+        }
+    }
+}
+
+/// `system_prompt` plus the note about tools left out of `selection`
+fn with_tool_note(system_prompt: &str, selection: &ToolSelection) -> String {
+    match selection.system_note() {
+        Some(note) => format!("{}\n\n{}", system_prompt, note),
+        None => system_prompt.to_string(),
+    }
+}
+
+/// `system_prompt` plus the working set policy, if results are ephemeral
+fn with_working_set_note(system_prompt: &str, policy: &WorkingSetPolicy) -> String {
+    match policy.guidance() {
+        Some(note) => format!("{}\n\n{}", system_prompt, note),
+        None => system_prompt.to_string(),
+    }
+}
+
+/// Record a `--confirm-each` decision in the audit log
+fn audit_step(tool: &str, arguments: &serde_json::Value, decision: &StepDecision) {
+    let mut details = serde_json::json!({
+        "tool": tool,
+        "decision": decision.label(),
+        "arguments": arguments,
+    });
+    if let StepDecision::Edit(edited) = decision {
+        details["edited_arguments"] = edited.clone();
+    }
+    AUDIT.log(
+        AuditEvent::new(
+            AuditEventType::UserConfirmation,
+            AuditSeverity::Info,
+            format!("Step-through {}: {}", decision.label(), tool),
+        )
+        .with_details(details),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Fails every request with `error`'s status and body, or answers with
+    /// `name` when None
+    struct MockProvider {
+        name: &'static str,
+        error: Option<(u16, &'static str)>,
+        calls: AtomicUsize,
+        /// Number of messages in the last request
+        received: AtomicUsize,
+    }
+
+    impl MockProvider {
+        fn new(name: &'static str, error: Option<(u16, &'static str)>) -> Arc<Self> {
+            Arc::new(Self {
+                name,
+                error,
+                calls: AtomicUsize::new(0),
+                received: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        async fn chat(
+            &self,
+            messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.received.store(messages.len(), Ordering::SeqCst);
+            match self.error {
+                Some((status, body)) => Err(ApiError {
+                    provider: "Mock",
+                    status: reqwest::StatusCode::from_u16(status).unwrap(),
+                    body: body.to_string(),
+                }
+                .into()),
+                None => Ok(ChatResponse {
+                    content: format!("answer from {}", self.name),
+                    tool_calls: Vec::new(),
+                    stop_reason: None,
+                    refusal: None,
+                    provenance: Default::default(),
+                }),
+            }
+        }
+
+        async fn chat_stream(
+            &self,
+            messages: Vec<Message>,
+            tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            self.chat(messages, tools).await
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+    }
+
+    fn client(primary: Arc<MockProvider>, fallback: Arc<MockProvider>) -> LlmClient {
+        let mut client = LlmClient::from_provider(primary, &Settings::default())
+            .with_fallbacks(vec![(fallback, "mock-model".to_string())]);
+        client.retry_config = RetryConfig {
+            max_retries: 0,
+            ..RetryConfig::quick()
+        };
+        client
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_hard_failure() {
+        let primary = MockProvider::new(
+            "primary",
+            Some((503, "overloaded")),
+        );
+        let secondary = MockProvider::new("secondary", None);
+        let client = client(primary.clone(), secondary.clone());
+
+        let answer = client.chat("system", &[], "hello").await.unwrap();
+        assert_eq!(answer, "answer from secondary");
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(secondary.calls.load(Ordering::SeqCst), 1);
+
+        let provenance = client.last_provenance().unwrap();
+        assert_eq!(provenance.provider, "secondary");
+        assert_eq!(provenance.model, "mock-model");
+        assert!(provenance.fallback_used && !provenance.cached);
+        assert_eq!(provenance.attempt, 2);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_request_fits_the_fallback_window() {
+        let primary = MockProvider::new("primary", Some((503, "overloaded")));
+        let secondary = MockProvider::new("secondary", None);
+        let mut client = client(primary.clone(), secondary.clone());
+        // 1,200 characters of window
+        let small = ContextBudget::new(Default::default(), 300, 0);
+        client.budgets.insert("mock-model".to_string(), small);
+
+        let history: Vec<Message> = (0..4).map(|i| Message::user(format!("{}{}", i, "x".repeat(500)))).collect();
+        client.chat("system", &history, "hello").await.unwrap();
+        // The primary's window holds everything; the fallback's only the latest turns
+        assert_eq!(primary.received.load(Ordering::SeqCst), 6);
+        assert_eq!(secondary.received.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_provenance_of_primary_and_cache_hit() {
+        let primary = MockProvider::new("primary", None);
+        let secondary = MockProvider::new("secondary", None);
+        let client = client(primary.clone(), secondary);
+        assert!(client.last_provenance().is_none());
+
+        client.chat("system", &[], "hello").await.unwrap();
+        let provenance = client.last_provenance().unwrap();
+        assert_eq!(provenance.provider, "primary");
+        assert_eq!(provenance.model, client.model_name());
+        assert!(!provenance.fallback_used && !provenance.cached);
+        assert_eq!(provenance.attempt, 1);
+
+        // Same request again: answered from the cache without calling the provider
+        client.chat("system", &[], "hello").await.unwrap();
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 1);
+        let provenance = client.last_provenance().unwrap();
+        assert!(provenance.cached && !provenance.fallback_used);
+        assert_eq!(provenance.latency_ms, 0);
+
+        // Streams get theirs once complete
+        let response = client.chat_stream("system", &[], "stream").await.unwrap();
+        assert_eq!(response, "answer from primary");
+        assert!(!client.last_provenance().unwrap().cached);
+    }
+
+    #[test]
+    fn test_provenance_suffix() {
+        let mut provenance = ResponseProvenance {
+            provider: "anthropic".to_string(),
+            model: "claude-sonnet-4".to_string(),
+            cached: false,
+            fallback_used: false,
+            attempt: 1,
+            latency_ms: 2_340,
+            ..Default::default()
+        };
+        assert_eq!(provenance.suffix(), "— claude-sonnet-4 via anthropic, 2.3s");
+
+        provenance.provider = "openai".to_string();
+        provenance.model = "gpt-4o".to_string();
+        provenance.fallback_used = true;
+        provenance.attempt = 2;
+        provenance.latency_ms = 940;
+        assert_eq!(provenance.suffix(), "— gpt-4o via openai (fallback), 0.9s");
+
+        provenance.seed = Some(42);
+        provenance.system_fingerprint = Some("fp_44709d6fcb".to_string());
+        assert_eq!(provenance.suffix(), "— gpt-4o via openai (fallback), 0.9s, seed 42 (fp_44709d6fcb)");
+
+        provenance.cached = true;
+        assert_eq!(provenance.suffix(), "— cached");
+    }
+
+    #[tokio::test]
+    async fn test_no_fallback_on_bad_request() {
+        let primary = MockProvider::new(
+            "primary",
+            Some((400, "content policy violation")),
+        );
+        let secondary = MockProvider::new("secondary", None);
+        let client = client(primary, secondary.clone());
+
+        assert!(client.chat("system", &[], "hello").await.is_err());
+        assert_eq!(secondary.calls.load(Ordering::SeqCst), 0);
+    }
+
+    /// Answers each request with the next scripted response
+    struct ScriptedProvider(std::sync::Mutex<std::collections::VecDeque<ChatResponse>>);
+
+    #[async_trait]
+    impl Provider for ScriptedProvider {
+        async fn chat(
+            &self,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            self.0
+                .lock()
+                .unwrap()
+                .pop_front()
+                .ok_or_else(|| anyhow::anyhow!("script exhausted"))
+        }
+
+        async fn chat_stream(
+            &self,
+            messages: Vec<Message>,
+            tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ChatResponse> {
+            self.chat(messages, tools).await
+        }
+
+        fn name(&self) -> &str {
+            "scripted"
+        }
+    }
+
+    fn add_tasks(descriptions: &[&str]) -> ChatResponse {
+        ChatResponse {
+            content: "Recording tasks".to_string(),
+            tool_calls: descriptions
+                .iter()
+                .enumerate()
+                .map(|(i, d)| ToolCall {
+                    id: format!("call_{}_{}", d, i),
+                    name: "task_add".to_string(),
+                    arguments: serde_json::json!({ "description": d }),
+                })
+                .collect(),
+            stop_reason: None,
+            refusal: None,
+            provenance: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_confirm_each_decisions() {
+        let script = vec![add_tasks(&["first", "second"]), add_tasks(&["third"]), add_tasks(&["fourth"])];
+        let provider = Arc::new(ScriptedProvider(std::sync::Mutex::new(script.into())));
+        let client = LlmClient::from_provider(provider, &Settings::default());
+
+        let decisions = std::sync::Mutex::new(std::collections::VecDeque::from(vec![
+            StepDecision::Continue,
+            StepDecision::Skip,
+            StepDecision::Edit(serde_json::json!({ "description": "third, edited" })),
+            StepDecision::Abort,
+        ]));
+        let mut registry = SkillRegistry::new();
+        registry.set_stepper(Some(Arc::new(move |_: &str, _: &serde_json::Value| {
+            decisions.lock().unwrap().pop_front().unwrap()
+        })));
+
+        let mut history = Vec::new();
+        let err = client
+            .chat_with_tools_loop("system", &mut history, "plan the work", &registry)
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<StepAborted>().is_some());
+
+        let tasks = registry.tasks().snapshot();
+        let added: Vec<&str> = tasks.tasks.iter().map(|t| t.description.as_str()).collect();
+        assert_eq!(added, vec!["first", "third, edited"]);
+        assert!(history
+            .iter()
+            .any(|m| m.content.contains("call_second_1") && m.content.contains("Skipped by the user")));
+
+        let audited: Vec<String> = AUDIT
+            .events_by_type(AuditEventType::UserConfirmation)
+            .into_iter()
+            .map(|e| e.message)
+            .collect();
+        for decision in ["continue", "skip", "edit", "abort"] {
+            assert!(audited.contains(&format!("Step-through {}: task_add", decision)));
+        }
+    }
+
+    /// Read-only skill that records how many calls overlap
+    struct ProbeSkill {
+        running: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl crate::skills::Skill for ProbeSkill {
+        fn definition(&self) -> crate::skills::SkillDefinition {
+            crate::skills::SkillDefinition {
+                name: "probe".to_string(),
+                description: "Reads a file slowly".to_string(),
+                parameters: serde_json::json!({"type": "object", "properties": {"path": {"type": "string"}}}),
+                requires_confirmation: false,
+                parallel_safe: true,
+            }
+        }
+
+        async fn execute(&self, args: &serde_json::Value, _settings: &Settings) -> Result<String> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(format!("contents of {}", args["path"].as_str().unwrap_or_default()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_only_tool_calls_run_concurrently() {
+        let reads = ChatResponse {
+            content: "Reading".to_string(),
+            tool_calls: ["a.rs", "b.rs", "c.rs"]
+                .iter()
+                .map(|path| ToolCall {
+                    id: format!("call_{}", path),
+                    name: "probe".to_string(),
+                    arguments: serde_json::json!({ "path": path }),
+                })
+                .collect(),
+            stop_reason: None,
+            refusal: None,
+            provenance: Default::default(),
+        };
+        let done = ChatResponse {
+            content: "Done".to_string(),
+            tool_calls: Vec::new(),
+            stop_reason: None,
+            refusal: None,
+            provenance: Default::default(),
+        };
+        let provider = Arc::new(ScriptedProvider(std::sync::Mutex::new(vec![reads, done].into())));
+        let client = LlmClient::from_provider(provider, &Settings::default());
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let mut registry = SkillRegistry::new();
+        registry.register(Box::new(ProbeSkill {
+            running: running.clone(),
+            peak: peak.clone(),
+        }));
+        assert!(registry.is_parallel_safe("probe"));
+        assert!(!registry.is_parallel_safe("write_file"));
+
+        let mut history = Vec::new();
+        let answer = client
+            .chat_with_tools_loop("system", &mut history, "read three files", &registry)
+            .await
+            .unwrap();
+        assert_eq!(answer, "Done");
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+
+        let results: Vec<&str> = history
+            .iter()
+            .filter(|m| m.content.starts_with("<tool_result"))
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(
+            results,
+            ["a.rs", "b.rs", "c.rs"].map(|path| format!(
+                "<tool_result tool_use_id=\"call_{}\">\ncontents of {}\n</tool_result>",
+                path, path
+            ))
+        );
+        assert_eq!(
+            client.take_tool_calls(),
+            ["a.rs", "b.rs", "c.rs"].map(|path| format!("probe({{\"path\":\"{}\"}})", path))
+        );
+    }
+
+    /// Plots `count` charts, each a tiny PNG
+    struct ChartSkill;
+
+    #[async_trait]
+    impl crate::skills::Skill for ChartSkill {
+        fn definition(&self) -> crate::skills::SkillDefinition {
+            crate::skills::SkillDefinition {
+                name: "plot".to_string(),
+                description: "Renders charts".to_string(),
+                parameters: serde_json::json!({"type": "object", "properties": {"count": {"type": "integer"}}}),
+                requires_confirmation: false,
+                parallel_safe: true,
+            }
+        }
+
+        async fn execute(&self, _args: &serde_json::Value, _settings: &Settings) -> Result<String> {
+            Ok("Plotted".to_string())
+        }
+
+        async fn execute_output(&self, args: &serde_json::Value, settings: &Settings) -> Result<SkillOutput> {
+            let count = args["count"].as_u64().unwrap_or(1) as usize;
+            let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+            Ok(SkillOutput {
+                text: self.execute(args, settings).await?,
+                images: (1..=count)
+                    .map(|n| ImageArtifact::new("image/png", png, Some(&format!("chart {}", n))))
+                    .collect(),
+            })
+        }
+    }
+
+    /// Run one `plot` call for `count` charts with `settings`; returns the
+    /// tool result message
+    async fn plot(settings: &Settings, count: usize, artifacts: &std::path::Path) -> Message {
+        let call = ChatResponse {
+            content: String::new(),
+            tool_calls: vec![ToolCall {
+                id: "call_1".to_string(),
+                name: "plot".to_string(),
+                arguments: serde_json::json!({ "count": count }),
+            }],
+            stop_reason: None,
+            refusal: None,
+            provenance: Default::default(),
+        };
+        let done = ChatResponse {
+            content: "Looks good".to_string(),
+            tool_calls: Vec::new(),
+            stop_reason: None,
+            refusal: None,
+            provenance: Default::default(),
+        };
+        let provider = Arc::new(ScriptedProvider(std::sync::Mutex::new(vec![call, done].into())));
+        let client = LlmClient::from_provider(provider, settings).with_artifacts_dir(artifacts);
+        let mut registry = SkillRegistry::new();
+        registry.register(Box::new(ChartSkill));
+
+        let mut history = Vec::new();
+        client
+            .chat_with_tools_loop("system", &mut history, "plot sales", &registry)
+            .await
+            .unwrap();
+        history.into_iter().find(|m| m.content.starts_with("<tool_result")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tool_result_images() {
+        let tmp = tempfile::TempDir::new().unwrap();
+
+        // The default model (claude-sonnet-4) sees images, up to the per-turn limit
+        let vision = Settings::default();
+        let result = plot(&vision, 1, tmp.path()).await;
+        assert_eq!(result.images.len(), 1);
+        assert_eq!(result.images[0].caption.as_deref(), Some("chart 1"));
+        assert!(result.content.contains("Plotted\n[image: chart 1 — attached]"), "{}", result.content);
+        assert_eq!(result.chars(), result.content.len() + images::IMAGE_CHARS);
+
+        let result = plot(&vision, MAX_IMAGES_PER_TURN + 1, tmp.path()).await;
+        assert_eq!(result.images.len(), MAX_IMAGES_PER_TURN);
+        assert!(result.content.contains("over the limit of 4 images per turn; saved to"));
+
+        // llama3 doesn't: the image is saved and described instead
+        let text_only = Settings {
+            default_model: "ollama".to_string(),
+            ..Settings::default()
+        };
+        let result = plot(&text_only, 1, tmp.path()).await;
+        assert!(result.images.is_empty());
+        let saved = tmp.path().join("plot-call_1-1.png");
+        assert!(
+            result.content.contains(&format!(
+                "[image: chart 1 — the current model can't view images; saved to {}]",
+                saved.display()
+            )),
+            "{}",
+            result.content
+        );
+        assert!(std::fs::read(&saved).unwrap().starts_with(b"\x89PNG"));
+    }
+
+    fn no_network(host: &str, port: u16) -> bool {
+        panic!("offline mode probed {}:{}", host, port)
+    }
+
+    fn unreachable(_: &str, _: u16) -> bool {
+        false
+    }
+
+    #[test]
+    fn test_offline_routes_to_local_model() {
+        let _guard = connectivity::TEST_PROBE_LOCK.lock().unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        connectivity::set_probe(Some(no_network));
+
+        // A local Ollama that must not even be contacted while choosing
+        let ollama = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        ollama.set_nonblocking(true).unwrap();
+        let mut settings = Settings::default();
+        let mut local = settings.models.remove("ollama").unwrap();
+        local.base_url = Some(format!("http://{}", ollama.local_addr().unwrap()));
+        settings.models.insert("local-llama".to_string(), local);
+
+        let ready = runtime.block_on(network_ready(&settings, true)).unwrap();
+        assert_eq!(ready.default_model, "local-llama");
+        assert!(ollama.accept().is_err(), "offline mode connected to the provider");
+
+        settings.models.remove("local-llama");
+        let err = runtime.block_on(network_ready(&settings, true)).unwrap_err().to_string();
+        assert!(err.starts_with("Offline mode: model 'claude' needs the network"), "{}", err);
+        connectivity::set_probe(None);
+    }
+
+    #[test]
+    fn test_unreachable_network_suggests_offline() {
+        let _guard = connectivity::TEST_PROBE_LOCK.lock().unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        connectivity::set_probe(Some(unreachable));
+
+        let settings = Settings::default();
+        let err = runtime.block_on(network_ready(&settings, false)).unwrap_err().to_string();
+        assert!(err.contains("did not answer) — retry with --offline"), "{}", err);
+
+        // Local models never wait on a probe
+        let local = Settings {
+            default_model: "ollama".to_string(),
+            ..Settings::default()
+        };
+        assert!(runtime.block_on(network_ready(&local, false)).is_ok());
+        connectivity::set_probe(None);
+    }
+}
@@ -0,0 +1,194 @@
+// ============================================
+// WEBRANA CLI - System Prompt Comparison
+// One prompt under two system prompts, with identical sampling
+// ============================================
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::client::LlmClient;
+use super::providers::ResponseProvenance;
+use crate::util::diff::{self, DiffStyle};
+
+/// The answer under one system prompt
+#[derive(Debug, Clone, Serialize)]
+pub struct Variant {
+    /// Where the system prompt came from, e.g. its file name
+    pub label: String,
+    pub response: String,
+    pub provenance: Option<ResponseProvenance>,
+}
+
+/// Ask `prompt` once per `(label, system prompt)`, in order, through the
+/// same client so every request carries the same model and sampling
+pub async fn compare_system_prompts(
+    llm: &LlmClient,
+    system_prompts: &[(String, String)],
+    prompt: &str,
+) -> Result<Vec<Variant>> {
+    let mut variants = Vec::with_capacity(system_prompts.len());
+    for (label, system_prompt) in system_prompts {
+        let response = llm.chat(system_prompt, &[], prompt).await?;
+        variants.push(Variant {
+            label: label.clone(),
+            response,
+            provenance: llm.last_provenance(),
+        });
+    }
+    Ok(variants)
+}
+
+/// Two answers in columns `width` characters wide in total, each wrapped
+/// at word boundaries
+pub fn side_by_side(left: &Variant, right: &Variant, width: usize) -> String {
+    let column = width.saturating_sub(3).max(20) / 2;
+    let left_lines = wrap(&left.response, column);
+    let right_lines = wrap(&right.response, column);
+
+    let mut out = format!("{} │ {}\n", pad(&left.label, column), right.label);
+    out.push_str(&format!("{}─┼─{}\n", "─".repeat(column), "─".repeat(column)));
+    for row in 0..left_lines.len().max(right_lines.len()) {
+        let left = left_lines.get(row).map(String::as_str).unwrap_or("");
+        let right = right_lines.get(row).map(String::as_str).unwrap_or("");
+        out.push_str(format!("{} │ {}", pad(left, column), right).trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// Unified diff from the first answer to the second
+pub fn answer_diff(left: &Variant, right: &Variant, style: DiffStyle) -> String {
+    let text = diff::unified(&left.response, &right.response, "answer", style);
+    if text.is_empty() {
+        return "The answers are identical\n".to_string();
+    }
+    format!("--- {}\n+++ {}\n{}", left.label, right.label, strip_headers(&text))
+}
+
+/// `unified` output without its `a/answer`/`b/answer` (or `file:`) header
+fn strip_headers(text: &str) -> String {
+    text.split_inclusive('\n')
+        .filter(|line| {
+            let plain = line.trim_start_matches(|c: char| c == '\u{1b}' || c == '[' || c.is_ascii_digit() || c == 'm');
+            !(plain.starts_with("--- a/") || plain.starts_with("+++ b/") || plain.starts_with("file: "))
+        })
+        .collect()
+}
+
+fn pad(text: &str, width: usize) -> String {
+    let count = text.chars().count();
+    if count >= width {
+        text.chars().take(width).collect()
+    } else {
+        format!("{}{}", text, " ".repeat(width - count))
+    }
+}
+
+/// Lines of at most `width` characters; words longer than that are split
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            while word.len() > width {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                lines.push(word.drain(..width).collect());
+            }
+            let word: String = word.into_iter().collect();
+            let needed = line.chars().count() + usize::from(!line.is_empty()) + word.chars().count();
+            if needed > width && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::llm::providers::OpenAIProvider;
+    use crate::vcs::mock::MockServer;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn variant(label: &str, response: &str) -> Variant {
+        Variant {
+            label: label.to_string(),
+            response: response.to_string(),
+            provenance: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_variants_differ_only_in_system_prompt() {
+        let server = MockServer::start().await;
+        server.route(
+            "POST",
+            "/chat/completions",
+            &[],
+            json!({
+                "choices": [{"message": {"content": "Use a HashMap."}, "finish_reason": "stop"}],
+                "system_fingerprint": "fp_44709d6fcb"
+            }),
+        );
+
+        let mut settings = Settings::default();
+        settings.sampling.seed = Some(7);
+        let provider = OpenAIProvider::new("sk-test".to_string(), "gpt-4o".to_string(), Some(server.url()))
+            .with_sampling(settings.sampling.resolved());
+        let llm = LlmClient::from_provider(Arc::new(provider), &settings);
+
+        let prompts = vec![
+            ("a.md".to_string(), "You are terse.".to_string()),
+            ("b.md".to_string(), "You are thorough.".to_string()),
+        ];
+        let variants = compare_system_prompts(&llm, &prompts, "How do I count words?").await.unwrap();
+        assert_eq!(variants.len(), 2);
+        let provenance = variants[1].provenance.as_ref().unwrap();
+        assert_eq!(provenance.seed, Some(7));
+        assert_eq!(provenance.system_fingerprint.as_deref(), Some("fp_44709d6fcb"));
+
+        // Same seed and prompt means a repeated request, which must not be served from the cache
+        let mut bodies: Vec<serde_json::Value> = server.requests().iter().map(|r| r.json()).collect();
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0]["messages"][0]["content"], "You are terse.");
+        assert_eq!(bodies[1]["messages"][0]["content"], "You are thorough.");
+        assert_eq!((bodies[0]["seed"].clone(), bodies[0]["temperature"].clone(), bodies[0]["top_p"].clone()), (json!(7), json!(0.0), json!(1.0)));
+        for body in &mut bodies {
+            body["messages"][0]["content"] = json!("");
+        }
+        assert_eq!(bodies[0], bodies[1]);
+    }
+
+    #[test]
+    fn test_side_by_side_wraps_columns() {
+        let left = variant("a.md", "short answer");
+        let right = variant("b.md", "a considerably longer answer that needs wrapping");
+        let out = side_by_side(&left, &right, 43);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0].trim_end(), format!("{} │ b.md", pad("a.md", 20)));
+        assert_eq!(lines[2], format!("{} │ a considerably", pad("short answer", 20)));
+        assert!(lines[3].starts_with(&format!("{} │ ", " ".repeat(20))));
+        assert!(out.lines().all(|line| line.chars().count() <= 43));
+    }
+
+    #[test]
+    fn test_answer_diff() {
+        let left = variant("a.md", "one\ntwo\n");
+        let right = variant("b.md", "one\nthree\n");
+        let out = answer_diff(&left, &right, DiffStyle::default());
+        assert!(out.starts_with("--- a.md\n+++ b.md\n@@"), "{}", out);
+        assert!(out.contains("-two\n+three\n"));
+        assert_eq!(answer_diff(&left, &left, DiffStyle::default()), "The answers are identical\n");
+    }
+}
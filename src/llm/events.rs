@@ -0,0 +1,293 @@
+// ============================================
+// WEBRANA CLI - JSON Stream Events
+// `ask --json-stream` / `chat --json-stream`: one JSON object per line on
+// stdout as the turn happens, everything else on stderr
+// ============================================
+
+use serde::Serialize;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use super::providers::{ResponseProvenance, TokenUsage};
+
+/// Longest `summary` of a `tool_result` event, in characters
+pub const SUMMARY_CHARS: usize = 200;
+
+/// One line of the stream
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum StreamEvent<'a> {
+    Start {
+        model: &'a str,
+        provider: &'a str,
+    },
+    /// A model request begins. Deltas since the previous `attempt` of the
+    /// same request came from a provider that failed; `done` leaves them out.
+    Attempt {
+        attempt: u32,
+        provider: &'a str,
+        model: &'a str,
+    },
+    Delta {
+        text: &'a str,
+    },
+    ToolCall {
+        name: &'a str,
+        arguments: &'a serde_json::Value,
+    },
+    ToolResult {
+        name: &'a str,
+        ok: bool,
+        summary: &'a str,
+    },
+    /// `response` is every delta of the turn concatenated
+    Done {
+        response: &'a str,
+        usage: TokenUsage,
+        provenance: Option<&'a ResponseProvenance>,
+    },
+    Error {
+        message: &'a str,
+    },
+}
+
+struct Sink {
+    out: Box<dyn Write + Send>,
+    /// Deltas so far
+    text: String,
+    /// Length of `text` when the current model request began, before any
+    /// failed attempt's deltas
+    request_start: usize,
+    usage: TokenUsage,
+}
+
+static SINK: Mutex<Option<Sink>> = Mutex::new(None);
+
+/// Send events to `out` from now on
+pub fn start(out: Box<dyn Write + Send>) {
+    *SINK.lock().unwrap() = Some(Sink { out, text: String::new(), request_start: 0, usage: TokenUsage::default() });
+}
+
+pub fn is_active() -> bool {
+    SINK.lock().unwrap().is_some()
+}
+
+fn write_line(sink: &mut Sink, event: &StreamEvent) {
+    let written = serde_json::to_writer(&mut sink.out, event)
+        .map_err(io::Error::from)
+        .and_then(|_| sink.out.write_all(b"\n"))
+        .and_then(|_| sink.out.flush());
+    if let Err(e) = written {
+        tracing::warn!("Could not write stream event: {}", e);
+    }
+}
+
+/// Write `event` if streaming events. Deltas go through [`text`].
+pub fn emit(event: &StreamEvent) {
+    if let Some(sink) = SINK.lock().unwrap().as_mut() {
+        write_line(sink, event);
+    }
+}
+
+/// Streamed model output: a `delta` event when streaming events, printed
+/// as it comes otherwise
+pub fn text(chunk: &str) {
+    if chunk.is_empty() {
+        return;
+    }
+    match SINK.lock().unwrap().as_mut() {
+        Some(sink) => {
+            sink.text.push_str(chunk);
+            write_line(sink, &StreamEvent::Delta { text: chunk });
+        }
+        None => {
+            print!("{}", chunk);
+            io::stdout().flush().ok();
+        }
+    }
+}
+
+/// End streamed output with a newline, unless it went out as events
+pub fn end_text() {
+    if !is_active() {
+        println!();
+    }
+}
+
+/// A progress line of the tool loop: stdout normally, stderr while stdout
+/// carries events
+pub fn status(line: &str) {
+    if is_active() {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Mark the start of attempt `attempt` (from 1) at a model request. A
+/// later attempt drops what the failed ones streamed.
+pub(crate) fn begin_request(attempt: u32, provider: &str, model: &str) {
+    let mut guard = SINK.lock().unwrap();
+    let Some(sink) = guard.as_mut() else { return };
+    if attempt <= 1 {
+        sink.request_start = sink.text.len();
+    } else {
+        let start = sink.request_start;
+        sink.text.truncate(start);
+    }
+    write_line(sink, &StreamEvent::Attempt { attempt, provider, model });
+}
+
+/// Stream whatever of `content` the provider did not, e.g. a cached or
+/// non-streaming answer, so the deltas always add up to the answer
+pub(crate) fn end_request(content: &str, usage: TokenUsage) {
+    let mut guard = SINK.lock().unwrap();
+    let Some(sink) = guard.as_mut() else { return };
+    sink.usage.input_tokens += usage.input_tokens;
+    sink.usage.output_tokens += usage.output_tokens;
+    let streamed = &sink.text[sink.request_start..];
+    if let Some(rest) = content.strip_prefix(streamed).filter(|rest| !rest.is_empty()) {
+        let rest = rest.to_string();
+        sink.text.push_str(&rest);
+        write_line(sink, &StreamEvent::Delta { text: &rest });
+    }
+}
+
+/// The `tool_result` summary: the first line of `output`, shortened
+pub fn summary(output: &str) -> String {
+    let line = output.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default();
+    match line.char_indices().nth(SUMMARY_CHARS) {
+        Some((cut, _)) => format!("{}…", &line[..cut]),
+        None => line.to_string(),
+    }
+}
+
+/// End the stream with `done`, or `error` if the turn failed
+pub fn finish(result: &anyhow::Result<()>, provenance: Option<&ResponseProvenance>) {
+    let mut guard = SINK.lock().unwrap();
+    let Some(mut sink) = guard.take() else { return };
+    let text = std::mem::take(&mut sink.text);
+    let event = match result {
+        Ok(()) => StreamEvent::Done { response: &text, usage: sink.usage, provenance },
+        Err(e) => StreamEvent::Error { message: &format!("{:#}", e) },
+    };
+    write_line(&mut sink, &event);
+}
+
+/// Text decoded from a byte stream without splitting characters: a
+/// multi-byte sequence cut across chunks waits for its remaining bytes
+#[derive(Debug, Default)]
+pub struct Utf8Buffer {
+    pending: Vec<u8>,
+}
+
+impl Utf8Buffer {
+    /// Append `chunk` and return all complete text. Invalid bytes become
+    /// U+FFFD.
+    pub fn push(&mut self, chunk: &[u8]) -> String {
+        self.pending.extend_from_slice(chunk);
+        let mut out = String::new();
+        loop {
+            match std::str::from_utf8(&self.pending) {
+                Ok(text) => {
+                    out.push_str(text);
+                    self.pending.clear();
+                    return out;
+                }
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    out.push_str(std::str::from_utf8(&self.pending[..valid]).unwrap_or_default());
+                    match e.error_len() {
+                        // Incomplete sequence at the end: keep it for the next chunk
+                        None => {
+                            self.pending.drain(..valid);
+                            return out;
+                        }
+                        Some(bad) => {
+                            out.push(char::REPLACEMENT_CHARACTER);
+                            self.pending.drain(..valid + bad);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_buffer_joins_split_characters() {
+        let text = "héllo — 世界 🦀";
+        let bytes = text.as_bytes();
+        for split in 1..bytes.len() {
+            let mut buffer = Utf8Buffer::default();
+            let mut out = buffer.push(&bytes[..split]);
+            out.push_str(&buffer.push(&bytes[split..]));
+            assert_eq!(out, text, "split at {}", split);
+        }
+
+        let mut buffer = Utf8Buffer::default();
+        assert_eq!(buffer.push(b"a\xffb"), "a\u{fffd}b");
+        assert_eq!(buffer.push(&[0xe4, 0xb8]), "");
+        assert_eq!(buffer.push(&[0x96]), "世");
+    }
+
+    /// Writer whose bytes the test can read back
+    #[derive(Clone, Default)]
+    struct Shared(std::sync::Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_failed_attempt_is_left_out_of_done() {
+        let out = Shared::default();
+        start(Box::new(out.clone()));
+        begin_request(1, "openai", "gpt-4o");
+        text("Hello ");
+        end_request("Hello ", TokenUsage::default());
+        begin_request(1, "openai", "gpt-4o");
+        text("The ans");
+        begin_request(2, "ollama", "llama3");
+        text("The answer");
+        end_request("The answer is 42", TokenUsage::default());
+        finish(&Ok(()), None);
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(out.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let kinds: Vec<&str> = lines.iter().map(|e| e["event"].as_str().unwrap()).collect();
+        assert_eq!(kinds, ["attempt", "delta", "attempt", "delta", "attempt", "delta", "delta", "done"]);
+        assert_eq!(lines[4]["provider"], "ollama");
+        assert_eq!(lines.last().unwrap()["response"], "Hello The answer is 42");
+    }
+
+    #[test]
+    fn test_event_shapes() {
+        let line = |event: &StreamEvent| serde_json::to_string(event).unwrap();
+        assert_eq!(line(&StreamEvent::Delta { text: "Hi" }), r#"{"event":"delta","text":"Hi"}"#);
+        assert_eq!(
+            line(&StreamEvent::ToolResult { name: "read_file", ok: true, summary: "MIT License" }),
+            r#"{"event":"tool_result","name":"read_file","ok":true,"summary":"MIT License"}"#
+        );
+        assert_eq!(line(&StreamEvent::Error { message: "boom" }), r#"{"event":"error","message":"boom"}"#);
+        assert_eq!(
+            line(&StreamEvent::Attempt { attempt: 2, provider: "ollama", model: "llama3" }),
+            r#"{"event":"attempt","attempt":2,"provider":"ollama","model":"llama3"}"#
+        );
+        assert_eq!(summary("\n  first line \nsecond"), "first line");
+        assert_eq!(summary(&"x".repeat(300)).chars().count(), SUMMARY_CHARS + 1);
+    }
+}
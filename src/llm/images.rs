@@ -0,0 +1,150 @@
+// ============================================
+// WEBRANA CLI - Tool Result Images
+// Screenshots, diagrams and charts a skill returns for the model to look
+// at: attached for vision models, saved to disk for the rest
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Largest decoded image sent to a model
+pub const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Most images attached to the tool results of one model turn
+pub const MAX_IMAGES_PER_TURN: usize = 4;
+
+/// Tokens an image is counted as, whatever its size (a 1024x1024 image is
+/// about 1,500 tokens for Anthropic and OpenAI alike)
+pub const IMAGE_TOKENS: usize = 1_500;
+
+/// Characters an image is counted as in context accounting (4 per token)
+pub const IMAGE_CHARS: usize = IMAGE_TOKENS * 4;
+
+/// Where images are saved for models that can't view them, relative to
+/// the working directory
+pub const ARTIFACTS_DIR: &str = ".webrana/artifacts";
+
+const MEDIA_TYPES: &[(&str, &str)] = &[
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+];
+
+/// An image returned by a skill or plugin
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ImageArtifact {
+    /// `image/png`, `image/jpeg`, `image/gif` or `image/webp`
+    pub media_type: String,
+    pub data_base64: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+}
+
+impl ImageArtifact {
+    pub fn new(media_type: &str, bytes: &[u8], caption: Option<&str>) -> Self {
+        Self {
+            media_type: media_type.to_string(),
+            data_base64: base64::engine::general_purpose::STANDARD.encode(bytes),
+            caption: caption.map(str::to_string),
+        }
+    }
+
+    /// Size once decoded, from the base64 length
+    pub fn decoded_len(&self) -> usize {
+        let padding = self.data_base64.bytes().rev().take_while(|b| *b == b'=').count();
+        (self.data_base64.len() / 4 * 3).saturating_sub(padding)
+    }
+
+    /// A supported media type within `MAX_IMAGE_BYTES`
+    pub fn validate(&self) -> Result<()> {
+        if extension(&self.media_type).is_none() {
+            bail!("unsupported image type '{}'", self.media_type);
+        }
+        if self.decoded_len() > MAX_IMAGE_BYTES {
+            bail!(
+                "image is {} KB, over the {} KB limit",
+                self.decoded_len() / 1024,
+                MAX_IMAGE_BYTES / 1024
+            );
+        }
+        Ok(())
+    }
+
+    pub fn label(&self) -> &str {
+        self.caption.as_deref().unwrap_or("untitled")
+    }
+
+    /// Anthropic Messages image content block
+    pub fn to_anthropic(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "image",
+            "source": {
+                "type": "base64",
+                "media_type": self.media_type,
+                "data": self.data_base64
+            }
+        })
+    }
+
+    /// OpenAI Chat Completions image content part
+    pub fn to_openai(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "image_url",
+            "image_url": {
+                "url": format!("data:{};base64,{}", self.media_type, self.data_base64)
+            }
+        })
+    }
+
+    /// Decode into `dir` as `<stem>.<ext>` and return the path
+    pub fn save(&self, dir: &Path, stem: &str) -> Result<PathBuf> {
+        let ext = extension(&self.media_type).context("unsupported image type")?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(&self.data_base64)
+            .context("image data is not valid base64")?;
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.{}", stem, ext));
+        std::fs::write(&path, bytes)?;
+        Ok(path)
+    }
+}
+
+fn extension(media_type: &str) -> Option<&'static str> {
+    MEDIA_TYPES
+        .iter()
+        .find(|(known, _)| *known == media_type)
+        .map(|(_, ext)| *ext)
+}
+
+/// Text standing in for an image the model doesn't get, saying `why`
+pub fn placeholder(image: &ImageArtifact, saved: &Result<PathBuf>, why: &str) -> String {
+    match saved {
+        Ok(path) => format!("[image: {} — {}; saved to {}]", image.label(), why, path.display()),
+        Err(e) => format!("[image: {} — not shown: {}]", image.label(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_and_save() {
+        let image = ImageArtifact::new("image/png", b"\x89PNG\r\n\x1a\n", Some("chart"));
+        assert_eq!(image.decoded_len(), 8);
+        image.validate().unwrap();
+
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = image.save(tmp.path(), "plot-1").unwrap();
+        assert_eq!(path, tmp.path().join("plot-1.png"));
+        assert_eq!(std::fs::read(&path).unwrap(), b"\x89PNG\r\n\x1a\n");
+
+        let svg = ImageArtifact::new("image/svg+xml", b"<svg/>", None);
+        assert!(svg.validate().is_err());
+        let huge = ImageArtifact::new("image/png", &vec![0; MAX_IMAGE_BYTES + 1], None);
+        assert!(huge.validate().unwrap_err().to_string().contains("over the"));
+    }
+}
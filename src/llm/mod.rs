@@ -1,17 +1,38 @@
 mod cache;
+mod citations;
 mod client;
+pub mod compare;
+pub mod events;
+pub mod images;
+pub mod pricing;
 mod providers;
 mod rag;
+mod replay;
 mod retry;
+mod tool_budget;
+mod usage;
 pub mod webrana;
 
 #[allow(unused_imports)]
 pub use cache::{CacheStats, ResponseCache};
-pub use client::LlmClient;
 #[allow(unused_imports)]
-pub use providers::{ChatResponse, Message, Provider, Role, ToolCall, ToolDefinition};
+pub use citations::{citation_markers, CitedAnswer, Source, CITATION_INSTRUCTIONS};
+pub use client::{LlmClient, REFUSALS_METRIC};
+#[allow(unused_imports)]
+pub use images::ImageArtifact;
+#[allow(unused_imports)]
+pub use providers::{
+    ChatResponse, Message, Provider, RefusalInfo, RefusalKind, Refused, ResponseProvenance, Role, TokenUsage,
+    ToolCall, ToolChoice, ToolDefinition,
+};
 #[allow(unused_imports)]
 pub use rag::{Document, RagConfig, RagContext, RetrievedChunk};
 #[allow(unused_imports)]
+pub use replay::ReplayProvider;
+#[allow(unused_imports)]
 pub use retry::{RetryConfig, with_retry};
+#[allow(unused_imports)]
+pub use tool_budget::{ToolSelection, ToolSelector, LIST_MORE_TOOLS};
+#[allow(unused_imports)]
+pub use usage::{UsageEntry, UsageGroup, UsageLedger, UsageSummary, UsageTotals};
 pub use webrana::WebranaProvider;
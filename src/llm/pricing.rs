@@ -0,0 +1,41 @@
+// ============================================
+// WEBRANA CLI - Model Pricing
+// Rough USD cost estimates for known models
+// ============================================
+
+/// USD per million (input, output) tokens, matched by model-name prefix.
+/// Longer prefixes come first so `gpt-4o-mini` doesn't match `gpt-4o`.
+const PRICES: &[(&str, f64, f64)] = &[
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1", 2.0, 8.0),
+];
+
+/// Estimated cost of a call, or None when the model's price is unknown.
+/// Local (ollama) models are free.
+pub fn estimate_cost(provider: &str, model: &str, input_tokens: usize, output_tokens: usize) -> Option<f64> {
+    if provider == "ollama" {
+        return Some(0.0);
+    }
+    let (_, input, output) = PRICES.iter().find(|(prefix, _, _)| model.starts_with(prefix))?;
+    Some((input_tokens as f64 * input + output_tokens as f64 * output) / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_cost() {
+        let cost = estimate_cost("openai", "gpt-4o-mini-2024-07-18", 1_000_000, 1_000_000).unwrap();
+        assert!((cost - 0.75).abs() < 1e-9);
+        assert_eq!(estimate_cost("ollama", "llama3", 5000, 5000), Some(0.0));
+        assert_eq!(estimate_cost("openai", "some-new-model", 10, 10), None);
+    }
+}
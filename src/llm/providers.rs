@@ -2,12 +2,40 @@ use anyhow::Result;
 use async_trait::async_trait;
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
-use std::io::{self, Write};
+use std::collections::HashSet;
+use std::sync::Arc;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Hash)]
+use super::events::{self, Utf8Buffer};
+use super::images::{ImageArtifact, IMAGE_CHARS};
+use crate::config::SamplingSettings;
+use crate::memory::ToolResultTag;
+use crate::core::rate_limit::{shared_limiter, Priority, SharedRateLimiter};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: Role,
     pub content: String,
+    /// Pinned messages are never trimmed from the context window
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Set on tool results in the ephemeral working set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_result: Option<ToolResultTag>,
+    /// Images from tool results, sent as multimodal content to vision models
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageArtifact>,
+    /// Tool calls an assistant message made
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// Id of the tool call a tool result answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Tool that produced a tool result
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Unix seconds when the message joined the conversation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -32,11 +60,235 @@ pub struct ToolDefinition {
     pub input_schema: serde_json::Value,
 }
 
+impl ToolDefinition {
+    /// Entry of an Anthropic Messages `tools` array
+    pub fn to_anthropic(&self) -> serde_json::Value {
+        serde_json::json!({
+            "name": self.name,
+            "description": self.description,
+            "input_schema": self.input_schema
+        })
+    }
+
+    /// Entry of an OpenAI Chat Completions `tools` array
+    pub fn to_openai(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.input_schema
+            }
+        })
+    }
+}
+
+/// Whether the model may, must not, or must call tools on a request
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ToolChoice {
+    /// The model decides
+    #[default]
+    Auto,
+    /// Text only, even with tools offered
+    None,
+    /// Call this tool
+    Tool(String),
+}
+
+impl std::str::FromStr for ToolChoice {
+    type Err = anyhow::Error;
+
+    /// `auto`, `none`, or the name of a tool
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "" => anyhow::bail!("tool choice is empty; use auto, none or a tool name"),
+            "auto" => Ok(Self::Auto),
+            "none" => Ok(Self::None),
+            name => Ok(Self::Tool(name.to_string())),
+        }
+    }
+}
+
+impl ToolChoice {
+    /// Value of the Anthropic Messages `tool_choice` field
+    pub fn to_anthropic(&self) -> serde_json::Value {
+        match self {
+            Self::Auto => serde_json::json!({"type": "auto"}),
+            Self::None => serde_json::json!({"type": "none"}),
+            Self::Tool(name) => serde_json::json!({"type": "tool", "name": name}),
+        }
+    }
+
+    /// Value of the OpenAI Chat Completions `tool_choice` field
+    pub fn to_openai(&self) -> serde_json::Value {
+        match self {
+            Self::Auto => serde_json::json!("auto"),
+            Self::None => serde_json::json!("none"),
+            Self::Tool(name) => serde_json::json!({"type": "function", "function": {"name": name}}),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ChatResponse {
     pub content: String,
     pub tool_calls: Vec<ToolCall>,
     pub stop_reason: Option<String>,
+    /// Set when the provider declined on content-policy grounds
+    pub refusal: Option<RefusalInfo>,
+    /// Filled in by `LlmClient` once the response (or stream) is complete
+    pub provenance: ResponseProvenance,
+}
+
+/// The provider signal behind a refusal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefusalKind {
+    /// Anthropic `stop_reason: "refusal"`
+    StopReason,
+    /// OpenAI `finish_reason: "content_filter"`
+    ContentFilter,
+    /// OpenAI `refusal` message field
+    RefusalMessage,
+}
+
+impl RefusalKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RefusalKind::StopReason => "refusal stop reason",
+            RefusalKind::ContentFilter => "content filter",
+            RefusalKind::RefusalMessage => "refusal message",
+        }
+    }
+}
+
+/// A content-policy refusal. Asking again gets the same answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefusalInfo {
+    pub kind: RefusalKind,
+    /// The model's explanation, empty when it gave none
+    pub message: String,
+}
+
+impl RefusalInfo {
+    /// Anthropic: `stop_reason` "refusal", explained by any text before it
+    pub fn from_anthropic(stop_reason: Option<&str>, content: &str) -> Option<Self> {
+        (stop_reason == Some("refusal")).then(|| Self {
+            kind: RefusalKind::StopReason,
+            message: content.trim().to_string(),
+        })
+    }
+
+    /// OpenAI: a non-empty `refusal` on the message, else `finish_reason`
+    /// "content_filter"
+    pub fn from_openai(finish_reason: Option<&str>, refusal: Option<&str>, content: &str) -> Option<Self> {
+        match refusal.map(str::trim).filter(|r| !r.is_empty()) {
+            Some(message) => Some(Self { kind: RefusalKind::RefusalMessage, message: message.to_string() }),
+            None => (finish_reason == Some("content_filter")).then(|| Self {
+                kind: RefusalKind::ContentFilter,
+                message: content.trim().to_string(),
+            }),
+        }
+    }
+}
+
+/// Error for a refused request, so callers can tell it from a failure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Refused(pub RefusalInfo);
+
+impl std::fmt::Display for Refused {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The model declined the request on content-policy grounds ({})", self.0.kind.label())?;
+        if !self.0.message.is_empty() {
+            write!(f, ": \"{}\"", self.0.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Refused {}
+
+/// A provider API's non-success response, so callers can decide by status
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    /// `Anthropic`, `OpenAI`, ...
+    pub provider: &'static str,
+    pub status: reqwest::StatusCode,
+    pub body: String,
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} API error ({}): {}", self.provider, self.status, self.body)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Which provider and model actually produced a response
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResponseProvenance {
+    pub provider: String,
+    pub model: String,
+    /// Served from the response cache; no request was made
+    pub cached: bool,
+    /// The primary provider failed and a fallback answered
+    pub fallback_used: bool,
+    /// Position in the fallback chain, 1 for the primary provider
+    pub attempt: u8,
+    pub latency_ms: u64,
+    /// Sampling seed sent with the request (reproducible runs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Backend configuration the provider reports (OpenAI `system_fingerprint`);
+    /// seeded runs are only comparable while it stays the same
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
+    /// Token counts the provider reported, when it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+}
+
+/// Prompt and completion tokens of one request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+}
+
+impl TokenUsage {
+    /// `json[input_key]` and `json[output_key]`, if both are present
+    fn from_json(json: &serde_json::Value, input_key: &str, output_key: &str) -> Option<Self> {
+        Some(Self {
+            input_tokens: json[input_key].as_u64()? as usize,
+            output_tokens: json[output_key].as_u64()? as usize,
+        })
+    }
+}
+
+impl ResponseProvenance {
+    /// One-line suffix for an answer, e.g. "— claude-sonnet-4 via anthropic, 2.3s"
+    pub fn suffix(&self) -> String {
+        if self.cached {
+            return "— cached".to_string();
+        }
+        format!(
+            "— {} via {}{}, {:.1}s{}",
+            self.model,
+            self.provider,
+            if self.fallback_used { " (fallback)" } else { "" },
+            self.latency_ms as f64 / 1000.0,
+            self.seed_note().map(|note| format!(", {}", note)).unwrap_or_default()
+        )
+    }
+
+    /// "seed 42 (fp_44709d6fcb)" for seeded requests
+    pub fn seed_note(&self) -> Option<String> {
+        let seed = self.seed?;
+        Some(match &self.system_fingerprint {
+            Some(fingerprint) => format!("seed {} ({})", seed, fingerprint),
+            None => format!("seed {}", seed),
+        })
+    }
 }
 
 impl Message {
@@ -44,6 +296,13 @@ impl Message {
         Self {
             role: Role::System,
             content: content.into(),
+            pinned: false,
+            tool_result: None,
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            name: None,
+            timestamp: None,
         }
     }
 
@@ -51,6 +310,13 @@ impl Message {
         Self {
             role: Role::User,
             content: content.into(),
+            pinned: false,
+            tool_result: None,
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            name: None,
+            timestamp: None,
         }
     }
 
@@ -58,8 +324,176 @@ impl Message {
         Self {
             role: Role::Assistant,
             content: content.into(),
+            pinned: false,
+            tool_result: None,
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            name: None,
+            timestamp: None,
+        }
+    }
+
+    /// The result of `call`, wrapped for the model and linked to the call
+    pub fn tool_result(call: &ToolCall, output: impl std::fmt::Display) -> Self {
+        let mut message = Self::user(format!(
+            "<tool_result tool_use_id=\"{}\">\n{}\n</tool_result>",
+            call.id, output
+        ));
+        message.tool_call_id = Some(call.id.clone());
+        message.name = Some(call.name.clone());
+        message.stamped()
+    }
+
+    /// Record the tool calls this assistant message made
+    pub fn with_tool_calls(mut self, calls: Vec<ToolCall>) -> Self {
+        self.tool_calls = calls;
+        self
+    }
+
+    /// Stamp the message with the current time
+    pub fn stamped(mut self) -> Self {
+        self.timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs());
+        self
+    }
+
+    /// Mark this message as pinned
+    pub fn pin(mut self) -> Self {
+        self.pinned = true;
+        self
+    }
+
+    pub fn with_images(mut self, images: Vec<ImageArtifact>) -> Self {
+        self.images = images;
+        self
+    }
+
+    /// Size for context budgets: the text, plus `IMAGE_CHARS` per image
+    pub fn chars(&self) -> usize {
+        self.content.len() + self.images.len() * IMAGE_CHARS
+    }
+
+    /// Text of a tool result without its `<tool_result>` wrapper
+    fn tool_output(&self) -> &str {
+        let Some(id) = &self.tool_call_id else {
+            return &self.content;
+        };
+        self.content
+            .strip_prefix(&format!("<tool_result tool_use_id=\"{}\">\n", id))
+            .and_then(|rest| rest.strip_suffix("\n</tool_result>"))
+            .unwrap_or(&self.content)
+    }
+
+    /// Anthropic message: `tool_use` and `tool_result` blocks for the
+    /// `paired` calls, otherwise the text form
+    fn anthropic_message(&self, paired: &HashSet<&str>) -> serde_json::Value {
+        if let Some(id) = self.tool_call_id.as_deref().filter(|id| paired.contains(id)) {
+            let mut content = serde_json::json!(self.tool_output());
+            if !self.images.is_empty() {
+                let mut blocks = vec![serde_json::json!({"type": "text", "text": self.tool_output()})];
+                blocks.extend(self.images.iter().map(ImageArtifact::to_anthropic));
+                content = serde_json::Value::Array(blocks);
+            }
+            return serde_json::json!({
+                "role": "user",
+                "content": [{"type": "tool_result", "tool_use_id": id, "content": content}]
+            });
+        }
+        let role = if self.role == Role::Assistant { "assistant" } else { "user" };
+        let calls: Vec<&ToolCall> = self.tool_calls.iter().filter(|c| paired.contains(c.id.as_str())).collect();
+        if calls.is_empty() {
+            return serde_json::json!({"role": role, "content": self.anthropic_content()});
+        }
+        let mut blocks = Vec::new();
+        if !self.content.trim().is_empty() {
+            blocks.push(serde_json::json!({"type": "text", "text": self.content}));
+        }
+        blocks.extend(calls.iter().map(|call| {
+            serde_json::json!({"type": "tool_use", "id": call.id, "name": call.name, "input": call.arguments})
+        }));
+        serde_json::json!({"role": role, "content": blocks})
+    }
+
+    /// OpenAI message: an assistant message with `tool_calls` or a `tool`
+    /// message for the `paired` calls, otherwise the text form
+    fn openai_message(&self, paired: &HashSet<&str>) -> serde_json::Value {
+        if let Some(id) = self.tool_call_id.as_deref().filter(|id| paired.contains(id)) {
+            return serde_json::json!({"role": "tool", "tool_call_id": id, "content": self.tool_output()});
+        }
+        let role = match self.role {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        };
+        let mut message = serde_json::json!({"role": role, "content": self.openai_content()});
+        let calls: Vec<serde_json::Value> = self
+            .tool_calls
+            .iter()
+            .filter(|c| paired.contains(c.id.as_str()))
+            .map(|call| {
+                serde_json::json!({
+                    "id": call.id,
+                    "type": "function",
+                    "function": {"name": call.name, "arguments": call.arguments.to_string()}
+                })
+            })
+            .collect();
+        if !calls.is_empty() {
+            if self.content.trim().is_empty() {
+                message["content"] = serde_json::Value::Null;
+            }
+            message["tool_calls"] = serde_json::Value::Array(calls);
+        }
+        message
+    }
+
+    /// Anthropic `content`: the text, or text and image blocks
+    fn anthropic_content(&self) -> serde_json::Value {
+        if self.images.is_empty() {
+            return serde_json::json!(self.content);
+        }
+        let mut blocks = vec![serde_json::json!({"type": "text", "text": self.content})];
+        blocks.extend(self.images.iter().map(ImageArtifact::to_anthropic));
+        serde_json::Value::Array(blocks)
+    }
+
+    /// OpenAI `content`: the text, or text and image_url parts
+    fn openai_content(&self) -> serde_json::Value {
+        if self.images.is_empty() {
+            return serde_json::json!(self.content);
+        }
+        let mut parts = vec![serde_json::json!({"type": "text", "text": self.content})];
+        parts.extend(self.images.iter().map(ImageArtifact::to_openai));
+        serde_json::Value::Array(parts)
+    }
+}
+
+/// Ids of the tool calls that go to the API as native tool turns: made by
+/// an assistant message and answered by the tool results right after it.
+/// Other calls and results, e.g. around a compaction summary or an aborted
+/// step, keep the text form, since the APIs reject a call without its result.
+fn paired_calls(messages: &[Message]) -> HashSet<&str> {
+    let mut paired = HashSet::new();
+    for (i, message) in messages.iter().enumerate() {
+        if message.tool_calls.is_empty() {
+            continue;
         }
+        let answered: HashSet<&str> = messages[i + 1..]
+            .iter()
+            .map_while(|m| m.tool_call_id.as_deref())
+            .collect();
+        paired.extend(
+            message
+                .tool_calls
+                .iter()
+                .map(|call| call.id.as_str())
+                .filter(|id| answered.contains(id)),
+        );
     }
+    paired
 }
 
 #[async_trait]
@@ -75,6 +509,24 @@ pub trait Provider: Send + Sync {
         tools: Option<Vec<ToolDefinition>>,
     ) -> Result<ChatResponse>;
     fn name(&self) -> &str;
+
+    /// `chat` or `chat_stream` with tool use steered by `choice`. Providers
+    /// without a tool_choice field get no tools for `None` and ignore a
+    /// forced tool.
+    async fn chat_with_choice(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        choice: &ToolChoice,
+        stream: bool,
+    ) -> Result<ChatResponse> {
+        let tools = if *choice == ToolChoice::None { None } else { tools };
+        if stream {
+            self.chat_stream(messages, tools).await
+        } else {
+            self.chat(messages, tools).await
+        }
+    }
 }
 
 // ============================================================================
@@ -85,46 +537,48 @@ pub struct AnthropicProvider {
     api_key: String,
     model: String,
     max_tokens: u32,
+    sampling: SamplingSettings,
+    limiter: Arc<SharedRateLimiter>,
 }
 
 impl AnthropicProvider {
     pub fn new(api_key: String, model: String, max_tokens: u32) -> Self {
+        let limiter = shared_limiter("anthropic", &api_key);
         Self {
             api_key,
             model,
             max_tokens,
+            sampling: SamplingSettings::default(),
+            limiter,
         }
     }
-}
 
-#[async_trait]
-impl Provider for AnthropicProvider {
-    async fn chat(
+    /// Temperature and top_p for every request; Anthropic has no seed
+    pub fn with_sampling(mut self, sampling: SamplingSettings) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    fn request_body(
         &self,
-        messages: Vec<Message>,
+        messages: &[Message],
         tools: Option<Vec<ToolDefinition>>,
-    ) -> Result<ChatResponse> {
-        let client = reqwest::Client::new();
-
+        choice: &ToolChoice,
+        stream: bool,
+    ) -> serde_json::Value {
+        // Anthropic takes a single system prompt; fold in later ones (e.g. compaction summaries)
         let system_msg = messages
             .iter()
-            .find(|m| m.role == Role::System)
-            .map(|m| m.content.clone())
-            .unwrap_or_default();
+            .filter(|m| m.role == Role::System)
+            .map(|m| m.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
 
+        let paired = paired_calls(messages);
         let chat_messages: Vec<serde_json::Value> = messages
             .iter()
             .filter(|m| m.role != Role::System)
-            .map(|m| {
-                serde_json::json!({
-                    "role": match m.role {
-                        Role::User => "user",
-                        Role::Assistant => "assistant",
-                        Role::System => "user",
-                    },
-                    "content": m.content
-                })
-            })
+            .map(|m| m.anthropic_message(&paired))
             .collect();
 
         let mut body = serde_json::json!({
@@ -133,21 +587,37 @@ impl Provider for AnthropicProvider {
             "system": system_msg,
             "messages": chat_messages
         });
+        if stream {
+            body["stream"] = serde_json::json!(true);
+        }
+
+        // Newer models reject temperature and top_p together; temperature wins
+        if let Some(temperature) = self.sampling.temperature {
+            body["temperature"] = serde_json::json!(temperature);
+        } else if let Some(top_p) = self.sampling.top_p {
+            body["top_p"] = serde_json::json!(top_p);
+        }
 
         if let Some(tool_defs) = tools {
-            let tools_json: Vec<serde_json::Value> = tool_defs
-                .iter()
-                .map(|t| {
-                    serde_json::json!({
-                        "name": t.name,
-                        "description": t.description,
-                        "input_schema": t.input_schema
-                    })
-                })
-                .collect();
+            let tools_json: Vec<serde_json::Value> =
+                tool_defs.iter().map(ToolDefinition::to_anthropic).collect();
             body["tools"] = serde_json::json!(tools_json);
+            if *choice != ToolChoice::Auto {
+                body["tool_choice"] = choice.to_anthropic();
+            }
         }
 
+        body
+    }
+}
+
+impl AnthropicProvider {
+    /// POST a request body and read the whole response
+    async fn send(&self, body: serde_json::Value) -> Result<ChatResponse> {
+        let client = crate::util::http::client()?;
+
+        self.limiter.acquire(Priority::Interactive).await;
+
         let response = client
             .post("https://api.anthropic.com/v1/messages")
             .header("x-api-key", &self.api_key)
@@ -157,8 +627,21 @@ impl Provider for AnthropicProvider {
             .send()
             .await?;
 
+        if !response.status().is_success() {
+            let status = response.status();
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                self.limiter.report_rate_limited();
+            }
+            let error = response.text().await?;
+            return Err(ApiError { provider: "Anthropic", status, body: error }.into());
+        }
+
         let json: serde_json::Value = response.json().await?;
+        Ok(Self::parse_response(&json))
+    }
 
+    /// Text, tool calls and stop reason of a Messages API response
+    fn parse_response(json: &serde_json::Value) -> ChatResponse {
         let mut content = String::new();
         let mut tool_calls = Vec::new();
 
@@ -183,63 +666,25 @@ impl Provider for AnthropicProvider {
         }
 
         let stop_reason = json["stop_reason"].as_str().map(String::from);
+        let refusal = RefusalInfo::from_anthropic(stop_reason.as_deref(), &content);
 
-        Ok(ChatResponse {
+        ChatResponse {
             content,
             tool_calls,
             stop_reason,
-        })
+            refusal,
+            provenance: ResponseProvenance {
+                usage: TokenUsage::from_json(&json["usage"], "input_tokens", "output_tokens"),
+                ..Default::default()
+            },
+        }
     }
 
-    async fn chat_stream(
-        &self,
-        messages: Vec<Message>,
-        tools: Option<Vec<ToolDefinition>>,
-    ) -> Result<ChatResponse> {
-        let client = reqwest::Client::new();
-
-        let system_msg = messages
-            .iter()
-            .find(|m| m.role == Role::System)
-            .map(|m| m.content.clone())
-            .unwrap_or_default();
+    /// POST a streaming request body, printing text as it arrives
+    async fn send_stream(&self, body: serde_json::Value) -> Result<ChatResponse> {
+        let client = crate::util::http::client()?;
 
-        let chat_messages: Vec<serde_json::Value> = messages
-            .iter()
-            .filter(|m| m.role != Role::System)
-            .map(|m| {
-                serde_json::json!({
-                    "role": match m.role {
-                        Role::User => "user",
-                        Role::Assistant => "assistant",
-                        Role::System => "user",
-                    },
-                    "content": m.content
-                })
-            })
-            .collect();
-
-        let mut body = serde_json::json!({
-            "model": self.model,
-            "max_tokens": self.max_tokens,
-            "system": system_msg,
-            "messages": chat_messages,
-            "stream": true
-        });
-
-        if let Some(tool_defs) = tools {
-            let tools_json: Vec<serde_json::Value> = tool_defs
-                .iter()
-                .map(|t| {
-                    serde_json::json!({
-                        "name": t.name,
-                        "description": t.description,
-                        "input_schema": t.input_schema
-                    })
-                })
-                .collect();
-            body["tools"] = serde_json::json!(tools_json);
-        }
+        self.limiter.acquire(Priority::Interactive).await;
 
         let response = client
             .post("https://api.anthropic.com/v1/messages")
@@ -250,16 +695,28 @@ impl Provider for AnthropicProvider {
             .send()
             .await?;
 
+        if !response.status().is_success() {
+            let status = response.status();
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                self.limiter.report_rate_limited();
+            }
+            let error = response.text().await?;
+            return Err(ApiError { provider: "Anthropic", status, body: error }.into());
+        }
+
         let mut stream = response.bytes_stream();
         let mut content = String::new();
         let mut tool_calls: Vec<ToolCall> = Vec::new();
         let mut current_tool: Option<(String, String, String)> = None; // (id, name, args_json)
         let mut stop_reason = None;
+        // Input tokens come with message_start, output tokens with message_delta
+        let mut usage: Option<TokenUsage> = None;
         let mut buffer = String::new();
+        let mut decoder = Utf8Buffer::default();
 
         while let Some(chunk) = stream.next().await {
             let chunk = chunk?;
-            buffer.push_str(&String::from_utf8_lossy(&chunk));
+            buffer.push_str(&decoder.push(&chunk));
 
             // Process complete SSE events
             while let Some(pos) = buffer.find("\n\n") {
@@ -274,6 +731,11 @@ impl Provider for AnthropicProvider {
 
                         if let Ok(json) = serde_json::from_str::<serde_json::Value>(data) {
                             match json["type"].as_str() {
+                                Some("message_start") => {
+                                    if let Some(input) = json["message"]["usage"]["input_tokens"].as_u64() {
+                                        usage.get_or_insert_with(Default::default).input_tokens = input as usize;
+                                    }
+                                }
                                 Some("content_block_start") => {
                                     if json["content_block"]["type"].as_str() == Some("tool_use") {
                                         current_tool = Some((
@@ -297,8 +759,7 @@ impl Provider for AnthropicProvider {
                                             if let Some(text) =
                                                 delta.get("text").and_then(|t| t.as_str())
                                             {
-                                                print!("{}", text);
-                                                io::stdout().flush().ok();
+                                                events::text(text);
                                                 content.push_str(text);
                                             }
                                         } else if delta.get("type").and_then(|t| t.as_str())
@@ -330,6 +791,9 @@ impl Provider for AnthropicProvider {
                                     if let Some(reason) = json["delta"]["stop_reason"].as_str() {
                                         stop_reason = Some(reason.to_string());
                                     }
+                                    if let Some(output) = json["usage"]["output_tokens"].as_u64() {
+                                        usage.get_or_insert_with(Default::default).output_tokens = output as usize;
+                                    }
                                 }
                                 _ => {}
                             }
@@ -339,14 +803,55 @@ impl Provider for AnthropicProvider {
             }
         }
 
-        println!(); // New line after streaming
+        events::end_text();
+        let refusal = RefusalInfo::from_anthropic(stop_reason.as_deref(), &content);
         Ok(ChatResponse {
             content,
             tool_calls,
             stop_reason,
+            refusal,
+            provenance: ResponseProvenance {
+                usage,
+                ..Default::default()
+            },
         })
     }
 
+}
+
+#[async_trait]
+impl Provider for AnthropicProvider {
+    async fn chat(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<ChatResponse> {
+        self.send(self.request_body(&messages, tools, &ToolChoice::Auto, false)).await
+    }
+
+    async fn chat_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<ChatResponse> {
+        self.send_stream(self.request_body(&messages, tools, &ToolChoice::Auto, true)).await
+    }
+
+    async fn chat_with_choice(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        choice: &ToolChoice,
+        stream: bool,
+    ) -> Result<ChatResponse> {
+        let body = self.request_body(&messages, tools, choice, stream);
+        if stream {
+            self.send_stream(body).await
+        } else {
+            self.send(body).await
+        }
+    }
+
     fn name(&self) -> &str {
         "anthropic"
     }
@@ -360,63 +865,113 @@ pub struct OpenAIProvider {
     api_key: String,
     model: String,
     base_url: String,
+    sampling: SamplingSettings,
+    limiter: Arc<SharedRateLimiter>,
 }
 
 impl OpenAIProvider {
     pub fn new(api_key: String, model: String, base_url: Option<String>) -> Self {
+        let base_url = base_url.unwrap_or_else(|| "https://api.openai.com/v1".to_string());
+        // Keyed like OpenAIEmbeddings so chat and indexing share one budget
+        let limiter = shared_limiter(&base_url, &api_key);
         Self {
             api_key,
             model,
-            base_url: base_url.unwrap_or_else(|| "https://api.openai.com/v1".to_string()),
+            base_url,
+            sampling: SamplingSettings::default(),
+            limiter,
         }
     }
-}
 
-#[async_trait]
-impl Provider for OpenAIProvider {
-    async fn chat(
+    /// Seed, temperature and top_p for every request
+    pub fn with_sampling(mut self, sampling: SamplingSettings) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Whether requests go to OpenAI itself rather than a compatible server
+    fn is_openai(&self) -> bool {
+        url::Url::parse(&self.base_url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host == "api.openai.com"))
+            .unwrap_or(false)
+    }
+
+    fn request_body(
         &self,
-        messages: Vec<Message>,
+        messages: &[Message],
         tools: Option<Vec<ToolDefinition>>,
-    ) -> Result<ChatResponse> {
-        let client = reqwest::Client::new();
-
-        let chat_messages: Vec<serde_json::Value> = messages
-            .iter()
-            .map(|m| {
-                serde_json::json!({
-                    "role": match m.role {
-                        Role::System => "system",
-                        Role::User => "user",
-                        Role::Assistant => "assistant",
-                    },
-                    "content": m.content
-                })
-            })
-            .collect();
+        choice: &ToolChoice,
+        stream: bool,
+    ) -> serde_json::Value {
+        // Tool messages take no images; those follow the tool messages of a
+        // step in one user message
+        let paired = paired_calls(messages);
+        let mut chat_messages = Vec::new();
+        let mut images = Vec::new();
+        for m in messages {
+            let paired_result = m.tool_call_id.as_deref().is_some_and(|id| paired.contains(id));
+            if !paired_result && !images.is_empty() {
+                chat_messages.push(tool_images_message(std::mem::take(&mut images)));
+            }
+            chat_messages.push(m.openai_message(&paired));
+            if paired_result {
+                images.extend(m.images.iter().map(ImageArtifact::to_openai));
+            }
+        }
+        if !images.is_empty() {
+            chat_messages.push(tool_images_message(images));
+        }
 
         let mut body = serde_json::json!({
             "model": self.model,
             "messages": chat_messages
         });
+        if stream {
+            body["stream"] = serde_json::json!(true);
+            // Ask for token counts in the final chunk. Many OpenAI-compatible
+            // servers reject unknown fields, so only OpenAI gets it.
+            if self.is_openai() {
+                body["stream_options"] = serde_json::json!({"include_usage": true});
+            }
+        }
+        if let Some(seed) = self.sampling.seed {
+            body["seed"] = serde_json::json!(seed);
+        }
+        if let Some(temperature) = self.sampling.temperature {
+            body["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(top_p) = self.sampling.top_p {
+            body["top_p"] = serde_json::json!(top_p);
+        }
 
         if let Some(tool_defs) = tools {
-            let tools_json: Vec<serde_json::Value> = tool_defs
-                .iter()
-                .map(|t| {
-                    serde_json::json!({
-                        "type": "function",
-                        "function": {
-                            "name": t.name,
-                            "description": t.description,
-                            "parameters": t.input_schema
-                        }
-                    })
-                })
-                .collect();
+            let tools_json: Vec<serde_json::Value> =
+                tool_defs.iter().map(ToolDefinition::to_openai).collect();
             body["tools"] = serde_json::json!(tools_json);
+            if *choice != ToolChoice::Auto {
+                body["tool_choice"] = choice.to_openai();
+            }
         }
 
+        body
+    }
+}
+
+/// User message carrying the images of a step's tool results
+fn tool_images_message(images: Vec<serde_json::Value>) -> serde_json::Value {
+    let mut parts = vec![serde_json::json!({"type": "text", "text": "Images from the tool results above"})];
+    parts.extend(images);
+    serde_json::json!({"role": "user", "content": parts})
+}
+
+impl OpenAIProvider {
+    /// POST a request body and read the whole response
+    async fn send(&self, body: serde_json::Value) -> Result<ChatResponse> {
+        let client = crate::util::http::client()?;
+
+        self.limiter.acquire(Priority::Interactive).await;
+
         let response = client
             .post(format!("{}/chat/completions", self.base_url))
             .header("Authorization", format!("Bearer {}", self.api_key))
@@ -425,8 +980,21 @@ impl Provider for OpenAIProvider {
             .send()
             .await?;
 
+        if !response.status().is_success() {
+            let status = response.status();
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                self.limiter.report_rate_limited();
+            }
+            let error = response.text().await?;
+            return Err(ApiError { provider: "OpenAI", status, body: error }.into());
+        }
+
         let json: serde_json::Value = response.json().await?;
+        Ok(Self::parse_response(&json))
+    }
 
+    /// Text, tool calls and finish reason of a Chat Completions response
+    fn parse_response(json: &serde_json::Value) -> ChatResponse {
         let content = json["choices"][0]["message"]["content"]
             .as_str()
             .unwrap_or("")
@@ -449,57 +1017,30 @@ impl Provider for OpenAIProvider {
         let stop_reason = json["choices"][0]["finish_reason"]
             .as_str()
             .map(String::from);
+        let refusal = RefusalInfo::from_openai(
+            stop_reason.as_deref(),
+            json["choices"][0]["message"]["refusal"].as_str(),
+            &content,
+        );
 
-        Ok(ChatResponse {
+        ChatResponse {
             content,
             tool_calls,
             stop_reason,
-        })
+            refusal,
+            provenance: ResponseProvenance {
+                system_fingerprint: json["system_fingerprint"].as_str().map(String::from),
+                usage: TokenUsage::from_json(&json["usage"], "prompt_tokens", "completion_tokens"),
+                ..Default::default()
+            },
+        }
     }
 
-    async fn chat_stream(
-        &self,
-        messages: Vec<Message>,
-        tools: Option<Vec<ToolDefinition>>,
-    ) -> Result<ChatResponse> {
-        let client = reqwest::Client::new();
-
-        let chat_messages: Vec<serde_json::Value> = messages
-            .iter()
-            .map(|m| {
-                serde_json::json!({
-                    "role": match m.role {
-                        Role::System => "system",
-                        Role::User => "user",
-                        Role::Assistant => "assistant",
-                    },
-                    "content": m.content
-                })
-            })
-            .collect();
-
-        let mut body = serde_json::json!({
-            "model": self.model,
-            "messages": chat_messages,
-            "stream": true
-        });
+    /// POST a streaming request body, printing text as it arrives
+    async fn send_stream(&self, body: serde_json::Value) -> Result<ChatResponse> {
+        let client = crate::util::http::client()?;
 
-        if let Some(tool_defs) = tools {
-            let tools_json: Vec<serde_json::Value> = tool_defs
-                .iter()
-                .map(|t| {
-                    serde_json::json!({
-                        "type": "function",
-                        "function": {
-                            "name": t.name,
-                            "description": t.description,
-                            "parameters": t.input_schema
-                        }
-                    })
-                })
-                .collect();
-            body["tools"] = serde_json::json!(tools_json);
-        }
+        self.limiter.acquire(Priority::Interactive).await;
 
         let response = client
             .post(format!("{}/chat/completions", self.base_url))
@@ -509,17 +1050,30 @@ impl Provider for OpenAIProvider {
             .send()
             .await?;
 
+        if !response.status().is_success() {
+            let status = response.status();
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                self.limiter.report_rate_limited();
+            }
+            let error = response.text().await?;
+            return Err(ApiError { provider: "OpenAI", status, body: error }.into());
+        }
+
         let mut stream = response.bytes_stream();
         let mut content = String::new();
         let mut tool_calls: Vec<ToolCall> = Vec::new();
         let mut tool_call_map: std::collections::HashMap<usize, (String, String, String)> =
             std::collections::HashMap::new();
         let mut stop_reason = None;
+        let mut refusal_text = String::new();
+        let mut system_fingerprint = None;
+        let mut usage = None;
         let mut buffer = String::new();
+        let mut decoder = Utf8Buffer::default();
 
         while let Some(chunk) = stream.next().await {
             let chunk = chunk?;
-            buffer.push_str(&String::from_utf8_lossy(&chunk));
+            buffer.push_str(&decoder.push(&chunk));
 
             while let Some(pos) = buffer.find("\n") {
                 let line = buffer[..pos].to_string();
@@ -531,13 +1085,22 @@ impl Provider for OpenAIProvider {
                     }
 
                     if let Ok(json) = serde_json::from_str::<serde_json::Value>(data) {
+                        if let Some(fingerprint) = json["system_fingerprint"].as_str() {
+                            system_fingerprint = Some(fingerprint.to_string());
+                        }
+                        // Sent in the last chunk, which has no choices
+                        if let Some(reported) = TokenUsage::from_json(&json["usage"], "prompt_tokens", "completion_tokens") {
+                            usage = Some(reported);
+                        }
                         if let Some(delta) = json["choices"][0]["delta"].as_object() {
                             // Text content
                             if let Some(text) = delta.get("content").and_then(|c| c.as_str()) {
-                                print!("{}", text);
-                                io::stdout().flush().ok();
+                                events::text(text);
                                 content.push_str(text);
                             }
+                            if let Some(text) = delta.get("refusal").and_then(|r| r.as_str()) {
+                                refusal_text.push_str(text);
+                            }
 
                             // Tool calls
                             if let Some(calls) = delta.get("tool_calls").and_then(|t| t.as_array())
@@ -583,14 +1146,56 @@ impl Provider for OpenAIProvider {
             });
         }
 
-        println!(); // New line after streaming
+        events::end_text();
+        let refusal = RefusalInfo::from_openai(stop_reason.as_deref(), Some(&refusal_text), &content);
         Ok(ChatResponse {
             content,
             tool_calls,
             stop_reason,
+            refusal,
+            provenance: ResponseProvenance {
+                system_fingerprint,
+                usage,
+                ..Default::default()
+            },
         })
     }
 
+}
+
+#[async_trait]
+impl Provider for OpenAIProvider {
+    async fn chat(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<ChatResponse> {
+        self.send(self.request_body(&messages, tools, &ToolChoice::Auto, false)).await
+    }
+
+    async fn chat_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<ChatResponse> {
+        self.send_stream(self.request_body(&messages, tools, &ToolChoice::Auto, true)).await
+    }
+
+    async fn chat_with_choice(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        choice: &ToolChoice,
+        stream: bool,
+    ) -> Result<ChatResponse> {
+        let body = self.request_body(&messages, tools, choice, stream);
+        if stream {
+            self.send_stream(body).await
+        } else {
+            self.send(body).await
+        }
+    }
+
     fn name(&self) -> &str {
         "openai"
     }
@@ -603,49 +1208,89 @@ impl Provider for OpenAIProvider {
 pub struct OllamaProvider {
     base_url: String,
     model: String,
+    sampling: SamplingSettings,
 }
 
 impl OllamaProvider {
     pub fn new(base_url: String, model: String) -> Self {
-        Self { base_url, model }
+        Self {
+            base_url,
+            model,
+            sampling: SamplingSettings::default(),
+        }
     }
-}
 
-#[async_trait]
-impl Provider for OllamaProvider {
-    async fn chat(
-        &self,
-        messages: Vec<Message>,
-        _tools: Option<Vec<ToolDefinition>>,
-    ) -> Result<ChatResponse> {
-        let client = reqwest::Client::new();
+    /// Seed, temperature and top_p for every request, sent as `options`
+    pub fn with_sampling(mut self, sampling: SamplingSettings) -> Self {
+        self.sampling = sampling;
+        self
+    }
 
+    fn request_body(&self, messages: &[Message], stream: bool) -> serde_json::Value {
         let chat_messages: Vec<serde_json::Value> = messages
             .iter()
             .map(|m| {
-                serde_json::json!({
+                let mut message = serde_json::json!({
                     "role": match m.role {
                         Role::System => "system",
                         Role::User => "user",
                         Role::Assistant => "assistant",
                     },
                     "content": m.content
-                })
+                });
+                // Ollama takes raw base64 images beside the text
+                if !m.images.is_empty() {
+                    message["images"] = m.images.iter().map(|image| image.data_base64.clone()).collect();
+                }
+                message
             })
             .collect();
 
-        let body = serde_json::json!({
+        let mut body = serde_json::json!({
             "model": self.model,
             "messages": chat_messages,
-            "stream": false
+            "stream": stream
         });
 
+        let mut options = serde_json::Map::new();
+        if let Some(seed) = self.sampling.seed {
+            options.insert("seed".to_string(), serde_json::json!(seed));
+        }
+        if let Some(temperature) = self.sampling.temperature {
+            options.insert("temperature".to_string(), serde_json::json!(temperature));
+        }
+        if let Some(top_p) = self.sampling.top_p {
+            options.insert("top_p".to_string(), serde_json::json!(top_p));
+        }
+        if !options.is_empty() {
+            body["options"] = serde_json::Value::Object(options);
+        }
+        body
+    }
+}
+
+#[async_trait]
+impl Provider for OllamaProvider {
+    async fn chat(
+        &self,
+        messages: Vec<Message>,
+        _tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<ChatResponse> {
+        let client = crate::util::http::client()?;
+        let body = self.request_body(&messages, false);
+
         let response = client
             .post(format!("{}/api/chat", self.base_url))
             .json(&body)
             .send()
             .await?;
 
+        if !response.status().is_success() {
+            let status = response.status();
+            let error = response.text().await?;
+            return Err(ApiError { provider: "Ollama", status, body: error }.into());
+        }
+
         let json: serde_json::Value = response.json().await?;
 
         let content = json["message"]["content"]
@@ -657,6 +1302,11 @@ impl Provider for OllamaProvider {
             content,
             tool_calls: Vec::new(),
             stop_reason: Some("stop".to_string()),
+            refusal: None,
+            provenance: ResponseProvenance {
+                usage: TokenUsage::from_json(&json, "prompt_eval_count", "eval_count"),
+                ..Default::default()
+            },
         })
     }
 
@@ -665,27 +1315,8 @@ impl Provider for OllamaProvider {
         messages: Vec<Message>,
         _tools: Option<Vec<ToolDefinition>>,
     ) -> Result<ChatResponse> {
-        let client = reqwest::Client::new();
-
-        let chat_messages: Vec<serde_json::Value> = messages
-            .iter()
-            .map(|m| {
-                serde_json::json!({
-                    "role": match m.role {
-                        Role::System => "system",
-                        Role::User => "user",
-                        Role::Assistant => "assistant",
-                    },
-                    "content": m.content
-                })
-            })
-            .collect();
-
-        let body = serde_json::json!({
-            "model": self.model,
-            "messages": chat_messages,
-            "stream": true
-        });
+        let client = crate::util::http::client()?;
+        let body = self.request_body(&messages, true);
 
         let response = client
             .post(format!("{}/api/chat", self.base_url))
@@ -693,29 +1324,55 @@ impl Provider for OllamaProvider {
             .send()
             .await?;
 
+        if !response.status().is_success() {
+            let status = response.status();
+            let error = response.text().await?;
+            return Err(ApiError { provider: "Ollama", status, body: error }.into());
+        }
+
         let mut stream = response.bytes_stream();
         let mut content = String::new();
+        let mut usage = None;
+        let mut buffer = String::new();
+        let mut decoder = Utf8Buffer::default();
+        let mut finished = false;
+
+        while !finished {
+            match stream.next().await {
+                Some(chunk) => buffer.push_str(&decoder.push(&chunk?)),
+                // The last line may have no newline
+                None => {
+                    buffer.push('\n');
+                    finished = true;
+                }
+            }
 
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            let text = String::from_utf8_lossy(&chunk);
-
-            for line in text.lines() {
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
+            // One JSON object per line; a line cut across chunks waits
+            while let Some(pos) = buffer.find('\n') {
+                let line: String = buffer.drain(..=pos).collect();
+                if let Ok(json) = serde_json::from_str::<serde_json::Value>(line.trim()) {
                     if let Some(msg_content) = json["message"]["content"].as_str() {
-                        print!("{}", msg_content);
-                        io::stdout().flush().ok();
+                        events::text(msg_content);
                         content.push_str(msg_content);
                     }
+                    // The final (`done`) line carries the counts
+                    if let Some(reported) = TokenUsage::from_json(&json, "prompt_eval_count", "eval_count") {
+                        usage = Some(reported);
+                    }
                 }
             }
         }
 
-        println!();
+        events::end_text();
         Ok(ChatResponse {
             content,
             tool_calls: Vec::new(),
             stop_reason: Some("stop".to_string()),
+            refusal: None,
+            provenance: ResponseProvenance {
+                usage,
+                ..Default::default()
+            },
         })
     }
 
@@ -723,3 +1380,228 @@ impl Provider for OllamaProvider {
         "ollama"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn seeded() -> SamplingSettings {
+        SamplingSettings {
+            seed: Some(42),
+            ..Default::default()
+        }
+        .resolved()
+    }
+
+    fn messages() -> Vec<Message> {
+        vec![Message::system("Be brief."), Message::user("Hi")]
+    }
+
+    #[test]
+    fn test_openai_body_sampling() {
+        let provider = OpenAIProvider::new("sk".to_string(), "gpt-4o".to_string(), None);
+        let body = provider.request_body(&messages(), None, &ToolChoice::Auto, false);
+        assert!(body.get("seed").is_none() && body.get("temperature").is_none() && body.get("top_p").is_none());
+
+        let body = provider.with_sampling(seeded()).request_body(&messages(), None, &ToolChoice::Auto, true);
+        assert_eq!((&body["seed"], &body["temperature"], &body["top_p"]), (&json!(42), &json!(0.0), &json!(1.0)));
+        assert_eq!(body["stream"], json!(true));
+        assert_eq!(body["stream_options"]["include_usage"], json!(true));
+
+        let compatible = OpenAIProvider::new("sk".to_string(), "qwen".to_string(), Some("http://localhost:8000/v1".to_string()));
+        let body = compatible.request_body(&messages(), None, &ToolChoice::Auto, true);
+        assert_eq!(body["stream"], json!(true));
+        assert!(body.get("stream_options").is_none());
+    }
+
+    #[test]
+    fn test_openai_body_tool_choice() {
+        let provider = OpenAIProvider::new("sk".to_string(), "gpt-4o".to_string(), None);
+        let tools = || {
+            Some(vec![ToolDefinition {
+                name: "read_file".to_string(),
+                description: "Read a file".to_string(),
+                input_schema: json!({"type": "object"}),
+            }])
+        };
+
+        let body = provider.request_body(&messages(), tools(), &ToolChoice::Auto, false);
+        assert!(body.get("tool_choice").is_none());
+
+        let body = provider.request_body(&messages(), tools(), &"none".parse().unwrap(), false);
+        assert_eq!(body["tool_choice"], json!("none"));
+
+        let forced = "read_file".parse().unwrap();
+        let body = provider.request_body(&messages(), tools(), &forced, false);
+        assert_eq!(body["tool_choice"], json!({"type": "function", "function": {"name": "read_file"}}));
+
+        // No tools, nothing to choose from
+        let body = provider.request_body(&messages(), None, &forced, false);
+        assert!(body.get("tool_choice").is_none());
+
+        assert_eq!(forced.to_anthropic(), json!({"type": "tool", "name": "read_file"}));
+    }
+
+    #[test]
+    fn test_ollama_body_sampling() {
+        let provider = OllamaProvider::new("http://localhost:11434".to_string(), "llama3".to_string());
+        assert!(provider.request_body(&messages(), false).get("options").is_none());
+
+        let sampling = SamplingSettings {
+            temperature: Some(0.3),
+            ..seeded()
+        };
+        let body = provider.with_sampling(sampling).request_body(&messages(), false);
+        assert_eq!(body["options"]["seed"], json!(42));
+        assert!((body["options"]["temperature"].as_f64().unwrap() - 0.3).abs() < 1e-6);
+        assert_eq!(body["options"]["top_p"], json!(1.0));
+        assert!(body.get("seed").is_none());
+    }
+
+    fn with_image() -> Vec<Message> {
+        let png = ImageArtifact::new("image/png", b"\x89PNG\r\n\x1a\n", Some("diagram"));
+        vec![
+            Message::system("Be brief."),
+            Message::user("<tool_result tool_use_id=\"call_1\">\nRendered\n</tool_result>").with_images(vec![png]),
+        ]
+    }
+
+    #[test]
+    fn test_image_content_shapes() {
+        let data = "iVBORw0KGgo=";
+
+        let anthropic = AnthropicProvider::new("key".to_string(), "claude-sonnet-4".to_string(), 1024)
+            .request_body(&with_image(), None, &ToolChoice::Auto, false);
+        let content = &anthropic["messages"][0]["content"];
+        assert_eq!(content[0]["type"], json!("text"));
+        assert!(content[0]["text"].as_str().unwrap().contains("Rendered"));
+        assert_eq!(
+            content[1],
+            json!({"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": data}})
+        );
+
+        let openai = OpenAIProvider::new("sk".to_string(), "gpt-4o".to_string(), None)
+            .request_body(&with_image(), None, &ToolChoice::Auto, false);
+        let content = &openai["messages"][1]["content"];
+        assert_eq!(content[0]["type"], json!("text"));
+        assert_eq!(
+            content[1],
+            json!({"type": "image_url", "image_url": {"url": format!("data:image/png;base64,{}", data)}})
+        );
+        // Messages without images keep plain string content
+        assert_eq!(openai["messages"][0]["content"], json!("Be brief."));
+
+        let ollama = OllamaProvider::new("http://localhost:11434".to_string(), "llava".to_string())
+            .request_body(&with_image(), false);
+        assert_eq!(ollama["messages"][1]["images"], json!([data]));
+        assert!(ollama["messages"][1]["content"].is_string());
+    }
+
+    #[test]
+    fn test_anthropic_body_has_no_seed() {
+        let provider = AnthropicProvider::new("key".to_string(), "claude-sonnet-4".to_string(), 1024);
+        let body = provider.request_body(&messages(), None, &ToolChoice::Auto, false);
+        assert!(body.get("temperature").is_none() && body.get("top_p").is_none());
+
+        let body = provider.with_sampling(seeded()).request_body(&messages(), None, &ToolChoice::Auto, false);
+        assert_eq!(body["temperature"], json!(0.0));
+        assert!(body.get("seed").is_none() && body.get("top_p").is_none());
+        assert_eq!(body["system"], json!("Be brief."));
+    }
+
+    fn tool_turn() -> Vec<Message> {
+        let read = ToolCall { id: "call_7".to_string(), name: "read_file".to_string(), arguments: json!({"path": "a.rs"}) };
+        let list = ToolCall { id: "call_8".to_string(), name: "list_files".to_string(), arguments: json!({}) };
+        let png = ImageArtifact::new("image/png", b"\x89PNG\r\n\x1a\n", None);
+        vec![
+            Message::system("Be brief."),
+            Message::user("Open a.rs"),
+            Message::assistant("").with_tool_calls(vec![read.clone(), list]),
+            Message::tool_result(&read, "fn main() {}").with_images(vec![png]),
+            // A result whose call was compacted away
+            Message::tool_result(&ToolCall { id: "call_1".to_string(), name: "x".to_string(), arguments: json!({}) }, "old"),
+        ]
+    }
+
+    #[test]
+    fn test_tool_turns_are_native_when_paired() {
+        let anthropic = AnthropicProvider::new("key".to_string(), "claude-sonnet-4".to_string(), 1024)
+            .request_body(&tool_turn(), None, &ToolChoice::Auto, false);
+        let messages = &anthropic["messages"];
+        // call_8 got no result, so only call_7 is sent as a tool_use
+        assert_eq!(
+            messages[1]["content"],
+            json!([{"type": "tool_use", "id": "call_7", "name": "read_file", "input": {"path": "a.rs"}}])
+        );
+        let result = &messages[2]["content"][0];
+        assert_eq!((&result["type"], &result["tool_use_id"]), (&json!("tool_result"), &json!("call_7")));
+        assert_eq!(result["content"][0]["text"], json!("fn main() {}"));
+        assert_eq!(result["content"][1]["type"], json!("image"));
+        assert_eq!(messages[3]["content"], json!("<tool_result tool_use_id=\"call_1\">\nold\n</tool_result>"));
+
+        let openai = OpenAIProvider::new("sk".to_string(), "gpt-4o".to_string(), None)
+            .request_body(&tool_turn(), None, &ToolChoice::Auto, false);
+        let messages = &openai["messages"];
+        assert_eq!(messages[2]["content"], json!(null));
+        assert_eq!(
+            messages[2]["tool_calls"],
+            json!([{"id": "call_7", "type": "function", "function": {"name": "read_file", "arguments": "{\"path\":\"a.rs\"}"}}])
+        );
+        assert_eq!(messages[3], json!({"role": "tool", "tool_call_id": "call_7", "content": "fn main() {}"}));
+        // The image follows the tool messages in a user message
+        assert_eq!((&messages[4]["role"], &messages[4]["content"][1]["type"]), (&json!("user"), &json!("image_url")));
+        assert_eq!(messages[5]["role"], json!("user"));
+        assert_eq!(messages.as_array().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_anthropic_refusal_is_detected() {
+        let refused = AnthropicProvider::parse_response(&json!({
+            "content": [{"type": "text", "text": "I can't help with that."}],
+            "stop_reason": "refusal"
+        }));
+        let refusal = refused.refusal.unwrap();
+        assert_eq!(refusal.kind, RefusalKind::StopReason);
+        assert_eq!(refusal.message, "I can't help with that.");
+        assert_eq!(
+            Refused(refusal).to_string(),
+            "The model declined the request on content-policy grounds (refusal stop reason): \"I can't help with that.\""
+        );
+
+        let answered = AnthropicProvider::parse_response(&json!({
+            "content": [{"type": "text", "text": "Done."}],
+            "stop_reason": "end_turn"
+        }));
+        assert!(answered.refusal.is_none());
+    }
+
+    #[test]
+    fn test_openai_refusals_are_detected() {
+        let refused = OpenAIProvider::parse_response(&json!({
+            "choices": [{
+                "message": {"role": "assistant", "content": null, "refusal": "I'm sorry, I can't assist with that."},
+                "finish_reason": "stop"
+            }]
+        }));
+        assert_eq!(
+            refused.refusal,
+            Some(RefusalInfo {
+                kind: RefusalKind::RefusalMessage,
+                message: "I'm sorry, I can't assist with that.".to_string()
+            })
+        );
+
+        let filtered = OpenAIProvider::parse_response(&json!({
+            "choices": [{"message": {"role": "assistant", "content": ""}, "finish_reason": "content_filter"}]
+        }));
+        let refusal = filtered.refusal.unwrap();
+        assert_eq!(refusal.kind, RefusalKind::ContentFilter);
+        assert_eq!(Refused(refusal).to_string(), "The model declined the request on content-policy grounds (content filter)");
+
+        let answered = OpenAIProvider::parse_response(&json!({
+            "choices": [{"message": {"role": "assistant", "content": "Done.", "refusal": null}, "finish_reason": "stop"}]
+        }));
+        assert!(answered.refusal.is_none());
+    }
+}
@@ -7,7 +7,9 @@
 use anyhow::Result;
 use std::path::Path;
 use std::sync::Arc;
+use tracing::Instrument;
 
+use super::citations::CITATION_INSTRUCTIONS;
 use crate::embeddings::{EmbeddingProvider, EmbeddingStore, StoredEmbedding};
 
 /// Configuration for RAG context building
@@ -91,13 +93,19 @@ impl RagContext {
 
     /// Retrieve relevant context for a query
     pub async fn retrieve(&self, query: &str) -> Result<Vec<RetrievedChunk>> {
-        let query_embedding = self.provider.embed(query).await?;
+        let span = tracing::info_span!(
+            "rag.retrieve",
+            top_k = self.config.top_k as u64,
+            results = tracing::field::Empty,
+        );
+        let query_embedding = self.provider.embed(query).instrument(span.clone()).await?;
         
         let results = self.store.search_with_threshold(
             &query_embedding,
             self.config.top_k,
             self.config.min_score,
         );
+        span.record("results", results.len() as u64);
 
         Ok(results
             .into_iter()
@@ -114,65 +122,104 @@ impl RagContext {
 
     /// Build context string from retrieved chunks
     pub fn build_context(&self, chunks: &[RetrievedChunk]) -> String {
+        self.build_numbered_context(chunks, self.config.max_context_chars).0
+    }
+
+    /// Context with each chunk headed by its citation marker, e.g.
+    /// `[1] src/main.rs:10-12`, and how many chunks fit
+    fn build_numbered_context(&self, chunks: &[RetrievedChunk], max_chars: usize) -> (String, usize) {
         let mut context = String::new();
         let mut total_chars = 0;
+        let mut included = 0;
 
         for (i, chunk) in chunks.iter().enumerate() {
             // Build chunk header
-            let mut header = format!("--- Relevant Code #{} ", i + 1);
-            
+            let mut location = String::new();
             if self.config.include_file_paths {
                 if let Some(ref path) = chunk.file_path {
-                    header.push_str(&format!("({})", path));
+                    location.push_str(path);
                 }
             }
-            
             if self.config.include_line_numbers {
                 if let (Some(start), Some(end)) = (chunk.start_line, chunk.end_line) {
-                    header.push_str(&format!(" lines {}-{}", start, end));
+                    if location.is_empty() {
+                        location.push_str(&format!("lines {}-{}", start, end));
+                    } else {
+                        location.push_str(&format!(":{}-{}", start, end));
+                    }
                 }
             }
-            
-            header.push_str(&format!(" [score: {:.2}] ---\n", chunk.score));
+            if !location.is_empty() {
+                location.push(' ');
+            }
+            let header = format!("[{}] {}(score: {:.2})\n", i + 1, location, chunk.score);
 
             // Check if adding this chunk would exceed limit
             let chunk_text = format!("{}{}\n\n", header, chunk.content);
-            if total_chars + chunk_text.len() > self.config.max_context_chars {
+            if total_chars + chunk_text.len() > max_chars {
                 // Add truncated version if we have room
-                let remaining = self.config.max_context_chars.saturating_sub(total_chars);
+                let remaining = max_chars.saturating_sub(total_chars);
                 if remaining > header.len() + 100 {
                     let truncated: String = chunk.content.chars().take(remaining - header.len() - 20).collect();
                     context.push_str(&header);
                     context.push_str(&truncated);
                     context.push_str("\n... [truncated]\n\n");
+                    included += 1;
                 }
                 break;
             }
 
             context.push_str(&chunk_text);
             total_chars += chunk_text.len();
+            included += 1;
         }
 
-        context
+        (context, included)
     }
 
     /// Augment a prompt with relevant context
     pub async fn augment_prompt(&self, query: &str, base_prompt: &str) -> Result<String> {
-        let chunks = self.retrieve(query).await?;
-        
+        Ok(self.augment_with_sources(query, base_prompt).await?.0)
+    }
+
+    /// `augment_prompt`, also returning the injected chunks in citation
+    /// order (`[1]` is the first)
+    pub async fn augment_with_sources(
+        &self,
+        query: &str,
+        base_prompt: &str,
+    ) -> Result<(String, Vec<RetrievedChunk>)> {
+        self.augment_within(query, base_prompt, self.config.max_context_chars).await
+    }
+
+    /// `augment_with_sources` with at most `max_chars` of code, fewer than
+    /// `max_context_chars` when the context budget is tight; 0 adds none
+    pub async fn augment_within(
+        &self,
+        query: &str,
+        base_prompt: &str,
+        max_chars: usize,
+    ) -> Result<(String, Vec<RetrievedChunk>)> {
+        let mut chunks = if max_chars == 0 { Vec::new() } else { self.retrieve(query).await? };
+        let (context, included) = self.build_numbered_context(&chunks, max_chars.min(self.config.max_context_chars));
+        chunks.truncate(included);
         if chunks.is_empty() {
-            return Ok(base_prompt.to_string());
+            return Ok((base_prompt.to_string(), chunks));
         }
-
-        let context = self.build_context(&chunks);
         
         let augmented = format!(
-            "{}\n\n## Relevant Code Context\n\nThe following code snippets may be relevant to the user's query:\n\n{}\n## End of Context\n",
+            "{}\n\n## Relevant Code Context\n\nThe following code snippets may be relevant to the user's query:\n\n{}\n## End of Context\n\n{}\n",
             base_prompt,
-            context
+            context,
+            CITATION_INSTRUCTIONS
         );
 
-        Ok(augmented)
+        Ok((augmented, chunks))
+    }
+
+    /// Most code `augment_with_sources` adds, in characters
+    pub fn max_context_chars(&self) -> usize {
+        self.config.max_context_chars
     }
 
     /// Get store reference for persistence
@@ -290,9 +337,7 @@ mod tests {
         ];
 
         let context = rag.build_context(&chunks);
-        assert!(context.contains("src/main.rs"));
-        assert!(context.contains("lines 10-12"));
-        assert!(context.contains("0.95"));
+        assert!(context.starts_with("[1] src/main.rs:10-12 (score: 0.95)\n"));
         assert!(context.contains("fn hello()"));
     }
 
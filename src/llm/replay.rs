@@ -0,0 +1,150 @@
+// ============================================
+// WEBRANA CLI - Replay Provider
+// Answers from a recorded file instead of a model, for demos and CI
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::events;
+use super::providers::{ChatResponse, Message, Provider, ToolCall, ToolDefinition};
+
+/// One recorded response: plain text, or text with tool calls
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ReplayEntry {
+    Text(String),
+    Response {
+        #[serde(default)]
+        content: String,
+        #[serde(default)]
+        tool_calls: Vec<ReplayCall>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplayCall {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+/// `provider = "replay"`: each request takes the next response from a YAML
+/// (or JSON) list, whatever was asked. Running out of responses is an error.
+pub struct ReplayProvider {
+    source: String,
+    responses: Mutex<VecDeque<ChatResponse>>,
+    served: Mutex<usize>,
+}
+
+impl ReplayProvider {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read replay file {}", path.display()))?;
+        Self::parse(&text, &path.display().to_string())
+    }
+
+    pub fn parse(text: &str, source: &str) -> Result<Self> {
+        let entries: Vec<ReplayEntry> = serde_yaml::from_str(text)
+            .with_context(|| format!("{} is not a list of responses", source))?;
+        let responses = entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, entry)| match entry {
+                ReplayEntry::Text(content) => ChatResponse {
+                    content,
+                    tool_calls: Vec::new(),
+                    stop_reason: Some("end_turn".to_string()),
+                    refusal: None,
+                    provenance: Default::default(),
+                },
+                ReplayEntry::Response { content, tool_calls } => ChatResponse {
+                    content,
+                    stop_reason: Some(if tool_calls.is_empty() { "end_turn" } else { "tool_use" }.to_string()),
+                    refusal: None,
+                    tool_calls: tool_calls
+                        .into_iter()
+                        .enumerate()
+                        .map(|(j, call)| ToolCall {
+                            id: format!("replay-{}-{}", i + 1, j + 1),
+                            name: call.name,
+                            arguments: call.arguments,
+                        })
+                        .collect(),
+                    provenance: Default::default(),
+                },
+            })
+            .collect();
+        Ok(Self {
+            source: source.to_string(),
+            responses: Mutex::new(responses),
+            served: Mutex::new(0),
+        })
+    }
+
+    fn next(&self) -> Result<ChatResponse> {
+        let mut served = self.served.lock().unwrap();
+        *served += 1;
+        match self.responses.lock().unwrap().pop_front() {
+            Some(response) => Ok(response),
+            None => bail!("Replay file {} has no response for request {}", self.source, *served),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for ReplayProvider {
+    async fn chat(
+        &self,
+        _messages: Vec<Message>,
+        _tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<ChatResponse> {
+        self.next()
+    }
+
+    async fn chat_stream(
+        &self,
+        _messages: Vec<Message>,
+        _tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<ChatResponse> {
+        let response = self.next()?;
+        events::text(&response.content);
+        Ok(response)
+    }
+
+    fn name(&self) -> &str {
+        "replay"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_responses_replay_in_order() {
+        let replay = ReplayProvider::parse(
+            "- content: Reading it\n  tool_calls:\n    - name: read_file\n      arguments: {path: README.md}\n- The project is MIT licensed.\n",
+            "replay.yaml",
+        )
+        .unwrap();
+
+        let first = replay.chat(vec![], None).await.unwrap();
+        assert_eq!(first.content, "Reading it");
+        assert_eq!(first.tool_calls[0].name, "read_file");
+        assert_eq!(first.tool_calls[0].arguments["path"], "README.md");
+        assert_eq!(first.tool_calls[0].id, "replay-1-1");
+
+        let second = replay.chat(vec![], None).await.unwrap();
+        assert_eq!(second.content, "The project is MIT licensed.");
+        assert!(second.tool_calls.is_empty());
+
+        let err = replay.chat(vec![], None).await.unwrap_err();
+        assert!(err.to_string().contains("no response for request 3"), "{}", err);
+        assert!(ReplayProvider::parse("answer: 42", "bad.yaml").is_err());
+    }
+}
@@ -5,9 +5,12 @@
 // ============================================
 
 use std::future::Future;
+use std::io::ErrorKind;
 use std::time::Duration;
 use tokio::time::sleep;
 
+use super::providers::ApiError;
+
 /// Retry configuration
 #[derive(Debug, Clone)]
 pub struct RetryConfig {
@@ -140,6 +143,33 @@ pub fn is_retryable_error(error: &anyhow::Error) -> RetryDecision {
     RetryDecision::Retry
 }
 
+/// Whether a failed request (after retries) should move on to a fallback
+/// provider: auth failures, 5xx, timeouts, rate limits and connection errors.
+/// Bad requests and content refusals fail the same way everywhere, so they
+/// don't, and neither does anything that is not a typed API or transport error.
+pub fn should_fall_back(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        if let Some(api) = cause.downcast_ref::<ApiError>() {
+            let status = api.status;
+            return status.is_server_error()
+                || matches!(status.as_u16(), 401 | 403 | 408 | 429);
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            return e.is_timeout()
+                || e.is_connect()
+                || e.is_request()
+                || e.status().is_some_and(|status| status.is_server_error());
+        }
+        if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+            return matches!(
+                e.kind(),
+                ErrorKind::TimedOut | ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted
+            );
+        }
+        cause.is::<tokio::time::error::Elapsed>()
+    })
+}
+
 /// Execute an async operation with retry logic
 pub async fn with_retry<F, Fut, T>(
     config: &RetryConfig,
@@ -213,6 +243,33 @@ mod tests {
         assert!(matches!(is_retryable_error(&auth_err), RetryDecision::NoRetry));
     }
 
+    #[test]
+    fn test_fall_back_by_status_and_error_kind() {
+        let api = |status: u16| -> anyhow::Error {
+            ApiError {
+                provider: "Anthropic",
+                status: reqwest::StatusCode::from_u16(status).unwrap(),
+                body: "{}".to_string(),
+            }
+            .into()
+        };
+        for status in [401, 403, 429, 500, 503, 529] {
+            assert!(should_fall_back(&api(status)), "{}", status);
+        }
+        for status in [400, 404, 413] {
+            assert!(!should_fall_back(&api(status)), "{}", status);
+        }
+        // Found anywhere in the chain
+        assert!(should_fall_back(&api(503).context("Chat failed")));
+
+        let reset: anyhow::Error = std::io::Error::from(ErrorKind::ConnectionReset).into();
+        assert!(should_fall_back(&reset));
+        let missing: anyhow::Error = std::io::Error::from(ErrorKind::NotFound).into();
+        assert!(!should_fall_back(&missing));
+        // Text that merely mentions a status is not a failed request
+        assert!(!should_fall_back(&anyhow::anyhow!("tool output: 503 lines")));
+    }
+
     #[tokio::test]
     async fn test_retry_success_first_attempt() {
         let config = RetryConfig::default();
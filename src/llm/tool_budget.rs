@@ -0,0 +1,332 @@
+// ============================================
+// WEBRANA CLI - Tool List Budgeting
+// Keeps the tools array within what the active model handles well
+// ============================================
+
+use std::collections::{BTreeSet, HashMap};
+
+use super::providers::ToolDefinition;
+use crate::config::ToolBudget;
+
+/// Meta-tool offered when some tools were left out of the request
+pub const LIST_MORE_TOOLS: &str = "list_more_tools";
+
+/// Longer descriptions are cut; schemas are always sent whole
+pub const MAX_DESCRIPTION_CHARS: usize = 1024;
+
+/// Always sent, whatever the budget
+const CORE_TOOLS: &[&str] = &[
+    "read_file",
+    "write_file",
+    "edit_file",
+    "apply_patch",
+    "list_files",
+    "search_files",
+    "shell_exec",
+];
+
+/// Tools one `list_more_tools` call loads
+const MAX_LOADED: usize = 5;
+
+/// Requested tools remembered, most recent first
+const MAX_REQUESTED: usize = 2 * MAX_LOADED;
+
+/// Tools chosen for one request
+#[derive(Debug, Clone, Default)]
+pub struct ToolSelection {
+    /// Sorted by name, with [`LIST_MORE_TOOLS`] last when anything was dropped
+    pub tools: Vec<ToolDefinition>,
+    /// Names of the tools left out, sorted
+    pub dropped: Vec<String>,
+}
+
+impl ToolSelection {
+    /// Appended to the system prompt when tools were left out
+    pub fn system_note(&self) -> Option<String> {
+        if self.dropped.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "{} more tools are available but not loaded. Call {} with a short description of what you need to load matching ones.",
+            self.dropped.len(),
+            LIST_MORE_TOOLS
+        ))
+    }
+}
+
+/// Per-session state behind the selection: which tools were used and which
+/// ones the model asked for
+#[derive(Debug, Default)]
+pub struct ToolSelector {
+    uses: HashMap<String, usize>,
+    requested: Vec<String>,
+}
+
+impl ToolSelector {
+    pub fn record_use(&mut self, name: &str) {
+        *self.uses.entry(name.to_string()).or_default() += 1;
+    }
+
+    /// Fit `tools` into `budget`. Core tools always go in; the rest are
+    /// ranked by (requested via the meta-tool, uses this session, lexical
+    /// similarity to `message`, name) and added while they fit.
+    pub fn select(&self, tools: Vec<ToolDefinition>, message: &str, budget: ToolBudget) -> ToolSelection {
+        let mut tools: Vec<ToolDefinition> = tools.into_iter().map(truncate_description).collect();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let total: usize = tools.iter().map(tool_bytes).sum();
+        if tools.len() <= budget.max_tools && total <= budget.max_bytes {
+            return ToolSelection {
+                tools,
+                dropped: Vec::new(),
+            };
+        }
+
+        let meta = list_more_tools_definition();
+        let mut count = 1;
+        let mut bytes = tool_bytes(&meta);
+        let (core, rest): (Vec<_>, Vec<_>) = tools
+            .into_iter()
+            .partition(|tool| CORE_TOOLS.contains(&tool.name.as_str()));
+        for tool in &core {
+            count += 1;
+            bytes += tool_bytes(tool);
+        }
+
+        let query = tokens(message);
+        let mut ranked: Vec<(ToolDefinition, Rank)> = rest
+            .into_iter()
+            .map(|tool| {
+                let rank = Rank {
+                    requested: self.requested.iter().position(|name| *name == tool.name),
+                    uses: self.uses.get(&tool.name).copied().unwrap_or(0),
+                    similarity: similarity(&query, &tool),
+                };
+                (tool, rank)
+            })
+            .collect();
+        ranked.sort_by(|(a, ra), (b, rb)| ra.cmp(rb).then_with(|| a.name.cmp(&b.name)));
+
+        let mut selected = core;
+        let mut dropped = Vec::new();
+        for (tool, _) in ranked {
+            let size = tool_bytes(&tool);
+            if count < budget.max_tools && bytes + size <= budget.max_bytes {
+                count += 1;
+                bytes += size;
+                selected.push(tool);
+            } else {
+                dropped.push(tool.name);
+            }
+        }
+
+        selected.sort_by(|a, b| a.name.cmp(&b.name));
+        dropped.sort();
+        if !dropped.is_empty() {
+            selected.push(meta);
+        }
+        ToolSelection {
+            tools: selected,
+            dropped,
+        }
+    }
+
+    /// Handle a `list_more_tools` call: the dropped tools best matching
+    /// `query` are ranked first from the next request on
+    pub fn list_more(&mut self, query: &str, tools: &[ToolDefinition], selection: &ToolSelection) -> String {
+        if selection.dropped.is_empty() {
+            return "All tools are already loaded.".to_string();
+        }
+
+        let query = tokens(query);
+        let mut matches: Vec<(&ToolDefinition, f64)> = tools
+            .iter()
+            .filter(|tool| selection.dropped.contains(&tool.name))
+            .map(|tool| (tool, similarity(&query, tool)))
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+        matches.sort_by(|(a, sa), (b, sb)| sb.total_cmp(sa).then_with(|| a.name.cmp(&b.name)));
+        matches.truncate(MAX_LOADED);
+
+        if matches.is_empty() {
+            return format!(
+                "No unloaded tool matches that. Not loaded: {}",
+                selection.dropped.join(", ")
+            );
+        }
+
+        for (tool, _) in matches.iter().rev() {
+            self.requested.retain(|name| *name != tool.name);
+            self.requested.insert(0, tool.name.clone());
+        }
+        self.requested.truncate(MAX_REQUESTED);
+
+        let mut out = String::from("Loaded for your next turn:\n");
+        for (tool, _) in &matches {
+            let summary = tool.description.lines().next().unwrap_or_default();
+            out.push_str(&format!("- {}: {}\n", tool.name, summary));
+        }
+        out
+    }
+}
+
+/// Lower sorts first
+#[derive(Debug, PartialEq)]
+struct Rank {
+    requested: Option<usize>,
+    uses: usize,
+    similarity: f64,
+}
+
+impl Rank {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let requested = |r: &Rank| r.requested.unwrap_or(usize::MAX);
+        requested(self)
+            .cmp(&requested(other))
+            .then_with(|| other.uses.cmp(&self.uses))
+            .then_with(|| other.similarity.total_cmp(&self.similarity))
+    }
+}
+
+pub fn list_more_tools_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: LIST_MORE_TOOLS.to_string(),
+        description: "Some tools are not loaded to keep this list short. Describe what you need (e.g. 'docker', 'commit history') and matching tools are loaded for your next turn.".to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "What the tool should do"
+                }
+            },
+            "required": ["query"]
+        }),
+    }
+}
+
+/// Size of the tool as sent to the provider
+pub fn tool_bytes(tool: &ToolDefinition) -> usize {
+    serde_json::to_string(tool).map(|s| s.len()).unwrap_or(0)
+}
+
+fn truncate_description(mut tool: ToolDefinition) -> ToolDefinition {
+    if tool.description.chars().count() > MAX_DESCRIPTION_CHARS {
+        let mut cut: String = tool.description.chars().take(MAX_DESCRIPTION_CHARS - 3).collect();
+        cut.push_str("...");
+        tool.description = cut;
+    }
+    tool
+}
+
+/// Lowercase words of three or more characters; `snake_case` names split
+fn tokens(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| word.len() >= 3)
+        .map(|word| word.to_ascii_lowercase())
+        .collect()
+}
+
+/// Cosine similarity of the word sets of `query` and the tool's name and
+/// description
+fn similarity(query: &BTreeSet<String>, tool: &ToolDefinition) -> f64 {
+    let words = tokens(&format!("{} {}", tool.name, tool.description));
+    if query.is_empty() || words.is_empty() {
+        return 0.0;
+    }
+    let shared = query.intersection(&words).count();
+    shared as f64 / ((query.len() * words.len()) as f64).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str, description: &str) -> ToolDefinition {
+        ToolDefinition {
+            name: name.to_string(),
+            description: description.to_string(),
+            input_schema: serde_json::json!({"type": "object", "properties": {}}),
+        }
+    }
+
+    fn tools() -> Vec<ToolDefinition> {
+        vec![
+            tool("git_log", "Show the commit history"),
+            tool("read_file", "Read a file"),
+            tool("docker_ps", "List running docker containers"),
+            tool("edit_file", "Search and replace in a file"),
+            tool("web_fetch", "Fetch a web page over HTTP"),
+            tool("task_add", "Add a task to the task list"),
+        ]
+    }
+
+    fn names(selection: &ToolSelection) -> Vec<&str> {
+        selection.tools.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_under_budget_sends_everything_sorted() {
+        let budget = ToolBudget {
+            max_tools: 10,
+            max_bytes: 100_000,
+        };
+        let selection = ToolSelector::default().select(tools(), "anything", budget);
+        assert_eq!(
+            names(&selection),
+            vec!["docker_ps", "edit_file", "git_log", "read_file", "task_add", "web_fetch"]
+        );
+        assert!(selection.system_note().is_none());
+    }
+
+    #[test]
+    fn test_ranking_by_request_use_and_similarity() {
+        let budget = ToolBudget {
+            max_tools: 5,
+            max_bytes: 100_000,
+        };
+        let mut selector = ToolSelector::default();
+
+        // Two core tools + the meta-tool leave two slots
+        let selection = selector.select(tools(), "list the running docker containers", budget);
+        assert_eq!(
+            names(&selection),
+            vec!["docker_ps", "edit_file", "read_file", "task_add", LIST_MORE_TOOLS]
+        );
+        assert_eq!(selection.dropped, vec!["git_log", "web_fetch"]);
+        assert!(selection.system_note().unwrap().starts_with("2 more tools"));
+
+        // Usage beats similarity
+        selector.record_use("git_log");
+        let selection = selector.select(tools(), "list the running docker containers", budget);
+        assert_eq!(selection.dropped, vec!["task_add", "web_fetch"]);
+
+        // A meta-tool request beats both
+        let result = selector.list_more("fetch a web page", &tools(), &selection);
+        assert!(result.contains("- web_fetch: Fetch a web page over HTTP"), "{}", result);
+        let selection = selector.select(tools(), "list the running docker containers", budget);
+        assert_eq!(selection.dropped, vec!["docker_ps", "task_add"]);
+    }
+
+    #[test]
+    fn test_byte_budget_and_truncation() {
+        let mut long = tools();
+        long.push(tool("huge", &"word ".repeat(1000)));
+        let truncated = truncate_description(long[6].clone());
+        assert_eq!(truncated.description.chars().count(), MAX_DESCRIPTION_CHARS);
+        assert_eq!(truncated.input_schema, long[6].input_schema);
+
+        let core: usize = ["read_file", "edit_file"]
+            .iter()
+            .map(|name| tool_bytes(tools().iter().find(|t| t.name == *name).unwrap()))
+            .sum();
+        let budget = ToolBudget {
+            max_tools: 100,
+            max_bytes: core + tool_bytes(&list_more_tools_definition()) + tool_bytes(&tools()[0]),
+        };
+        let selection = ToolSelector::default().select(long, "commit history", budget);
+        assert_eq!(names(&selection), vec!["edit_file", "git_log", "read_file", LIST_MORE_TOOLS]);
+        let sent: usize = selection.tools.iter().map(tool_bytes).sum();
+        assert!(sent <= budget.max_bytes);
+    }
+}
@@ -0,0 +1,355 @@
+// ============================================
+// WEBRANA CLI - Usage Ledger
+// Tokens and estimated cost of every LLM request, one file per day
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use super::pricing;
+use super::providers::TokenUsage;
+use crate::memory::utc_date;
+
+/// One request in the ledger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageEntry {
+    /// Unix seconds
+    pub time: u64,
+    pub provider: String,
+    pub model: String,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    /// USD, None when the model's price is unknown
+    pub cost: Option<f64>,
+    /// Token counts are a chars/4 guess; the provider reported none
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub estimated: bool,
+}
+
+impl UsageEntry {
+    pub fn new(time: u64, provider: &str, model: &str, usage: TokenUsage, estimated: bool) -> Self {
+        Self {
+            time,
+            provider: provider.to_string(),
+            model: model.to_string(),
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cost: pricing::estimate_cost(provider, model, usage.input_tokens, usage.output_tokens),
+            estimated,
+        }
+    }
+
+    /// `YYYY-MM-DD` (UTC)
+    pub fn day(&self) -> String {
+        utc_date(self.time)[..10].to_string()
+    }
+}
+
+/// What `webrana usage --by` groups on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageGroup {
+    Provider,
+    Model,
+    Day,
+}
+
+impl UsageGroup {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "provider" => Ok(Self::Provider),
+            "model" => Ok(Self::Model),
+            "day" => Ok(Self::Day),
+            _ => bail!("Unknown grouping '{}'; use provider, model or day", name),
+        }
+    }
+
+    fn key(&self, entry: &UsageEntry) -> String {
+        match self {
+            Self::Provider => entry.provider.clone(),
+            Self::Model => format!("{} ({})", entry.model, entry.provider),
+            Self::Day => entry.day(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageTotals {
+    pub requests: usize,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    /// Sum over the requests with a known price
+    pub cost: f64,
+    /// Requests whose model has no known price
+    pub unpriced: usize,
+}
+
+impl UsageTotals {
+    fn add(&mut self, entry: &UsageEntry) {
+        self.requests += 1;
+        self.input_tokens += entry.input_tokens;
+        self.output_tokens += entry.output_tokens;
+        match entry.cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageSummary {
+    /// Sorted by key
+    pub groups: BTreeMap<String, UsageTotals>,
+    pub total: UsageTotals,
+    /// Ledger lines that could not be read
+    #[serde(skip_serializing_if = "is_zero")]
+    pub skipped: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+impl UsageSummary {
+    pub fn render(&self, group: UsageGroup) -> String {
+        if self.total.requests == 0 {
+            return "No usage recorded\n".to_string();
+        }
+        let heading = match group {
+            UsageGroup::Provider => "Provider",
+            UsageGroup::Model => "Model",
+            UsageGroup::Day => "Day",
+        };
+        let width = self.groups.keys().map(|k| k.chars().count()).max().unwrap_or(0).max(heading.len()).max(5);
+        let row = |key: &str, totals: &UsageTotals| {
+            format!(
+                "{:<width$}  {:>8}  {:>12}  {:>12}  {:>10}{}\n",
+                key,
+                totals.requests,
+                totals.input_tokens,
+                totals.output_tokens,
+                format!("${:.4}", totals.cost),
+                if totals.unpriced > 0 { format!("  ({} unpriced)", totals.unpriced) } else { String::new() },
+                width = width
+            )
+        };
+        let mut out = format!(
+            "{:<width$}  {:>8}  {:>12}  {:>12}  {:>10}\n",
+            heading,
+            "Requests",
+            "Input",
+            "Output",
+            "Cost",
+            width = width
+        );
+        for (key, totals) in &self.groups {
+            out.push_str(&row(key, totals));
+        }
+        out.push_str(&row("Total", &self.total));
+        if self.skipped > 0 {
+            out.push_str(&format!("{} unreadable ledger line(s) skipped\n", self.skipped));
+        }
+        out
+    }
+}
+
+/// Append-only JSON Lines files named `YYYY-MM-DD.jsonl`
+#[derive(Debug, Clone)]
+pub struct UsageLedger {
+    dir: PathBuf,
+}
+
+impl UsageLedger {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `<data_dir>/usage`
+    pub fn open_default() -> Option<Self> {
+        directories::ProjectDirs::from("dev", "webrana", "webrana-cli")
+            .map(|dirs| Self::new(dirs.data_dir().join("usage")))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Append `entry` to the file of its day, as one write so concurrent
+    /// sessions don't interleave lines
+    pub fn record(&self, entry: &UsageEntry) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let path = self.dir.join(format!("{}.jsonl", entry.day()));
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Totals of the entries on or after `since` (`YYYY-MM-DD`), reading
+    /// one line at a time and skipping the files of earlier days unopened
+    pub fn summarize(&self, since: Option<&str>, group: UsageGroup) -> Result<UsageSummary> {
+        if let Some(since) = since {
+            if !is_day(since) {
+                bail!("Invalid date '{}'; expected YYYY-MM-DD", since);
+            }
+        }
+        self.summarize_where(since, group, |_| true)
+    }
+
+    /// Totals of the entries at or after `since` (unix seconds)
+    pub fn summarize_after(&self, since: u64, group: UsageGroup) -> Result<UsageSummary> {
+        self.summarize_where(Some(&utc_date(since)[..10]), group, |entry| entry.time >= since)
+    }
+
+    fn summarize_where(
+        &self,
+        since: Option<&str>,
+        group: UsageGroup,
+        keep: impl Fn(&UsageEntry) -> bool,
+    ) -> Result<UsageSummary> {
+        let mut summary = UsageSummary::default();
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(summary),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", self.dir.display())),
+        };
+        let mut days: Vec<(String, PathBuf)> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter_map(|path| {
+                let day = path.file_name()?.to_str()?.strip_suffix(".jsonl")?.to_string();
+                is_day(&day).then_some((day, path))
+            })
+            .filter(|(day, _)| since.is_none_or(|since| day.as_str() >= since))
+            .collect();
+        days.sort();
+
+        for (_, path) in days {
+            let file = fs::File::open(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            for line in BufReader::new(file).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<UsageEntry>(&line) {
+                    Ok(entry) if keep(&entry) => {
+                        summary.groups.entry(group.key(&entry)).or_default().add(&entry);
+                        summary.total.add(&entry);
+                    }
+                    Ok(_) => {}
+                    Err(_) => summary.skipped += 1,
+                }
+            }
+        }
+        Ok(summary)
+    }
+}
+
+/// `YYYY-MM-DD`
+fn is_day(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::llm::providers::OpenAIProvider;
+    use crate::llm::LlmClient;
+    use crate::vcs::mock::MockServer;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn entry(time: u64, provider: &str, model: &str, input: usize, output: usize) -> UsageEntry {
+        let usage = TokenUsage {
+            input_tokens: input,
+            output_tokens: output,
+        };
+        UsageEntry::new(time, provider, model, usage, false)
+    }
+
+    #[tokio::test]
+    async fn test_llm_calls_are_recorded_and_totalled() {
+        let server = MockServer::start().await;
+        server.route(
+            "POST",
+            "/chat/completions",
+            &[],
+            json!({
+                "choices": [{"message": {"content": "Done."}, "finish_reason": "stop"}],
+                "usage": {"prompt_tokens": 1200, "completion_tokens": 300, "total_tokens": 1500}
+            }),
+        );
+        let tmp = tempfile::TempDir::new().unwrap();
+        let ledger = UsageLedger::new(tmp.path());
+
+        let provider = OpenAIProvider::new("sk-test".to_string(), "gpt-4o-mini".to_string(), Some(server.url()));
+        let mut settings = Settings::default();
+        settings.models.get_mut("gpt").unwrap().model = "gpt-4o-mini".to_string();
+        settings.default_model = "gpt".to_string();
+        let llm = LlmClient::from_provider(Arc::new(provider), &settings).with_usage_ledger(ledger.clone());
+        llm.chat("system", &[], "first").await.unwrap();
+        llm.chat("system", &[], "second").await.unwrap();
+        // Served from the cache: no request, nothing recorded
+        llm.chat("system", &[], "first").await.unwrap();
+
+        let files: Vec<_> = fs::read_dir(tmp.path()).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        let text = fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+        let entries: Vec<UsageEntry> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].provider.as_str(), entries[0].model.as_str()), ("openai", "gpt-4o-mini"));
+        assert_eq!((entries[0].input_tokens, entries[0].output_tokens, entries[0].estimated), (1200, 300, false));
+
+        let summary = ledger.summarize(None, UsageGroup::Model).unwrap();
+        let totals = &summary.groups["gpt-4o-mini (openai)"];
+        assert_eq!((totals.requests, totals.input_tokens, totals.output_tokens), (2, 2400, 600));
+        // 2400 * 0.15 + 600 * 0.6 per million
+        assert!((summary.total.cost - 0.00072).abs() < 1e-12, "{}", summary.total.cost);
+        assert_eq!(summary.total.unpriced, 0);
+    }
+
+    #[test]
+    fn test_summary_groups_and_since() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let ledger = UsageLedger::new(tmp.path());
+        let day = 86_400;
+        let start = 1_792_152_000; // 2026-10-16 12:00 UTC
+        ledger.record(&entry(start - day, "anthropic", "claude-sonnet-4-20250514", 1000, 100)).unwrap();
+        ledger.record(&entry(start, "anthropic", "claude-sonnet-4-20250514", 2000, 200)).unwrap();
+        ledger.record(&entry(start + 60, "openai", "some-new-model", 10, 5)).unwrap();
+        fs::write(tmp.path().join("notes.txt"), "ignored").unwrap();
+        let mut file = OpenOptions::new().append(true).open(tmp.path().join("2026-10-16.jsonl")).unwrap();
+        file.write_all(b"{truncated\n").unwrap();
+
+        let by_day = ledger.summarize(None, UsageGroup::Day).unwrap();
+        assert_eq!(by_day.groups.keys().collect::<Vec<_>>(), vec!["2026-10-15", "2026-10-16"]);
+        assert_eq!(by_day.groups["2026-10-16"].requests, 2);
+        assert_eq!((by_day.total.requests, by_day.total.unpriced, by_day.skipped), (3, 1, 1));
+
+        let by_provider = ledger.summarize(Some("2026-10-16"), UsageGroup::Provider).unwrap();
+        assert_eq!(by_provider.groups["anthropic"].input_tokens, 2000);
+        assert_eq!(by_provider.total.requests, 2);
+        let text = by_provider.render(UsageGroup::Provider);
+        assert!(text.starts_with("Provider "), "{}", text);
+        assert!(text.contains("(1 unpriced)"));
+        assert!(text.contains("1 unreadable ledger line(s) skipped"));
+
+        assert!(ledger.summarize(Some("16/10/2026"), UsageGroup::Day).is_err());
+        assert!(UsageGroup::parse("week").is_err());
+        let empty = UsageLedger::new(tmp.path().join("missing")).summarize(None, UsageGroup::Day).unwrap();
+        assert_eq!(empty.render(UsageGroup::Day), "No usage recorded\n");
+    }
+}
@@ -1,11 +1,11 @@
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::io::{self, Write};
 use std::path::PathBuf;
 use futures_util::StreamExt;
 
-use super::providers::{ChatResponse, Message, Provider, Role, ToolCall, ToolDefinition};
+use super::events::{self, Utf8Buffer};
+use super::providers::{ApiError, ChatResponse, Message, Provider, Role, ToolCall, ToolDefinition};
 
 const API_BASE_URL: &str = "https://api.webrana.id";
 
@@ -29,14 +29,14 @@ pub struct TierLimits {
     pub tokens_per_day: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StatusResponse {
     pub tier: String,
     pub usage: UsageInfo,
     pub resets_at: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsageInfo {
     pub requests_today: i32,
     pub tokens_today: i32,
@@ -44,6 +44,34 @@ pub struct UsageInfo {
     pub tokens_limit: i32,
 }
 
+/// The last status fetched, shown when the API can't be reached
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedStatus {
+    /// Unix seconds
+    pub fetched_at: u64,
+    pub status: StatusResponse,
+}
+
+impl CachedStatus {
+    pub fn load(path: &std::path::Path) -> Option<Self> {
+        serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+    }
+
+    pub fn save(path: &std::path::Path, status: &StatusResponse, fetched_at: u64) -> Result<()> {
+        let cached = Self {
+            fetched_at,
+            status: status.clone(),
+        };
+        std::fs::write(path, serde_json::to_string_pretty(&cached)?)?;
+        Ok(())
+    }
+
+    /// Shown with a status served from the cache
+    pub fn note(&self) -> String {
+        format!("offline, cached {}", crate::memory::utc_date(self.fetched_at))
+    }
+}
+
 pub struct WebranaProvider {
     credentials: Credentials,
 }
@@ -100,7 +128,7 @@ impl WebranaProvider {
     }
 
     async fn register() -> Result<Credentials> {
-        let client = reqwest::Client::new();
+        let client = crate::util::http::client()?;
         let device_id = Self::generate_device_id();
 
         let response = client
@@ -146,8 +174,9 @@ impl WebranaProvider {
     }
 
     pub async fn get_status() -> Result<StatusResponse> {
+        crate::util::http::ensure_online("The Webrana API status")?;
         let credentials = Self::load_or_register().await?;
-        let client = reqwest::Client::new();
+        let client = crate::util::http::client()?;
 
         let response = client
             .get(format!("{}/v1/auth/status", API_BASE_URL))
@@ -160,7 +189,24 @@ impl WebranaProvider {
             return Err(anyhow!("Failed to get status: {}", error));
         }
 
-        Ok(response.json().await?)
+        let status: StatusResponse = response.json().await?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        if let Err(e) = CachedStatus::save(&Self::status_cache_path(), &status, now) {
+            tracing::debug!("Could not cache the API status: {}", e);
+        }
+        Ok(status)
+    }
+
+    fn status_cache_path() -> PathBuf {
+        Self::credentials_path().with_file_name("status-cache.json")
+    }
+
+    /// The status from the last successful `get_status`
+    pub fn cached_status() -> Option<CachedStatus> {
+        CachedStatus::load(&Self::status_cache_path())
     }
 
     pub fn get_credentials() -> Option<Credentials> {
@@ -183,7 +229,7 @@ impl Provider for WebranaProvider {
         messages: Vec<Message>,
         _tools: Option<Vec<ToolDefinition>>,
     ) -> Result<ChatResponse> {
-        let client = reqwest::Client::new();
+        let client = crate::util::http::client()?;
 
         let chat_messages: Vec<serde_json::Value> = messages
             .iter()
@@ -211,8 +257,9 @@ impl Provider for WebranaProvider {
             .await?;
 
         if !response.status().is_success() {
+            let status = response.status();
             let error = response.text().await?;
-            return Err(anyhow!("Chat request failed: {}", error));
+            return Err(ApiError { provider: "Webrana", status, body: error }.into());
         }
 
         let json: serde_json::Value = response.json().await?;
@@ -226,6 +273,8 @@ impl Provider for WebranaProvider {
             content,
             tool_calls: Vec::new(),
             stop_reason: Some("stop".to_string()),
+            refusal: None,
+            provenance: Default::default(),
         })
     }
 
@@ -234,7 +283,7 @@ impl Provider for WebranaProvider {
         messages: Vec<Message>,
         _tools: Option<Vec<ToolDefinition>>,
     ) -> Result<ChatResponse> {
-        let client = reqwest::Client::new();
+        let client = crate::util::http::client()?;
 
         let chat_messages: Vec<serde_json::Value> = messages
             .iter()
@@ -262,27 +311,30 @@ impl Provider for WebranaProvider {
             .await?;
 
         if !response.status().is_success() {
+            let status = response.status();
             let error = response.text().await?;
-            return Err(anyhow!("Chat request failed: {}", error));
+            return Err(ApiError { provider: "Webrana", status, body: error }.into());
         }
 
         let mut stream = response.bytes_stream();
         let mut content = String::new();
+        let mut buffer = String::new();
+        let mut decoder = Utf8Buffer::default();
 
         while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            let text = String::from_utf8_lossy(&chunk);
+            buffer.push_str(&decoder.push(&chunk?));
 
-            for line in text.lines() {
-                if let Some(data) = line.strip_prefix("data: ") {
+            // SSE lines; a line cut across chunks waits for the rest
+            while let Some(pos) = buffer.find('\n') {
+                let line: String = buffer.drain(..=pos).collect();
+                if let Some(data) = line.trim_end().strip_prefix("data: ") {
                     if data == "[DONE]" {
                         continue;
                     }
 
                     if let Ok(json) = serde_json::from_str::<serde_json::Value>(data) {
                         if let Some(delta_content) = json["choices"][0]["delta"]["content"].as_str() {
-                            print!("{}", delta_content);
-                            io::stdout().flush().ok();
+                            events::text(delta_content);
                             content.push_str(delta_content);
                         }
                     }
@@ -290,11 +342,13 @@ impl Provider for WebranaProvider {
             }
         }
 
-        println!();
+        events::end_text();
         Ok(ChatResponse {
             content,
             tool_calls: Vec::new(),
             stop_reason: Some("stop".to_string()),
+            refusal: None,
+            provenance: Default::default(),
         })
     }
 
@@ -302,3 +356,32 @@ impl Provider for WebranaProvider {
         "webrana"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_status_round_trip() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("status-cache.json");
+        assert!(CachedStatus::load(&path).is_none());
+
+        let status = StatusResponse {
+            tier: "free".to_string(),
+            usage: UsageInfo {
+                requests_today: 12,
+                tokens_today: 3_400,
+                requests_limit: 50,
+                tokens_limit: 100_000,
+            },
+            resets_at: "2026-10-17T00:00:00Z".to_string(),
+        };
+        CachedStatus::save(&path, &status, 1_791_000_000).unwrap();
+
+        let cached = CachedStatus::load(&path).unwrap();
+        assert_eq!(cached.status.usage.requests_today, 12);
+        assert_eq!(cached.status.tier, "free");
+        assert_eq!(cached.note(), "offline, cached 2026-10-03 04:00 UTC");
+    }
+}
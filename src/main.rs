@@ -11,51 +11,167 @@ mod llm;
 mod mcp;
 mod memory;
 mod plugins;
+mod scaffold;
 mod skills;
 mod tui;
 mod ui;
+mod util;
+mod vcs;
 
 use anyhow::Result;
-use clap::Parser;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use clap::{CommandFactory, FromArgMatches};
+use tracing::Instrument;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
 use crate::cli::{Cli, Commands};
 use crate::config::Settings;
-use crate::core::Orchestrator;
+use crate::core::{logging, telemetry, ChatScript, Orchestrator, Verbosity};
 use crate::ui::Console;
+use crate::util::unix_now;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    init_tracing();
+    let matches = Cli::command().get_matches();
+    let command = matches.subcommand_name().unwrap_or("repl").to_string();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // Change working directory if specified: the project config and `.env`
+    // are read there, and the session header shows it
+    if let Some(workdir) = &cli.workdir {
+        std::env::set_current_dir(workdir)?;
+    }
+
+    // Opt-in .env loading, ahead of the settings so they see its variables;
+    // already-exported variables take precedence
+    let env_file = if cli.env_file.is_some() || Settings::dotenv_enabled()? {
+        Settings::load_env_file(cli.env_file.as_deref().map(std::path::Path::new))?
+    } else {
+        None
+    };
 
-    let cli = Cli::parse();
     let settings = Settings::load()?;
+    util::http::configure(&settings)?;
+    util::egress::configure(&settings)?;
+    util::http::set_offline(cli.offline);
+    core::updater::cleanup_replaced_binary();
+
+    let verbosity = Verbosity::from_flags(cli.verbose, cli.quiet);
+    verbosity.set_current();
+    ui::plain::set_plain(cli.plain || ui::plain::ci_detected());
+    ui::plain::set_a11y(cli.a11y);
+    if !cli.a11y
+        && ui::plain::suggest_a11y(
+            std::env::var("TERM").ok().as_deref(),
+            std::env::var("WEBRANA_A11Y").ok().as_deref(),
+        )
+    {
+        eprintln!("Tip: TERM=dumb; for screen-reader-friendly output use --a11y or set WEBRANA_A11Y=1 (WEBRANA_A11Y=0 hides this tip)");
+    }
+    init_tracing(&settings, verbosity);
+    if let Some(loaded) = env_file {
+        tracing::debug!("Loaded environment from {}", loaded.display());
+    }
+    core::rate_limit::configure_shared_limits(core::SharedLimitConfig {
+        requests_per_minute: settings.rate_limit.requests_per_minute as f64,
+        background_share: settings.rate_limit.background_share.clamp(0.0, 1.0) as f64,
+        ..Default::default()
+    });
+
+    // One trace per command; flushed even when the command fails
+    let span = tracing::info_span!("webrana.command", command = command.as_str());
+    let result = run(cli, settings).instrument(span).await;
+    telemetry::shutdown();
+    result
+}
+
+async fn run(cli: Cli, mut settings: Settings) -> Result<()> {
+    if cli.read_only {
+        settings.shell.read_only = true;
+    }
+    if let Some(threshold) = &cli.auto_compact_at {
+        settings.auto_compact_at = Some(memory::parse_threshold(threshold)?);
+    }
+    if cli.seed.is_some() {
+        settings.sampling.seed = cli.seed;
+    }
+    if cli.temperature.is_some() {
+        settings.sampling.temperature = cli.temperature;
+    }
+    if cli.top_p.is_some() {
+        settings.sampling.top_p = cli.top_p;
+    }
+    if cli.global_memory {
+        settings.conversation_memory.global = true;
+    }
     let console = Console::new();
 
     // Check if we should suppress banner (for clean output modes)
-    let suppress_banner = matches!(
+    let suppress_banner = cli.no_banner || cli.quiet || ui::is_plain() || matches!(
         &cli.command,
         Some(Commands::Ask { print: true, .. }) | Some(Commands::Ask { json: true, .. })
-    );
+            | Some(Commands::Ask { json_stream: true, .. }) | Some(Commands::Chat { json_stream: true, .. })
+    ) || matches!(&cli.command, Some(Commands::Ask { output_template: Some(_), .. })) || matches!(&cli.command, Some(Commands::Scan { format, quiet_clean, .. }) if format == "json" || *quiet_clean)
+        || matches!(&cli.command, Some(Commands::Search { format, .. }) if format == "json")
+        || matches!(&cli.command, Some(Commands::Dupes { format, .. }) if format == "json")
+        || matches!(&cli.command, Some(Commands::Index { json: true, .. }))
+        || matches!(&cli.command, Some(Commands::Usage { format, .. }) if format == "json")
+        || matches!(&cli.command, Some(Commands::Digest { .. }))
+        || matches!(&cli.command, Some(Commands::Skills { command: Some(cli::SkillsCommands::Run { .. }) }))
+        || matches!(&cli.command, Some(Commands::Skills { command: Some(cli::SkillsCommands::Export { .. }) }))
+        || matches!(&cli.command, Some(Commands::Embeddings { .. }))
+        || matches!(&cli.command, Some(Commands::Memory { command: cli::MemoryCommands::Show }))
+        || matches!(&cli.command, Some(Commands::MemorySearch { json: true, .. }))
+        || matches!(&cli.command, Some(Commands::Doctor { json: true, .. }))
+        || matches!(&cli.command, Some(Commands::Mr { json: true, .. }))
+        || matches!(&cli.command, Some(Commands::Plugin { command: cli::PluginCommands::Run { .. } }))
+        || matches!(&cli.command, Some(Commands::Config { command: Some(c) }) if !matches!(c, cli::ConfigCommands::Show));
     
-    if !suppress_banner {
-        console.banner();
-    }
-
-    // Change working directory if specified
-    if let Some(workdir) = &cli.workdir {
-        std::env::set_current_dir(workdir)?;
-        if !suppress_banner {
-            console.info(&format!("Working directory: {}", workdir));
-        }
-    }
+    ui::header::set_mode(if suppress_banner { config::BannerMode::Off } else { settings.ui.banner });
+    console.banner();
 
     match cli.command {
-        Some(Commands::Chat { message, auto }) => {
-            let orchestrator = Orchestrator::new(settings, auto || cli.auto).await?;
-            orchestrator.chat(&message).await?;
+        Some(Commands::Chat { message, script, interactive_between, transcript, auto, allow, deny, json_stream }) => {
+            if json_stream {
+                let message = message.unwrap_or_default();
+                return json_stream_turn(settings, auto || cli.auto, &allow, &deny, &message).await;
+            }
+            // Parse errors surface before any setup
+            let script = script.as_deref().map(|path| ChatScript::load(std::path::Path::new(path))).transpose()?;
+            let orchestrator = Orchestrator::new(settings, auto || cli.auto)
+                .await?
+                .with_skill_overrides(&allow, &deny)?;
+            console.session_header(&orchestrator.session_header());
+            match script {
+                Some(script) => {
+                    let run = orchestrator.run_script(&script, interactive_between).await?;
+                    if let Some(path) = &transcript {
+                        orchestrator.save_transcript(&run.context, std::path::Path::new(path))?;
+                        console.success(&format!("Transcript saved to {}", path));
+                    }
+                    if !run.unmet.is_empty() {
+                        console.error(&format!("{} expectation(s) not met:", run.unmet.len()));
+                        for unmet in &run.unmet {
+                            println!("  - {}", unmet);
+                        }
+                        telemetry::exit(1);
+                    }
+                }
+                None => orchestrator.chat(message.as_deref().unwrap_or_default()).await?,
+            }
         }
-        Some(Commands::Ask { query, print, json, model: _, provider: _ }) => {
+        Some(Commands::Ask {
+            query,
+            print,
+            json,
+            json_stream,
+            clean,
+            output_template,
+            rag,
+            model: _,
+            provider: _,
+            compare,
+            diff,
+        }) => {
             use std::io::{self, Read};
             
             // Check if we have pipe input
@@ -87,11 +203,19 @@ async fn main() -> Result<()> {
                 (None, true) => {
                     // No input at all
                     console.error("No input provided. Use: webrana ask \"query\" or pipe content");
-                    std::process::exit(1);
+                    telemetry::exit(1);
                 }
             };
             
-            if !print && !json {
+            if json_stream {
+                return json_stream_turn(settings, false, &[], &[], &full_prompt).await;
+            }
+
+            if let Some(files) = compare {
+                return ask_compare(&console, settings, &files, &full_prompt, json, diff).await;
+            }
+
+            if !print && !json && output_template.is_none() {
                 console.info(&format!(
                     "📝 Ask mode{}",
                     if has_pipe { " (with pipe input)" } else { "" }
@@ -99,21 +223,72 @@ async fn main() -> Result<()> {
             }
             
             // Create orchestrator and get response
-            let orchestrator = Orchestrator::new(settings.clone(), false).await?;
+            let mut orchestrator = Orchestrator::new(settings.clone(), false).await?;
+            if rag {
+                use skills::{SemanticSearch, SemanticSearchConfig};
+
+                let config = SemanticSearchConfig::default();
+                let (provider, mock) = embeddings::default_provider(&settings);
+                if let Some(reason) = mock {
+                    tracing::warn!("{}, using mock embeddings", reason);
+                }
+                let mut search = SemanticSearch::with_provider(provider, config);
+                let mut progress = ui::Progress::new("Indexing");
+                search
+                    .index_directory_with_progress(std::path::Path::new("."), &mut |done, total| {
+                        progress.update(done, total)
+                    })
+                    .await?;
+                progress.finish();
+                orchestrator = orchestrator.with_rag(search.into_rag(llm::RagConfig::default()));
+            }
             
-            if json {
+            if let Some(template) = output_template {
+                let values = orchestrator.ask_with_usage(&query, &full_prompt).await?;
+                println!("{}", template.render(&values));
+            } else if json {
                 // JSON output mode
-                let response = orchestrator.ask_simple(&full_prompt).await?;
-                let output = serde_json::json!({
+                let mut output = serde_json::json!({
                     "query": query,
                     "has_pipe_input": has_pipe,
-                    "response": response,
                 });
+                if rag {
+                    let answer = orchestrator.ask_cited(&full_prompt).await?;
+                    output["response"] = serde_json::json!(answer.text);
+                    output["sources"] = serde_json::to_value(&answer.sources)?;
+                } else {
+                    let response = orchestrator.ask_simple(&full_prompt).await?;
+                    output["response"] = serde_json::json!(response);
+                }
+                output["provenance"] = serde_json::to_value(orchestrator.last_provenance())?;
                 println!("{}", serde_json::to_string_pretty(&output)?);
             } else if print {
-                // Print mode - clean output only
-                let response = orchestrator.ask_simple(&full_prompt).await?;
-                println!("{}", response);
+                // Print mode - clean output only, errors on stderr
+                let result = if rag {
+                    orchestrator
+                        .ask_cited(&full_prompt)
+                        .await
+                        .map(|answer| answer.with_footer())
+                } else {
+                    orchestrator.ask_simple(&full_prompt).await
+                };
+                let result = if clean || settings.strip_persona_prefix {
+                    result.map(|response| core::strip_persona_prefix(&response))
+                } else {
+                    result
+                };
+                let code = core::write_print_response(
+                    result,
+                    &mut std::io::stdout(),
+                    &mut std::io::stderr(),
+                );
+                if code != 0 {
+                    telemetry::exit(code);
+                }
+            } else if rag {
+                let answer = orchestrator.ask_cited(&full_prompt).await?;
+                println!("\n{}", answer.with_footer());
+                orchestrator.print_provenance();
             } else {
                 // Normal mode with formatting
                 orchestrator.chat(&full_prompt).await?;
@@ -123,30 +298,375 @@ async fn main() -> Result<()> {
             task,
             max_iterations,
             yolo,
+            allow,
+            deny,
+            allow_paths,
+            deny_paths,
+            confirm_each,
+            deadline,
+            tool_timeout,
+            tool_choice,
+            task_file,
+            assess_every,
         }) => {
+            let brief = match (&task, &task_file) {
+                (_, Some(path)) => core::task_brief::TaskBrief::load(path)?,
+                (Some(task), None) => core::task_brief::TaskBrief { body: task.clone(), ..Default::default() },
+                (None, None) => unreachable!("clap requires a task or --task-file"),
+            };
+            if confirm_each && !atty::is(atty::Stream::Stdin) {
+                console.error("--confirm-each needs an interactive terminal");
+                telemetry::exit(1);
+            }
+            let deadline = deadline.as_deref().map(memory::parse_age).transpose()?;
+            if let Some(limit) = tool_timeout.as_deref().map(memory::parse_age).transpose()? {
+                settings.skill_timeouts = config::SkillTimeouts::uniform(limit);
+            }
             console.info(&format!(
-                "🤖 Auto Mode: max {} iterations{}",
+                "🤖 Auto Mode: max {} iterations{}{}",
                 max_iterations,
-                if yolo { " (YOLO mode)" } else { "" }
+                if yolo { " (YOLO mode)" } else { "" },
+                if confirm_each { " (step-through)" } else { "" }
             ));
-            let orchestrator = Orchestrator::new(settings, true).await?;
+            // Stashing or branching a dirty tree asks first unless --auto
+            let mut orchestrator = Orchestrator::new(settings, cli.auto)
+                .await?
+                .with_skill_overrides(&allow, &deny)?
+                .with_write_paths(allow_paths, deny_paths)
+                .with_tool_choice(tool_choice)?
+                .with_acceptance_criteria(brief.acceptance_criteria.clone(), assess_every);
+            if confirm_each {
+                orchestrator = orchestrator.with_stepper(std::sync::Arc::new(core::ConfirmationPrompt::step));
+            }
+            if let Some(deadline) = deadline {
+                orchestrator = orchestrator.with_deadline(deadline);
+            }
+            console.session_header(&ui::SessionHeader {
+                yolo,
+                ..orchestrator.session_header()
+            });
             orchestrator
-                .run_autonomous(&task, max_iterations, yolo)
+                .run_autonomous(&brief.prompt(), max_iterations, yolo)
                 .await?;
         }
         Some(Commands::Agents) => {
             console.list_agents(&settings);
         }
-        Some(Commands::Skills) => {
-            console.list_skills();
+        Some(Commands::Skills { command }) => match command {
+            None | Some(cli::SkillsCommands::List) => console.list_skills(),
+            Some(cli::SkillsCommands::Run { name, args, json, yes }) => {
+                let registry = skills::SkillRegistry::new();
+                let skill = match registry.get(&name) {
+                    Some(skill) => skill.definition(),
+                    None => {
+                        console.error(&format!("Unknown skill: {} (see `webrana skills`)", name));
+                        telemetry::exit(1);
+                    }
+                };
+
+                let result: Result<(String, std::time::Duration)> = async {
+                    let parsed: serde_json::Value = serde_json::from_str(&args)
+                        .map_err(|e| anyhow::anyhow!("Invalid --args JSON: {}", e))?;
+                    skill.validate_args(&parsed)?;
+                    if skill.requires_confirmation && !(yes || cli.auto) {
+                        let question = match core::ConfirmationPrompt::write_preview(
+                            &name,
+                            &parsed,
+                            util::diff::DiffStyle::terminal(),
+                        ) {
+                            Some(preview) => format!("{}\nRun '{}'?", preview, name),
+                            None => format!("Run '{}' with {}?", name, parsed),
+                        };
+                        if !core::ConfirmationPrompt::confirm(&question) {
+                            anyhow::bail!("Cancelled: '{}' requires confirmation", name);
+                        }
+                    }
+                    let start = std::time::Instant::now();
+                    let output = registry.execute(&name, &parsed, &settings).await?;
+                    Ok((output, start.elapsed()))
+                }
+                .await;
+
+                if json {
+                    let output = match &result {
+                        Ok((output, elapsed)) => serde_json::json!({
+                            "skill": name,
+                            "success": true,
+                            "output": output,
+                            "duration_ms": elapsed.as_millis() as u64,
+                        }),
+                        Err(e) => serde_json::json!({
+                            "skill": name,
+                            "success": false,
+                            "error": e.to_string(),
+                        }),
+                    };
+                    println!("{}", serde_json::to_string_pretty(&output)?);
+                } else {
+                    match &result {
+                        Ok((output, _)) => println!(
+                            "{}",
+                            core::scrub::scrub_tool_output(&name, output, settings.tool_output.keep_colors)
+                                .display
+                        ),
+                        Err(e) => console.error(&format!("{}", e)),
+                    }
+                }
+                if result.is_err() {
+                    telemetry::exit(1);
+                }
+            }
+            Some(cli::SkillsCommands::Export { format }) => {
+                let registry = skills::SkillRegistry::new();
+                let plugins = plugins::PluginManager::default_manager()?;
+                // Tool schemas come from the servers themselves, so start the configured ones
+                let mcp_path = mcp::McpRegistry::default_config_path()?;
+                let mut upstream = if mcp_path.exists() {
+                    mcp::McpRegistry::from_config(&mcp::McpRegistry::load_config(&mcp_path)?)?
+                } else {
+                    mcp::McpRegistry::new()
+                };
+                upstream.hydrate_all();
+                let tools = skills::export::collect(&registry, &plugins.list_enabled(), &upstream.list_all_tools());
+                upstream.shutdown()?;
+                println!("{}", serde_json::to_string_pretty(&skills::export::render(&tools, &format)?)?);
+            }
+        },
+        Some(Commands::Config { command }) => {
+            use cli::ConfigCommands;
+            use config::{ConfigFile, ConfigScope};
+
+            let scope = |project: bool| {
+                if project {
+                    ConfigScope::Project
+                } else {
+                    ConfigScope::Global
+                }
+            };
+
+            match command.unwrap_or(ConfigCommands::Show) {
+                ConfigCommands::Show => console.show_config(&settings),
+                ConfigCommands::Get { key, project } => {
+                    let schema = config::lookup_key(&key)?;
+                    let value = if project {
+                        ConfigFile::load(&ConfigScope::Project.path()?)?.get(&key)
+                    } else {
+                        let effective = toml::Value::try_from(&settings)?;
+                        config::get_path(&effective, &key).map(|v| v.to_string())
+                    };
+                    match value {
+                        Some(_) if schema.kind == config::KeyType::Secret => println!("(set, hidden)"),
+                        Some(value) => println!("{}", value),
+                        None => println!("(unset)"),
+                    }
+                }
+                ConfigCommands::Set { key, value, project } => {
+                    let path = scope(project).path()?;
+                    let (old, new) = config::set_value(scope(project), &path, &key, &value)?;
+                    println!("{}: {} → {}", key, old.as_deref().unwrap_or("(unset)"), new);
+                    console.info(&format!("Saved {}", path.display()));
+                }
+                ConfigCommands::Unset { key, project } => {
+                    let path = scope(project).path()?;
+                    match config::unset_value(&path, &key)? {
+                        Some(old) => println!("{}: {} → (unset)", key, old),
+                        None => console.info(&format!("{} is not set in {}", key, path.display())),
+                    }
+                }
+                ConfigCommands::Encrypt { key, recipients, project } => {
+                    if !config::AGE_SUPPORTED {
+                        anyhow::bail!("This webrana was built without age support (rebuild with `--features age`)");
+                    }
+                    let schema = config::lookup_key(&key)?;
+                    if !matches!(schema.kind, config::KeyType::String | config::KeyType::Secret) {
+                        anyhow::bail!("Only string values can be encrypted; '{}' is not one", key);
+                    }
+                    if project && !schema.project && schema.kind != config::KeyType::Secret {
+                        anyhow::bail!("'{}' can only be set in the global config (drop --project)", key);
+                    }
+
+                    let path = scope(project).path()?;
+                    let mut file = ConfigFile::load(&path)?;
+                    let mut known = file.get_list(config::RECIPIENTS_KEY);
+                    // A cloned repository's config could name someone else's keys, so
+                    // recipients only come from flags or the user config unless confirmed
+                    let from_project = recipients.is_empty() && project && !known.is_empty();
+                    let recipients = if !recipients.is_empty() {
+                        recipients
+                    } else if !known.is_empty() {
+                        known.clone()
+                    } else {
+                        ConfigFile::load(&ConfigScope::Global.path()?)?.get_list(config::RECIPIENTS_KEY)
+                    };
+                    if recipients.is_empty() {
+                        anyhow::bail!(
+                            "No recipients: pass --recipient age1... or set {} in the global config",
+                            config::RECIPIENTS_KEY
+                        );
+                    }
+                    if from_project {
+                        let question = format!(
+                            "Encrypt {} to these recipients from {}?\n  {}\n",
+                            key,
+                            path.display(),
+                            recipients.join("\n  ")
+                        );
+                        if !atty::is(atty::Stream::Stdin) {
+                            anyhow::bail!(
+                                "Recipients from {} need confirmation; pass --recipient to name them",
+                                path.display()
+                            );
+                        }
+                        if !core::ConfirmationPrompt::confirm(&question) {
+                            anyhow::bail!("Cancelled");
+                        }
+                    }
+
+                    let plaintext = config::read_secret(&format!("Value for {}: ", key))?;
+                    config::encrypt_into(&mut file, &key, &plaintext, &recipients)?;
+                    // Remember who it's encrypted to, for `config recipients`
+                    let before = known.len();
+                    for recipient in &recipients {
+                        if !known.contains(recipient) {
+                            known.push(recipient.clone());
+                        }
+                    }
+                    if known.len() != before {
+                        file.set_list(config::RECIPIENTS_KEY, &known)?;
+                    }
+                    file.save()?;
+                    console.success(&format!(
+                        "Encrypted {} to {} recipient{} in {}",
+                        key,
+                        recipients.len(),
+                        if recipients.len() == 1 { "" } else { "s" },
+                        path.display()
+                    ));
+                }
+                ConfigCommands::Recipients { add, remove, project } => {
+                    let path = scope(project).path()?;
+                    let mut file = ConfigFile::load(&path)?;
+                    let mut recipients = file.get_list(config::RECIPIENTS_KEY);
+                    if !add.is_empty() || !remove.is_empty() {
+                        if !config::AGE_SUPPORTED {
+                            anyhow::bail!("This webrana was built without age support (rebuild with `--features age`)");
+                        }
+                        recipients.retain(|r| !remove.contains(r));
+                        for recipient in add {
+                            if !recipients.contains(&recipient) {
+                                recipients.push(recipient);
+                            }
+                        }
+                        let encrypted = file.encrypted_keys();
+                        if recipients.is_empty() && !encrypted.is_empty() {
+                            anyhow::bail!("Refusing to remove the last recipient while {} values are encrypted", encrypted.len());
+                        }
+                        let keys = config::reencrypt(&mut file, &recipients)?;
+                        file.set_list(config::RECIPIENTS_KEY, &recipients)?;
+                        file.save()?;
+                        console.success(&format!("Re-encrypted {} value(s) in {}", keys.len(), path.display()));
+                    }
+                    if recipients.is_empty() {
+                        console.info(&format!("No recipients in {}", path.display()));
+                    }
+                    for recipient in recipients {
+                        println!("{}", recipient);
+                    }
+                }
+            }
+        }
+        Some(Commands::Memory { command }) => {
+            use cli::MemoryCommands;
+
+            let root = std::env::current_dir()?;
+            match command {
+                MemoryCommands::Show => match memory::memory_path(&root) {
+                    Some(path) => print!("{}", std::fs::read_to_string(path)?),
+                    None => console.info(&format!(
+                        "No {} or {} here. Create one with: webrana memory init",
+                        memory::MEMORY_FILE,
+                        memory::FALLBACK_FILE
+                    )),
+                },
+                MemoryCommands::Edit => {
+                    let path = memory::target_path(&root);
+                    if !path.exists() {
+                        std::fs::write(&path, memory::scaffold(&root)?)?;
+                    }
+                    let editor = std::env::var("VISUAL")
+                        .or_else(|_| std::env::var("EDITOR"))
+                        .unwrap_or_else(|_| {
+                            if cfg!(windows) { "notepad" } else { "vi" }.to_string()
+                        });
+                    // The editor may come with arguments, e.g. "code --wait"
+                    let mut parts = editor.split_whitespace();
+                    let program = parts.next().unwrap_or("vi");
+                    let status = std::process::Command::new(program)
+                        .args(parts)
+                        .arg(&path)
+                        .status()
+                        .map_err(|e| anyhow::anyhow!("Failed to start editor '{}': {}", program, e))?;
+                    if !status.success() {
+                        anyhow::bail!("Editor '{}' exited with {}", program, status);
+                    }
+                }
+                MemoryCommands::Init { force } => {
+                    let path = root.join(memory::MEMORY_FILE);
+                    if path.exists() && !force {
+                        anyhow::bail!("{} already exists (use --force to replace it)", path.display());
+                    }
+                    std::fs::write(&path, memory::scaffold(&root)?)?;
+                    console.success(&format!("Created {}", path.display()));
+                }
+                MemoryCommands::Prune { older_than } => {
+                    let age = memory::parse_age(&older_than)?;
+                    let mut store = open_conversation_memory(&console, &settings)?;
+                    let scope = memory_scope(&settings, &root);
+                    let removed = store.prune(unix_now().saturating_sub(age.as_secs()), scope.as_deref());
+                    if removed > 0 {
+                        store.save()?;
+                    }
+                    console.success(&format!(
+                        "Removed {} conversation memory entries older than {} ({} left)",
+                        removed,
+                        older_than,
+                        store.len()
+                    ));
+                }
+            }
+        }
+        Some(Commands::Context { command: cli::ContextCommands::Stats { session } }) => {
+            let report =
+                core::saved_context_report(&session, settings.context_window(), ui::plain::Mode::current())?;
+            print!("{}", report);
         }
-        Some(Commands::Config) => {
-            console.show_config(&settings);
+        Some(Commands::MemorySearch { query, limit, json }) => {
+            let store = open_conversation_memory(&console, &settings)?;
+            let scope = memory_scope(&settings, &std::env::current_dir()?);
+            let found = store
+                .search(&query, scope.as_deref(), limit, settings.conversation_memory.min_score)
+                .await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&found)?);
+            } else if found.is_empty() {
+                console.info("No related past sessions found");
+            } else {
+                for memory in &found {
+                    println!("[session {}, {}] score {:.2}  {}", memory.session, memory.date, memory.score, memory.project);
+                    for line in memory.text.lines() {
+                        println!("  {}", line);
+                    }
+                    println!();
+                }
+            }
         }
         Some(Commands::Crew { command }) => {
             use crew::{Crew, CrewManager, CrewTemplate};
 
             let mut manager = CrewManager::new()?;
+            for warning in manager.warnings() {
+                console.warn(warning);
+            }
 
             match command {
                 cli::CrewCommands::List => {
@@ -214,6 +734,9 @@ async fn main() -> Result<()> {
                         if let Some(author) = &crew.author {
                             println!("Author: {}", author);
                         }
+                        if let Some(source) = &crew.source {
+                            println!("Source: {}", source);
+                        }
                         println!("\nDescription:\n  {}", crew.description);
                         println!("\nSystem Prompt:\n  {}", crew.system_prompt.replace('\n', "\n  "));
                         println!("\nConfig:");
@@ -225,10 +748,9 @@ async fn main() -> Result<()> {
                         }
                         println!("  Auto Mode: {}", crew.config.auto_mode);
                         println!("\nPermissions:");
-                        println!("  Shell: {}", crew.permissions.shell_access);
-                        println!("  File Read: {}", crew.permissions.file_read);
-                        println!("  File Write: {}", crew.permissions.file_write);
-                        println!("  Network: {}", crew.permissions.network_access);
+                        for line in crew.permissions.summary() {
+                            println!("  {}", line);
+                        }
                     } else {
                         console.error(&format!("Crew '{}' not found", id));
                     }
@@ -242,8 +764,7 @@ async fn main() -> Result<()> {
                 }
                 cli::CrewCommands::Use { id } => {
                     match manager.set_active(&id) {
-                        Ok(_) => {
-                            let crew = manager.get(&id).unwrap();
+                        Ok(crew) => {
                             console.success(&format!("Now using crew '{}'", crew.name));
                             if let Some(greeting) = &crew.config.greeting {
                                 println!("\n{}", greeting);
@@ -256,6 +777,14 @@ async fn main() -> Result<()> {
                     manager.clear_active()?;
                     console.success("Cleared active crew. Using default agent.");
                 }
+                cli::CrewCommands::Clone { src, new_id, name } => {
+                    match manager.clone_crew(&src, &new_id, name.as_deref()) {
+                        Ok(crew) => {
+                            console.success(&format!("Cloned '{}' into '{}'", src, crew.id));
+                        }
+                        Err(e) => console.error(&format!("Failed to clone: {}", e)),
+                    }
+                }
                 cli::CrewCommands::Export { id, output } => {
                     match manager.export(&id) {
                         Ok(yaml) => {
@@ -269,13 +798,29 @@ async fn main() -> Result<()> {
                         Err(e) => console.error(&format!("{}", e)),
                     }
                 }
-                cli::CrewCommands::Import { file } => {
-                    let yaml = std::fs::read_to_string(&file)?;
-                    match manager.import(&yaml) {
+                cli::CrewCommands::Import { file, url } => {
+                    let fetched = match (file, url) {
+                        (_, Some(url)) => crew::remote::fetch(&url).await.map(|(yaml, served_from)| {
+                            if served_from != url {
+                                console.info(&format!("Fetched from {}", served_from));
+                            }
+                            (yaml, url)
+                        }),
+                        (Some(file), None) => std::fs::read_to_string(&file)
+                            .map(|yaml| (yaml, file))
+                            .map_err(Into::into),
+                        (None, None) => unreachable!("clap requires a file or --url"),
+                    };
+                    match fetched.and_then(|(yaml, source)| manager.import_from(&yaml, Some(&source))) {
                         Ok(crew) => {
-                            console.success(&format!("Imported crew '{}'", crew.id));
+                            let source = crew.source.as_deref().unwrap_or_default();
+                            console.success(&format!("Imported crew '{}' from {}", crew.id, source));
+                            println!("\nPermissions:");
+                            for line in crew.permissions.summary() {
+                                println!("  {}", line);
+                            }
                         }
-                        Err(e) => console.error(&format!("Failed to import: {}", e)),
+                        Err(e) => console.error(&format!("Failed to import: {:#}", e)),
                     }
                 }
                 cli::CrewCommands::Templates => {
@@ -305,6 +850,27 @@ async fn main() -> Result<()> {
                     console.info(&format!("Starting MCP server on port {}...", port));
                     mcp::server::start(port).await?;
                 }
+                cli::McpCommands::Proxy { port, config } => {
+                    let path = match config {
+                        Some(path) => std::path::PathBuf::from(path),
+                        None => McpRegistry::default_config_path()?,
+                    };
+                    let upstream = if path.exists() {
+                        McpRegistry::from_config(&McpRegistry::load_config(&path)?)?
+                    } else {
+                        console.warn(&format!(
+                            "No upstream servers: {} not found",
+                            path.display()
+                        ));
+                        McpRegistry::new()
+                    };
+                    for name in upstream.connected_servers() {
+                        let tools = upstream.list_server_tools(name).map_or(0, |t| t.len());
+                        console.info(&format!("Upstream '{}': {} tools", name, tools));
+                    }
+                    let proxy = mcp::McpProxy::new(upstream, settings.clone());
+                    mcp::proxy::start(port, proxy).await?;
+                }
                 cli::McpCommands::List => {
                     let reg = registry.lock().unwrap();
                     let servers = reg.connected_servers();
@@ -325,6 +891,7 @@ async fn main() -> Result<()> {
                         args,
                         env: HashMap::new(),
                         enabled: true,
+                        ..Default::default()
                     };
                     let mut reg = registry.lock().unwrap();
                     match reg.add_server(&name, &config) {
@@ -347,9 +914,7 @@ async fn main() -> Result<()> {
                 cli::McpCommands::Tools { server } => {
                     let reg = registry.lock().unwrap();
                     let tools = if let Some(name) = server {
-                        reg.list_server_tools(&name)
-                            .map(|t| t.iter().map(|tool| (name.clone(), tool.clone())).collect())
-                            .unwrap_or_default()
+                        reg.list_all_tools().into_iter().filter(|(server, _)| *server == name).collect()
                     } else {
                         reg.list_all_tools()
                     };
@@ -359,7 +924,8 @@ async fn main() -> Result<()> {
                     } else {
                         println!("\nAvailable MCP tools:\n");
                         for (server_name, tool) in tools {
-                            println!("  {} (from {})", tool.name, server_name);
+                            let schema = if tool.input_schema.is_some() { "schema loaded" } else { "schema on demand" };
+                            println!("  {} (from {}) [{}]", tool.name, server_name, schema);
                             if let Some(desc) = &tool.description {
                                 println!("    {}", desc);
                             }
@@ -400,6 +966,11 @@ async fn main() -> Result<()> {
             dir,
             top_k,
             index,
+            file_glob,
+            language,
+            symbol_contains,
+            format,
+            save_output,
         }) => {
             use skills::{SemanticSearch, SemanticSearchConfig};
             use std::path::Path;
@@ -410,138 +981,510 @@ async fn main() -> Result<()> {
                 ..Default::default()
             };
 
-            // Check for API key
-            let api_key = std::env::var("OPENAI_API_KEY").ok();
-            
-            let mut search = if let Some(key) = api_key {
-                SemanticSearch::new(&key, config)
-            } else {
-                console.warn("OPENAI_API_KEY not set, using mock embeddings");
-                SemanticSearch::new_mock(config)
-            };
+            let (provider, mock) = embeddings::default_provider(&settings);
+            if let Some(reason) = mock {
+                console.warn(&format!("{}, using mock embeddings", reason));
+            }
+            let mut search = SemanticSearch::with_provider(provider, config);
 
             if index {
                 console.info(&format!("Indexing {}...", search_dir));
-                let stats = search.index_directory(Path::new(search_dir)).await?;
+                let mut progress = ui::Progress::new("Indexing");
+                let stats = search
+                    .index_directory_with_progress(Path::new(search_dir), &mut |done, total| {
+                        progress.update(done, total)
+                    })
+                    .await?;
+                progress.finish();
                 console.info(&format!(
-                    "Indexed {} files, {} chunks ({} skipped, {} errors)",
-                    stats.files, stats.chunks, stats.skipped, stats.errors
+                    "Indexed {} files, {} chunks ({} skipped, {} errors){}",
+                    stats.files, stats.chunks, stats.skipped, stats.errors,
+                    throttle_note(stats.throttled)
                 ));
+                console.info(&stats.file_changes());
             }
 
-            console.info(&format!("Searching for: {}", query));
-            let results = search.search(&query).await?;
+            if format != "json" {
+                console.info(&format!("Searching for: {}", query));
+            }
+            let filter = embeddings::MetadataFilter {
+                file_glob,
+                language,
+                symbol_contains,
+            };
+            let results = search.search_filtered(&query, &filter).await?;
 
-            if results.is_empty() {
-                console.warn("No results found. Try indexing first with --index");
+            let report = if format == "json" {
+                let results: Vec<_> = results
+                    .iter()
+                    .map(|r| {
+                        serde_json::json!({
+                            "id": r.id,
+                            "score": r.score,
+                            "file": r.metadata.get("file"),
+                            "text": r.text,
+                        })
+                    })
+                    .collect();
+                format!("{}\n", serde_json::to_string_pretty(&results)?)
             } else {
+                let mut report = String::new();
                 for (i, result) in results.iter().enumerate() {
-                    println!("\n{}. {} (score: {:.3})", i + 1, result.id, result.score);
+                    report.push_str(&format!("\n{}. {} (score: {:.3})\n", i + 1, result.id, result.score));
                     if let Some(file) = result.metadata.get("file") {
-                        println!("   File: {}", file);
+                        report.push_str(&format!("   File: {}\n", file));
                     }
                     // Show snippet
                     let snippet: String = result.text.chars().take(200).collect();
-                    println!("   {}", snippet.replace('\n', " "));
+                    report.push_str(&format!("   {}\n", snippet.replace('\n', " ")));
                 }
+                report
+            };
+
+            if let Some(path) = &save_output {
+                let written = save_output_file(path, &report)?;
+                console.success(&format!(
+                    "{} result(s) written to {}",
+                    results.len(),
+                    written.display()
+                ));
+            } else if results.is_empty() && format != "json" {
+                console.warn("No results found. Try indexing first with --index");
+            } else {
+                print!("{}", report);
             }
         }
-        Some(Commands::Index { dir }) => {
+        Some(Commands::Dupes { dir, threshold, top, format }) => {
             use skills::{SemanticSearch, SemanticSearchConfig};
             use std::path::Path;
 
+            if !(0.0..=1.0).contains(&threshold) {
+                console.error("--threshold must be between 0.0 and 1.0");
+                telemetry::exit(1);
+            }
             let search_dir = dir.as_deref().unwrap_or(".");
             let config = SemanticSearchConfig::default();
 
-            let api_key = std::env::var("OPENAI_API_KEY").ok();
-            
-            let mut search = if let Some(key) = api_key {
-                SemanticSearch::new(&key, config)
+            let (provider, mock) = embeddings::default_provider(&settings);
+            if let Some(reason) = mock {
+                console.warn(&format!("{}, using mock embeddings", reason));
+            }
+            let mut search = SemanticSearch::with_provider(provider, config);
+
+            // The index isn't persisted, so build it for this run
+            let json = format == "json";
+            if !json {
+                console.info(&format!("Indexing {}...", search_dir));
+            }
+            let mut progress = ui::Progress::new("Indexing");
+            let stats = search
+                .index_directory_with_progress(Path::new(search_dir), &mut |done, total| {
+                    progress.update(done, total)
+                })
+                .await?;
+            progress.finish();
+            if !json {
+                console.info(&format!(
+                    "Indexed {} files, {} chunks{}",
+                    stats.files,
+                    stats.chunks,
+                    throttle_note(stats.throttled)
+                ));
+            }
+
+            let pairs = search.find_duplicates(threshold, top);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&pairs)?);
+            } else if pairs.is_empty() {
+                console.success(&format!("No duplicates above {:.2} similarity", threshold));
             } else {
-                console.warn("OPENAI_API_KEY not set, using mock embeddings");
-                SemanticSearch::new_mock(config)
-            };
+                for (i, pair) in pairs.iter().enumerate() {
+                    println!("\n{}. similarity {:.3}", i + 1, pair.score);
+                    println!("   {}", pair.a);
+                    println!("   {}", pair.b);
+                }
+            }
+        }
+        Some(Commands::Embeddings { command }) => match command {
+            cli::EmbeddingsCommands::Similarity { a, b, mock, json } => {
+                use embeddings::{EmbeddingProvider, MockEmbeddingProvider};
+
+                let provider: std::sync::Arc<dyn EmbeddingProvider> = if mock {
+                    std::sync::Arc::new(MockEmbeddingProvider::new(384))
+                } else {
+                    let (provider, fallback) = embeddings::default_provider(&settings);
+                    if let Some(reason) = fallback {
+                        console.warn(&format!("{}, using mock embeddings", reason));
+                    }
+                    provider
+                };
+
+                let result = embeddings::compare_texts(provider.as_ref(), &a, &b).await?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&result)?);
+                } else {
+                    println!("Similarity: {:.4}", result.similarity);
+                    println!("Dimension:  {}", result.dimension);
+                    println!("Model:      {}", result.model);
+                }
+            }
+        },
+        Some(Commands::Index { dir, export: Some(output), qdrant, yes, .. }) => {
+            let written = export_index(&console, dir.as_deref().unwrap_or("."), &output, qdrant.as_deref(), yes).await?;
+            if let Some(written) = written {
+                console.success(&format!("Exported {} chunks to {}", written, output));
+            }
+        }
+        Some(Commands::Index { dir, json, .. }) => {
+            use skills::{SemanticSearch, SemanticSearchConfig};
+            use std::path::Path;
+
+            let search_dir = dir.as_deref().unwrap_or(".");
+            let config = SemanticSearchConfig::default();
+
+            // With --json, stdout holds only the JSON
+            let (provider, mock) = embeddings::default_provider(&settings);
+            if let Some(reason) = mock {
+                let message = format!("{}, using mock embeddings", reason);
+                if json {
+                    eprintln!("Warning: {}", message);
+                } else {
+                    console.warn(&message);
+                }
+            }
+            let mut search = SemanticSearch::with_provider(provider, config)
+                .with_file_results(json || Verbosity::current() == Verbosity::Verbose);
 
-            console.info(&format!("Indexing {}...", search_dir));
-            let stats = search.index_directory(Path::new(search_dir)).await?;
+            // Continue from the last run so unchanged and moved files are not re-embedded
+            let index_path = skills::project_index_path(Path::new(search_dir));
+            if let Some(path) = index_path.as_ref().filter(|path| path.exists()) {
+                if let Err(e) = search.load(path) {
+                    tracing::warn!("Ignoring saved index {}: {}", path.display(), e);
+                }
+            }
+
+            if !json {
+                console.info(&format!("Indexing {}...", search_dir));
+            }
+            let mut progress = ui::Progress::new("Indexing");
+            let stats = search
+                .index_directory_with_progress(Path::new(search_dir), &mut |done, total| {
+                    progress.update(done, total)
+                })
+                .await?;
+            progress.finish();
+            if let Some(path) = &index_path {
+                search.save(path)?;
+            }
+            if json {
+                let output = serde_json::json!({
+                    "files": stats.files,
+                    "chunks": stats.chunks,
+                    "skipped": stats.skipped,
+                    "moved": stats.moved,
+                    "removed": stats.removed,
+                    "errors": stats.errors,
+                    "throttled_secs": stats.throttled.as_secs(),
+                    "results": stats.file_results.unwrap_or_default(),
+                });
+                println!("{}", serde_json::to_string_pretty(&output)?);
+                return Ok(());
+            }
+            for result in stats.file_results.iter().flatten() {
+                println!("  {}", result);
+            }
             console.info(&format!(
-                "Done! Indexed {} files, {} chunks ({} skipped, {} errors)",
-                stats.files, stats.chunks, stats.skipped, stats.errors
+                "Done! Indexed {} files, {} chunks ({} skipped, {} errors){}",
+                stats.files, stats.chunks, stats.skipped, stats.errors,
+                throttle_note(stats.throttled)
             ));
+            console.info(&stats.file_changes());
+            if stats.errors > 0 && stats.file_results.is_none() {
+                console.info("Run with --verbose to see which files failed and why.");
+            }
         }
         Some(Commands::Scan {
             dir,
             format,
             min_severity,
             fail_on_secrets,
+            fail_on,
+            exit_zero,
+            quiet_clean,
+            save_output,
+            stream,
+            git_history,
+            since,
+            resume,
         }) => {
-            use core::{ScanSummary, ScannerConfig, SecretScanner, SecretSeverity};
+            use core::{scan_fails, ScanSummary, ScannerConfig, SecretScanner, SecretSeverity};
             use std::path::Path;
 
             let scan_dir = dir.as_deref().unwrap_or(".");
             
             // Parse minimum severity
-            let min_sev = match min_severity.to_lowercase().as_str() {
-                "low" => SecretSeverity::Low,
-                "medium" => SecretSeverity::Medium,
-                "high" => SecretSeverity::High,
-                "critical" => SecretSeverity::Critical,
-                _ => {
+            let min_sev = match min_severity.parse::<SecretSeverity>() {
+                Ok(severity) => severity,
+                Err(_) => {
                     console.error("Invalid severity. Use: low, medium, high, critical");
                     return Ok(());
                 }
             };
+            let fail_on = fail_on.map(|s| s.parse::<SecretSeverity>()).transpose()?;
 
+            // Findings below --min-severity still count towards --fail-on
             let config = ScannerConfig {
-                min_severity: min_sev,
+                min_severity: fail_on.map_or(min_sev, |threshold| threshold.min(min_sev)),
                 ..Default::default()
             };
 
             let scanner = SecretScanner::new(config);
+
+            if git_history {
+                if stream {
+                    anyhow::bail!("--stream cannot be combined with --git-history");
+                }
+                if format != "json" && !quiet_clean {
+                    console.info(&format!("Scanning the git history of {} for secrets...", scan_dir));
+                }
+                let mut scan = core::scan_git_history(&scanner, Path::new(scan_dir), since.as_deref())?;
+                let all: Vec<_> = scan.findings.iter().map(|f| f.secret.clone()).collect();
+                let threshold_failed = scan_fails(&all, false, fail_on);
+                scan.findings.retain(|f| f.secret.severity >= min_sev);
+                let secrets: Vec<_> = scan.findings.iter().map(|f| f.secret.clone()).collect();
+                let failed = threshold_failed || scan_fails(&secrets, fail_on_secrets, None);
+                let summary = ScanSummary::from_secrets(&secrets);
+
+                let report = if format == "json" {
+                    let output = serde_json::json!({
+                        "commits_scanned": scan.commits,
+                        "summary": summary,
+                        "findings": scan.findings,
+                    });
+                    format!("{}\n", serde_json::to_string_pretty(&output)?)
+                } else {
+                    core::secret_history::history_text_report(&scan, &summary, ui::is_a11y())
+                };
+
+                if let Some(path) = &save_output {
+                    let written = save_output_file(path, &report)?;
+                    if !(quiet_clean && secrets.is_empty()) {
+                        console.info(&format!(
+                            "{} secret(s) found in {} commit(s); results written to {}",
+                            summary.total_secrets,
+                            scan.commits,
+                            written.display()
+                        ));
+                    }
+                } else if !(quiet_clean && secrets.is_empty()) {
+                    print!("{}", report);
+                }
+
+                if failed && !exit_zero {
+                    telemetry::exit(1);
+                }
+                return Ok(());
+            }
+
+            if stream {
+                if format != "json" {
+                    anyhow::bail!("--stream needs --format json");
+                }
+                let failed = scan_streaming(
+                    &scanner,
+                    Path::new(scan_dir),
+                    save_output.as_deref(),
+                    !quiet_clean,
+                    min_sev,
+                    fail_on_secrets,
+                    fail_on,
+                )?;
+                if failed && !exit_zero {
+                    telemetry::exit(1);
+                }
+                return Ok(());
+            }
             
-            console.info(&format!("Scanning {} for secrets...", scan_dir));
+            if format != "json" && !quiet_clean {
+                console.info(&format!("Scanning {} for secrets...", scan_dir));
+            }
             
-            let secrets = scanner.scan_directory(Path::new(scan_dir))?;
+            let secrets = if resume {
+                use core::scan_checkpoint::{self, ResumeFrom};
+                let checkpoint = scan_checkpoint::default_path(Path::new(scan_dir));
+                let mut progress = (!quiet_clean).then(|| ui::Progress::new("Scanning"));
+                let secrets = scan_checkpoint::scan_directory_resumable(
+                    &scanner,
+                    Path::new(scan_dir),
+                    &checkpoint,
+                    &mut |from| match from {
+                        ResumeFrom::Checkpoint(done) if !quiet_clean => {
+                            console.info(&format!("Resuming: {} file(s) already scanned", done))
+                        }
+                        ResumeFrom::Stale if !quiet_clean => {
+                            console.warn("Files or settings changed since the last checkpoint; starting over")
+                        }
+                        _ => {}
+                    },
+                    &mut |done, total| {
+                        if let Some(progress) = progress.as_mut() {
+                            progress.update(done, Some(total));
+                        }
+                        Ok(())
+                    },
+                )?;
+                if let Some(progress) = progress.as_mut() {
+                    progress.finish();
+                }
+                secrets
+            } else if quiet_clean {
+                scanner.scan_directory(Path::new(scan_dir))?
+            } else {
+                let mut progress = ui::Progress::new("Scanning");
+                let secrets = scanner
+                    .scan_directory_with_progress(Path::new(scan_dir), &mut |files| {
+                        progress.update(files, None)
+                    })?;
+                progress.finish();
+                secrets
+            };
+            let threshold_failed = scan_fails(&secrets, false, fail_on);
+            let secrets: Vec<_> = secrets.into_iter().filter(|s| s.severity >= min_sev).collect();
+            let failed = threshold_failed || scan_fails(&secrets, fail_on_secrets, None);
             let summary = ScanSummary::from_secrets(&secrets);
+            record_scan(Path::new(scan_dir), &summary);
 
-            if format == "json" {
-                println!("{}", serde_json::to_string_pretty(&secrets)?);
+            let report = if format == "json" {
+                let output = serde_json::json!({
+                    "summary": summary,
+                    "findings": secrets,
+                });
+                format!("{}\n", serde_json::to_string_pretty(&output)?)
             } else {
-                if secrets.is_empty() {
-                    console.success("No secrets detected!");
-                } else {
-                    println!("\n{} secrets found:\n", secrets.len());
-                    
-                    for secret in &secrets {
-                        let severity_icon = match secret.severity {
-                            SecretSeverity::Critical => "🔴 CRITICAL",
-                            SecretSeverity::High => "🟠 HIGH",
-                            SecretSeverity::Medium => "🟡 MEDIUM",
-                            SecretSeverity::Low => "🟢 LOW",
-                        };
-                        
-                        println!(
-                            "{}: {}:{}\n   Type: {}\n   Match: {}\n",
-                            severity_icon,
-                            secret.file,
-                            secret.line,
-                            secret.secret_type.description(),
-                            secret.matched_text
-                        );
+                core::secrets::text_report(&secrets, &summary)
+            };
+
+            if let Some(path) = &save_output {
+                let written = save_output_file(path, &report)?;
+                if !(quiet_clean && secrets.is_empty()) {
+                    console.info(&format!(
+                        "{} secret(s) found in {} file(s); results written to {}",
+                        summary.total_secrets,
+                        summary.files_with_secrets,
+                        written.display()
+                    ));
+                }
+            } else if quiet_clean && secrets.is_empty() {
+                // Clean: no output at all
+            } else if format != "json" && secrets.is_empty() {
+                console.success("No secrets detected!");
+            } else {
+                print!("{}", report);
+            }
+
+            if failed && !exit_zero {
+                telemetry::exit(1);
+            }
+        }
+        Some(Commands::New {
+            template,
+            name,
+            vars,
+            force,
+            dry_run,
+            skip_checks,
+        }) => {
+            use scaffold::{parse_vars, RunReport, ScaffoldOptions, ScaffoldTemplate, Scaffolder, WorkdirBoundary};
+
+            let name = name.unwrap_or_else(|| template.clone());
+            let dest = std::env::current_dir()?.join(&name);
+            let options = ScaffoldOptions {
+                vars: parse_vars(&vars)?,
+                force,
+                dry_run,
+                skip_checks,
+            };
+            let scaffolder = Scaffolder::new(&dest, &name, options);
+
+            let report: RunReport = match ScaffoldTemplate::builtin(&template) {
+                Some(builtin) => {
+                    console.info(&format!("Generating {} into {}", builtin.name, dest.display()));
+                    let report = scaffolder.generate(&builtin)?;
+                    if report.dry_run {
+                        for diff in scaffolder.overwrite_diffs(&builtin, util::diff::DiffStyle::terminal())? {
+                            print!("{}", diff);
+                        }
+                    }
+                    report
+                }
+                None => {
+                    scaffolder.check_destination()?;
+                    if dry_run {
+                        console.info(&format!(
+                            "'{}' is not a built-in template ({}); the agent would create the files in {}",
+                            template,
+                            ScaffoldTemplate::builtin_names().join(", "),
+                            dest.display()
+                        ));
+                        return Ok(());
                     }
 
-                    println!("Summary:");
-                    println!("  Files with secrets: {}", summary.files_with_secrets);
-                    println!("  Total secrets: {}", summary.total_secrets);
-                    
-                    for (severity, count) in &summary.by_severity {
-                        println!("  {}: {}", severity, count);
+                    console.info(&format!("No built-in template '{}', using the agent", template));
+                    std::fs::create_dir_all(&dest)?;
+                    let previous_dir = std::env::current_dir()?;
+                    std::env::set_current_dir(&dest)?;
+
+                    let result = async {
+                        let orchestrator = Orchestrator::new(settings, true)
+                            .await?
+                            .with_boundary(WorkdirBoundary::new(&dest));
+                        orchestrator
+                            .run_autonomous(&scaffolder.agent_prompt(&template), 25, false)
+                            .await
                     }
+                    .await;
+
+                    std::env::set_current_dir(previous_dir)?;
+                    result?;
+                    scaffolder.finish_agent_run(&template)?
                 }
+            };
+
+            if report.dry_run {
+                println!("Files that would be created in {}:", report.destination);
+            } else {
+                println!("\nCreated {} files in {}:", report.files.len(), report.destination);
+            }
+            for file in &report.files {
+                println!("  {}", file);
             }
 
-            if fail_on_secrets && !secrets.is_empty() {
-                std::process::exit(1);
+            if !report.checks.is_empty() {
+                println!("\nChecklist:");
+                for check in &report.checks {
+                    let status = if check.skipped {
+                        "SKIP"
+                    } else if check.passed {
+                        "PASS"
+                    } else {
+                        "FAIL"
+                    };
+                    let command = check.command.as_deref().unwrap_or("-");
+                    println!("  [{}] {} ({})", status, check.check, command);
+                    if !check.passed && !check.skipped {
+                        for line in check.output.lines().take(10) {
+                            println!("      {}", line);
+                        }
+                    }
+                }
+            }
+
+            if !report.dry_run {
+                console.info(&format!("Report written to {}", dest.join(scaffold::REPORT_FILE).display()));
+            }
+            if !report.passed() {
+                console.error("Some checklist items failed");
+                telemetry::exit(1);
             }
         }
         Some(Commands::Plugin { command }) => {
@@ -589,6 +1532,30 @@ async fn main() -> Result<()> {
                         }
                     }
                 }
+                cli::PluginCommands::Pack { plugin, output } => {
+                    let dir = if Path::new(&plugin).is_dir() {
+                        Path::new(&plugin).to_path_buf()
+                    } else if let Some(installed) = manager.get(&plugin) {
+                        installed.install_path.clone()
+                    } else {
+                        anyhow::bail!("{} is neither a plugin directory nor an installed plugin", plugin);
+                    };
+                    let output = match output {
+                        Some(output) => std::path::PathBuf::from(output),
+                        None => {
+                            let (manifest, _) = plugins::read_manifest(&dir)?;
+                            format!("{}-{}.{}", manifest.id, manifest.version, plugins::ARCHIVE_EXTENSION).into()
+                        }
+                    };
+                    let manifest = plugins::pack(&dir, &output)?;
+                    console.success(&format!(
+                        "Packed {} v{} into {}",
+                        manifest.name,
+                        manifest.version,
+                        output.display()
+                    ));
+                    println!("Install it with: webrana plugin install {}", output.display());
+                }
                 cli::PluginCommands::Uninstall { plugin_id } => {
                     if manager.uninstall(&plugin_id)? {
                         console.success(&format!("Uninstalled {}", plugin_id));
@@ -610,6 +1577,55 @@ async fn main() -> Result<()> {
                         console.error(&format!("Plugin {} not found", plugin_id));
                     }
                 }
+                cli::PluginCommands::New { name, skill } => {
+                    let (dir, files) = plugins::scaffold_plugin(Path::new("."), &name, &skill)?;
+                    console.success(&format!("Created plugin project {}", dir.display()));
+                    for file in files {
+                        println!("  {}", file);
+                    }
+                    println!("\nBuild and install it with:");
+                    println!("  cd {}", name);
+                    println!("  cargo build --target wasm32-unknown-unknown --release");
+                    println!(
+                        "  cp target/wasm32-unknown-unknown/release/{}.wasm plugin.wasm",
+                        name.replace('-', "_")
+                    );
+                    println!("  webrana plugin install .");
+                }
+                cli::PluginCommands::Run { plugin_id, skill, params } => {
+                    let params: serde_json::Value = serde_json::from_str(&params)
+                        .map_err(|e| anyhow::anyhow!("--params is not valid JSON: {}", e))?;
+                    match manager.get(&plugin_id) {
+                        None => anyhow::bail!("Plugin {} is not installed", plugin_id),
+                        Some(plugin) if !plugin.config.enabled => {
+                            anyhow::bail!("Plugin {} is disabled", plugin_id)
+                        }
+                        Some(_) => {}
+                    }
+
+                    let mut runtime = plugins::PluginRuntime::new();
+                    runtime.add_plugin_dir(manager.plugins_dir());
+                    runtime.init()?;
+                    runtime.load_plugin(&plugin_id)?;
+                    let output = runtime.execute_skill(&plugin_id, &skill, params)?;
+                    for log in &output.logs {
+                        eprintln!("[{}] {}", plugin_id, log);
+                    }
+                    println!("{}", serde_json::to_string_pretty(&output.result)?);
+                    let artifacts = std::path::Path::new(llm::images::ARTIFACTS_DIR);
+                    for (index, image) in output.images.iter().enumerate() {
+                        let saved = image
+                            .validate()
+                            .and_then(|_| image.save(artifacts, &format!("{}-{}-{}", plugin_id, skill, index + 1)));
+                        match saved {
+                            Ok(path) => eprintln!("[{}] image {}: {}", plugin_id, image.label(), path.display()),
+                            Err(e) => eprintln!("[{}] image {} not saved: {}", plugin_id, image.label(), e),
+                        }
+                    }
+                    if !output.success {
+                        anyhow::bail!("Skill {} of {} failed", skill, plugin_id);
+                    }
+                }
                 cli::PluginCommands::Info { plugin_id } => {
                     if let Some(plugin) = manager.get(&plugin_id) {
                         println!("\nPlugin: {}", plugin.manifest.name);
@@ -650,60 +1666,63 @@ async fn main() -> Result<()> {
             #[cfg(not(feature = "tui"))]
             println!("  - TUI: disabled");
         }
-        Some(Commands::Doctor) => {
-            println!("Webrana CLI - System Check\n");
-            
-            // Check config
-            print!("Configuration... ");
-            if settings.get_model(&settings.default_model).is_some() {
-                println!("OK (model: {})", settings.default_model);
-            } else {
-                println!("WARN (no default model)");
-            }
+        Some(Commands::Doctor { json, fix, check_network }) => {
+            use core::doctor::{network_checks, render_text, security_checks, system_checks, DoctorFix};
 
-            // Check API keys
-            print!("OpenAI API Key... ");
-            if std::env::var("OPENAI_API_KEY").is_ok() {
-                println!("OK");
-            } else {
-                println!("NOT SET");
-            }
+            let data_dir = directories::ProjectDirs::from("dev", "webrana", "webrana-cli")
+                .map(|dirs| dirs.data_dir().to_path_buf());
+            let project = std::env::current_dir()?;
+            let run_checks = || {
+                let mut checks = system_checks(&settings, data_dir.as_deref());
+                checks.extend(security_checks(&settings, data_dir.as_deref(), &project, unix_now()));
+                checks
+            };
+            let mut checks = run_checks();
+            let network = if check_network { network_checks(&settings).await } else { Vec::new() };
+            checks.extend(network.iter().cloned());
 
-            print!("Anthropic API Key... ");
-            if std::env::var("ANTHROPIC_API_KEY").is_ok() {
-                println!("OK");
-            } else {
-                println!("NOT SET");
+            if json {
+                println!("{}", serde_json::to_string_pretty(&checks)?);
+                return Ok(());
             }
 
-            // Check git
-            print!("Git... ");
-            match std::process::Command::new("git").arg("--version").output() {
-                Ok(output) => {
-                    let version = String::from_utf8_lossy(&output.stdout);
-                    println!("OK ({})", version.trim());
+            println!("Webrana CLI - System Check");
+            print!("{}", render_text(&checks));
+
+            if fix && checks.iter().any(|c| c.fix == Some(DoctorFix::RunScan)) {
+                println!();
+                if console.confirm("Run a secret scan of this project now?") {
+                    let scanner = core::SecretScanner::new(core::ScannerConfig::default());
+                    let secrets = scanner.scan_directory(&project)?;
+                    let summary = core::ScanSummary::from_secrets(&secrets);
+                    record_scan(&project, &summary);
+                    console.info(&format!(
+                        "{} secret(s) found in {} file(s); run `webrana scan` for details",
+                        summary.total_secrets, summary.files_with_secrets
+                    ));
+                    checks = run_checks();
+                    checks.extend(network.iter().cloned());
+                    print!("{}", render_text(&checks));
                 }
-                Err(_) => println!("NOT FOUND"),
             }
 
-            // Check plugins directory
-            print!("Plugins directory... ");
-            let plugins_dir = directories::ProjectDirs::from("dev", "webrana", "webrana-cli")
-                .map(|dirs| dirs.data_dir().join("plugins"));
-            if let Some(dir) = plugins_dir {
-                if dir.exists() {
-                    println!("OK ({})", dir.display());
-                } else {
-                    println!("OK (will be created: {})", dir.display());
+            if fix && checks.iter().any(|c| c.fix == Some(DoctorFix::RepairCrews)) {
+                println!();
+                let mut manager = crew::CrewManager::new()?;
+                let report = manager.repair()?;
+                for (path, error) in &report.broken {
+                    console.warn(&format!("Moved unreadable crew file to {}: {}", path.display(), error));
                 }
-            } else {
-                println!("WARN (using .webrana/plugins)");
+                if let Some(active) = &report.cleared_active {
+                    console.info(&format!("Cleared active crew '{}': it no longer exists", active));
+                }
+                console.success(&format!("Crew storage repaired: {} crew(s) loaded", report.crews));
             }
 
             println!("\nAll checks complete.");
         }
-        Some(Commands::Update) => {
-            use core::updater::{check_for_updates, format_update_message, UpdateStatus};
+        Some(Commands::Update { install }) => {
+            use core::updater::{check_for_updates, format_update_message, install_latest, UpdateStatus};
 
             console.info("Checking for updates...");
 
@@ -716,47 +1735,124 @@ async fn main() -> Result<()> {
                     println!("  Current: v{}", current);
                     println!("  Latest:  v{}", latest);
                     println!("\nDownload: {}", url);
-                    println!("\nTo update, download the latest release and replace the binary.");
+                    if install {
+                        match install_latest().await {
+                            Ok(version) => console.success(&format!(
+                                "Installed v{}; it takes effect the next time webrana starts.",
+                                version
+                            )),
+                            Err(e) => console.error(&format!("Update failed: {:#}", e)),
+                        }
+                    } else {
+                        println!("\nRun `webrana update --install` to replace the binary.");
+                    }
                 }
                 UpdateStatus::CheckFailed(err) => {
                     console.error(&format!("Failed to check for updates: {}", err));
                 }
+                status @ UpdateStatus::Offline => console.info(&format_update_message(&status)),
+            }
+        }
+        Some(Commands::Mr { url, number, json }) => {
+            let target = url.or(number.map(|n| n.to_string())).unwrap_or_default();
+            let mr = vcs::MrRef::resolve(&target, &settings).await?;
+            let context = mr.provider(&settings)?.review_context(mr.number).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&context)?);
+            } else {
+                print!("{}", context.render());
+            }
+        }
+        Some(Commands::Usage { since, by, format }) => {
+            let group = llm::UsageGroup::parse(&by)?;
+            let ledger = llm::UsageLedger::open_default()
+                .ok_or_else(|| anyhow::anyhow!("No data directory for the usage ledger"))?;
+            let summary = ledger.summarize(since.as_deref(), group)?;
+            if format == "json" {
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            } else {
+                print!("{}", summary.render(group));
+            }
+        }
+        Some(Commands::Digest { since, format }) => {
+            let now = unix_now();
+            let since = core::audit::since_cutoff(&since, now)?;
+            let sources = core::digest::Sources::default_for(&std::env::current_dir()?)
+                .ok_or_else(|| anyhow::anyhow!("No data directory to read activity from"))?;
+            let digest = core::digest::build(&sources, since, now)?;
+            if format == "json" {
+                println!("{}", serde_json::to_string_pretty(&digest)?);
+            } else {
+                print!("{}", digest.render_markdown());
             }
         }
+        Some(Commands::Undo { run, session, file, interactive }) => {
+            run_undo(&console, run, session, file, interactive)?;
+        }
         Some(Commands::Status) => {
-            use llm::webrana::WebranaProvider;
-
-            console.info("Checking Webrana API status...");
+            use llm::webrana::{StatusResponse, WebranaProvider};
 
-            match WebranaProvider::get_status().await {
-                Ok(status) => {
-                    println!("\n📊 Webrana API Status\n");
-                    println!("  Tier: {}", status.tier.to_uppercase());
-                    println!();
-                    println!("  Requests today: {}/{}", status.usage.requests_today, status.usage.requests_limit);
+            let print_status = |status: &StatusResponse, note: Option<String>| {
+                match note {
+                    Some(note) => println!("\n{} ({})\n", ui::plain_text("📊 Webrana API Status"), note),
+                    None => println!("\n{}\n", ui::plain_text("📊 Webrana API Status")),
+                }
+                println!("  Tier: {}", status.tier.to_uppercase());
+                println!();
+                println!("  Requests today: {}/{}", status.usage.requests_today, status.usage.requests_limit);
+                if ui::is_a11y() {
+                    println!("  Tokens today: {} of {}", status.usage.tokens_today, status.usage.tokens_limit);
+                } else {
                     println!("  Tokens today:   {}/{}", status.usage.tokens_today, status.usage.tokens_limit);
-                    println!();
-                    println!("  Resets at: {}", status.resets_at);
-                    
-                    // Progress bar for requests
-                    let pct = (status.usage.requests_today as f32 / status.usage.requests_limit as f32 * 100.0) as i32;
-                    let filled = pct / 5;
-                    let empty = 20 - filled;
-                    println!();
-                    println!("  Usage: [{}{}] {}%", 
-                        "█".repeat(filled as usize), 
+                }
+                println!();
+                println!("  Resets at: {}", status.resets_at);
+
+                // Progress bar for requests
+                let pct = (status.usage.requests_today as f32 / status.usage.requests_limit as f32 * 100.0) as i32;
+                let filled = pct / 5;
+                let empty = 20 - filled;
+                println!();
+                if ui::is_a11y() {
+                    println!("  Usage: {}% of today's request limit", pct);
+                } else {
+                    println!("  Usage: [{}{}] {}%",
+                        "█".repeat(filled as usize),
                         "░".repeat(empty as usize),
                         pct
                     );
                 }
-                Err(e) => {
-                    console.error(&format!("Failed to get status: {}", e));
+            };
+
+            if util::http::is_offline() {
+                match (WebranaProvider::cached_status(), WebranaProvider::get_credentials()) {
+                    (Some(cached), _) => print_status(&cached.status, Some(cached.note())),
+                    (None, Some(credentials)) => console.info(&format!(
+                        "Offline: logged in as device {} (tier {} when last checked); usage needs the network",
+                        credentials.device_id, credentials.tier
+                    )),
+                    (None, None) => console.info("Offline: not logged in to the Webrana API"),
                 }
+                return Ok(());
+            }
+
+            console.info("Checking Webrana API status...");
+
+            match WebranaProvider::get_status().await {
+                Ok(status) => print_status(&status, None),
+                Err(e) => match WebranaProvider::cached_status() {
+                    Some(cached) => {
+                        console.warn(&format!("Failed to get status: {}", e));
+                        print_status(&cached.status, Some(cached.note()));
+                    }
+                    None => console.error(&format!("Failed to get status: {}", e)),
+                },
             }
         }
         Some(Commands::Login) => {
             use llm::webrana::WebranaProvider;
 
+            util::http::ensure_online("Logging in to the Webrana API")?;
             console.info("Registering device with Webrana API...");
 
             // Clear existing credentials first
@@ -792,6 +1888,7 @@ async fn main() -> Result<()> {
         }
         None => {
             let orchestrator = Orchestrator::new(settings, cli.auto).await?;
+            console.session_header(&orchestrator.session_header());
             orchestrator.repl().await?;
         }
     }
@@ -799,12 +1896,346 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-fn init_tracing() {
-    tracing_subscriber::registry()
+/// The `webrana-memory` index, embedded with `embeddings::default_provider`
+fn open_conversation_memory(console: &Console, settings: &Settings) -> Result<memory::ConversationMemory> {
+    let path = memory::default_index_path()
+        .ok_or_else(|| anyhow::anyhow!("No data directory for the conversation memory"))?;
+    let (provider, mock) = embeddings::default_provider(settings);
+    if let Some(reason) = mock {
+        console.warn(&format!("{}, using mock embeddings", reason));
+    }
+    memory::ConversationMemory::open(&path, provider)
+}
+
+/// Project whose sessions memory commands see; None with --global-memory
+fn memory_scope(settings: &Settings, root: &std::path::Path) -> Option<String> {
+    (!settings.conversation_memory.global).then(|| root.display().to_string())
+}
+
+/// `--json-stream`: one turn as NDJSON events on stdout, ending in `done`
+/// or, if setup or the turn fails, `error`
+async fn json_stream_turn(
+    settings: Settings,
+    auto: bool,
+    allow: &[String],
+    deny: &[String],
+    message: &str,
+) -> Result<()> {
+    llm::events::start(Box::new(std::io::stdout()));
+    let mut provenance = None;
+    let result = async {
+        let orchestrator = Orchestrator::new(settings, auto).await?.with_skill_overrides(allow, deny)?;
+        let result = orchestrator.chat_events(message).await;
+        provenance = orchestrator.last_provenance();
+        result
+    }
+    .await;
+    llm::events::finish(&result, provenance.as_ref());
+    result
+}
+
+/// `webrana ask --compare A B`: the prompt under both system prompt files,
+/// with one seed (picked at random unless `--seed` is given)
+async fn ask_compare(
+    console: &Console,
+    mut settings: Settings,
+    files: &[String],
+    prompt: &str,
+    json: bool,
+    diff: bool,
+) -> Result<()> {
+    use anyhow::Context;
+
+    let seed = settings.sampling.seed.unwrap_or_else(|| uuid::Uuid::new_v4().as_u128() as u64 % 1_000_000);
+    settings.sampling.seed = Some(seed);
+
+    let mut system_prompts = Vec::with_capacity(files.len());
+    for file in files {
+        let text = std::fs::read_to_string(file).with_context(|| format!("Failed to read system prompt {}", file))?;
+        system_prompts.push((file.clone(), text));
+    }
+
+    if !json {
+        console.info(&format!("Comparing {} and {} with seed {}", files[0], files[1], seed));
+    }
+    let llm = llm::LlmClient::new(&settings).await?;
+    let variants = llm::compare::compare_system_prompts(&llm, &system_prompts, prompt).await?;
+
+    if json {
+        let output = serde_json::json!({ "query": prompt, "seed": seed, "variants": variants });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if diff {
+        print!("{}", llm::compare::answer_diff(&variants[0], &variants[1], util::diff::DiffStyle::terminal()));
+    } else {
+        let width = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()).unwrap_or(120);
+        print!("{}", llm::compare::side_by_side(&variants[0], &variants[1], width));
+    }
+    if !json {
+        for variant in &variants {
+            if let Some(provenance) = &variant.provenance {
+                println!("{}: {}", variant.label, provenance.suffix().trim_start_matches("— "));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the `.webrana/last-scan.json` stamp read by `webrana doctor`
+fn record_scan(dir: &std::path::Path, summary: &core::ScanSummary) {
+    if let Err(e) = core::ScanStamp::new(summary, unix_now()).save(dir) {
+        tracing::debug!("Could not write scan stamp in {}: {}", dir.display(), e);
+    }
+}
+
+/// Write a `--save-output` report, creating parent directories
+fn save_output_file(path: &str, content: &str) -> Result<std::path::PathBuf> {
+    let path = std::path::PathBuf::from(path);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, content)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// `scan --format json --stream`: findings go out as they are found, the
+/// summary goes to stderr. Returns whether the scan failed its thresholds.
+fn scan_streaming(
+    scanner: &core::SecretScanner,
+    dir: &std::path::Path,
+    save_output: Option<&str>,
+    show_progress: bool,
+    min_severity: core::SecretSeverity,
+    fail_on_secrets: bool,
+    fail_on: Option<core::SecretSeverity>,
+) -> Result<bool> {
+    let out: Box<dyn std::io::Write> = match save_output {
+        Some(path) => {
+            let path = std::path::Path::new(path);
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
+            }
+            let file = std::fs::File::create(path)
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+            Box::new(std::io::BufWriter::new(file))
+        }
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let mut writer = ui::JsonArrayWriter::new(out)?;
+    let mut summary = core::ScanSummary::default();
+    let mut threshold_failed = false;
+    let mut progress = show_progress.then(|| ui::Progress::new("Scanning"));
+
+    scanner.scan_directory_streaming(
+        dir,
+        &mut |files| {
+            if let Some(progress) = progress.as_mut() {
+                progress.update(files, None)
+            }
+        },
+        &mut |secret| {
+            threshold_failed |= fail_on.is_some_and(|threshold| secret.severity >= threshold);
+            if secret.severity >= min_severity {
+                summary.add(&secret);
+                writer.push(&secret)?;
+            }
+            Ok(())
+        },
+    )?;
+    if let Some(progress) = progress.as_mut() {
+        progress.finish();
+    }
+    writer.finish()?;
+
+    record_scan(dir, &summary);
+    eprintln!("{}", serde_json::json!({ "summary": summary }));
+    Ok(threshold_failed || (fail_on_secrets && summary.total_secrets > 0))
+}
+
+/// Whether to go ahead with an export of about `bytes`: large ones ask
+/// first unless `yes`, and are refused when there is no one to ask
+fn confirm_export_size(console: &Console, bytes: u64, yes: bool) -> Result<bool> {
+    use embeddings::export::LARGE_EXPORT_BYTES;
+
+    if bytes < LARGE_EXPORT_BYTES || yes {
+        return Ok(true);
+    }
+    let warning = format!("The export will be about {} MB", bytes / (1024 * 1024));
+    if !atty::is(atty::Stream::Stdin) {
+        anyhow::bail!("{}; pass --yes to write it anyway", warning);
+    }
+    console.warn(&warning);
+    Ok(core::ConfirmationPrompt::confirm("Write it?"))
+}
+
+/// `webrana undo`: revert the changes of the run or session `id` (the
+/// latest when neither is given), recorded as a new undo
+fn run_undo(
+    console: &Console,
+    run: Option<String>,
+    session: Option<String>,
+    file: Option<String>,
+    interactive: bool,
+) -> Result<()> {
+    use colored::Colorize;
+    use core::undo::{self, Ask, JournalKind, Selection, UndoAction};
+
+    let root = std::env::current_dir()?;
+    let manifest = undo::load(&root, run.as_deref().or(session.as_deref()))?;
+    match (&manifest.kind, &run, &session) {
+        (JournalKind::Session, Some(_), _) => anyhow::bail!("{} is a session; use --session", manifest.id),
+        (JournalKind::Run | JournalKind::Undo { .. }, _, Some(_)) => {
+            anyhow::bail!("{} is a {}; use --run", manifest.id, manifest.kind.label())
+        }
+        _ => {}
+    }
+
+    let items = undo::plan(&root, &manifest);
+    if items.is_empty() {
+        console.info(&format!("Nothing to undo for {} {}", manifest.kind.label(), manifest.id));
+        return Ok(());
+    }
+    let describe = |item: &undo::UndoItem| {
+        let action = match item.action {
+            UndoAction::Restore => "restore",
+            UndoAction::Delete => "delete (created)",
+        };
+        let note = if item.modified_since { " — changed since, by someone else".yellow().to_string() } else { String::new() };
+        format!("  {} {}{}", action, item.path, note)
+    };
+
+    let selection = match (file, interactive) {
+        (Some(path), _) => Selection::File(path),
+        (None, true) => Selection::Interactive,
+        (None, false) => Selection::All,
+    };
+    let undo_id = skills::new_run_id();
+    let undone = undo::undo(&root, &manifest, &selection, &undo_id, &mut |ask| {
+        if !atty::is(atty::Stream::Stdin) {
+            anyhow::bail!("Undo needs confirmation; run it in a terminal");
+        }
+        Ok(match ask {
+            Ask::All(items) => {
+                println!("Undo {} {}:", manifest.kind.label(), manifest.id);
+                for item in items {
+                    println!("{}", describe(item));
+                }
+                core::ConfirmationPrompt::confirm(&format!("Revert these {} file(s)?", items.len()))
+            }
+            Ask::File { item, diff } => {
+                println!("\n{}", describe(item));
+                print!("{}", diff);
+                core::ConfirmationPrompt::confirm(&format!("Undo {}?", item.path))
+            }
+            Ask::Overwrite(item) => core::ConfirmationPrompt::confirm(&format!(
+                "{} changed after the {} finished; overwrite those changes?",
+                item.path,
+                manifest.kind.label()
+            )),
+        })
+    })?;
+
+    match undone {
+        Some(undone) => console.success(&format!(
+            "Reverted {} file(s); `webrana undo --run {}` puts them back",
+            undone.files.len(),
+            undone.id
+        )),
+        None => console.info("Nothing was undone"),
+    }
+    Ok(())
+}
+
+/// `webrana index --export`: the chunks of the project index under `dir`,
+/// or of a Qdrant collection, as JSON Lines. None when the user declined.
+async fn export_index(
+    console: &Console,
+    dir: &str,
+    output: &str,
+    collection: Option<&str>,
+    yes: bool,
+) -> Result<Option<usize>> {
+    use anyhow::Context;
+    use embeddings::export::{estimated_size, write_jsonl};
+
+    let create = || {
+        std::fs::File::create(output)
+            .map(std::io::BufWriter::new)
+            .with_context(|| format!("Failed to create {}", output))
+    };
+
+    if let Some(collection) = collection {
+        #[cfg(feature = "qdrant")]
+        {
+            let store = embeddings::QdrantStore::new(embeddings::QdrantConfig {
+                collection_name: collection.to_string(),
+                ..Default::default()
+            })
+            .await?;
+            let info = store.info().await?;
+            // Payload text size is unknown up front; assume a typical chunk
+            if !confirm_export_size(console, estimated_size(info.points_count as usize, info.vector_size as usize, 1_000), yes)? {
+                return Ok(None);
+            }
+            let mut out = create()?;
+            let (mut written, mut offset) = (0, None);
+            loop {
+                let (page, next) = store.scroll(offset, 256).await?;
+                written += write_jsonl(&page, &mut out)?;
+                match next {
+                    Some(next) => offset = Some(next),
+                    None => break,
+                }
+            }
+            return Ok(Some(written));
+        }
+        #[cfg(not(feature = "qdrant"))]
+        anyhow::bail!("Exporting the Qdrant collection '{}' needs a build with the `qdrant` feature", collection);
+    }
+
+    let path = skills::project_index_path(std::path::Path::new(dir))
+        .filter(|path| path.exists())
+        .ok_or_else(|| anyhow::anyhow!("No index for {}; run `webrana index` first", dir))?;
+    let store = embeddings::EmbeddingStore::load(&path)?;
+    let text_bytes = store.iter().map(|chunk| chunk.text.len()).sum::<usize>() / store.len().max(1);
+    if !confirm_export_size(console, estimated_size(store.len(), store.dimension(), text_bytes), yes)? {
+        return Ok(None);
+    }
+    Ok(Some(write_jsonl(store.iter(), &mut create()?)?))
+}
+
+/// ", throttled Ns" when indexing waited on the shared rate limit
+fn throttle_note(throttled: std::time::Duration) -> String {
+    if throttled.as_secs() == 0 {
+        String::new()
+    } else {
+        format!(", throttled {}s", throttled.as_secs())
+    }
+}
+
+fn init_tracing(settings: &Settings, verbosity: Verbosity) {
+    let rust_log = std::env::var(tracing_subscriber::EnvFilter::DEFAULT_ENV).ok();
+    let log_filter = tracing_subscriber::EnvFilter::try_new(logging::filter_directive(
+        verbosity,
+        rust_log.as_deref(),
+    ))
+    .unwrap_or_else(|_| verbosity.directive().into());
+    let registry = tracing_subscriber::registry()
         .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "webrana=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_ansi(!ui::is_plain())
+                .with_filter(log_filter),
+        );
+
+    // Exported spans don't depend on RUST_LOG
+    #[cfg(feature = "otel")]
+    let registry = registry.with(
+        telemetry::layer(settings.otel && !util::http::is_offline())
+            .with_filter(tracing_subscriber::filter::Targets::new().with_target("webrana", tracing::Level::INFO)),
+    );
+    #[cfg(not(feature = "otel"))]
+    let _ = settings;
+
+    registry.init();
 }
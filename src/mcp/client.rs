@@ -1,12 +1,35 @@
 //! MCP Client implementation
 
 use super::protocol::*;
-use anyhow::{anyhow, Result};
-use std::collections::HashMap;
+use crate::core::glob_match;
+use crate::util::egress::EgressGuard;
+use anyhow::{anyhow, Context, Result};
+use std::collections::{HashMap, HashSet};
 use std::io::{BufRead, BufReader, Write};
 use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a tool listing is used before the next call lists again
+const SCHEMA_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Which of a server's tools the session sees
+#[derive(Debug, Clone, Default)]
+pub struct ToolScope {
+    /// Name globs; empty allows every tool
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub max_tools: Option<usize>,
+}
+
+impl ToolScope {
+    /// Matches an include glob (or there are none) and no exclude glob
+    pub fn allows(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|glob| glob_match(glob, name)))
+            && !self.exclude.iter().any(|glob| glob_match(glob, name))
+    }
+}
 
 /// MCP Client for connecting to MCP servers
 pub struct McpClient {
@@ -15,22 +38,93 @@ pub struct McpClient {
     request_id: AtomicU64,
     server_info: Option<ServerInfo>,
     capabilities: Option<ServerCapabilities>,
+    /// Names and one-line descriptions; schemas live in `catalog`
     tools: Vec<McpTool>,
+    /// Input schemas from the last listing, by tool name
+    catalog: HashMap<String, serde_json::Value>,
+    /// Tools whose schemas are handed out, e.g. to the model's tool list
+    hydrated: HashSet<String>,
+    /// Scope and time of the last listing
+    listed: Option<(ToolScope, Instant)>,
 }
 
+type Handler = Box<dyn FnMut(McpRequest) -> McpResponse + Send>;
+
 enum Transport {
     Stdio(StdioTransport),
-    #[allow(dead_code)]
     Http(HttpTransport),
+    /// In-process server, for tests
+    #[allow(dead_code)]
+    Handler(Handler),
 }
 
 struct StdioTransport {
     process: Arc<Mutex<Child>>,
 }
 
+/// Streamable HTTP: each JSON-RPC message is a POST to `url`
 struct HttpTransport {
-    #[allow(dead_code)]
     url: String,
+    guard: EgressGuard,
+    /// Built on the first request, pinned to the addresses the guard checked
+    client: Option<reqwest::Client>,
+    /// `Mcp-Session-Id` the server assigned at initialization
+    session: Option<String>,
+}
+
+impl HttpTransport {
+    /// POST `message` and return the JSON-RPC response, if the server sent
+    /// one. Blocks on the async runtime like the stdio transport blocks on
+    /// the pipe.
+    fn post(&mut self, server: &str, message: &serde_json::Value) -> Result<Option<McpResponse>> {
+        let handle = tokio::runtime::Handle::try_current()
+            .map_err(|_| anyhow!("MCP over HTTP needs the async runtime"))?;
+        tokio::task::block_in_place(|| handle.block_on(self.post_async(server, message)))
+    }
+
+    async fn post_async(&mut self, server: &str, message: &serde_json::Value) -> Result<Option<McpResponse>> {
+        let client = match &self.client {
+            Some(client) => client.clone(),
+            None => {
+                let permit = self.guard.authorize(&self.url, &format!("MCP server {}", server)).await?;
+                let client = permit.client()?;
+                self.client = Some(client.clone());
+                client
+            }
+        };
+        let mut request = client
+            .post(&self.url)
+            .header(reqwest::header::ACCEPT, "application/json, text/event-stream")
+            .json(message);
+        if let Some(session) = &self.session {
+            request = request.header("Mcp-Session-Id", session);
+        }
+        let response = request
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("MCP server {} at {}", server, self.url))?;
+        if let Some(session) = response.headers().get("mcp-session-id").and_then(|v| v.to_str().ok()) {
+            self.session = Some(session.to_string());
+        }
+        let is_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|kind| kind.starts_with("text/event-stream"));
+        let body = response.text().await?;
+        if body.trim().is_empty() {
+            return Ok(None);
+        }
+        if !is_stream {
+            return Ok(Some(serde_json::from_str(&body)?));
+        }
+        // The response is the first `data:` event that parses as one
+        Ok(body
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .find_map(|data| serde_json::from_str(data.trim()).ok()))
+    }
 }
 
 impl McpClient {
@@ -53,24 +147,59 @@ impl McpClient {
             server_info: None,
             capabilities: None,
             tools: Vec::new(),
+            catalog: HashMap::new(),
+            hydrated: HashSet::new(),
+            listed: None,
         })
     }
 
-    /// Create a new MCP client connecting via HTTP
-    #[allow(dead_code)]
+    /// Create a new MCP client connecting via HTTP; the URL must pass
+    /// `safety.egress_allowlist`, and where it resolves is checked on the
+    /// first request
     pub fn new_http(name: &str, url: &str) -> Result<Self> {
+        Self::http(name, url, crate::util::egress::current())
+    }
+
+    fn http(name: &str, url: &str, guard: EgressGuard) -> Result<Self> {
+        let parsed = url::Url::parse(url).with_context(|| format!("Invalid MCP server URL '{}'", url))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            anyhow::bail!("MCP server URL '{}' must be http or https", url);
+        }
+        guard.check_url(&parsed, &format!("MCP server {}", name))?;
         Ok(Self {
             name: name.to_string(),
             transport: Transport::Http(HttpTransport {
                 url: url.to_string(),
+                guard,
+                client: None,
+                session: None,
             }),
             request_id: AtomicU64::new(1),
             server_info: None,
             capabilities: None,
             tools: Vec::new(),
+            catalog: HashMap::new(),
+            hydrated: HashSet::new(),
+            listed: None,
         })
     }
 
+    /// A client answered in-process by `handler`
+    #[allow(dead_code)]
+    pub fn with_handler(name: &str, handler: impl FnMut(McpRequest) -> McpResponse + Send + 'static) -> Self {
+        Self {
+            name: name.to_string(),
+            transport: Transport::Handler(Box::new(handler)),
+            request_id: AtomicU64::new(1),
+            server_info: None,
+            capabilities: None,
+            tools: Vec::new(),
+            catalog: HashMap::new(),
+            hydrated: HashSet::new(),
+            listed: None,
+        }
+    }
+
     /// Get the client name
     pub fn name(&self) -> &str {
         &self.name
@@ -81,11 +210,28 @@ impl McpClient {
         self.server_info.as_ref()
     }
 
-    /// Get available tools
+    /// Get available tools, without their input schemas
     pub fn tools(&self) -> &[McpTool] {
         &self.tools
     }
 
+    /// Available tools with the input schemas hydrated so far
+    pub fn tools_with_schemas(&self) -> Vec<McpTool> {
+        self.tools
+            .iter()
+            .map(|tool| McpTool { input_schema: self.schema(&tool.name).cloned(), ..tool.clone() })
+            .collect()
+    }
+
+    /// Input schema of `name`, once hydrated
+    pub fn schema(&self, name: &str) -> Option<&serde_json::Value> {
+        self.hydrated.get(name).and_then(|name| self.catalog.get(name))
+    }
+
+    pub fn is_hydrated(&self, name: &str) -> bool {
+        self.schema(name).is_some()
+    }
+
     /// Initialize the connection
     pub fn initialize(&mut self) -> Result<InitializeResult> {
         let params = InitializeParams {
@@ -111,15 +257,69 @@ impl McpClient {
         Ok(response)
     }
 
-    /// List available tools from the server
-    pub fn list_tools(&mut self) -> Result<Vec<McpTool>> {
-        let response: ListToolsResult = self.send_request("tools/list", None)?;
-        self.tools = response.tools.clone();
-        Ok(response.tools)
+    /// List the server's tools in `scope`, page by page. The tool list
+    /// keeps names and first description lines; schemas stay in the
+    /// catalog until `hydrate` hands them out.
+    pub fn list_tools(&mut self, scope: &ToolScope) -> Result<Vec<McpTool>> {
+        let limit = scope.max_tools.unwrap_or(usize::MAX);
+        let mut tools = Vec::new();
+        let mut catalog = HashMap::new();
+        self.each_tool(|tool| {
+            if tools.len() < limit && scope.allows(&tool.name) {
+                let description = tool.description.and_then(|d| d.lines().next().map(|line| line.trim().to_string()));
+                let schema = tool.input_schema.unwrap_or_else(|| serde_json::json!({"type": "object"}));
+                catalog.insert(tool.name.clone(), schema);
+                tools.push(McpTool { name: tool.name, description, input_schema: None });
+            }
+            tools.len() < limit
+        })?;
+        self.tools = tools.clone();
+        self.hydrated.retain(|name| catalog.contains_key(name));
+        self.catalog = catalog;
+        self.listed = Some((scope.clone(), Instant::now()));
+        Ok(tools)
+    }
+
+    /// Hand out the schemas of listed tools among `names`. No request is
+    /// made; the schemas come from the last listing.
+    pub fn hydrate(&mut self, names: &[&str]) {
+        for name in names {
+            if self.catalog.contains_key(*name) {
+                self.hydrated.insert(name.to_string());
+            }
+        }
+    }
+
+    /// List again when the last listing is older than `SCHEMA_TTL`
+    fn refresh_if_stale(&mut self) -> Result<()> {
+        match self.listed.clone() {
+            Some((scope, at)) if at.elapsed() >= SCHEMA_TTL => self.list_tools(&scope).map(|_| ()),
+            _ => Ok(()),
+        }
     }
 
-    /// Call a tool
+    /// Walk `tools/list` until `visit` returns false or the pages run out
+    fn each_tool(&mut self, mut visit: impl FnMut(McpTool) -> bool) -> Result<()> {
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = cursor.take().map(|cursor| serde_json::json!({ "cursor": cursor }));
+            let page: ListToolsResult = self.send_request("tools/list", params)?;
+            for tool in page.tools {
+                if !visit(tool) {
+                    return Ok(());
+                }
+            }
+            match page.next_cursor {
+                Some(next) if !next.is_empty() => cursor = Some(next),
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Call a tool, refreshing a stale listing first
     pub fn call_tool(&mut self, name: &str, arguments: HashMap<String, serde_json::Value>) -> Result<ToolCallResult> {
+        self.refresh_if_stale()?;
+        self.hydrate(&[name]);
         let params = ToolCallRequest {
             name: name.to_string(),
             arguments,
@@ -133,7 +333,7 @@ impl McpClient {
         let id = self.request_id.fetch_add(1, Ordering::SeqCst);
         let request = McpRequest::new(id, method, params);
 
-        match &self.transport {
+        let response = match &mut self.transport {
             Transport::Stdio(stdio) => {
                 let mut process = stdio.process.lock().map_err(|e| anyhow!("Lock error: {}", e))?;
                 
@@ -150,19 +350,20 @@ impl McpClient {
                 reader.read_line(&mut line)?;
 
                 let response: McpResponse = serde_json::from_str(&line)?;
-                
-                if let Some(error) = response.error {
-                    return Err(anyhow!("MCP error {}: {}", error.code, error.message));
-                }
-
-                let result = response.result.ok_or_else(|| anyhow!("No result in response"))?;
-                Ok(serde_json::from_value(result)?)
-            }
-            Transport::Http(_http) => {
-                // HTTP transport would use reqwest here
-                Err(anyhow!("HTTP transport not yet implemented"))
+                response
             }
+            Transport::Http(http) => http
+                .post(&self.name, &serde_json::to_value(&request)?)?
+                .ok_or_else(|| anyhow!("MCP server {} sent no response to {}", self.name, method))?,
+            Transport::Handler(handler) => handler(request),
+        };
+
+        if let Some(error) = response.error {
+            return Err(anyhow!("MCP error {}: {}", error.code, error.message));
         }
+
+        let result = response.result.ok_or_else(|| anyhow!("No result in response"))?;
+        Ok(serde_json::from_value(result)?)
     }
 
     /// Send a notification (no response expected)
@@ -173,7 +374,7 @@ impl McpClient {
             "params": params
         });
 
-        match &self.transport {
+        match &mut self.transport {
             Transport::Stdio(stdio) => {
                 let mut process = stdio.process.lock().map_err(|e| anyhow!("Lock error: {}", e))?;
                 let stdin = process.stdin.as_mut().ok_or_else(|| anyhow!("No stdin"))?;
@@ -182,9 +383,8 @@ impl McpClient {
                 stdin.flush()?;
                 Ok(())
             }
-            Transport::Http(_) => {
-                Err(anyhow!("HTTP transport not yet implemented"))
-            }
+            Transport::Http(http) => http.post(&self.name, &notification).map(|_| ()),
+            Transport::Handler(_) => Ok(()),
         }
     }
 
@@ -216,4 +416,84 @@ mod tests {
         let result = McpClient::new_stdio("test", "nonexistent_binary", &[]);
         assert!(result.is_err()); // Expected to fail without the binary
     }
+
+    #[test]
+    fn test_http_client_respects_egress_allowlist() {
+        let guard = || EgressGuard::new(&["mcp.example.com:8080".to_string()], "test config").unwrap();
+
+        assert!(McpClient::http("docs", "http://mcp.example.com:8080/mcp", guard()).is_ok());
+        let err = McpClient::http("evil", "https://evil.example.com/mcp", guard()).err().unwrap();
+        let denied = err.downcast_ref::<crate::util::egress::EgressDenied>().unwrap();
+        assert_eq!((denied.host.as_str(), denied.consumer.as_str()), ("evil.example.com", "MCP server evil"));
+        assert!(McpClient::http("bad", "not a url", guard()).is_err());
+    }
+
+    /// Answer JSON-RPC requests over SSE and notifications with 202,
+    /// recording each request's session header and method
+    fn serve_mcp(listener: std::net::TcpListener, seen: Arc<Mutex<Vec<String>>>) {
+        use std::io::Read;
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { return };
+            let mut reader = BufReader::new(stream);
+            loop {
+                let (mut length, mut session) = (0, "-".to_string());
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                    break;
+                }
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    let Some((name, value)) = line.trim_end().split_once(": ") else { break };
+                    match name.to_ascii_lowercase().as_str() {
+                        "content-length" => length = value.parse().unwrap(),
+                        "mcp-session-id" => session = value.to_string(),
+                        _ => {}
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let message: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                seen.lock().unwrap().push(format!("{} {}", session, message["method"].as_str().unwrap()));
+                let reply = match message.get("id") {
+                    Some(id) => {
+                        let result = serde_json::json!({"jsonrpc": "2.0", "id": id, "result": {
+                            "protocolVersion": "2024-11-05",
+                            "capabilities": {},
+                            "serverInfo": {"name": "docs", "version": "1.0"}
+                        }});
+                        let event = format!("event: message\ndata: {}\n\n", result);
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nMcp-Session-Id: s1\r\nContent-Length: {}\r\n\r\n{}",
+                            event.len(),
+                            event
+                        )
+                    }
+                    None => "HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n".to_string(),
+                };
+                reader.get_mut().write_all(reply.as_bytes()).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_http_transport_checks_resolved_address() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        std::thread::spawn(move || serve_mcp(listener, log));
+
+        // No allowlist: the name check passes, the loopback address doesn't
+        let mut open = McpClient::http("docs", &url, EgressGuard::default()).unwrap();
+        let err = open.initialize().unwrap_err();
+        assert!(err.downcast_ref::<crate::util::egress::EgressDenied>().is_some(), "{}", err);
+        assert!(seen.lock().unwrap().is_empty());
+
+        // Listing the address lets the session through
+        let guard = EgressGuard::new(&["127.0.0.1".to_string()], "config.toml").unwrap();
+        let mut client = McpClient::http("docs", &url, guard).unwrap();
+        assert_eq!(client.initialize().unwrap().server_info.name, "docs");
+        assert_eq!(*seen.lock().unwrap(), vec!["- initialize", "s1 notifications/initialized"]);
+    }
 }
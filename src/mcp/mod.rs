@@ -2,12 +2,15 @@
 //! 
 //! - Server: Exposes Webrana skills as MCP tools
 //! - Client: Connects to external MCP servers to discover additional tools
+//! - Proxy: Serves Webrana skills and upstream servers' tools on one endpoint
 
 pub mod protocol;
 pub mod server;
 pub mod client;
 pub mod registry;
+pub mod proxy;
 
 pub use protocol::*;
 pub use client::McpClient;
+pub use proxy::McpProxy;
 pub use registry::{McpRegistry, McpConfig, McpServerConfig, format_mcp_tools_for_llm};
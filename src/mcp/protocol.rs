@@ -197,10 +197,12 @@ pub struct InitializeResult {
     pub server_info: ServerInfo,
 }
 
-/// List tools result
+/// List tools result, one page of it when the server paginates
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListToolsResult {
     pub tools: Vec<McpTool>,
+    #[serde(default, rename = "nextCursor", skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 #[cfg(test)]
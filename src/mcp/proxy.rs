@@ -0,0 +1,284 @@
+//! MCP Proxy
+//!
+//! One MCP endpoint serving Webrana's skills plus the tools of every
+//! connected upstream server. Upstream tools are exposed as
+//! `<server>__<tool>`, so equal tool names on different servers don't clash.
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use super::client::McpClient;
+use super::protocol::*;
+use super::registry::McpRegistry;
+use super::server::{handle_request, serve};
+use crate::config::Settings;
+use crate::skills::SkillRegistry;
+
+/// Separates the server name from the upstream tool name
+pub const NAMESPACE_SEPARATOR: &str = "__";
+
+pub struct McpProxy {
+    upstreams: BTreeMap<String, Upstream>,
+    settings: Settings,
+    skills: Vec<Value>,
+}
+
+/// One upstream server. Calls hold only its own client lock, and
+/// `tools/list` reads the snapshot, so a slow server stalls nobody else.
+struct Upstream {
+    client: Arc<Mutex<McpClient>>,
+    tools: Arc<Mutex<Vec<McpTool>>>,
+}
+
+impl Upstream {
+    fn new(mut client: McpClient) -> Self {
+        let tools = snapshot(&mut client);
+        Self {
+            client: Arc::new(Mutex::new(client)),
+            tools: Arc::new(Mutex::new(tools)),
+        }
+    }
+}
+
+/// Every listed tool of `client`, with its schema handed out
+fn snapshot(client: &mut McpClient) -> Vec<McpTool> {
+    let names: Vec<String> = client.tools().iter().map(|t| t.name.clone()).collect();
+    client.hydrate(&names.iter().map(String::as_str).collect::<Vec<_>>());
+    client.tools_with_schemas()
+}
+
+impl McpProxy {
+    pub fn new(registry: McpRegistry, settings: Settings) -> Self {
+        Self {
+            upstreams: registry
+                .into_clients()
+                .into_iter()
+                .map(|(name, client)| (name, Upstream::new(client)))
+                .collect(),
+            settings,
+            skills: SkillRegistry::new().to_tool_definitions(),
+        }
+    }
+
+    /// Webrana skills followed by the namespaced upstream tools, each with
+    /// its input schema from the latest listing
+    pub fn list_tools(&self) -> Vec<Value> {
+        let mut tools = self.skills.clone();
+        for (server, upstream) in &self.upstreams {
+            let mut listed = upstream.tools.lock().unwrap().clone();
+            listed.sort_by(|a, b| a.name.cmp(&b.name));
+            for tool in listed {
+                tools.push(json!({
+                    "name": namespaced(server, &tool.name),
+                    "description": tool.description.unwrap_or_default(),
+                    "inputSchema": tool.input_schema.unwrap_or_else(|| json!({"type": "object"})),
+                }));
+            }
+        }
+        tools
+    }
+
+    /// Split `<server>__<tool>` when `<server>` is connected. The longest
+    /// matching server name wins.
+    fn route(&self, name: &str) -> Option<(String, String)> {
+        self.upstreams
+            .keys()
+            .filter_map(|server| {
+                let tool = name
+                    .strip_prefix(server.as_str())?
+                    .strip_prefix(NAMESPACE_SEPARATOR)?;
+                Some((server.clone(), tool.to_string()))
+            })
+            .max_by_key(|(server, _)| server.len())
+    }
+
+    /// Answer one request: namespaced tool calls go to their upstream
+    /// server, everything else to the Webrana skills
+    pub async fn handle(&self, request: &McpRequest) -> McpResponse {
+        match request.method.as_str() {
+            "tools/list" => McpResponse::success(request.id, json!({ "tools": self.list_tools() })),
+            "tools/call" => {
+                let name = request
+                    .params
+                    .as_ref()
+                    .and_then(|p| p["name"].as_str())
+                    .unwrap_or("");
+                match self.route(name) {
+                    Some((server, tool)) => {
+                        let arguments = request
+                            .params
+                            .as_ref()
+                            .and_then(|p| p.get("arguments"))
+                            .cloned()
+                            .unwrap_or_else(|| json!({}));
+                        match self.call_upstream(server, tool, arguments).await {
+                            Ok(result) => McpResponse::success(request.id, result),
+                            Err(e) => McpResponse::error(request.id, INTERNAL_ERROR, &e.to_string()),
+                        }
+                    }
+                    None => handle_request(request, &self.settings, &self.skills).await,
+                }
+            }
+            _ => handle_request(request, &self.settings, &self.skills).await,
+        }
+    }
+
+    /// Forward a call; the stdio client blocks, so it runs off the runtime
+    /// holding only this server's lock
+    async fn call_upstream(&self, server: String, tool: String, arguments: Value) -> Result<Value> {
+        let arguments: HashMap<String, Value> = serde_json::from_value(arguments)
+            .map_err(|_| anyhow!("Tool arguments must be a JSON object"))?;
+        let upstream = self
+            .upstreams
+            .get(&server)
+            .ok_or_else(|| anyhow!("Server '{}' not connected", server))?;
+        let (client, tools) = (upstream.client.clone(), upstream.tools.clone());
+        let result = tokio::task::spawn_blocking(move || {
+            let mut client = client.lock().unwrap();
+            if !client.tools().iter().any(|t| t.name == tool) {
+                return Err(anyhow!("Tool '{}' not found on server '{}'", tool, server));
+            }
+            let result = client.call_tool(&tool, arguments);
+            // The call may have listed again
+            *tools.lock().unwrap() = snapshot(&mut client);
+            result
+        })
+        .await??;
+        Ok(serde_json::to_value(result)?)
+    }
+}
+
+fn namespaced(server: &str, tool: &str) -> String {
+    format!("{}{}{}", server, NAMESPACE_SEPARATOR, tool)
+}
+
+/// Serve `proxy` on `127.0.0.1:port`, one JSON-RPC message per line
+pub async fn start(port: u16, proxy: McpProxy) -> Result<()> {
+    let proxy = Arc::new(proxy);
+    serve(port, "MCP proxy", move |request| {
+        let proxy = proxy.clone();
+        async move { proxy.handle(&request).await }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unknown_namespace_falls_through_to_skills() {
+        let proxy = McpProxy::new(McpRegistry::new(), Settings::default());
+        assert!(proxy.route("fs__read_file").is_none());
+
+        let names: Vec<String> = proxy
+            .list_tools()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap().to_string())
+            .collect();
+        assert!(names.contains(&"read_file".to_string()));
+
+        let request = McpRequest::new(7, "tools/call", Some(json!({"name": "fs__read_file"})));
+        let response = proxy.handle(&request).await;
+        assert_eq!(response.id, 7);
+        assert!(response.error.unwrap().message.contains("not found"));
+    }
+
+    #[tokio::test]
+    async fn test_every_upstream_tool_lists_its_schema() {
+        let lists = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = lists.clone();
+        let client = crate::mcp::McpClient::with_handler("docs", move |request: McpRequest| {
+            let result = match request.method.as_str() {
+                "initialize" => json!({
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {},
+                    "serverInfo": {"name": "docs", "version": "1.0"}
+                }),
+                _ => {
+                    counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    let tools: Vec<Value> = (0..40)
+                        .map(|i| json!({
+                            "name": format!("tool_{:02}", i),
+                            "inputSchema": {"type": "object", "properties": {"path": {"type": "string"}}}
+                        }))
+                        .collect();
+                    json!({ "tools": tools })
+                }
+            };
+            McpResponse::success(request.id, result)
+        });
+        let mut registry = McpRegistry::new();
+        registry.add_client("docs", client, &Default::default()).unwrap();
+        let proxy = McpProxy::new(registry, Settings::default());
+
+        let tools = proxy.list_tools();
+        let last = tools.iter().find(|t| t["name"] == "docs__tool_39").unwrap();
+        assert_eq!(last["inputSchema"]["properties"]["path"]["type"], "string");
+        assert_eq!(lists.load(std::sync::atomic::Ordering::SeqCst), 1, "listed once, at connect");
+    }
+
+    /// A client serving `tool`, whose calls run `on_call` first
+    fn upstream(name: &str, tool: &'static str, on_call: impl Fn() + Send + 'static) -> McpClient {
+        McpClient::with_handler(name, move |request: McpRequest| {
+            let result = match request.method.as_str() {
+                "initialize" => json!({
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {},
+                    "serverInfo": {"name": "test", "version": "1.0"}
+                }),
+                "tools/list" => json!({ "tools": [{"name": tool}] }),
+                _ => {
+                    on_call();
+                    json!({ "content": [{"type": "text", "text": tool}] })
+                }
+            };
+            McpResponse::success(request.id, result)
+        })
+    }
+
+    #[tokio::test]
+    async fn test_slow_upstream_blocks_no_other_request() {
+        let (entered_tx, entered_rx) = std::sync::mpsc::channel::<()>();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let entered_tx = Mutex::new(entered_tx);
+        let release_rx = Mutex::new(release_rx);
+        let mut registry = McpRegistry::new();
+        let slow = upstream("slow", "wait", move || {
+            entered_tx.lock().unwrap().send(()).unwrap();
+            release_rx.lock().unwrap().recv().unwrap();
+        });
+        registry.add_client("slow", slow, &Default::default()).unwrap();
+        registry
+            .add_client("fast", upstream("fast", "echo", || {}), &Default::default())
+            .unwrap();
+        let proxy = Arc::new(McpProxy::new(registry, Settings::default()));
+
+        let call = |id: u64, name: &str| McpRequest::new(id, "tools/call", Some(json!({"name": name})));
+        let pending = {
+            let proxy = proxy.clone();
+            let request = call(1, "slow__wait");
+            tokio::spawn(async move { proxy.handle(&request).await })
+        };
+        tokio::task::spawn_blocking(move || entered_rx.recv().unwrap())
+            .await
+            .unwrap();
+
+        // The slow call is in flight: listing and other servers still answer
+        let quick = async {
+            let names: Vec<Value> = proxy.list_tools().iter().map(|t| t["name"].clone()).collect();
+            assert!(names.contains(&json!("slow__wait")));
+            proxy.handle(&call(2, "fast__echo")).await
+        };
+        let response = tokio::time::timeout(std::time::Duration::from_secs(5), quick)
+            .await
+            .expect("blocked behind the slow server");
+        assert_eq!(response.result.unwrap()["content"][0]["text"], "echo");
+
+        release_tx.send(()).unwrap();
+        let response = pending.await.unwrap();
+        assert_eq!(response.result.unwrap()["content"][0]["text"], "wait");
+    }
+}
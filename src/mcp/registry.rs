@@ -2,6 +2,7 @@
 //! 
 //! Manages multiple MCP server connections and provides unified tool access.
 
+use super::client::ToolScope;
 use super::{McpClient, McpTool, ToolCallResult};
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
@@ -9,8 +10,9 @@ use std::collections::HashMap;
 use std::path::Path;
 
 /// MCP server configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct McpServerConfig {
+    #[serde(default)]
     pub command: String,
     #[serde(default)]
     pub args: Vec<String>,
@@ -18,6 +20,28 @@ pub struct McpServerConfig {
     pub env: HashMap<String, String>,
     #[serde(default)]
     pub enabled: bool,
+    /// Most tools taken from the server, in its listing order
+    #[serde(default)]
+    pub max_tools: Option<usize>,
+    /// Tool name globs to take; empty takes all
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Tool name globs to leave out
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Streamable HTTP endpoint, used instead of `command`
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+impl McpServerConfig {
+    pub fn scope(&self) -> ToolScope {
+        ToolScope {
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            max_tools: self.max_tools,
+        }
+    }
 }
 
 /// MCP Registry configuration
@@ -42,6 +66,11 @@ impl McpRegistry {
         }
     }
 
+    /// Default upstream server list, next to the main config file
+    pub fn default_config_path() -> Result<std::path::PathBuf> {
+        Ok(crate::config::Settings::config_path()?.with_file_name("mcp.toml"))
+    }
+
     /// Load configuration from file
     pub fn load_config(path: &Path) -> Result<McpConfig> {
         let content = std::fs::read_to_string(path)?;
@@ -71,12 +100,21 @@ impl McpRegistry {
 
     /// Add a server to the registry
     pub fn add_server(&mut self, name: &str, config: &McpServerConfig) -> Result<()> {
-        let args: Vec<&str> = config.args.iter().map(|s| s.as_str()).collect();
-        let mut client = McpClient::new_stdio(name, &config.command, &args)?;
+        let client = match &config.url {
+            Some(url) => McpClient::new_http(name, url)?,
+            None => {
+                let args: Vec<&str> = config.args.iter().map(|s| s.as_str()).collect();
+                McpClient::new_stdio(name, &config.command, &args)?
+            }
+        };
+        self.add_client(name, client, &config.scope())
+    }
 
-        // Initialize and get tools
+    /// Initialize `client` and register the tools in `scope`, without
+    /// their schemas
+    pub fn add_client(&mut self, name: &str, mut client: McpClient, scope: &ToolScope) -> Result<()> {
         client.initialize()?;
-        let tools = client.list_tools()?;
+        let tools = client.list_tools(scope)?;
 
         // Map tools to this server
         for tool in &tools {
@@ -97,17 +135,39 @@ impl McpRegistry {
         Ok(())
     }
 
-    /// Get all available tools from all servers
+    /// Get all available tools from all servers, with the input schemas
+    /// hydrated so far
     pub fn list_all_tools(&self) -> Vec<(String, McpTool)> {
         let mut tools = Vec::new();
         for (name, client) in &self.clients {
-            for tool in client.tools() {
-                tools.push((name.clone(), tool.clone()));
+            for tool in client.tools_with_schemas() {
+                tools.push((name.clone(), tool));
             }
         }
         tools
     }
 
+    /// Hand out the schemas of `tools` on `server_name`
+    pub fn hydrate(&mut self, server_name: &str, tools: &[&str]) -> Result<()> {
+        let client = self.clients.get_mut(server_name)
+            .ok_or_else(|| anyhow!("Server '{}' not connected", server_name))?;
+        client.hydrate(tools);
+        Ok(())
+    }
+
+    /// Hand out every listed tool's schema, for a full tool list
+    pub fn hydrate_all(&mut self) {
+        for client in self.clients.values_mut() {
+            let names: Vec<String> = client.tools().iter().map(|t| t.name.clone()).collect();
+            client.hydrate(&names.iter().map(String::as_str).collect::<Vec<_>>());
+        }
+    }
+
+    /// Whether the schema of `tool_name` on `server_name` is cached
+    pub fn is_hydrated(&self, server_name: &str, tool_name: &str) -> bool {
+        self.clients.get(server_name).is_some_and(|c| c.is_hydrated(tool_name))
+    }
+
     /// Get tools from a specific server
     pub fn list_server_tools(&self, server_name: &str) -> Option<&[McpTool]> {
         self.clients.get(server_name).map(|c| c.tools())
@@ -130,6 +190,28 @@ impl McpRegistry {
         client.call_tool(tool_name, arguments)
     }
 
+    /// Call a tool on a named server, for tools that several servers share
+    pub fn call_server_tool(
+        &mut self,
+        server_name: &str,
+        tool_name: &str,
+        arguments: HashMap<String, serde_json::Value>,
+    ) -> Result<ToolCallResult> {
+        let client = self.clients.get_mut(server_name)
+            .ok_or_else(|| anyhow!("Server '{}' not connected", server_name))?;
+        if !client.tools().iter().any(|t| t.name == tool_name) {
+            return Err(anyhow!("Tool '{}' not found on server '{}'", tool_name, server_name));
+        }
+
+        client.call_tool(tool_name, arguments)
+    }
+
+    /// Take the connected clients by server name, leaving the registry empty
+    pub fn into_clients(mut self) -> HashMap<String, McpClient> {
+        self.tool_map.clear();
+        std::mem::take(&mut self.clients)
+    }
+
     /// Get connected server names
     pub fn connected_servers(&self) -> Vec<&str> {
         self.clients.keys().map(|s| s.as_str()).collect()
@@ -193,6 +275,105 @@ pub fn format_mcp_tools_for_llm(tools: &[(String, McpTool)]) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mcp::{McpRequest, McpResponse, METHOD_NOT_FOUND};
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// A server with `count` tools listed 100 per page, each with a ~2 KB
+    /// schema. Counts its `tools/list` requests and the bytes they return.
+    fn big_server(count: usize, lists: Arc<AtomicUsize>, bytes: Arc<AtomicUsize>) -> McpClient {
+        McpClient::with_handler("big", move |request: McpRequest| {
+            let result = match request.method.as_str() {
+                "initialize" => json!({
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {},
+                    "serverInfo": {"name": "big", "version": "1.0"}
+                }),
+                "tools/list" => {
+                    lists.fetch_add(1, Ordering::SeqCst);
+                    let start = request.params.as_ref().and_then(|p| p["cursor"].as_str()).map_or(0, |c| c.parse().unwrap());
+                    let end = (start + 100).min(count);
+                    let tools: Vec<Value> = (start..end)
+                        .map(|i| json!({
+                            "name": format!("tool_{:03}", i),
+                            "description": format!("Tool {}\nUsage notes that stay on the server", i),
+                            "inputSchema": {"type": "object", "properties": {"query": {"type": "string", "description": "x".repeat(2_000)}}}
+                        }))
+                        .collect();
+                    let mut page = json!({ "tools": tools });
+                    if end < count {
+                        page["nextCursor"] = json!(end.to_string());
+                    }
+                    bytes.fetch_add(page.to_string().len(), Ordering::SeqCst);
+                    page
+                }
+                "tools/call" => json!({"content": [{"type": "text", "text": "done"}]}),
+                _ => return McpResponse::error(request.id, METHOD_NOT_FOUND, "Method not found"),
+            };
+            McpResponse::success(request.id, result)
+        })
+    }
+
+    fn connect(scope: &ToolScope) -> (McpRegistry, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+        let (lists, bytes) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let mut registry = McpRegistry::new();
+        registry.add_client("big", big_server(500, lists.clone(), bytes.clone()), scope).unwrap();
+        (registry, lists, bytes)
+    }
+
+    #[test]
+    fn test_connect_keeps_names_and_descriptions_only() {
+        let (registry, lists, bytes) = connect(&ToolScope::default());
+        let tools = registry.list_server_tools("big").unwrap();
+        assert_eq!(tools.len(), 500);
+        assert_eq!(lists.load(Ordering::SeqCst), 5, "one request per page");
+        assert!(tools.iter().all(|t| t.input_schema.is_none()));
+        assert_eq!(tools[7].description.as_deref(), Some("Tool 7"));
+
+        // Over a megabyte on the wire, a few kilobytes kept
+        let kept = serde_json::to_string(tools).unwrap().len();
+        assert!(bytes.load(Ordering::SeqCst) > 1_000_000);
+        assert!(kept < 40_000, "{} bytes kept", kept);
+    }
+
+    #[test]
+    fn test_schema_is_fetched_on_first_call() {
+        let (mut registry, lists, _) = connect(&ToolScope::default());
+        assert!(!registry.is_hydrated("big", "tool_420"));
+
+        registry.call_tool("tool_420", HashMap::new()).unwrap();
+        assert!(registry.is_hydrated("big", "tool_420"));
+        assert!(!registry.is_hydrated("big", "tool_421"));
+        assert_eq!(lists.load(Ordering::SeqCst), 5, "served from the connect-time listing");
+        let listed = registry.list_all_tools();
+        let (_, tool) = listed.iter().find(|(_, t)| t.name == "tool_420").unwrap();
+        assert!(tool.input_schema.as_ref().unwrap()["properties"]["query"].is_object());
+
+        registry.call_server_tool("big", "tool_420", HashMap::new()).unwrap();
+        registry.hydrate("big", &["tool_002"]).unwrap();
+        assert!(registry.is_hydrated("big", "tool_002") && !registry.is_hydrated("big", "tool_003"));
+        assert_eq!(lists.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_scope_globs_and_max_tools() {
+        let scope = ToolScope {
+            include: vec!["tool_1*".to_string(), "tool_42?".to_string()],
+            exclude: vec!["tool_1?5".to_string()],
+            max_tools: None,
+        };
+        let (mut registry, _, _) = connect(&scope);
+        let names: Vec<String> = registry.list_server_tools("big").unwrap().iter().map(|t| t.name.clone()).collect();
+        assert_eq!(names.len(), 100);
+        assert!(names.contains(&"tool_104".to_string()) && names.contains(&"tool_429".to_string()));
+        assert!(!names.contains(&"tool_105".to_string()) && !names.contains(&"tool_200".to_string()));
+        assert!(registry.call_tool("tool_200", HashMap::new()).is_err());
+
+        let (registry, lists, _) = connect(&ToolScope { max_tools: Some(50), ..scope });
+        assert_eq!(registry.list_server_tools("big").unwrap().len(), 50);
+        assert_eq!(lists.load(Ordering::SeqCst), 2, "stops listing once full");
+    }
 
     #[test]
     fn test_registry_new() {
@@ -212,11 +393,16 @@ enabled = true
 command = "npx"
 args = ["-y", "@modelcontextprotocol/server-github"]
 enabled = false
+max_tools = 40
+include = ["search_*"]
 "#;
         let config: McpConfig = toml::from_str(toml).unwrap();
         assert_eq!(config.servers.len(), 2);
         assert!(config.servers.get("filesystem").unwrap().enabled);
         assert!(!config.servers.get("github").unwrap().enabled);
+        let scope = config.servers["github"].scope();
+        assert_eq!(scope.max_tools, Some(40));
+        assert!(scope.allows("search_issues") && !scope.allows("create_issue"));
     }
 
     #[test]
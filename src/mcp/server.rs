@@ -1,25 +1,43 @@
 use anyhow::Result;
 use serde_json::{json, Value};
+use std::future::Future;
+use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::TcpListener;
 
 use super::protocol::*;
 use crate::config::Settings;
+use crate::core::scrub::scrub_tool_output;
 use crate::skills::SkillRegistry;
 
 pub async fn start(port: u16) -> Result<()> {
-    let listener = TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
-    println!("MCP Server listening on port {}", port);
+    let settings = Arc::new(Settings::load()?);
+    let skills_defs = Arc::new(SkillRegistry::new().to_tool_definitions());
 
-    let settings = Settings::load()?;
-    let skills = SkillRegistry::new();
+    serve(port, "MCP Server", move |request| {
+        let settings = settings.clone();
+        let skills_defs = skills_defs.clone();
+        async move { handle_request(&request, &settings, &skills_defs).await }
+    })
+    .await
+}
+
+/// Accept connections on `127.0.0.1:port` and answer each JSON-RPC line
+/// with `handler`. Lines that don't parse, such as notifications, get no
+/// response.
+pub(crate) async fn serve<F, Fut>(port: u16, label: &str, handler: F) -> Result<()>
+where
+    F: Fn(McpRequest) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = McpResponse> + Send,
+{
+    let listener = TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
+    println!("{} listening on port {}", label, port);
+    let handler = Arc::new(handler);
 
     loop {
         let (socket, addr) = listener.accept().await?;
         println!("New connection from: {}", addr);
-
-        let settings = settings.clone();
-        let skills_defs = skills.to_tool_definitions();
+        let handler = handler.clone();
 
         tokio::spawn(async move {
             let (reader, mut writer) = socket.into_split();
@@ -29,23 +47,23 @@ pub async fn start(port: u16) -> Result<()> {
             loop {
                 line.clear();
                 match reader.read_line(&mut line).await {
-                    Ok(0) => break, // Connection closed
+                    Ok(0) | Err(_) => break, // Connection closed
                     Ok(_) => {
                         if let Ok(request) = serde_json::from_str::<McpRequest>(&line) {
-                            let response = handle_request(&request, &settings, &skills_defs).await;
+                            let response = handler(request).await;
                             let response_json = serde_json::to_string(&response).unwrap();
                             let _ = writer.write_all(response_json.as_bytes()).await;
                             let _ = writer.write_all(b"\n").await;
                         }
                     }
-                    Err(_) => break,
                 }
             }
         });
     }
 }
 
-async fn handle_request(
+/// Answer one request with Webrana's skills as the tools
+pub(crate) async fn handle_request(
     request: &McpRequest,
     _settings: &Settings,
     tools: &[Value],
@@ -89,7 +107,7 @@ async fn handle_request(
                         json!({
                             "content": [{
                                 "type": "text",
-                                "text": result
+                                "text": scrub_tool_output(tool_name, &result, false).model
                             }]
                         }),
                     ),
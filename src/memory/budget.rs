@@ -0,0 +1,184 @@
+// ============================================
+// WEBRANA CLI - Context Budget
+// Splits the model's window between the system prompt, retrieved code and
+// the conversation, so a huge prompt can't starve the other two
+// ============================================
+
+use crate::config::ContextBudgetSettings;
+use crate::llm::{Message, Role};
+
+/// Characters per token, as everywhere else in context accounting
+const CHARS_PER_TOKEN: usize = 4;
+
+/// What each region would like, in characters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Demand {
+    pub system: usize,
+    pub rag: usize,
+    pub conversation: usize,
+}
+
+/// What the regions get. The system prompt is never cut, only warned about.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Allocation {
+    pub rag: usize,
+    pub conversation: usize,
+    pub warnings: Vec<String>,
+}
+
+/// Window shares of one model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContextBudget {
+    shares: ContextBudgetSettings,
+    /// Room for the request, in characters: the window less the answer
+    window_chars: usize,
+}
+
+impl ContextBudget {
+    pub fn new(shares: ContextBudgetSettings, window_tokens: usize, answer_tokens: usize) -> Self {
+        Self { shares, window_chars: window_tokens.saturating_sub(answer_tokens) * CHARS_PER_TOKEN }
+    }
+
+    /// The default model's window, keeping its `max_tokens` for the answer
+    pub fn from_settings(settings: &crate::config::Settings) -> Self {
+        match settings.get_model(&settings.default_model) {
+            Some(model) => Self::for_model(settings, model),
+            None => Self::new(settings.context_budget, settings.context_window(), 0),
+        }
+    }
+
+    /// `model`'s window, keeping its `max_tokens` for the answer
+    pub fn for_model(settings: &crate::config::Settings, model: &crate::config::ModelConfig) -> Self {
+        Self::new(settings.context_budget, model.context_window(), model.max_tokens as usize)
+    }
+
+    /// Characters left for the request beside a `system` character system prompt
+    pub fn room_after(&self, system: usize) -> usize {
+        self.window_chars.saturating_sub(system)
+    }
+
+    fn share(&self, fraction: f32) -> usize {
+        (self.window_chars as f64 * fraction.clamp(0.0, 1.0) as f64) as usize
+    }
+
+    /// Fit `demand` into the window. Overflow comes first out of regions
+    /// beyond their own share, then out of the reserves, retrieved code
+    /// before conversation both times.
+    pub fn allocate(&self, demand: Demand) -> Allocation {
+        let mut warnings = Vec::new();
+        let system_share = self.share(self.shares.system);
+        if demand.system > system_share {
+            warnings.push(format!(
+                "The system prompt is ~{} tokens, over its {:.0}% share ({} tokens) of the context window",
+                demand.system / CHARS_PER_TOKEN,
+                self.shares.system * 100.0,
+                system_share / CHARS_PER_TOKEN
+            ));
+        }
+
+        let (mut rag, mut conversation) = (demand.rag, demand.conversation);
+        let mut over = (demand.system + rag + conversation).saturating_sub(self.window_chars);
+        let shares = [self.share(self.shares.rag), self.share(self.shares.conversation)];
+        for reserve in [Some(shares), None] {
+            for (i, region) in [&mut rag, &mut conversation].into_iter().enumerate() {
+                let keep = reserve.map_or(0, |shares| shares[i]);
+                let cut = over.min(region.saturating_sub(keep));
+                *region -= cut;
+                over -= cut;
+            }
+        }
+
+        if rag < demand.rag {
+            warnings.push(format!(
+                "Retrieved code cut from ~{} to ~{} tokens to fit the context window",
+                demand.rag / CHARS_PER_TOKEN,
+                rag / CHARS_PER_TOKEN
+            ));
+        }
+        Allocation { rag, conversation, warnings }
+    }
+
+    /// Drop the oldest unpinned non-system messages of `messages` until the
+    /// conversation fits beside its system messages, always keeping the
+    /// latest. Returns the warnings.
+    pub fn fit_messages(&self, messages: &mut Vec<Message>) -> Vec<String> {
+        let system: usize = messages.iter().filter(|m| m.role == Role::System).map(Message::chars).sum();
+        let conversation: usize = messages.iter().filter(|m| m.role != Role::System).map(Message::chars).sum();
+        let mut allocation = self.allocate(Demand { system, rag: 0, conversation });
+
+        let (mut size, mut dropped) = (conversation, 0);
+        let latest = messages.iter().rposition(|m| m.role != Role::System);
+        while size > allocation.conversation {
+            let oldest = messages.iter().position(|m| m.role != Role::System && !m.pinned);
+            let Some(index) = oldest.filter(|&i| Some(i + dropped) != latest) else { break };
+            size -= messages.remove(index).chars();
+            dropped += 1;
+        }
+        if dropped > 0 {
+            allocation
+                .warnings
+                .push(format!("Left the {} oldest message(s) out to fit the context window", dropped));
+        }
+        allocation.warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget() -> ContextBudget {
+        // 1,000 tokens: 4,000 characters
+        ContextBudget::new(ContextBudgetSettings { system: 0.3, rag: 0.2, conversation: 0.5 }, 1_200, 200)
+    }
+
+    #[test]
+    fn test_oversized_system_prompt_drops_rag_first() {
+        let allocation = budget().allocate(Demand { system: 2_600, rag: 800, conversation: 1_400 });
+        // 800 over: all retrieved code goes before any conversation
+        assert_eq!(allocation.rag, 0);
+        assert_eq!(allocation.conversation, 1_400);
+        assert_eq!(allocation.warnings.len(), 2);
+        assert!(allocation.warnings[0].contains("system prompt is ~650 tokens, over its 30% share"));
+        assert!(allocation.warnings[1].contains("Retrieved code cut from ~200 to ~0 tokens"));
+
+        // Still over after the code is gone: the conversation gives way too
+        let allocation = budget().allocate(Demand { system: 3_000, rag: 800, conversation: 1_400 });
+        assert_eq!((allocation.rag, allocation.conversation), (0, 1_000));
+    }
+
+    #[test]
+    fn test_regions_over_their_share_give_way_first() {
+        // 500 over; the conversation is 1,000 past its share, code within its own
+        let allocation = budget().allocate(Demand { system: 500, rag: 800, conversation: 3_200 });
+        assert_eq!((allocation.rag, allocation.conversation), (800, 2_700));
+        assert!(allocation.warnings.is_empty());
+
+        let fits = budget().allocate(Demand { system: 1_000, rag: 1_000, conversation: 1_000 });
+        assert_eq!((fits.rag, fits.conversation, fits.warnings.len()), (1_000, 1_000, 0));
+    }
+
+    #[test]
+    fn test_fit_messages_drops_oldest() {
+        let mut messages = vec![
+            Message::system("s".repeat(1_000)),
+            Message::user("a".repeat(1_500)),
+            Message::assistant("b".repeat(1_000)),
+            Message::user("c".repeat(1_000)),
+        ];
+        let warnings = budget().fit_messages(&mut messages);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].content, "b".repeat(1_000));
+        assert!(warnings[0].contains("1 oldest message"), "{:?}", warnings);
+
+        // The latest message stays even when it alone is too big
+        let mut messages = vec![Message::system("s"), Message::user("x".repeat(9_000))];
+        budget().fit_messages(&mut messages);
+        assert_eq!(messages.len(), 2);
+
+        // Pinned messages stay too
+        let mut messages = vec![Message::user("p".repeat(3_000)).pin(), Message::user("y".repeat(3_000))];
+        assert!(budget().fit_messages(&mut messages).is_empty());
+        assert_eq!(messages.len(), 2);
+    }
+}
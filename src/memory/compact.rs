@@ -0,0 +1,160 @@
+// ============================================
+// WEBRANA CLI - Context Compaction
+// Summarize older turns into a single message
+// ============================================
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::llm::{LlmClient, Message, Role};
+
+/// Recent turns kept verbatim by `/compact` when no count is given
+pub const DEFAULT_KEEP_LAST: usize = 4;
+
+/// First line of every summary message, so compaction points stay visible
+pub const SUMMARY_HEADER: &str = "[Conversation summary]";
+
+pub const SUMMARY_PROMPT: &str = "You compress conversation history for a coding agent. \
+Summarize the transcript you are given so it can replace the original messages. \
+You MUST preserve: every file path mentioned, decisions made and why, commands or tools run \
+and their outcomes, errors still unresolved, and open TODOs. \
+Drop pleasantries and repeated content. Answer with concise bullet points only.";
+
+/// Produces the summary text for a span of messages
+#[async_trait]
+pub trait Summarizer: Send + Sync {
+    async fn summarize(&self, messages: &[Message]) -> Result<String>;
+}
+
+/// Summarizer backed by the session's model
+pub struct LlmSummarizer<'a> {
+    llm: &'a LlmClient,
+}
+
+impl<'a> LlmSummarizer<'a> {
+    pub fn new(llm: &'a LlmClient) -> Self {
+        Self { llm }
+    }
+}
+
+#[async_trait]
+impl Summarizer for LlmSummarizer<'_> {
+    async fn summarize(&self, messages: &[Message]) -> Result<String> {
+        self.llm.chat(SUMMARY_PROMPT, &[], &transcript(messages)).await
+    }
+}
+
+/// Render messages as a plain `ROLE: content` transcript
+pub fn transcript(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(|m| {
+            let role = match m.role {
+                Role::System => "SYSTEM",
+                Role::User => "USER",
+                Role::Assistant => "ASSISTANT",
+            };
+            format!("{}: {}", role, m.content)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// One compaction, kept in the context so history listings show where it happened
+#[derive(Debug, Clone, Serialize)]
+pub struct CompactionRecord {
+    /// Index of the summary message after compaction
+    pub position: usize,
+    /// Messages replaced by the summary
+    pub summarized: usize,
+    pub tokens_before: usize,
+    pub tokens_after: usize,
+    pub automatic: bool,
+}
+
+/// Fires once each time context usage crosses the threshold upward
+#[derive(Debug, Clone)]
+pub struct AutoCompact {
+    threshold: f32,
+    armed: bool,
+}
+
+impl AutoCompact {
+    /// `threshold` is a fraction of the context window (0.8 = 80%)
+    pub fn new(threshold: f32) -> Self {
+        Self {
+            threshold,
+            armed: true,
+        }
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Returns true when `used_tokens` has just crossed the threshold.
+    /// Re-arms only after usage drops back below it.
+    pub fn check(&mut self, used_tokens: usize, window_tokens: usize) -> bool {
+        if window_tokens == 0 {
+            return false;
+        }
+        let usage = used_tokens as f32 / window_tokens as f32;
+        if usage < self.threshold {
+            self.armed = true;
+            false
+        } else if self.armed {
+            self.armed = false;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Parse a threshold like `80%`, `80` or `0.8` into a fraction
+pub fn parse_threshold(value: &str) -> Result<f32> {
+    let trimmed = value.trim();
+    let number: f32 = trimmed
+        .trim_end_matches('%')
+        .trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid threshold '{}': expected e.g. 80%", value))?;
+    let fraction = if trimmed.ends_with('%') || number > 1.0 {
+        number / 100.0
+    } else {
+        number
+    };
+    if !(fraction > 0.0 && fraction <= 1.0) {
+        anyhow::bail!("Threshold '{}' must be between 0% and 100%", value);
+    }
+    Ok(fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_compact_fires_once_per_crossing() {
+        let mut auto = AutoCompact::new(0.8);
+        assert!(!auto.check(500, 1000));
+        assert!(auto.check(800, 1000));
+        // Still above the threshold: no second trigger
+        assert!(!auto.check(900, 1000));
+        assert!(!auto.check(850, 1000));
+        // Drops below, then crosses again
+        assert!(!auto.check(300, 1000));
+        assert!(auto.check(810, 1000));
+    }
+
+    #[test]
+    fn test_parse_threshold() {
+        assert_eq!(parse_threshold("80%").unwrap(), 0.8);
+        assert_eq!(parse_threshold("80").unwrap(), 0.8);
+        assert_eq!(parse_threshold("0.5").unwrap(), 0.5);
+        assert!(parse_threshold("0%").is_err());
+        assert!(parse_threshold("150%").is_err());
+        assert!(parse_threshold("lots").is_err());
+    }
+}
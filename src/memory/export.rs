@@ -0,0 +1,586 @@
+// ============================================
+// WEBRANA CLI - Conversation Export
+// Self-contained HTML: inlined CSS, code highlighted at export time
+// ============================================
+
+use std::path::Path;
+
+use super::SUMMARY_HEADER;
+use crate::core::{ScannerConfig, SecretScanner};
+use crate::llm::{Message, Role};
+
+/// Tool results longer than this keep only their head and tail
+pub const DEFAULT_MAX_TOOL_OUTPUT: usize = 8_000;
+
+/// Header shown above the conversation
+#[derive(Debug, Clone, Default)]
+pub struct ExportMeta {
+    /// Already formatted; passed in so exports are reproducible
+    pub date: String,
+    pub model: String,
+    pub crew: Option<String>,
+    pub tokens: usize,
+    pub cost: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HtmlOptions {
+    /// Redact secrets again and shorten absolute paths
+    pub redact: bool,
+    /// Paths under this directory become project-relative with `redact`
+    pub project_root: Option<std::path::PathBuf>,
+    pub home_dir: Option<std::path::PathBuf>,
+    pub max_tool_output: usize,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self {
+            redact: false,
+            project_root: None,
+            home_dir: None,
+            max_tool_output: DEFAULT_MAX_TOOL_OUTPUT,
+        }
+    }
+}
+
+const STYLE: &str = "
+body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; max-width: 920px; margin: 2em auto; padding: 0 1em; color: #1f2328; background: #fff; line-height: 1.5; }
+header { border-bottom: 1px solid #d0d7de; margin-bottom: 1.5em; }
+header h1 { font-size: 1.4em; margin: 0 0 .3em; }
+header dl { display: grid; grid-template-columns: max-content 1fr; gap: .1em 1em; margin: 0 0 1em; font-size: .9em; }
+header dt { color: #59636e; }
+header dd { margin: 0; }
+.turn { border-radius: 8px; padding: .6em 1em; margin: 1em 0; }
+.turn .role { font-size: .75em; font-weight: 600; text-transform: uppercase; letter-spacing: .05em; color: #59636e; }
+.user { background: #ddf4ff; border: 1px solid #b6e3ff; margin-left: 3em; }
+.assistant { background: #f6f8fa; border: 1px solid #d0d7de; margin-right: 3em; }
+.system { background: #fff8c5; border: 1px solid #eac54f; }
+details { margin: .5em 0; }
+details.tool { border: 1px solid #d0d7de; border-radius: 6px; padding: .3em .8em; background: #fff; }
+summary { cursor: pointer; font-size: .85em; color: #59636e; }
+.truncated summary { color: #9a6700; }
+pre { background: #f6f8fa; border: 1px solid #d0d7de; border-radius: 6px; padding: .8em; overflow-x: auto; font-size: .85em; }
+code { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; }
+p code { background: #eff1f3; padding: .1em .3em; border-radius: 4px; }
+.kw { color: #cf222e; }
+.str { color: #0a3069; }
+.com { color: #6e7781; font-style: italic; }
+.num { color: #0550ae; }
+";
+
+/// Render `messages` as one HTML document that needs no network access.
+/// The same input always gives the same bytes.
+pub fn render_html(messages: &[Message], meta: &ExportMeta, options: &HtmlOptions) -> String {
+    let redactor = options
+        .redact
+        .then(|| SecretScanner::new(ScannerConfig::default()));
+    let clean = |text: &str| -> String {
+        match &redactor {
+            Some(scanner) => strip_paths(&scanner.redact_text(text), options),
+            None => text.to_string(),
+        }
+    };
+
+    let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str(&format!("<title>Webrana conversation – {}</title>\n", escape(&meta.date)));
+    html.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+
+    html.push_str("<header>\n<h1>Webrana conversation</h1>\n<dl>\n");
+    let mut field = |name: &str, value: &str| {
+        html.push_str(&format!("<dt>{}</dt><dd>{}</dd>\n", name, escape(value)));
+    };
+    field("Date", &meta.date);
+    field("Model", &meta.model);
+    if let Some(crew) = &meta.crew {
+        field("Crew", crew);
+    }
+    field("Tokens", &format!("~{}", meta.tokens));
+    field(
+        "Cost",
+        &meta.cost.map(|c| format!("${:.4}", c)).unwrap_or_else(|| "unknown".to_string()),
+    );
+    html.push_str("</dl>\n</header>\n<main>\n");
+
+    for message in messages {
+        let content = clean(&message.content);
+        if let Some((id, output)) = tool_result(&content) {
+            html.push_str(&render_tool_result(id, output, options.max_tool_output));
+            continue;
+        }
+        let (class, label) = match message.role {
+            Role::User => ("user", "You"),
+            Role::Assistant => ("assistant", "Assistant"),
+            Role::System => ("system", "System"),
+        };
+        html.push_str(&format!(
+            "<section class=\"turn {}\">\n<div class=\"role\">{}</div>\n",
+            class, label
+        ));
+        if let Some(summary) = content.strip_prefix(SUMMARY_HEADER) {
+            html.push_str("<details>\n<summary>Earlier conversation (compacted)</summary>\n");
+            html.push_str(&render_markdown(summary.trim()));
+            html.push_str("</details>\n");
+        } else {
+            html.push_str(&render_markdown(&content));
+        }
+        html.push_str("</section>\n");
+    }
+
+    html.push_str("</main>\n</body>\n</html>\n");
+    html
+}
+
+/// Render `messages` as a Markdown transcript with the same header, redaction
+/// and tool output limits as `render_html`
+pub fn render_transcript(messages: &[Message], meta: &ExportMeta, options: &HtmlOptions) -> String {
+    let redactor = options
+        .redact
+        .then(|| SecretScanner::new(ScannerConfig::default()));
+    let clean = |text: &str| -> String {
+        match &redactor {
+            Some(scanner) => strip_paths(&scanner.redact_text(text), options),
+            None => text.to_string(),
+        }
+    };
+
+    let mut md = String::from("# Webrana conversation\n\n");
+    md.push_str(&format!("- Date: {}\n- Model: {}\n", meta.date, meta.model));
+    if let Some(crew) = &meta.crew {
+        md.push_str(&format!("- Crew: {}\n", crew));
+    }
+    md.push_str(&format!(
+        "- Tokens: ~{}\n- Cost: {}\n",
+        meta.tokens,
+        meta.cost.map(|c| format!("${:.4}", c)).unwrap_or_else(|| "unknown".to_string())
+    ));
+
+    for message in messages {
+        let content = clean(&message.content);
+        if let Some((id, output)) = tool_result(&content) {
+            md.push_str(&format!("\n### Tool result `{}`\n\n", id));
+            match head_and_tail(output, options.max_tool_output) {
+                Some((head, omitted, tail)) => {
+                    md.push_str(&fenced(&head));
+                    md.push_str(&format!("\n… {} characters omitted …\n\n", omitted));
+                    md.push_str(&fenced(&tail));
+                }
+                None => md.push_str(&fenced(output)),
+            }
+            continue;
+        }
+        let label = match message.role {
+            Role::User => "You",
+            Role::Assistant => "Assistant",
+            Role::System => "System",
+        };
+        match content.strip_prefix(SUMMARY_HEADER) {
+            Some(summary) => md.push_str(&format!("\n## Earlier conversation (compacted)\n\n{}\n", summary.trim())),
+            None => md.push_str(&format!("\n## {}\n\n{}\n", label, content.trim())),
+        }
+    }
+    md
+}
+
+/// `text` in a code fence longer than any backtick run inside it
+fn fenced(text: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}\n{}\n{}\n", fence, text, fence)
+}
+
+/// `(tool_use_id, output)` of a `<tool_result>` message
+fn tool_result(content: &str) -> Option<(&str, &str)> {
+    let rest = content.strip_prefix("<tool_result tool_use_id=\"")?;
+    let (id, rest) = rest.split_once("\">")?;
+    let output = rest.strip_suffix("</tool_result>")?;
+    Some((id, output.trim_matches('\n')))
+}
+
+/// The beginning and end of `output` when it is over `max` characters, cut
+/// at line boundaries when there are any, and how many characters are left out
+fn head_and_tail(output: &str, max: usize) -> Option<(String, usize, String)> {
+    let chars = output.chars().count();
+    if chars <= max {
+        return None;
+    }
+    let mut head: String = output.chars().take(max / 2).collect();
+    if let Some(end) = head.rfind('\n') {
+        head.truncate(end);
+    }
+    let mut tail: String = output.chars().skip(chars - max / 2).collect();
+    if let Some(start) = tail.find('\n') {
+        tail.drain(..=start);
+    }
+    let omitted = chars - head.chars().count() - tail.chars().count();
+    Some((head, omitted, tail))
+}
+
+fn render_tool_result(id: &str, output: &str, max: usize) -> String {
+    let lines = output.lines().count();
+    let mut html = format!(
+        "<details class=\"tool\">\n<summary>Tool result <code>{}</code> · {} line{}</summary>\n",
+        escape(id),
+        lines,
+        if lines == 1 { "" } else { "s" }
+    );
+    if let Some((head, omitted, tail)) = head_and_tail(output, max) {
+        html.push_str(&format!("<pre><code>{}</code></pre>\n", escape(&head)));
+        html.push_str(&format!(
+            "<details class=\"truncated\">\n<summary>… {} characters omitted …</summary>\n<p>Tool output over {} characters keeps only its beginning and end in exports.</p>\n</details>\n",
+            omitted,
+            max
+        ));
+        html.push_str(&format!("<pre><code>{}</code></pre>\n", escape(&tail)));
+    } else {
+        html.push_str(&format!("<pre><code>{}</code></pre>\n", escape(output)));
+    }
+    html.push_str("</details>\n");
+    html
+}
+
+/// Paragraphs, inline code and fenced code blocks
+fn render_markdown(text: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut fence: Option<(String, Vec<&str>)> = None;
+
+    let flush = |paragraph: &mut Vec<&str>, html: &mut String| {
+        if !paragraph.is_empty() {
+            let lines: Vec<String> = paragraph.iter().map(|l| inline(l)).collect();
+            html.push_str(&format!("<p>{}</p>\n", lines.join("<br>\n")));
+            paragraph.clear();
+        }
+    };
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match fence.as_mut() {
+            Some((lang, body)) => {
+                if trimmed.starts_with("```") {
+                    html.push_str(&render_code(lang, &body.join("\n")));
+                    fence = None;
+                } else {
+                    body.push(line);
+                }
+            }
+            None if trimmed.starts_with("```") => {
+                flush(&mut paragraph, &mut html);
+                fence = Some((trimmed[3..].trim().to_lowercase(), Vec::new()));
+            }
+            None if trimmed.is_empty() => flush(&mut paragraph, &mut html),
+            None => paragraph.push(line),
+        }
+    }
+    // An unclosed fence still renders as code
+    if let Some((lang, body)) = fence {
+        html.push_str(&render_code(&lang, &body.join("\n")));
+    }
+    flush(&mut paragraph, &mut html);
+    html
+}
+
+/// Escaped text with `code` spans
+fn inline(line: &str) -> String {
+    let mut out = String::new();
+    for (i, part) in line.split('`').enumerate() {
+        if i % 2 == 1 {
+            out.push_str(&format!("<code>{}</code>", escape(part)));
+        } else {
+            out.push_str(&escape(part));
+        }
+    }
+    out
+}
+
+fn render_code(lang: &str, code: &str) -> String {
+    let class = if lang.is_empty() {
+        String::new()
+    } else {
+        format!(" class=\"language-{}\"", escape(lang))
+    };
+    format!("<pre><code{}>{}</code></pre>\n", class, highlight(lang, code))
+}
+
+/// Lexical rules for one language family
+struct Syntax {
+    keywords: &'static [&'static str],
+    line_comment: &'static [&'static str],
+    block_comment: bool,
+    single_quote_strings: bool,
+}
+
+fn syntax(lang: &str) -> Option<Syntax> {
+    Some(match lang {
+        "rust" | "rs" => Syntax {
+            keywords: &[
+                "as", "async", "await", "break", "const", "continue", "crate", "else", "enum", "false", "fn",
+                "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+                "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+                "use", "where", "while",
+            ],
+            line_comment: &["//"],
+            block_comment: true,
+            single_quote_strings: false,
+        },
+        "python" | "py" => Syntax {
+            keywords: &[
+                "and", "as", "async", "await", "class", "def", "elif", "else", "except", "False", "finally",
+                "for", "from", "if", "import", "in", "is", "lambda", "None", "not", "or", "pass", "raise",
+                "return", "True", "try", "while", "with", "yield",
+            ],
+            line_comment: &["#"],
+            block_comment: false,
+            single_quote_strings: true,
+        },
+        "javascript" | "js" | "typescript" | "ts" | "tsx" | "jsx" => Syntax {
+            keywords: &[
+                "async", "await", "break", "case", "class", "const", "continue", "default", "else", "export",
+                "extends", "false", "for", "function", "if", "import", "in", "interface", "let", "new",
+                "null", "of", "return", "switch", "this", "throw", "true", "try", "type", "undefined",
+                "var", "while",
+            ],
+            line_comment: &["//"],
+            block_comment: true,
+            single_quote_strings: true,
+        },
+        "bash" | "sh" | "shell" | "zsh" => Syntax {
+            keywords: &[
+                "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if", "in",
+                "local", "return", "then", "while",
+            ],
+            line_comment: &["#"],
+            block_comment: false,
+            single_quote_strings: true,
+        },
+        "json" => Syntax {
+            keywords: &["true", "false", "null"],
+            line_comment: &[],
+            block_comment: false,
+            single_quote_strings: false,
+        },
+        _ => return None,
+    })
+}
+
+/// Escaped `code` with keyword, string, comment and number spans
+pub fn highlight(lang: &str, code: &str) -> String {
+    let Some(syntax) = syntax(lang) else {
+        return escape(code);
+    };
+
+    let chars: Vec<char> = code.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    let span = |out: &mut String, class: &str, text: &[char]| {
+        let text: String = text.iter().collect();
+        out.push_str(&format!("<span class=\"{}\">{}</span>", class, escape(&text)));
+    };
+    let starts_with = |i: usize, prefix: &str| {
+        prefix.chars().enumerate().all(|(k, p)| chars.get(i + k) == Some(&p))
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        if let Some(prefix) = syntax.line_comment.iter().find(|p| starts_with(i, p)) {
+            // `#` only starts a comment at a word boundary (not `$#` or `a#b`)
+            if *prefix != "#" || i == 0 || chars[i - 1].is_whitespace() {
+                let end = chars[i..].iter().position(|&c| c == '\n').map_or(chars.len(), |p| i + p);
+                span(&mut out, "com", &chars[i..end]);
+                i = end;
+                continue;
+            }
+        }
+        if syntax.block_comment && starts_with(i, "/*") {
+            let end = (i + 2..chars.len())
+                .find(|&j| starts_with(j, "*/"))
+                .map_or(chars.len(), |j| j + 2);
+            span(&mut out, "com", &chars[i..end]);
+            i = end;
+            continue;
+        }
+        if c == '"' || c == '`' || (c == '\'' && syntax.single_quote_strings) {
+            let mut j = i + 1;
+            while j < chars.len() && chars[j] != c {
+                if chars[j] == '\\' {
+                    j += 1;
+                }
+                j += 1;
+            }
+            let end = (j + 1).min(chars.len());
+            span(&mut out, "str", &chars[i..end]);
+            i = end;
+            continue;
+        }
+        if c.is_ascii_digit() && (i == 0 || !is_word(chars[i - 1])) {
+            let end = (i..chars.len())
+                .find(|&j| !(chars[j].is_ascii_alphanumeric() || chars[j] == '.' || chars[j] == '_'))
+                .unwrap_or(chars.len());
+            span(&mut out, "num", &chars[i..end]);
+            i = end;
+            continue;
+        }
+        if is_word(c) {
+            let end = (i..chars.len()).find(|&j| !is_word(chars[j])).unwrap_or(chars.len());
+            let word: String = chars[i..end].iter().collect();
+            if syntax.keywords.contains(&word.as_str()) {
+                span(&mut out, "kw", &chars[i..end]);
+            } else {
+                out.push_str(&escape(&word));
+            }
+            i = end;
+            continue;
+        }
+        out.push_str(&escape(&c.to_string()));
+        i += 1;
+    }
+    out
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// `YYYY-MM-DD HH:MM UTC` for a unix timestamp
+pub fn utc_date(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let (hour, minute) = ((secs % 86_400) / 3600, (secs % 3600) / 60);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, hour, minute)
+}
+
+/// Project paths become relative and the home directory becomes `~`
+fn strip_paths(text: &str, options: &HtmlOptions) -> String {
+    let mut text = text.to_string();
+    let as_str = |path: &Path| path.to_string_lossy().trim_end_matches('/').to_string();
+    if let Some(root) = options.project_root.as_deref().map(as_str).filter(|r| r.len() > 1) {
+        text = text.replace(&format!("{}/", root), "").replace(&root, ".");
+    }
+    if let Some(home) = options.home_dir.as_deref().map(as_str).filter(|h| h.len() > 1) {
+        text = text.replace(&home, "~");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNAPSHOT: &str = "src/memory/snapshots/export.html";
+
+    fn fixture() -> Vec<Message> {
+        let giant: String = (1..=2_000).map(|i| format!("line {} of the build log\n", i)).collect();
+        vec![
+            Message::user("Why does `parse` fail on /home/ada/project/src/lib.rs?"),
+            Message::assistant(
+                "The loop never advances:\n\n```rust\nfn parse(s: &str) -> usize {\n    // count digits\n    let n = 42;\n    s.len() + n\n}\n```\n\nThe Python version is fine:\n\n```python\ndef parse(s):\n    return len(s)  # 'ok'\n```",
+            ),
+            Message::user(format!(
+                "<tool_result tool_use_id=\"call_1\">\n{}\n</tool_result>",
+                giant
+            )),
+            Message::user(
+                "<tool_result tool_use_id=\"call_2\">\nOPENAI_API_KEY=sk-abcdefghijklmnopqrstuvwxyz123456 in /home/ada/project/.env\n</tool_result>",
+            ),
+            Message::assistant("Run it with:\n\n```javascript\nconst out = await run(\"<tests>\", 3);\n```"),
+        ]
+    }
+
+    fn options() -> HtmlOptions {
+        HtmlOptions {
+            redact: true,
+            project_root: Some("/home/ada/project".into()),
+            home_dir: Some("/home/ada".into()),
+            max_tool_output: 400,
+        }
+    }
+
+    fn meta() -> ExportMeta {
+        ExportMeta {
+            date: "2026-10-16".to_string(),
+            model: "claude-sonnet-4-20250514".to_string(),
+            crew: Some("Reviewer".to_string()),
+            tokens: 12_345,
+            cost: Some(0.0421),
+        }
+    }
+
+    #[test]
+    fn test_html_export_snapshot() {
+        let html = render_html(&fixture(), &meta(), &options());
+        assert_eq!(html, render_html(&fixture(), &meta(), &options()));
+
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(SNAPSHOT, &html).unwrap();
+        }
+        let expected = std::fs::read_to_string(SNAPSHOT).expect("run with UPDATE_SNAPSHOTS=1 to create");
+        assert_eq!(html, expected);
+    }
+
+    #[test]
+    fn test_utc_date() {
+        assert_eq!(utc_date(0), "1970-01-01 00:00 UTC");
+        assert_eq!(utc_date(1_792_152_000), "2026-10-16 12:00 UTC");
+        assert_eq!(utc_date(951_782_400), "2000-02-29 00:00 UTC");
+    }
+
+    #[test]
+    fn test_html_export_is_self_contained_and_redacted() {
+        let html = render_html(&fixture(), &meta(), &options());
+        assert!(!html.contains("http://") && !html.contains("https://") && !html.contains("<script"));
+        assert!(!html.contains("sk-abcdefghijklmnopqrstuvwxyz"));
+        assert!(!html.contains("/home/ada"));
+        assert!(html.contains("src/lib.rs"));
+        assert!(html.contains("<span class=\"kw\">fn</span> parse"));
+        assert!(html.contains("<span class=\"com\"># &#39;ok&#39;</span>"));
+        assert!(html.contains("<span class=\"str\">&quot;&lt;tests&gt;&quot;</span>"));
+        assert!(html.contains("characters omitted"));
+        assert!(html.len() < 10_000, "{}", html.len());
+
+        let plain = render_html(&fixture(), &meta(), &HtmlOptions::default());
+        assert!(plain.contains("/home/ada/project/src/lib.rs"));
+    }
+
+    #[test]
+    fn test_markdown_transcript() {
+        let md = render_transcript(&fixture(), &meta(), &options());
+        assert!(md.starts_with("# Webrana conversation\n\n- Date: "), "{}", md);
+        assert!(md.contains("- Crew: Reviewer\n"));
+        assert!(md.contains("\n## You\n\n"));
+        assert!(md.contains("\n## Assistant\n\n"));
+        assert!(md.contains("\n### Tool result `"));
+        assert!(md.contains("characters omitted"));
+        assert!(!md.contains("sk-abcdefghijklmnopqrstuvwxyz"));
+        assert!(!md.contains("/home/ada"));
+        assert_eq!(fenced("a ``` b"), "````\na ``` b\n````\n");
+    }
+}
@@ -4,7 +4,39 @@
 // Created by: FORGE (Team Beta)
 // ============================================
 
-use crate::llm::Message;
+use anyhow::{Context as _, Result};
+use std::path::Path;
+
+use crate::llm::{Message, Role};
+
+pub mod budget;
+pub mod compact;
+pub mod export;
+pub mod project;
+pub mod recall;
+pub mod working_set;
+
+#[allow(unused_imports)]
+pub use budget::{Allocation, ContextBudget, Demand};
+#[allow(unused_imports)]
+pub use compact::{
+    parse_threshold, AutoCompact, CompactionRecord, LlmSummarizer, Summarizer, DEFAULT_KEEP_LAST,
+    SUMMARY_HEADER,
+};
+#[allow(unused_imports)]
+pub use export::{render_html, render_transcript, utc_date, ExportMeta, HtmlOptions, DEFAULT_MAX_TOOL_OUTPUT};
+#[allow(unused_imports)]
+pub use recall::{
+    default_index_path, parse_age, recall_block, ConversationMemory, Recollection, SessionInfo,
+    MEMORY_INDEX, RECALL_HEADER,
+};
+#[allow(unused_imports)]
+pub use working_set::{tier_sizes, ToolResultTag, WorkingSetPolicy, DEFAULT_KEEP_ITERATIONS};
+#[allow(unused_imports)]
+pub use project::{
+    memory_path, prompt_section, scaffold, target_path, upsert_section, DEFAULT_BUDGET_CHARS,
+    FALLBACK_FILE, MEMORY_FILE,
+};
 
 /// Configuration for context window management
 #[derive(Debug, Clone)]
@@ -30,12 +62,24 @@ impl Default for ContextConfig {
     }
 }
 
+impl ContextConfig {
+    /// Never trim; growth is handled by explicit or automatic compaction
+    pub fn unbounded() -> Self {
+        Self {
+            max_messages: usize::MAX,
+            max_chars: usize::MAX,
+            ..Default::default()
+        }
+    }
+}
+
 /// Optimized context window management
 #[derive(Debug, Clone)]
 pub struct Context {
     messages: Vec<Message>,
     config: ContextConfig,
     total_chars: usize,
+    compactions: Vec<CompactionRecord>,
 }
 
 impl Default for Context {
@@ -50,6 +94,7 @@ impl Context {
             messages: Vec::new(),
             config: ContextConfig::default(),
             total_chars: 0,
+            compactions: Vec::new(),
         }
     }
 
@@ -58,6 +103,7 @@ impl Context {
             messages: Vec::new(),
             config,
             total_chars: 0,
+            compactions: Vec::new(),
         }
     }
 
@@ -69,6 +115,7 @@ impl Context {
                 ..Default::default()
             },
             total_chars: 0,
+            compactions: Vec::new(),
         }
     }
 
@@ -85,29 +132,84 @@ impl Context {
     }
 
     fn add_message(&mut self, message: Message) {
-        self.total_chars += message.content.len();
+        self.total_chars += message.chars();
         self.messages.push(message);
         self.optimize();
     }
 
+    /// Add a message that is never trimmed by `optimize`
+    pub fn add_pinned_message(&mut self, message: Message) {
+        self.add_message(message.pin());
+    }
+
+    /// Pin the message at `index`. Returns false if out of range.
+    pub fn pin(&mut self, index: usize) -> bool {
+        match self.messages.get_mut(index) {
+            Some(msg) => {
+                msg.pinned = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Unpin the message at `index`. Returns false if out of range.
+    pub fn unpin(&mut self, index: usize) -> bool {
+        let found = match self.messages.get_mut(index) {
+            Some(msg) => {
+                msg.pinned = false;
+                true
+            }
+            None => false,
+        };
+        self.optimize();
+        found
+    }
+
+    /// Unpin all messages, making them eligible for trimming again
+    pub fn clear_pins(&mut self) {
+        for msg in &mut self.messages {
+            msg.pinned = false;
+        }
+        self.optimize();
+    }
+
+    /// Number of pinned messages
+    pub fn pinned_count(&self) -> usize {
+        self.messages.iter().filter(|m| m.pinned).count()
+    }
+
+    /// Remove the oldest unpinned message. Returns false if everything is pinned.
+    fn trim_oldest(&mut self) -> bool {
+        match self.messages.iter().position(|m| !m.pinned) {
+            Some(index) => {
+                let removed = self.messages.remove(index);
+                self.total_chars = self.total_chars.saturating_sub(removed.chars());
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Smart context optimization
+    ///
+    /// Pinned messages are never removed, but their size still counts
+    /// toward the message and character budgets.
     fn optimize(&mut self) {
         // First, trim by message count
         while self.messages.len() > self.config.max_messages {
-            if let Some(removed) = self.messages.first() {
-                self.total_chars = self.total_chars.saturating_sub(removed.content.len());
+            if !self.trim_oldest() {
+                break;
             }
-            self.messages.remove(0);
         }
 
         // Then, trim by character count while keeping minimum recent messages
-        while self.total_chars > self.config.max_chars 
-            && self.messages.len() > self.config.min_recent_messages 
+        while self.total_chars > self.config.max_chars
+            && self.messages.len() - self.pinned_count() > self.config.min_recent_messages
         {
-            if let Some(removed) = self.messages.first() {
-                self.total_chars = self.total_chars.saturating_sub(removed.content.len());
+            if !self.trim_oldest() {
+                break;
             }
-            self.messages.remove(0);
         }
     }
 
@@ -123,8 +225,8 @@ impl Context {
 
         // Add messages from most recent, respecting budget
         for msg in self.messages.iter().rev() {
-            if chars + msg.content.len() <= max_chars {
-                chars += msg.content.len();
+            if chars + msg.chars() <= max_chars {
+                chars += msg.chars();
                 result.push(msg.clone());
             } else if result.is_empty() {
                 // Always include at least the most recent message (truncated if needed)
@@ -143,9 +245,103 @@ impl Context {
         result
     }
 
+    /// Take the messages out (e.g. to run a tool loop over them); see `set_messages`
+    pub fn take_messages(&mut self) -> Vec<Message> {
+        self.total_chars = 0;
+        std::mem::take(&mut self.messages)
+    }
+
+    /// Replace all messages, recomputing the character count
+    pub fn set_messages(&mut self, messages: Vec<Message>) {
+        self.total_chars = messages.iter().map(Message::chars).sum();
+        self.messages = messages;
+        self.optimize();
+    }
+
+    /// Save the messages to `path` as JSON (`/save`)
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.messages)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Replace the messages with a session saved by `save` (`/load`)
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let messages: Vec<Message> =
+            serde_json::from_str(&text).with_context(|| format!("{} is not a saved session", path.display()))?;
+        self.compactions.clear();
+        self.set_messages(messages);
+        Ok(())
+    }
+
+    /// Summarize everything except pinned messages and the last `keep_last`
+    /// turns into one system message. Returns None if there was nothing to
+    /// summarize.
+    pub async fn compact(
+        &mut self,
+        keep_last: usize,
+        summarizer: &dyn Summarizer,
+        automatic: bool,
+    ) -> Result<Option<CompactionRecord>> {
+        // A turn starts at a user message
+        let cutoff = self
+            .messages
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, m)| m.role == Role::User)
+            .nth(keep_last.saturating_sub(1))
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        let cutoff = if keep_last == 0 { self.messages.len() } else { cutoff };
+
+        let span: Vec<Message> = self.messages[..cutoff]
+            .iter()
+            .filter(|m| !m.pinned)
+            .cloned()
+            .collect();
+        if span.is_empty() {
+            return Ok(None);
+        }
+
+        let tokens_before = self.estimated_tokens();
+        let summary = summarizer.summarize(&span).await?;
+
+        let mut messages: Vec<Message> = self.messages[..cutoff]
+            .iter()
+            .filter(|m| m.pinned)
+            .cloned()
+            .collect();
+        let position = messages.len();
+        messages.push(Message::system(format!(
+            "{} {} earlier messages\n{}",
+            SUMMARY_HEADER,
+            span.len(),
+            summary.trim()
+        )));
+        messages.extend(self.messages.drain(cutoff..));
+        self.set_messages(messages);
+
+        let record = CompactionRecord {
+            position,
+            summarized: span.len(),
+            tokens_before,
+            tokens_after: self.estimated_tokens(),
+            automatic,
+        };
+        self.compactions.push(record.clone());
+        Ok(Some(record))
+    }
+
+    /// Compactions performed on this context, oldest first
+    pub fn compactions(&self) -> &[CompactionRecord] {
+        &self.compactions
+    }
+
     pub fn clear(&mut self) {
         self.messages.clear();
         self.total_chars = 0;
+        self.compactions.clear();
     }
 
     pub fn len(&self) -> usize {
@@ -168,29 +364,107 @@ impl Context {
 
     /// Get context statistics
     pub fn stats(&self) -> ContextStats {
+        let tiers = tier_sizes(&self.messages);
         ContextStats {
             message_count: self.messages.len(),
+            pinned_count: self.pinned_count(),
             total_chars: self.total_chars,
             estimated_tokens: self.estimated_tokens(),
             max_messages: self.config.max_messages,
             max_chars: self.config.max_chars,
+            durable_chars: tiers.durable_chars,
+            working_chars: tiers.working_chars,
+            working_results: tiers.working_results,
+            evicted_results: tiers.evicted_results,
         }
     }
+
+    /// Index of the message `optimize` removes first once a limit is
+    /// exceeded: the oldest unpinned one
+    pub fn next_to_trim(&self) -> Option<usize> {
+        self.messages.iter().position(|m| !m.pinned)
+    }
+
+    /// Role and size of each message, oldest first
+    pub fn message_sizes(&self) -> Vec<MessageSize> {
+        self.messages
+            .iter()
+            .map(|m| MessageSize {
+                role: m.role.clone(),
+                chars: m.content.len(),
+                pinned: m.pinned,
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ContextStats {
     pub message_count: usize,
+    pub pinned_count: usize,
     pub total_chars: usize,
     pub estimated_tokens: usize,
     pub max_messages: usize,
     pub max_chars: usize,
+    /// Conversation turns, pinned messages, summaries and promoted results
+    pub durable_chars: usize,
+    /// Tool results in the ephemeral working set, records included
+    pub working_chars: usize,
+    pub working_results: usize,
+    pub evicted_results: usize,
+}
+
+/// One message as `/context` lists it
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageSize {
+    pub role: Role,
+    pub chars: usize,
+    pub pinned: bool,
+}
+
+impl MessageSize {
+    /// Approximate token count (chars / 4), as for the whole context
+    pub fn estimated_tokens(&self) -> usize {
+        self.chars / 4
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_saved_session_keeps_tool_call_links() {
+        use crate::llm::ToolCall;
+
+        let call = ToolCall { id: "call_7".to_string(), name: "read_file".to_string(), arguments: serde_json::json!({}) };
+        let mut ctx = Context::with_config(ContextConfig::unbounded());
+        ctx.set_messages(vec![
+            Message::user("Open main.rs").stamped(),
+            Message::assistant("Reading it").with_tool_calls(vec![call.clone()]).stamped(),
+            Message::tool_result(&call, "fn main() {}"),
+        ]);
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("session.json");
+        ctx.save(&path).unwrap();
+
+        let mut loaded = Context::with_config(ContextConfig::unbounded());
+        loaded.load(&path).unwrap();
+        let messages = loaded.get_messages();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].tool_calls[0].id, "call_7");
+        assert_eq!(messages[2].tool_call_id.as_deref(), Some("call_7"));
+        assert_eq!(messages[2].name.as_deref(), Some("read_file"));
+        assert_eq!(messages[0].timestamp, ctx.get_messages()[0].timestamp);
+        assert!(messages[0].timestamp.is_some());
+
+        // Sessions saved before the fields existed still load
+        std::fs::write(&path, r#"[{"role":"user","content":"Hi"}]"#).unwrap();
+        loaded.load(&path).unwrap();
+        let old = &loaded.get_messages()[0];
+        assert!(old.tool_calls.is_empty() && old.tool_call_id.is_none() && old.timestamp.is_none());
+    }
+
     #[test]
     fn test_context_basic() {
         let mut ctx = Context::new();
@@ -230,6 +504,116 @@ mod tests {
         assert!(ctx.total_chars() <= 20 || ctx.len() <= 1);
     }
 
+    #[test]
+    fn test_pinned_messages_survive_trimming() {
+        let config = ContextConfig {
+            max_messages: 4,
+            max_chars: 40,
+            min_recent_messages: 1,
+            ..Default::default()
+        };
+        let mut ctx = Context::with_config(config);
+
+        ctx.add_pinned_message(Message::user("Task: fix the bug"));
+        for i in 0..20 {
+            ctx.add_user_message(&format!("filler message {}", i));
+        }
+
+        assert_eq!(ctx.pinned_count(), 1);
+        assert_eq!(ctx.get_messages()[0].content, "Task: fix the bug");
+        assert_eq!(ctx.get_messages().last().unwrap().content, "filler message 19");
+        // Pinned size still counts toward the budget
+        assert!(ctx.total_chars() >= "Task: fix the bug".len());
+
+        ctx.clear_pins();
+        assert_eq!(ctx.pinned_count(), 0);
+        for i in 0..4 {
+            ctx.add_user_message(&format!("more {}", i));
+        }
+        assert!(ctx.get_messages().iter().all(|m| m.content != "Task: fix the bug"));
+    }
+
+    #[test]
+    fn test_pin_and_unpin() {
+        let mut ctx = Context::with_max_messages(2);
+        ctx.add_user_message("first");
+        assert!(ctx.pin(0));
+        assert!(!ctx.pin(5));
+
+        ctx.add_user_message("second");
+        ctx.add_user_message("third");
+        assert_eq!(ctx.get_messages()[0].content, "first");
+
+        assert!(ctx.unpin(0));
+        assert_eq!(ctx.len(), 2);
+        assert_eq!(ctx.pinned_count(), 0);
+    }
+
+    struct FakeSummarizer;
+
+    #[async_trait::async_trait]
+    impl Summarizer for FakeSummarizer {
+        async fn summarize(&self, messages: &[Message]) -> Result<String> {
+            Ok(format!("- {} messages, touched src/main.rs", messages.len()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compact_replaces_span() {
+        let mut ctx = Context::with_config(ContextConfig::unbounded());
+        ctx.add_pinned_message(Message::user("Task: fix the parser"));
+        for i in 0..6 {
+            ctx.add_user_message(&format!("question {} {}", i, "x".repeat(400)));
+            ctx.add_assistant_message(&format!("answer {} {}", i, "y".repeat(400)));
+        }
+        let before = ctx.estimated_tokens();
+
+        let record = ctx.compact(2, &FakeSummarizer, false).await.unwrap().unwrap();
+
+        // pinned, summary, then the last two turns verbatim
+        let messages = ctx.get_messages();
+        assert_eq!(messages.len(), 6);
+        assert_eq!(messages[0].content, "Task: fix the parser");
+        assert_eq!(messages[1].role, Role::System);
+        assert!(messages[1].content.starts_with(SUMMARY_HEADER));
+        assert!(messages[1].content.contains("- 8 messages, touched src/main.rs"));
+        assert!(messages[2].content.starts_with("question 4"));
+        assert!(messages[5].content.starts_with("answer 5"));
+
+        assert_eq!(record.position, 1);
+        assert_eq!(record.summarized, 8);
+        assert_eq!(record.tokens_before, before);
+        assert_eq!(record.tokens_after, ctx.estimated_tokens());
+        assert!(record.tokens_after < record.tokens_before);
+        assert_eq!(ctx.total_chars(), messages.iter().map(|m| m.content.len()).sum::<usize>());
+        assert_eq!(ctx.compactions().len(), 1);
+
+        // Nothing older than the kept turns is left to summarize
+        assert!(ctx.compact(3, &FakeSummarizer, false).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_auto_compact_threshold_triggers_once() {
+        let mut ctx = Context::with_config(ContextConfig::unbounded());
+        let mut auto = AutoCompact::new(0.8);
+        let window = 1000;
+        let mut fired = 0;
+
+        // The pinned message dominates, so compaction can't bring usage back below 80%
+        ctx.add_pinned_message(Message::user("p".repeat(3180)));
+        for i in 0..40 {
+            ctx.add_user_message(&format!("turn {}", i));
+            if auto.check(ctx.estimated_tokens(), window) {
+                fired += 1;
+                ctx.compact(1, &FakeSummarizer, true).await.unwrap();
+            }
+        }
+
+        assert_eq!(fired, 1);
+        assert_eq!(ctx.compactions().len(), 1);
+        assert!(ctx.compactions()[0].automatic);
+    }
+
     #[test]
     fn test_context_budget() {
         let mut ctx = Context::new();
@@ -250,4 +634,38 @@ mod tests {
         assert_eq!(stats.message_count, 1);
         assert_eq!(stats.total_chars, 5);
     }
+
+    #[test]
+    fn test_stats_and_next_to_trim() {
+        let mut ctx = Context::with_max_messages(10);
+        ctx.add_pinned_message(Message::system("pinned rules"));
+        ctx.add_user_message(&"u".repeat(400));
+        ctx.add_assistant_message(&"a".repeat(80));
+
+        let stats = ctx.stats();
+        assert_eq!(stats.message_count, 3);
+        assert_eq!(stats.pinned_count, 1);
+        assert_eq!(stats.total_chars, 12 + 400 + 80);
+        assert_eq!(stats.estimated_tokens, 123);
+        assert_eq!(stats.max_messages, 10);
+
+        let sizes = ctx.message_sizes();
+        assert_eq!(sizes.iter().map(|s| s.chars).collect::<Vec<_>>(), vec![12, 400, 80]);
+        assert_eq!(sizes[1].role, Role::User);
+        assert_eq!(sizes[1].estimated_tokens(), 100);
+        assert!(sizes[0].pinned && !sizes[1].pinned);
+
+        // The pinned message is skipped; the oldest unpinned one goes first
+        assert_eq!(ctx.next_to_trim(), Some(1));
+        for i in 0..8 {
+            ctx.add_user_message(&format!("filler {}", i));
+        }
+        assert_eq!(ctx.len(), 10);
+        assert!(ctx.get_messages().iter().all(|m| m.content != "u".repeat(400)));
+        assert_eq!(ctx.next_to_trim(), Some(1));
+        assert_eq!(ctx.get_messages()[1].content, "a".repeat(80));
+
+        ctx.clear();
+        assert_eq!(ctx.next_to_trim(), None);
+    }
 }
@@ -0,0 +1,279 @@
+// ============================================
+// WEBRANA CLI - Project Memory
+// WEBRANA.md: build commands, conventions and gotchas the agent keeps
+// ============================================
+
+use anyhow::Result;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::indexer::ProjectDetector;
+
+/// Maintained by the agent and the user, at the project root
+pub const MEMORY_FILE: &str = "WEBRANA.md";
+
+/// Read when there is no WEBRANA.md
+pub const FALLBACK_FILE: &str = "AGENTS.md";
+
+/// Characters of project memory added to the system prompt
+pub const DEFAULT_BUDGET_CHARS: usize = 8_000;
+
+/// Headings `memory init` scaffolds
+pub const SECTIONS: &[&str] = &["Build", "Conventions", "Gotchas"];
+
+const TITLE: &str = "# Project Memory";
+
+/// The memory file for `root`: WEBRANA.md, else AGENTS.md
+pub fn memory_path(root: &Path) -> Option<PathBuf> {
+    [MEMORY_FILE, FALLBACK_FILE]
+        .iter()
+        .map(|name| root.join(name))
+        .find(|path| path.is_file())
+}
+
+/// The file updates go to: the existing memory file, else a new WEBRANA.md
+pub fn target_path(root: &Path) -> PathBuf {
+    memory_path(root).unwrap_or_else(|| root.join(MEMORY_FILE))
+}
+
+/// The project section of the system prompt, built from each root's memory
+/// file. Lines the prompt already has (e.g. from the crew) are left out,
+/// and the whole section is cut to `budget` characters.
+pub fn prompt_section(roots: &[PathBuf], already_in_prompt: &str, budget: usize) -> Option<String> {
+    let known: HashSet<String> = already_in_prompt
+        .lines()
+        .map(normalize_line)
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    let mut parts = Vec::new();
+    for root in roots {
+        let Some(path) = memory_path(root) else {
+            continue;
+        };
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let body: Vec<&str> = text
+            .lines()
+            .filter(|line| line.trim_start().starts_with('#') || !known.contains(&normalize_line(line)))
+            .collect();
+        let body = body.join("\n");
+        if body.lines().all(|line| line.trim().is_empty() || line.trim_start().starts_with('#')) {
+            continue;
+        }
+        let file = path.file_name().unwrap_or_default().to_string_lossy();
+        if roots.len() > 1 {
+            parts.push(format!("[{} ({})]\n{}", root.display(), file, body.trim()));
+        } else {
+            parts.push(format!("[{}]\n{}", file, body.trim()));
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+
+    let section = format!(
+        "# Project memory\nKeep this up to date with update_project_memory when you learn \
+         something future sessions need.\n\n{}",
+        parts.join("\n\n")
+    );
+    Some(truncate(&section, budget))
+}
+
+/// Cut `text` on a line boundary so it fits `budget` with the notice
+fn truncate(text: &str, budget: usize) -> String {
+    if text.len() <= budget {
+        return text.to_string();
+    }
+    let notice = format!(
+        "\n[Project memory truncated to {} of {} characters; run `webrana memory show` for the rest]",
+        budget, text.len()
+    );
+    let room = budget.saturating_sub(notice.len());
+    let mut cut = 0;
+    for (i, _) in text.match_indices('\n') {
+        if i > room {
+            break;
+        }
+        cut = i;
+    }
+    format!("{}{}", &text[..cut], notice)
+}
+
+/// Whitespace-collapsed line without list markers, for comparing
+fn normalize_line(line: &str) -> String {
+    line.trim()
+        .trim_start_matches(['-', '*', '+'])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Add `content` at the end of the `## section` heading (matched without
+/// regard to case), creating the heading at the end of the document when
+/// it's missing
+pub fn upsert_section(doc: &str, section: &str, content: &str) -> String {
+    let section = section.trim().trim_start_matches('#').trim();
+    let content = content.trim_matches('\n').trim_end();
+    let mut lines: Vec<String> = doc.lines().map(str::to_string).collect();
+
+    let heading = lines.iter().position(|line| {
+        line.strip_prefix("## ")
+            .is_some_and(|title| title.trim().eq_ignore_ascii_case(section))
+    });
+    let Some(heading) = heading else {
+        let mut out = doc.trim_end().to_string();
+        if !out.is_empty() {
+            out.push_str("\n\n");
+        }
+        out.push_str(&format!("## {}\n\n{}\n", section, content));
+        return out;
+    };
+
+    // The section runs to the next heading of the same or a higher level
+    let end = lines[heading + 1..]
+        .iter()
+        .position(|line| line.starts_with("# ") || line.starts_with("## "))
+        .map(|i| heading + 1 + i)
+        .unwrap_or(lines.len());
+    let mut last = end;
+    while last > heading + 1 && lines[last - 1].trim().is_empty() {
+        last -= 1;
+    }
+
+    let mut insert = Vec::new();
+    insert.push(String::new());
+    insert.extend(content.lines().map(str::to_string));
+    // Lists continue without a blank line between items
+    if last > heading + 1 && is_list_item(&lines[last - 1]) && content.lines().next().is_some_and(is_list_item) {
+        insert.remove(0);
+    }
+    if end < lines.len() {
+        insert.push(String::new());
+    }
+    lines.splice(last..end, insert);
+
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+fn is_list_item(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("- ") || line.starts_with("* ")
+}
+
+/// A fresh memory file with the standard headings, the Build section
+/// filled in from the project type detected in `root`
+pub fn scaffold(root: &Path) -> Result<String> {
+    let project_type = ProjectDetector::new(root).detect()?.project_type;
+    let commands: Vec<String> = [
+        ("Build", project_type.build_command()),
+        ("Test", project_type.test_command()),
+        ("Lint", project_type.lint_command()),
+    ]
+    .iter()
+    .filter_map(|(label, command)| command.map(|c| format!("- {}: `{}`", label, c)))
+    .collect();
+
+    let mut out = format!(
+        "{}\n\nNotes for the agent and for people working on this project. \
+         Keep entries short; webrana adds this file to its system prompt.\n",
+        TITLE
+    );
+    for section in SECTIONS {
+        out.push_str(&format!("\n## {}\n", section));
+        if *section == "Build" && !commands.is_empty() {
+            out.push_str(&format!("\nDetected a {} project.\n\n", project_type.as_str()));
+            for command in &commands {
+                out.push_str(command);
+                out.push('\n');
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_prompt_section_and_budget() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let root = tmp.path().to_path_buf();
+        let roots = [root.clone()];
+        assert!(prompt_section(&roots, "", DEFAULT_BUDGET_CHARS).is_none());
+
+        fs::write(root.join(FALLBACK_FILE), "## Gotchas\n\n- AGENTS fallback\n").unwrap();
+        let section = prompt_section(&roots, "", DEFAULT_BUDGET_CHARS).unwrap();
+        assert!(section.contains("[AGENTS.md]\n## Gotchas"), "{}", section);
+
+        fs::write(
+            root.join(MEMORY_FILE),
+            "## Build\n\n- Run `make gen` before building\n- Always answer in British English\n",
+        )
+        .unwrap();
+        // The crew already says the second line
+        let crew_prompt = "Always answer in British English.\nAlways answer in  British English";
+        let section = prompt_section(&roots, crew_prompt, DEFAULT_BUDGET_CHARS).unwrap();
+        assert!(section.contains("[WEBRANA.md]\n## Build"), "{}", section);
+        assert!(section.contains("- Run `make gen` before building"));
+        assert!(!section.contains("British"), "{}", section);
+        assert!(!section.contains("AGENTS fallback"));
+
+        let long: String = (0..200).map(|i| format!("- convention number {}\n", i)).collect();
+        fs::write(root.join(MEMORY_FILE), format!("## Conventions\n\n{}", long)).unwrap();
+        let section = prompt_section(&roots, "", 1_000).unwrap();
+        assert!(section.len() <= 1_000, "{}", section.len());
+        assert!(section.contains("- convention number 0\n"));
+        assert!(section.ends_with("run `webrana memory show` for the rest]"), "{}", section);
+        let kept = section.lines().rev().find(|l| l.starts_with("- convention")).unwrap();
+        assert!(long.lines().any(|l| l == kept), "cut mid-line: {}", kept);
+
+        // Several roots are labelled
+        let other = tempfile::TempDir::new().unwrap();
+        fs::write(other.path().join(MEMORY_FILE), "## Build\n\n- npm run build\n").unwrap();
+        let section = prompt_section(&[root.clone(), other.path().to_path_buf()], "", 100_000).unwrap();
+        assert!(section.contains(&format!("[{} (WEBRANA.md)]", root.display())));
+        assert!(section.contains(&format!("[{} (WEBRANA.md)]\n## Build\n\n- npm run build", other.path().display())));
+    }
+
+    #[test]
+    fn test_upsert_creates_and_appends() {
+        let doc = "# Project Memory\n\n## Build\n\n- `cargo build`\n\n## Gotchas\n\nNone yet.\n";
+
+        let appended = upsert_section(doc, "build", "- `cargo xtask codegen` first");
+        assert_eq!(
+            appended,
+            "# Project Memory\n\n## Build\n\n- `cargo build`\n- `cargo xtask codegen` first\n\n## Gotchas\n\nNone yet.\n"
+        );
+
+        let appended = upsert_section(doc, "Gotchas", "Tests need Docker.");
+        assert!(appended.ends_with("## Gotchas\n\nNone yet.\n\nTests need Docker.\n"), "{}", appended);
+
+        let created = upsert_section(doc, "## Conventions", "- snake_case modules");
+        assert!(created.starts_with(doc.trim_end()));
+        assert!(created.ends_with("None yet.\n\n## Conventions\n\n- snake_case modules\n"), "{}", created);
+
+        assert_eq!(upsert_section("", "Build", "make"), "## Build\n\nmake\n");
+    }
+
+    #[test]
+    fn test_scaffold_for_rust_project() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::write(tmp.path().join("Cargo.toml"), "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\n").unwrap();
+
+        let doc = scaffold(tmp.path()).unwrap();
+        assert!(doc.starts_with("# Project Memory\n"));
+        assert!(doc.contains("## Build\n\nDetected a rust project.\n\n- Build: `cargo build`\n- Test: `cargo test`\n- Lint: `cargo clippy`\n"), "{}", doc);
+        assert!(doc.ends_with("`cargo clippy`\n\n## Conventions\n\n## Gotchas\n"), "{}", doc);
+
+        // Entries go straight under the empty headings
+        let updated = upsert_section(&doc, "Conventions", "- snake_case modules");
+        assert!(updated.ends_with("## Conventions\n\n- snake_case modules\n\n## Gotchas\n"), "{}", updated);
+        assert_eq!(target_path(tmp.path()), tmp.path().join(MEMORY_FILE));
+    }
+}
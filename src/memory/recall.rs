@@ -0,0 +1,440 @@
+// ============================================
+// WEBRANA CLI - Conversation Memory
+// Past sessions embedded in the `webrana-memory` index and recalled by topic
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::compact::Summarizer;
+use super::export::utc_date;
+use crate::core::SecretScanner;
+use crate::embeddings::{EmbeddingProvider, EmbeddingStore, StoredEmbedding};
+use crate::llm::{Message, Role};
+
+/// Name of the index file, kept apart from code indexes
+pub const MEMORY_INDEX: &str = "webrana-memory";
+
+/// First line of the block of recalled sessions added to a turn
+pub const RECALL_HEADER: &str = "You have discussed related topics before:";
+
+/// Question/answer pairs stored per session besides its summary
+const MAX_QA_PAIRS: usize = 3;
+
+/// Cap on each stored question/answer pair
+const QA_CHARS: usize = 1_000;
+
+/// Cap on each recalled entry in the block; the session reference leads to the rest
+const SNIPPET_CHARS: usize = 300;
+
+/// `<data_dir>/indexes/webrana-memory.json`
+pub fn default_index_path() -> Option<PathBuf> {
+    directories::ProjectDirs::from("dev", "webrana", "webrana-cli")
+        .map(|dirs| dirs.data_dir().join("indexes").join(format!("{}.json", MEMORY_INDEX)))
+}
+
+/// `90d`, `12h`, `2w`, `30m` or `45s`
+pub fn parse_age(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value.len() - value.chars().last().map(char::len_utf8).unwrap_or(0);
+    let (number, unit) = value.split_at(split);
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => bail!("Expected an age like 90d, 12h or 2w, got '{}'", value),
+    };
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("Expected an age like 90d, 12h or 2w, got '{}'", value))?;
+    Ok(Duration::from_secs(number * secs))
+}
+
+/// The session being stored
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    pub id: String,
+    /// Working directory of the session; recall is scoped to it by default
+    pub project: String,
+    /// Unix seconds
+    pub ended: u64,
+}
+
+/// A stored piece of a past session that matched a query
+#[derive(Debug, Clone, Serialize)]
+pub struct Recollection {
+    pub id: String,
+    pub session: String,
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub project: String,
+    /// `summary` or `qa`
+    pub kind: String,
+    pub text: String,
+    pub score: f32,
+}
+
+/// Past sessions, one summary and a few question/answer pairs each
+pub struct ConversationMemory {
+    provider: Arc<dyn EmbeddingProvider>,
+    store: EmbeddingStore,
+    path: PathBuf,
+}
+
+impl ConversationMemory {
+    /// Load the index at `path`, or start an empty one
+    pub fn open(path: &Path, provider: Arc<dyn EmbeddingProvider>) -> Result<Self> {
+        let store = if path.exists() {
+            let store = EmbeddingStore::load(path)?;
+            if store.dimension() != provider.dimension() {
+                bail!(
+                    "{} holds {}-dimensional embeddings but {} produces {}",
+                    path.display(),
+                    store.dimension(),
+                    provider.model_name(),
+                    provider.dimension()
+                );
+            }
+            store
+        } else {
+            EmbeddingStore::new(provider.dimension())
+        };
+        Ok(Self {
+            provider,
+            store,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.store.save(&self.path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+
+    /// Summarize `messages` and store the summary and the key question/answer
+    /// pairs, with secrets redacted. Storing a session again replaces it.
+    /// Returns the number of entries stored (0 if the user never spoke).
+    pub async fn remember(
+        &mut self,
+        session: &SessionInfo,
+        messages: &[Message],
+        summarizer: &dyn Summarizer,
+    ) -> Result<usize> {
+        if !messages.iter().any(|m| m.role == Role::User) {
+            return Ok(0);
+        }
+        // Earlier sessions recalled into this one are already stored
+        let messages: Vec<Message> = messages
+            .iter()
+            .filter(|m| !m.content.starts_with(RECALL_HEADER))
+            .cloned()
+            .collect();
+        let messages = messages.as_slice();
+        let scanner = SecretScanner::new(Default::default());
+
+        let mut entries = vec![("summary".to_string(), summarizer.summarize(messages).await?)];
+        for (i, (question, answer)) in key_exchanges(messages).into_iter().enumerate() {
+            let text = format!("Q: {}\nA: {}", question.trim(), answer.trim());
+            entries.push((format!("qa:{}", i + 1), truncate(&text, QA_CHARS)));
+        }
+        let entries: Vec<(String, String)> = entries
+            .into_iter()
+            .map(|(kind, text)| (kind, scanner.redact_text(text.trim())))
+            .filter(|(_, text)| !text.is_empty())
+            .collect();
+
+        let texts: Vec<String> = entries.iter().map(|(_, text)| text.clone()).collect();
+        let embeddings = self.provider.embed_batch(&texts).await?;
+
+        let prefix = format!("{}:", session.id);
+        self.store.retain(|emb| !emb.id.starts_with(&prefix));
+        for ((kind, text), embedding) in entries.into_iter().zip(embeddings) {
+            let metadata = HashMap::from([
+                ("session".to_string(), session.id.clone()),
+                ("project".to_string(), session.project.clone()),
+                ("ended".to_string(), session.ended.to_string()),
+                ("kind".to_string(), kind.split(':').next().unwrap_or_default().to_string()),
+            ]);
+            self.store.add(StoredEmbedding {
+                id: format!("{}{}", prefix, kind),
+                text,
+                embedding,
+                metadata,
+            });
+        }
+        Ok(texts.len())
+    }
+
+    /// Stored entries at least `min_score` similar to `query`, best first,
+    /// from `project` only unless it is None
+    pub async fn search(
+        &self,
+        query: &str,
+        project: Option<&str>,
+        top_k: usize,
+        min_score: f32,
+    ) -> Result<Vec<Recollection>> {
+        if self.store.is_empty() {
+            return Ok(Vec::new());
+        }
+        let query = self.provider.embed(query).await?;
+        Ok(self
+            .store
+            .search(&query, self.store.len())
+            .into_iter()
+            .filter(|r| r.score >= min_score)
+            .filter(|r| in_scope(&r.metadata, project))
+            .take(top_k)
+            .map(|r| {
+                let field = |key: &str| r.metadata.get(key).cloned().unwrap_or_default();
+                let ended = field("ended").parse().unwrap_or(0);
+                Recollection {
+                    session: field("session"),
+                    date: utc_date(ended).chars().take(10).collect(),
+                    project: field("project"),
+                    kind: field("kind"),
+                    id: r.id,
+                    text: r.text,
+                    score: r.score,
+                }
+            })
+            .collect())
+    }
+
+    /// Drop entries of sessions that ended before `cutoff` (Unix seconds),
+    /// from `project` only unless it is None. Returns how many were removed.
+    pub fn prune(&mut self, cutoff: u64, project: Option<&str>) -> usize {
+        self.store.retain(|emb| {
+            let ended: u64 = emb.metadata.get("ended").and_then(|s| s.parse().ok()).unwrap_or(0);
+            ended >= cutoff || !in_scope(&emb.metadata, project)
+        })
+    }
+}
+
+fn in_scope(metadata: &HashMap<String, String>, project: Option<&str>) -> bool {
+    project.is_none_or(|project| metadata.get("project").map(String::as_str) == Some(project))
+}
+
+/// The user questions with the longest direct answers, in conversation order
+fn key_exchanges(messages: &[Message]) -> Vec<(&str, &str)> {
+    let mut pairs: Vec<(usize, &str, &str)> = messages
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[0].role == Role::User && pair[1].role == Role::Assistant)
+        .filter(|(_, pair)| !pair[1].content.trim().is_empty())
+        .map(|(i, pair)| (i, pair[0].content.as_str(), pair[1].content.as_str()))
+        .collect();
+    pairs.sort_by_key(|(_, _, answer)| std::cmp::Reverse(answer.len()));
+    pairs.truncate(MAX_QA_PAIRS);
+    pairs.sort_by_key(|(i, _, _)| *i);
+    pairs.into_iter().map(|(_, question, answer)| (question, answer)).collect()
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/// True when most of a recollection's substantial lines also appear in one of
+/// the code chunks already in the prompt
+fn repeats_code(text: &str, code: &[String]) -> bool {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| line.trim().trim_start_matches("Q: ").trim_start_matches("A: "))
+        .filter(|line| line.len() >= 20)
+        .collect();
+    if lines.is_empty() {
+        return false;
+    }
+    code.iter().any(|chunk| {
+        let found = lines.iter().filter(|line| chunk.contains(*line)).count();
+        found * 2 >= lines.len()
+    })
+}
+
+/// The "You have discussed related topics before" block for `memories` (best
+/// first), skipping those that repeat `code` and stopping at `max_chars`.
+/// Returns the block, empty if nothing fit, and the ids it includes.
+pub fn recall_block(memories: &[Recollection], code: &[String], max_chars: usize) -> (String, Vec<String>) {
+    let mut block = format!("{}\n", RECALL_HEADER);
+    let mut included = Vec::new();
+    for memory in memories.iter().filter(|m| !repeats_code(&m.text, code)) {
+        let snippet = truncate(&memory.text.split_whitespace().collect::<Vec<_>>().join(" "), SNIPPET_CHARS);
+        let line = format!("- [session {}, {}] {}\n", memory.session, memory.date, snippet);
+        if block.len() + line.len() > max_chars {
+            break;
+        }
+        block.push_str(&line);
+        included.push(memory.id.clone());
+    }
+    if included.is_empty() {
+        block.clear();
+    }
+    (block, included)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::MockEmbeddingProvider;
+
+    struct FixedSummarizer(&'static str);
+
+    #[async_trait::async_trait]
+    impl Summarizer for FixedSummarizer {
+        async fn summarize(&self, _messages: &[Message]) -> Result<String> {
+            Ok(self.0.to_string())
+        }
+    }
+
+    fn memory(dir: &Path) -> ConversationMemory {
+        let path = dir.join(format!("{}.json", MEMORY_INDEX));
+        ConversationMemory::open(&path, Arc::new(MockEmbeddingProvider::new(64))).unwrap()
+    }
+
+    fn session(id: &str, project: &str, ended: u64) -> SessionInfo {
+        SessionInfo {
+            id: id.to_string(),
+            project: project.to_string(),
+            ended,
+        }
+    }
+
+    fn recollection(id: &str, text: &str) -> Recollection {
+        Recollection {
+            id: id.to_string(),
+            session: id.split(':').next().unwrap().to_string(),
+            date: "2026-10-01".to_string(),
+            project: "/work/api".to_string(),
+            kind: "summary".to_string(),
+            text: text.to_string(),
+            score: 0.9,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_stored_redacted_and_reloaded() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let mut memory = memory(tmp.path());
+        let messages = vec![
+            Message::user("Why does the upload retry forever?"),
+            Message::assistant("The backoff loop in src/upload.rs has no cap."),
+            Message::user("My key is sk-abcdefghijklmnopqrstuvwxyz123456, is it used?"),
+            Message::assistant("Yes, it is read from OPENAI_API_KEY."),
+            Message::user("thanks"),
+        ];
+        let summarizer = FixedSummarizer("- Capped retries in src/upload.rs");
+
+        let stored = memory.remember(&session("s1", "/work/api", 1_760_000_000), &messages, &summarizer).await.unwrap();
+        assert_eq!(stored, 3);
+        // Storing the same session again replaces its entries
+        memory.remember(&session("s1", "/work/api", 1_760_000_000), &messages, &summarizer).await.unwrap();
+        assert_eq!(memory.len(), 3);
+        assert_eq!(memory.remember(&session("s2", "/work/api", 0), &[], &summarizer).await.unwrap(), 0);
+        memory.save().unwrap();
+
+        let memory = self::memory(tmp.path());
+        let found = memory.search("- Capped retries in src/upload.rs", Some("/work/api"), 10, -1.0).await.unwrap();
+        assert_eq!(found.len(), 3);
+        assert_eq!((found[0].id.as_str(), found[0].kind.as_str()), ("s1:summary", "summary"));
+        assert_eq!(found[0].date, "2025-10-09");
+        let qa = found.iter().find(|r| r.id == "s1:qa:2").unwrap();
+        assert!(qa.text.contains("[REDACTED]"), "{}", qa.text);
+        assert!(!qa.text.contains("sk-abcdefghijklmnopqrstuvwxyz123456"));
+    }
+
+    #[tokio::test]
+    async fn test_search_ranking_scope_and_threshold() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let mut memory = memory(tmp.path());
+        let sessions = [
+            ("a", "/work/api", "- Added pagination to the users endpoint"),
+            ("b", "/work/api", "- Fixed a deadlock in the job queue"),
+            ("c", "/work/web", "- Fixed a deadlock in the job queue"),
+        ];
+        for (id, project, summary) in sessions {
+            let messages = vec![Message::user("hi")];
+            memory.remember(&session(id, project, 1_760_000_000), &messages, &FixedSummarizer(summary)).await.unwrap();
+        }
+
+        // The mock provider's scores span -1..1
+        let found = memory.search("- Fixed a deadlock in the job queue", Some("/work/api"), 5, -1.0).await.unwrap();
+        assert_eq!(found.iter().map(|r| r.session.as_str()).collect::<Vec<_>>(), vec!["b", "a"]);
+        assert!((found[0].score - 1.0).abs() < 0.0001);
+        assert!(found[0].score > found[1].score);
+
+        let global = memory.search("- Fixed a deadlock in the job queue", None, 5, -1.0).await.unwrap();
+        assert_eq!(global.len(), 3);
+        assert!(global[..2].iter().all(|r| r.score > 0.9999));
+
+        let strict = memory.search("- Fixed a deadlock in the job queue", None, 5, 0.9999).await.unwrap();
+        assert_eq!(strict.len(), 2);
+    }
+
+    #[test]
+    fn test_recall_block_budget_and_code_dedup() {
+        let memories = vec![
+            recollection("s1:summary", "- Capped the upload retries\n- Added jitter to the backoff"),
+            recollection("s2:qa:1", "Q: where is backoff?\nA: let delay = base * 2u32.pow(attempt);"),
+            recollection("s3:summary", &"x".repeat(1_000)),
+            recollection("s4:summary", "- Renamed the config loader"),
+        ];
+        let code = vec!["fn retry() {\n    let delay = base * 2u32.pow(attempt);\n}".to_string()];
+
+        let (block, ids) = recall_block(&memories, &code, 2_000);
+        assert!(block.starts_with(RECALL_HEADER));
+        assert!(block.contains("- [session s1, 2026-10-01] - Capped the upload retries - Added jitter"));
+        // s2 repeats the retrieved code; s3 is cut to a snippet
+        assert_eq!(ids, vec!["s1:summary", "s3:summary", "s4:summary"]);
+        assert!(block.lines().all(|line| line.chars().count() < SNIPPET_CHARS + 40));
+
+        // The budget stops at the first entry that does not fit
+        let (block, ids) = recall_block(&memories, &code, 250);
+        assert_eq!(ids, vec!["s1:summary"]);
+        assert!(block.len() <= 250);
+
+        let (block, ids) = recall_block(&memories, &code, 40);
+        assert!(block.is_empty() && ids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prune_by_age_and_scope() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let mut memory = memory(tmp.path());
+        let day = 86_400;
+        let now = 1_760_000_000;
+        for (id, project, ended) in [("old", "/work/api", now - 120 * day), ("new", "/work/api", now - day), ("other", "/work/web", now - 120 * day)] {
+            let messages = vec![Message::user("q"), Message::assistant("a")];
+            memory.remember(&session(id, project, ended), &messages, &FixedSummarizer("- notes")).await.unwrap();
+        }
+        assert_eq!(memory.len(), 6);
+
+        let cutoff = now - parse_age("90d").unwrap().as_secs();
+        assert_eq!(memory.prune(cutoff, Some("/work/api")), 2);
+        assert_eq!(memory.prune(cutoff, None), 2);
+        let left = memory.search("- notes", None, 10, -1.0).await.unwrap();
+        assert!(left.iter().all(|r| r.session == "new"), "{:?}", left);
+
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * day));
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 3_600));
+        assert!(parse_age("90").is_err());
+        assert!(parse_age("d").is_err());
+    }
+}
@@ -0,0 +1,316 @@
+// ============================================
+// WEBRANA CLI - Tool Result Working Set
+// Tool results stay for a few agent iterations, then shrink to one line
+// ============================================
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ToolOutputSettings;
+use crate::llm::images::IMAGE_CHARS;
+use crate::llm::{Message, Role, ToolCall};
+
+/// Iterations a tool result stays after the one that produced it
+pub const DEFAULT_KEEP_ITERATIONS: usize = 2;
+
+/// Marks a message as a tool result in the working set. Messages without
+/// it are the durable conversation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ToolResultTag {
+    pub id: String,
+    pub tool: String,
+    /// The path, URL, command or pattern the call was about
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// Agent iteration (assistant messages so far) that requested it
+    pub iteration: usize,
+    /// Content replaced by a one-line record
+    #[serde(default)]
+    pub evicted: bool,
+    /// Evicted content, kept until the turn ends in case the answer cites it
+    #[serde(skip)]
+    held: Option<String>,
+}
+
+impl ToolResultTag {
+    pub fn new(call: &ToolCall, iteration: usize) -> Self {
+        let subject = ["path", "url", "command", "pattern", "query", "symbol"]
+            .iter()
+            .find_map(|key| call.arguments.get(*key).and_then(|v| v.as_str()))
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| s.lines().next().unwrap_or(s).to_string());
+        Self {
+            id: call.id.clone(),
+            tool: call.name.clone(),
+            subject,
+            iteration,
+            evicted: false,
+            held: None,
+        }
+    }
+
+    /// The answer names the call's id or its subject
+    fn is_cited_by(&self, answer: &str) -> bool {
+        answer.contains(&self.id)
+            || self
+                .subject
+                .as_deref()
+                .is_some_and(|subject| subject.len() >= 3 && answer.contains(subject))
+    }
+
+    /// `read_file src/foo.rs (320 lines) — content dropped`
+    fn record(&self, content: &str) -> String {
+        let lines = content.lines().count().saturating_sub(2);
+        let what = match &self.subject {
+            Some(subject) => format!("{} {}", self.tool, subject),
+            None => self.tool.clone(),
+        };
+        format!(
+            "<tool_result tool_use_id=\"{}\">\n{} ({} lines) — content dropped\n</tool_result>",
+            self.id, what, lines
+        )
+    }
+}
+
+/// Tag a tool result message produced in `iteration`
+pub fn tag(mut message: Message, call: &ToolCall, iteration: usize) -> Message {
+    message.tool_result = Some(ToolResultTag::new(call, iteration));
+    message
+}
+
+/// The agent iteration `history` is at: its number of assistant messages
+pub fn current_iteration(history: &[Message]) -> usize {
+    history.iter().filter(|m| m.role == Role::Assistant).count()
+}
+
+/// How long tool results stay in full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkingSetPolicy {
+    /// None keeps every result (the flat policy)
+    pub keep_iterations: Option<usize>,
+}
+
+impl WorkingSetPolicy {
+    pub fn new(keep_iterations: usize) -> Self {
+        Self {
+            keep_iterations: Some(keep_iterations),
+        }
+    }
+
+    pub fn flat() -> Self {
+        Self { keep_iterations: None }
+    }
+
+    pub fn from_settings(settings: &ToolOutputSettings) -> Self {
+        if settings.ephemeral {
+            Self::new(settings.keep_iterations)
+        } else {
+            Self::flat()
+        }
+    }
+
+    /// The note for the system prompt, None under the flat policy
+    pub fn guidance(&self) -> Option<String> {
+        let keep = self.keep_iterations?;
+        Some(format!(
+            "Tool results are temporary: each stays in full for {} more step(s) after the one that \
+             requested it, then shrinks to a one-line record. Note what you need from a result in your \
+             reply right away, or call the tool again later. Results you cite (by path, URL or command) \
+             in your final answer are kept.",
+            keep
+        ))
+    }
+
+    /// Replace the results older than the policy allows with their
+    /// one-line record. Returns the number of characters removed.
+    pub fn evict(&self, history: &mut [Message]) -> usize {
+        let Some(keep) = self.keep_iterations else {
+            return 0;
+        };
+        let now = current_iteration(history);
+        let mut saved = 0;
+        for message in history.iter_mut().filter(|m| !m.pinned) {
+            let Some(tag) = message.tool_result.as_mut() else {
+                continue;
+            };
+            if tag.evicted || now <= tag.iteration + keep {
+                continue;
+            }
+            let record = tag.record(&message.content);
+            saved += (message.content.len() + message.images.len() * IMAGE_CHARS).saturating_sub(record.len());
+            tag.held = Some(std::mem::replace(&mut message.content, record));
+            tag.evicted = true;
+            // Images are not held: a restored result keeps only its text
+            message.images.clear();
+        }
+        saved
+    }
+}
+
+/// End of a turn: results cited by `answer` become durable, restored in
+/// full if they were evicted meanwhile; other evicted results are let go.
+/// Returns the number promoted.
+pub fn settle(history: &mut [Message], answer: &str) -> usize {
+    let mut promoted = 0;
+    for message in history.iter_mut() {
+        let Some(tag) = message.tool_result.as_mut() else {
+            continue;
+        };
+        if tag.is_cited_by(answer) && (!tag.evicted || tag.held.is_some()) {
+            if let Some(content) = tag.held.take() {
+                message.content = content;
+            }
+            message.tool_result = None;
+            promoted += 1;
+        } else {
+            tag.held = None;
+        }
+    }
+    promoted
+}
+
+/// Size of each tier of a conversation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TierSizes {
+    pub durable_chars: usize,
+    pub working_chars: usize,
+    /// Tool results still in full
+    pub working_results: usize,
+    /// Tool results reduced to their record
+    pub evicted_results: usize,
+}
+
+pub fn tier_sizes(messages: &[Message]) -> TierSizes {
+    let mut sizes = TierSizes::default();
+    for message in messages {
+        match &message.tool_result {
+            None => sizes.durable_chars += message.chars(),
+            Some(tag) => {
+                sizes.working_chars += message.chars();
+                if tag.evicted {
+                    sizes.evicted_results += 1;
+                } else {
+                    sizes.working_results += 1;
+                }
+            }
+        }
+    }
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(id: &str, path: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            name: "read_file".to_string(),
+            arguments: json!({ "path": path }),
+        }
+    }
+
+    fn result(call: &ToolCall, lines: usize, iteration: usize) -> Message {
+        let body = vec!["let x = 1; // some code"; lines].join("\n");
+        let content = format!("<tool_result tool_use_id=\"{}\">\n{}\n</tool_result>", call.id, body);
+        tag(Message::user(content), call, iteration)
+    }
+
+    /// One user turn where each iteration reads one file, under `policy`;
+    /// returns the history and the characters sent over all requests
+    fn run(policy: WorkingSetPolicy, iterations: usize, answer: &str) -> (Vec<Message>, usize) {
+        let mut history = vec![Message::user("Refactor the parser")];
+        let mut sent = 0;
+        for i in 1..=iterations {
+            history.push(Message::assistant(format!("Reading file {}", i)));
+            let call = call(&format!("call-{}", i), &format!("src/file{}.rs", i));
+            history.push(result(&call, 200, current_iteration(&history)));
+            policy.evict(&mut history);
+            sent += history.iter().map(|m| m.content.len()).sum::<usize>();
+        }
+        settle(&mut history, answer);
+        history.push(Message::assistant(answer));
+        (history, sent)
+    }
+
+    fn evicted(history: &[Message]) -> Vec<String> {
+        history
+            .iter()
+            .filter_map(|m| m.tool_result.as_ref())
+            .filter(|tag| tag.evicted)
+            .map(|tag| tag.id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_results_evicted_after_keep_iterations() {
+        let policy = WorkingSetPolicy::new(2);
+        let mut history = vec![Message::user("go")];
+        for i in 1..=3 {
+            history.push(Message::assistant("step"));
+            let call = call(&format!("call-{}", i), &format!("src/file{}.rs", i));
+            history.push(result(&call, 320, current_iteration(&history)));
+            policy.evict(&mut history);
+        }
+        // Iteration 3: the result of iteration 1 is still in its last step
+        assert!(evicted(&history).is_empty());
+
+        history.push(Message::assistant("step"));
+        let saved = policy.evict(&mut history);
+        assert_eq!(evicted(&history), vec!["call-1"]);
+        assert!(saved > 7000, "{}", saved);
+        assert_eq!(
+            history[2].content,
+            "<tool_result tool_use_id=\"call-1\">\nread_file src/file1.rs (320 lines) — content dropped\n</tool_result>"
+        );
+
+        history.push(Message::assistant("step"));
+        policy.evict(&mut history);
+        assert_eq!(evicted(&history), vec!["call-1", "call-2"]);
+
+        // Pinned results and the flat policy keep everything
+        let mut pinned = history.clone();
+        pinned[6].pinned = true;
+        history.push(Message::assistant("step"));
+        pinned.push(Message::assistant("step"));
+        policy.evict(&mut pinned);
+        assert_eq!(evicted(&pinned), vec!["call-1", "call-2"]);
+        assert_eq!(WorkingSetPolicy::flat().evict(&mut history), 0);
+        assert!(WorkingSetPolicy::flat().guidance().is_none());
+        assert!(policy.guidance().unwrap().contains("2 more step(s)"));
+    }
+
+    #[test]
+    fn test_cited_results_promoted_to_durable() {
+        let answer = "The bug is in src/file1.rs; call-5 confirms the fix.";
+        let (history, _) = run(WorkingSetPolicy::new(2), 6, answer);
+
+        // file1 was evicted mid-turn, but the answer cites it: restored
+        let file1 = &history[2];
+        assert!(file1.tool_result.is_none());
+        assert!(file1.content.lines().count() > 200);
+        // call-5 is cited by id and was never evicted
+        assert!(history[10].tool_result.is_none());
+        // The others stay in the working set, evicted or not
+        assert_eq!(evicted(&history), vec!["call-2", "call-3"]);
+        let sizes = tier_sizes(&history);
+        assert_eq!((sizes.working_results, sizes.evicted_results), (2, 2));
+        assert!(history[4].content.contains("content dropped"));
+    }
+
+    #[test]
+    fn test_token_savings_versus_flat() {
+        let (flat, flat_sent) = run(WorkingSetPolicy::flat(), 8, "Done.");
+        let (ephemeral, ephemeral_sent) = run(WorkingSetPolicy::new(2), 8, "Done.");
+
+        let flat_sizes = tier_sizes(&flat);
+        let sizes = tier_sizes(&ephemeral);
+        assert_eq!(flat_sizes.durable_chars, sizes.durable_chars);
+        assert_eq!((flat_sizes.working_results, flat_sizes.evicted_results), (8, 0));
+        assert_eq!((sizes.working_results, sizes.evicted_results), (3, 5));
+        // 8 results of ~4.8k chars: the flat run resends all of them every
+        // iteration, the working set at most three
+        assert!(sizes.working_chars * 2 < flat_sizes.working_chars, "{:?} vs {:?}", sizes, flat_sizes);
+        assert!(ephemeral_sent * 10 < flat_sent * 7, "{} vs {}", ephemeral_sent, flat_sent);
+    }
+}
@@ -11,6 +11,7 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use super::manifest::{PluginConfig, PluginManifest};
+use super::pack;
 
 /// Plugin installation status
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +60,22 @@ impl Default for ManagerConfig {
     }
 }
 
+/// Version of `plugins.json`. Version 1 was the bare plugin map.
+const STATE_VERSION: u32 = 2;
+
+/// On-disk envelope of `plugins.json`
+#[derive(Deserialize)]
+struct PluginState {
+    version: u32,
+    plugins: HashMap<String, InstalledPlugin>,
+}
+
+#[derive(Serialize)]
+struct PluginStateRef<'a> {
+    version: u32,
+    plugins: &'a HashMap<String, InstalledPlugin>,
+}
+
 /// Plugin manager for installing, updating, and managing plugins
 pub struct PluginManager {
     config: ManagerConfig,
@@ -73,13 +90,17 @@ impl PluginManager {
         fs::create_dir_all(&config.plugins_dir)?;
 
         let state_file = config.plugins_dir.join("plugins.json");
-        let installed = Self::load_state(&state_file)?;
+        let (installed, migrated) = Self::load_state(&state_file)?;
 
-        Ok(Self {
+        let manager = Self {
             config,
             installed,
             state_file,
-        })
+        };
+        if migrated {
+            manager.save_state()?;
+        }
+        Ok(manager)
     }
 
     /// Create with default config
@@ -87,36 +108,83 @@ impl PluginManager {
         Self::new(ManagerConfig::default())
     }
 
-    /// Load plugin state from file
-    fn load_state(path: &Path) -> Result<HashMap<String, InstalledPlugin>> {
+    /// Load plugin state from file. The flag is set when an older format was
+    /// migrated and should be written back.
+    fn load_state(path: &Path) -> Result<(HashMap<String, InstalledPlugin>, bool)> {
         if !path.exists() {
-            return Ok(HashMap::new());
+            return Ok((HashMap::new(), false));
         }
 
         let content = fs::read_to_string(path)?;
-        let state: HashMap<String, InstalledPlugin> = serde_json::from_str(&content)?;
-        Ok(state)
+        let value: serde_json::Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        let version = value.get("version").and_then(|v| v.as_u64());
+
+        match version {
+            // v1: a bare map of plugin id -> record
+            None => {
+                let plugins = serde_json::from_value(value)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+                tracing::info!("Migrated {} to state version {}", path.display(), STATE_VERSION);
+                Ok((plugins, true))
+            }
+            Some(v) if v <= STATE_VERSION as u64 => {
+                let state: PluginState = serde_json::from_value(value)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+                Ok((state.plugins, false))
+            }
+            // Written by a newer webrana: keep a copy and read what we can
+            Some(v) => {
+                let backup = path.with_extension(format!("json.v{}.bak", v));
+                fs::copy(path, &backup)?;
+                let plugins: HashMap<String, InstalledPlugin> = value
+                    .get("plugins")
+                    .and_then(|p| p.as_object())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(id, record)| {
+                        serde_json::from_value(record.clone()).ok().map(|r| (id.clone(), r))
+                    })
+                    .collect();
+                tracing::warn!(
+                    "{} has state version {} (this webrana supports {}); backed it up to {} and loaded {} plugin records",
+                    path.display(),
+                    v,
+                    STATE_VERSION,
+                    backup.display(),
+                    plugins.len()
+                );
+                Ok((plugins, false))
+            }
+        }
     }
 
     /// Save plugin state to file
     fn save_state(&self) -> Result<()> {
-        let content = serde_json::to_string_pretty(&self.installed)?;
+        let state = PluginStateRef {
+            version: STATE_VERSION,
+            plugins: &self.installed,
+        };
+        let content = serde_json::to_string_pretty(&state)?;
         fs::write(&self.state_file, content)?;
         Ok(())
     }
 
-    /// Install plugin from local path
+    /// Install plugin from a local directory or a packed `.wbp` / `.tar.gz`
     pub fn install_local(&mut self, path: &Path) -> Result<InstallResult> {
-        let manifest_path = path.join("plugin.yaml");
-        if !manifest_path.exists() {
-            anyhow::bail!("No plugin.yaml found at {}", path.display());
+        if !pack::is_archive(path) {
+            return self.install_dir(path, PluginSource::Local(path.to_path_buf()));
         }
 
-        let manifest_content = fs::read_to_string(&manifest_path)?;
-        let manifest = PluginManifest::from_yaml(&manifest_content)
-            .context("Failed to parse plugin.yaml")?;
+        let staging = std::env::temp_dir().join(format!("webrana-plugin-{}", uuid::Uuid::new_v4()));
+        let result = pack::unpack(path, &staging)
+            .and_then(|_| self.install_dir(&staging, PluginSource::Local(path.to_path_buf())));
+        let _ = fs::remove_dir_all(&staging);
+        result
+    }
 
-        manifest.validate().map_err(|e| anyhow::anyhow!(e))?;
+    fn install_dir(&mut self, path: &Path, source: PluginSource) -> Result<InstallResult> {
+        let (manifest, _) = pack::read_manifest(path)?;
 
         // Check if already installed
         if self.installed.contains_key(&manifest.id) {
@@ -140,7 +208,7 @@ impl PluginManager {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
-            source: PluginSource::Local(path.to_path_buf()),
+            source,
         };
 
         self.installed.insert(manifest.id.clone(), installed);
@@ -149,7 +217,7 @@ impl PluginManager {
         Ok(InstallResult::Installed(manifest))
     }
 
-    /// Copy directory recursively
+    /// Copy directory recursively, leaving out build output and VCS data
     fn copy_dir_recursive(&self, src: &Path, dst: &Path) -> Result<()> {
         fs::create_dir_all(dst)?;
 
@@ -159,6 +227,9 @@ impl PluginManager {
             let dst_path = dst.join(entry.file_name());
 
             if src_path.is_dir() {
+                if entry.file_name() == "target" || entry.file_name() == ".git" {
+                    continue;
+                }
                 self.copy_dir_recursive(&src_path, &dst_path)?;
             } else {
                 fs::copy(&src_path, &dst_path)?;
@@ -308,12 +379,14 @@ impl RegistryClient {
     pub fn new(base_url: &str) -> Self {
         Self {
             base_url: base_url.trim_end_matches('/').to_string(),
-            client: reqwest::Client::new(),
+            // Settings were validated by http::configure at startup
+            client: crate::util::http::client().unwrap_or_default(),
         }
     }
 
     /// Search plugins in registry
     pub async fn search(&self, query: &str) -> Result<Vec<RegistryPlugin>> {
+        crate::util::http::ensure_online("Searching the plugin registry")?;
         let url = format!("{}/api/plugins/search?q={}", self.base_url, query);
         
         let response = self
@@ -333,6 +406,7 @@ impl RegistryClient {
 
     /// Get plugin info from registry
     pub async fn get_plugin(&self, plugin_id: &str) -> Result<RegistryPlugin> {
+        crate::util::http::ensure_online("Fetching from the plugin registry")?;
         let url = format!("{}/api/plugins/{}", self.base_url, plugin_id);
         
         let response = self
@@ -352,6 +426,7 @@ impl RegistryClient {
 
     /// List featured plugins
     pub async fn featured(&self) -> Result<Vec<RegistryPlugin>> {
+        crate::util::http::ensure_online("Listing featured plugins")?;
         let url = format!("{}/api/plugins/featured", self.base_url);
         
         let response = self
@@ -398,4 +473,74 @@ mod tests {
         assert_eq!(stats.enabled, 0);
         assert_eq!(stats.disabled, 0);
     }
+
+    fn record(id: &str) -> serde_json::Value {
+        serde_json::json!({
+            "manifest": {
+                "id": id,
+                "name": id,
+                "version": "1.0.0",
+                "description": "test",
+                "author": { "name": "Dev", "email": null, "url": null },
+                "plugin_type": "wasm",
+                "min_webrana_version": "0.3.0",
+                "permissions": [],
+                "skills": [],
+                "entry_point": "plugin.wasm"
+            },
+            "config": { "enabled": true, "settings": {} },
+            "install_path": "/plugins/x",
+            "installed_at": 1,
+            "source": { "Local": "/src/x" }
+        })
+    }
+
+    fn manager_in(dir: &Path) -> Result<PluginManager> {
+        PluginManager::new(ManagerConfig {
+            plugins_dir: dir.to_path_buf(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_v1_state_is_migrated() {
+        let dir = tempdir().unwrap();
+        let state_file = dir.path().join("plugins.json");
+        let v1 = serde_json::json!({ "fmt": record("fmt"), "lint": record("lint") });
+        fs::write(&state_file, v1.to_string()).unwrap();
+
+        let manager = manager_in(dir.path()).unwrap();
+        assert_eq!(manager.stats().total, 2);
+        assert_eq!(manager.get("fmt").unwrap().manifest.version, "1.0.0");
+
+        let saved: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&state_file).unwrap()).unwrap();
+        assert_eq!(saved["version"], STATE_VERSION);
+        assert_eq!(saved["plugins"].as_object().unwrap().len(), 2);
+        // And it loads again
+        assert_eq!(manager_in(dir.path()).unwrap().stats().total, 2);
+    }
+
+    #[test]
+    fn test_newer_state_is_backed_up_not_fatal() {
+        let dir = tempdir().unwrap();
+        let state_file = dir.path().join("plugins.json");
+        let mut future = record("fmt");
+        future["checksum"] = serde_json::json!("sha256:abc");
+        let newer = serde_json::json!({
+            "version": 99,
+            "plugins": { "fmt": future, "broken": { "shape": "unknown" } },
+            "registries": []
+        });
+        fs::write(&state_file, newer.to_string()).unwrap();
+
+        let manager = manager_in(dir.path()).unwrap();
+        assert_eq!(manager.stats().total, 1);
+        assert!(manager.get("fmt").is_some());
+
+        // The original is kept byte for byte, and the file itself is untouched
+        let backup = dir.path().join("plugins.json.v99.bak");
+        assert_eq!(fs::read_to_string(backup).unwrap(), newer.to_string());
+        assert_eq!(fs::read_to_string(&state_file).unwrap(), newer.to_string());
+    }
 }
@@ -5,6 +5,8 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::crew::CrewPermissions;
+
 /// Plugin manifest describing plugin metadata and capabilities
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginManifest {
@@ -96,6 +98,45 @@ pub enum Permission {
     LlmAccess,
 }
 
+impl Permission {
+    /// Manifest spelling, e.g. `fs:write`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FileRead => "fs:read",
+            Self::FileWrite => "fs:write",
+            Self::ShellExecute => "shell:execute",
+            Self::NetworkRequest => "net:request",
+            Self::EnvRead => "env:read",
+            Self::GitAccess => "git:access",
+            Self::LlmAccess => "llm:access",
+        }
+    }
+
+    /// Crew permission flags this capability needs. Git can rewrite the work
+    /// tree, environment variables are as sensitive as the shell, and LLM
+    /// calls go over the network.
+    pub fn crew_flags(&self) -> &'static [&'static str] {
+        match self {
+            Self::FileRead => &["file_read"],
+            Self::FileWrite => &["file_write"],
+            Self::ShellExecute | Self::EnvRead => &["shell_access"],
+            Self::NetworkRequest | Self::LlmAccess => &["network_access"],
+            Self::GitAccess => &["file_read", "file_write"],
+        }
+    }
+
+    /// Whether a crew with `crew` permissions may use this capability
+    pub fn allowed_for(&self, crew: &CrewPermissions) -> bool {
+        self.crew_flags().iter().all(|flag| match *flag {
+            "file_read" => crew.file_read,
+            "file_write" => crew.file_write,
+            "shell_access" => crew.shell_access,
+            "network_access" => crew.network_access,
+            _ => false,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SkillDefinition {
     /// Skill name (used in tool calls)
@@ -113,7 +154,7 @@ pub struct SkillDefinition {
 }
 
 /// Plugin configuration (user-provided)
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginConfig {
     /// Whether plugin is enabled
     #[serde(default = "default_true")]
@@ -128,6 +169,16 @@ fn default_true() -> bool {
     true
 }
 
+// Newly installed plugins start enabled, as when the field is missing
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            settings: HashMap::new(),
+        }
+    }
+}
+
 impl PluginManifest {
     /// Load manifest from YAML file
     pub fn from_yaml(content: &str) -> Result<Self, serde_yaml::Error> {
@@ -172,6 +223,14 @@ impl PluginManifest {
     pub fn has_permission(&self, permission: &Permission) -> bool {
         self.permissions.contains(permission)
     }
+
+    /// Declared permissions that `crew` does not grant
+    pub fn denied_permissions(&self, crew: &CrewPermissions) -> Vec<&Permission> {
+        self.permissions
+            .iter()
+            .filter(|p| !p.allowed_for(crew))
+            .collect()
+    }
 }
 
 // Example manifest YAML:
@@ -6,7 +6,10 @@
 mod loader;
 mod manager;
 mod manifest;
+mod pack;
 mod runtime;
+mod scaffold;
+mod skill;
 
 #[allow(unused_imports)]
 pub use loader::PluginLoader;
@@ -15,11 +18,19 @@ pub use manager::{InstallResult, InstalledPlugin, ManagerConfig, ManagerStats, P
 #[allow(unused_imports)]
 pub use manifest::{PluginConfig, PluginManifest};
 #[allow(unused_imports)]
+pub use pack::{is_archive, pack, read_manifest, unpack, ARCHIVE_EXTENSION};
+#[allow(unused_imports)]
 pub use runtime::{PluginInstance, PluginRuntime};
+#[allow(unused_imports)]
+pub use scaffold::{scaffold_plugin, DEFAULT_SKILL};
+#[allow(unused_imports)]
+pub use skill::{plugin_skills, PluginSkill};
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::llm::ImageArtifact;
+
 /// Plugin trait that all plugins must implement
 pub trait Plugin: Send + Sync {
     /// Get plugin metadata
@@ -56,8 +67,13 @@ pub struct PluginContext {
 pub struct PluginOutput {
     pub success: bool,
     pub result: serde_json::Value,
+    #[serde(default)]
     pub logs: Vec<String>,
+    #[serde(default)]
     pub artifacts: Vec<PluginArtifact>,
+    /// Screenshots, diagrams or charts for the model to look at
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageArtifact>,
 }
 
 /// Artifact produced by plugin
@@ -83,6 +99,7 @@ impl Default for PluginOutput {
             result: serde_json::Value::Null,
             logs: Vec::new(),
             artifacts: Vec::new(),
+            images: Vec::new(),
         }
     }
 }
@@ -0,0 +1,292 @@
+// ============================================
+// WEBRANA CLI - Plugin Archives
+// `webrana plugin pack`: manifest + entry point in one checksummed .wbp
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path};
+
+use super::manifest::PluginManifest;
+use crate::util::sha256_hex;
+
+/// Default extension for packed plugins (a gzipped tar)
+pub const ARCHIVE_EXTENSION: &str = "wbp";
+
+/// Checksums of every other file in the archive
+pub const CHECKSUMS_FILE: &str = "checksums.json";
+
+const FORMAT_VERSION: u32 = 1;
+
+/// Manifest file names, in the order the loader prefers them
+const MANIFEST_FILES: &[&str] = &["manifest.yaml", "plugin.yaml"];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Checksums {
+    format: u32,
+    id: String,
+    version: String,
+    /// File name -> "sha256:<hex>"
+    files: BTreeMap<String, String>,
+}
+
+/// Whether `path` looks like a packed plugin rather than a directory
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+    path.is_file()
+        && (name.ends_with(&format!(".{}", ARCHIVE_EXTENSION)) || name.ends_with(".tar.gz") || name.ends_with(".tgz"))
+}
+
+/// The manifest of the plugin in `dir`, and the file it was read from
+pub fn read_manifest(dir: &Path) -> Result<(PluginManifest, String)> {
+    let Some(file) = MANIFEST_FILES.iter().find(|name| dir.join(name).is_file()) else {
+        bail!("No manifest.yaml or plugin.yaml found at {}", dir.display());
+    };
+    let path = dir.join(file);
+    let manifest = PluginManifest::from_yaml(&fs::read_to_string(&path)?)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    manifest.validate().map_err(|e| anyhow::anyhow!(e))?;
+    Ok((manifest, file.to_string()))
+}
+
+/// Write the manifest and entry point of the plugin in `dir` to `output`,
+/// with their checksums
+pub fn pack(dir: &Path, output: &Path) -> Result<PluginManifest> {
+    let (manifest, manifest_file) = read_manifest(dir)?;
+    if !is_plain_relative(&manifest.entry_point) {
+        bail!("Entry point {} must be a path inside the plugin directory", manifest.entry_point);
+    }
+    let entry = dir.join(&manifest.entry_point);
+    if !entry.is_file() {
+        bail!(
+            "Entry point {} not found; build the plugin before packing it",
+            entry.display()
+        );
+    }
+
+    let files = vec![
+        (manifest_file.clone(), fs::read(dir.join(&manifest_file))?),
+        (manifest.entry_point.clone(), fs::read(&entry)?),
+    ];
+    let checksums = Checksums {
+        format: FORMAT_VERSION,
+        id: manifest.id.clone(),
+        version: manifest.version.clone(),
+        files: files.iter().map(|(name, data)| (name.clone(), sha256(data))).collect(),
+    };
+    let mut all = files;
+    all.push((CHECKSUMS_FILE.to_string(), serde_json::to_vec_pretty(&checksums)?));
+    write_archive(&all, output)?;
+    Ok(manifest)
+}
+
+/// Extract the archive at `archive` into `dest` after checking every file
+/// against the packed checksums. Nothing is written when a check fails.
+pub fn unpack(archive: &Path, dest: &Path) -> Result<()> {
+    let mut files = read_archive(archive)?;
+    let Some(checksums) = files.remove(CHECKSUMS_FILE) else {
+        bail!("Plugin archive has no {}; repack it with `webrana plugin pack`", CHECKSUMS_FILE);
+    };
+    let checksums: Checksums =
+        serde_json::from_slice(&checksums).with_context(|| format!("Invalid {}", CHECKSUMS_FILE))?;
+    if checksums.format > FORMAT_VERSION {
+        bail!("Plugin archive format {} is newer than this webrana supports", checksums.format);
+    }
+    for (name, data) in &files {
+        match checksums.files.get(name) {
+            None => bail!("Plugin archive contains {}, which has no checksum", name),
+            Some(expected) if *expected != sha256(data) => {
+                bail!("Checksum mismatch for {}: the archive was modified after packing", name)
+            }
+            Some(_) => {}
+        }
+    }
+    if let Some(missing) = checksums.files.keys().find(|name| !files.contains_key(*name)) {
+        bail!("Plugin archive is missing {}", missing);
+    }
+
+    for (name, data) in files {
+        let path = dest.join(&name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, data)?;
+    }
+    Ok(())
+}
+
+/// The regular files in a gzipped tar, by name
+fn read_archive(archive: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let file = fs::File::open(archive).with_context(|| format!("Failed to open {}", archive.display()))?;
+    let mut tar = tar::Archive::new(GzDecoder::new(file));
+    let mut files = BTreeMap::new();
+    for entry in tar.entries().context("Not a plugin archive (.wbp / .tar.gz)")? {
+        let mut entry = entry.context("Corrupt plugin archive")?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().to_string();
+        if !is_plain_relative(&name) {
+            bail!("Plugin archive contains an unsafe path: {}", name);
+        }
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        if files.insert(name.clone(), data).is_some() {
+            bail!("Plugin archive contains {} twice", name);
+        }
+    }
+    Ok(files)
+}
+
+/// Gzipped tar of `files`, with fixed modes and times so packing the same
+/// plugin twice gives the same bytes
+fn write_archive(files: &[(String, Vec<u8>)], output: &Path) -> Result<()> {
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let file = fs::File::create(output).with_context(|| format!("Failed to create {}", output.display()))?;
+    let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    for (name, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        tar.append_data(&mut header, name, data.as_slice())?;
+    }
+    tar.into_inner()?.finish()?;
+    Ok(())
+}
+
+fn sha256(data: &[u8]) -> String {
+    format!("sha256:{}", sha256_hex(data))
+}
+
+/// Relative, without `..`: safe to join onto an extraction directory
+fn is_plain_relative(path: &str) -> bool {
+    !path.is_empty() && Path::new(path).components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::{ManagerConfig, PluginManager, PluginSource};
+    use tempfile::tempdir;
+
+    const MANIFEST: &str = r#"
+id: hello
+name: Hello
+version: 1.2.0
+description: Says hello
+author:
+  name: Dev
+plugin_type: wasm
+min_webrana_version: 0.7.0
+permissions: []
+skills:
+  - name: greet
+    description: Greet someone
+    input_schema: {}
+entry_point: plugin.wasm
+"#;
+
+    fn plugin_dir() -> tempfile::TempDir {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("manifest.yaml"), MANIFEST).unwrap();
+        fs::write(dir.path().join("plugin.wasm"), b"\0asm\x01\0\0\0").unwrap();
+        // Not part of the archive
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        dir
+    }
+
+    fn manager(dir: &Path) -> PluginManager {
+        PluginManager::new(ManagerConfig {
+            plugins_dir: dir.to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_pack_then_install_matches_directory_install() {
+        let plugin = plugin_dir();
+        let out = tempdir().unwrap();
+        let archive = out.path().join("hello-1.2.0.wbp");
+        let packed = pack(plugin.path(), &archive).unwrap();
+        assert_eq!((packed.id.as_str(), packed.version.as_str()), ("hello", "1.2.0"));
+        assert!(is_archive(&archive));
+
+        // Same bytes every time
+        let again = out.path().join("again.wbp");
+        pack(plugin.path(), &again).unwrap();
+        assert_eq!(fs::read(&archive).unwrap(), fs::read(&again).unwrap());
+
+        let from_dir = tempdir().unwrap();
+        let mut by_dir = manager(from_dir.path());
+        by_dir.install_local(plugin.path()).unwrap();
+        let from_archive = tempdir().unwrap();
+        let mut by_archive = manager(from_archive.path());
+        by_archive.install_local(&archive).unwrap();
+
+        let a = by_dir.get("hello").unwrap();
+        let b = by_archive.get("hello").unwrap();
+        assert_eq!(serde_json::to_value(&a.manifest).unwrap(), serde_json::to_value(&b.manifest).unwrap());
+        assert_eq!(
+            fs::read(a.install_path.join("plugin.wasm")).unwrap(),
+            fs::read(b.install_path.join("plugin.wasm")).unwrap()
+        );
+        assert!(b.install_path.join("manifest.yaml").is_file());
+        assert!(!b.install_path.join(CHECKSUMS_FILE).exists());
+        assert!(matches!(&b.source, PluginSource::Local(path) if *path == archive));
+    }
+
+    #[test]
+    fn test_tampered_archive_fails_checksum() {
+        let plugin = plugin_dir();
+        let out = tempdir().unwrap();
+        let archive = out.path().join("hello.wbp");
+        pack(plugin.path(), &archive).unwrap();
+
+        // Swap the entry point, keep the original checksums
+        let checksums = read_archive(&archive).unwrap().remove(CHECKSUMS_FILE).unwrap();
+        let tampered = out.path().join("tampered.wbp");
+        write_archive(
+            &[
+                ("manifest.yaml".to_string(), MANIFEST.as_bytes().to_vec()),
+                ("plugin.wasm".to_string(), b"\0asm\x01\0\0\0evil".to_vec()),
+                (CHECKSUMS_FILE.to_string(), checksums.clone()),
+            ],
+            &tampered,
+        )
+        .unwrap();
+
+        let plugins = tempdir().unwrap();
+        let mut manager = manager(plugins.path());
+        let err = manager.install_local(&tampered).unwrap_err().to_string();
+        assert!(err.contains("Checksum mismatch for plugin.wasm"), "{}", err);
+        assert!(manager.get("hello").is_none());
+        assert!(!plugins.path().join("hello").exists());
+
+        // Extra files are rejected too
+        let extra = out.path().join("extra.wbp");
+        write_archive(
+            &[
+                ("manifest.yaml".to_string(), MANIFEST.as_bytes().to_vec()),
+                ("plugin.wasm".to_string(), b"\0asm\x01\0\0\0".to_vec()),
+                ("hook.sh".to_string(), b"rm -rf ~".to_vec()),
+                (CHECKSUMS_FILE.to_string(), checksums),
+            ],
+            &extra,
+        )
+        .unwrap();
+        let err = manager.install_local(&extra).unwrap_err().to_string();
+        assert!(err.contains("hook.sh, which has no checksum"), "{}", err);
+    }
+}
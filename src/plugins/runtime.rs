@@ -1,19 +1,26 @@
 // ============================================
 // Plugin Runtime - CIPHER (Team Beta)
-// WASM Integration (requires wasmtime feature)
+// WASM Integration via wasmtime
 // ============================================
 
-use anyhow::{anyhow, Result};
-use std::path::PathBuf;
+use anyhow::{anyhow, Context, Result};
+use std::path::{Component, Path, PathBuf};
+use wasmtime::{
+    Caller, Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, Trap,
+};
 
-use super::manifest::{PluginManifest, PluginType};
+use super::manifest::{Permission, PluginManifest, PluginType};
 use super::{PluginContext, PluginInput, PluginOutput};
+use crate::core::audit::{AuditEvent, AuditEventType, AuditSeverity, AUDIT};
+use crate::crew::{CrewManager, CrewPermissions};
 
 /// Plugin instance managing the lifecycle of a loaded plugin
 pub struct PluginInstance {
     manifest: PluginManifest,
     plugin_dir: PathBuf,
     state: PluginState,
+    /// Compiled entry point of a WASM plugin
+    module: Option<Module>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -37,6 +44,7 @@ impl PluginInstance {
             manifest,
             plugin_dir,
             state: PluginState::Loaded,
+            module: None,
         })
     }
 
@@ -62,8 +70,13 @@ impl PluginInstance {
         Ok(())
     }
 
-    /// Execute plugin with given input
-    pub fn execute(&self, input: &PluginInput) -> Result<PluginOutput> {
+    /// Execute plugin with given input. With `crew` set, the manifest's
+    /// permissions must all be granted by the crew.
+    pub fn execute(
+        &self,
+        input: &PluginInput,
+        crew: Option<&CrewPermissions>,
+    ) -> Result<PluginOutput> {
         if self.state != PluginState::Ready {
             return Err(anyhow!("Plugin not ready. State: {:?}", self.state));
         }
@@ -77,7 +90,7 @@ impl PluginInstance {
             .ok_or_else(|| anyhow!("Skill not found: {}", input.action))?;
 
         // Check permissions if needed
-        self.check_permissions(&input.action)?;
+        self.check_permissions(&input.action, crew)?;
 
         // Execute based on plugin type
         match self.manifest.plugin_type {
@@ -104,21 +117,78 @@ impl PluginInstance {
             return Err(anyhow!("WASM file not found: {:?}", wasm_path));
         }
 
-        // WASM runtime disabled - requires Rust 1.80+ and wasmtime feature
-        tracing::warn!(
-            "WASM plugin '{}' loaded but WASM runtime is disabled. \
-             Rebuild with wasmtime feature for full WASM support.",
-            self.manifest.id
-        );
-
+        // Accepts .wasm and .wat
+        let engine = Engine::new(Config::new().consume_fuel(true))?;
+        let module = Module::from_file(&engine, &wasm_path)
+            .with_context(|| format!("Failed to compile {}", wasm_path.display()))?;
+        self.module = Some(module);
         Ok(())
     }
 
-    fn execute_wasm(&self, _input: &PluginInput) -> Result<PluginOutput> {
-        // WASM runtime disabled
-        Ok(PluginOutput::error(
-            "WASM runtime is disabled. Rebuild with wasmtime feature for WASM plugin support.",
-        ))
+    /// Run a plugin built with webrana-plugin-sdk. Every call gets a fresh
+    /// instance; see plugin-sdk/README.md for the ABI.
+    fn execute_wasm(&self, input: &PluginInput) -> Result<PluginOutput> {
+        let module = self
+            .module
+            .as_ref()
+            .ok_or_else(|| anyhow!("Plugin '{}' is not initialized", self.manifest.id))?;
+
+        let host = WasmHost {
+            working_dir: PathBuf::from(&input.context.working_dir),
+            can_read: self.manifest.has_permission(&Permission::FileRead),
+            can_write: self.manifest.has_permission(&Permission::FileWrite),
+            logs: Vec::new(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(WASM_MEMORY_LIMIT)
+                .instances(1)
+                .build(),
+        };
+        let mut store = Store::new(module.engine(), host);
+        store.limiter(|host| &mut host.limits);
+        store.set_fuel(WASM_FUEL)?;
+        let instance = wasm_linker(module.engine())?
+            .instantiate(&mut store, module)
+            .with_context(|| format!("Failed to instantiate plugin '{}'", self.manifest.id))?;
+
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("Plugin '{}' does not export its memory", self.manifest.id))?;
+        let execute = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, "webrana_execute")
+            .map_err(|_| {
+                anyhow!(
+                    "Plugin '{}' does not export webrana_execute (is it built with webrana-plugin-sdk?)",
+                    self.manifest.id
+                )
+            })?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "webrana_alloc")?;
+        let dealloc = instance.get_typed_func::<(i32, i32), ()>(&mut store, "webrana_dealloc")?;
+
+        // The plugin frees the input buffer
+        let bytes = serde_json::to_vec(input)?;
+        let ptr = alloc.call(&mut store, bytes.len() as i32)?;
+        memory.write(&mut store, ptr as usize, &bytes)?;
+        let packed = execute
+            .call(&mut store, (ptr, bytes.len() as i32))
+            .map_err(|e| match e.downcast_ref::<Trap>() {
+                Some(Trap::OutOfFuel) => anyhow!(
+                    "Plugin '{}' exceeded its budget of {} instructions",
+                    self.manifest.id,
+                    WASM_FUEL
+                ),
+                _ => e,
+            })?;
+        let (out_ptr, out_len) = unpack(packed);
+        let mut reply = vec![0u8; out_len as usize];
+        memory.read(&store, out_ptr as usize, &mut reply)?;
+        dealloc.call(&mut store, (out_ptr as i32, out_len as i32))?;
+
+        let mut output: PluginOutput = serde_json::from_slice(&reply)
+            .with_context(|| format!("Plugin '{}' returned invalid output", self.manifest.id))?;
+        let mut logs = std::mem::take(&mut store.data_mut().logs);
+        logs.append(&mut output.logs);
+        output.logs = logs;
+        Ok(output)
     }
 
     // ==========================================
@@ -166,33 +236,217 @@ impl PluginInstance {
     // Permission Checking
     // ==========================================
 
-    fn check_permissions(&self, action: &str) -> Result<()> {
+    fn check_permissions(&self, action: &str, crew: Option<&CrewPermissions>) -> Result<()> {
         tracing::debug!(
             "Plugin {} executing action {} with permissions: {:?}",
             self.manifest.id,
             action,
             self.manifest.permissions
         );
-        Ok(())
+
+        let Some(crew) = crew else {
+            return Ok(());
+        };
+        let denied = self.manifest.denied_permissions(crew);
+        if denied.is_empty() {
+            return Ok(());
+        }
+
+        let needs: Vec<String> = denied
+            .iter()
+            .map(|p| format!("{} ({} = false)", p.as_str(), p.crew_flags().join("/")))
+            .collect();
+        let message = format!(
+            "Plugin skill '{}' from '{}' needs {}, which the active crew does not allow",
+            action,
+            self.manifest.id,
+            needs.join(", ")
+        );
+        AUDIT.log(
+            AuditEvent::new(
+                AuditEventType::PluginPermissionDenied,
+                AuditSeverity::Warning,
+                message.clone(),
+            )
+            .with_details(serde_json::json!({
+                "plugin": self.manifest.id,
+                "skill": action,
+                "denied": denied.iter().map(|p| p.as_str()).collect::<Vec<_>>(),
+            })),
+        );
+        Err(anyhow!(message))
     }
 }
 
+// ==========================================
+// WASM Host Functions
+// ==========================================
+
+/// Instructions one WASM plugin call may run before it is stopped
+const WASM_FUEL: u64 = 1_000_000_000;
+
+/// Linear memory one WASM plugin call may grow to
+const WASM_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Store data for one WASM plugin call
+struct WasmHost {
+    working_dir: PathBuf,
+    can_read: bool,
+    can_write: bool,
+    logs: Vec<String>,
+    limits: StoreLimits,
+}
+
+fn unpack(packed: i64) -> (u32, u32) {
+    ((packed as u64 >> 32) as u32, packed as u32)
+}
+
+/// Host functions imported from the `webrana` module. File access needs the
+/// manifest permission and stays inside the working directory.
+fn wasm_linker(engine: &Engine) -> Result<Linker<WasmHost>> {
+    let mut linker = Linker::new(engine);
+    linker.func_wrap(
+        "webrana",
+        "log",
+        |mut caller: Caller<'_, WasmHost>, ptr: i32, len: i32| -> Result<()> {
+            let message = read_guest_string(&mut caller, ptr, len)?;
+            caller.data_mut().logs.push(message);
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "webrana",
+        "read_file",
+        |mut caller: Caller<'_, WasmHost>, ptr: i32, len: i32| -> Result<i64> {
+            let path = read_guest_string(&mut caller, ptr, len)?;
+            let host = caller.data();
+            let reply = if !host.can_read {
+                Err("Plugin lacks the fs:read permission".to_string())
+            } else {
+                guest_path(&host.working_dir, &path)
+                    .and_then(|p| std::fs::read_to_string(p).map_err(|e| e.to_string()))
+                    .map(serde_json::Value::String)
+            };
+            write_host_reply(&mut caller, reply)
+        },
+    )?;
+    linker.func_wrap(
+        "webrana",
+        "write_file",
+        |mut caller: Caller<'_, WasmHost>,
+         path_ptr: i32,
+         path_len: i32,
+         data_ptr: i32,
+         data_len: i32|
+         -> Result<i64> {
+            let path = read_guest_string(&mut caller, path_ptr, path_len)?;
+            let data = read_guest_string(&mut caller, data_ptr, data_len)?;
+            let host = caller.data();
+            let reply = if !host.can_write {
+                Err("Plugin lacks the fs:write permission".to_string())
+            } else {
+                guest_path(&host.working_dir, &path)
+                    .and_then(|p| std::fs::write(p, data).map_err(|e| e.to_string()))
+                    .map(|_| serde_json::Value::Null)
+            };
+            write_host_reply(&mut caller, reply)
+        },
+    )?;
+    Ok(linker)
+}
+
+fn guest_memory(caller: &mut Caller<'_, WasmHost>) -> Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| anyhow!("Plugin does not export its memory"))
+}
+
+fn read_guest_string(caller: &mut Caller<'_, WasmHost>, ptr: i32, len: i32) -> Result<String> {
+    let memory = guest_memory(caller)?;
+    let mut bytes = vec![0u8; len as u32 as usize];
+    memory.read(&*caller, ptr as u32 as usize, &mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Hand `{"ok": value}` or `{"error": message}` to the plugin, which frees it
+fn write_host_reply(
+    caller: &mut Caller<'_, WasmHost>,
+    reply: std::result::Result<serde_json::Value, String>,
+) -> Result<i64> {
+    let reply = match reply {
+        Ok(value) => serde_json::json!({ "ok": value }),
+        Err(message) => serde_json::json!({ "error": message }),
+    };
+    let bytes = serde_json::to_vec(&reply)?;
+    let alloc = caller
+        .get_export("webrana_alloc")
+        .and_then(|export| export.into_func())
+        .ok_or_else(|| anyhow!("Plugin does not export webrana_alloc"))?
+        .typed::<i32, i32>(&*caller)?;
+    let ptr = alloc.call(&mut *caller, bytes.len() as i32)?;
+    guest_memory(caller)?.write(&mut *caller, ptr as u32 as usize, &bytes)?;
+    Ok(((ptr as u32 as u64) << 32 | bytes.len() as u64) as i64)
+}
+
+/// Resolve a plugin-supplied path, which must stay inside `working_dir`.
+/// Symlinks are followed, so a link inside the directory cannot point out of it.
+fn guest_path(working_dir: &Path, path: &str) -> std::result::Result<PathBuf, String> {
+    let outside = || format!("Path '{}' is outside the working directory", path);
+    let relative = Path::new(path);
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(outside());
+    }
+    let root = working_dir.canonicalize().map_err(|e| e.to_string())?;
+    let joined = root.join(relative);
+
+    // A file about to be written may not exist yet; resolve its closest existing ancestor
+    let mut existing = joined.as_path();
+    while !existing.exists() && existing.symlink_metadata().is_err() {
+        existing = existing.parent().ok_or_else(outside)?;
+    }
+    let resolved = existing.canonicalize().map_err(|_| outside())?;
+    if !resolved.starts_with(&root) {
+        return Err(outside());
+    }
+    Ok(joined)
+}
+
 /// Plugin runtime managing all plugins
 pub struct PluginRuntime {
     loader: super::loader::PluginLoader,
+    /// Permissions of the active crew, checked against each plugin's manifest
+    crew: Option<CrewPermissions>,
 }
 
 impl PluginRuntime {
     pub fn new() -> Self {
+        let crew = CrewManager::new()
+            .ok()
+            .and_then(|manager| manager.active().map(|crew| crew.permissions.clone()));
         Self {
             loader: super::loader::PluginLoader::new(),
+            crew,
         }
     }
 
+    /// Check plugin skills against `crew` instead of the active crew
+    pub fn set_crew_permissions(&mut self, crew: Option<CrewPermissions>) {
+        self.crew = crew;
+    }
+
+    /// Also discover plugins in `dir` (e.g. the install directory)
+    pub fn add_plugin_dir(&mut self, dir: impl AsRef<Path>) {
+        self.loader.add_plugin_dir(dir);
+    }
+
     /// Initialize runtime and discover plugins
     pub fn init(&mut self) -> Result<()> {
         let discovered = self.loader.discover()?;
-        tracing::info!("Discovered {} plugins", discovered.len());
+        tracing::debug!("Discovered {} plugins", discovered.len());
         Ok(())
     }
 
@@ -225,7 +479,14 @@ impl PluginRuntime {
             },
         };
 
-        instance.execute(&input)
+        instance.execute(&input, self.crew.as_ref())
+    }
+
+    /// Whether the plugin's manifest asks for `fs:write`
+    pub fn can_write(&self, plugin_id: &str) -> bool {
+        self.loader
+            .get_manifest(plugin_id)
+            .is_some_and(|manifest| manifest.has_permission(&Permission::FileWrite))
     }
 
     /// Get all available skills from loaded plugins
@@ -239,3 +500,189 @@ impl Default for PluginRuntime {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crew::CrewTemplate;
+
+    const MANIFEST: &str = r#"
+id: formatter
+name: Formatter
+version: 1.0.0
+description: Rewrites files in place
+author:
+  name: Dev
+plugin_type: script
+min_webrana_version: 0.3.0
+permissions:
+  - fs:read
+  - fs:write
+skills:
+  - name: format_file
+    description: Format a file
+    input_schema:
+      type: object
+entry_point: main.sh
+"#;
+
+    /// Hand-written module following the webrana-plugin-sdk ABI
+    const ABI_WAT: &str = r#"
+(module
+  (import "webrana" "log" (func $log (param i32 i32)))
+  (import "webrana" "read_file" (func $read_file (param i32 i32) (result i64)))
+  (memory (export "memory") 1)
+  (global $next (mut i32) (i32.const 4096))
+  (data (i32.const 16) "input ok")
+  (data (i32.const 32) "../secret")
+  (data (i32.const 64) "{\"success\":true,\"result\":\"done\",\"logs\":[\"from output\"]}")
+  (func $alloc (export "webrana_alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next (i32.add (global.get $next) (local.get $len)))
+    (local.get $ptr))
+  (func (export "webrana_dealloc") (param i32 i32))
+  (func (export "webrana_execute") (param $ptr i32) (param $len i32) (result i64)
+    (local $reply i64)
+    ;; The input is JSON, so it starts with '{'
+    (if (i32.eq (i32.load8_u (local.get $ptr)) (i32.const 123))
+      (then (call $log (i32.const 16) (i32.const 8))))
+    ;; Log the host's reply to reading outside the working directory
+    (local.set $reply (call $read_file (i32.const 32) (i32.const 9)))
+    (call $log
+      (i32.wrap_i64 (i64.shr_u (local.get $reply) (i64.const 32)))
+      (i32.wrap_i64 (local.get $reply)))
+    (i64.or (i64.shl (i64.const 64) (i64.const 32)) (i64.const 55)))
+)
+"#;
+
+    #[test]
+    fn test_wasm_plugin_abi() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("plugin.wat"), ABI_WAT).unwrap();
+        let manifest = PluginManifest::from_yaml(
+            &MANIFEST
+                .replace("plugin_type: script", "plugin_type: wasm")
+                .replace("entry_point: main.sh", "entry_point: plugin.wat"),
+        )
+        .unwrap();
+        let mut instance = PluginInstance::new(manifest, tmp.path().to_path_buf()).unwrap();
+        instance.init().unwrap();
+
+        let input = PluginInput {
+            action: "format_file".to_string(),
+            params: serde_json::json!({}),
+            context: PluginContext {
+                working_dir: tmp.path().to_string_lossy().to_string(),
+                project_type: None,
+                user_config: serde_json::Value::Null,
+            },
+        };
+        let output = instance.execute(&input, None).unwrap();
+        assert!(output.success);
+        assert_eq!(output.result, serde_json::json!("done"));
+        assert_eq!(
+            output.logs,
+            vec![
+                "input ok",
+                r#"{"error":"Path '../secret' is outside the working directory"}"#,
+                "from output",
+            ]
+        );
+    }
+
+    fn wasm_instance(dir: &Path, wat: &str) -> PluginInstance {
+        std::fs::write(dir.join("plugin.wat"), wat).unwrap();
+        let manifest = PluginManifest::from_yaml(
+            &MANIFEST
+                .replace("plugin_type: script", "plugin_type: wasm")
+                .replace("entry_point: main.sh", "entry_point: plugin.wat"),
+        )
+        .unwrap();
+        let mut instance = PluginInstance::new(manifest, dir.to_path_buf()).unwrap();
+        instance.init().unwrap();
+        instance
+    }
+
+    #[test]
+    fn test_wasm_plugin_limits() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let input = PluginInput {
+            action: "format_file".to_string(),
+            params: serde_json::json!({}),
+            context: PluginContext {
+                working_dir: tmp.path().to_string_lossy().to_string(),
+                project_type: None,
+                user_config: serde_json::Value::Null,
+            },
+        };
+
+        let spin = ABI_WAT.replace(
+            "(local $reply i64)",
+            "(local $reply i64)\n    (loop $spin (br $spin))",
+        );
+        let err = wasm_instance(tmp.path(), &spin).execute(&input, None).unwrap_err();
+        assert!(err.to_string().contains("exceeded its budget"), "{err}");
+
+        // 2048 pages is 128 MiB, over the memory cap
+        let greedy = ABI_WAT.replace(
+            "(memory (export \"memory\") 1)",
+            "(memory (export \"memory\") 2048)",
+        );
+        let err = wasm_instance(tmp.path(), &greedy).execute(&input, None).unwrap_err();
+        assert!(err.to_string().contains("Failed to instantiate"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_guest_path_follows_symlinks() {
+        let outside = tempfile::TempDir::new().unwrap();
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::os::unix::fs::symlink(outside.path(), tmp.path().join("escape")).unwrap();
+        std::fs::create_dir(tmp.path().join("src")).unwrap();
+
+        assert!(guest_path(tmp.path(), "escape/secret").is_err());
+        assert!(guest_path(tmp.path(), "escape").is_err());
+        assert!(guest_path(tmp.path(), "src/new.rs").is_ok());
+        assert!(guest_path(tmp.path(), "new/dir/file.rs").is_ok());
+    }
+
+    #[test]
+    fn test_fs_write_plugin_blocked_for_read_only_crew() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("main.sh"), "echo formatted").unwrap();
+        let manifest = PluginManifest::from_yaml(MANIFEST).unwrap();
+        let mut instance = PluginInstance::new(manifest, tmp.path().to_path_buf()).unwrap();
+        instance.init().unwrap();
+
+        let input = PluginInput {
+            action: "format_file".to_string(),
+            params: serde_json::json!({}),
+            context: PluginContext {
+                working_dir: ".".to_string(),
+                project_type: None,
+                user_config: serde_json::Value::Null,
+            },
+        };
+
+        let reviewer = CrewTemplate::CodeReviewer.create().permissions;
+        assert!(!reviewer.file_write);
+        let err = instance.execute(&input, Some(&reviewer)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Plugin skill 'format_file' from 'formatter' needs fs:write (file_write = false), \
+             which the active crew does not allow"
+        );
+        assert!(AUDIT
+            .events_by_type(AuditEventType::PluginPermissionDenied)
+            .iter()
+            .any(|e| e.message.contains("format_file")));
+
+        let writer = CrewTemplate::all()
+            .into_iter()
+            .map(|t| t.create().permissions)
+            .find(|p| p.file_read && p.file_write)
+            .unwrap();
+        assert!(instance.execute(&input, Some(&writer)).unwrap().success);
+    }
+}
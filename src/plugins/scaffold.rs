@@ -0,0 +1,215 @@
+// ============================================
+// Plugin Scaffold - `webrana plugin new`
+// Ready-to-build WASM plugin project using webrana-plugin-sdk
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::scaffold::render;
+
+/// Skill generated when `--skill` is not given
+pub const DEFAULT_SKILL: &str = "greet";
+
+/// Files written by [`scaffold_plugin`]: (path, template)
+const FILES: &[(&str, &str)] = &[
+    ("manifest.yaml", MANIFEST),
+    ("Cargo.toml", CARGO_TOML),
+    ("src/lib.rs", LIB_RS),
+    ("README.md", README),
+    (".gitignore", GITIGNORE),
+];
+
+/// Create `<parent>/<name>` with a plugin exposing one skill. Returns the
+/// project directory and the files written.
+pub fn scaffold_plugin(parent: &Path, name: &str, skill: &str) -> Result<(PathBuf, Vec<String>)> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    {
+        bail!("Invalid plugin name '{}': use lowercase letters, digits, '-' and '_'", name);
+    }
+    if !is_identifier(skill) {
+        bail!("Invalid skill name '{}': use letters, digits and '_'", skill);
+    }
+
+    let dest = parent.join(name);
+    if dest.exists() && fs::read_dir(&dest).map(|mut d| d.next().is_some()).unwrap_or(true) {
+        bail!("{} already exists and is not empty", dest.display());
+    }
+
+    let mut vars = HashMap::new();
+    vars.insert("name".to_string(), name.to_string());
+    vars.insert("crate_name".to_string(), name.replace('-', "_"));
+    vars.insert("skill".to_string(), skill.to_string());
+    vars.insert("version".to_string(), env!("CARGO_PKG_VERSION").to_string());
+
+    let mut written = Vec::new();
+    for (path, template) in FILES {
+        let target = dest.join(path);
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = render(template, &vars).with_context(|| format!("Rendering {}", path))?;
+        fs::write(&target, content).with_context(|| format!("Writing {}", target.display()))?;
+        written.push(path.to_string());
+    }
+    Ok((dest, written))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+const MANIFEST: &str = r#"id: {{name}}
+name: {{name}}
+version: 0.1.0
+description: Webrana plugin providing {{skill}}
+author:
+  name: {{name}} authors
+plugin_type: wasm
+min_webrana_version: "{{version}}"
+# Add fs:read / fs:write to use host::read_file / host::write_file
+permissions: []
+skills:
+  - name: {{skill}}
+    description: Greets someone by name
+    input_schema:
+      type: object
+      properties:
+        name:
+          type: string
+          description: Who to greet
+      required: []
+    requires_confirmation: false
+entry_point: plugin.wasm
+"#;
+
+const CARGO_TOML: &str = r#"[package]
+name = "{{name}}"
+version = "0.1.0"
+edition = "2021"
+publish = false
+
+[lib]
+crate-type = ["cdylib"]
+
+[dependencies]
+webrana-plugin-sdk = "{{version}}"
+
+[profile.release]
+opt-level = "s"
+lto = true
+
+# Keep this project out of any enclosing workspace
+[workspace]
+"#;
+
+const LIB_RS: &str = r#"use webrana_plugin_sdk::{host, json, register_handler, PluginInput, PluginOutput};
+
+register_handler!(handle);
+
+/// Entry point for every skill in manifest.yaml
+fn handle(input: PluginInput) -> PluginOutput {
+    match input.action.as_str() {
+        "{{skill}}" => {{skill}}(&input),
+        other => PluginOutput::error(&format!("Unknown skill: {}", other)),
+    }
+}
+
+fn {{skill}}(input: &PluginInput) -> PluginOutput {
+    let name = input
+        .params
+        .get("name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("world");
+    host::log(&format!("{{skill}} called for {}", name));
+    PluginOutput::success(json!({ "message": format!("Hello, {}!", name) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use webrana_plugin_sdk::PluginContext;
+
+    #[test]
+    fn test_{{skill}}() {
+        let input = PluginInput {
+            action: "{{skill}}".to_string(),
+            params: json!({ "name": "Ada" }),
+            context: PluginContext {
+                working_dir: ".".to_string(),
+                project_type: None,
+                user_config: json!(null),
+            },
+        };
+        let output = handle(input);
+        assert!(output.success);
+        assert_eq!(output.result["message"], "Hello, Ada!");
+    }
+}
+"#;
+
+const README: &str = r#"# {{name}}
+
+A Webrana WASM plugin providing the `{{skill}}` skill.
+
+## Build
+
+Plugins are built for the `wasm32-unknown-unknown` target:
+
+```bash
+rustup target add wasm32-unknown-unknown
+cargo build --target wasm32-unknown-unknown --release
+cp target/wasm32-unknown-unknown/release/{{crate_name}}.wasm plugin.wasm
+```
+
+`cargo test` runs the skill natively; the host functions then use `std::fs`.
+
+## Install and run
+
+```bash
+webrana plugin install .
+webrana plugin run {{name}} {{skill}} --params '{"name": "Ada"}'
+```
+"#;
+
+const GITIGNORE: &str = "/target
+/plugin.wasm
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaffold_plugin() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (dir, files) = scaffold_plugin(tmp.path(), "word-count", "count_words").unwrap();
+        assert_eq!(files, vec!["manifest.yaml", "Cargo.toml", "src/lib.rs", "README.md", ".gitignore"]);
+
+        let manifest = super::super::PluginManifest::from_yaml(
+            &fs::read_to_string(dir.join("manifest.yaml")).unwrap(),
+        )
+        .unwrap();
+        manifest.validate().unwrap();
+        assert_eq!(manifest.id, "word-count");
+        assert_eq!(manifest.skills[0].name, "count_words");
+
+        let lib = fs::read_to_string(dir.join("src/lib.rs")).unwrap();
+        assert!(lib.contains("\"count_words\" => count_words(&input),"));
+        let readme = fs::read_to_string(dir.join("README.md")).unwrap();
+        assert!(readme.contains("release/word_count.wasm plugin.wasm"));
+
+        assert!(scaffold_plugin(tmp.path(), "word-count", "x").is_err());
+        assert!(scaffold_plugin(tmp.path(), "Bad Name", "x").is_err());
+        assert!(scaffold_plugin(tmp.path(), "ok", "not-an-ident").is_err());
+    }
+}
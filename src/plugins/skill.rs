@@ -0,0 +1,291 @@
+// Plugin skills offered to the model as tools
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::manager::PluginManager;
+use super::manifest::{self, PluginType};
+use super::runtime::PluginRuntime;
+use crate::config::Settings;
+use crate::crew::CrewPermissions;
+use crate::skills::{Skill, SkillDefinition, SkillOutput};
+
+/// One skill of a loaded plugin. Images in its output go to the model with
+/// the tool result.
+pub struct PluginSkill {
+    plugin_id: String,
+    skill: manifest::SkillDefinition,
+    runtime: Arc<PluginRuntime>,
+    /// Has `fs:write`; the guest picks the paths
+    can_write: bool,
+}
+
+/// Load every enabled WebAssembly plugin and wrap its skills, checked
+/// against the session's crew. Other plugin types have no runtime that
+/// really executes them yet, so they are not offered. A plugin that fails
+/// to load is skipped with a warning.
+pub fn plugin_skills(manager: &PluginManager, crew: Option<&CrewPermissions>) -> Vec<PluginSkill> {
+    let mut runtime = PluginRuntime::new();
+    runtime.set_crew_permissions(crew.cloned());
+    runtime.add_plugin_dir(manager.plugins_dir());
+    if let Err(e) = runtime.init() {
+        tracing::warn!("Plugin discovery failed: {}", e);
+        return Vec::new();
+    }
+    for plugin in manager.list_enabled() {
+        if !matches!(plugin.manifest.plugin_type, PluginType::Wasm) {
+            tracing::debug!("Plugin {} not offered as tools: only wasm plugins run", plugin.manifest.id);
+            continue;
+        }
+        if let Err(e) = runtime.load_plugin(&plugin.manifest.id) {
+            tracing::warn!("Plugin {} not loaded: {}", plugin.manifest.id, e);
+        }
+    }
+    from_runtime(runtime)
+}
+
+fn from_runtime(runtime: PluginRuntime) -> Vec<PluginSkill> {
+    let skills: Vec<(String, manifest::SkillDefinition)> = runtime
+        .get_all_skills()
+        .into_iter()
+        .map(|(plugin_id, skill)| (plugin_id.to_string(), skill.clone()))
+        .collect();
+    let runtime = Arc::new(runtime);
+    skills
+        .into_iter()
+        .map(|(plugin_id, skill)| PluginSkill {
+            can_write: runtime.can_write(&plugin_id),
+            plugin_id,
+            skill,
+            runtime: runtime.clone(),
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Skill for PluginSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: self.skill.name.clone(),
+            description: self.skill.description.clone(),
+            parameters: self.skill.input_schema.clone(),
+            requires_confirmation: self.skill.requires_confirmation,
+            parallel_safe: false,
+        }
+    }
+
+    async fn execute(&self, args: &Value, settings: &Settings) -> Result<String> {
+        self.execute_output(args, settings).await.map(|output| output.text)
+    }
+
+    async fn execute_output(&self, args: &Value, _settings: &Settings) -> Result<SkillOutput> {
+        // A plugin call runs up to its fuel budget without yielding; off
+        // the async workers, a skill timeout can still fire
+        let (runtime, plugin_id, name, args) = (
+            self.runtime.clone(),
+            self.plugin_id.clone(),
+            self.skill.name.clone(),
+            args.clone(),
+        );
+        let output =
+            tokio::task::spawn_blocking(move || runtime.execute_skill(&plugin_id, &name, args))
+                .await??;
+        for log in &output.logs {
+            tracing::debug!("[{}] {}", self.plugin_id, log);
+        }
+        let text = match output.result {
+            Value::String(text) => text,
+            other => serde_json::to_string_pretty(&other)?,
+        };
+        if !output.success {
+            anyhow::bail!("Skill {} of {} failed: {}", self.skill.name, self.plugin_id, text);
+        }
+        Ok(SkillOutput {
+            text,
+            images: output.images,
+        })
+    }
+
+    fn writes_unlisted_files(&self) -> bool {
+        self.can_write
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+id: charts
+name: Charts
+version: 1.0.0
+description: Draws charts
+author:
+  name: Dev
+plugin_type: wasm
+min_webrana_version: 0.3.0
+permissions: []
+skills:
+  - name: draw
+    description: Draw a chart
+    input_schema:
+      type: object
+entry_point: plugin.wat
+"#;
+
+    /// Returns a one-image output following the webrana-plugin-sdk ABI
+    const CHART_WAT: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (global $next (mut i32) (i32.const 4096))
+  (data (i32.const 64) "{\"success\":true,\"result\":\"drawn\",\"images\":[{\"media_type\":\"image/png\",\"data_base64\":\"iVBORw0KGgo=\",\"caption\":\"chart\"}]}")
+  (func (export "webrana_alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next (i32.add (global.get $next) (local.get $len)))
+    (local.get $ptr))
+  (func (export "webrana_dealloc") (param i32 i32))
+  (func (export "webrana_execute") (param i32 i32) (result i64)
+    (i64.or (i64.shl (i64.const 64) (i64.const 32)) (i64.const 118)))
+)
+"#;
+
+    /// Asks the host for a file 300k times, which takes seconds
+    const SPIN_WAT: &str = r#"
+(module
+  (import "webrana" "read_file" (func $read (param i32 i32) (result i64)))
+  (memory (export "memory") 1)
+  (func (export "webrana_alloc") (param i32) (result i32) (i32.const 64))
+  (func (export "webrana_dealloc") (param i32 i32))
+  (func (export "webrana_execute") (param i32 i32) (result i64)
+    (local $left i32)
+    (local.set $left (i32.const 300000))
+    (loop $spin
+      (drop (call $read (i32.const 0) (i32.const 0)))
+      (local.set $left (i32.sub (local.get $left) (i32.const 1)))
+      (br_if $spin (local.get $left)))
+    (i64.const 0))
+)
+"#;
+
+    /// Writes `target/plugin-denied.txt` through the host
+    const WRITER_WAT: &str = r#"
+(module
+  (import "webrana" "write_file" (func $write (param i32 i32 i32 i32) (result i64)))
+  (memory (export "memory") 1)
+  (data (i32.const 64) "target/plugin-denied.txt")
+  (data (i32.const 128) "written")
+  (data (i32.const 192) "{\"success\":true,\"result\":\"ok\"}")
+  (func (export "webrana_alloc") (param i32) (result i32) (i32.const 1024))
+  (func (export "webrana_dealloc") (param i32 i32))
+  (func (export "webrana_execute") (param i32 i32) (result i64)
+    (drop (call $write (i32.const 64) (i32.const 24) (i32.const 128) (i32.const 7)))
+    (i64.or (i64.shl (i64.const 192) (i64.const 32)) (i64.const 30)))
+)
+"#;
+
+    fn load_skills(tmp: &tempfile::TempDir, code: &str) -> Vec<PluginSkill> {
+        load_skills_with(tmp, MANIFEST, code)
+    }
+
+    fn load_skills_with(tmp: &tempfile::TempDir, manifest: &str, code: &str) -> Vec<PluginSkill> {
+        let plugin_dir = tmp.path().join("charts");
+        std::fs::create_dir(&plugin_dir).unwrap();
+        std::fs::write(plugin_dir.join("manifest.yaml"), manifest).unwrap();
+        std::fs::write(plugin_dir.join("plugin.wat"), code).unwrap();
+
+        let mut runtime = PluginRuntime::new();
+        runtime.set_crew_permissions(None);
+        runtime.add_plugin_dir(tmp.path());
+        runtime.init().unwrap();
+        runtime.load_plugin("charts").unwrap();
+        from_runtime(runtime)
+    }
+
+    #[test]
+    fn test_only_wasm_plugins_become_tools() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let mut manager = PluginManager::new(crate::plugins::ManagerConfig {
+            plugins_dir: tmp.path().join("installed"),
+            registries: Vec::new(),
+            auto_update: false,
+        })
+        .unwrap();
+        for (id, kind, entry, code) in [
+            ("charts", "wasm", "plugin.wat", CHART_WAT),
+            ("scripted", "script", "plugin.py", "print('hi')\n"),
+        ] {
+            let dir = tmp.path().join(id);
+            std::fs::create_dir(&dir).unwrap();
+            let manifest = MANIFEST
+                .replace("id: charts", &format!("id: {}", id))
+                .replace("plugin_type: wasm", &format!("plugin_type: {}", kind))
+                .replace("entry_point: plugin.wat", &format!("entry_point: {}", entry));
+            std::fs::write(dir.join("manifest.yaml"), manifest).unwrap();
+            std::fs::write(dir.join(entry), code).unwrap();
+            manager.install_local(&dir).unwrap();
+        }
+        assert_eq!(manager.list_enabled().len(), 2);
+
+        let skills = plugin_skills(&manager, None);
+        let owners: Vec<&str> = skills.iter().map(|skill| skill.plugin_id.as_str()).collect();
+        assert_eq!(owners, ["charts"]);
+    }
+
+    #[tokio::test]
+    async fn test_plugin_images_reach_the_skill_output() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let skills = load_skills(&tmp, CHART_WAT);
+        assert_eq!(skills.len(), 1);
+        assert_eq!(skills[0].definition().name, "draw");
+
+        let output = skills[0]
+            .execute_output(&serde_json::json!({}), &Settings::default())
+            .await
+            .unwrap();
+        assert_eq!(output.text, "drawn");
+        assert_eq!(output.images.len(), 1);
+        assert_eq!(output.images[0].caption.as_deref(), Some("chart"));
+    }
+
+    #[tokio::test]
+    async fn test_spinning_plugin_cut_off_by_skill_timeout() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let mut registry = crate::skills::SkillRegistry::new();
+        for skill in load_skills(&tmp, SPIN_WAT) {
+            registry.register(Box::new(skill));
+        }
+        let mut settings = Settings::default();
+        settings.skill_timeouts.skills.insert("draw".to_string(), 1);
+
+        let start = std::time::Instant::now();
+        let err = registry
+            .execute("draw", &serde_json::json!({}), &settings)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Skill 'draw' timed out after 1s"), "{}", err);
+        assert!(start.elapsed() < std::time::Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn test_plugin_write_refused_under_deny_paths() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let manifest = MANIFEST.replace("permissions: []", "permissions:\n  - fs:write");
+        let mut registry = crate::skills::SkillRegistry::new();
+        for skill in load_skills_with(&tmp, &manifest, WRITER_WAT) {
+            assert!(skill.writes_unlisted_files());
+            registry.register(Box::new(skill));
+        }
+        registry.set_write_paths(Vec::new(), vec!["target/plugin-denied.txt".to_string()]);
+        assert!(!registry.list().iter().any(|def| def.name == "draw"));
+
+        let err = registry
+            .execute("draw", &serde_json::json!({}), &Settings::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("disabled while write paths are restricted"), "{}", err);
+        assert!(!std::path::Path::new("target/plugin-denied.txt").exists());
+    }
+}
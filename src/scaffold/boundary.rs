@@ -0,0 +1,123 @@
+//! Working-directory boundary for agent-driven scaffolding
+
+use anyhow::{bail, Result};
+use std::path::{Component, Path, PathBuf};
+
+use crate::core::resolve_existing;
+use crate::skills::written_paths;
+
+/// Skills that can touch the filesystem outside any path argument
+const UNBOUNDED_SKILLS: &[&str] = &["execute_command", "git_commit", "git_checkout"];
+
+/// Restricts file writes to a single directory tree
+#[derive(Debug, Clone)]
+pub struct WorkdirBoundary {
+    root: PathBuf,
+}
+
+impl WorkdirBoundary {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        let root = root.canonicalize().unwrap_or_else(|_| normalize(root));
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolve `path` against the boundary root, rejecting anything outside it
+    pub fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let candidate = Path::new(path);
+        let joined = if candidate.is_absolute() {
+            candidate.to_path_buf()
+        } else {
+            self.root.join(candidate)
+        };
+        // Follow symlinks for the part of the path that already exists
+        let resolved = resolve_existing(&joined);
+
+        if !resolved.starts_with(&self.root) {
+            bail!(
+                "Path '{}' is outside the project directory {}",
+                path,
+                self.root.display()
+            );
+        }
+        Ok(resolved)
+    }
+
+    /// Check a skill invocation against the boundary
+    pub fn check_skill(&self, name: &str, args: &serde_json::Value) -> Result<()> {
+        if UNBOUNDED_SKILLS.contains(&name) {
+            bail!("Skill '{}' is not available while scaffolding", name);
+        }
+        for path in written_paths(name, args) {
+            self.resolve(&path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lexically resolve `.` and `..` without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_boundary_paths() {
+        let tmp = TempDir::new().unwrap();
+        let boundary = WorkdirBoundary::new(tmp.path());
+
+        assert!(boundary.resolve("src/main.rs").is_ok());
+        assert!(boundary.resolve("./a/../b.txt").is_ok());
+        assert!(boundary.resolve("../escape.txt").is_err());
+        assert!(boundary.resolve("a/../../escape.txt").is_err());
+        assert!(boundary.resolve("/etc/passwd").is_err());
+
+        let inside = boundary.root().join("nested/file.rs");
+        assert!(boundary.resolve(&inside.to_string_lossy()).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_boundary_rejects_symlink_escape() {
+        let tmp = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        std::os::unix::fs::symlink(outside.path(), tmp.path().join("link")).unwrap();
+
+        let boundary = WorkdirBoundary::new(tmp.path());
+        assert!(boundary.resolve("link/file.txt").is_err());
+    }
+
+    #[test]
+    fn test_boundary_skills() {
+        let tmp = TempDir::new().unwrap();
+        let boundary = WorkdirBoundary::new(tmp.path());
+
+        let inside = serde_json::json!({"path": "src/lib.rs", "content": ""});
+        let outside = serde_json::json!({"path": "../lib.rs", "content": ""});
+        assert!(boundary.check_skill("write_file", &inside).is_ok());
+        assert!(boundary.check_skill("write_file", &outside).is_err());
+        assert!(boundary.check_skill("edit_file", &outside).is_err());
+        assert!(boundary.check_skill("read_file", &outside).is_ok());
+        assert!(boundary
+            .check_skill("execute_command", &serde_json::json!({"command": "ls"}))
+            .is_err());
+    }
+}
@@ -0,0 +1,378 @@
+//! Project generation, verification checklist and run report
+
+use anyhow::{bail, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::template::{default_vars, render, Check, ScaffoldTemplate};
+use crate::indexer::ProjectDetector;
+use crate::util::diff::{unified, DiffStyle};
+
+/// Where the run report is written inside the generated project
+pub const REPORT_FILE: &str = ".webrana/scaffold-report.json";
+
+/// Directories ignored when listing generated files
+const SKIP_DIRS: &[&str] = &[".git", ".webrana", "target", "node_modules", "__pycache__", ".venv"];
+
+#[derive(Debug, Clone, Default)]
+pub struct ScaffoldOptions {
+    /// Extra `--var` values, overriding the defaults
+    pub vars: HashMap<String, String>,
+    /// Allow writing into a non-empty directory
+    pub force: bool,
+    /// Only list the files that would be created
+    pub dry_run: bool,
+    /// Skip the post-generation checklist
+    pub skip_checks: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaffoldMode {
+    Builtin,
+    Agent,
+}
+
+/// Outcome of one checklist item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub check: String,
+    pub command: Option<String>,
+    pub passed: bool,
+    pub skipped: bool,
+    pub output: String,
+}
+
+/// Everything that happened during a `webrana new` run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    pub template: String,
+    pub mode: ScaffoldMode,
+    pub destination: String,
+    pub dry_run: bool,
+    pub vars: HashMap<String, String>,
+    pub files: Vec<String>,
+    pub checks: Vec<CheckResult>,
+}
+
+impl RunReport {
+    /// True when no checklist item failed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed || c.skipped)
+    }
+
+    /// Write the report to `<destination>/.webrana/scaffold-report.json`
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = Path::new(&self.destination).join(REPORT_FILE);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+}
+
+/// Generates a project into a destination directory
+pub struct Scaffolder {
+    dest: PathBuf,
+    name: String,
+    options: ScaffoldOptions,
+}
+
+impl Scaffolder {
+    /// `name` defaults the `{{name}}` variable; `--var name=...` still wins
+    pub fn new(dest: impl Into<PathBuf>, name: &str, options: ScaffoldOptions) -> Self {
+        Self {
+            dest: dest.into(),
+            name: name.to_string(),
+            options,
+        }
+    }
+
+    pub fn dest(&self) -> &Path {
+        &self.dest
+    }
+
+    /// Template variables: defaults from the project name plus `--var` overrides
+    pub fn vars(&self) -> HashMap<String, String> {
+        let mut vars = default_vars(&self.name);
+        vars.extend(self.options.vars.clone());
+        vars
+    }
+
+    /// Refuse to write into a non-empty directory unless forced
+    pub fn check_destination(&self) -> Result<()> {
+        if self.dest.is_file() {
+            bail!("{} exists and is not a directory", self.dest.display());
+        }
+        let non_empty = self.dest.is_dir() && fs::read_dir(&self.dest)?.next().is_some();
+        if non_empty && !self.options.force {
+            bail!(
+                "{} is not empty (use --force to generate into it anyway)",
+                self.dest.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Render every file in the template as (relative path, content)
+    pub fn plan(&self, template: &ScaffoldTemplate) -> Result<Vec<(String, String)>> {
+        let vars = self.vars();
+        template
+            .files
+            .iter()
+            .map(|file| {
+                let path = render(file.path, &vars)
+                    .with_context(|| format!("Rendering path {}", file.path))?;
+                let content = render(file.content, &vars)
+                    .with_context(|| format!("Rendering {}", file.path))?;
+                Ok((path, content))
+            })
+            .collect()
+    }
+
+    /// Diffs for planned files that already exist with other content, i.e.
+    /// what `--force` would overwrite
+    pub fn overwrite_diffs(&self, template: &ScaffoldTemplate, style: DiffStyle) -> Result<Vec<String>> {
+        let mut diffs = Vec::new();
+        for (path, content) in self.plan(template)? {
+            if let Ok(existing) = fs::read_to_string(self.dest.join(&path)) {
+                let diff = unified(&existing, &content, &path, style);
+                if !diff.is_empty() {
+                    diffs.push(diff);
+                }
+            }
+        }
+        Ok(diffs)
+    }
+
+    /// Generate a built-in template and run its checklist
+    pub fn generate(&self, template: &ScaffoldTemplate) -> Result<RunReport> {
+        self.check_destination()?;
+        let planned = self.plan(template)?;
+
+        let mut report = self.report(template.name, ScaffoldMode::Builtin);
+        report.files = planned.iter().map(|(path, _)| path.clone()).collect();
+        if self.options.dry_run {
+            return Ok(report);
+        }
+
+        for (path, content) in &planned {
+            let target = self.dest.join(path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&target, content)
+                .with_context(|| format!("Writing {}", target.display()))?;
+        }
+
+        if !self.options.skip_checks {
+            report.checks = self.run_checklist(template.setup, &template.checklist);
+        }
+        report.save()?;
+        Ok(report)
+    }
+
+    /// Task given to the agent for templates that are not built in
+    pub fn agent_prompt(&self, description: &str) -> String {
+        let mut vars: Vec<String> = self
+            .vars()
+            .iter()
+            .map(|(k, v)| format!("- {} = {}", k, v))
+            .collect();
+        vars.sort();
+
+        format!(
+            "Scaffold a new project: {}\n\n\
+            The current directory is the new project root. Create files ONLY inside it, \
+            using relative paths; writes elsewhere will be rejected and shell commands are unavailable.\n\n\
+            Template variables:\n{}\n\n\
+            Include a README, a .gitignore and a CI workflow. When you are done the project \
+            must build, its tests must pass and lints must be clean; this will be verified \
+            with the standard build/test/lint commands for the project type.",
+            description,
+            vars.join("\n")
+        )
+    }
+
+    /// Record the files the agent created and verify them with the checklist
+    pub fn finish_agent_run(&self, description: &str) -> Result<RunReport> {
+        let mut report = self.report(description, ScaffoldMode::Agent);
+        report.files = list_files(&self.dest)?;
+        if !self.options.skip_checks {
+            report.checks = self.run_checklist(None, &Check::all());
+        }
+        report.save()?;
+        Ok(report)
+    }
+
+    /// Run build/test/lint using the commands for the detected project type
+    pub fn run_checklist(&self, setup: Option<&str>, checks: &[Check]) -> Vec<CheckResult> {
+        let mut results = Vec::new();
+
+        if let Some(setup) = setup {
+            let result = self.run_check("setup", setup);
+            let failed = !result.passed;
+            results.push(result);
+            if failed {
+                return results;
+            }
+        }
+
+        let project_type = ProjectDetector::new(&self.dest)
+            .detect()
+            .map(|info| info.project_type)
+            .unwrap_or(crate::indexer::ProjectType::Unknown);
+
+        for check in checks {
+            let command = match check {
+                Check::Build => project_type.build_command(),
+                Check::Test => project_type.test_command(),
+                Check::Lint => project_type.lint_command(),
+            };
+            match command {
+                Some(command) => results.push(self.run_check(check.as_str(), command)),
+                None => results.push(CheckResult {
+                    check: check.as_str().to_string(),
+                    command: None,
+                    passed: false,
+                    skipped: true,
+                    output: format!("No {} command for {} projects", check.as_str(), project_type.as_str()),
+                }),
+            }
+        }
+        results
+    }
+
+    fn run_check(&self, name: &str, command: &str) -> CheckResult {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        let output = Command::new(parts[0])
+            .args(&parts[1..])
+            .current_dir(&self.dest)
+            .output();
+
+        let (passed, output) = match output {
+            Ok(out) => (
+                out.status.success(),
+                format!(
+                    "{}{}",
+                    String::from_utf8_lossy(&out.stdout),
+                    String::from_utf8_lossy(&out.stderr)
+                ),
+            ),
+            Err(e) => (false, format!("Failed to run {}: {}", parts[0], e)),
+        };
+
+        CheckResult {
+            check: name.to_string(),
+            command: Some(command.to_string()),
+            passed,
+            skipped: false,
+            output: output.trim().to_string(),
+        }
+    }
+
+    fn report(&self, template: &str, mode: ScaffoldMode) -> RunReport {
+        RunReport {
+            template: template.to_string(),
+            mode,
+            destination: self.dest.to_string_lossy().to_string(),
+            dry_run: self.options.dry_run,
+            vars: self.vars(),
+            files: Vec::new(),
+            checks: Vec::new(),
+        }
+    }
+}
+
+/// Relative paths of all files under `root`, skipping build and VCS directories
+fn list_files(root: &Path) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string());
+            if path.is_dir() {
+                if !name.map(|n| SKIP_DIRS.contains(&n.as_str())).unwrap_or(false) {
+                    stack.push(path);
+                }
+            } else if let Ok(rel) = path.strip_prefix(root) {
+                files.push(rel.to_string_lossy().to_string());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_generate_and_dry_run() {
+        let tmp = TempDir::new().unwrap();
+        let template = ScaffoldTemplate::builtin("python-fastapi").unwrap();
+        let options = ScaffoldOptions {
+            skip_checks: true,
+            dry_run: true,
+            ..Default::default()
+        };
+
+        let dest = tmp.path().join("my-api");
+        let report = Scaffolder::new(&dest, "my-api", options.clone())
+            .generate(&template)
+            .unwrap();
+        assert!(report.files.contains(&"my_api/main.py".to_string()));
+        assert!(!dest.exists());
+
+        let options = ScaffoldOptions { dry_run: false, ..options };
+        let report = Scaffolder::new(&dest, "my-api", options.clone())
+            .generate(&template)
+            .unwrap();
+        assert!(report.passed());
+        let test = fs::read_to_string(dest.join("tests/test_main.py")).unwrap();
+        assert!(test.contains("from my_api.main import app"));
+        assert!(dest.join(REPORT_FILE).exists());
+
+        // Non-empty destination needs --force
+        assert!(Scaffolder::new(&dest, "my-api", options.clone())
+            .generate(&template)
+            .is_err());
+        let forced = ScaffoldOptions { force: true, ..options };
+        assert!(Scaffolder::new(&dest, "my-api", forced.clone()).generate(&template).is_ok());
+
+        // A forced dry run shows what would be overwritten
+        fs::write(dest.join("tests/test_main.py"), "# local changes\n").unwrap();
+        let diffs = Scaffolder::new(&dest, "my-api", ScaffoldOptions { dry_run: true, ..forced })
+            .overwrite_diffs(&template, DiffStyle::default())
+            .unwrap();
+        assert_eq!(diffs.len(), 1);
+        assert!(diffs[0].starts_with("--- a/tests/test_main.py"));
+        assert!(diffs[0].contains("\n-# local changes\n"));
+    }
+
+    #[test]
+    fn test_agent_report_lists_files() {
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("src")).unwrap();
+        fs::create_dir_all(tmp.path().join("target/debug")).unwrap();
+        fs::write(tmp.path().join("src/app.go"), "package main").unwrap();
+        fs::write(tmp.path().join("target/debug/out"), "").unwrap();
+
+        let options = ScaffoldOptions {
+            skip_checks: true,
+            ..Default::default()
+        };
+        let report = Scaffolder::new(tmp.path(), "app", options)
+            .finish_agent_run("go service")
+            .unwrap();
+        assert_eq!(report.mode, ScaffoldMode::Agent);
+        assert_eq!(report.files, vec!["src/app.go".to_string()]);
+    }
+}
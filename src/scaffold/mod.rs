@@ -0,0 +1,15 @@
+//! Scaffold - Generate new projects from templates
+//!
+//! Built-in templates are file manifests with `{{var}}` placeholders and a
+//! post-generation checklist. Unknown templates are handed to the agent,
+//! which may only write inside the new project directory.
+
+mod boundary;
+mod generator;
+mod template;
+
+pub use boundary::WorkdirBoundary;
+#[allow(unused_imports)]
+pub use generator::{CheckResult, RunReport, ScaffoldMode, ScaffoldOptions, Scaffolder, REPORT_FILE};
+#[allow(unused_imports)]
+pub use template::{parse_vars, render, Check, ScaffoldTemplate, TemplateFile};
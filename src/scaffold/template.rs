@@ -0,0 +1,428 @@
+//! Built-in project templates and `{{var}}` rendering
+
+use anyhow::{anyhow, bail, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+
+lazy_static! {
+    static ref PLACEHOLDER: Regex = Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").unwrap();
+}
+
+/// A post-generation verification step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    Build,
+    Test,
+    Lint,
+}
+
+impl Check {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Check::Build => "build",
+            Check::Test => "tests",
+            Check::Lint => "lint",
+        }
+    }
+
+    pub fn all() -> Vec<Check> {
+        vec![Check::Build, Check::Test, Check::Lint]
+    }
+}
+
+/// A file in a template manifest. Both path and content may contain placeholders.
+#[derive(Debug, Clone)]
+pub struct TemplateFile {
+    pub path: &'static str,
+    pub content: &'static str,
+}
+
+/// A project template: files to create plus the checklist to verify them
+#[derive(Debug, Clone)]
+pub struct ScaffoldTemplate {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub files: Vec<TemplateFile>,
+    /// Command run before the checklist (e.g. installing dependencies)
+    pub setup: Option<&'static str>,
+    pub checklist: Vec<Check>,
+}
+
+impl ScaffoldTemplate {
+    /// Names of all built-in templates
+    pub fn builtin_names() -> Vec<&'static str> {
+        vec!["rust-cli", "rust-axum-service", "python-fastapi", "node-ts-lib"]
+    }
+
+    /// Look up a built-in template by name
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "rust-cli" => Some(Self {
+                name: "rust-cli",
+                description: "Rust command-line application",
+                files: vec![
+                    TemplateFile { path: "Cargo.toml", content: RUST_CLI_CARGO },
+                    TemplateFile { path: "src/main.rs", content: RUST_CLI_MAIN },
+                    TemplateFile { path: ".gitignore", content: RUST_GITIGNORE },
+                    TemplateFile { path: "README.md", content: README },
+                    TemplateFile { path: ".github/workflows/ci.yml", content: RUST_CI },
+                ],
+                setup: None,
+                checklist: Check::all(),
+            }),
+            "rust-axum-service" => Some(Self {
+                name: "rust-axum-service",
+                description: "Rust HTTP service using axum and tokio",
+                files: vec![
+                    TemplateFile { path: "Cargo.toml", content: AXUM_CARGO },
+                    TemplateFile { path: "src/main.rs", content: AXUM_MAIN },
+                    TemplateFile { path: ".gitignore", content: RUST_GITIGNORE },
+                    TemplateFile { path: "README.md", content: README },
+                    TemplateFile { path: ".github/workflows/ci.yml", content: RUST_CI },
+                ],
+                setup: None,
+                checklist: Check::all(),
+            }),
+            "python-fastapi" => Some(Self {
+                name: "python-fastapi",
+                description: "Python FastAPI service with pytest",
+                files: vec![
+                    TemplateFile { path: "pyproject.toml", content: FASTAPI_PYPROJECT },
+                    TemplateFile { path: "{{snake_name}}/__init__.py", content: "" },
+                    TemplateFile { path: "{{snake_name}}/main.py", content: FASTAPI_MAIN },
+                    TemplateFile { path: "tests/test_main.py", content: FASTAPI_TEST },
+                    TemplateFile { path: ".gitignore", content: PYTHON_GITIGNORE },
+                    TemplateFile { path: "README.md", content: README },
+                    TemplateFile { path: ".github/workflows/ci.yml", content: PYTHON_CI },
+                ],
+                setup: None,
+                checklist: vec![Check::Test, Check::Lint],
+            }),
+            "node-ts-lib" => Some(Self {
+                name: "node-ts-lib",
+                description: "TypeScript library with vitest",
+                files: vec![
+                    TemplateFile { path: "package.json", content: NODE_PACKAGE },
+                    TemplateFile { path: "tsconfig.json", content: NODE_TSCONFIG },
+                    TemplateFile { path: "src/index.ts", content: NODE_INDEX },
+                    TemplateFile { path: "test/index.test.ts", content: NODE_TEST },
+                    TemplateFile { path: ".gitignore", content: NODE_GITIGNORE },
+                    TemplateFile { path: "README.md", content: README },
+                    TemplateFile { path: ".github/workflows/ci.yml", content: NODE_CI },
+                ],
+                setup: Some("npm install"),
+                checklist: Check::all(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Default variables derived from the project name
+pub fn default_vars(name: &str) -> HashMap<String, String> {
+    let snake = name.replace(['-', ' ', '.'], "_").to_lowercase();
+    let mut vars = HashMap::new();
+    vars.insert("name".to_string(), name.to_string());
+    vars.insert("snake_name".to_string(), snake);
+    vars.insert("description".to_string(), format!("{} - generated by webrana", name));
+    vars
+}
+
+/// Parse `key=value` pairs from `--var` arguments
+pub fn parse_vars(pairs: &[String]) -> Result<HashMap<String, String>> {
+    let mut vars = HashMap::new();
+    for pair in pairs {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid --var '{}', expected key=value", pair))?;
+        let key = key.trim();
+        if key.is_empty() {
+            bail!("Invalid --var '{}', key is empty", pair);
+        }
+        vars.insert(key.to_string(), value.to_string());
+    }
+    Ok(vars)
+}
+
+/// Replace `{{var}}` placeholders. Unknown variables are an error.
+pub fn render(text: &str, vars: &HashMap<String, String>) -> Result<String> {
+    let missing: Vec<String> = PLACEHOLDER
+        .captures_iter(text)
+        .map(|cap| cap[1].to_string())
+        .filter(|key| !vars.contains_key(key))
+        .collect();
+    if !missing.is_empty() {
+        bail!("Missing template variable(s): {}", missing.join(", "));
+    }
+
+    Ok(PLACEHOLDER
+        .replace_all(text, |cap: &regex::Captures| vars[&cap[1]].clone())
+        .into_owned())
+}
+
+const README: &str = "# {{name}}
+
+{{description}}
+";
+
+const RUST_GITIGNORE: &str = "/target
+";
+
+const RUST_CI: &str = "name: CI
+
+on: [push, pull_request]
+
+jobs:
+  check:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - uses: dtolnay/rust-toolchain@stable
+        with:
+          components: clippy
+      - run: cargo build
+      - run: cargo test
+      - run: cargo clippy -- -D warnings
+";
+
+const RUST_CLI_CARGO: &str = "[package]
+name = \"{{name}}\"
+version = \"0.1.0\"
+edition = \"2021\"
+description = \"{{description}}\"
+
+[dependencies]
+";
+
+const RUST_CLI_MAIN: &str = "use std::env;
+
+fn greeting(name: &str) -> String {
+    format!(\"Hello, {}!\", name)
+}
+
+fn main() {
+    let name = env::args().nth(1).unwrap_or_else(|| \"world\".to_string());
+    println!(\"{}\", greeting(&name));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_greeting() {
+        assert_eq!(greeting(\"{{name}}\"), \"Hello, {{name}}!\");
+    }
+}
+";
+
+const AXUM_CARGO: &str = "[package]
+name = \"{{name}}\"
+version = \"0.1.0\"
+edition = \"2021\"
+description = \"{{description}}\"
+
+[dependencies]
+axum = \"0.7\"
+tokio = { version = \"1\", features = [\"full\"] }
+";
+
+const AXUM_MAIN: &str = "use axum::{routing::get, Router};
+
+async fn health() -> &'static str {
+    \"ok\"
+}
+
+fn app() -> Router {
+    Router::new().route(\"/health\", get(health))
+}
+
+#[tokio::main]
+async fn main() {
+    let port = std::env::var(\"PORT\").unwrap_or_else(|_| \"3000\".to_string());
+    let listener = tokio::net::TcpListener::bind(format!(\"0.0.0.0:{}\", port))
+        .await
+        .expect(\"failed to bind\");
+    println!(\"{{name}} listening on port {}\", port);
+    axum::serve(listener, app()).await.expect(\"server error\");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_health() {
+        assert_eq!(health().await, \"ok\");
+    }
+}
+";
+
+const PYTHON_GITIGNORE: &str = "__pycache__/
+*.pyc
+.venv/
+.pytest_cache/
+";
+
+const PYTHON_CI: &str = "name: CI
+
+on: [push, pull_request]
+
+jobs:
+  check:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - uses: actions/setup-python@v5
+        with:
+          python-version: \"3.12\"
+      - run: pip install -e .[dev]
+      - run: ruff check .
+      - run: pytest
+";
+
+const FASTAPI_PYPROJECT: &str = "[project]
+name = \"{{name}}\"
+version = \"0.1.0\"
+description = \"{{description}}\"
+requires-python = \">=3.9\"
+dependencies = [\"fastapi\", \"uvicorn\"]
+
+[project.optional-dependencies]
+dev = [\"pytest\", \"httpx\", \"ruff\"]
+
+[tool.setuptools]
+packages = [\"{{snake_name}}\"]
+";
+
+const FASTAPI_MAIN: &str = "from fastapi import FastAPI
+
+app = FastAPI(title=\"{{name}}\")
+
+
+@app.get(\"/health\")
+def health() -> dict:
+    return {\"status\": \"ok\"}
+";
+
+const FASTAPI_TEST: &str = "from fastapi.testclient import TestClient
+
+from {{snake_name}}.main import app
+
+
+def test_health():
+    client = TestClient(app)
+    response = client.get(\"/health\")
+    assert response.status_code == 200
+    assert response.json() == {\"status\": \"ok\"}
+";
+
+const NODE_GITIGNORE: &str = "node_modules/
+dist/
+";
+
+const NODE_CI: &str = "name: CI
+
+on: [push, pull_request]
+
+jobs:
+  check:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - uses: actions/setup-node@v4
+        with:
+          node-version: 20
+      - run: npm install
+      - run: npm run build
+      - run: npm test
+";
+
+const NODE_PACKAGE: &str = "{
+  \"name\": \"{{name}}\",
+  \"version\": \"0.1.0\",
+  \"description\": \"{{description}}\",
+  \"main\": \"dist/index.js\",
+  \"types\": \"dist/index.d.ts\",
+  \"scripts\": {
+    \"build\": \"tsc\",
+    \"test\": \"vitest run\"
+  },
+  \"devDependencies\": {
+    \"typescript\": \"^5.4.0\",
+    \"vitest\": \"^1.6.0\"
+  }
+}
+";
+
+const NODE_TSCONFIG: &str = "{
+  \"compilerOptions\": {
+    \"target\": \"ES2020\",
+    \"module\": \"commonjs\",
+    \"declaration\": true,
+    \"outDir\": \"dist\",
+    \"strict\": true,
+    \"esModuleInterop\": true
+  },
+  \"include\": [\"src\"]
+}
+";
+
+const NODE_INDEX: &str = "export function greet(name: string): string {
+  return `Hello, ${name}!`;
+}
+";
+
+const NODE_TEST: &str = "import { describe, expect, it } from \"vitest\";
+import { greet } from \"../src/index\";
+
+describe(\"{{name}}\", () => {
+  it(\"greets\", () => {
+    expect(greet(\"world\")).toBe(\"Hello, world!\");
+  });
+});
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_placeholders() {
+        let mut vars = default_vars("my-app");
+        vars.insert("author".to_string(), "Ada".to_string());
+
+        let out = render("{{name}} by {{ author }} ({{snake_name}})", &vars).unwrap();
+        assert_eq!(out, "my-app by Ada (my_app)");
+
+        // Single braces and template literals are left alone
+        let out = render("format!(\"{}\", x); `${name}`", &vars).unwrap();
+        assert_eq!(out, "format!(\"{}\", x); `${name}`");
+
+        let err = render("{{name}} {{missing}}", &vars).unwrap_err();
+        assert!(err.to_string().contains("missing"));
+    }
+
+    #[test]
+    fn test_parse_vars() {
+        let vars = parse_vars(&["port=8080".to_string(), "msg=a=b".to_string()]).unwrap();
+        assert_eq!(vars["port"], "8080");
+        assert_eq!(vars["msg"], "a=b");
+        assert!(parse_vars(&["novalue".to_string()]).is_err());
+        assert!(parse_vars(&["=x".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_builtin_templates_render() {
+        for name in ScaffoldTemplate::builtin_names() {
+            let template = ScaffoldTemplate::builtin(name).unwrap();
+            let vars = default_vars("demo-project");
+            for file in &template.files {
+                render(file.path, &vars).unwrap();
+                render(file.content, &vars).unwrap();
+            }
+        }
+        assert!(ScaffoldTemplate::builtin("unknown").is_none());
+    }
+}
@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 
+use crate::indexer::encoding;
+
 #[allow(unused_imports)]
 use crate::indexer::{FileIndex, FileType, FileWalker, ProjectDetector, ProjectInfo};
 
@@ -34,8 +36,9 @@ impl CodebaseSkill {
         if self.index.is_none() {
             let mut walker = FileWalker::new(&self.root);
             walker.load_gitignore()?;
-            let entries = walker.walk()?;
-            self.index = Some(FileIndex::build(entries));
+            let mut index = FileIndex::build(walker.walk_stream());
+            index.sort();
+            self.index = Some(index);
         }
         Ok(self.index.as_ref().unwrap())
     }
@@ -76,7 +79,7 @@ impl CodebaseSkill {
 
     pub fn get_file_content(&self, path: &str) -> Result<String> {
         let full_path = self.root.join(path);
-        Ok(fs::read_to_string(full_path)?)
+        Ok(encoding::read_to_string(&full_path)?)
     }
 
     pub fn grep(&self, pattern: &str) -> Result<Vec<GrepResult>> {
@@ -109,7 +112,7 @@ impl CodebaseSkill {
             if path.is_dir() {
                 self.grep_recursive(&path, pattern, results, depth + 1)?;
             } else if path.is_file() {
-                if let Ok(content) = fs::read_to_string(&path) {
+                if let Ok(content) = encoding::read_to_string(&path) {
                     let relative_path = path
                         .strip_prefix(&self.root)
                         .unwrap_or(&path)
@@ -137,7 +140,7 @@ impl CodebaseSkill {
 
     pub fn list_symbols(&self, path: &str) -> Result<Vec<Symbol>> {
         let full_path = self.root.join(path);
-        let content = fs::read_to_string(&full_path)?;
+        let content = encoding::read_to_string(&full_path)?;
         let extension = Path::new(path)
             .extension()
             .and_then(|e| e.to_str())
@@ -156,6 +159,61 @@ impl CodebaseSkill {
         Ok(symbols)
     }
 
+    /// Which functions call which within one file (Rust and Python)
+    pub fn call_graph(&self, path: &str) -> Result<Vec<CallEdge>> {
+        self.call_graph_files(&[path])
+    }
+
+    /// Call edges across a small set of files. A callee counts when it is
+    /// defined in any of the given files; calls into other code are dropped.
+    pub fn call_graph_files(&self, paths: &[&str]) -> Result<Vec<CallEdge>> {
+        let mut sources = Vec::new();
+        for path in paths {
+            let content = encoding::read_to_string(&self.root.join(path))?;
+            let extension = Path::new(path)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("")
+                .to_string();
+            sources.push((path.to_string(), extension, content));
+        }
+
+        let mut known = std::collections::HashSet::new();
+        for (_, extension, content) in &sources {
+            for line in content.lines() {
+                let name = match extension.as_str() {
+                    "rs" => rust_fn_def(line.trim()),
+                    "py" => python_fn_def(line.trim()),
+                    _ => None,
+                };
+                known.extend(name);
+            }
+        }
+
+        let mut edges = Vec::new();
+        for (path, extension, content) in &sources {
+            let mut calls = Vec::new();
+            match extension.as_str() {
+                "rs" => rust_calls(content, &mut calls),
+                "py" => python_calls(content, &mut calls),
+                _ => {}
+            }
+            edges.extend(
+                calls
+                    .into_iter()
+                    .filter(|(_, callee, _)| known.contains(callee))
+                    .map(|(caller, callee, line)| CallEdge {
+                        file: path.clone(),
+                        caller,
+                        callee,
+                        line,
+                    }),
+            );
+        }
+
+        Ok(edges)
+    }
+
     fn extract_rust_symbols(&self, content: &str, symbols: &mut Vec<Symbol>) {
         for (line_num, line) in content.lines().enumerate() {
             let trimmed = line.trim();
@@ -403,6 +461,179 @@ impl CodebaseSkill {
     }
 }
 
+// ============================================
+// Call graph heuristics
+// ============================================
+
+/// Function name if `line` (trimmed) starts a Rust `fn` item
+fn rust_fn_def(line: &str) -> Option<String> {
+    let mut rest = line;
+    loop {
+        let before = rest;
+        for prefix in [
+            "pub(crate) ",
+            "pub(super) ",
+            "pub ",
+            "async ",
+            "const ",
+            "unsafe ",
+            "extern \"C\" ",
+        ] {
+            rest = rest.strip_prefix(prefix).unwrap_or(rest);
+        }
+        if rest == before {
+            break;
+        }
+    }
+    identifier(rest.strip_prefix("fn ")?)
+}
+
+fn python_fn_def(line: &str) -> Option<String> {
+    let rest = line.strip_prefix("async ").unwrap_or(line);
+    identifier(rest.strip_prefix("def ")?)
+}
+
+pub(super) fn identifier(text: &str) -> Option<String> {
+    let name: String = text
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+/// Drop string literal contents and anything after a line comment marker
+fn strip_code_line(line: &str, comment: &str) -> String {
+    let mut out = String::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+                out.push(c);
+            }
+            continue;
+        }
+        if line[i..].starts_with(comment) {
+            break;
+        }
+        if c == '"' || (c == '\'' && comment == "#") {
+            quote = Some(c);
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Identifiers directly followed by `(`, i.e. `b()`, `self.b()`, `Self::b()`
+fn called_names(code: &str) -> Vec<String> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut names = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let mut j = i;
+            while j < chars.len() && chars[j] == ' ' {
+                j += 1;
+            }
+            if j < chars.len() && chars[j] == '(' {
+                names.push(chars[start..i].iter().collect());
+            }
+        } else if c.is_numeric() {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    names
+}
+
+/// (caller, callee, line) for Rust, scoping functions by brace depth
+fn rust_calls(content: &str, calls: &mut Vec<(String, String, usize)>) {
+    // (name, brace depth at the definition, body opened)
+    let mut stack: Vec<(String, i32, bool)> = Vec::new();
+    let mut depth = 0i32;
+
+    for (line_num, line) in content.lines().enumerate() {
+        let code = strip_code_line(line, "//");
+        let mut body = code.as_str();
+
+        if let Some(name) = rust_fn_def(code.trim()) {
+            // Bodiless declarations (`fn f();` in traits) have no scope
+            if !code.trim_end().ends_with(';') {
+                stack.push((name, depth, false));
+            }
+            body = code.find('{').map(|i| &code[i..]).unwrap_or("");
+        }
+
+        if let Some((caller, _, _)) = stack.last() {
+            for callee in called_names(body) {
+                calls.push((caller.clone(), callee, line_num + 1));
+            }
+        }
+
+        for c in code.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            if let Some(top) = stack.last_mut() {
+                if depth > top.1 {
+                    top.2 = true;
+                }
+            }
+            while matches!(stack.last(), Some((_, d, true)) if depth <= *d) {
+                stack.pop();
+            }
+        }
+    }
+}
+
+/// (caller, callee, line) for Python, scoping functions by indentation
+fn python_calls(content: &str, calls: &mut Vec<(String, String, usize)>) {
+    // (name, indentation of the def line)
+    let mut stack: Vec<(String, usize)> = Vec::new();
+
+    for (line_num, line) in content.lines().enumerate() {
+        let code = strip_code_line(line, "#");
+        if code.trim().is_empty() {
+            continue;
+        }
+        let indent = code.len() - code.trim_start().len();
+        while matches!(stack.last(), Some((_, i)) if indent <= *i) {
+            stack.pop();
+        }
+
+        if let Some(name) = python_fn_def(code.trim()) {
+            stack.push((name, indent));
+            continue;
+        }
+
+        if let Some((caller, _)) = stack.last() {
+            for callee in called_names(&code) {
+                calls.push((caller.clone(), callee, line_num + 1));
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GrepResult {
     pub file: String,
@@ -417,6 +648,15 @@ pub struct Symbol {
     pub line: usize,
 }
 
+/// `caller` calls `callee` at `line` of `file`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallEdge {
+    pub file: String,
+    pub caller: String,
+    pub callee: String,
+    pub line: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SymbolKind {
     Function,
@@ -445,3 +685,56 @@ impl SymbolKind {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn edge(file: &str, caller: &str, callee: &str, line: usize) -> CallEdge {
+        CallEdge {
+            file: file.to_string(),
+            caller: caller.to_string(),
+            callee: callee.to_string(),
+            line,
+        }
+    }
+
+    #[test]
+    fn test_call_graph_rust() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("lib.rs"),
+            "fn a() {\n    // b() in a comment\n    let s = \"b()\";\n    b();\n    println!(\"{}\", s);\n}\n\npub fn b() -> u32 {\n    if true { 1 } else { 2 }\n}\n\nfn c() {\n    a();\n}\n",
+        )
+        .unwrap();
+
+        let skill = CodebaseSkill::new(dir.path());
+        let edges = skill.call_graph("lib.rs").unwrap();
+        assert_eq!(
+            edges,
+            vec![edge("lib.rs", "a", "b", 4), edge("lib.rs", "c", "a", 13)]
+        );
+    }
+
+    #[test]
+    fn test_call_graph_python_across_files() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("a.py"),
+            "from b import b\n\ndef a():\n    value = b()\n    print(value)\n    return value\n\nclass K:\n    def run(self):\n        return a()\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("b.py"), "def b():\n    return 1\n").unwrap();
+
+        let skill = CodebaseSkill::new(dir.path());
+        assert_eq!(
+            skill.call_graph("a.py").unwrap(),
+            vec![edge("a.py", "run", "a", 10)]
+        );
+        assert_eq!(
+            skill.call_graph_files(&["a.py", "b.py"]).unwrap(),
+            vec![edge("a.py", "a", "b", 4), edge("a.py", "run", "a", 10)]
+        );
+    }
+}
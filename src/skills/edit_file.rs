@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 
+use crate::indexer::encoding::{self, DecodedText};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EditOperation {
     pub search: String,
@@ -17,11 +19,112 @@ pub struct EditResult {
     pub message: String,
 }
 
-pub struct EditFileSkill;
+/// Trailing newline handling for line-based edits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrailingNewline {
+    /// Keep the file's current state (an empty file counts as terminated)
+    #[default]
+    Preserve,
+    Always,
+    Never,
+}
+
+/// How `insert_at_line`/`delete_lines` join lines back into a file
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LineJoin {
+    pub trailing_newline: TrailingNewline,
+}
+
+/// File content split into lines, remembering the line ending and whether
+/// the last line was terminated, so re-joining reproduces the original bytes
+pub(super) struct LineBuffer {
+    pub(super) lines: Vec<String>,
+    ending: &'static str,
+    pub(super) trailing_newline: bool,
+}
+
+impl LineBuffer {
+    pub(super) fn parse(content: &str) -> Self {
+        let ending = if content.contains("\r\n") { "\r\n" } else { "\n" };
+        let trailing_newline = content.is_empty() || content.ends_with('\n');
+        let body = content.strip_suffix('\n').unwrap_or(content);
+        let body = body.strip_suffix('\r').unwrap_or(body);
+        let lines = if content.is_empty() {
+            Vec::new()
+        } else {
+            body.split('\n')
+                .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+                .collect()
+        };
+        Self {
+            lines,
+            ending,
+            trailing_newline,
+        }
+    }
+
+    /// Lines of inserted text; one trailing newline ends the last line rather
+    /// than adding a blank one, and empty text is a single blank line
+    fn insertion(content: &str) -> Vec<String> {
+        if content.is_empty() {
+            return vec![String::new()];
+        }
+        Self::parse(content).lines
+    }
+
+    pub(super) fn render(&self, join: LineJoin) -> String {
+        if self.lines.is_empty() {
+            return String::new();
+        }
+        let mut out = self.lines.join(self.ending);
+        let trailing = match join.trailing_newline {
+            TrailingNewline::Preserve => self.trailing_newline,
+            TrailingNewline::Always => true,
+            TrailingNewline::Never => false,
+        };
+        if trailing {
+            out.push_str(self.ending);
+        }
+        out
+    }
+}
+
+pub struct EditFileSkill {
+    join: LineJoin,
+    convert_to_utf8: bool,
+}
 
 impl EditFileSkill {
     pub fn new() -> Self {
-        Self
+        Self {
+            join: LineJoin::default(),
+            convert_to_utf8: false,
+        }
+    }
+
+    pub fn with_line_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    /// Save edited files as UTF-8 instead of re-encoding them in the
+    /// encoding they were read in (Latin-1, Shift-JIS, UTF-16, ...)
+    pub fn with_convert_to_utf8(mut self, convert: bool) -> Self {
+        self.convert_to_utf8 = convert;
+        self
+    }
+
+    fn load(&self, path: &Path) -> Result<DecodedText> {
+        Ok(encoding::read_text(path)?)
+    }
+
+    fn save(&self, path: &Path, text: &str, original: &DecodedText) -> Result<()> {
+        let target = if self.convert_to_utf8 {
+            Default::default()
+        } else {
+            original.encoding
+        };
+        encoding::write_text(path, text, target)
     }
 
     pub fn edit_file(&self, path: &str, search: &str, replace: &str) -> Result<EditResult> {
@@ -36,7 +139,8 @@ impl EditFileSkill {
             });
         }
 
-        let content = fs::read_to_string(file_path)?;
+        let decoded = self.load(file_path)?;
+        let content = &decoded.text;
 
         if !content.contains(search) {
             return Ok(EditResult {
@@ -50,7 +154,7 @@ impl EditFileSkill {
         let changes = content.matches(search).count();
         let new_content = content.replace(search, replace);
 
-        fs::write(file_path, &new_content)?;
+        self.save(file_path, &new_content, &decoded)?;
 
         Ok(EditResult {
             success: true,
@@ -72,7 +176,8 @@ impl EditFileSkill {
             });
         }
 
-        let content = fs::read_to_string(file_path)?;
+        let decoded = self.load(file_path)?;
+        let content = &decoded.text;
 
         if !content.contains(search) {
             return Ok(EditResult {
@@ -84,7 +189,7 @@ impl EditFileSkill {
         }
 
         let new_content = content.replacen(search, replace, 1);
-        fs::write(file_path, &new_content)?;
+        self.save(file_path, &new_content, &decoded)?;
 
         Ok(EditResult {
             success: true,
@@ -107,11 +212,15 @@ impl EditFileSkill {
         }
 
         let file_path = Path::new(path);
-        let mut content = if file_path.exists() {
-            fs::read_to_string(file_path)?
+        let decoded = if file_path.exists() {
+            self.load(file_path)?
         } else {
-            String::new()
+            DecodedText {
+                text: String::new(),
+                encoding: Default::default(),
+            }
         };
+        let mut content = decoded.text.clone();
 
         let mut total_changes = 0;
         for op in &operations {
@@ -130,7 +239,7 @@ impl EditFileSkill {
             });
         }
 
-        fs::write(file_path, &content)?;
+        self.save(file_path, &content, &decoded)?;
 
         Ok(EditResult {
             success: true,
@@ -192,12 +301,12 @@ impl EditFileSkill {
             });
         }
 
-        let file_content = fs::read_to_string(file_path)?;
-        let mut lines: Vec<&str> = file_content.lines().collect();
+        let decoded = self.load(file_path)?;
+        let mut buffer = LineBuffer::parse(&decoded.text);
 
         let insert_at = if line_number == 0 { 0 } else { line_number - 1 };
 
-        if insert_at > lines.len() {
+        if insert_at > buffer.lines.len() {
             return Ok(EditResult {
                 success: false,
                 file_path: path.to_string(),
@@ -205,14 +314,15 @@ impl EditFileSkill {
                 message: format!(
                     "Line number {} exceeds file length {}",
                     line_number,
-                    lines.len()
+                    buffer.lines.len()
                 ),
             });
         }
 
-        lines.insert(insert_at, content);
-        let new_content = lines.join("\n");
-        fs::write(file_path, &new_content)?;
+        buffer
+            .lines
+            .splice(insert_at..insert_at, LineBuffer::insertion(content));
+        self.save(file_path, &buffer.render(self.join), &decoded)?;
 
         Ok(EditResult {
             success: true,
@@ -239,13 +349,13 @@ impl EditFileSkill {
             });
         }
 
-        let file_content = fs::read_to_string(file_path)?;
-        let lines: Vec<&str> = file_content.lines().collect();
+        let decoded = self.load(file_path)?;
+        let mut buffer = LineBuffer::parse(&decoded.text);
 
         let start = start_line.saturating_sub(1);
-        let end = end_line.min(lines.len());
+        let end = end_line.min(buffer.lines.len());
 
-        if start >= lines.len() {
+        if start >= buffer.lines.len() {
             return Ok(EditResult {
                 success: false,
                 file_path: path.to_string(),
@@ -254,16 +364,9 @@ impl EditFileSkill {
             });
         }
 
-        let deleted = end - start;
-        let new_lines: Vec<&str> = lines
-            .iter()
-            .enumerate()
-            .filter(|(i, _)| *i < start || *i >= end)
-            .map(|(_, line)| *line)
-            .collect();
-
-        let new_content = new_lines.join("\n");
-        fs::write(file_path, &new_content)?;
+        let deleted = end.saturating_sub(start);
+        buffer.lines.drain(start..end.max(start));
+        self.save(file_path, &buffer.render(self.join), &decoded)?;
 
         Ok(EditResult {
             success: true,
@@ -284,11 +387,11 @@ impl MultiEditSkill {
     pub fn batch_edit(&self, edits: Vec<(String, String, String)>) -> Result<Vec<EditResult>> {
         let skill = EditFileSkill::new();
         let mut results = Vec::new();
-        let mut backups: Vec<(String, String)> = Vec::new();
+        let mut backups: Vec<(String, Vec<u8>)> = Vec::new();
 
         for (path, _search, _replace) in &edits {
             if Path::new(path).exists() {
-                let content = fs::read_to_string(path)?;
+                let content = fs::read(path)?;
                 backups.push((path.clone(), content));
             }
         }
@@ -354,6 +457,131 @@ mod tests {
         assert_eq!(content, "hello Webrana");
     }
 
+    #[test]
+    fn test_edit_latin1_round_trip() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("legacy.py");
+        let path = file_path.to_str().unwrap();
+        let original: &[u8] = b"# R\xe9sum\xe9 des donn\xe9es\r\nname = 'Fran\xe7ois'\r\n";
+        fs::write(&file_path, original).unwrap();
+
+        // No-op edit writes back identical bytes
+        let skill = EditFileSkill::new();
+        assert!(skill.edit_file(path, "name", "name").unwrap().success);
+        assert_eq!(fs::read(&file_path).unwrap(), original);
+
+        assert!(skill.edit_file(path, "François", "Zoë").unwrap().success);
+        assert!(skill.insert_at_line(path, 2, "café = True").unwrap().success);
+        assert_eq!(
+            fs::read(&file_path).unwrap(),
+            b"# R\xe9sum\xe9 des donn\xe9es\r\ncaf\xe9 = True\r\nname = 'Zo\xeb'\r\n"
+        );
+
+        // Opting into conversion saves UTF-8
+        let converting = EditFileSkill::new().with_convert_to_utf8(true);
+        assert!(converting.edit_file(path, "True", "False").unwrap().success);
+        assert_eq!(
+            fs::read_to_string(&file_path).unwrap(),
+            "# Résumé des données\r\ncafé = False\r\nname = 'Zoë'\r\n"
+        );
+    }
+
+    #[test]
+    fn test_edit_utf16le_bom_round_trip() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("strings.txt");
+        let path = file_path.to_str().unwrap();
+        let encode = |text: &str| {
+            let mut bytes = vec![0xFF, 0xFE];
+            bytes.extend(text.encode_utf16().flat_map(|u| u.to_le_bytes()));
+            bytes
+        };
+        let original = encode("greeting=héllo\nfarewell=さようなら\n");
+        fs::write(&file_path, &original).unwrap();
+
+        let skill = EditFileSkill::new();
+        assert!(skill.edit_file(path, "héllo", "héllo").unwrap().success);
+        assert_eq!(fs::read(&file_path).unwrap(), original);
+
+        assert!(skill.edit_file(path, "héllo", "こんにちは").unwrap().success);
+        assert!(skill.delete_lines(path, 2, 2).unwrap().success);
+        assert_eq!(fs::read(&file_path).unwrap(), encode("greeting=こんにちは\n"));
+    }
+
+    #[test]
+    fn test_edit_refuses_binary() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("blob.bin");
+        fs::write(&file_path, b"\x00\x01binary\x00").unwrap();
+        assert!(EditFileSkill::new()
+            .edit_file(file_path.to_str().unwrap(), "binary", "text")
+            .is_err());
+    }
+
+    fn insert(initial: &str, line: usize, content: &str) -> String {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, initial).unwrap();
+        let result = EditFileSkill::new()
+            .insert_at_line(file_path.to_str().unwrap(), line, content)
+            .unwrap();
+        assert!(result.success, "{}", result.message);
+        fs::read_to_string(&file_path).unwrap()
+    }
+
+    fn delete(initial: &str, start: usize, end: usize, join: LineJoin) -> String {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, initial).unwrap();
+        let result = EditFileSkill::new()
+            .with_line_join(join)
+            .delete_lines(file_path.to_str().unwrap(), start, end)
+            .unwrap();
+        assert!(result.success, "{}", result.message);
+        fs::read_to_string(&file_path).unwrap()
+    }
+
+    #[test]
+    fn test_insert_at_file_boundaries() {
+        // Line 0 and line 1 both insert before the first line
+        assert_eq!(insert("a\nb\n", 0, "x"), "x\na\nb\n");
+        assert_eq!(insert("\na\n", 1, "x"), "x\n\na\n");
+        // Before the last line, and appended after it
+        assert_eq!(insert("a\nb\n", 2, "x"), "a\nx\nb\n");
+        assert_eq!(insert("a\nb\n", 3, "x"), "a\nb\nx\n");
+        // A missing trailing newline stays missing, without welding lines
+        assert_eq!(insert("a\nb", 3, "x"), "a\nb\nx");
+        // Blank lines survive, including trailing ones
+        assert_eq!(insert("a\n\nb\n\n", 3, "x"), "a\n\nx\nb\n\n");
+        // Content's own newline ends the line; extra ones are blank lines
+        assert_eq!(insert("a\n", 1, "x\n"), "x\na\n");
+        assert_eq!(insert("a\n", 1, "x\n\n"), "x\n\na\n");
+        // Empty file
+        assert_eq!(insert("", 0, "x"), "x\n");
+        assert_eq!(insert("", 1, "x\ny"), "x\ny\n");
+        // CRLF files stay CRLF
+        assert_eq!(insert("a\r\nb\r\n", 2, "x"), "a\r\nx\r\nb\r\n");
+    }
+
+    #[test]
+    fn test_delete_lines_at_file_boundaries() {
+        let preserve = LineJoin::default();
+        assert_eq!(delete("a\nb\nc\n", 1, 1, preserve), "b\nc\n");
+        assert_eq!(delete("a\nb\nc\n", 3, 3, preserve), "a\nb\n");
+        assert_eq!(delete("a\nb", 2, 2, preserve), "a");
+        assert_eq!(delete("a\n\n\nb\n", 4, 4, preserve), "a\n\n\n");
+        assert_eq!(delete("a\nb\n", 1, 2, preserve), "");
+
+        let always = LineJoin {
+            trailing_newline: TrailingNewline::Always,
+        };
+        assert_eq!(delete("a\nb", 2, 2, always), "a\n");
+        let never = LineJoin {
+            trailing_newline: TrailingNewline::Never,
+        };
+        assert_eq!(delete("a\nb\nc\n", 1, 1, never), "b\nc");
+    }
+
     #[test]
     fn test_parse_diff() {
         let skill = EditFileSkill::new();
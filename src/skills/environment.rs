@@ -0,0 +1,442 @@
+// ============================================
+// WEBRANA CLI - Environment Inspection Skill
+// Toolchain versions, CPU, memory, disk and notable environment variables,
+// probed once per session in parallel
+// ============================================
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::registry::{Skill, SkillDefinition};
+use crate::config::Settings;
+use crate::indexer::{ProjectDetector, ProjectType};
+
+/// All probes together never take longer than this
+pub const PROBE_BUDGET: Duration = Duration::from_millis(2500);
+
+/// Environment variables whose presence (never their value) is reported
+const NOTABLE_VARS: &[&str] = &[
+    "CI",
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "DOCKER_HOST",
+    "VIRTUAL_ENV",
+    "CONDA_DEFAULT_ENV",
+    "NVM_DIR",
+    "JAVA_HOME",
+    "GOPATH",
+    "CARGO_HOME",
+    "RUSTUP_TOOLCHAIN",
+];
+
+/// A tool and the command that prints its version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Probe {
+    pub tool: &'static str,
+    pub args: &'static [&'static str],
+}
+
+const fn probe(tool: &'static str, args: &'static [&'static str]) -> Probe {
+    Probe { tool, args }
+}
+
+const GIT: Probe = probe("git", &["--version"]);
+const MAKE: Probe = probe("make", &["--version"]);
+const DOCKER: Probe = probe("docker", &["--version"]);
+const PODMAN: Probe = probe("podman", &["--version"]);
+
+/// Probes relevant to `project_type`: its toolchain first, then the tools
+/// every project may use. Unknown projects get every toolchain.
+pub fn probes_for(project_type: &ProjectType) -> Vec<Probe> {
+    const RUST: &[Probe] = &[probe("rustc", &["--version"]), probe("cargo", &["--version"])];
+    const NODE: &[Probe] = &[
+        probe("node", &["--version"]),
+        probe("npm", &["--version"]),
+        probe("pnpm", &["--version"]),
+    ];
+    const PYTHON: &[Probe] = &[
+        probe("python3", &["--version"]),
+        probe("pip3", &["--version"]),
+        probe("poetry", &["--version"]),
+    ];
+    const GO: &[Probe] = &[probe("go", &["version"])];
+    const JAVA: &[Probe] = &[probe("java", &["-version"])];
+
+    let toolchains: Vec<&[Probe]> = match project_type {
+        ProjectType::Rust => vec![RUST],
+        ProjectType::JavaScript | ProjectType::TypeScript => vec![NODE],
+        ProjectType::Python => vec![PYTHON],
+        ProjectType::Go => vec![GO],
+        ProjectType::Java | ProjectType::Kotlin => vec![JAVA],
+        ProjectType::Unknown => vec![RUST, NODE, PYTHON, GO, JAVA],
+        _ => Vec::new(),
+    };
+    let mut probes: Vec<Probe> = toolchains.concat();
+    probes.extend([GIT, MAKE, DOCKER, PODMAN]);
+    probes
+}
+
+/// What a version probe found
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ToolStatus {
+    Installed { version: String },
+    NotInstalled,
+    /// Still running when the budget ran out
+    TimedOut,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ToolReport {
+    pub tool: String,
+    #[serde(flatten)]
+    pub status: ToolStatus,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Resources {
+    pub cpus: Option<usize>,
+    pub memory_total_mb: Option<u64>,
+    pub memory_available_mb: Option<u64>,
+    /// Free space on the working directory's filesystem
+    pub disk_free_mb: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvironmentReport {
+    pub os: String,
+    pub arch: String,
+    pub project_type: String,
+    pub tools: Vec<ToolReport>,
+    pub resources: Resources,
+    /// Notable variables that are set; values are never read out
+    pub env_vars_set: Vec<String>,
+}
+
+impl EnvironmentReport {
+    /// One short block for the system prompt
+    pub fn summary(&self) -> String {
+        let tools: Vec<String> = self
+            .tools
+            .iter()
+            .map(|report| match &report.status {
+                ToolStatus::Installed { version } => format!("{} {}", report.tool, version),
+                ToolStatus::NotInstalled => format!("{} not installed", report.tool),
+                ToolStatus::TimedOut => format!("{} unknown (timed out)", report.tool),
+            })
+            .collect();
+        let mut lines = vec![
+            "## Environment".to_string(),
+            format!("- OS: {} {}", self.os, self.arch),
+            format!("- Tools: {}", tools.join(", ")),
+        ];
+
+        let gb = |mb: u64| format!("{:.1} GB", mb as f64 / 1024.0);
+        let resources = &self.resources;
+        let mut machine = Vec::new();
+        if let Some(cpus) = resources.cpus {
+            machine.push(format!("{} CPUs", cpus));
+        }
+        match (resources.memory_total_mb, resources.memory_available_mb) {
+            (Some(total), Some(available)) => machine.push(format!("{} RAM ({} available)", gb(total), gb(available))),
+            (Some(total), None) => machine.push(format!("{} RAM", gb(total))),
+            _ => {}
+        }
+        if let Some(free) = resources.disk_free_mb {
+            machine.push(format!("{} disk free", gb(free)));
+        }
+        if !machine.is_empty() {
+            lines.push(format!("- Machine: {}", machine.join(", ")));
+        }
+        if !self.env_vars_set.is_empty() {
+            lines.push(format!("- Set: {}", self.env_vars_set.join(", ")));
+        }
+        lines.join("\n")
+    }
+}
+
+/// The version in a probe's output: the first token of its first line that
+/// starts with a digit (after an optional `v`, or `go` for `go1.22.1`), or
+/// the whole line when there is none
+pub fn parse_version(output: &str) -> String {
+    let line = output.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+    line.split_whitespace()
+        .map(|token| token.trim_matches(|c| c == '"' || c == ',' || c == ')' || c == '('))
+        .map(|token| {
+            token
+                .strip_prefix("go")
+                .filter(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+                .or_else(|| token.strip_prefix('v'))
+                .unwrap_or(token)
+        })
+        .find(|token| token.starts_with(|c: char| c.is_ascii_digit()))
+        .unwrap_or(line)
+        .to_string()
+}
+
+/// Run every probe in parallel through `run`, which returns the command's
+/// output or None when the tool is missing. Probes still running after
+/// `budget` are reported as timed out.
+pub async fn probe_tools<F, Fut>(probes: &[Probe], budget: Duration, run: F) -> Vec<ToolReport>
+where
+    F: Fn(Probe) -> Fut,
+    Fut: Future<Output = Option<String>>,
+{
+    let runs = probes.iter().map(|probe| {
+        let output = run(*probe);
+        async move {
+            let status = match tokio::time::timeout(budget, output).await {
+                Ok(Some(output)) => ToolStatus::Installed {
+                    version: parse_version(&output),
+                },
+                Ok(None) => ToolStatus::NotInstalled,
+                Err(_) => ToolStatus::TimedOut,
+            };
+            ToolReport {
+                tool: probe.tool.to_string(),
+                status,
+            }
+        }
+    });
+    futures::future::join_all(runs).await
+}
+
+/// Run a probe's version command; stdout, or stderr for tools like `java`
+/// that print their version there
+async fn run_probe(probe: Probe) -> Option<String> {
+    let output = tokio::process::Command::new(probe.tool)
+        .args(probe.args)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !stdout.trim().is_empty() {
+        return Some(stdout.into_owned());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    (!stderr.trim().is_empty()).then(|| stderr.into_owned())
+}
+
+/// `MemTotal` and `MemAvailable` from /proc/meminfo, in MB
+pub fn parse_meminfo(meminfo: &str) -> (Option<u64>, Option<u64>) {
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|rest| rest.split_whitespace().next()?.parse::<u64>().ok())
+            .map(|kb| kb / 1024)
+    };
+    (field("MemTotal"), field("MemAvailable"))
+}
+
+/// The available column of `df -Pk <dir>`, in MB
+pub fn parse_df(output: &str) -> Option<u64> {
+    let row = output.lines().nth(1)?;
+    let available: u64 = row.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available / 1024)
+}
+
+async fn resources(dir: &Path, budget: Duration) -> Resources {
+    let (memory_total_mb, memory_available_mb) = std::fs::read_to_string("/proc/meminfo")
+        .map(|meminfo| parse_meminfo(&meminfo))
+        .unwrap_or_default();
+    let df = tokio::process::Command::new("df")
+        .arg("-Pk")
+        .arg(dir)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let disk_free_mb = match tokio::time::timeout(budget, df).await {
+        Ok(Ok(output)) if output.status.success() => parse_df(&String::from_utf8_lossy(&output.stdout)),
+        _ => None,
+    };
+    Resources {
+        cpus: std::thread::available_parallelism().ok().map(|n| n.get()),
+        memory_total_mb,
+        memory_available_mb,
+        disk_free_mb,
+    }
+}
+
+/// Inspect the environment for the project in `dir`
+pub async fn inspect(dir: &Path) -> EnvironmentReport {
+    let project_type = ProjectDetector::new(dir)
+        .detect()
+        .map(|info| info.project_type)
+        .unwrap_or(ProjectType::Unknown);
+    let probes = probes_for(&project_type);
+    let (tools, resources) = tokio::join!(
+        probe_tools(&probes, PROBE_BUDGET, run_probe),
+        resources(dir, PROBE_BUDGET)
+    );
+    EnvironmentReport {
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        project_type: project_type.as_str().to_string(),
+        tools,
+        resources,
+        env_vars_set: NOTABLE_VARS
+            .iter()
+            .filter(|name| std::env::var_os(name).is_some_and(|value| !value.is_empty()))
+            .map(|name| name.to_string())
+            .collect(),
+    }
+}
+
+/// Report for `dir`, inspected once per session
+static CACHE: Mutex<Option<(PathBuf, EnvironmentReport)>> = Mutex::new(None);
+
+pub async fn cached(dir: &Path) -> EnvironmentReport {
+    if let Some((cached_dir, report)) = CACHE.lock().unwrap().as_ref() {
+        if cached_dir == dir {
+            return report.clone();
+        }
+    }
+    let report = inspect(dir).await;
+    *CACHE.lock().unwrap() = Some((dir.to_path_buf(), report.clone()));
+    report
+}
+
+pub struct InspectEnvironmentSkill;
+
+#[async_trait]
+impl Skill for InspectEnvironmentSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: "inspect_environment".to_string(),
+            description: "Report installed toolchain versions (for the detected project type plus git, \
+                          make, docker, podman), CPU count, memory, free disk space and which notable \
+                          environment variables (CI, DOCKER_HOST, VIRTUAL_ENV, ...) are set, as JSON. \
+                          Cached for the session; use it instead of running version commands."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {}
+            }),
+            requires_confirmation: false,
+            parallel_safe: true,
+        }
+    }
+
+    async fn execute(&self, _args: &Value, _settings: &Settings) -> Result<String> {
+        let report = cached(&std::env::current_dir()?).await;
+        Ok(serde_json::to_string_pretty(&report)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn fake(probe: Probe) -> Option<String> {
+        match probe.tool {
+            "rustc" => Some("rustc 1.80.1 (3f5fd8dd4 2024-08-06)\n".to_string()),
+            "cargo" => Some("cargo 1.80.1 (376290515 2024-07-16)\n".to_string()),
+            "git" => Some("git version 2.43.0\n".to_string()),
+            "docker" => Some("Docker version 27.1.1, build 6312585\n".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("rustc 1.80.1 (3f5fd8dd4 2024-08-06)"), "1.80.1");
+        assert_eq!(parse_version("v20.11.0\n"), "20.11.0");
+        assert_eq!(parse_version("go version go1.22.1 linux/amd64"), "1.22.1");
+        assert_eq!(parse_version("openjdk version \"21.0.2\" 2024-01-16\nOpenJDK Runtime"), "21.0.2");
+        assert_eq!(parse_version("Poetry (version 1.8.2)"), "1.8.2");
+        assert_eq!(parse_version("GNU Make 4.3"), "4.3");
+        assert_eq!(parse_version("some tool, no version"), "some tool, no version");
+    }
+
+    #[test]
+    fn test_probes_follow_project_type() {
+        let tools = |project_type: ProjectType| -> Vec<&str> {
+            probes_for(&project_type).iter().map(|probe| probe.tool).collect()
+        };
+        assert_eq!(tools(ProjectType::Rust), ["rustc", "cargo", "git", "make", "docker", "podman"]);
+        assert_eq!(tools(ProjectType::TypeScript)[..3], ["node", "npm", "pnpm"]);
+        assert!(!tools(ProjectType::Python).contains(&"cargo"));
+        assert!(tools(ProjectType::Unknown).contains(&"java"));
+        assert_eq!(tools(ProjectType::Ruby), ["git", "make", "docker", "podman"]);
+    }
+
+    #[tokio::test]
+    async fn test_report_and_summary_from_fake_outputs() {
+        let probes = probes_for(&ProjectType::Rust);
+        let tools = probe_tools(&probes, PROBE_BUDGET, |probe| async move { fake(probe) }).await;
+        let report = EnvironmentReport {
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            project_type: "rust".to_string(),
+            tools,
+            resources: Resources {
+                cpus: Some(8),
+                memory_total_mb: Some(16_384),
+                memory_available_mb: Some(9_728),
+                disk_free_mb: Some(122_880),
+            },
+            env_vars_set: vec!["CI".to_string()],
+        };
+
+        assert_eq!(
+            report.summary(),
+            "## Environment\n\
+             - OS: linux x86_64\n\
+             - Tools: rustc 1.80.1, cargo 1.80.1, git 2.43.0, make not installed, docker 27.1.1, podman not installed\n\
+             - Machine: 8 CPUs, 16.0 GB RAM (9.5 GB available), 120.0 GB disk free\n\
+             - Set: CI"
+        );
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["tools"][0], json!({"tool": "rustc", "status": "installed", "version": "1.80.1"}));
+        assert_eq!(json["tools"][3], json!({"tool": "make", "status": "not_installed"}));
+        assert_eq!(json["resources"]["cpus"], 8);
+    }
+
+    #[tokio::test]
+    async fn test_slow_probe_times_out_within_budget() {
+        let probes = [GIT, DOCKER];
+        let started = Instant::now();
+        let tools = probe_tools(&probes, Duration::from_millis(100), |probe| async move {
+            if probe.tool == "docker" {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            }
+            fake(probe)
+        })
+        .await;
+
+        assert!(started.elapsed() < Duration::from_secs(2), "{:?}", started.elapsed());
+        assert_eq!(tools[0].status, ToolStatus::Installed { version: "2.43.0".to_string() });
+        assert_eq!(tools[1].status, ToolStatus::TimedOut);
+        assert!(EnvironmentReport {
+            os: "linux".to_string(),
+            arch: "aarch64".to_string(),
+            project_type: "unknown".to_string(),
+            tools,
+            resources: Resources::default(),
+            env_vars_set: Vec::new(),
+        }
+        .summary()
+        .contains("docker unknown (timed out)"));
+    }
+
+    #[test]
+    fn test_parse_resources() {
+        let meminfo = "MemTotal:       16384000 kB\nMemFree:         1024000 kB\nMemAvailable:    8192000 kB\n";
+        assert_eq!(parse_meminfo(meminfo), (Some(16_000), Some(8_000)));
+        assert_eq!(parse_meminfo(""), (None, None));
+        let df = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                  /dev/nvme0n1p2   488245288 301234567 162345678      65% /\n";
+        assert_eq!(parse_df(df), Some(158_540));
+        assert_eq!(parse_df("garbage"), None);
+    }
+}
@@ -0,0 +1,437 @@
+// ============================================
+// WEBRANA CLI - Permission Escalation
+// Lets a restricted crew ask the user for one narrow exception
+// ============================================
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::permissions::{SkillPermissions, FILE_WRITE_SKILLS};
+use super::registry::{Skill, SkillDefinition};
+use crate::config::Settings;
+use crate::core::audit::{AuditEvent, AuditEventType, AuditSeverity, AUDIT};
+use crate::core::resolve_existing;
+
+pub const REQUEST_PERMISSION_SKILL: &str = "request_permission";
+
+/// Asks the user a yes/no question
+pub type Confirmer = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Session-only exception to the crew's permissions
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionGrant {
+    pub crew: String,
+    pub skill: String,
+    /// Every path the call writes must be under this; None for skills
+    /// that take no path
+    pub path_prefix: Option<PathBuf>,
+    pub justification: String,
+}
+
+impl PermissionGrant {
+    pub fn covers(&self, skill: &str, paths: &[String]) -> bool {
+        if self.skill != skill {
+            return false;
+        }
+        match &self.path_prefix {
+            None => true,
+            Some(prefix) => {
+                !paths.is_empty() && paths.iter().all(|p| resolve(Path::new(p)).starts_with(prefix))
+            }
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let scope = match &self.path_prefix {
+            Some(prefix) => format!("{} under {}", self.skill, prefix.display()),
+            None => self.skill.clone(),
+        };
+        format!("{} (crew {}): {}", scope, self.crew, self.justification)
+    }
+}
+
+struct CrewScope {
+    name: String,
+    permissions: SkillPermissions,
+}
+
+/// The crew's permissions plus the exceptions the user granted this session
+#[derive(Default)]
+pub struct Escalation {
+    crew: Mutex<Option<CrewScope>>,
+    /// None in auto or non-interactive mode: every request is denied
+    confirmer: Mutex<Option<Confirmer>>,
+    grants: Mutex<Vec<PermissionGrant>>,
+}
+
+impl Escalation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_crew(&self, name: &str, permissions: SkillPermissions) {
+        *self.crew.lock().unwrap() = Some(CrewScope {
+            name: name.to_string(),
+            permissions,
+        });
+    }
+
+    pub fn set_confirmer(&self, confirmer: Option<Confirmer>) {
+        *self.confirmer.lock().unwrap() = confirmer;
+    }
+
+    /// Who answers questions this session; None in auto or non-interactive mode
+    pub fn confirmer(&self) -> Option<Confirmer> {
+        self.confirmer.lock().unwrap().clone()
+    }
+
+    pub fn grants(&self) -> Vec<PermissionGrant> {
+        self.grants.lock().unwrap().clone()
+    }
+
+    /// Whether the model should see `skill` at all
+    pub fn lists(&self, skill: &str) -> bool {
+        match &*self.crew.lock().unwrap() {
+            None => skill != REQUEST_PERMISSION_SKILL,
+            Some(crew) => {
+                skill == REQUEST_PERMISSION_SKILL
+                    || crew.permissions.is_allowed(skill)
+                    || self.grants.lock().unwrap().iter().any(|g| g.skill == skill)
+            }
+        }
+    }
+
+    /// Check a call writing `paths` against the crew's permissions and grants
+    pub fn check(&self, skill: &str, paths: &[String]) -> Result<()> {
+        let crew = self.crew.lock().unwrap();
+        let Some(crew) = &*crew else {
+            if skill == REQUEST_PERMISSION_SKILL {
+                anyhow::bail!("No crew restrictions are active; nothing to request");
+            }
+            return Ok(());
+        };
+        if skill == REQUEST_PERMISSION_SKILL || crew.permissions.is_allowed(skill) {
+            return Ok(());
+        }
+        if self.grants.lock().unwrap().iter().any(|g| g.covers(skill, paths)) {
+            return Ok(());
+        }
+
+        if self.confirmer.lock().unwrap().is_some() {
+            anyhow::bail!(
+                "Skill '{}' is blocked by the permissions of crew '{}'. If it is essential, call \
+                 {} with capability '{}', the path you need and a justification; the user \
+                 decides. Don't retry without approval.",
+                skill,
+                crew.name,
+                REQUEST_PERMISSION_SKILL,
+                skill
+            );
+        }
+        anyhow::bail!(
+            "Skill '{}' is not permitted for crew '{}' in this session",
+            skill,
+            crew.name
+        );
+    }
+
+    /// Ask the user for a grant; returns the message for the model
+    pub fn request(&self, skill: &str, path: Option<&str>, justification: &str) -> Result<String> {
+        let crew = match &*self.crew.lock().unwrap() {
+            Some(crew) if crew.permissions.is_allowed(skill) => {
+                return Ok(format!("'{}' is already permitted; no request needed", skill));
+            }
+            Some(crew) => crew.name.clone(),
+            None => anyhow::bail!("No crew restrictions are active; nothing to request"),
+        };
+        if justification.trim().is_empty() {
+            anyhow::bail!("A justification is required");
+        }
+        // Only write skills are checked against paths, so a path on any
+        // other grant would never match
+        let writes = FILE_WRITE_SKILLS.contains(&skill);
+        let path_prefix = match path.filter(|p| !p.trim().is_empty()) {
+            Some(p) if writes => Some(resolve(Path::new(p))),
+            Some(_) => anyhow::bail!("'{}' writes no files: request it without a path", skill),
+            None if writes => anyhow::bail!("'{}' writes files: name the path you need", skill),
+            None => None,
+        };
+        let grant = PermissionGrant {
+            crew,
+            skill: skill.to_string(),
+            path_prefix,
+            justification: justification.trim().to_string(),
+        };
+        let details = json!({
+            "crew": grant.crew,
+            "skill": grant.skill,
+            "path": grant.path_prefix.as_ref().map(|p| p.display().to_string()),
+            "justification": grant.justification,
+        });
+        audit(
+            AuditEventType::PermissionRequested,
+            AuditSeverity::Info,
+            format!("Permission requested: {}", grant.describe()),
+            &details,
+        );
+
+        let confirmer = self.confirmer.lock().unwrap().clone();
+        let Some(confirmer) = confirmer else {
+            audit(
+                AuditEventType::PermissionDenied,
+                AuditSeverity::Warning,
+                format!("Permission auto-denied (non-interactive): {}", grant.describe()),
+                &details,
+            );
+            return Ok(format!(
+                "Denied: permission requests are not available in auto or non-interactive \
+                 mode. Continue without {}.",
+                skill
+            ));
+        };
+
+        let scope = match &grant.path_prefix {
+            Some(prefix) => format!("{} for {}", skill, prefix.display()),
+            None => skill.to_string(),
+        };
+        let question = format!(
+            "Crew '{}' asks for {} (this session only)\n   Reason: {}\n   Allow?",
+            grant.crew, scope, grant.justification
+        );
+        if !confirmer(&question) {
+            audit(
+                AuditEventType::PermissionDenied,
+                AuditSeverity::Info,
+                format!("Permission denied by user: {}", grant.describe()),
+                &details,
+            );
+            return Ok(format!("Denied by the user. Continue without {}.", skill));
+        }
+
+        audit(
+            AuditEventType::PermissionGranted,
+            AuditSeverity::Warning,
+            format!("Permission granted: {}", grant.describe()),
+            &details,
+        );
+        self.grants.lock().unwrap().push(grant);
+        Ok(format!(
+            "Granted: {} for the rest of this session. Retry the call.",
+            scope
+        ))
+    }
+}
+
+fn audit(kind: AuditEventType, severity: AuditSeverity, message: String, details: &Value) {
+    AUDIT.log(AuditEvent::new(kind, severity, message).with_details(details.clone()));
+}
+
+/// Absolute form of `path` with symlinks followed for the part that
+/// exists (it may not exist yet), so a link inside a granted directory
+/// can't extend the grant outside it
+fn resolve(path: &Path) -> PathBuf {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    resolve_existing(&path)
+}
+
+pub struct RequestPermissionSkill {
+    escalation: Arc<Escalation>,
+}
+
+impl RequestPermissionSkill {
+    pub fn new(escalation: Arc<Escalation>) -> Self {
+        Self { escalation }
+    }
+}
+
+#[async_trait]
+impl Skill for RequestPermissionSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: REQUEST_PERMISSION_SKILL.to_string(),
+            description: "Ask the user for a skill your crew's permissions block, e.g. write_file \
+                          for one report file. Only for genuine needs; the grant is limited to \
+                          that skill and path for this session."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "capability": {
+                        "type": "string",
+                        "description": "The blocked skill, e.g. write_file"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "File or directory the skill needs to write (required for file writes, not allowed for other skills)"
+                    },
+                    "justification": {
+                        "type": "string",
+                        "description": "Why the task needs it, shown to the user"
+                    }
+                },
+                "required": ["capability", "justification"]
+            }),
+            // The skill asks the user itself
+            requires_confirmation: false,
+            parallel_safe: false,
+        }
+    }
+
+    async fn execute(&self, args: &Value, _settings: &Settings) -> Result<String> {
+        let capability = args["capability"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing capability"))?;
+        let justification = args["justification"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing justification"))?;
+        self.escalation
+            .request(capability, args["path"].as_str(), justification)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crew::Crew;
+
+    fn reviewer(confirmer: Option<Confirmer>) -> Escalation {
+        let mut crew = Crew::new("reviewer", "Code Reviewer", "Reviews code", "Review.");
+        crew.permissions.file_write = false;
+        let escalation = Escalation::new();
+        escalation.set_crew(&crew.name, SkillPermissions::from_crew(&crew));
+        escalation.set_confirmer(confirmer);
+        escalation
+    }
+
+    fn paths(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_grant_is_scoped_to_skill_and_path() {
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let log = asked.clone();
+        let escalation = reviewer(Some(Arc::new(move |q: &str| {
+            log.lock().unwrap().push(q.to_string());
+            true
+        })));
+
+        let err = escalation.check("write_file", &paths(&["reports/review.md"])).unwrap_err();
+        assert!(err.to_string().contains("call request_permission with capability 'write_file'"));
+        assert!(!escalation.lists("write_file"));
+        assert!(escalation.request("write_file", None, "save report").is_err());
+
+        let reply = escalation
+            .request("write_file", Some("reports/review.md"), "save the review report")
+            .unwrap();
+        assert!(reply.starts_with("Granted"));
+        let question = &asked.lock().unwrap()[0];
+        assert!(question.contains("Crew 'Code Reviewer' asks for write_file for"));
+        assert!(question.contains("Reason: save the review report"));
+
+        assert!(escalation.check("write_file", &paths(&["reports/review.md"])).is_ok());
+        assert!(escalation.check("write_file", &paths(&["./reports/review.md"])).is_ok());
+        assert!(escalation.lists("write_file"));
+        // Sibling paths, escapes and other write skills stay blocked
+        assert!(escalation.check("write_file", &paths(&["reports/other.md"])).is_err());
+        assert!(escalation.check("write_file", &paths(&["reports/review.md.bak"])).is_err());
+        assert!(escalation
+            .check("write_file", &paths(&["reports/review.md/../../Cargo.toml"]))
+            .is_err());
+        assert!(escalation.check("edit_file", &paths(&["reports/review.md"])).is_err());
+        assert!(escalation.check("read_file", &[]).is_ok());
+    }
+
+    #[test]
+    fn test_auto_mode_denies_and_audits() {
+        let escalation = reviewer(None);
+        let err = escalation.check("write_file", &paths(&["out.md"])).unwrap_err();
+        assert!(!err.to_string().contains("request_permission"));
+
+        let reply = escalation
+            .request("write_file", Some("out.md"), "auto-mode audit trail check")
+            .unwrap();
+        assert!(reply.starts_with("Denied"));
+        assert!(escalation.grants().is_empty());
+        assert!(escalation.check("write_file", &paths(&["out.md"])).is_err());
+
+        let requested = AUDIT.events_by_type(AuditEventType::PermissionRequested);
+        assert!(requested
+            .iter()
+            .any(|e| e.message.contains("auto-mode audit trail check")));
+        let denied = AUDIT.events_by_type(AuditEventType::PermissionDenied);
+        let event = denied
+            .iter()
+            .find(|e| e.message.contains("auto-mode audit trail check"))
+            .unwrap();
+        assert!(event.message.starts_with("Permission auto-denied"));
+        assert_eq!(event.details.as_ref().unwrap()["crew"], "Code Reviewer");
+    }
+
+    #[test]
+    fn test_user_decision_is_audited() {
+        let escalation = reviewer(Some(Arc::new(|_: &str| false)));
+        let reply = escalation
+            .request("git_apply", Some("src"), "apply the suggested fix")
+            .unwrap();
+        assert!(reply.starts_with("Denied by the user"));
+        assert!(AUDIT
+            .events_by_type(AuditEventType::PermissionDenied)
+            .iter()
+            .any(|e| e.message.starts_with("Permission denied by user: git_apply under ")
+                && e.message.ends_with("(crew Code Reviewer): apply the suggested fix")));
+
+        let escalation = reviewer(Some(Arc::new(|_: &str| true)));
+        escalation
+            .request("git_apply", Some("src"), "apply the approved fix")
+            .unwrap();
+        assert!(AUDIT
+            .events_by_type(AuditEventType::PermissionGranted)
+            .iter()
+            .any(|e| e.message.contains("apply the approved fix")));
+        assert!(escalation.request("read_file", None, "x").unwrap().contains("already permitted"));
+    }
+
+    #[test]
+    fn test_path_only_on_write_grants() {
+        let mut crew = Crew::new("reviewer", "Code Reviewer", "Reviews code", "Review.");
+        crew.permissions.denied_skills.insert("execute_command".to_string());
+        let escalation = Escalation::new();
+        escalation.set_crew(&crew.name, SkillPermissions::from_crew(&crew));
+        escalation.set_confirmer(Some(Arc::new(|_: &str| true)));
+
+        // A path on a grant that is never checked against paths is refused
+        let err = escalation
+            .request("execute_command", Some("src"), "run the linter")
+            .unwrap_err();
+        assert!(err.to_string().contains("writes no files"), "{}", err);
+        escalation.request("execute_command", None, "run the linter").unwrap();
+        assert!(escalation.check("execute_command", &[]).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_does_not_extend_a_path_grant() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let outside = tempfile::TempDir::new().unwrap();
+        let reports = tmp.path().join("reports");
+        std::fs::create_dir(&reports).unwrap();
+        std::os::unix::fs::symlink(outside.path(), reports.join("link")).unwrap();
+
+        let escalation = reviewer(Some(Arc::new(|_: &str| true)));
+        escalation
+            .request("write_file", reports.to_str(), "save the review report")
+            .unwrap();
+        let inside = reports.join("review.md").to_string_lossy().to_string();
+        let through_link = reports.join("link").join("x.md").to_string_lossy().to_string();
+        assert!(escalation.check("write_file", &[inside]).is_ok());
+        assert!(escalation.check("write_file", &[through_link]).is_err());
+    }
+}
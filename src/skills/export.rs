@@ -0,0 +1,115 @@
+//! `webrana skills export` - every tool schema as a provider's tools array,
+//! for reuse in other agents
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::SkillRegistry;
+use crate::llm::ToolDefinition;
+use crate::mcp::McpTool;
+use crate::plugins::InstalledPlugin;
+
+/// Formats `skills export --format` accepts
+pub const EXPORT_FORMATS: &[&str] = &["openai", "anthropic"];
+
+/// Built-in skills, then tools of enabled plugins and connected MCP
+/// servers, sorted by name. A name already taken keeps its first owner.
+pub fn collect(
+    registry: &SkillRegistry,
+    plugins: &[&InstalledPlugin],
+    mcp_tools: &[(String, McpTool)],
+) -> Vec<ToolDefinition> {
+    let mut tools: BTreeMap<String, ToolDefinition> = BTreeMap::new();
+    let mut add = |tool: ToolDefinition, owner: &str| {
+        if tools.contains_key(&tool.name) {
+            tracing::warn!("Skipping {}'s tool '{}': the name is already taken", owner, tool.name);
+            return;
+        }
+        tools.insert(tool.name.clone(), tool);
+    };
+
+    for skill in registry.list() {
+        add(
+            ToolDefinition {
+                name: skill.name,
+                description: skill.description,
+                input_schema: skill.parameters,
+            },
+            "webrana",
+        );
+    }
+    for plugin in plugins {
+        for skill in &plugin.manifest.skills {
+            add(
+                ToolDefinition {
+                    name: skill.name.clone(),
+                    description: skill.description.clone(),
+                    input_schema: skill.input_schema.clone(),
+                },
+                &format!("plugin {}", plugin.manifest.id),
+            );
+        }
+    }
+    for (server, tool) in mcp_tools {
+        add(
+            ToolDefinition {
+                name: tool.name.clone(),
+                description: tool.description.clone().unwrap_or_default(),
+                input_schema: tool
+                    .input_schema
+                    .clone()
+                    .unwrap_or_else(|| serde_json::json!({"type": "object", "properties": {}})),
+            },
+            &format!("MCP server {}", server),
+        );
+    }
+    tools.into_values().collect()
+}
+
+/// `tools` as the `tools` array of an OpenAI or Anthropic request
+pub fn render(tools: &[ToolDefinition], format: &str) -> Result<Value> {
+    let entries = match format {
+        "openai" => tools.iter().map(ToolDefinition::to_openai).collect(),
+        "anthropic" => tools.iter().map(ToolDefinition::to_anthropic).collect(),
+        other => bail!("Unknown format '{}'. Use: {}", other, EXPORT_FORMATS.join(", ")),
+    };
+    Ok(Value::Array(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_export_covers_every_skill() {
+        let registry = SkillRegistry::new();
+        let mcp = vec![(
+            "docs".to_string(),
+            McpTool {
+                name: "search_docs".to_string(),
+                description: None,
+                input_schema: None,
+            },
+        )];
+        let tools = collect(&registry, &[], &mcp);
+        let exported = render(&tools, "openai").unwrap();
+        let entries = exported.as_array().unwrap();
+        assert_eq!(entries.len(), registry.list().len() + 1);
+
+        for skill in registry.list() {
+            let entry = entries
+                .iter()
+                .find(|e| e["function"]["name"] == skill.name)
+                .unwrap_or_else(|| panic!("{} missing", skill.name));
+            assert_eq!(entry["type"], "function");
+            assert_eq!(entry["function"]["parameters"], skill.parameters);
+        }
+        let mcp_entry = entries.iter().find(|e| e["function"]["name"] == "search_docs").unwrap();
+        assert_eq!(mcp_entry["function"]["parameters"]["type"], "object");
+
+        let anthropic = render(&tools, "anthropic").unwrap();
+        assert!(anthropic[0].get("input_schema").is_some() && anthropic[0].get("type").is_none());
+        assert!(render(&tools, "gemini").is_err());
+    }
+}
@@ -12,6 +12,7 @@ use std::path::Path;
 use super::registry::{Skill, SkillDefinition};
 use crate::config::Settings;
 use crate::core::{InputSanitizer, SecurityConfig};
+use crate::indexer::encoding::{self, TextEncoding};
 
 pub struct ReadFileSkill {
     sanitizer: InputSanitizer,
@@ -54,6 +55,7 @@ impl Skill for ReadFileSkill {
                 "required": ["path"]
             }),
             requires_confirmation: false,
+            parallel_safe: true,
         }
     }
 
@@ -65,12 +67,22 @@ impl Skill for ReadFileSkill {
             anyhow::bail!("🛡️ SECURITY: Access denied to sensitive file: {}", path);
         }
 
-        let content = fs::read_to_string(path).context(format!("Failed to read file: {}", path))?;
+        let decoded = encoding::read_text(Path::new(path))
+            .context(format!("Failed to read file: {}", path))?;
 
         // SENTINEL Security: Sanitize output to remove any secrets
-        let sanitized = self.sanitizer.sanitize_output(&content);
+        let sanitized = self.sanitizer.sanitize_output(&decoded.text);
 
-        Ok(sanitized)
+        // Non-UTF-8 files are decoded for the model; say how they're stored
+        if decoded.encoding.is_utf8() {
+            Ok(sanitized)
+        } else {
+            Ok(format!(
+                "[encoding: {}; edits are saved back in this encoding]\n{}",
+                decoded.encoding.label(),
+                sanitized
+            ))
+        }
     }
 }
 
@@ -114,11 +126,17 @@ impl Skill for WriteFileSkill {
                     "content": {
                         "type": "string",
                         "description": "Content to write to the file"
-                    }
+                    },
+                    "convert_to_utf8": {
+                        "type": "boolean",
+                        "description": "Save as UTF-8 when overwriting a file stored in another encoding (default: false)"
+                    },
+                    "expected_hash": super::registry::expected_hash_param()
                 },
                 "required": ["path", "content"]
             }),
             requires_confirmation: true,
+            parallel_safe: false,
         }
     }
 
@@ -127,6 +145,7 @@ impl Skill for WriteFileSkill {
         let content = args["content"]
             .as_str()
             .context("Missing 'content' argument")?;
+        let convert_to_utf8 = args["convert_to_utf8"].as_bool().unwrap_or(false);
 
         // SENTINEL Security Layer 1: Check if path is blocked by settings
         for blocked in &settings.safety.blocked_paths {
@@ -148,7 +167,12 @@ impl Skill for WriteFileSkill {
                     fs::create_dir_all(parent)?;
                 }
 
-                fs::write(&validated_path, content)
+                // Overwrites keep the existing file's encoding (and BOM)
+                let target = match encoding::read_text(&validated_path) {
+                    Ok(existing) if !convert_to_utf8 => existing.encoding,
+                    _ => TextEncoding::default(),
+                };
+                encoding::write_text(&validated_path, content, target)
                     .context(format!("Failed to write file: {}", path))?;
 
                 tracing::info!("📝 File written: {} ({} bytes)", path, content.len());
@@ -188,6 +212,7 @@ impl Skill for ListFilesSkill {
                 "required": ["path"]
             }),
             requires_confirmation: false,
+            parallel_safe: true,
         }
     }
 
@@ -244,6 +269,7 @@ impl Skill for SearchFilesSkill {
                 "required": ["path", "pattern"]
             }),
             requires_confirmation: false,
+            parallel_safe: true,
         }
     }
 
@@ -273,7 +299,7 @@ fn search_in_dir(path: &Path, pattern: &str, results: &mut Vec<String>) -> Resul
             if entry_path.is_dir() {
                 search_in_dir(&entry_path, pattern, results)?;
             } else if entry_path.is_file() {
-                if let Ok(content) = fs::read_to_string(&entry_path) {
+                if let Ok(content) = encoding::read_to_string(&entry_path) {
                     for (line_num, line) in content.lines().enumerate() {
                         if line.contains(pattern) {
                             results.push(format!(
@@ -1,20 +1,26 @@
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use serde_json::{json, Value};
-use std::process::Command;
+use std::path::{Component, Path};
+use tokio::process::Command;
 
 use super::registry::{Skill, SkillDefinition};
 use crate::config::Settings;
+use crate::core::binaries;
+use crate::llm::LlmClient;
+use crate::memory::ContextBudget;
 
-fn run_git_command(args: &[&str], cwd: Option<&str>) -> Result<String> {
-    let mut cmd = Command::new("git");
-    cmd.args(args);
+/// Runs git; the process is killed if the returned future is dropped
+/// (e.g. by a skill timeout)
+pub async fn run_git_command(args: &[&str], cwd: Option<&str>) -> Result<String> {
+    let mut cmd = Command::new(binaries::git()?);
+    cmd.args(args).kill_on_drop(true);
 
     if let Some(dir) = cwd {
         cmd.current_dir(dir);
     }
 
-    let output = cmd.output().context("Failed to execute git command")?;
+    let output = cmd.output().await.context("Failed to execute git command")?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     let stderr = String::from_utf8_lossy(&output.stderr);
@@ -49,12 +55,13 @@ impl Skill for GitStatusSkill {
                 "required": []
             }),
             requires_confirmation: false,
+            parallel_safe: true,
         }
     }
 
     async fn execute(&self, args: &Value, _settings: &Settings) -> Result<String> {
         let path = args["path"].as_str();
-        run_git_command(&["status"], path)
+        run_git_command(&["status"], path).await
     }
 }
 
@@ -85,6 +92,7 @@ impl Skill for GitDiffSkill {
                 "required": []
             }),
             requires_confirmation: false,
+            parallel_safe: true,
         }
     }
 
@@ -102,7 +110,7 @@ impl Skill for GitDiffSkill {
             git_args.push(f);
         }
 
-        let result = run_git_command(&git_args, path)?;
+        let result = run_git_command(&git_args, path).await?;
         if result.trim().is_empty() {
             Ok("No changes".to_string())
         } else {
@@ -138,6 +146,7 @@ impl Skill for GitLogSkill {
                 "required": []
             }),
             requires_confirmation: false,
+            parallel_safe: true,
         }
     }
 
@@ -153,7 +162,7 @@ impl Skill for GitLogSkill {
             git_args.push("--oneline");
         }
 
-        run_git_command(&git_args, path)
+        run_git_command(&git_args, path).await
     }
 }
 
@@ -180,6 +189,7 @@ impl Skill for GitCommitSkill {
                 "required": ["message"]
             }),
             requires_confirmation: true,
+            parallel_safe: false,
         }
     }
 
@@ -189,7 +199,7 @@ impl Skill for GitCommitSkill {
             .as_str()
             .context("Commit message is required")?;
 
-        run_git_command(&["commit", "-m", message], path)
+        run_git_command(&["commit", "-m", message], path).await
     }
 }
 
@@ -216,6 +226,7 @@ impl Skill for GitAddSkill {
                 "required": ["files"]
             }),
             requires_confirmation: true,
+            parallel_safe: false,
         }
     }
 
@@ -225,7 +236,7 @@ impl Skill for GitAddSkill {
             .as_str()
             .context("Files argument is required")?;
 
-        run_git_command(&["add", files], path)?;
+        run_git_command(&["add", files], path).await?;
         Ok(format!("Added: {}", files))
     }
 }
@@ -258,6 +269,7 @@ impl Skill for GitBranchSkill {
                 "required": []
             }),
             requires_confirmation: false,
+            parallel_safe: false,
         }
     }
 
@@ -267,20 +279,20 @@ impl Skill for GitBranchSkill {
         let name = args["name"].as_str();
 
         match action {
-            "list" => run_git_command(&["branch", "-a"], path),
+            "list" => run_git_command(&["branch", "-a"], path).await,
             "create" => {
                 let name = name.context("Branch name is required for create")?;
-                run_git_command(&["branch", name], path)?;
+                run_git_command(&["branch", name], path).await?;
                 Ok(format!("Created branch: {}", name))
             }
             "switch" => {
                 let name = name.context("Branch name is required for switch")?;
-                run_git_command(&["checkout", name], path)?;
+                run_git_command(&["checkout", name], path).await?;
                 Ok(format!("Switched to branch: {}", name))
             }
             "delete" => {
                 let name = name.context("Branch name is required for delete")?;
-                run_git_command(&["branch", "-d", name], path)?;
+                run_git_command(&["branch", "-d", name], path).await?;
                 Ok(format!("Deleted branch: {}", name))
             }
             _ => anyhow::bail!("Unknown action: {}", action),
@@ -315,6 +327,7 @@ impl Skill for GitCheckoutSkill {
                 "required": ["target"]
             }),
             requires_confirmation: false,
+            parallel_safe: false,
         }
     }
 
@@ -324,9 +337,512 @@ impl Skill for GitCheckoutSkill {
         let create_branch = args["create_branch"].as_bool().unwrap_or(false);
 
         if create_branch {
-            run_git_command(&["checkout", "-b", target], path)
+            run_git_command(&["checkout", "-b", target], path).await
         } else {
-            run_git_command(&["checkout", target], path)
+            run_git_command(&["checkout", target], path).await
         }
     }
 }
+
+/// Files a patch writes to. `diff --git` sections count both sides and
+/// their `rename`/`copy to` lines, so renames, mode changes and binary
+/// patches are covered; copy sources are only read. Plain unified diffs
+/// use their `+++ b/...` headers (or `--- a/...` for deletions).
+pub fn patch_paths(patch: &str) -> Vec<String> {
+    let strip = |header: &str| -> Option<String> {
+        let path = header.split('\t').next()?.trim();
+        if path == "/dev/null" {
+            return None;
+        }
+        let path = path
+            .strip_prefix("a/")
+            .or_else(|| path.strip_prefix("b/"))
+            .unwrap_or(path);
+        Some(path.to_string())
+    };
+    fn add(paths: &mut Vec<String>, path: Option<String>) {
+        if let Some(path) = path.filter(|p| !p.is_empty() && !paths.contains(p)) {
+            paths.push(path);
+        }
+    }
+    /// Old and new path of a `diff --git` section, and whether it is a copy
+    type Section = (Option<String>, Option<String>, bool);
+    fn finish(paths: &mut Vec<String>, section: Option<Section>) {
+        if let Some((old, new, copied)) = section {
+            add(paths, new);
+            if !copied {
+                add(paths, old);
+            }
+        }
+    }
+
+    let mut paths: Vec<String> = Vec::new();
+    let mut section: Option<Section> = None;
+    let mut old_path = None;
+    for line in patch.lines() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            finish(&mut paths, section.take());
+            let (old, new) = git_header_paths(rest);
+            section = Some((old, new, false));
+        } else if let Some(header) = line.strip_prefix("--- ") {
+            old_path = strip(&unquote(header));
+        } else if let Some(header) = line.strip_prefix("+++ ") {
+            add(&mut paths, strip(&unquote(header)).or_else(|| old_path.take()));
+        } else if let Some((old, new, copied)) = section.as_mut() {
+            if let Some(from) = line.strip_prefix("rename from ") {
+                *old = Some(unquote(from));
+            } else if let Some(to) = line.strip_prefix("rename to ") {
+                *new = Some(unquote(to));
+            } else if let Some(from) = line.strip_prefix("copy from ") {
+                *old = Some(unquote(from));
+                *copied = true;
+            } else if let Some(to) = line.strip_prefix("copy to ") {
+                *new = Some(unquote(to));
+            }
+        }
+    }
+    finish(&mut paths, section);
+    paths
+}
+
+/// `a/<old> b/<new>` of a `diff --git` line, either side possibly quoted
+fn git_header_paths(rest: &str) -> (Option<String>, Option<String>) {
+    let side = |path: &str, prefix: &str| {
+        let path = unquote(path);
+        Some(path.strip_prefix(prefix).map(str::to_string).unwrap_or(path))
+    };
+    if rest.starts_with('"') {
+        let end = quoted_len(rest);
+        return (side(&rest[..end], "a/"), side(rest[end..].trim_start(), "b/"));
+    }
+    // Unquoted names may contain " b/"; the split where both sides name the
+    // same file wins, as git itself assumes
+    let splits: Vec<usize> = rest.match_indices(" b/").map(|(i, _)| i).collect();
+    let at = splits
+        .iter()
+        .copied()
+        .find(|&i| rest[..i].strip_prefix("a/") == Some(&rest[i + 3..]))
+        .or(splits.first().copied())
+        .or_else(|| rest.find(" \""));
+    match at {
+        Some(i) => (side(&rest[..i], "a/"), side(rest[i..].trim_start(), "b/")),
+        None => (None, None),
+    }
+}
+
+/// Byte length of the leading `"..."` of `text`, quotes included
+fn quoted_len(text: &str) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return i + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// A path as git quotes names with special characters (`"caf\303\251.txt"`)
+fn unquote(path: &str) -> String {
+    let path = path.trim_end();
+    let Some(inner) = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) else {
+        return path.to_string();
+    };
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some(d @ '0'..='7') => {
+                let mut value = d.to_digit(8).unwrap_or(0);
+                for _ in 0..2 {
+                    if let Some(next) = chars.peek().and_then(|c| c.to_digit(8)) {
+                        value = value * 8 + next;
+                        chars.next();
+                    }
+                }
+                bytes.push(value as u8);
+            }
+            Some(other) => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+            None => {}
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+/// Hunks of `patch` that `git apply` reported as failing
+/// (`error: patch failed: <file>:<line>`)
+fn rejected_hunks(patch: &str, stderr: &str) -> Vec<String> {
+    let failures: Vec<(String, usize)> = stderr
+        .lines()
+        .filter_map(|line| line.strip_prefix("error: patch failed: "))
+        .filter_map(|rest| {
+            let (file, line) = rest.rsplit_once(':')?;
+            Some((file.to_string(), line.trim().parse().ok()?))
+        })
+        .collect();
+
+    let mut hunks = Vec::new();
+    let mut file = None;
+    let mut current: Option<(usize, String)> = None;
+    let mut flush = |current: &mut Option<(usize, String)>, file: &Option<String>| {
+        if let (Some((start, text)), Some(file)) = (current.take(), file) {
+            if failures.iter().any(|(f, l)| f == file && *l == start) {
+                hunks.push(format!("{}:\n{}", file, text));
+            }
+        }
+    };
+
+    for line in patch.lines() {
+        if line.starts_with("diff ") || line.starts_with("--- ") {
+            flush(&mut current, &file);
+        } else if line.starts_with("+++ ") {
+            file = patch_paths(&format!("--- /dev/null\n{}", line)).pop();
+        } else if let Some(range) = line.strip_prefix("@@ -") {
+            flush(&mut current, &file);
+            let start = range
+                .split([',', ' '])
+                .next()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0);
+            current = Some((start, format!("{}\n", line)));
+        } else if let Some((_, text)) = current.as_mut() {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    flush(&mut current, &file);
+    hunks
+}
+
+pub struct GitApplySkill;
+
+impl GitApplySkill {
+    /// Check `patch` with `git apply --check`, then apply it in `dir`.
+    /// Nothing is written unless every hunk applies.
+    pub async fn apply(&self, patch: &str, dir: &Path) -> Result<String> {
+        let paths = patch_paths(patch);
+        if paths.is_empty() {
+            anyhow::bail!("No file headers (---/+++) found; expected a unified diff");
+        }
+        for path in &paths {
+            let p = Path::new(path);
+            if p.is_absolute() || p.components().any(|c| c == Component::ParentDir) {
+                anyhow::bail!("Patch path '{}' is outside the working directory", path);
+            }
+        }
+
+        let patch_file =
+            std::env::temp_dir().join(format!("webrana-{}.patch", uuid::Uuid::new_v4()));
+        let mut content = patch.to_string();
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        std::fs::write(&patch_file, content)?;
+        let patch_arg = patch_file.to_string_lossy().to_string();
+
+        let result = async {
+            let check = Command::new(binaries::git()?)
+                .args(["apply", "--check", "--verbose", &patch_arg])
+                .current_dir(dir)
+                .kill_on_drop(true)
+                .output()
+                .await
+                .context("Failed to execute git command")?;
+            if !check.status.success() {
+                let stderr = String::from_utf8_lossy(&check.stderr);
+                let mut report = format!(
+                    "Patch does not apply; no files were changed.\n{}",
+                    stderr.trim()
+                );
+                let hunks = rejected_hunks(patch, &stderr);
+                if !hunks.is_empty() {
+                    report.push_str("\n\nRejected hunks:\n");
+                    report.push_str(&hunks.join("\n"));
+                }
+                anyhow::bail!(report);
+            }
+
+            run_git_command(&["apply", &patch_arg], dir.to_str()).await?;
+            Ok(format!(
+                "Applied patch to {} file(s): {}",
+                paths.len(),
+                paths.join(", ")
+            ))
+        }
+        .await;
+
+        let _ = std::fs::remove_file(&patch_file);
+        result
+    }
+}
+
+#[async_trait]
+impl Skill for GitApplySkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: "git_apply".to_string(),
+            description: "Apply a unified diff patch to the working directory with git apply. The patch is checked first; if any hunk fails, nothing is changed and the rejected hunks are returned.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "patch": {
+                        "type": "string",
+                        "description": "Unified diff (as produced by git diff), paths relative to the working directory"
+                    }
+                },
+                "required": ["patch"]
+            }),
+            requires_confirmation: true,
+            parallel_safe: false,
+        }
+    }
+
+    async fn execute(&self, args: &Value, _settings: &Settings) -> Result<String> {
+        let patch = args["patch"].as_str().context("Patch is required")?;
+        self.apply(patch, &std::env::current_dir()?).await
+    }
+}
+
+const PR_DESCRIPTION_PROMPT: &str = "You write pull request descriptions. From the commit log and diff \
+you are given, write a Markdown description with three sections: `## Summary` (two or three sentences \
+on what the change does and why), `## Changes` (one bullet per notable change, naming the files) and \
+`## Testing` (tests added or changed, and how a reviewer can verify the change). Describe only what \
+the diff shows. Answer with the Markdown only.";
+
+/// Writes text for a system prompt and a message
+#[async_trait]
+pub trait Drafter: Send + Sync {
+    async fn draft(&self, system_prompt: &str, message: &str) -> Result<String>;
+}
+
+#[async_trait]
+impl Drafter for LlmClient {
+    async fn draft(&self, system_prompt: &str, message: &str) -> Result<String> {
+        self.chat(system_prompt, &[], message).await
+    }
+}
+
+pub struct GitPrDescriptionSkill;
+
+impl GitPrDescriptionSkill {
+    /// Commit log, diffstat and diff of HEAD against `base`, cut to
+    /// `max_chars` at a line boundary with a note when the diff is too big
+    pub async fn prompt(&self, path: Option<&str>, base: &str, max_chars: usize) -> Result<String> {
+        // Would be parsed as an option (e.g. `--output=<file>`) inside the ranges below
+        if base.starts_with('-') {
+            anyhow::bail!("Invalid base '{}': must be a branch, tag or commit", base);
+        }
+        let verify = format!("{}^{{commit}}", base);
+        run_git_command(&["rev-parse", "--verify", "--quiet", "--end-of-options", &verify], path)
+            .await
+            .map_err(|_| anyhow::anyhow!("Unknown base '{}': no such branch, tag or commit", base))?;
+        let commits = format!("{}..HEAD", base);
+        let changes = format!("{}...HEAD", base);
+        let log = run_git_command(&["log", "--no-merges", "--format=- %s%n%b", &commits], path).await?;
+        if log.trim().is_empty() {
+            anyhow::bail!("No commits on this branch since '{}'", base);
+        }
+        let stat = run_git_command(&["diff", "--stat", &changes], path).await?;
+        let diff = run_git_command(&["diff", &changes], path).await?;
+
+        let mut prompt = format!("Commits since {}:\n{}\nFiles changed:\n{}\nDiff:\n", base, log.trim_end(), stat);
+        let room = max_chars.saturating_sub(prompt.len());
+        if diff.len() <= room {
+            prompt.push_str(&diff);
+        } else {
+            let cut = diff[..diff.floor_char_boundary(room)].rfind('\n').map_or(0, |i| i + 1);
+            prompt.push_str(&diff[..cut]);
+            prompt.push_str(&format!(
+                "\n[Diff truncated to {} of {} characters to fit the context window; the file list above is complete]\n",
+                cut,
+                diff.len()
+            ));
+        }
+        Ok(prompt)
+    }
+
+    /// Markdown PR description of HEAD against `base`, drafted by `drafter`
+    pub async fn describe(
+        &self,
+        path: Option<&str>,
+        base: &str,
+        max_chars: usize,
+        drafter: &dyn Drafter,
+    ) -> Result<String> {
+        let prompt = self.prompt(path, base, max_chars).await?;
+        drafter.draft(PR_DESCRIPTION_PROMPT, &prompt).await
+    }
+}
+
+#[async_trait]
+impl Skill for GitPrDescriptionSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: "git_pr_description".to_string(),
+            description: "Draft a Markdown pull request description (summary, changes, testing notes) from the commits and diff of the current branch against a base branch. Read-only: nothing is pushed or opened.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path to the git repository (defaults to current directory)"
+                    },
+                    "base": {
+                        "type": "string",
+                        "description": "Branch the PR targets (default: main)"
+                    }
+                },
+                "required": []
+            }),
+            requires_confirmation: false,
+            parallel_safe: true,
+        }
+    }
+
+    async fn execute(&self, args: &Value, settings: &Settings) -> Result<String> {
+        let path = args["path"].as_str();
+        let base = args["base"].as_str().unwrap_or("main");
+        let max_chars = ContextBudget::from_settings(settings).room_after(PR_DESCRIPTION_PROMPT.len());
+        let llm = LlmClient::new(settings).await?;
+        self.describe(path, base, max_chars, &llm).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    const PATCH: &str = "diff --git a/greet.txt b/greet.txt
+--- a/greet.txt
++++ b/greet.txt
+@@ -1,3 +1,3 @@
+ one
+-two
++TWO
+ three
+";
+
+    async fn repo(content: &str) -> tempfile::TempDir {
+        let dir = tempdir().unwrap();
+        run_git_command(&["init", "-q"], dir.path().to_str()).await.unwrap();
+        fs::write(dir.path().join("greet.txt"), content).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_patch_paths() {
+        assert_eq!(patch_paths(PATCH), vec!["greet.txt"]);
+        let deletion = "--- a/old.rs\n+++ /dev/null\n@@ -1 +0,0 @@\n-gone\n";
+        assert_eq!(patch_paths(deletion), vec!["old.rs"]);
+
+        // Headers without ---/+++ lines
+        let rename = "diff --git a/src/old.rs b/../escape.rs\nsimilarity index 100%\nrename from src/old.rs\nrename to ../escape.rs\n";
+        assert_eq!(patch_paths(rename), vec!["../escape.rs", "src/old.rs"]);
+        let mode = "diff --git a/run.sh b/run.sh\nold mode 100644\nnew mode 100755\n";
+        assert_eq!(patch_paths(mode), vec!["run.sh"]);
+        let binary = "diff --git a/logo.png b/logo.png\nindex 1234567..89abcde 100644\nGIT binary patch\nliteral 4\nLcmZ?d00001\n\n";
+        assert_eq!(patch_paths(binary), vec!["logo.png"]);
+        let copy = "diff --git a/a.rs b/b.rs\nsimilarity index 100%\ncopy from a.rs\ncopy to b.rs\n";
+        assert_eq!(patch_paths(copy), vec!["b.rs"]);
+        let quoted = "diff --git \"a/my file.txt\" \"b/caf\\303\\251 b/x.txt\"\nnew file mode 100644\n";
+        assert_eq!(patch_paths(quoted), vec!["café b/x.txt", "my file.txt"]);
+        let spaced = "diff --git a/x b/y.txt b/x b/y.txt\ndeleted file mode 100644\n";
+        assert_eq!(patch_paths(spaced), vec!["x b/y.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_git_apply_clean_patch() {
+        let dir = repo("one\ntwo\nthree\n").await;
+        let output = GitApplySkill.apply(PATCH, dir.path()).await.unwrap();
+        assert!(output.contains("greet.txt"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("greet.txt")).unwrap(),
+            "one\nTWO\nthree\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_git_apply_refuses_rename_out_of_the_directory() {
+        let dir = repo("one\n").await;
+        let rename = "diff --git a/greet.txt b/../greet.txt\nsimilarity index 100%\nrename from greet.txt\nrename to ../greet.txt\n";
+        let err = GitApplySkill.apply(rename, dir.path()).await.unwrap_err().to_string();
+        assert!(err.contains("'../greet.txt' is outside the working directory"), "{}", err);
+        assert!(dir.path().join("greet.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_git_apply_rejected_patch_changes_nothing() {
+        let original = "one\nchanged\nthree\n";
+        let dir = repo(original).await;
+        let err = GitApplySkill.apply(PATCH, dir.path()).await.unwrap_err().to_string();
+        assert!(err.contains("no files were changed"), "{}", err);
+        assert!(err.contains("Rejected hunks:\ngreet.txt:\n@@ -1,3 +1,3 @@"), "{}", err);
+        assert_eq!(fs::read_to_string(dir.path().join("greet.txt")).unwrap(), original);
+
+        assert!(GitApplySkill
+            .apply("--- a/../escape.txt\n+++ b/../escape.txt\n", dir.path())
+            .await
+            .is_err());
+    }
+
+    struct FakeDrafter(Mutex<String>);
+
+    #[async_trait]
+    impl Drafter for FakeDrafter {
+        async fn draft(&self, _system_prompt: &str, message: &str) -> Result<String> {
+            *self.0.lock().unwrap() = message.to_string();
+            Ok("## Summary\nShouts the greeting.\n\n## Changes\n- greet.txt\n\n## Testing\nRead it.".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pr_description_from_branch_diff() {
+        let dir = repo("one\ntwo\nthree\n").await;
+        let cwd = dir.path().to_str();
+        let git = |args: &'static [&'static str]| async move {
+            let mut full = vec!["-c", "user.name=Test", "-c", "user.email=test@example.com"];
+            full.extend_from_slice(args);
+            run_git_command(&full, cwd).await.unwrap();
+        };
+        git(&["checkout", "-q", "-b", "main"]).await;
+        git(&["add", "greet.txt"]).await;
+        git(&["commit", "-q", "-m", "Add greeting"]).await;
+        git(&["checkout", "-q", "-b", "shout"]).await;
+        fs::write(dir.path().join("greet.txt"), "one\nTWO\nthree\n").unwrap();
+        git(&["commit", "-q", "-am", "Shout the second line"]).await;
+
+        let drafter = FakeDrafter(Mutex::new(String::new()));
+        let body = GitPrDescriptionSkill.describe(cwd, "main", 100_000, &drafter).await.unwrap();
+        assert!(body.starts_with("## Summary") && body.contains("## Testing"));
+        let prompt = drafter.0.lock().unwrap().clone();
+        assert!(prompt.contains("- Shout the second line") && !prompt.contains("Add greeting"), "{}", prompt);
+        assert!(prompt.contains("-two\n+TWO"), "{}", prompt);
+
+        let prompt = GitPrDescriptionSkill.prompt(cwd, "main", 150).await.unwrap();
+        assert!(prompt.contains("[Diff truncated to") && !prompt.contains("+TWO"), "{}", prompt);
+        assert!(GitPrDescriptionSkill.prompt(cwd, "shout", 100_000).await.is_err());
+
+        // An option in place of the base writes nothing
+        let out = dir.path().join("written");
+        let base = format!("--output={}", out.display());
+        assert!(GitPrDescriptionSkill.prompt(cwd, &base, 100_000).await.is_err());
+        assert!(GitPrDescriptionSkill.prompt(cwd, "no-such-branch", 100_000).await.is_err());
+        assert!(!out.exists());
+    }
+}
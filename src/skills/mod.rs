@@ -1,16 +1,59 @@
 mod codebase;
 mod edit_file;
+mod environment;
+mod escalation;
+pub mod export;
 mod file_ops;
 mod git_ops;
+mod patch;
+mod permissions;
+mod project_commands;
+mod project_memory;
 mod registry;
+mod sandbox;
 mod semantic_search;
 mod shell;
+mod symbol_edit;
+mod tasks;
+mod test_impact;
+mod vcs_context;
+mod web_fetch;
 
 #[allow(unused_imports)]
 pub use codebase::CodebaseSkill;
 #[allow(unused_imports)]
-pub use edit_file::{EditFileSkill, MultiEditSkill};
+pub use edit_file::{EditFileSkill, LineJoin, MultiEditSkill, TrailingNewline};
 #[allow(unused_imports)]
-pub use registry::{Skill, SkillDefinition, SkillRegistry};
+pub use environment::{cached as inspect_environment, EnvironmentReport, InspectEnvironmentSkill};
 #[allow(unused_imports)]
-pub use semantic_search::{SemanticSearch, SemanticSearchConfig};
+pub use escalation::{Confirmer, Escalation, PermissionGrant, REQUEST_PERMISSION_SKILL};
+#[allow(unused_imports)]
+pub use git_ops::{patch_paths, run_git_command, GitApplySkill};
+#[allow(unused_imports)]
+pub use patch::{edit_guidance, parse_patch, ApplyPatchSkill, FilePatch, PatchKind, DEFAULT_FUZZ};
+#[allow(unused_imports)]
+pub use permissions::{written_paths, SkillPermissions, FILE_WRITE_SKILLS, NETWORK_SKILLS};
+#[allow(unused_imports)]
+pub use project_commands::{BuildSkill, LintSkill, ProjectTask, TestSkill};
+#[allow(unused_imports)]
+pub use project_memory::{UpdateProjectMemorySkill, UPDATE_PROJECT_MEMORY_SKILL};
+#[allow(unused_imports)]
+pub use registry::{Skill, SkillDefinition, SkillOutput, SkillRegistry};
+#[allow(unused_imports)]
+pub use shell::{shell_join, ExecuteCommandSkill, ShellRun};
+#[allow(unused_imports)]
+pub use semantic_search::{ChunkLocation, DuplicatePair, SemanticSearch, SemanticSearchConfig, project_index_path};
+#[allow(unused_imports)]
+pub use symbol_edit::{edit_symbol, symbol_spans, EditSymbolSkill, SymbolSpan};
+#[allow(unused_imports)]
+pub use tasks::{
+    new_run_id, run_dir, Task, TaskList, TaskPriority, TaskStatus, TaskStore, TASK_SKILLS,
+};
+#[allow(unused_imports)]
+pub use test_impact::{SelectedTest, TestSelection, TestSelector};
+#[allow(unused_imports)]
+pub use vcs_context::{PostReviewCommentSkill, ReviewContextSkill};
+#[allow(unused_imports)]
+pub use web_fetch::WebFetchSkill;
+#[allow(unused_imports)]
+pub use sandbox::{runtime_available, runtime_binary};
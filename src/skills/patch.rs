@@ -0,0 +1,746 @@
+// ============================================
+// WEBRANA CLI - Unified Diff Patches
+// `apply_patch`: parse unified diffs and apply them with drift tolerance
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::path::{Component, Path, PathBuf};
+
+use super::edit_file::{LineBuffer, LineJoin};
+use super::registry::{Skill, SkillDefinition};
+use crate::config::{EditFormat, Settings};
+use crate::core::{InputSanitizer, SecurityConfig};
+use crate::indexer::encoding::{self, DecodedText};
+
+/// Lines a hunk may drift from its header position when `fuzz` is not given
+pub const DEFAULT_FUZZ: usize = 10;
+
+/// What a file entry of a patch does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchKind {
+    Modify,
+    Create,
+    Delete,
+    /// Only `old mode`/`new mode` lines, no hunks
+    ModeOnly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    /// 1-based, as in the `@@ -old_start,len +new_start,len @@` header
+    pub old_start: usize,
+    pub new_start: usize,
+    pub header: String,
+    pub lines: Vec<HunkLine>,
+    /// `\ No newline at end of file` after the last old / new line
+    pub old_no_newline: bool,
+    pub new_no_newline: bool,
+}
+
+impl Hunk {
+    /// Lines the hunk expects to find (context and removals)
+    pub fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(s) | HunkLine::Remove(s) => Some(s.as_str()),
+                HunkLine::Add(_) => None,
+            })
+            .collect()
+    }
+
+    /// Lines the hunk leaves behind (context and additions)
+    pub fn new_lines(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(s) | HunkLine::Add(s) => Some(s.clone()),
+                HunkLine::Remove(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// One file's entry in a unified diff
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePatch {
+    /// Path before the change; None for new files
+    pub old_path: Option<String>,
+    /// Path after the change; None for deleted files
+    pub new_path: Option<String>,
+    pub kind: PatchKind,
+    pub new_mode: Option<u32>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// The path the entry is reported under
+    pub fn path(&self) -> &str {
+        self.new_path.as_deref().or(self.old_path.as_deref()).unwrap_or("")
+    }
+}
+
+/// Strip `a/`/`b/` prefixes and trailing timestamps; `/dev/null` is None
+fn header_path(header: &str) -> Option<String> {
+    let path = header.split('\t').next()?.trim();
+    if path == "/dev/null" || path.is_empty() {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// `@@ -12,5 +12,6 @@ fn context` -> (12, 12)
+fn parse_hunk_header(line: &str) -> Option<(usize, usize)> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (ranges, _) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let start = |range: &str| range.split(',').next()?.parse::<usize>().ok();
+    Some((start(old)?, start(new)?))
+}
+
+/// Permission bits of a git mode such as `100755`
+fn parse_mode(value: &str) -> Option<u32> {
+    u32::from_str_radix(value.trim(), 8).ok().map(|mode| mode & 0o777)
+}
+
+/// Parse a unified diff with one or more file entries. Hunk line counts in
+/// headers are not trusted (models often get them wrong); a hunk runs until
+/// the next line that is not context, a removal or an addition.
+pub fn parse_patch(text: &str) -> Result<Vec<FilePatch>> {
+    let lines: Vec<&str> = text.lines().map(|l| l.strip_suffix('\r').unwrap_or(l)).collect();
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut current: Option<FilePatch> = None;
+    // A `diff --git` header has been seen for `current` but no ---/+++ yet
+    let mut git_header_only = false;
+
+    let new_entry = || FilePatch {
+        old_path: None,
+        new_path: None,
+        kind: PatchKind::Modify,
+        new_mode: None,
+        hunks: Vec::new(),
+    };
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            files.extend(current.take());
+            let mut entry = new_entry();
+            if let Some((a, b)) = rest.split_once(" b/") {
+                entry.old_path = header_path(a);
+                entry.new_path = Some(b.to_string());
+            }
+            entry.kind = PatchKind::ModeOnly;
+            current = Some(entry);
+            git_header_only = true;
+        } else if let Some(header) = line.strip_prefix("--- ").filter(|_| {
+            lines.get(i + 1).is_some_and(|next| next.starts_with("+++ "))
+        }) {
+            if !git_header_only {
+                files.extend(current.take());
+            }
+            let mut entry = current.take().unwrap_or_else(new_entry);
+            git_header_only = false;
+            entry.old_path = header_path(header);
+            entry.new_path = header_path(&lines[i + 1][4..]);
+            entry.kind = match (&entry.old_path, &entry.new_path) {
+                (None, Some(_)) => PatchKind::Create,
+                (Some(_), None) => PatchKind::Delete,
+                (None, None) => bail!("Patch line {}: both paths are /dev/null", i + 1),
+                _ if entry.kind == PatchKind::Create || entry.kind == PatchKind::Delete => entry.kind,
+                _ => PatchKind::Modify,
+            };
+            current = Some(entry);
+            i += 1;
+        } else if let Some(entry) = current.as_mut() {
+            if let Some(mode) = line.strip_prefix("new file mode ") {
+                entry.kind = PatchKind::Create;
+                entry.new_mode = parse_mode(mode);
+            } else if line.starts_with("deleted file mode ") {
+                entry.kind = PatchKind::Delete;
+            } else if let Some(mode) = line.strip_prefix("new mode ") {
+                entry.new_mode = parse_mode(mode);
+            } else if let Some(from) = line.strip_prefix("rename from ") {
+                entry.old_path = Some(from.to_string());
+            } else if let Some(to) = line.strip_prefix("rename to ") {
+                entry.new_path = Some(to.to_string());
+                if entry.kind == PatchKind::ModeOnly {
+                    entry.kind = PatchKind::Modify;
+                }
+            } else if line.starts_with("@@ ") {
+                let (old_start, new_start) = parse_hunk_header(line)
+                    .with_context(|| format!("Patch line {}: malformed hunk header '{}'", i + 1, line))?;
+                let mut hunk = Hunk {
+                    old_start,
+                    new_start,
+                    header: line.to_string(),
+                    lines: Vec::new(),
+                    old_no_newline: false,
+                    new_no_newline: false,
+                };
+                let mut bare_empty = 0;
+                while let Some(&body) = lines.get(i + 1) {
+                    let hunk_line = match body.chars().next() {
+                        Some(' ') => HunkLine::Context(body[1..].to_string()),
+                        // `--- ` starts the next file only when `+++ ` follows
+                        Some('-')
+                            if !(body.starts_with("--- ")
+                                && lines.get(i + 2).is_some_and(|l| l.starts_with("+++ "))) =>
+                        {
+                            HunkLine::Remove(body[1..].to_string())
+                        }
+                        Some('+') => HunkLine::Add(body[1..].to_string()),
+                        Some('\\') => {
+                            match hunk.lines.last() {
+                                Some(HunkLine::Remove(_)) => hunk.old_no_newline = true,
+                                Some(HunkLine::Add(_)) => hunk.new_no_newline = true,
+                                _ => {
+                                    hunk.old_no_newline = true;
+                                    hunk.new_no_newline = true;
+                                }
+                            }
+                            i += 1;
+                            continue;
+                        }
+                        // Blank context lines often lose their leading space
+                        None => {
+                            bare_empty += 1;
+                            hunk.lines.push(HunkLine::Context(String::new()));
+                            i += 1;
+                            continue;
+                        }
+                        _ => break,
+                    };
+                    bare_empty = 0;
+                    hunk.lines.push(hunk_line);
+                    i += 1;
+                }
+                // Blank lines after the last hunk separate entries
+                hunk.lines.truncate(hunk.lines.len() - bare_empty);
+                if hunk.lines.is_empty() {
+                    bail!("Patch line {}: empty hunk", i + 1);
+                }
+                if entry.kind == PatchKind::ModeOnly {
+                    entry.kind = PatchKind::Modify;
+                }
+                entry.hunks.push(hunk);
+            }
+        }
+        i += 1;
+    }
+    files.extend(current);
+
+    if files.is_empty() {
+        bail!("No file headers (---/+++ or diff --git) found; expected a unified diff");
+    }
+    for file in &files {
+        if file.kind == PatchKind::Modify && file.hunks.is_empty() && file.old_path == file.new_path {
+            bail!("Patch entry for {} has no hunks", file.path());
+        }
+    }
+    Ok(files)
+}
+
+/// Where a hunk landed, or why it could not be placed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkPlacement {
+    /// 0-based index in the file's lines, and the drift from the header
+    At { index: usize, offset: isize },
+    /// Best partial match: 1-based line and how many context lines agreed
+    Failed { closest: Option<(usize, usize)>, expected: usize },
+}
+
+/// Find `old` in `lines` at `expected`, then at growing drift up to `fuzz`
+/// lines in either direction, never before `min_index`
+pub fn locate(lines: &[String], old: &[&str], expected: usize, fuzz: usize, min_index: usize) -> HunkPlacement {
+    let matches_at = |index: usize| {
+        index >= min_index
+            && index + old.len() <= lines.len()
+            && old.iter().zip(&lines[index..]).all(|(a, b)| *a == b)
+    };
+    for drift in 0..=fuzz {
+        let candidates = [expected.checked_add(drift), expected.checked_sub(drift)];
+        for index in candidates.into_iter().flatten() {
+            if matches_at(index) {
+                return HunkPlacement::At {
+                    index,
+                    offset: index as isize - expected as isize,
+                };
+            }
+        }
+    }
+
+    let closest = (0..lines.len())
+        .map(|index| {
+            let agree = old
+                .iter()
+                .zip(lines.get(index..).unwrap_or_default())
+                .filter(|(a, b)| *a == *b)
+                .count();
+            (index, agree)
+        })
+        .filter(|&(_, agree)| agree > 0)
+        .max_by_key(|&(index, agree)| (agree, std::cmp::Reverse(index.abs_diff(expected))))
+        .map(|(index, agree)| (index + 1, agree));
+    HunkPlacement::Failed { closest, expected: expected + 1 }
+}
+
+/// A file change computed in memory, written only if every file succeeds
+struct PendingChange {
+    path: PathBuf,
+    /// Removed after `path` is written (renames)
+    remove: Option<PathBuf>,
+    content: Option<(String, DecodedText)>,
+    mode: Option<u32>,
+    summary: String,
+}
+
+/// Applies unified diffs relative to a root directory
+pub struct ApplyPatchSkill;
+
+impl ApplyPatchSkill {
+    /// Apply `patch` under `root`. Every hunk of every file must locate
+    /// within `fuzz` lines of its header, otherwise nothing is written and
+    /// the failing hunks are reported with their closest match.
+    pub fn apply(&self, patch: &str, root: &Path, fuzz: usize, settings: &Settings) -> Result<String> {
+        let files = parse_patch(patch)?;
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let sanitizer = InputSanitizer::new(SecurityConfig {
+            working_dir: root.clone(),
+            ..Default::default()
+        });
+
+        let mut changes = Vec::new();
+        let mut failures = Vec::new();
+        for file in &files {
+            for path in file.old_path.iter().chain(&file.new_path) {
+                check_path(path, &root, &sanitizer, settings)?;
+            }
+            match plan_file(file, &root, fuzz) {
+                Ok(change) => changes.push(change),
+                Err(e) => failures.push(format!("{}: {}", file.path(), e)),
+            }
+        }
+        if !failures.is_empty() {
+            bail!("Patch does not apply; no files were changed.\n{}", failures.join("\n"));
+        }
+
+        write_all(&changes)?;
+        let summaries: Vec<&str> = changes.iter().map(|c| c.summary.as_str()).collect();
+        Ok(format!(
+            "Applied patch to {} file(s):\n{}",
+            changes.len(),
+            summaries.join("\n")
+        ))
+    }
+}
+
+/// The write checks `write_file` applies, relative to `root`
+fn check_path(path: &str, root: &Path, sanitizer: &InputSanitizer, settings: &Settings) -> Result<()> {
+    let p = Path::new(path);
+    if p.is_absolute() || p.components().any(|c| c == Component::ParentDir) {
+        bail!("Patch path '{}' is outside the working directory", path);
+    }
+    if settings.safety.blocked_paths.iter().any(|blocked| path.starts_with(blocked.as_str())) {
+        bail!("🛡️ SECURITY: Path blocked by safety rules: {}", path);
+    }
+    if sanitizer.is_sensitive_file(path) {
+        bail!("🛡️ SECURITY: Cannot write to sensitive file: {}", path);
+    }
+    sanitizer
+        .validate_path(&root.join(path).to_string_lossy())
+        .map_err(|e| anyhow::anyhow!("🛡️ SECURITY: Path validation failed - {}", e))?;
+    Ok(())
+}
+
+fn plan_file(file: &FilePatch, root: &Path, fuzz: usize) -> Result<PendingChange> {
+    let path = root.join(file.path());
+    match file.kind {
+        PatchKind::ModeOnly => {
+            if !path.exists() {
+                bail!("file not found");
+            }
+            Ok(PendingChange {
+                path,
+                remove: None,
+                content: None,
+                mode: file.new_mode,
+                summary: format!("{} (mode {:o})", file.path(), file.new_mode.unwrap_or(0o644)),
+            })
+        }
+        PatchKind::Create => {
+            if path.exists() && std::fs::metadata(&path)?.len() > 0 {
+                bail!("file already exists");
+            }
+            let mut buffer = LineBuffer::parse("");
+            for hunk in &file.hunks {
+                buffer.lines.extend(hunk.new_lines());
+            }
+            buffer.trailing_newline = !file.hunks.last().is_some_and(|h| h.new_no_newline);
+            Ok(PendingChange {
+                path,
+                remove: None,
+                content: Some((buffer.render(LineJoin::default()), DecodedText {
+                    text: String::new(),
+                    encoding: Default::default(),
+                })),
+                mode: file.new_mode,
+                summary: format!("{} (created)", file.path()),
+            })
+        }
+        PatchKind::Delete => {
+            let decoded = encoding::read_text(&path).context("file not found")?;
+            let buffer = LineBuffer::parse(&decoded.text);
+            let expected: Vec<&str> = file.hunks.iter().flat_map(|h| h.old_lines()).collect();
+            if !file.hunks.is_empty() && expected != buffer.lines {
+                bail!("file content differs from the deletion hunk; refusing to delete");
+            }
+            Ok(PendingChange {
+                path: path.clone(),
+                remove: Some(path),
+                content: None,
+                mode: None,
+                summary: format!("{} (deleted)", file.path()),
+            })
+        }
+        PatchKind::Modify => {
+            let source = root.join(file.old_path.as_deref().unwrap_or(file.path()));
+            let decoded = encoding::read_text(&source).context("file not found")?;
+            let mut buffer = LineBuffer::parse(&decoded.text);
+            let mut failures = Vec::new();
+            let mut notes = Vec::new();
+            // Net lines added by earlier hunks, and where the last one ended
+            let mut delta: isize = 0;
+            let mut min_index = 0;
+
+            for (n, hunk) in file.hunks.iter().enumerate() {
+                let old = hunk.old_lines();
+                // A zero-length old range names the line *before* the hunk
+                let header_index = if old.is_empty() { hunk.old_start } else { hunk.old_start.saturating_sub(1) };
+                let expected = (header_index as isize + delta).max(0) as usize;
+                match locate(&buffer.lines, &old, expected, fuzz, min_index) {
+                    HunkPlacement::At { index, offset } => {
+                        if offset != 0 {
+                            notes.push(format!("hunk {} offset {:+} lines", n + 1, offset));
+                        }
+                        let new = hunk.new_lines();
+                        min_index = index + new.len();
+                        delta += new.len() as isize - old.len() as isize;
+                        buffer.lines.splice(index..index + old.len(), new);
+                        if hunk.new_no_newline {
+                            buffer.trailing_newline = false;
+                        } else if hunk.old_no_newline {
+                            buffer.trailing_newline = true;
+                        }
+                    }
+                    HunkPlacement::Failed { closest, expected } => failures.push(format!(
+                        "hunk {} ({}) does not match at line {} (±{}); {}",
+                        n + 1,
+                        hunk.header,
+                        expected,
+                        fuzz,
+                        match closest {
+                            Some((line, agree)) => format!(
+                                "closest context at line {} ({} of {} lines match)",
+                                line,
+                                agree,
+                                old.len()
+                            ),
+                            None => "no context lines found in the file".to_string(),
+                        }
+                    )),
+                }
+            }
+            if !failures.is_empty() {
+                bail!("{}", failures.join("\n  "));
+            }
+
+            let renamed = source != path;
+            let mut summary = format!(
+                "{} ({} hunk{}{})",
+                file.path(),
+                file.hunks.len(),
+                if file.hunks.len() == 1 { "" } else { "s" },
+                if renamed { ", renamed" } else { "" }
+            );
+            if !notes.is_empty() {
+                summary.push_str(&format!(": {}", notes.join(", ")));
+            }
+            Ok(PendingChange {
+                path,
+                remove: renamed.then_some(source),
+                content: Some((buffer.render(LineJoin::default()), decoded)),
+                mode: file.new_mode,
+                summary,
+            })
+        }
+    }
+}
+
+/// Write every change, restoring the originals if any write fails
+fn write_all(changes: &[PendingChange]) -> Result<()> {
+    let mut backups: Vec<(PathBuf, Option<Vec<u8>>)> = Vec::new();
+    for change in changes {
+        for path in std::iter::once(&change.path).chain(&change.remove) {
+            if !backups.iter().any(|(p, _)| p == path) {
+                backups.push((path.clone(), std::fs::read(path).ok()));
+            }
+        }
+    }
+
+    let result = changes.iter().try_for_each(|change| -> Result<()> {
+        if let Some((text, original)) = &change.content {
+            if let Some(dir) = change.path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            encoding::write_text(&change.path, text, original.encoding)
+                .with_context(|| format!("Failed to write {}", change.path.display()))?;
+        }
+        if let Some(remove) = &change.remove {
+            std::fs::remove_file(remove)
+                .with_context(|| format!("Failed to remove {}", remove.display()))?;
+        }
+        #[cfg(unix)]
+        if let (Some(mode), true) = (change.mode, change.path.exists()) {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&change.path, std::fs::Permissions::from_mode(mode))?;
+        }
+        Ok(())
+    });
+
+    if result.is_err() {
+        for (path, content) in backups {
+            match content {
+                Some(bytes) => {
+                    let _ = std::fs::write(&path, bytes);
+                }
+                None => {
+                    let _ = std::fs::remove_file(&path);
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Tool guidance appended to the system prompt for `format`
+pub fn edit_guidance(format: EditFormat) -> &'static str {
+    match format {
+        EditFormat::SearchReplace => {
+            "To change existing files, prefer edit_file with an exact search string. \
+             Use apply_patch only for changes spanning many files."
+        }
+        EditFormat::UnifiedDiff => {
+            "To change existing files, prefer apply_patch with a unified diff \
+             (--- a/path, +++ b/path, @@ hunks with 3 lines of context). \
+             Use edit_file for a single small replacement."
+        }
+    }
+}
+
+#[async_trait]
+impl Skill for ApplyPatchSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: "apply_patch".to_string(),
+            description: "Apply a unified diff (--- a/path, +++ b/path, @@ hunks; several files allowed, including new and deleted files). Hunks are located by their context lines, tolerating some line drift. If any hunk fails, nothing is changed and the failing hunks are reported with the closest match.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "patch_text": {
+                        "type": "string",
+                        "description": "Unified diff, paths relative to the working directory"
+                    },
+                    "fuzz": {
+                        "type": "integer",
+                        "description": format!("Lines a hunk may have drifted from its header position (default: {})", DEFAULT_FUZZ)
+                    }
+                },
+                "required": ["patch_text"]
+            }),
+            requires_confirmation: true,
+            parallel_safe: false,
+        }
+    }
+
+    async fn execute(&self, args: &Value, settings: &Settings) -> Result<String> {
+        let patch = args["patch_text"].as_str().context("Missing 'patch_text' argument")?;
+        let fuzz = args["fuzz"].as_u64().map(|f| f as usize).unwrap_or(DEFAULT_FUZZ);
+        self.apply(patch, &std::env::current_dir()?, fuzz, settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const MULTI: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -2,3 +2,3 @@ fn main() {
+ one
+-two
++TWO
+ three
+diff --git a/notes.txt b/notes.txt
+new file mode 100644
+--- /dev/null
++++ b/notes.txt
+@@ -0,0 +1,2 @@
++first
++second
+\\ No newline at end of file
+diff --git a/old.txt b/old.txt
+deleted file mode 100644
+--- a/old.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+";
+
+    /// Line numbers in the header are 2 lines too early
+    const SHIFTED: &str = "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@
+ two
+ three
++three and a half
+ four
+";
+
+    /// The second hunk's context was never in the file
+    const CONFLICT: &str = "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,2 +1,2 @@
+-zero
++ZERO
+ one
+@@ -5,3 +5,3 @@
+ four
+-FIVE
++5
+ six
+";
+
+    fn project() -> tempfile::TempDir {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "zero\none\ntwo\nthree\nfour\nfive\nsix\n").unwrap();
+        fs::write(dir.path().join("old.txt"), "bye\n").unwrap();
+        fs::write(dir.path().join("run.sh"), "echo hi\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_multi_file_patch() {
+        let files = parse_patch(MULTI).unwrap();
+        let kinds: Vec<(&str, PatchKind)> = files.iter().map(|f| (f.path(), f.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("src/lib.rs", PatchKind::Modify),
+                ("notes.txt", PatchKind::Create),
+                ("old.txt", PatchKind::Delete),
+                ("run.sh", PatchKind::ModeOnly),
+            ]
+        );
+        let hunk = &files[0].hunks[0];
+        assert_eq!((hunk.old_start, hunk.new_start), (2, 2));
+        assert_eq!(hunk.old_lines(), vec!["one", "two", "three"]);
+        assert_eq!(hunk.new_lines(), vec!["one", "TWO", "three"]);
+        assert!(files[1].hunks[0].new_no_newline);
+        assert_eq!(files[3].new_mode, Some(0o755));
+
+        assert!(parse_patch("just some text\n").is_err());
+        assert!(parse_patch("--- a/x\n+++ b/x\n@@ bogus @@\n").is_err());
+    }
+
+    #[test]
+    fn test_apply_multi_file_patch() {
+        let dir = project();
+        let settings = Settings::default();
+        let report = ApplyPatchSkill.apply(MULTI, dir.path(), DEFAULT_FUZZ, &settings).unwrap();
+        assert!(report.starts_with("Applied patch to 4 file(s)"), "{}", report);
+
+        let read = |p: &str| fs::read_to_string(dir.path().join(p)).unwrap();
+        assert_eq!(read("src/lib.rs"), "zero\none\nTWO\nthree\nfour\nfive\nsix\n");
+        assert_eq!(read("notes.txt"), "first\nsecond");
+        assert!(!dir.path().join("old.txt").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.path().join("run.sh")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+    }
+
+    #[test]
+    fn test_shifted_hunk_applies_within_fuzz() {
+        let dir = project();
+        let settings = Settings::default();
+        let report = ApplyPatchSkill.apply(SHIFTED, dir.path(), 3, &settings).unwrap();
+        assert!(report.contains("hunk 1 offset +2 lines"), "{}", report);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("src/lib.rs")).unwrap(),
+            "zero\none\ntwo\nthree\nthree and a half\nfour\nfive\nsix\n"
+        );
+
+        // Exact position only: the drift is too large
+        let dir = project();
+        let err = ApplyPatchSkill.apply(SHIFTED, dir.path(), 1, &settings).unwrap_err();
+        assert!(err.to_string().contains("closest context at line 3 (3 of 3 lines match)"), "{}", err);
+    }
+
+    #[test]
+    fn test_conflicting_hunk_changes_nothing() {
+        let dir = project();
+        let before = fs::read_to_string(dir.path().join("src/lib.rs")).unwrap();
+        let err = ApplyPatchSkill
+            .apply(CONFLICT, dir.path(), DEFAULT_FUZZ, &Settings::default())
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.starts_with("Patch does not apply; no files were changed."), "{}", err);
+        assert!(!err.contains("hunk 1 "), "{}", err);
+        assert!(
+            err.contains("hunk 2 (@@ -5,3 +5,3 @@) does not match at line 5 (±10); closest context at line 5 (2 of 3 lines match)"),
+            "{}",
+            err
+        );
+        // The first hunk located fine but was not written either
+        assert_eq!(fs::read_to_string(dir.path().join("src/lib.rs")).unwrap(), before);
+    }
+
+    #[test]
+    fn test_patch_paths_are_confined() {
+        let dir = project();
+        let escape = "--- a/../outside.txt\n+++ b/../outside.txt\n@@ -1 +1 @@\n-a\n+b\n";
+        assert!(ApplyPatchSkill
+            .apply(escape, dir.path(), DEFAULT_FUZZ, &Settings::default())
+            .unwrap_err()
+            .to_string()
+            .contains("outside the working directory"));
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+
+use super::git_ops::patch_paths;
+use crate::crew::Crew;
+
+/// Skills that modify files; denied for crews without `file_write`. The
+/// `--allow-paths` guard and the scaffolding boundary check the same list
+/// through [`written_paths`].
+pub const FILE_WRITE_SKILLS: &[&str] =
+    &["write_file", "edit_file", "edit_symbol", "git_apply", "apply_patch"];
+
+/// The argument holding the patch of a write skill that applies one
+fn patch_argument(skill: &str) -> Option<&'static str> {
+    match skill {
+        "git_apply" => Some("patch"),
+        "apply_patch" => Some("patch_text"),
+        _ => None,
+    }
+}
+
+/// Whether `skill` writes the one file named by its `path` argument
+pub fn writes_path_argument(skill: &str) -> bool {
+    FILE_WRITE_SKILLS.contains(&skill) && patch_argument(skill).is_none()
+}
+
+/// Files a write skill call will modify: the `path` argument, or every file
+/// a `git_apply`/`apply_patch` patch touches, both sides of renames and
+/// mode-only changes included
+pub fn written_paths(skill: &str, args: &serde_json::Value) -> Vec<String> {
+    if !FILE_WRITE_SKILLS.contains(&skill) {
+        return Vec::new();
+    }
+    let key = patch_argument(skill).unwrap_or("path");
+    match args.get(key).and_then(|v| v.as_str()) {
+        Some(patch) if key != "path" => patch_paths(patch),
+        Some(path) => vec![path.to_string()],
+        None => Vec::new(),
+    }
+}
+
+/// Skills that call remote APIs; denied for crews without `network_access`
+pub const NETWORK_SKILLS: &[&str] =
+    &["vcs_review_context", "post_review_comment", "web_fetch", "git_pr_description"];
+
+/// Allow/deny set for skills. Deny takes precedence; an empty allow set allows everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SkillPermissions {
+    pub allowed: HashSet<String>,
+    pub denied: HashSet<String>,
+}
+
+impl SkillPermissions {
+    pub fn new<I, J>(allowed: I, denied: J) -> Self
+    where
+        I: IntoIterator<Item = String>,
+        J: IntoIterator<Item = String>,
+    {
+        Self {
+            allowed: allowed.into_iter().collect(),
+            denied: denied.into_iter().collect(),
+        }
+    }
+
+    /// Permissions declared by a crew
+    pub fn from_crew(crew: &Crew) -> Self {
+        let mut denied = crew.permissions.denied_skills.clone();
+        if !crew.permissions.file_write {
+            denied.extend(FILE_WRITE_SKILLS.iter().map(|s| s.to_string()));
+        }
+        if !crew.permissions.network_access {
+            denied.extend(NETWORK_SKILLS.iter().map(|s| s.to_string()));
+        }
+        Self {
+            allowed: crew.permissions.allowed_skills.clone(),
+            denied,
+        }
+    }
+
+    pub fn is_allowed(&self, skill: &str) -> bool {
+        if self.denied.contains(skill) {
+            return false;
+        }
+        self.allowed.is_empty() || self.allowed.contains(skill)
+    }
+
+    /// Layer `other` on top: it can only narrow what is already allowed
+    pub fn layer(&self, other: &SkillPermissions) -> SkillPermissions {
+        let allowed = match (self.allowed.is_empty(), other.allowed.is_empty()) {
+            (true, _) => other.allowed.clone(),
+            (false, true) => self.allowed.clone(),
+            (false, false) => self.allowed.intersection(&other.allowed).cloned().collect(),
+        };
+        let mut denied = self.denied.clone();
+        denied.extend(other.denied.iter().cloned());
+
+        // Two non-empty allow lists with nothing in common allow nothing
+        if allowed.is_empty() && !(self.allowed.is_empty() && other.allowed.is_empty()) {
+            denied.extend(self.allowed.iter().chain(other.allowed.iter()).cloned());
+        }
+
+        SkillPermissions { allowed, denied }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_deny_takes_precedence() {
+        let perms = SkillPermissions::new(set(&["read_file", "write_file"]), set(&["write_file"]));
+        assert!(perms.is_allowed("read_file"));
+        assert!(!perms.is_allowed("write_file"));
+        assert!(!perms.is_allowed("execute_command"));
+        assert!(SkillPermissions::default().is_allowed("anything"));
+    }
+
+    #[test]
+    fn test_layer_narrows() {
+        let crew = SkillPermissions::new(set(&["read_file", "write_file", "git_diff"]), set(&["git_commit"]));
+        let run = SkillPermissions::new(set(&["read_file", "execute_command"]), set(&[]));
+
+        let layered = crew.layer(&run);
+        assert!(layered.is_allowed("read_file"));
+        // Not allowed by the crew, so the run can't add it
+        assert!(!layered.is_allowed("execute_command"));
+        assert!(!layered.is_allowed("write_file"));
+        assert!(!layered.is_allowed("git_commit"));
+
+        let disjoint = crew.layer(&SkillPermissions::new(set(&["execute_command"]), set(&[])));
+        assert!(!disjoint.is_allowed("read_file"));
+        assert!(!disjoint.is_allowed("execute_command"));
+
+        let deny_only = SkillPermissions::default().layer(&SkillPermissions::new(set(&[]), set(&["write_file"])));
+        assert!(deny_only.is_allowed("read_file"));
+        assert!(!deny_only.is_allowed("write_file"));
+    }
+
+    #[test]
+    fn test_every_write_skill_reports_its_paths() {
+        let patch = "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-a\n+b\n";
+        let args = serde_json::json!({"path": "src/lib.rs", "patch": patch, "patch_text": patch});
+        for skill in FILE_WRITE_SKILLS {
+            assert_eq!(written_paths(skill, &args), ["src/lib.rs"], "{}", skill);
+        }
+        assert!(written_paths("read_file", &args).is_empty());
+    }
+
+    #[test]
+    fn test_read_only_crew_denies_file_writes() {
+        let mut crew = Crew::new("reader", "Reader", "Reads code", "Only read.");
+        assert!(SkillPermissions::from_crew(&crew).is_allowed("git_apply"));
+
+        crew.permissions.file_write = false;
+        let perms = SkillPermissions::from_crew(&crew);
+        for skill in FILE_WRITE_SKILLS {
+            assert!(!perms.is_allowed(skill), "{} should be denied", skill);
+        }
+        assert!(perms.is_allowed("read_file"));
+        assert!(perms.is_allowed("post_review_comment"));
+
+        crew.permissions.network_access = false;
+        let perms = SkillPermissions::from_crew(&crew);
+        assert!(NETWORK_SKILLS.iter().all(|skill| !perms.is_allowed(skill)));
+    }
+}
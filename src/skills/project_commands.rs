@@ -0,0 +1,211 @@
+// ============================================
+// WEBRANA CLI - Project Build/Test/Lint Skills
+// Commands chosen from the detected project type instead of guessed
+// ============================================
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use super::registry::{Skill, SkillDefinition};
+use super::sandbox::resolve_working_dir;
+use super::shell::ExecuteCommandSkill;
+use crate::config::Settings;
+use crate::indexer::{ProjectDetector, ProjectType};
+
+/// Which of the project's standard commands a skill runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectTask {
+    Build,
+    Test,
+    Lint,
+}
+
+impl ProjectTask {
+    pub fn skill_name(&self) -> &'static str {
+        match self {
+            ProjectTask::Build => "build_project",
+            ProjectTask::Test => "test_project",
+            ProjectTask::Lint => "lint_project",
+        }
+    }
+
+    fn verb(&self) -> &'static str {
+        match self {
+            ProjectTask::Build => "Build",
+            ProjectTask::Test => "Test",
+            ProjectTask::Lint => "Lint",
+        }
+    }
+
+    pub fn command(&self, project_type: &ProjectType) -> Option<&'static str> {
+        match self {
+            ProjectTask::Build => project_type.build_command(),
+            ProjectTask::Test => project_type.test_command(),
+            ProjectTask::Lint => project_type.lint_command(),
+        }
+    }
+
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: self.skill_name().to_string(),
+            description: format!(
+                "{} the project with the standard command for its detected type (e.g. cargo, npm, pytest, go). \
+                 Reports PASS or FAIL with the command output.",
+                self.verb()
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "subdir": {
+                        "type": "string",
+                        "description": "Project directory inside a monorepo (defaults to the current directory)"
+                    }
+                },
+                "required": []
+            }),
+            requires_confirmation: true,
+            parallel_safe: false,
+        }
+    }
+
+    /// Detect the project in `subdir` and run the command through the
+    /// shell skill's checks, sandbox and timeout
+    async fn run(&self, args: &Value, settings: &Settings) -> Result<String> {
+        let subdir = args["subdir"].as_str().filter(|s| !s.is_empty());
+        // Symlinks and `..` resolved; the project must be inside cwd
+        let cwd = std::env::current_dir()?;
+        let dir = cwd.join(resolve_working_dir(&cwd, subdir)?);
+        if !dir.is_dir() {
+            bail!("{} is not a directory", dir.display());
+        }
+
+        let project_type = ProjectDetector::new(&dir).detect()?.project_type;
+        let Some(command) = self.command(&project_type) else {
+            bail!(
+                "No {} command known for {} projects in {}; use execute_command instead",
+                self.verb().to_lowercase(),
+                project_type.as_str(),
+                dir.display()
+            );
+        };
+
+        let run = ExecuteCommandSkill::new()
+            .run(command, subdir, false, settings)
+            .await?;
+        Ok(format!(
+            "{} `{}` ({} project{})\n{}",
+            if run.success { "PASS" } else { "FAIL" },
+            command,
+            project_type.as_str(),
+            subdir.map(|s| format!(" in {}", s)).unwrap_or_default(),
+            run.output
+        ))
+    }
+}
+
+/// Build with the detected project's build command
+pub struct BuildSkill;
+
+/// Run the detected project's whole test suite
+pub struct TestSkill;
+
+/// Lint with the detected project's linter
+pub struct LintSkill;
+
+#[async_trait]
+impl Skill for BuildSkill {
+    fn definition(&self) -> SkillDefinition {
+        ProjectTask::Build.definition()
+    }
+
+    async fn execute(&self, args: &Value, settings: &Settings) -> Result<String> {
+        ProjectTask::Build.run(args, settings).await
+    }
+}
+
+#[async_trait]
+impl Skill for TestSkill {
+    fn definition(&self) -> SkillDefinition {
+        ProjectTask::Test.definition()
+    }
+
+    async fn execute(&self, args: &Value, settings: &Settings) -> Result<String> {
+        ProjectTask::Test.run(args, settings).await
+    }
+}
+
+#[async_trait]
+impl Skill for LintSkill {
+    fn definition(&self) -> SkillDefinition {
+        ProjectTask::Lint.definition()
+    }
+
+    async fn execute(&self, args: &Value, settings: &Settings) -> Result<String> {
+        ProjectTask::Lint.run(args, settings).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Fixtures live under the working directory, which commands can't leave
+    fn rust_fixture(test_body: &str) -> tempfile::TempDir {
+        let tmp = tempfile::TempDir::new_in("target").unwrap();
+        let crate_dir = tmp.path().join("crates/fixture");
+        fs::create_dir_all(crate_dir.join("src")).unwrap();
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        )
+        .unwrap();
+        fs::write(
+            crate_dir.join("src/lib.rs"),
+            format!("#[cfg(test)]\nmod tests {{\n    #[test]\n    fn check() {{\n        {}\n    }}\n}}\n", test_body),
+        )
+        .unwrap();
+        tmp
+    }
+
+    #[tokio::test]
+    async fn test_runs_cargo_test_in_subdir() {
+        let tmp = rust_fixture("assert_eq!(1 + 1, 2);");
+        let subdir = tmp.path().join("crates/fixture");
+        let args = json!({ "subdir": subdir.to_string_lossy() });
+
+        let output = TestSkill.execute(&args, &Settings::default()).await.unwrap();
+        assert!(output.starts_with("PASS `cargo test` (rust project in "), "{}", output);
+        assert!(output.contains("test tests::check ... ok"), "{}", output);
+
+        fs::write(
+            subdir.join("src/lib.rs"),
+            "#[test]\nfn check() {\n    assert_eq!(1 + 1, 3, \"fixture failure\");\n}\n",
+        )
+        .unwrap();
+        let output = TestSkill.execute(&args, &Settings::default()).await.unwrap();
+        assert!(output.starts_with("FAIL `cargo test`"), "{}", output);
+        assert!(output.contains("fixture failure"), "{}", output);
+        assert!(output.contains("[Exit code: 101]"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_project_is_an_error() {
+        let tmp = tempfile::TempDir::new_in("target").unwrap();
+        let args = json!({ "subdir": tmp.path().to_string_lossy() });
+        let err = BuildSkill.execute(&args, &Settings::default()).await.unwrap_err();
+        assert!(err.to_string().contains("No build command known for unknown projects"), "{}", err);
+        assert_eq!(ProjectTask::Lint.command(&ProjectType::Rust), Some("cargo clippy"));
+    }
+
+    #[tokio::test]
+    async fn test_subdir_outside_cwd_is_refused() {
+        let outside = tempfile::TempDir::new().unwrap();
+        for subdir in ["..", "../..", &outside.path().to_string_lossy()] {
+            let args = json!({ "subdir": subdir });
+            let err = BuildSkill.execute(&args, &Settings::default()).await.unwrap_err();
+            assert!(err.to_string().contains("is outside"), "{}: {}", subdir, err);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+// ============================================
+// WEBRANA CLI - Project Memory Skill
+// Lets the agent record what it learns in WEBRANA.md, with the user's OK
+// ============================================
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::escalation::Escalation;
+use super::registry::{Skill, SkillDefinition};
+use crate::config::Settings;
+use crate::memory::project::{scaffold, target_path, upsert_section};
+use crate::util::diff::{self, DiffStyle};
+
+pub const UPDATE_PROJECT_MEMORY_SKILL: &str = "update_project_memory";
+
+/// Adds an entry under a heading of the project memory file. Every change
+/// is shown to the user as a diff and only written once they accept it.
+pub struct UpdateProjectMemorySkill {
+    escalation: Arc<Escalation>,
+    /// Project root; the current directory when None
+    root: Option<PathBuf>,
+}
+
+impl UpdateProjectMemorySkill {
+    pub fn new(escalation: Arc<Escalation>) -> Self {
+        Self { escalation, root: None }
+    }
+
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = Some(root);
+        self
+    }
+}
+
+#[async_trait]
+impl Skill for UpdateProjectMemorySkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: UPDATE_PROJECT_MEMORY_SKILL.to_string(),
+            description: "Record a build command, convention or gotcha in the project memory file \
+                          (WEBRANA.md) so later sessions know it. The user reviews each change."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "section": {
+                        "type": "string",
+                        "description": "Heading to add under: Build, Conventions, Gotchas, or a new one"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Markdown to append, usually one or more '- ' list items"
+                    }
+                },
+                "required": ["section", "content"]
+            }),
+            requires_confirmation: true,
+            parallel_safe: false,
+        }
+    }
+
+    async fn execute(&self, args: &Value, _settings: &Settings) -> Result<String> {
+        let section = args["section"].as_str().unwrap_or_default();
+        let content = args["content"].as_str().unwrap_or_default();
+        if section.trim().trim_start_matches('#').trim().is_empty() {
+            bail!("A section heading is required");
+        }
+        if content.trim().is_empty() {
+            bail!("Nothing to add: content is empty");
+        }
+
+        let root = match &self.root {
+            Some(root) => root.clone(),
+            None => std::env::current_dir()?,
+        };
+        let path = target_path(&root);
+        let current = std::fs::read_to_string(&path).ok();
+        let file = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let old = current.clone().unwrap_or_default();
+        let base = match current {
+            Some(text) => text,
+            None => scaffold(&root).unwrap_or_default(),
+        };
+        let new = upsert_section(&base, section, content);
+
+        let Some(confirmer) = self.escalation.confirmer() else {
+            return Ok(format!(
+                "Not applied: project memory changes need the user's review, which isn't \
+                 available in auto or non-interactive mode. Proposed change:\n{}",
+                diff::unified(&old, &new, &file, DiffStyle::default())
+            ));
+        };
+        let question = format!(
+            "Update project memory?\n{}   Apply?",
+            diff::unified(&old, &new, &file, DiffStyle::terminal())
+        );
+        if !confirmer(&question) {
+            return Ok(format!("The user declined the change to {}.", file));
+        }
+
+        std::fs::write(&path, &new)?;
+        Ok(format!("Updated '{}' in {}", section.trim().trim_start_matches('#').trim(), file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::project::MEMORY_FILE;
+    use std::fs;
+    use std::sync::Mutex;
+
+    fn skill(root: &std::path::Path, answer: bool) -> (UpdateProjectMemorySkill, Arc<Mutex<Vec<String>>>) {
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let escalation = Arc::new(Escalation::new());
+        let log = asked.clone();
+        escalation.set_confirmer(Some(Arc::new(move |q: &str| {
+            log.lock().unwrap().push(q.to_string());
+            answer
+        })));
+        (UpdateProjectMemorySkill::new(escalation).with_root(root.to_path_buf()), asked)
+    }
+
+    #[tokio::test]
+    async fn test_update_creates_then_appends_after_review() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join(MEMORY_FILE);
+        fs::write(&path, "# Project Memory\n\n## Build\n\n- `make`\n").unwrap();
+        let (skill, asked) = skill(tmp.path(), true);
+
+        let args = json!({ "section": "Gotchas", "content": "- Tests need Docker running" });
+        let out = skill.execute(&args, &Settings::default()).await.unwrap();
+        assert_eq!(out, "Updated 'Gotchas' in WEBRANA.md");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Project Memory\n\n## Build\n\n- `make`\n\n## Gotchas\n\n- Tests need Docker running\n"
+        );
+        assert!(asked.lock().unwrap()[0].contains("+## Gotchas"));
+
+        let args = json!({ "section": "build", "content": "- `make gen` first" });
+        skill.execute(&args, &Settings::default()).await.unwrap();
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("## Build\n\n- `make`\n- `make gen` first\n\n## Gotchas"));
+        assert!(asked.lock().unwrap()[1].contains("+- `make gen` first"));
+    }
+
+    #[tokio::test]
+    async fn test_declined_or_unattended_changes_are_not_written() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join(MEMORY_FILE);
+        let args = json!({ "section": "Conventions", "content": "- snake_case" });
+
+        let (declining, _) = skill(tmp.path(), false);
+        let out = declining.execute(&args, &Settings::default()).await.unwrap();
+        assert!(out.contains("declined"));
+        assert!(!path.exists());
+
+        let unattended = UpdateProjectMemorySkill::new(Arc::new(Escalation::new()))
+            .with_root(tmp.path().to_path_buf());
+        let out = unattended.execute(&args, &Settings::default()).await.unwrap();
+        assert!(out.starts_with("Not applied"));
+        assert!(out.contains("+- snake_case"));
+        assert!(!path.exists());
+    }
+}
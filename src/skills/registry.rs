@@ -3,30 +3,170 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::Instrument;
 
+use super::environment::InspectEnvironmentSkill;
+use super::escalation::{Confirmer, Escalation, RequestPermissionSkill, REQUEST_PERMISSION_SKILL};
+use super::project_memory::{UpdateProjectMemorySkill, UPDATE_PROJECT_MEMORY_SKILL};
 use super::file_ops::*;
+use super::patch::ApplyPatchSkill;
+use super::permissions::{writes_path_argument, written_paths, SkillPermissions};
+use super::project_commands::{BuildSkill, LintSkill, TestSkill};
 use super::git_ops::{
-    GitAddSkill, GitBranchSkill, GitCheckoutSkill, GitCommitSkill, GitDiffSkill, GitLogSkill,
-    GitStatusSkill,
+    GitAddSkill, GitApplySkill, GitBranchSkill, GitCheckoutSkill, GitCommitSkill,
+    GitDiffSkill, GitLogSkill, GitPrDescriptionSkill, GitStatusSkill,
 };
 use super::shell::*;
+use super::symbol_edit::EditSymbolSkill;
+use super::tasks::{TaskAddSkill, TaskListSkill, TaskStore, TaskUpdateSkill};
+use super::test_impact::RunTestsSkill;
+use super::vcs_context::{PostReviewCommentSkill, ReviewContextSkill};
+use super::web_fetch::WebFetchSkill;
 use crate::config::Settings;
+use crate::crew::Crew;
+use crate::llm::ImageArtifact;
+use crate::core::audit::{AuditEvent, AuditEventType, AuditSeverity, AUDIT};
+use crate::core::hooks::{self, HookEvent, HookRunner};
+use crate::core::undo::{ChangeJournal, ChangeManifest};
+use crate::core::{InputSanitizer, SecurityConfig, Stepper};
+use crate::scaffold::WorkdirBoundary;
+use crate::util::sha256_hex;
+
+/// The `expected_hash` parameter of the write skills
+pub(super) fn expected_hash_param() -> Value {
+    serde_json::json!({
+        "type": "string",
+        "description": "Only write if the file still has this content hash; filled in from your last read_file when omitted"
+    })
+}
+
+/// Short SHA-256 of a file's bytes, None when it can't be read
+pub fn content_hash(path: &str) -> Option<String> {
+    let bytes = std::fs::read(path).ok()?;
+    let mut hash = sha256_hex(&bytes);
+    hash.truncate(16);
+    Some(hash)
+}
+
+/// What this session last saw of a file, for write preconditions
+#[derive(Debug, Clone)]
+struct SeenFile {
+    /// Content hash at the last read, or after our own last write
+    hash: String,
+    /// Write calls applied since that read, with the hash each applied to
+    applied: Vec<(String, String)>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SkillDefinition {
     pub name: String,
     pub description: String,
     pub parameters: Value,
     pub requires_confirmation: bool,
+    /// Read-only, so calls from one response may run at the same time
+    pub parallel_safe: bool,
+}
+
+impl SkillDefinition {
+    /// Check `args` against the parameter schema: an object with every
+    /// required property present and declared property types respected
+    pub fn validate_args(&self, args: &Value) -> Result<()> {
+        let obj = args
+            .as_object()
+            .ok_or_else(|| anyhow::anyhow!("Arguments for '{}' must be a JSON object", self.name))?;
+
+        let required = self.parameters["required"].as_array().cloned().unwrap_or_default();
+        for name in required.iter().filter_map(|r| r.as_str()) {
+            if obj.get(name).is_none_or(|v| v.is_null()) {
+                anyhow::bail!("Missing required parameter '{}' for skill '{}'", name, self.name);
+            }
+        }
+
+        if let Some(properties) = self.parameters["properties"].as_object() {
+            for (name, value) in obj {
+                let expected = properties.get(name).and_then(|p| p["type"].as_str());
+                let matches = match expected {
+                    Some("string") => value.is_string(),
+                    Some("boolean") => value.is_boolean(),
+                    Some("integer") => value.is_i64() || value.is_u64(),
+                    Some("number") => value.is_number(),
+                    Some("array") => value.is_array(),
+                    Some("object") => value.is_object(),
+                    _ => true,
+                };
+                if !matches && !value.is_null() {
+                    anyhow::bail!(
+                        "Parameter '{}' for skill '{}' must be of type {}",
+                        name,
+                        self.name,
+                        expected.unwrap_or("unknown")
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What a skill returns: text for the model, plus any images it should see
+#[derive(Debug, Clone, Default)]
+pub struct SkillOutput {
+    pub text: String,
+    pub images: Vec<ImageArtifact>,
+}
+
+impl From<String> for SkillOutput {
+    fn from(text: String) -> Self {
+        Self { text, images: Vec::new() }
+    }
 }
 
 #[async_trait]
 pub trait Skill: Send + Sync {
     fn definition(&self) -> SkillDefinition;
     async fn execute(&self, args: &Value, settings: &Settings) -> Result<String>;
+
+    /// Text and images; skills that produce images override this
+    async fn execute_output(&self, args: &Value, settings: &Settings) -> Result<SkillOutput> {
+        self.execute(args, settings).await.map(SkillOutput::from)
+    }
+
+    /// Writes files its arguments don't name (plugins with `fs:write`), so
+    /// the path guards can't check them
+    fn writes_unlisted_files(&self) -> bool {
+        false
+    }
 }
 
 pub struct SkillRegistry {
     skills: HashMap<String, Box<dyn Skill>>,
+    /// Files successfully written or edited through this registry
+    edited_files: Mutex<Vec<String>>,
+    /// Pass `edited_files` to `run_tests` when the model doesn't specify any
+    auto_select_tests: AtomicBool,
+    /// Project hooks run after file edits
+    hooks: Option<HookRunner>,
+    /// Restricts file writes to one directory (used by `webrana new`)
+    boundary: Option<WorkdirBoundary>,
+    /// Skills exposed to the model and allowed to run
+    permissions: SkillPermissions,
+    /// `--allow-paths`/`--deny-paths` checks for file writes
+    write_guard: Option<InputSanitizer>,
+    /// Task list behind `task_add`/`task_update`/`task_list`
+    tasks: Arc<TaskStore>,
+    /// Crew permissions and the session grants from `request_permission`
+    escalation: Arc<Escalation>,
+    /// Asked before each model tool call (`run --confirm-each`)
+    stepper: Option<Stepper>,
+    /// Files read or written this session, keyed by path; writes derived
+    /// from a read carry its hash so a retried turn can't apply them twice
+    seen_files: Mutex<HashMap<String, SeenFile>>,
+    /// Content of files before the current run or session changed them,
+    /// for `webrana undo`
+    journal: Mutex<Option<ChangeJournal>>,
 }
 
 impl SkillRegistry {
@@ -53,19 +193,171 @@ impl SkillRegistry {
         skills.insert("git_add".to_string(), Box::new(GitAddSkill));
         skills.insert("git_branch".to_string(), Box::new(GitBranchSkill));
         skills.insert("git_checkout".to_string(), Box::new(GitCheckoutSkill));
+        skills.insert("git_apply".to_string(), Box::new(GitApplySkill));
+        skills.insert("git_pr_description".to_string(), Box::new(GitPrDescriptionSkill));
+        skills.insert("apply_patch".to_string(), Box::new(ApplyPatchSkill));
 
         // Edit operations
         skills.insert("edit_file".to_string(), Box::new(EditFileSkillWrapper));
+        skills.insert("edit_symbol".to_string(), Box::new(EditSymbolSkill));
 
         // Codebase operations
         skills.insert("grep_codebase".to_string(), Box::new(GrepCodebaseSkill));
         skills.insert("list_symbols".to_string(), Box::new(ListSymbolsSkill));
+        skills.insert(
+            "build_call_graph".to_string(),
+            Box::new(BuildCallGraphSkill),
+        );
         skills.insert(
             "get_project_info".to_string(),
             Box::new(GetProjectInfoSkill),
         );
 
-        Self { skills }
+        // Test operations
+        skills.insert("run_tests".to_string(), Box::new(RunTestsSkill));
+
+        // Build/test/lint with the detected project's commands
+        skills.insert("build_project".to_string(), Box::new(BuildSkill));
+        skills.insert("test_project".to_string(), Box::new(TestSkill));
+        skills.insert("lint_project".to_string(), Box::new(LintSkill));
+
+        // Task tracking
+        let tasks = Arc::new(TaskStore::new());
+        skills.insert("task_add".to_string(), Box::new(TaskAddSkill::new(tasks.clone())));
+        skills.insert(
+            "task_update".to_string(),
+            Box::new(TaskUpdateSkill::new(tasks.clone())),
+        );
+        skills.insert("task_list".to_string(), Box::new(TaskListSkill::new(tasks.clone())));
+
+        // Merge request context (GitLab, Bitbucket)
+        skills.insert("vcs_review_context".to_string(), Box::new(ReviewContextSkill));
+        skills.insert("post_review_comment".to_string(), Box::new(PostReviewCommentSkill));
+
+        // Toolchain versions and machine resources, probed once per session
+        skills.insert("inspect_environment".to_string(), Box::new(InspectEnvironmentSkill));
+
+        // Permission escalation for restricted crews
+        let escalation = Arc::new(Escalation::new());
+
+        // Web pages, limited by safety.egress_allowlist or confirmed per host
+        skills.insert("web_fetch".to_string(), Box::new(WebFetchSkill::new(escalation.clone())));
+        skills.insert(
+            REQUEST_PERMISSION_SKILL.to_string(),
+            Box::new(RequestPermissionSkill::new(escalation.clone())),
+        );
+
+        // Project memory (WEBRANA.md); changes are confirmed like permission requests
+        skills.insert(
+            UPDATE_PROJECT_MEMORY_SKILL.to_string(),
+            Box::new(UpdateProjectMemorySkill::new(escalation.clone())),
+        );
+
+        Self {
+            skills,
+            edited_files: Mutex::new(Vec::new()),
+            auto_select_tests: AtomicBool::new(false),
+            hooks: None,
+            boundary: None,
+            permissions: SkillPermissions::default(),
+            write_guard: None,
+            tasks,
+            escalation,
+            stepper: None,
+            seen_files: Mutex::new(HashMap::new()),
+            journal: Mutex::new(None),
+        }
+    }
+
+    /// Attach project hooks (`post_edit` runs after write_file/edit_file)
+    pub fn with_hooks(mut self, hooks: HookRunner) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    pub fn hooks(&self) -> Option<&HookRunner> {
+        self.hooks.as_ref()
+    }
+
+    /// The agent's task list, shared by the task skills
+    pub fn tasks(&self) -> &TaskStore {
+        &self.tasks
+    }
+
+    /// Record the files written from now on in `journal`
+    pub fn start_journal(&self, journal: ChangeJournal) {
+        *self.journal.lock().unwrap() = Some(journal);
+    }
+
+    /// Stop recording; the finished manifest, None if nothing changed
+    pub fn finish_journal(&self) -> Option<ChangeManifest> {
+        let journal = self.journal.lock().unwrap().take()?;
+        journal
+            .finish()
+            .unwrap_or_else(|e| {
+                tracing::warn!("Could not record the changed files for undo: {}", e);
+                None
+            })
+    }
+
+    /// Reject writes outside `boundary` and skills that could escape it
+    pub fn set_boundary(&mut self, boundary: WorkdirBoundary) {
+        self.boundary = Some(boundary);
+    }
+
+    /// Only allow file writes matching `allow` globs and not matching `deny`
+    /// globs (relative to the current directory). Empty lists remove the guard.
+    pub fn set_write_paths(&mut self, allow: Vec<String>, deny: Vec<String>) {
+        self.write_guard = if allow.is_empty() && deny.is_empty() {
+            None
+        } else {
+            Some(InputSanitizer::new(SecurityConfig {
+                write_allow: allow,
+                write_deny: deny,
+                ..Default::default()
+            }))
+        };
+    }
+
+    /// Narrow the usable skills by layering `permissions` over the current set
+    pub fn restrict(&mut self, permissions: &SkillPermissions) {
+        self.permissions = self.permissions.layer(permissions);
+    }
+
+    /// Apply a crew's permissions. Unlike `restrict`, the user can grant
+    /// narrow exceptions when the model asks through `request_permission`.
+    pub fn restrict_to_crew(&mut self, crew: &Crew) {
+        self.escalation
+            .set_crew(&crew.name, SkillPermissions::from_crew(crew));
+    }
+
+    /// Who answers `request_permission`; None (the default) denies every request
+    pub fn set_confirmer(&mut self, confirmer: Option<Confirmer>) {
+        self.escalation.set_confirmer(confirmer);
+    }
+
+    /// Ask `stepper` before each tool call the model makes
+    pub fn set_stepper(&mut self, stepper: Option<Stepper>) {
+        self.stepper = stepper;
+    }
+
+    pub fn stepper(&self) -> Option<&Stepper> {
+        self.stepper.as_ref()
+    }
+
+    /// Crew permissions and the grants made this session
+    pub fn escalation(&self) -> &Escalation {
+        &self.escalation
+    }
+
+    pub fn is_allowed(&self, name: &str) -> bool {
+        self.permissions.is_allowed(name) && self.escalation.lists(name)
+    }
+
+    /// Whether `skill` is off because its writes would bypass `--allow-paths`,
+    /// `--deny-paths` or the scaffolding boundary
+    fn bypasses_write_paths(&self, skill: &dyn Skill) -> bool {
+        skill.writes_unlisted_files() && (self.write_guard.is_some() || self.boundary.is_some())
     }
 
     pub fn register(&mut self, skill: Box<dyn Skill>) {
@@ -77,24 +369,225 @@ impl SkillRegistry {
         self.skills.get(name)
     }
 
+    /// Whether calls to `name` may run alongside other read-only calls
+    pub fn is_parallel_safe(&self, name: &str) -> bool {
+        self.skills.get(name).is_some_and(|skill| skill.definition().parallel_safe)
+    }
+
+    /// Definitions of the skills permitted in this session
     pub fn list(&self) -> Vec<SkillDefinition> {
-        self.skills.values().map(|s| s.definition()).collect()
+        self.skills
+            .iter()
+            .filter(|(name, s)| self.is_allowed(name) && !self.bypasses_write_paths(s.as_ref()))
+            .map(|(_, s)| s.definition())
+            .collect()
     }
 
     pub async fn execute(&self, name: &str, args: &Value, settings: &Settings) -> Result<String> {
+        self.execute_output(name, args, settings).await.map(|output| output.text)
+    }
+
+    /// Run a skill, keeping the images it returns
+    pub async fn execute_output(&self, name: &str, args: &Value, settings: &Settings) -> Result<SkillOutput> {
+        let span = tracing::info_span!(
+            "skill.execute",
+            skill = name,
+            success = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        let start = Instant::now();
+        let result = self
+            .execute_inner(name, args, settings)
+            .instrument(span.clone())
+            .await;
+        span.record("duration_ms", start.elapsed().as_millis() as u64);
+        span.record("success", result.is_ok());
+        result
+    }
+
+    async fn execute_inner(&self, name: &str, args: &Value, settings: &Settings) -> Result<SkillOutput> {
         let skill = self
             .skills
             .get(name)
             .ok_or_else(|| anyhow::anyhow!("Skill not found: {}", name))?;
 
-        skill.execute(args, settings).await
+        if !self.permissions.is_allowed(name) {
+            anyhow::bail!("Skill '{}' is not permitted in this session", name);
+        }
+
+        let written = written_paths(name, args);
+        self.escalation.check(name, &written)?;
+
+        if let Some(boundary) = &self.boundary {
+            boundary.check_skill(name, args)?;
+        }
+        if self.bypasses_write_paths(skill.as_ref()) {
+            anyhow::bail!(
+                "Skill '{}' writes files its arguments don't name, so it is disabled while \
+                 write paths are restricted",
+                name
+            );
+        }
+
+        if let Some(guard) = &self.write_guard {
+            for path in &written {
+                if let Err(e) = guard.validate_write_path(path) {
+                    AUDIT.log(
+                        AuditEvent::new(
+                            AuditEventType::FileAccessDenied,
+                            AuditSeverity::Warning,
+                            e.to_string(),
+                        )
+                        .with_details(serde_json::json!({ "skill": name, "path": path })),
+                    );
+                    return Err(e);
+                }
+            }
+        }
+
+        let args = self.prepare_args(name, args);
+        if let Some(refusal) = precondition_refusal(name, &args) {
+            return Ok(refusal.into());
+        }
+        if let Some(journal) = self.journal.lock().unwrap().as_ref() {
+            // Without the old content the write couldn't be undone
+            journal.before_write(&written)?;
+        }
+        let mut result = match settings.skill_timeouts.for_skill(name) {
+            Some(limit) => tokio::time::timeout(limit, skill.execute_output(&args, settings))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(anyhow::anyhow!(
+                        "Skill '{}' timed out after {}s and was cancelled \
+                         (raise skill_timeouts.skills.{} in the config to allow longer)",
+                        name,
+                        limit.as_secs(),
+                        name
+                    ))
+                }),
+            None => skill.execute_output(&args, settings).await,
+        };
+        if let Some(journal) = self.journal.lock().unwrap().as_ref() {
+            // What the files are now, so later edits by hand are noticed
+            // even if the journal is never finished
+            if let Err(e) = journal.after_write(&written) {
+                tracing::warn!("Could not record the changed files for undo: {}", e);
+            }
+        }
+
+        if result.is_ok() && name == "read_file" {
+            if let Some(path) = args.get("path").and_then(|p| p.as_str()) {
+                self.remember_read(path);
+            }
+        }
+
+        if result.is_ok() && !written.is_empty() {
+            {
+                let mut edited = self.edited_files.lock().unwrap();
+                for path in &written {
+                    if !edited.iter().any(|f| f == path) {
+                        edited.push(path.clone());
+                    }
+                }
+            }
+
+            // Report failing post_edit hooks (e.g. formatter errors) to the agent
+            if let (Some(runner), Ok(output)) = (&self.hooks, &mut result) {
+                let results = runner.run(HookEvent::PostEdit, &written, settings).await;
+                if let Some(report) = hooks::failure_report(HookEvent::PostEdit, &results) {
+                    output.text.push_str("\n\n");
+                    output.text.push_str(&report);
+                }
+            }
+
+            // After hooks, so a formatter's changes count as ours
+            self.remember_writes(name, args_key(&args), &written);
+        }
+
+        result
+    }
+
+    fn remember_read(&self, path: &str) {
+        if let Some(hash) = content_hash(path) {
+            let seen = SeenFile { hash, applied: Vec::new() };
+            self.seen_files.lock().unwrap().insert(seen_key(path), seen);
+        }
+    }
+
+    /// Advance the hashes of files we just wrote, noting the call that did it
+    /// for files written through `path`
+    fn remember_writes(&self, name: &str, call: String, written: &[String]) {
+        let mut seen_files = self.seen_files.lock().unwrap();
+        for path in written {
+            let Some(seen) = seen_files.get_mut(&seen_key(path)) else {
+                continue;
+            };
+            let Some(hash) = content_hash(path) else {
+                seen_files.remove(&seen_key(path));
+                continue;
+            };
+            if writes_path_argument(name) {
+                seen.applied.push((call.clone(), seen.hash.clone()));
+            }
+            seen.hash = hash;
+        }
+    }
+
+    /// Fill in `changed_files` for `run_tests` from the files edited so far,
+    /// and `expected_hash` for writes to a file read this session. Repeating
+    /// a write already applied expects the hash it applied to, which the file
+    /// no longer has, so it is refused instead of applied twice.
+    fn prepare_args(&self, name: &str, args: &Value) -> Value {
+        let mut args = args.clone();
+        if writes_path_argument(name) && args.get("expected_hash").is_none_or(|h| h.is_null()) {
+            let path = args.get("path").and_then(|p| p.as_str()).map(seen_key);
+            let expected = path.and_then(|path| {
+                let seen_files = self.seen_files.lock().unwrap();
+                let seen = seen_files.get(&path)?;
+                let call = args_key(&args);
+                Some(match seen.applied.iter().find(|(applied, _)| *applied == call) {
+                    Some((_, before)) => before.clone(),
+                    None => seen.hash.clone(),
+                })
+            });
+            if let (Some(expected), Some(obj)) = (expected, args.as_object_mut()) {
+                obj.insert("expected_hash".to_string(), serde_json::json!(expected));
+            }
+        }
+        if name == "run_tests"
+            && self.auto_select_tests.load(Ordering::Relaxed)
+            && args.get("changed_files").is_none()
+            && !args.get("full").and_then(|f| f.as_bool()).unwrap_or(false)
+        {
+            let edited = self.edited_files();
+            if !edited.is_empty() {
+                if let Some(obj) = args.as_object_mut() {
+                    obj.insert("changed_files".to_string(), serde_json::json!(edited));
+                }
+            }
+        }
+        args
+    }
+
+    /// Automatically restrict `run_tests` to the files edited in this session
+    pub fn set_auto_select_tests(&self, enabled: bool) {
+        self.auto_select_tests.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Files written or edited through this registry
+    pub fn edited_files(&self) -> Vec<String> {
+        self.edited_files.lock().unwrap().clone()
+    }
+
+    /// Forget the tracked edited files
+    pub fn clear_edited_files(&self) {
+        self.edited_files.lock().unwrap().clear();
     }
 
     pub fn to_tool_definitions(&self) -> Vec<Value> {
-        self.skills
-            .values()
-            .map(|skill| {
-                let def = skill.definition();
+        self.list()
+            .into_iter()
+            .map(|def| {
                 serde_json::json!({
                     "name": def.name,
                     "description": def.description,
@@ -105,6 +598,44 @@ impl SkillRegistry {
     }
 }
 
+/// Start of the result of a write refused by its `expected_hash`
+const CHANGED_SINCE_READ: &str = "File changed since read";
+
+/// The refusal for a write whose `expected_hash` the file no longer matches
+fn precondition_refusal(name: &str, args: &Value) -> Option<String> {
+    if !writes_path_argument(name) {
+        return None;
+    }
+    let expected = args.get("expected_hash").and_then(|h| h.as_str())?;
+    let path = args.get("path").and_then(|p| p.as_str())?;
+    match content_hash(path) {
+        Some(current) if current.eq_ignore_ascii_case(expected.trim()) => None,
+        Some(current) => Some(format!(
+            "{}: {} has hash {}, not the expected {}. Nothing was written; \
+             read the file again and redo the change against its current content.",
+            CHANGED_SINCE_READ, path, current, expected
+        )),
+        None => Some(format!(
+            "{}: {} no longer exists or can't be read. Nothing was written.",
+            CHANGED_SINCE_READ, path
+        )),
+    }
+}
+
+/// `src/x.rs` and `./src/x.rs` are the same file
+fn seen_key(path: &str) -> String {
+    path.trim_start_matches("./").to_string()
+}
+
+/// A write call as the model made it, to recognize a repeat
+fn args_key(args: &Value) -> String {
+    let mut args = args.clone();
+    if let Some(obj) = args.as_object_mut() {
+        obj.remove("expected_hash");
+    }
+    args.to_string()
+}
+
 impl Default for SkillRegistry {
     fn default() -> Self {
         Self::new()
@@ -136,11 +667,17 @@ impl Skill for EditFileSkillWrapper {
                     "replace": {
                         "type": "string",
                         "description": "Text to replace with"
-                    }
+                    },
+                    "convert_to_utf8": {
+                        "type": "boolean",
+                        "description": "Save as UTF-8 instead of the file's original encoding (default: false)"
+                    },
+                    "expected_hash": expected_hash_param()
                 },
                 "required": ["path", "search", "replace"]
             }),
             requires_confirmation: true,
+            parallel_safe: false,
         }
     }
 
@@ -158,7 +695,13 @@ impl Skill for EditFileSkillWrapper {
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow::anyhow!("Missing replace"))?;
 
-        let skill = super::edit_file::EditFileSkill::new();
+        let convert_to_utf8 = args
+            .get("convert_to_utf8")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let skill =
+            super::edit_file::EditFileSkill::new().with_convert_to_utf8(convert_to_utf8);
         let result = skill.edit_file(path, search, replace)?;
 
         Ok(serde_json::to_string_pretty(&result)?)
@@ -189,6 +732,7 @@ impl Skill for GrepCodebaseSkill {
                 "required": ["pattern"]
             }),
             requires_confirmation: false,
+            parallel_safe: true,
         }
     }
 
@@ -236,6 +780,7 @@ impl Skill for ListSymbolsSkill {
                 "required": ["path"]
             }),
             requires_confirmation: false,
+            parallel_safe: true,
         }
     }
 
@@ -262,6 +807,67 @@ impl Skill for ListSymbolsSkill {
     }
 }
 
+// Build Call Graph Skill
+pub struct BuildCallGraphSkill;
+
+#[async_trait]
+impl Skill for BuildCallGraphSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: "build_call_graph".to_string(),
+            description: "List which functions call which (caller -> callee with line) in Rust or Python files".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path to the source file"
+                    },
+                    "paths": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Additional files whose functions also count as callees (optional, keep small)"
+                    }
+                },
+                "required": ["path"]
+            }),
+            requires_confirmation: false,
+            parallel_safe: true,
+        }
+    }
+
+    async fn execute(&self, args: &Value, _settings: &Settings) -> Result<String> {
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing path"))?;
+        let mut paths = vec![path];
+        if let Some(extra) = args.get("paths").and_then(|v| v.as_array()) {
+            paths.extend(
+                extra
+                    .iter()
+                    .filter_map(|p| p.as_str())
+                    .filter(|p| *p != path),
+            );
+        }
+
+        let current_dir = std::env::current_dir()?;
+        let skill = super::codebase::CodebaseSkill::new(&current_dir);
+        let edges = skill.call_graph_files(&paths)?;
+
+        if edges.is_empty() {
+            return Ok("No calls found".to_string());
+        }
+
+        let output: Vec<String> = edges
+            .iter()
+            .map(|e| format!("{}:{} {} -> {}", e.file, e.line, e.caller, e.callee))
+            .collect();
+
+        Ok(output.join("\n"))
+    }
+}
+
 // Get Project Info Skill
 pub struct GetProjectInfoSkill;
 
@@ -282,6 +888,7 @@ impl Skill for GetProjectInfoSkill {
                 }
             }),
             requires_confirmation: false,
+            parallel_safe: true,
         }
     }
 
@@ -294,3 +901,354 @@ impl Skill for GetProjectInfoSkill {
         Ok(info.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_tests_receives_edited_files() {
+        let registry = SkillRegistry::new();
+        registry
+            .edited_files
+            .lock()
+            .unwrap()
+            .push("src/lib.rs".to_string());
+
+        // Disabled by default
+        let args = registry.prepare_args("run_tests", &serde_json::json!({}));
+        assert!(args.get("changed_files").is_none());
+
+        registry.set_auto_select_tests(true);
+        let args = registry.prepare_args("run_tests", &serde_json::json!({}));
+        assert_eq!(args["changed_files"], serde_json::json!(["src/lib.rs"]));
+
+        // Explicit full run and other skills are left untouched
+        let args = registry.prepare_args("run_tests", &serde_json::json!({"full": true}));
+        assert!(args.get("changed_files").is_none());
+        let args = registry.prepare_args("read_file", &serde_json::json!({"path": "a"}));
+        assert!(args.get("changed_files").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_denied_skill_hidden_and_rejected() {
+        let mut registry = SkillRegistry::new();
+        registry.restrict(&SkillPermissions::new(
+            vec!["read_file".to_string(), "execute_command".to_string()],
+            vec!["execute_command".to_string()],
+        ));
+
+        let names: Vec<String> = registry.list().into_iter().map(|d| d.name).collect();
+        assert_eq!(names, vec!["read_file".to_string()]);
+        assert_eq!(registry.to_tool_definitions().len(), 1);
+
+        // The model calls it anyway
+        let err = registry
+            .execute(
+                "execute_command",
+                &serde_json::json!({"command": "echo hi"}),
+                &Settings::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not permitted"));
+    }
+
+    #[tokio::test]
+    async fn test_crew_grant_allows_only_the_granted_path() {
+        // write_file only writes inside the working directory
+        let tmp = tempfile::TempDir::new_in("target").unwrap();
+        let report = tmp.path().join("review.md").to_string_lossy().to_string();
+        let sibling = tmp.path().join("notes.md").to_string_lossy().to_string();
+
+        let mut crew = Crew::new("reviewer", "Code Reviewer", "Reviews code", "Review.");
+        crew.permissions.file_write = false;
+        let mut registry = SkillRegistry::new();
+        registry.restrict_to_crew(&crew);
+        registry.set_confirmer(Some(Arc::new(|_: &str| true)));
+        let settings = Settings::default();
+        let write = |path: &str| serde_json::json!({"path": path, "content": "LGTM"});
+
+        let names: Vec<String> = registry.list().into_iter().map(|d| d.name).collect();
+        assert!(names.contains(&REQUEST_PERMISSION_SKILL.to_string()));
+        assert!(!names.contains(&"write_file".to_string()));
+        let err = registry.execute("write_file", &write(&report), &settings).await.unwrap_err();
+        assert!(err.to_string().contains("request_permission"));
+
+        let reply = registry
+            .execute(
+                REQUEST_PERMISSION_SKILL,
+                &serde_json::json!({
+                    "capability": "write_file",
+                    "path": report,
+                    "justification": "save the review"
+                }),
+                &settings,
+            )
+            .await
+            .unwrap();
+        assert!(reply.starts_with("Granted"));
+
+        registry.execute("write_file", &write(&report), &settings).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&report).unwrap(), "LGTM");
+        assert!(registry.execute("write_file", &write(&sibling), &settings).await.is_err());
+        assert!(!std::path::Path::new(&sibling).exists());
+        assert_eq!(registry.escalation().grants().len(), 1);
+
+        // Without a crew there is nothing to escalate
+        assert!(!SkillRegistry::new().is_allowed(REQUEST_PERMISSION_SKILL));
+    }
+
+    #[tokio::test]
+    async fn test_write_paths_allowlist() {
+        let mut registry = SkillRegistry::new();
+        registry.set_write_paths(vec!["src/**".to_string()], Vec::new());
+        let guard = registry.write_guard.as_ref().unwrap();
+
+        assert!(guard.validate_write_path("src/x.rs").is_ok());
+
+        let err = registry
+            .execute(
+                "write_file",
+                &serde_json::json!({"path": "README.md", "content": "overwritten"}),
+                &Settings::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("outside --allow-paths (src/**)"));
+        assert!(AUDIT
+            .events_by_type(AuditEventType::FileAccessDenied)
+            .iter()
+            .any(|e| e.message.contains("README.md")));
+    }
+
+    #[tokio::test]
+    async fn test_write_paths_cover_patch_renames_and_mode_changes() {
+        let rename = "diff --git a/src/lib.rs b/docs/lib.rs\nsimilarity index 100%\nrename from src/lib.rs\nrename to docs/lib.rs\n";
+        let mode = "diff --git a/README.md b/README.md\nold mode 100644\nnew mode 100755\n";
+        let args = |patch: &str| serde_json::json!({"patch_text": patch});
+        assert_eq!(written_paths("apply_patch", &args(rename)), ["docs/lib.rs", "src/lib.rs"]);
+        assert_eq!(written_paths("git_apply", &serde_json::json!({"patch": mode})), ["README.md"]);
+
+        let mut registry = SkillRegistry::new();
+        registry.set_write_paths(vec!["src/**".to_string()], Vec::new());
+        for patch in [rename, mode] {
+            let err = registry
+                .execute("apply_patch", &args(patch), &Settings::default())
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("outside --allow-paths (src/**)"), "{}", err);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_post_edit_hook_receives_edited_path() {
+        use crate::core::hooks::HooksConfig;
+
+        // Hooks run inside the current directory, like every shell command
+        let tmp = tempfile::TempDir::new_in("target").unwrap();
+        let file = tmp.path().join("main.rs");
+        std::fs::write(&file, "fn main() {}\n").unwrap();
+        let path = file.to_string_lossy().to_string();
+
+        let config = HooksConfig {
+            post_edit: vec!["printf '%s\\n' >> hook.log".to_string(), "false".to_string()],
+            ..Default::default()
+        };
+        let registry = SkillRegistry::new().with_hooks(HookRunner::new(config, tmp.path()));
+
+        let output = registry
+            .execute(
+                "edit_file",
+                &serde_json::json!({"path": path, "search": "main", "replace": "start"}),
+                &Settings::default(),
+            )
+            .await
+            .unwrap();
+
+        let log = std::fs::read_to_string(tmp.path().join("hook.log")).unwrap();
+        assert_eq!(log.trim(), path);
+        // The failing hook is reported back in the tool result
+        assert!(output.contains("[post_edit hook]"));
+        assert!(output.contains("`false` failed"));
+    }
+
+    #[test]
+    fn test_validate_args() {
+        let registry = SkillRegistry::new();
+        let read_file = registry.get("read_file").unwrap().definition();
+
+        assert!(read_file.validate_args(&serde_json::json!({"path": "a.rs"})).is_ok());
+        let err = read_file.validate_args(&serde_json::json!({})).unwrap_err();
+        assert!(err.to_string().contains("Missing required parameter 'path'"));
+        let err = read_file.validate_args(&serde_json::json!({"path": 3})).unwrap_err();
+        assert!(err.to_string().contains("must be of type string"));
+        assert!(read_file.validate_args(&serde_json::json!(["a.rs"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_direct_git_status() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::process::Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(tmp.path())
+            .status()
+            .unwrap();
+        std::fs::write(tmp.path().join("new.txt"), "hi").unwrap();
+
+        let registry = SkillRegistry::new();
+        let args = serde_json::json!({"path": tmp.path().to_string_lossy()});
+        registry.get("git_status").unwrap().definition().validate_args(&args).unwrap();
+        let output = registry
+            .execute("git_status", &args, &Settings::default())
+            .await
+            .unwrap();
+        assert!(output.contains("new.txt"));
+    }
+
+    struct SlowSkill;
+
+    #[async_trait]
+    impl Skill for SlowSkill {
+        fn definition(&self) -> SkillDefinition {
+            SkillDefinition {
+                name: "slow".to_string(),
+                description: "Sleeps for an hour".to_string(),
+                parameters: serde_json::json!({"type": "object", "properties": {}}),
+                requires_confirmation: false,
+                parallel_safe: false,
+            }
+        }
+
+        async fn execute(&self, _args: &Value, _settings: &Settings) -> Result<String> {
+            tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+            Ok("done".to_string())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_skill_cancelled_at_timeout() {
+        let mut registry = SkillRegistry::new();
+        registry.register(Box::new(SlowSkill));
+        let mut settings = Settings::default();
+        settings.skill_timeouts.skills.insert("slow".to_string(), 5);
+
+        let start = tokio::time::Instant::now();
+        let err = registry
+            .execute("slow", &serde_json::json!({}), &settings)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Skill 'slow' timed out after 5s"));
+        assert_eq!(start.elapsed().as_secs(), 5);
+
+        // 0 disables the limit
+        settings.skill_timeouts.skills.insert("slow".to_string(), 0);
+        assert_eq!(settings.skill_timeouts.for_skill("slow"), None);
+        assert_eq!(
+            settings.skill_timeouts.for_skill("read_file"),
+            Some(std::time::Duration::from_secs(120))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_task_skills_crud_and_parallel_updates() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let registry = SkillRegistry::new();
+        let run_dir = crate::skills::run_dir(tmp.path(), "run-1");
+        registry.tasks().start_run(&run_dir);
+        let settings = Settings::default();
+
+        let added = registry
+            .execute(
+                "task_add",
+                &serde_json::json!({"description": "Write parser", "priority": "high"}),
+                &settings,
+            )
+            .await
+            .unwrap();
+        assert_eq!(added, "Added t1 [pending] (high) Write parser");
+
+        // Parallel tool calls in one turn must not lose writes
+        let adds = (0..10).map(|i| {
+            let args = serde_json::json!({"description": format!("step {}", i)});
+            let registry = &registry;
+            let settings = &settings;
+            async move { registry.execute("task_add", &args, settings).await }
+        });
+        for result in futures::future::join_all(adds).await {
+            result.unwrap();
+        }
+
+        let updated = registry
+            .execute(
+                "task_update",
+                &serde_json::json!({"id": "t1", "status": "blocked", "note": "needs spec"}),
+                &settings,
+            )
+            .await
+            .unwrap();
+        assert!(updated.contains("t1 [blocked] (high) Write parser — needs spec"));
+        assert!(registry
+            .execute("task_update", &serde_json::json!({"id": "t1", "status": "gone"}), &settings)
+            .await
+            .is_err());
+
+        let listed = registry
+            .execute("task_list", &serde_json::json!({}), &settings)
+            .await
+            .unwrap();
+        assert!(listed.starts_with("0 done, 11 remaining"));
+
+        let saved: crate::skills::TaskList =
+            serde_json::from_str(&std::fs::read_to_string(run_dir.join("tasks.json")).unwrap())
+                .unwrap();
+        let mut ids: Vec<String> = saved.tasks.iter().map(|t| t.id.clone()).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 11);
+        assert_eq!(saved.tasks[0].note.as_deref(), Some("needs spec"));
+    }
+
+    #[tokio::test]
+    async fn test_write_preconditions() {
+        let tmp = tempfile::TempDir::new_in("target").unwrap();
+        let path = tmp.path().join("lib.rs").to_string_lossy().to_string();
+        std::fs::write(&path, "fn a() {}\n").unwrap();
+        let registry = SkillRegistry::new();
+        let settings = Settings::default();
+        let edit = |search: &str, replace: &str| serde_json::json!({"path": path, "search": search, "replace": replace});
+
+        // An explicit matching hash applies; a stale one is refused
+        let mut args = edit("fn a", "fn b");
+        args["expected_hash"] = serde_json::json!(content_hash(&path).unwrap());
+        let out = registry.execute("edit_file", &args, &settings).await.unwrap();
+        assert!(out.contains("\"success\": true"), "{}", out);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn b() {}\n");
+        let out = registry.execute("edit_file", &args, &settings).await.unwrap();
+        assert!(out.starts_with("File changed since read:"), "{}", out);
+
+        // Hashes from read_file are passed on: an outside change is refused
+        registry.execute("read_file", &serde_json::json!({"path": path}), &settings).await.unwrap();
+        std::fs::write(&path, "fn c() {}\n").unwrap();
+        let out = registry.execute("edit_file", &edit("fn c", "fn d"), &settings).await.unwrap();
+        assert!(out.starts_with("File changed since read:"), "{}", out);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn c() {}\n");
+
+        // After a fresh read, successive edits apply, but replaying one doesn't
+        registry.execute("read_file", &serde_json::json!({"path": path}), &settings).await.unwrap();
+        let append = edit("{}\n", "{}\n// one\n");
+        registry.execute("edit_file", &append, &settings).await.unwrap();
+        registry.execute("edit_file", &edit("fn c", "fn e"), &settings).await.unwrap();
+        let out = registry.execute("edit_file", &append, &settings).await.unwrap();
+        assert!(out.starts_with("File changed since read:"), "{}", out);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn e() {}\n// one\n");
+
+        // Files never read are written as before
+        let fresh = tmp.path().join("new.rs").to_string_lossy().to_string();
+        let write = serde_json::json!({"path": fresh, "content": "x"});
+        registry.execute("write_file", &write, &settings).await.unwrap();
+        registry.execute("write_file", &write, &settings).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&fresh).unwrap(), "x");
+    }
+}
@@ -0,0 +1,554 @@
+// ============================================
+// Shell Sandbox Backend
+// Runs risky commands inside a throwaway docker/podman container
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::config::{SandboxFallback, SandboxKind, ShellConfig};
+use crate::core::CommandRisk;
+use crate::indexer::{ProjectDetector, ProjectType};
+
+/// Mount point of the working directory inside the container
+pub const CONTAINER_WORKDIR: &str = "/workspace";
+
+lazy_static! {
+    static ref RUNTIME_CACHE: Mutex<HashMap<&'static str, bool>> = Mutex::new(HashMap::new());
+}
+
+/// Container invocation for a single command
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerSpec {
+    pub runtime: &'static str,
+    pub image: String,
+    pub host_dir: PathBuf,
+    /// Directory the command starts in, relative to `host_dir`
+    pub subdir: PathBuf,
+    pub read_only: bool,
+    pub network: bool,
+    pub cpus: String,
+    pub memory: String,
+    pub name: String,
+}
+
+impl ContainerSpec {
+    /// Arguments for `<runtime> run ...` that execute `command` via `sh -c`
+    pub fn args(&self, command: &str) -> Vec<String> {
+        let mount = format!(
+            "{}:{}{}",
+            self.host_dir.display(),
+            CONTAINER_WORKDIR,
+            if self.read_only { ":ro" } else { "" }
+        );
+        let mut args: Vec<String> = vec![
+            "run".into(),
+            "--rm".into(),
+            "-i".into(),
+            "--name".into(),
+            self.name.clone(),
+            "--network".into(),
+            if self.network { "bridge".into() } else { "none".into() },
+            "--cpus".into(),
+            self.cpus.clone(),
+            "--memory".into(),
+            self.memory.clone(),
+            "-v".into(),
+            mount,
+            "-w".into(),
+            self.workdir(),
+        ];
+        args.push(self.image.clone());
+        args.extend(["sh".into(), "-c".into(), command.to_string()]);
+        args
+    }
+
+    /// `CONTAINER_WORKDIR` plus `subdir`, with forward slashes
+    fn workdir(&self) -> String {
+        std::iter::once(CONTAINER_WORKDIR.to_string())
+            .chain(self.subdir.components().map(|c| c.as_os_str().to_string_lossy().to_string()))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} ({}), network: {}, mount: {}",
+            self.runtime,
+            self.image,
+            if self.network { "on" } else { "off" },
+            if self.read_only { "ro" } else { "rw" }
+        )
+    }
+}
+
+/// Where a command will run
+#[derive(Debug, Clone, PartialEq)]
+pub enum Backend {
+    Host,
+    Container(ContainerSpec),
+}
+
+/// Output of a command, identical in shape for host and container runs
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        !self.timed_out && self.exit_code == Some(0)
+    }
+}
+
+pub fn runtime_binary(kind: SandboxKind) -> Option<&'static str> {
+    match kind {
+        SandboxKind::None => None,
+        SandboxKind::Docker => Some("docker"),
+        SandboxKind::Podman => Some("podman"),
+    }
+}
+
+/// Whether the container runtime can be executed (checked once per process)
+pub fn runtime_available(kind: SandboxKind) -> bool {
+    let Some(binary) = runtime_binary(kind) else {
+        return false;
+    };
+    let mut cache = RUNTIME_CACHE.lock().unwrap();
+    *cache.entry(binary).or_insert_with(|| {
+        std::process::Command::new(binary)
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    })
+}
+
+/// Slim image for the project's language
+pub fn default_image(project_type: &ProjectType) -> &'static str {
+    match project_type {
+        ProjectType::Rust => "rust:slim",
+        ProjectType::Python => "python:3.12-slim",
+        ProjectType::JavaScript | ProjectType::TypeScript => "node:20-slim",
+        ProjectType::Go => "golang:1.22",
+        ProjectType::Java | ProjectType::Kotlin => "eclipse-temurin:21",
+        ProjectType::Ruby => "ruby:3.3-slim",
+        ProjectType::Php => "php:8.3-cli",
+        ProjectType::CSharp => "mcr.microsoft.com/dotnet/sdk:8.0",
+        _ => "debian:bookworm-slim",
+    }
+}
+
+/// `dir` (relative to `cwd`) resolved through symlinks and `..`, as a path
+/// relative to `cwd`; anything outside `cwd` is refused
+pub fn resolve_working_dir(cwd: &Path, dir: Option<&str>) -> Result<PathBuf> {
+    let Some(dir) = dir else { return Ok(PathBuf::new()) };
+    let root = cwd.canonicalize().context("Cannot resolve the current directory")?;
+    let resolved = root
+        .join(dir)
+        .canonicalize()
+        .with_context(|| format!("Working directory {} does not exist", dir))?;
+    match resolved.strip_prefix(&root) {
+        Ok(relative) => Ok(relative.to_path_buf()),
+        Err(_) => bail!("🛡️ Working directory {} is outside {}", dir, root.display()),
+    }
+}
+
+/// Choose host or container execution for a command of the given risk
+pub fn select_backend(
+    config: &ShellConfig,
+    risk: &CommandRisk,
+    runtime_available: bool,
+    network_requested: bool,
+    host_dir: &Path,
+) -> Result<Backend> {
+    let Some(runtime) = runtime_binary(config.sandbox) else {
+        return Ok(Backend::Host);
+    };
+
+    // Read-only commands stay on the host for speed
+    if matches!(risk, CommandRisk::Low) {
+        return Ok(Backend::Host);
+    }
+
+    if !runtime_available {
+        return match config.fallback {
+            SandboxFallback::Host => {
+                tracing::warn!("{} not found, running command on the host", runtime);
+                Ok(Backend::Host)
+            }
+            SandboxFallback::Deny => bail!(
+                "🛡️ SANDBOX: container runtime '{}' not found; refusing to run on the host \
+                (install {} or set shell.fallback = \"host\")",
+                runtime,
+                runtime
+            ),
+        };
+    }
+
+    let image = match &config.image {
+        Some(image) => image.clone(),
+        None => {
+            let project_type = ProjectDetector::new(host_dir)
+                .detect()
+                .map(|info| info.project_type)
+                .unwrap_or(ProjectType::Unknown);
+            default_image(&project_type).to_string()
+        }
+    };
+
+    Ok(Backend::Container(ContainerSpec {
+        runtime,
+        image,
+        host_dir: host_dir.to_path_buf(),
+        subdir: PathBuf::new(),
+        read_only: config.read_only,
+        network: network_requested && config.allow_network,
+        cpus: config.cpus.clone(),
+        memory: config.memory.clone(),
+        name: format!("webrana-{}", uuid::Uuid::new_v4().simple()),
+    }))
+}
+
+/// Stops a container when its run ends without the command finishing: on
+/// `shell.timeout_secs`, or when the caller drops the future (a skill
+/// timeout, a run deadline). `kill_on_drop` only reaches the runtime
+/// client, not the container it started.
+struct ContainerKill<'a> {
+    spec: Option<&'a ContainerSpec>,
+}
+
+impl ContainerKill<'_> {
+    fn disarm(&mut self) {
+        self.spec = None;
+    }
+}
+
+impl Drop for ContainerKill<'_> {
+    fn drop(&mut self) {
+        // Dropped on an async worker: reap the kill elsewhere instead of
+        // waiting for the runtime to answer
+        if let Some(spec) = self.spec {
+            let kill = std::process::Command::new(spec.runtime)
+                .args(["kill", &spec.name])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+            if let Ok(mut kill) = kill {
+                std::thread::spawn(move || kill.wait());
+            }
+        }
+    }
+}
+
+/// Run `command` on the chosen backend with the same timeout and output handling
+pub async fn run_command(
+    backend: &Backend,
+    command: &str,
+    working_dir: Option<&Path>,
+    timeout_secs: u64,
+) -> Result<CommandOutput> {
+    let mut cmd = match backend {
+        Backend::Host => {
+            let mut c = if cfg!(target_os = "windows") {
+                let mut c = Command::new("cmd");
+                c.args(["/C", command]);
+                c
+            } else {
+                let mut c = Command::new("sh");
+                c.args(["-c", command]);
+                c
+            };
+            if let Some(dir) = working_dir {
+                c.current_dir(dir);
+            }
+            c
+        }
+        Backend::Container(spec) => {
+            let mut c = Command::new(spec.runtime);
+            c.args(spec.args(command));
+            c
+        }
+    };
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let child = cmd.spawn().context("Failed to execute command")?;
+    let mut kill = ContainerKill {
+        spec: match backend {
+            Backend::Container(spec) => Some(spec),
+            Backend::Host => None,
+        },
+    };
+    let output = child.wait_with_output();
+
+    let result = if timeout_secs > 0 {
+        tokio::time::timeout(Duration::from_secs(timeout_secs), output).await
+    } else {
+        Ok(output.await)
+    };
+
+    match result {
+        Ok(output) => {
+            kill.disarm();
+            let output = output.context("Failed to execute command")?;
+            Ok(CommandOutput {
+                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+                exit_code: output.status.code(),
+                timed_out: false,
+            })
+        }
+        // `kill` stops the container when it goes out of scope
+        Err(_) => {
+            Ok(CommandOutput {
+                stdout: String::new(),
+                stderr: format!("Command timed out after {}s", timeout_secs),
+                exit_code: None,
+                timed_out: true,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn docker_config() -> ShellConfig {
+        ShellConfig {
+            sandbox: SandboxKind::Docker,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_backend_selection() {
+        let tmp = TempDir::new().unwrap();
+        let medium = CommandRisk::Medium("build".to_string());
+
+        // No sandbox configured
+        let backend = select_backend(&ShellConfig::default(), &medium, true, false, tmp.path());
+        assert_eq!(backend.unwrap(), Backend::Host);
+
+        // Low risk stays on the host
+        let backend = select_backend(&docker_config(), &CommandRisk::Low, true, false, tmp.path());
+        assert_eq!(backend.unwrap(), Backend::Host);
+
+        // Medium risk goes to the container
+        let backend = select_backend(&docker_config(), &medium, true, false, tmp.path()).unwrap();
+        assert!(matches!(backend, Backend::Container(ref spec) if spec.runtime == "docker"));
+
+        // Missing runtime: deny by default, host when configured
+        assert!(select_backend(&docker_config(), &medium, false, false, tmp.path()).is_err());
+        let fallback = ShellConfig {
+            fallback: SandboxFallback::Host,
+            ..docker_config()
+        };
+        let backend = select_backend(&fallback, &medium, false, false, tmp.path());
+        assert_eq!(backend.unwrap(), Backend::Host);
+    }
+
+    #[test]
+    fn test_image_and_network_policy() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("Cargo.toml"), "[package]\nname = \"x\"\n").unwrap();
+        let high = CommandRisk::High("rm".to_string());
+
+        let Backend::Container(spec) =
+            select_backend(&docker_config(), &high, true, true, tmp.path()).unwrap()
+        else {
+            panic!("expected container backend");
+        };
+        assert_eq!(spec.image, "rust:slim");
+        // Network requested but not allowed by policy
+        assert!(!spec.network);
+
+        let config = ShellConfig {
+            allow_network: true,
+            image: Some("alpine:3".to_string()),
+            ..docker_config()
+        };
+        let Backend::Container(spec) = select_backend(&config, &high, true, true, tmp.path()).unwrap()
+        else {
+            panic!("expected container backend");
+        };
+        assert_eq!(spec.image, "alpine:3");
+        assert!(spec.network);
+    }
+
+    #[test]
+    fn test_container_args() {
+        let spec = ContainerSpec {
+            runtime: "podman",
+            image: "python:3.12-slim".to_string(),
+            host_dir: PathBuf::from("/home/dev/project"),
+            subdir: PathBuf::new(),
+            read_only: true,
+            network: false,
+            cpus: "1.5".to_string(),
+            memory: "512m".to_string(),
+            name: "webrana-test".to_string(),
+        };
+        let args = spec.args("pytest -q");
+
+        assert_eq!(&args[..3], &["run", "--rm", "-i"]);
+        let joined = args.join(" ");
+        assert!(joined.contains("--network none"));
+        assert!(joined.contains("--cpus 1.5"));
+        assert!(joined.contains("--memory 512m"));
+        assert!(joined.contains("-v /home/dev/project:/workspace:ro"));
+        assert!(joined.contains("-w /workspace"));
+        assert_eq!(
+            &args[args.len() - 4..],
+            &["python:3.12-slim", "sh", "-c", "pytest -q"]
+        );
+
+        let rw = ContainerSpec {
+            read_only: false,
+            network: true,
+            ..spec
+        };
+        let joined = rw.args("ls").join(" ");
+        assert!(joined.contains("-v /home/dev/project:/workspace -w"));
+        assert!(joined.contains("--network bridge"));
+
+        let nested = ContainerSpec {
+            subdir: PathBuf::from("crates/core"),
+            ..rw
+        };
+        let joined = nested.args("ls").join(" ");
+        assert!(joined.contains("-v /home/dev/project:/workspace -w /workspace/crates/core"), "{}", joined);
+    }
+
+    #[test]
+    fn test_working_dir_stays_inside_cwd() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path().join("project");
+        std::fs::create_dir_all(root.join("src/nested")).unwrap();
+
+        assert_eq!(resolve_working_dir(&root, None).unwrap(), PathBuf::new());
+        assert_eq!(resolve_working_dir(&root, Some("src/./nested")).unwrap(), PathBuf::from("src/nested"));
+        assert_eq!(resolve_working_dir(&root, Some("src/..")).unwrap(), PathBuf::new());
+        assert!(resolve_working_dir(&root, Some("..")).is_err());
+        assert!(resolve_working_dir(&root, Some(tmp.path().to_str().unwrap())).is_err());
+        assert!(resolve_working_dir(&root, Some("/")).is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(tmp.path(), root.join("escape")).unwrap();
+            assert!(resolve_working_dir(&root, Some("escape")).is_err());
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_host_exit_code_and_timeout() {
+        let output = run_command(&Backend::Host, "echo out; echo err >&2; exit 3", None, 10)
+            .await
+            .unwrap();
+        assert_eq!(output.stdout.trim(), "out");
+        assert_eq!(output.stderr.trim(), "err");
+        assert_eq!(output.exit_code, Some(3));
+
+        let output = run_command(&Backend::Host, "sleep 5", None, 1).await.unwrap();
+        assert!(output.timed_out);
+        assert!(!output.success());
+    }
+
+    /// A fake runtime that records `kill`: cancelling the run by dropping
+    /// its future, as a skill timeout or run deadline does, stops the
+    /// container as well as the client
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancelled_run_kills_the_container() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = TempDir::new().unwrap();
+        let killed = tmp.path().join("killed");
+        let runtime = tmp.path().join("fake-runtime");
+        std::fs::write(
+            &runtime,
+            format!(
+                "#!/bin/sh\nif [ \"$1\" = kill ]; then sleep 1; echo \"$2\" >> '{}'; exit 0; fi\nsleep 30\n",
+                killed.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&runtime, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let spec = ContainerSpec {
+            runtime: Box::leak(runtime.to_string_lossy().into_owned().into_boxed_str()),
+            image: "alpine:3".to_string(),
+            host_dir: tmp.path().to_path_buf(),
+            subdir: PathBuf::new(),
+            read_only: true,
+            network: false,
+            cpus: "1".to_string(),
+            memory: "256m".to_string(),
+            name: "webrana-cancelled".to_string(),
+        };
+        let backend = Backend::Container(spec);
+
+        let start = std::time::Instant::now();
+        let cancelled =
+            tokio::time::timeout(Duration::from_millis(300), run_command(&backend, "sleep 30", None, 0)).await;
+        assert!(cancelled.is_err());
+        // The slow kill is not waited for
+        assert!(start.elapsed() < Duration::from_secs(1));
+        while !killed.exists() && start.elapsed() < Duration::from_secs(10) {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(std::fs::read_to_string(&killed).unwrap().trim(), "webrana-cancelled");
+    }
+
+    /// Runs only with WEBRANA_DOCKER_TESTS=1 and a working docker install
+    #[tokio::test]
+    async fn test_docker_matches_host() {
+        if std::env::var("WEBRANA_DOCKER_TESTS").is_err() || !runtime_available(SandboxKind::Docker) {
+            return;
+        }
+        let tmp = TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("input.txt"), "hello").unwrap();
+        let config = ShellConfig {
+            image: Some("alpine:3".to_string()),
+            read_only: true,
+            ..docker_config()
+        };
+        let medium = CommandRisk::Medium("test".to_string());
+        let backend = select_backend(&config, &medium, true, false, tmp.path()).unwrap();
+        let command = "cat input.txt; echo err >&2; exit 4";
+
+        let container = run_command(&backend, command, None, 120).await.unwrap();
+        let host = run_command(&Backend::Host, command, Some(tmp.path()), 120)
+            .await
+            .unwrap();
+        assert_eq!(container.stdout, host.stdout);
+        assert_eq!(container.stderr, host.stderr);
+        assert_eq!(container.exit_code, host.exit_code);
+
+        // Read-only mount and no network
+        let write = run_command(&backend, "touch new.txt", None, 120).await.unwrap();
+        assert!(!write.success());
+        let net = run_command(&backend, "wget -q -T 3 http://example.com", None, 120)
+            .await
+            .unwrap();
+        assert!(!net.success());
+
+        let timeout = run_command(&backend, "sleep 30", None, 2).await.unwrap();
+        assert!(timeout.timed_out);
+    }
+}
@@ -4,17 +4,34 @@
 // Created by: SYNAPSE (Team Beta)
 // ============================================
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
+use tracing::Instrument;
 
 use crate::embeddings::{
-    EmbeddingProvider, EmbeddingStore, MockEmbeddingProvider,
-    OpenAIEmbeddings, SearchResult, StoredEmbedding,
+    language_for_extension, EmbeddingProvider, EmbeddingStore, MetadataFilter,
+    MockEmbeddingProvider, OpenAIEmbeddings, SearchResult, StoredEmbedding,
 };
-use crate::indexer::FileWalker;
+use crate::indexer::{encoding, markdown, BlockKind, FileWalker, Notebook};
+use crate::llm::{RagConfig, RagContext};
+use crate::skills::symbol_spans;
+use crate::util::{dir_key, sha256_hex};
+
+/// Minimum extra throttling between indexing progress messages
+const THROTTLE_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `<data_dir>/indexes/project-<hash>.json`, where `webrana index` keeps the
+/// index of the project at `root`
+pub fn project_index_path(root: &Path) -> Option<PathBuf> {
+    let root = root.canonicalize().ok()?;
+    let key = dir_key(&root);
+    directories::ProjectDirs::from("dev", "webrana", "webrana-cli")
+        .map(|dirs| dirs.data_dir().join("indexes").join(format!("project-{}.json", key)))
+}
 
 /// Semantic search configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,7 +62,9 @@ pub struct SemanticSearch {
     provider: Arc<dyn EmbeddingProvider>,
     store: EmbeddingStore,
     config: SemanticSearchConfig,
-    indexed_files: HashMap<String, u64>, // file path -> last modified timestamp
+    indexed_files: HashMap<String, IndexedFile>,
+    /// Record what happened to each file in `IndexStats::file_results`
+    file_results: bool,
 }
 
 impl SemanticSearch {
@@ -59,6 +78,7 @@ impl SemanticSearch {
             store: EmbeddingStore::new(dimension),
             config,
             indexed_files: HashMap::new(),
+            file_results: false,
         }
     }
 
@@ -72,165 +92,447 @@ impl SemanticSearch {
             store: EmbeddingStore::new(dimension),
             config,
             indexed_files: HashMap::new(),
+            file_results: false,
+        }
+    }
+
+    /// Create with any embedding provider
+    pub fn with_provider(
+        provider: Arc<dyn EmbeddingProvider>,
+        config: SemanticSearchConfig,
+    ) -> Self {
+        let dimension = provider.dimension();
+
+        Self {
+            provider,
+            store: EmbeddingStore::new(dimension),
+            config,
+            indexed_files: HashMap::new(),
+            file_results: false,
         }
     }
 
+    /// Also report each file's outcome (`IndexStats::file_results`)
+    pub fn with_file_results(mut self, enabled: bool) -> Self {
+        self.file_results = enabled;
+        self
+    }
+
     /// Index a directory
     pub async fn index_directory(&mut self, dir: &Path) -> Result<IndexStats> {
-        let mut stats = IndexStats::default();
+        self.index_directory_with_progress(dir, &mut |_, _| {}).await
+    }
 
-        // Walk directory and find code files
-        let walker = FileWalker::new(dir);
-        let files = walker.walk()?;
+    /// `index_directory`, calling `on_progress(done, total)` as files are
+    /// processed. The total is known once the walk is over.
+    pub async fn index_directory_with_progress(
+        &mut self,
+        dir: &Path,
+        on_progress: &mut (dyn FnMut(usize, Option<usize>) + Send),
+    ) -> Result<IndexStats> {
+        let span = tracing::info_span!(
+            "index.build",
+            files = tracing::field::Empty,
+            chunks = tracing::field::Empty,
+            errors = tracing::field::Empty,
+        );
+        let stats = self
+            .index_files(dir, on_progress)
+            .instrument(span.clone())
+            .await?;
+        span.record("files", stats.files as u64);
+        span.record("chunks", stats.chunks as u64);
+        span.record("errors", stats.errors as u64);
+        Ok(stats)
+    }
+
+    async fn index_files(
+        &mut self,
+        dir: &Path,
+        on_progress: &mut (dyn FnMut(usize, Option<usize>) + Send),
+    ) -> Result<IndexStats> {
+        let mut stats = IndexStats {
+            file_results: self.file_results.then(Vec::new),
+            ..Default::default()
+        };
+        let throttled_at_start = self.provider.throttled();
+        let mut reported_throttle = Duration::ZERO;
 
         let code_extensions = [
             "rs", "py", "js", "ts", "go", "java", "cpp", "c", "h", "rb", "php",
-            "swift", "kt", "scala", "md", "txt", "json", "yaml", "toml",
+            "swift", "kt", "scala", "md", "txt", "json", "yaml", "toml", "ipynb",
         ];
 
-        for entry in files {
-            let path = std::path::Path::new(&entry.path);
-            
-            // Skip non-code files
-            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-            if !code_extensions.contains(&ext) {
+        // Index code files as the walk finds them; walker paths are relative
+        // to `dir`. A new file with the content of an indexed one may be a
+        // move, which only the finished walk can tell, so it waits.
+        let indexed_hashes: HashSet<String> =
+            self.indexed_files.values().map(|file| file.hash.clone()).collect();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut maybe_moved: Vec<NewFile> = Vec::new();
+        let walk = FileWalker::new(dir)
+            .walk_stream()
+            .filter(|entry| {
+                let ext = Path::new(&entry.path).extension().and_then(|e| e.to_str()).unwrap_or("");
+                code_extensions.contains(&ext)
+            })
+            .map(|entry| dir.join(&entry.path).to_string_lossy().to_string());
+        for path_str in walk {
+            on_progress(seen.len(), None);
+            seen.insert(path_str.clone());
+            let Some(file) = self.changed_file(dir, path_str, &mut stats) else {
+                continue;
+            };
+            if !self.indexed_files.contains_key(&file.path) && indexed_hashes.contains(&file.hash) {
+                maybe_moved.push(file);
                 continue;
             }
+            self.embed_file(file, &mut stats).await;
+            self.note_throttle(&mut stats, throttled_at_start, &mut reported_throttle);
+        }
+        let total = seen.len();
 
-            // Check if file needs re-indexing
-            let modified = std::fs::metadata(&path)
-                .and_then(|m| m.modified())
-                .ok()
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs())
-                .unwrap_or(0);
-
-            let path_str = path.to_string_lossy().to_string();
-            
-            if let Some(&cached_time) = self.indexed_files.get(&path_str) {
-                if cached_time >= modified {
-                    stats.skipped += 1;
+        // Indexed files under `dir` that are gone now, by content hash. A new
+        // file with the same content (and no other claimant) was moved there.
+        let gone: Vec<String> = self
+            .indexed_files
+            .keys()
+            .filter(|p| Path::new(p).starts_with(dir) && !seen.contains(p.as_str()))
+            .cloned()
+            .collect();
+        let mut gone_by_hash: HashMap<String, Vec<String>> = HashMap::new();
+        for path in &gone {
+            gone_by_hash
+                .entry(self.indexed_files[path].hash.clone())
+                .or_default()
+                .push(path.clone());
+        }
+        let mut new_by_hash: HashMap<String, usize> = HashMap::new();
+        for file in &maybe_moved {
+            *new_by_hash.entry(file.hash.clone()).or_default() += 1;
+        }
+
+        maybe_moved.sort_by(|a, b| a.path.cmp(&b.path));
+        for file in maybe_moved {
+            if new_by_hash.get(&file.hash) == Some(&1) {
+                if let Some([from]) = gone_by_hash.get(&file.hash).map(Vec::as_slice) {
+                    let from = from.clone();
+                    gone_by_hash.remove(&file.hash);
+                    self.move_file(&from, &file.path, file.modified);
+                    stats.moved += 1;
+                    let from = Path::new(&from);
+                    let from = from.strip_prefix(dir).unwrap_or(from).display().to_string();
+                    stats.record(&file.shown, FileStatus::Moved { from });
                     continue;
                 }
             }
+            self.embed_file(file, &mut stats).await;
+            self.note_throttle(&mut stats, throttled_at_start, &mut reported_throttle);
+        }
 
-            // Read and chunk file
-            match std::fs::read_to_string(&path) {
-                Ok(content) => {
-                    let chunks = self.chunk_text(&content, &path_str);
-                    
-                    if chunks.is_empty() {
-                        continue;
-                    }
+        // Gone files that nothing moved into were deleted
+        for path in gone {
+            if self.indexed_files.contains_key(&path) {
+                self.remove_file(&path);
+                stats.removed += 1;
+            }
+        }
 
-                    // Generate embeddings for chunks
-                    let texts: Vec<String> = chunks.iter().map(|c| c.text.clone()).collect();
-                    
-                    match self.provider.embed_batch(&texts).await {
-                        Ok(embeddings) => {
-                            for (chunk, embedding) in chunks.into_iter().zip(embeddings) {
-                                let stored = StoredEmbedding {
-                                    id: chunk.id,
-                                    text: chunk.text,
-                                    embedding,
-                                    metadata: chunk.metadata,
-                                };
-                                self.store.add(stored);
-                                stats.chunks += 1;
-                            }
-                            
-                            self.indexed_files.insert(path_str, modified);
-                            stats.files += 1;
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to embed {}: {}", path.display(), e);
-                            stats.errors += 1;
-                        }
+        if let Some(results) = &mut stats.file_results {
+            results.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        on_progress(total, Some(total));
+        stats.throttled = self.provider.throttled().saturating_sub(throttled_at_start);
+        Ok(stats)
+    }
+
+    /// Read a walked file, or record why it needs no indexing
+    fn changed_file(&mut self, dir: &Path, path_str: String, stats: &mut IndexStats) -> Option<NewFile> {
+        let path = Path::new(&path_str);
+        let shown = path.strip_prefix(dir).unwrap_or(path).display().to_string();
+
+        // Check if file needs re-indexing
+        let modified = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        if let Some(cached) = self.indexed_files.get(&path_str) {
+            if cached.modified >= modified {
+                stats.skip(&shown, "unchanged");
+                return None;
+            }
+        }
+
+        match read_indexable(path) {
+            Ok(content) => {
+                let hash = content_hash(&content);
+
+                // Touched but not changed
+                if let Some(cached) = self.indexed_files.get_mut(&path_str) {
+                    if cached.hash == hash {
+                        cached.modified = modified;
+                        stats.skip(&shown, "unchanged");
+                        return None;
                     }
                 }
-                Err(e) => {
-                    tracing::debug!("Failed to read {}: {}", path.display(), e);
-                    stats.errors += 1;
+                Some(NewFile { path: path_str, shown, content, hash, modified })
+            }
+            // Binary content under a text extension; notebooks fail the same way when malformed
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData && !Notebook::is_notebook(path) => {
+                stats.skip(&shown, "binary");
+                None
+            }
+            Err(e) => {
+                tracing::debug!("Failed to read {}: {}", path.display(), e);
+                stats.fail(&shown, format!("read failed: {}", e));
+                None
+            }
+        }
+    }
+
+    /// Chunk and embed a file, replacing the chunks of its previous version
+    async fn embed_file(&mut self, file: NewFile, stats: &mut IndexStats) {
+        let chunks = self.chunk_text(&file.content, &file.path);
+        if chunks.is_empty() {
+            stats.skip(&file.shown, "no text");
+            return;
+        }
+
+        // Generate embeddings for chunks
+        let texts: Vec<String> = chunks.iter().map(|c| c.text.clone()).collect();
+        match self.provider.embed_batch(&texts).await {
+            Ok(embeddings) => {
+                self.remove_file(&file.path);
+                let mut ids = Vec::with_capacity(chunks.len());
+                stats.record(&file.shown, FileStatus::Indexed { chunks: chunks.len() });
+                for (chunk, embedding) in chunks.into_iter().zip(embeddings) {
+                    ids.push(chunk.id.clone());
+                    let stored = StoredEmbedding {
+                        id: chunk.id,
+                        text: chunk.text,
+                        embedding,
+                        metadata: chunk.metadata,
+                    };
+                    self.store.add(stored);
+                    stats.chunks += 1;
                 }
+
+                self.indexed_files.insert(
+                    file.path,
+                    IndexedFile {
+                        modified: file.modified,
+                        hash: file.hash,
+                        chunks: ids,
+                    },
+                );
+                stats.files += 1;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to embed {}: {}", file.path, e);
+                stats.fail(&file.shown, format!("embedding failed: {:#}", e));
             }
         }
+    }
 
-        Ok(stats)
+    /// Chat turns go first on a shared key; say when that slows us down
+    fn note_throttle(&self, stats: &mut IndexStats, start: Duration, reported: &mut Duration) {
+        stats.throttled = self.provider.throttled().saturating_sub(start);
+        if stats.throttled >= *reported + THROTTLE_REPORT_INTERVAL {
+            *reported = stats.throttled;
+            tracing::info!(
+                "Indexing: {} files done, throttled for {}s so far",
+                stats.files,
+                stats.throttled.as_secs()
+            );
+        }
     }
 
-    /// Chunk text into smaller pieces
-    fn chunk_text(&self, content: &str, file_path: &str) -> Vec<TextChunk> {
-        let mut chunks = Vec::new();
-        let lines: Vec<&str> = content.lines().collect();
-        
-        if lines.is_empty() {
-            return chunks;
+    /// Point a moved file's chunks at `to` without re-embedding them
+    fn move_file(&mut self, from: &str, to: &str, modified: u64) {
+        let Some(mut file) = self.indexed_files.remove(from) else { return };
+        for id in &mut file.chunks {
+            let new_id = format!("{}{}", to, &id[from.len()..]);
+            if let Some(stored) = self.store.rename(id, &new_id) {
+                stored.metadata.insert("file".to_string(), to.to_string());
+            }
+            *id = new_id;
         }
+        file.modified = modified;
+        self.indexed_files.insert(to.to_string(), file);
+    }
 
-        let mut current_chunk = String::new();
-        let mut chunk_start_line = 0;
-        let mut chunk_idx = 0;
+    /// Drop a file's chunks from the index
+    fn remove_file(&mut self, path: &str) {
+        if let Some(file) = self.indexed_files.remove(path) {
+            for id in &file.chunks {
+                self.store.remove(id);
+            }
+        }
+    }
 
-        for (line_num, line) in lines.iter().enumerate() {
-            current_chunk.push_str(line);
-            current_chunk.push('\n');
+    /// Chunk text into smaller pieces. Markdown is chunked per block, so
+    /// prose and fenced code never share a chunk; code chunks carry their
+    /// fence language.
+    fn chunk_text(&self, content: &str, file_path: &str) -> Vec<TextChunk> {
+        let path = Path::new(file_path);
+        let language = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(language_for_extension);
+        let sections: Vec<Section> = if markdown::is_markdown(path) {
+            markdown::parse(content)
+                .into_iter()
+                .map(|block| {
+                    let code = block.kind == BlockKind::Code;
+                    Section {
+                        language: match &block.language {
+                            Some(fence) => Some(language_for_extension(fence).unwrap_or(fence).to_string()),
+                            None if code => None,
+                            None => language.map(str::to_string),
+                        },
+                        block: Some(if code { "code" } else { "prose" }),
+                        start_line: block.start_line,
+                        text: block.text,
+                    }
+                })
+                .collect()
+        } else {
+            vec![Section {
+                language: language.map(str::to_string),
+                block: None,
+                start_line: 0,
+                text: content.to_string(),
+            }]
+        };
 
-            if current_chunk.len() >= self.config.chunk_size {
+        let spans = symbol_spans(path, content);
+        let mut chunks = Vec::new();
+        for section in &sections {
+            // Metadata shared by every chunk; lines are 0-based, `end` exclusive
+            let base_metadata = |start: usize, end: usize, idx: usize| {
                 let mut metadata = HashMap::new();
                 metadata.insert("file".to_string(), file_path.to_string());
-                metadata.insert("start_line".to_string(), chunk_start_line.to_string());
-                metadata.insert("end_line".to_string(), line_num.to_string());
+                metadata.insert("start_line".to_string(), start.to_string());
+                metadata.insert("end_line".to_string(), end.to_string());
+                metadata.insert("chunk".to_string(), idx.to_string());
+                if let Some(language) = &section.language {
+                    metadata.insert("language".to_string(), language.clone());
+                }
+                if let Some(block) = section.block {
+                    metadata.insert("block".to_string(), block.to_string());
+                }
+                let symbols: Vec<&str> = spans
+                    .iter()
+                    .filter(|s| s.start_line <= end && s.end_line > start)
+                    .map(|s| s.qualified.as_str())
+                    .collect();
+                if !symbols.is_empty() {
+                    metadata.insert("symbols".to_string(), symbols.join(","));
+                }
+                metadata
+            };
+
+            let lines: Vec<&str> = section.text.lines().collect();
+            if lines.is_empty() {
+                continue;
+            }
+            let offset = section.start_line;
+            let mut current_chunk = String::new();
+            let mut chunk_start_line = 0;
+
+            for (line_num, line) in lines.iter().enumerate() {
+                current_chunk.push_str(line);
+                current_chunk.push('\n');
+
+                if current_chunk.len() >= self.config.chunk_size {
+                    let chunk_idx = chunks.len();
+                    let metadata = base_metadata(offset + chunk_start_line, offset + line_num + 1, chunk_idx);
+
+                    chunks.push(TextChunk {
+                        id: format!("{}:chunk:{}", file_path, chunk_idx),
+                        text: current_chunk.clone(),
+                        metadata,
+                    });
+
+                    // Keep overlap
+                    let overlap_start = current_chunk
+                        .char_indices()
+                        .rev()
+                        .nth(self.config.chunk_overlap)
+                        .map(|(i, _)| i)
+                        .unwrap_or(0);
+                    
+                    current_chunk = current_chunk[overlap_start..].to_string();
+                    chunk_start_line = line_num.saturating_sub(5);
+                }
+            }
+
+            // Add remaining content
+            if !current_chunk.trim().is_empty() {
+                let chunk_idx = chunks.len();
+                let metadata = base_metadata(offset + chunk_start_line, offset + lines.len(), chunk_idx);
 
                 chunks.push(TextChunk {
                     id: format!("{}:chunk:{}", file_path, chunk_idx),
-                    text: current_chunk.clone(),
+                    text: current_chunk,
                     metadata,
                 });
-
-                // Keep overlap
-                let overlap_start = current_chunk
-                    .char_indices()
-                    .rev()
-                    .nth(self.config.chunk_overlap)
-                    .map(|(i, _)| i)
-                    .unwrap_or(0);
-                
-                current_chunk = current_chunk[overlap_start..].to_string();
-                chunk_start_line = line_num.saturating_sub(5);
-                chunk_idx += 1;
             }
         }
 
-        // Add remaining content
-        if !current_chunk.trim().is_empty() {
-            let mut metadata = HashMap::new();
-            metadata.insert("file".to_string(), file_path.to_string());
-            metadata.insert("start_line".to_string(), chunk_start_line.to_string());
-            metadata.insert("end_line".to_string(), lines.len().to_string());
-
-            chunks.push(TextChunk {
-                id: format!("{}:chunk:{}", file_path, chunk_idx),
-                text: current_chunk,
-                metadata,
-            });
-        }
-
         chunks
     }
 
     /// Search for relevant code
     pub async fn search(&self, query: &str) -> Result<Vec<SearchResult>> {
-        let query_embedding = self.provider.embed(query).await?;
-        
-        let results = self.store.search_with_threshold(
-            &query_embedding,
-            self.config.top_k,
-            self.config.min_score,
+        self.search_filtered(query, &MetadataFilter::default()).await
+    }
+
+    /// Search only chunks passing `filter`; the filter is applied before scoring
+    pub async fn search_filtered(
+        &self,
+        query: &str,
+        filter: &MetadataFilter,
+    ) -> Result<Vec<SearchResult>> {
+        let span = tracing::info_span!(
+            "rag.retrieve",
+            top_k = self.config.top_k as u64,
+            results = tracing::field::Empty,
         );
+        let query_embedding = self.provider.embed(query).instrument(span.clone()).await?;
+        
+        let results: Vec<_> = self
+            .store
+            .search_filtered(&query_embedding, self.config.top_k, filter)
+            .into_iter()
+            .filter(|r| r.score >= self.config.min_score)
+            .collect();
+        span.record("results", results.len() as u64);
 
         Ok(results)
     }
 
+    /// Pairs of indexed chunks at least `threshold` similar, most similar
+    /// first. Neighbouring chunks of one file overlap, so they are skipped.
+    pub fn find_duplicates(&self, threshold: f32, top: usize) -> Vec<DuplicatePair> {
+        self.store
+            .similar_pairs(threshold)
+            .into_iter()
+            .map(|(a, b, score)| DuplicatePair {
+                a: ChunkLocation::from_metadata(&a.metadata),
+                b: ChunkLocation::from_metadata(&b.metadata),
+                score,
+            })
+            .filter(|pair| {
+                pair.a.file != pair.b.file || pair.a.chunk.abs_diff(pair.b.chunk) > 1
+            })
+            .take(top)
+            .collect()
+    }
+
     /// Get index statistics
     pub fn stats(&self) -> SemanticSearchStats {
         SemanticSearchStats {
@@ -241,17 +543,38 @@ impl SemanticSearch {
         }
     }
 
-    /// Save index to file
+    /// Save index to file, with the per-file manifest next to it
     pub fn save(&self, path: &Path) -> Result<()> {
-        self.store.save(path)
+        self.store.save(path)?;
+        let manifest = IndexManifest {
+            model: self.provider.model_name().to_string(),
+            files: self.indexed_files.clone(),
+        };
+        std::fs::write(manifest_path(path), serde_json::to_string_pretty(&manifest)?)
+            .context("Failed to write index manifest")?;
+        Ok(())
     }
 
-    /// Load index from file
+    /// Load index from file. Nothing is loaded without the manifest or when
+    /// another embedding model built the index.
     pub fn load(&mut self, path: &Path) -> Result<()> {
+        let text = std::fs::read_to_string(manifest_path(path))
+            .context("Failed to read index manifest")?;
+        let manifest: IndexManifest =
+            serde_json::from_str(&text).context("Failed to parse index manifest")?;
+        if manifest.model != self.provider.model_name() {
+            anyhow::bail!("it was built with {}", manifest.model);
+        }
         self.store = EmbeddingStore::load(path)?;
+        self.indexed_files = manifest.files;
         Ok(())
     }
 
+    /// Hand the indexed chunks to a RAG context for prompt injection
+    pub fn into_rag(self, config: RagConfig) -> RagContext {
+        RagContext::with_store(self.provider, self.store, config)
+    }
+
     /// Clear the index
     pub fn clear(&mut self) {
         self.store.clear();
@@ -259,6 +582,66 @@ impl SemanticSearch {
     }
 }
 
+/// `index.json` -> `index.files.json`
+fn manifest_path(index: &Path) -> PathBuf {
+    index.with_extension("files.json")
+}
+
+/// Saved next to the index so the next run can skip unchanged and moved files
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexManifest {
+    model: String,
+    files: HashMap<String, IndexedFile>,
+}
+
+/// What the index holds for one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedFile {
+    /// Last modified timestamp when indexed
+    modified: u64,
+    /// SHA-256 of the indexed text
+    hash: String,
+    /// Ids of the file's chunks in the store
+    chunks: Vec<String>,
+}
+
+/// A walked file whose content is new to the index
+struct NewFile {
+    path: String,
+    /// Path relative to the indexed directory, for file results
+    shown: String,
+    content: String,
+    hash: String,
+    modified: u64,
+}
+
+/// Read a file as indexed text (notebooks contribute only code/markdown cells)
+fn read_indexable(path: &Path) -> std::io::Result<String> {
+    encoding::read_to_string(path).and_then(|raw| {
+        if Notebook::is_notebook(path) {
+            Notebook::parse(&raw)
+                .map(|nb| nb.to_text())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+        } else {
+            Ok(raw)
+        }
+    })
+}
+
+fn content_hash(content: &str) -> String {
+    sha256_hex(content)
+}
+
+/// A run of a file chunked on its own: the whole file, or one markdown block
+struct Section {
+    language: Option<String>,
+    /// "prose" or "code" for markdown blocks
+    block: Option<&'static str>,
+    /// Zero-based line of the section's first line in the file
+    start_line: usize,
+    text: String,
+}
+
 #[derive(Debug, Clone)]
 struct TextChunk {
     id: String,
@@ -266,12 +649,119 @@ struct TextChunk {
     metadata: HashMap<String, String>,
 }
 
+/// Where an indexed chunk came from
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkLocation {
+    pub file: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    #[serde(skip)]
+    chunk: usize,
+}
+
+impl ChunkLocation {
+    fn from_metadata(metadata: &HashMap<String, String>) -> Self {
+        let number = |key: &str| {
+            metadata
+                .get(key)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0)
+        };
+        Self {
+            file: metadata.get("file").cloned().unwrap_or_default(),
+            // Stored start lines are 0-based
+            start_line: number("start_line") + 1,
+            end_line: number("end_line"),
+            chunk: number("chunk"),
+        }
+    }
+}
+
+impl std::fmt::Display for ChunkLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}-{}", self.file, self.start_line, self.end_line)
+    }
+}
+
+/// Two chunks that look like copies of each other
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicatePair {
+    pub a: ChunkLocation,
+    pub b: ChunkLocation,
+    pub score: f32,
+}
+
 #[derive(Debug, Default)]
 pub struct IndexStats {
+    /// Files (re-)embedded
     pub files: usize,
     pub chunks: usize,
     pub skipped: usize,
+    /// Renamed or moved files, updated without re-embedding
+    pub moved: usize,
+    /// Files deleted since the last run
+    pub removed: usize,
     pub errors: usize,
+    /// Time spent waiting on the provider's shared rate limit
+    pub throttled: Duration,
+    /// Each file's outcome, in path order; only `with_file_results`
+    pub file_results: Option<Vec<FileResult>>,
+}
+
+/// What indexing did with one file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FileStatus {
+    Indexed { chunks: usize },
+    Skipped { reason: String },
+    /// Counted in `IndexStats::moved`; `from` is relative like `path`
+    Moved { from: String },
+    Error { message: String },
+}
+
+/// One file's outcome; `path` is relative to the indexed directory
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileResult {
+    pub path: String,
+    #[serde(flatten)]
+    pub status: FileStatus,
+}
+
+impl std::fmt::Display for FileResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.status {
+            FileStatus::Indexed { chunks } => write!(f, "indexed {} ({} chunks)", self.path, chunks),
+            FileStatus::Skipped { reason } => write!(f, "skipped {}: {}", self.path, reason),
+            FileStatus::Moved { from } => write!(f, "moved   {}: from {}", self.path, from),
+            FileStatus::Error { message } => write!(f, "error   {}: {}", self.path, message),
+        }
+    }
+}
+
+impl IndexStats {
+    fn record(&mut self, path: &str, status: FileStatus) {
+        if let Some(results) = &mut self.file_results {
+            results.push(FileResult { path: path.to_string(), status });
+        }
+    }
+
+    fn skip(&mut self, path: &str, reason: &str) {
+        self.skipped += 1;
+        self.record(path, FileStatus::Skipped { reason: reason.to_string() });
+    }
+
+    fn fail(&mut self, path: &str, message: String) {
+        self.errors += 1;
+        self.record(path, FileStatus::Error { message });
+    }
+
+    /// "files: 3 changed, 41 moved, 0 removed"
+    pub fn file_changes(&self) -> String {
+        format!(
+            "files: {} changed, {} moved, {} removed",
+            self.files, self.moved, self.removed
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -307,6 +797,340 @@ mod tests {
         assert!(results.len() <= 5);
     }
 
+    #[tokio::test]
+    async fn test_index_notebook_cells() {
+        let mut search = SemanticSearch::new_mock(SemanticSearchConfig::default());
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("analysis.ipynb");
+        std::fs::write(
+            &path,
+            r##"{"cells": [
+                {"cell_type": "markdown", "source": ["# Churn analysis"]},
+                {"cell_type": "code", "source": ["df = load()\n", "df.plot()"],
+                 "outputs": [{"data": {"image/png": "iVBORw0KGgoAAAANSUhEUg=="}}]}
+            ]}"##,
+        )
+        .unwrap();
+
+        let stats = search.index_directory(dir.path()).await.unwrap();
+        assert_eq!(stats.files, 1);
+
+        let id = format!("{}:chunk:0", path.to_string_lossy());
+        let text = &search.store.get(&id).unwrap().text;
+        assert!(text.contains("# Churn analysis"));
+        assert!(text.contains("df.plot()"));
+        assert!(!text.contains("iVBORw0KGgo"));
+        assert!(!text.contains("cell_type"));
+    }
+
+    #[test]
+    fn test_chunk_end_lines_are_exclusive() {
+        let search = SemanticSearch::new_mock(SemanticSearchConfig {
+            chunk_size: 25,
+            chunk_overlap: 0,
+            ..Default::default()
+        });
+        let content = "aaaaaaaaa\n".repeat(4);
+        let chunks = search.chunk_text(&content, "src/a.txt");
+        assert_eq!(chunks.len(), 2);
+        assert_eq!((chunks[0].metadata["start_line"].as_str(), chunks[0].metadata["end_line"].as_str()), ("0", "3"));
+        assert_eq!(chunks[0].text.lines().count(), 3);
+        assert_eq!(chunks[1].metadata["end_line"], "4");
+    }
+
+    #[test]
+    fn test_markdown_prose_and_code_chunks() {
+        let search = SemanticSearch::new_mock(SemanticSearchConfig::default());
+        let doc = "# Usage\n\nStart the server:\n\n```rs\nfn main() {\n    serve();\n}\n```\n\nOr from Python:\n\n```python\nimport client\n```\n";
+        let chunks = search.chunk_text(doc, "docs/usage.md");
+
+        let tags: Vec<_> = chunks
+            .iter()
+            .map(|c| (c.metadata["block"].as_str(), c.metadata.get("language").map(String::as_str)))
+            .collect();
+        assert_eq!(
+            tags,
+            [
+                ("prose", Some("markdown")),
+                ("code", Some("rust")),
+                ("prose", Some("markdown")),
+                ("code", Some("python")),
+            ]
+        );
+        assert_eq!(chunks[1].text, "fn main() {\n    serve();\n}\n");
+        assert!(!chunks[0].text.contains("fn main"));
+        assert_eq!(chunks[1].metadata["start_line"], "5");
+        assert_eq!(chunks[3].id, "docs/usage.md:chunk:3");
+    }
+
+    /// Bag-of-words embeddings, so near-identical code scores close to 1
+    struct WordEmbeddings;
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for WordEmbeddings {
+        async fn embed_batch(&self, texts: &[String]) -> Result<Vec<crate::embeddings::Embedding>> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let mut v = vec![0.0; 64];
+                    let words = text.split(|c: char| !c.is_alphanumeric());
+                    for word in words.filter(|w| !w.is_empty()) {
+                        let bucket = word
+                            .bytes()
+                            .fold(7usize, |h, b| h.wrapping_mul(31).wrapping_add(b as usize));
+                        v[bucket % 64] += 1.0;
+                    }
+                    v
+                })
+                .collect())
+        }
+
+        fn dimension(&self) -> usize {
+            64
+        }
+
+        fn model_name(&self) -> &str {
+            "words"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicates() {
+        let dir = tempdir().unwrap();
+        let body = "(items: &[Item]) -> f64 {
+    let mut sum = 0.0;
+    for item in items {
+        sum += item.price * item.quantity as f64;
+    }
+    sum
+}
+";
+        std::fs::write(dir.path().join("orders.rs"), format!("fn total_price{}", body)).unwrap();
+        std::fs::write(dir.path().join("cart.rs"), format!("fn cart_total{}", body)).unwrap();
+        std::fs::write(
+            dir.path().join("log.rs"),
+            "pub fn init_logging(level: &str) {\n    fmt().with_env_filter(level).init();\n}\n",
+        )
+        .unwrap();
+
+        let mut search =
+            SemanticSearch::with_provider(Arc::new(WordEmbeddings), SemanticSearchConfig::default());
+        search.index_directory(dir.path()).await.unwrap();
+
+        let pairs = search.find_duplicates(0.9, 10);
+        assert_eq!(pairs.len(), 1, "{:?}", pairs);
+        let mut files = [&pairs[0].a.file, &pairs[0].b.file]
+            .map(|f| Path::new(f).file_name().unwrap().to_string_lossy().to_string());
+        files.sort();
+        assert_eq!(files, ["cart.rs", "orders.rs"]);
+        assert!(pairs[0].score > 0.9 && pairs[0].score < 1.0);
+        assert_eq!(pairs[0].a.start_line, 1);
+        assert_eq!(pairs[0].a.end_line, 7);
+
+        assert!(search.find_duplicates(0.9, 0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_language_filter_restricts_results() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("auth")).unwrap();
+        std::fs::write(dir.path().join("auth/token.rs"), "fn verify_token(token: &str) -> bool {\n    !token.is_empty()\n}\n").unwrap();
+        std::fs::write(dir.path().join("auth/token.py"), "def verify_token(token):\n    return bool(token)\n").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "# Tokens\nverify token handling\n").unwrap();
+
+        let config = SemanticSearchConfig {
+            top_k: 10,
+            min_score: -1.0,
+            ..Default::default()
+        };
+        let mut search = SemanticSearch::with_provider(Arc::new(WordEmbeddings), config);
+        search.index_directory(dir.path()).await.unwrap();
+        assert_eq!(search.search("verify token").await.unwrap().len(), 3);
+
+        let files = |results: Vec<SearchResult>| -> Vec<String> {
+            results
+                .iter()
+                .map(|r| Path::new(&r.metadata["file"]).file_name().unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+        let rust = MetadataFilter {
+            language: Some("rust".to_string()),
+            ..Default::default()
+        };
+        let results = search.search_filtered("verify token", &rust).await.unwrap();
+        assert_eq!(results[0].metadata["symbols"], "verify_token");
+        assert_eq!(files(results), vec!["token.rs"]);
+
+        let auth_python = MetadataFilter {
+            file_glob: Some("auth".to_string()),
+            language: Some("py".to_string()),
+            ..Default::default()
+        };
+        let results = search.search_filtered("verify token", &auth_python).await.unwrap();
+        assert_eq!(files(results), vec!["token.py"]);
+    }
+
+    /// Counts the texts sent for embedding
+    struct CountingEmbeddings(std::sync::atomic::AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for CountingEmbeddings {
+        async fn embed_batch(&self, texts: &[String]) -> Result<Vec<crate::embeddings::Embedding>> {
+            self.0.fetch_add(texts.len(), std::sync::atomic::Ordering::SeqCst);
+            WordEmbeddings.embed_batch(texts).await
+        }
+
+        fn dimension(&self) -> usize {
+            64
+        }
+
+        fn model_name(&self) -> &str {
+            "counting"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_renamed_directory_is_not_reembedded() {
+        let root = tempdir().unwrap();
+        let old_dir = root.path().join("helpers");
+        std::fs::create_dir(&old_dir).unwrap();
+        for i in 0..5 {
+            std::fs::write(old_dir.join(format!("h{}.rs", i)), format!("fn helper_{}() {{}}\n", i))
+                .unwrap();
+        }
+
+        let provider = Arc::new(CountingEmbeddings(Default::default()));
+        let config = SemanticSearchConfig {
+            top_k: 10,
+            min_score: -1.0,
+            ..Default::default()
+        };
+        let mut search = SemanticSearch::with_provider(provider.clone(), config.clone());
+        let first = search.index_directory(root.path()).await.unwrap();
+        assert_eq!(first.files, 5);
+        let embedded = provider.0.load(std::sync::atomic::Ordering::SeqCst);
+
+        // The next run starts from the saved index and its manifest
+        let saved = tempdir().unwrap();
+        let index_path = saved.path().join("index.json");
+        search.save(&index_path).unwrap();
+        assert!(saved.path().join("index.files.json").is_file());
+        let mut other_model = SemanticSearch::with_provider(Arc::new(WordEmbeddings), config.clone());
+        assert!(other_model.load(&index_path).is_err());
+        let mut search = SemanticSearch::with_provider(provider.clone(), config).with_file_results(true);
+        search.load(&index_path).unwrap();
+        assert_eq!(search.stats().indexed_files, 5);
+
+        let new_dir = root.path().join("util");
+        std::fs::rename(&old_dir, &new_dir).unwrap();
+        let stats = search.index_directory(root.path()).await.unwrap();
+        assert_eq!(stats.file_changes(), "files: 0 changed, 5 moved, 0 removed");
+        assert_eq!(provider.0.load(std::sync::atomic::Ordering::SeqCst), embedded);
+        let results = stats.file_results.unwrap();
+        assert_eq!(results.len(), stats.moved);
+        assert_eq!(
+            serde_json::to_value(&results[0]).unwrap(),
+            serde_json::json!({"path": "util/h0.rs", "status": "moved", "from": "helpers/h0.rs"})
+        );
+
+        let results = search.search("helper").await.unwrap();
+        assert_eq!(results.len(), 5);
+        for result in &results {
+            let file = &result.metadata["file"];
+            assert!(Path::new(file).starts_with(&new_dir), "{}", file);
+            assert!(result.id.starts_with(file.as_str()));
+        }
+        assert_eq!(search.stats().indexed_files, 5);
+
+        // Deleted files drop out of the index
+        std::fs::remove_file(new_dir.join("h0.rs")).unwrap();
+        let stats = search.index_directory(root.path()).await.unwrap();
+        assert_eq!(stats.file_changes(), "files: 0 changed, 0 moved, 1 removed");
+        assert_eq!(search.search("helper").await.unwrap().len(), 4);
+    }
+
+    /// Fails any batch mentioning `poison`
+    struct PickyEmbeddings;
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for PickyEmbeddings {
+        async fn embed_batch(&self, texts: &[String]) -> Result<Vec<crate::embeddings::Embedding>> {
+            if texts.iter().any(|t| t.contains("poison")) {
+                anyhow::bail!("input rejected by the embedding API (400)");
+            }
+            WordEmbeddings.embed_batch(texts).await
+        }
+
+        fn dimension(&self) -> usize {
+            64
+        }
+
+        fn model_name(&self) -> &str {
+            "picky"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_results_explain_skips_and_errors() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn parse() {}\n").unwrap();
+        std::fs::write(dir.path().join("blob.txt"), [0x89, b'P', b'N', b'G', 0, 0, 0, 13, 0xff]).unwrap();
+        std::fs::write(dir.path().join("bad.py"), "poison = True\n").unwrap();
+
+        let mut search = SemanticSearch::with_provider(Arc::new(PickyEmbeddings), SemanticSearchConfig::default())
+            .with_file_results(true);
+        let stats = search.index_directory(dir.path()).await.unwrap();
+        assert_eq!((stats.files, stats.skipped, stats.errors), (1, 1, 1));
+        let results = stats.file_results.unwrap();
+        assert_eq!(
+            results,
+            [
+                FileResult {
+                    path: "bad.py".to_string(),
+                    status: FileStatus::Error {
+                        message: "embedding failed: input rejected by the embedding API (400)".to_string()
+                    },
+                },
+                FileResult { path: "blob.txt".to_string(), status: FileStatus::Skipped { reason: "binary".to_string() } },
+                FileResult { path: "lib.rs".to_string(), status: FileStatus::Indexed { chunks: 1 } },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&results[1]).unwrap(),
+            serde_json::json!({"path": "blob.txt", "status": "skipped", "reason": "binary"})
+        );
+        assert_eq!(results[0].to_string(), "error   bad.py: embedding failed: input rejected by the embedding API (400)");
+
+        // Off by default
+        let mut search = SemanticSearch::with_provider(Arc::new(PickyEmbeddings), SemanticSearchConfig::default());
+        assert!(search.index_directory(dir.path()).await.unwrap().file_results.is_none());
+    }
+
+    #[test]
+    fn test_adjacent_chunks_are_not_duplicates() {
+        let config = SemanticSearchConfig {
+            chunk_size: 40,
+            chunk_overlap: 30,
+            ..Default::default()
+        };
+        let mut search = SemanticSearch::new_mock(config);
+        let content = "x = 1\n".repeat(30);
+        for chunk in search.chunk_text(&content, "same.py") {
+            search.store.add(StoredEmbedding {
+                id: chunk.id,
+                text: chunk.text,
+                embedding: vec![1.0; 384],
+                metadata: chunk.metadata,
+            });
+        }
+        assert!(search.store.len() > 3);
+        assert!(search
+            .find_duplicates(0.5, 100)
+            .iter()
+            .all(|p| p.a.chunk.abs_diff(p.b.chunk) > 1));
+        assert!(!search.find_duplicates(0.5, 100).is_empty());
+    }
+
     #[test]
     fn test_chunk_text() {
         let config = SemanticSearchConfig {
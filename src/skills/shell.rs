@@ -6,9 +6,9 @@
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use serde_json::{json, Value};
-use std::process::Command;
 
 use super::registry::{Skill, SkillDefinition};
+use super::sandbox::{resolve_working_dir, run_command, runtime_available, select_backend, Backend};
 use crate::config::Settings;
 use crate::core::{CommandRisk, InputSanitizer, SecurityConfig};
 
@@ -36,6 +36,28 @@ impl Default for ExecuteCommandSkill {
     }
 }
 
+/// `argv` as one command line for [`ExecuteCommandSkill::run`], quoting
+/// arguments the shell would otherwise split or expand
+pub fn shell_join<S: AsRef<str>>(argv: &[S]) -> String {
+    argv.iter()
+        .map(|arg| {
+            let arg = arg.as_ref();
+            let plain = !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_./:=@+,".contains(c));
+            if plain {
+                arg.to_string()
+            } else if cfg!(target_os = "windows") {
+                format!("\"{}\"", arg.replace('"', "\"\""))
+            } else {
+                format!("'{}'", arg.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 #[async_trait]
 impl Skill for ExecuteCommandSkill {
     fn definition(&self) -> SkillDefinition {
@@ -52,11 +74,16 @@ impl Skill for ExecuteCommandSkill {
                     "working_dir": {
                         "type": "string",
                         "description": "Working directory for the command"
+                    },
+                    "network": {
+                        "type": "boolean",
+                        "description": "Request network access when sandboxed (subject to policy)"
                     }
                 },
                 "required": ["command"]
             }),
             requires_confirmation: true,
+            parallel_safe: false,
         }
     }
 
@@ -65,7 +92,32 @@ impl Skill for ExecuteCommandSkill {
             .as_str()
             .context("Missing 'command' argument")?;
         let working_dir = args["working_dir"].as_str();
+        let network = args["network"].as_bool().unwrap_or(false);
+
+        Ok(self.run(command, working_dir, network, settings).await?.output)
+    }
+}
+
+/// Result of a command run through [`ExecuteCommandSkill::run`]
+#[derive(Debug, Clone)]
+pub struct ShellRun {
+    /// Risk and sandbox notes, sanitized stdout and stderr, exit status
+    pub output: String,
+    /// Exited with status 0 within the time limit
+    pub success: bool,
+}
 
+impl ExecuteCommandSkill {
+    /// Run `command` with the allow-list and risk checks, the sandbox policy
+    /// and `shell.timeout_secs` applied. `working_dir` is relative to the
+    /// current directory.
+    pub async fn run(
+        &self,
+        command: &str,
+        working_dir: Option<&str>,
+        network: bool,
+        settings: &Settings,
+    ) -> Result<ShellRun> {
         // SENTINEL Security Layer 1: Validate command against allowed list
         if !settings.safety.allowed_commands.is_empty() {
             let cmd_name = command.split_whitespace().next().unwrap_or("");
@@ -102,34 +154,41 @@ impl Skill for ExecuteCommandSkill {
             }
         }
 
-        // Execute the command
-        let mut cmd = if cfg!(target_os = "windows") {
-            let mut c = Command::new("cmd");
-            c.args(["/C", command]);
-            c
-        } else {
-            let mut c = Command::new("sh");
-            c.args(["-c", command]);
-            c
-        };
-
-        if let Some(dir) = working_dir {
-            cmd.current_dir(dir);
+        // Execute the command, in a container when the sandbox policy requires it.
+        // The container always mounts cwd and starts in the subdirectory.
+        let cwd = std::env::current_dir()?;
+        let subdir = resolve_working_dir(&cwd, working_dir)?;
+        let mut backend = select_backend(
+            &settings.shell,
+            &risk,
+            runtime_available(settings.shell.sandbox),
+            network,
+            &cwd,
+        )?;
+        if let Backend::Container(spec) = &mut backend {
+            spec.subdir = subdir.clone();
         }
 
-        let output = cmd.output().context("Failed to execute command")?;
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
+        let host_dir = cwd.join(&subdir);
+        let output = run_command(
+            &backend,
+            command,
+            working_dir.map(|_| host_dir.as_path()),
+            settings.shell.timeout_secs,
+        )
+        .await?;
 
         // SENTINEL Security Layer 3: Sanitize output to remove secrets
-        let sanitized_stdout = self.sanitizer.sanitize_output(&stdout);
-        let sanitized_stderr = self.sanitizer.sanitize_output(&stderr);
+        let sanitized_stdout = self.sanitizer.sanitize_output(&output.stdout);
+        let sanitized_stderr = self.sanitizer.sanitize_output(&output.stderr);
 
         let mut result = String::new();
 
         // Add risk level indicator
         result.push_str(&format!("[Risk: {}]\n", risk.description()));
+        if let Backend::Container(spec) = &backend {
+            result.push_str(&format!("[Sandbox: {}]\n", spec.describe()));
+        }
 
         if !sanitized_stdout.is_empty() {
             result.push_str(&sanitized_stdout);
@@ -142,13 +201,15 @@ impl Skill for ExecuteCommandSkill {
             result.push_str(&sanitized_stderr);
         }
 
-        if !output.status.success() {
-            result.push_str(&format!(
-                "\n[Exit code: {}]",
-                output.status.code().unwrap_or(-1)
-            ));
+        if output.timed_out {
+            result.push_str("\n[Timed out]");
+        } else if !output.success() {
+            result.push_str(&format!("\n[Exit code: {}]", output.exit_code.unwrap_or(-1)));
         }
 
-        Ok(result)
+        Ok(ShellRun {
+            output: result,
+            success: output.success(),
+        })
     }
 }
@@ -0,0 +1,944 @@
+// ============================================
+// Symbol-Scoped Editing
+// Replace a whole function/method/class by name
+// ============================================
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+
+use super::codebase::{identifier, SymbolKind};
+use super::registry::{Skill, SkillDefinition};
+use crate::config::Settings;
+use crate::indexer::encoding;
+
+/// A symbol and the lines it occupies, attributes/decorators included
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolSpan {
+    pub name: String,
+    /// Name with its enclosing impl/trait/class, e.g. `Parser::parse`
+    pub qualified: String,
+    pub kind: SymbolKind,
+    /// First line (1-based), the first attribute or decorator if any
+    pub start_line: usize,
+    /// Last line (1-based), the closing brace or last body line
+    pub end_line: usize,
+}
+
+impl SymbolSpan {
+    fn lines(&self) -> usize {
+        self.end_line - self.start_line + 1
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} ({}) at lines {}-{}",
+            self.qualified,
+            self.kind.as_str(),
+            self.start_line,
+            self.end_line
+        )
+    }
+
+    /// `query` is a bare name or a `::`/`.` qualified path suffix
+    fn matches(&self, query: &str, kind: Option<&str>) -> bool {
+        let query = query.replace('.', "::");
+        let name_matches = self.qualified == query
+            || self.qualified.ends_with(&format!("::{}", query));
+        let kind_matches = match kind {
+            None => true,
+            Some("method") => {
+                matches!(self.kind, SymbolKind::Function) && self.qualified.contains("::")
+            }
+            Some(kind) => self.kind.as_str() == kind,
+        };
+        name_matches && kind_matches
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolEditResult {
+    pub success: bool,
+    pub file_path: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// Line count of the replaced span
+    pub lines_before: usize,
+    /// Line count of the replacement
+    pub lines_after: usize,
+    /// Matching symbols when the name was ambiguous
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<String>,
+}
+
+impl SymbolEditResult {
+    fn failed(path: &str, message: String) -> Self {
+        Self {
+            success: false,
+            file_path: path.to_string(),
+            message,
+            symbol: None,
+            lines_before: 0,
+            lines_after: 0,
+            candidates: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    Rust,
+    Python,
+    Script,
+}
+
+impl Language {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|e| e.to_str())? {
+            "rs" => Some(Self::Rust),
+            "py" => Some(Self::Python),
+            "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => Some(Self::Script),
+            _ => None,
+        }
+    }
+}
+
+/// Symbols of a source file with their full line spans (heuristic: brace
+/// counting for Rust/JS/TS, indentation for Python)
+pub fn symbol_spans(path: &Path, content: &str) -> Vec<SymbolSpan> {
+    let lines: Vec<&str> = content.lines().collect();
+    match Language::from_path(path) {
+        Some(Language::Python) => python_spans(&lines),
+        Some(language) => brace_spans(&lines, language),
+        None => Vec::new(),
+    }
+}
+
+/// Replace the symbol `query` in `path` with `new_body`
+pub fn edit_symbol(
+    path: &str,
+    query: &str,
+    kind: Option<&str>,
+    new_body: &str,
+) -> Result<SymbolEditResult> {
+    let file_path = Path::new(path);
+    if !file_path.exists() {
+        return Ok(SymbolEditResult::failed(path, format!("File not found: {}", path)));
+    }
+    let Some(language) = Language::from_path(file_path) else {
+        return Ok(SymbolEditResult::failed(
+            path,
+            "edit_symbol supports Rust, Python, JavaScript and TypeScript files".to_string(),
+        ));
+    };
+
+    let decoded = encoding::read_text(file_path)?;
+    let spans = symbol_spans(file_path, &decoded.text);
+    let matches: Vec<&SymbolSpan> = spans.iter().filter(|s| s.matches(query, kind)).collect();
+
+    let span = match matches.as_slice() {
+        [span] => *span,
+        [] => {
+            return Ok(SymbolEditResult::failed(
+                path,
+                format!("Symbol '{}' not found in {}", query, path),
+            ))
+        }
+        _ => {
+            return Ok(SymbolEditResult {
+                candidates: matches.iter().map(|s| s.describe()).collect(),
+                ..SymbolEditResult::failed(
+                    path,
+                    format!(
+                        "Symbol '{}' is ambiguous ({} matches); qualify it or pass a kind",
+                        query,
+                        matches.len()
+                    ),
+                )
+            })
+        }
+    };
+
+    if let Err(e) = check_balanced(new_body, language) {
+        return Ok(SymbolEditResult::failed(
+            path,
+            format!("Replacement rejected: {}", e),
+        ));
+    }
+
+    let content = &decoded.text;
+    let ending = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let indent = leading_whitespace(&lines[span.start_line - 1]).to_string();
+    let replacement = reindent(new_body, &indent);
+    let lines_after = replacement.len();
+    lines.splice(span.start_line - 1..span.end_line, replacement);
+
+    let mut new_content = lines.join(ending);
+    if content.ends_with('\n') {
+        new_content.push_str(ending);
+    }
+    write_atomic(file_path, &decoded.encoding.encode(&new_content)?)?;
+
+    Ok(SymbolEditResult {
+        success: true,
+        file_path: path.to_string(),
+        message: format!(
+            "Replaced {} (lines {}-{}): {} -> {} lines",
+            span.qualified,
+            span.start_line,
+            span.end_line,
+            span.lines(),
+            lines_after
+        ),
+        symbol: Some(span.qualified.clone()),
+        lines_before: span.lines(),
+        lines_after,
+        candidates: Vec::new(),
+    })
+}
+
+/// Write via a sibling temp file and rename, so a failed write leaves the
+/// original untouched
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .context("Invalid file path")?;
+    let tmp = path.with_file_name(format!(".{}.webrana-tmp", file_name));
+    std::fs::write(&tmp, bytes)?;
+    if let Ok(meta) = std::fs::metadata(path) {
+        let _ = std::fs::set_permissions(&tmp, meta.permissions());
+    }
+    if let Err(e) = std::fs::rename(&tmp, path) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Lines of `body`, indented to `indent` when it was written flush-left
+fn reindent(body: &str, indent: &str) -> Vec<String> {
+    let body = body.trim_matches('\n');
+    let flush_left = body
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .map(|l| leading_whitespace(l).is_empty())
+        .unwrap_or(true);
+    body.lines()
+        .map(|line| {
+            let line = line.trim_end_matches('\r');
+            if flush_left && !indent.is_empty() && !line.trim().is_empty() {
+                format!("{}{}", indent, line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Brackets must close in order and nothing may be left open
+fn check_balanced(body: &str, language: Language) -> Result<()> {
+    if body.trim().is_empty() {
+        anyhow::bail!("replacement body is empty");
+    }
+    let mut stack = Vec::new();
+    let mut state = LexState::default();
+    for (i, line) in body.lines().enumerate() {
+        for c in code_only(line, language, &mut state).chars() {
+            match c {
+                '(' | '[' | '{' => stack.push((c, i + 1)),
+                ')' | ']' | '}' => {
+                    let open = match c {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '{',
+                    };
+                    match stack.pop() {
+                        Some((o, _)) if o == open => {}
+                        _ => anyhow::bail!("unbalanced '{}' on line {}", c, i + 1),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    if let Some((c, line)) = stack.pop() {
+        anyhow::bail!("'{}' opened on line {} is never closed", c, line);
+    }
+    Ok(())
+}
+
+/// Lexer state carried across lines (block comments, template literals)
+#[derive(Debug, Default)]
+struct LexState {
+    block_comment: bool,
+    template: bool,
+}
+
+/// `line` with string/char literal contents and comments removed
+fn code_only(line: &str, language: Language, state: &mut LexState) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let comment = if language == Language::Python { '#' } else { '/' };
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if state.block_comment {
+            if c == '*' && next == Some('/') {
+                state.block_comment = false;
+                i += 1;
+            }
+        } else if state.template {
+            if c == '\\' {
+                i += 1;
+            } else if c == '`' {
+                state.template = false;
+            }
+        } else if c == comment && (language == Language::Python || next == Some('/')) {
+            break;
+        } else if language != Language::Python && c == '/' && next == Some('*') {
+            state.block_comment = true;
+            i += 1;
+        } else if language == Language::Script && c == '`' {
+            state.template = true;
+        } else if c == '"' || (c == '\'' && language != Language::Rust) {
+            i = skip_string(&chars, i, c);
+        } else if c == '\'' {
+            // Rust char literal ('x', '\n', '{'); a lifetime has no closing quote
+            if let Some(end) = (i + 2..chars.len().min(i + 12)).find(|&j| chars[j] == '\'') {
+                if chars[i + 1] == '\\' || end == i + 2 {
+                    i = end;
+                } else {
+                    out.push(c);
+                }
+            } else {
+                out.push(c);
+            }
+        } else {
+            out.push(c);
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Index of the closing quote (or the last char when unterminated)
+fn skip_string(chars: &[char], start: usize, quote: char) -> usize {
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            c if c == quote => return i,
+            _ => {}
+        }
+        i += 1;
+    }
+    chars.len()
+}
+
+/// Strip visibility and other modifiers in front of a Rust item keyword
+fn strip_rust_modifiers(mut rest: &str) -> &str {
+    loop {
+        let before = rest;
+        if rest.starts_with("pub(") {
+            if let Some(end) = rest.find(") ") {
+                rest = &rest[end + 2..];
+            }
+        }
+        for prefix in ["pub ", "async ", "const ", "unsafe ", "default ", "extern \"C\" "] {
+            rest = rest.strip_prefix(prefix).unwrap_or(rest);
+        }
+        if rest == before {
+            return rest;
+        }
+    }
+}
+
+/// Kind and name of a Rust item starting on `trimmed`
+fn rust_item(trimmed: &str) -> Option<(SymbolKind, String)> {
+    let rest = strip_rust_modifiers(trimmed);
+    for (keyword, kind) in [
+        ("fn ", SymbolKind::Function),
+        ("struct ", SymbolKind::Struct),
+        ("enum ", SymbolKind::Enum),
+        ("trait ", SymbolKind::Trait),
+    ] {
+        if let Some(after) = rest.strip_prefix(keyword) {
+            return identifier(after).map(|name| (kind, name));
+        }
+    }
+    let after = rest.strip_prefix("impl")?;
+    if !after.starts_with([' ', '<']) {
+        return None;
+    }
+    // `impl<T> Trait for Type<T>` names the self type
+    let header = after.split(['{', '\n']).next()?;
+    let header = header.split(" where ").next()?;
+    let self_type = header.rsplit(" for ").next()?.trim();
+    let self_type = if header.contains(" for ") {
+        self_type
+    } else {
+        skip_generics(self_type)
+    };
+    identifier(self_type.rsplit("::").next()?).map(|name| (SymbolKind::Impl, name))
+}
+
+/// Text after a leading `<...>` generic parameter list
+fn skip_generics(text: &str) -> &str {
+    let text = text.trim_start();
+    if !text.starts_with('<') {
+        return text;
+    }
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return text[i + 1..].trim_start();
+                }
+            }
+            _ => {}
+        }
+    }
+    text
+}
+
+const SCRIPT_MODIFIERS: &[&str] = &[
+    "export ", "default ", "declare ", "abstract ", "async ", "public ", "private ",
+    "protected ", "static ", "readonly ", "override ", "get ", "set ",
+];
+
+const SCRIPT_KEYWORDS: &[&str] = &[
+    "if", "for", "while", "switch", "catch", "return", "function", "new", "await", "typeof",
+];
+
+/// Kind and name of a JS/TS declaration starting on `trimmed`. Methods and
+/// class properties are only recognized when `in_class` is set.
+fn script_item(trimmed: &str, in_class: bool) -> Option<(SymbolKind, String)> {
+    let mut rest = trimmed;
+    loop {
+        let before = rest;
+        for prefix in SCRIPT_MODIFIERS {
+            rest = rest.strip_prefix(prefix).unwrap_or(rest);
+        }
+        if rest == before {
+            break;
+        }
+    }
+
+    if let Some(after) = rest.strip_prefix("function") {
+        let after = after.trim_start_matches('*');
+        return identifier(after).map(|name| (SymbolKind::Function, name));
+    }
+    for (keyword, kind) in [
+        ("class ", SymbolKind::Class),
+        ("interface ", SymbolKind::Interface),
+        ("enum ", SymbolKind::Enum),
+    ] {
+        if let Some(after) = rest.strip_prefix(keyword) {
+            return identifier(after).map(|name| (kind, name));
+        }
+    }
+    for keyword in ["const ", "let ", "var "] {
+        if let Some(after) = rest.strip_prefix(keyword) {
+            let name = identifier(after)?;
+            let value = after.split_once('=')?.1.trim_start();
+            let is_function = value.starts_with("function")
+                || value.starts_with("async")
+                || (value.starts_with('(') && trimmed.contains("=>"))
+                || value.split("=>").next().is_some_and(|v| {
+                    trimmed.contains("=>") && identifier(v).is_some_and(|i| i.len() == v.trim().len())
+                });
+            return is_function.then_some((SymbolKind::Function, name));
+        }
+    }
+
+    if in_class {
+        let name = identifier(rest)?;
+        if SCRIPT_KEYWORDS.contains(&name.as_str()) {
+            return None;
+        }
+        let after = rest[name.len()..].trim_start();
+        let after = after.strip_prefix('?').unwrap_or(after);
+        let is_method = after.starts_with('(') || after.starts_with('<');
+        let is_arrow_property = after.starts_with('=') && trimmed.contains("=>");
+        if is_method || is_arrow_property {
+            return Some((SymbolKind::Function, name));
+        }
+    }
+    None
+}
+
+/// Last line (0-based) of a brace-delimited item starting at `start`: the
+/// line where its first `{` closes, or a `;` ending a bodiless item
+fn brace_item_end(lines: &[&str], start: usize, language: Language) -> usize {
+    let mut state = LexState::default();
+    let mut braces = 0i32;
+    let mut nesting = 0i32;
+    let mut opened = false;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        let code = code_only(line, language, &mut state);
+        for c in code.chars() {
+            match c {
+                '{' => {
+                    braces += 1;
+                    opened = true;
+                }
+                '}' => {
+                    braces -= 1;
+                    if opened && braces == 0 {
+                        return i;
+                    }
+                }
+                '(' | '[' => nesting += 1,
+                ')' | ']' => nesting -= 1,
+                ';' if !opened && nesting == 0 => return i,
+                _ => {}
+            }
+        }
+        // An expression-bodied arrow function without a semicolon
+        if !opened && nesting == 0 && i > start && line.trim().is_empty() {
+            return i - 1;
+        }
+    }
+    lines.len().saturating_sub(1)
+}
+
+fn brace_spans(lines: &[&str], language: Language) -> Vec<SymbolSpan> {
+    let mut spans = Vec::new();
+    // (name, is_class_body, last line, brace depth inside the body)
+    let mut containers: Vec<(String, bool, usize, i32)> = Vec::new();
+    let mut prefix_start: Option<usize> = None;
+    let mut state = LexState::default();
+    let mut depth = 0i32;
+
+    for (i, line) in lines.iter().enumerate() {
+        while containers.last().is_some_and(|c| c.2 < i) {
+            containers.pop();
+        }
+        let trimmed = line.trim();
+        let in_block_comment = state.block_comment;
+        let code = code_only(line, language, &mut state);
+
+        let is_prefix = match language {
+            Language::Rust => trimmed.starts_with("#["),
+            _ => trimmed.starts_with('@'),
+        };
+        if in_block_comment || trimmed.starts_with("//") || trimmed.starts_with("/*") {
+            // Comments between attributes and the item keep the span open
+        } else if is_prefix {
+            prefix_start.get_or_insert(i);
+        } else {
+            let in_class = containers
+                .last()
+                .is_some_and(|c| c.1 && c.3 == depth);
+            let item = match language {
+                Language::Rust => rust_item(trimmed),
+                _ => script_item(trimmed, in_class),
+            };
+            if let Some((kind, name)) = item {
+                let end = brace_item_end(lines, i, language);
+                let qualified = containers
+                    .iter()
+                    .map(|c| c.0.as_str())
+                    .chain([name.as_str()])
+                    .collect::<Vec<_>>()
+                    .join("::");
+                let is_container = matches!(
+                    kind,
+                    SymbolKind::Impl | SymbolKind::Trait | SymbolKind::Class | SymbolKind::Function
+                );
+                if is_container && end > i {
+                    let is_class = matches!(kind, SymbolKind::Class);
+                    containers.push((name.clone(), is_class, end, depth + 1));
+                }
+                spans.push(SymbolSpan {
+                    name,
+                    qualified,
+                    kind,
+                    start_line: prefix_start.unwrap_or(i) + 1,
+                    end_line: end + 1,
+                });
+            }
+            prefix_start = None;
+        }
+
+        depth += code.matches('{').count() as i32 - code.matches('}').count() as i32;
+    }
+    spans
+}
+
+fn indent_of(line: &str) -> usize {
+    leading_whitespace(line).chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+fn python_spans(lines: &[&str]) -> Vec<SymbolSpan> {
+    let mut spans = Vec::new();
+    // (name, indent)
+    let mut containers: Vec<(String, usize)> = Vec::new();
+    let mut decorator_start: Option<usize> = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = indent_of(line);
+        while containers.last().is_some_and(|c| c.1 >= indent) {
+            containers.pop();
+        }
+        if trimmed.starts_with('@') {
+            decorator_start.get_or_insert(i);
+            continue;
+        }
+
+        let rest = trimmed.strip_prefix("async ").unwrap_or(trimmed);
+        let item = if let Some(after) = rest.strip_prefix("def ") {
+            identifier(after).map(|name| (SymbolKind::Function, name))
+        } else if let Some(after) = rest.strip_prefix("class ") {
+            identifier(after).map(|name| (SymbolKind::Class, name))
+        } else {
+            None
+        };
+        let start = decorator_start.take().unwrap_or(i);
+        let Some((kind, name)) = item else {
+            continue;
+        };
+
+        let end = python_block_end(lines, i, indent);
+        let qualified = containers
+            .iter()
+            .map(|c| c.0.as_str())
+            .chain([name.as_str()])
+            .collect::<Vec<_>>()
+            .join("::");
+        containers.push((name.clone(), indent));
+        spans.push(SymbolSpan {
+            name,
+            qualified,
+            kind,
+            start_line: start + 1,
+            end_line: end + 1,
+        });
+    }
+    spans
+}
+
+/// Last line (0-based) of the `def`/`class` block at `start`: the signature
+/// (which may span lines) plus every following line indented deeper
+fn python_block_end(lines: &[&str], start: usize, indent: usize) -> usize {
+    let mut state = LexState::default();
+    let mut nesting = 0i32;
+    let mut header_end = start;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        for c in code_only(line, Language::Python, &mut state).chars() {
+            match c {
+                '(' | '[' | '{' => nesting += 1,
+                ')' | ']' | '}' => nesting -= 1,
+                _ => {}
+            }
+        }
+        header_end = i;
+        if nesting <= 0 {
+            break;
+        }
+    }
+
+    let mut end = header_end;
+    for (i, line) in lines.iter().enumerate().skip(header_end + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if indent_of(line) <= indent {
+            break;
+        }
+        end = i;
+    }
+    end
+}
+
+pub struct EditSymbolSkill;
+
+#[async_trait]
+impl Skill for EditSymbolSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: "edit_symbol".to_string(),
+            description: "Replace a whole function, method, class or other symbol by name. \
+                More reliable than search/replace for rewriting a function. \
+                Supports Rust, Python, JavaScript and TypeScript."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path to the file to edit"
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "Symbol name, optionally qualified (e.g. 'Parser::parse' or 'Parser.parse')"
+                    },
+                    "kind": {
+                        "type": "string",
+                        "description": "Only match this kind: function, method, struct, enum, trait, impl, class, interface"
+                    },
+                    "new_body": {
+                        "type": "string",
+                        "description": "Full replacement: attributes/decorators, signature and body"
+                    },
+                    "expected_hash": super::registry::expected_hash_param()
+                },
+                "required": ["path", "symbol", "new_body"]
+            }),
+            requires_confirmation: true,
+            parallel_safe: false,
+        }
+    }
+
+    async fn execute(&self, args: &Value, _settings: &Settings) -> Result<String> {
+        let path = args["path"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing path"))?;
+        let symbol = args["symbol"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing symbol"))?;
+        let new_body = args["new_body"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing new_body"))?;
+        let kind = args["kind"].as_str();
+
+        let result = edit_symbol(path, symbol, kind, new_body)?;
+        Ok(serde_json::to_string_pretty(&result)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const RUST: &str = r#"use std::fmt;
+
+/// A parser
+pub struct Parser {
+    pos: usize,
+}
+
+impl Parser {
+    /// Create a parser
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        let brace = '{';
+        Self { pos: if brace == '{' { 0 } else { 1 } }
+    }
+
+    pub(crate) async fn parse<'a>(&self, input: &'a str) -> Option<&'a str> {
+        // a } in a comment
+        let s = "}";
+        if input.is_empty() {
+            return None;
+        }
+        Some(s)
+    }
+}
+
+impl fmt::Display for Parser {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.pos)
+    }
+}
+
+fn new() {}
+"#;
+
+    const PYTHON: &str = r#"import functools
+
+
+class Cache:
+    def __init__(self):
+        self.items = {}
+
+    @functools.lru_cache(maxsize=None)
+    @staticmethod
+    def lookup(
+        key,
+        default=None,
+    ):
+        if key:
+            return key
+
+        return default
+
+    def size(self):
+        return len(self.items)
+
+
+def lookup(key):
+    return key
+"#;
+
+    const TYPESCRIPT: &str = r#"export const add = (a: number, b: number): number => {
+  const s = `${a} }`;
+  return a + b;
+};
+
+export const double = (x: number) => x * 2;
+
+export class Counter {
+  private count = 0;
+
+  @log()
+  increment(by: number = 1): void {
+    if (by > 0) {
+      this.count += by;
+    }
+  }
+
+  reset = () => {
+    this.count = 0;
+  };
+}
+"#;
+
+    fn write(dir: &TempDir, name: &str, content: &str) -> String {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn span<'a>(spans: &'a [SymbolSpan], qualified: &str) -> &'a SymbolSpan {
+        spans.iter().find(|s| s.qualified == qualified).unwrap()
+    }
+
+    #[test]
+    fn test_rust_spans() {
+        let spans = symbol_spans(Path::new("a.rs"), RUST);
+        let new = span(&spans, "Parser::new");
+        // Attributes are part of the span; the doc comment above them is kept
+        assert_eq!((new.start_line, new.end_line), (10, 15));
+        let parse = span(&spans, "Parser::parse");
+        assert_eq!((parse.start_line, parse.end_line), (17, 24));
+        assert_eq!(span(&spans, "Parser::fmt").start_line, 28);
+        assert_eq!(span(&spans, "new").end_line, 33);
+    }
+
+    #[test]
+    fn test_edit_rust_impl_method() {
+        let dir = TempDir::new().unwrap();
+        let path = write(&dir, "lib.rs", RUST);
+
+        let result = edit_symbol(
+            &path,
+            "Parser::new",
+            None,
+            "#[inline]\npub fn new() -> Self {\n    Self { pos: 0 }\n}",
+        )
+        .unwrap();
+        assert!(result.success, "{}", result.message);
+        assert_eq!((result.lines_before, result.lines_after), (6, 4));
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains(
+            "    /// Create a parser\n    #[inline]\n    pub fn new() -> Self {\n        Self { pos: 0 }\n    }\n\n    pub(crate) async fn parse"
+        ));
+        assert!(!content.contains("must_use"));
+        assert!(content.ends_with("fn new() {}\n"));
+
+        // Unbalanced replacements are refused without touching the file
+        let result = edit_symbol(&path, "Parser::parse", None, "fn parse(&self) {\n    (\n}").unwrap();
+        assert!(!result.success);
+        assert!(result.message.contains("unbalanced"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_ambiguous_symbol_lists_candidates() {
+        let dir = TempDir::new().unwrap();
+        let path = write(&dir, "lib.rs", RUST);
+
+        let result = edit_symbol(&path, "new", None, "fn new() {}").unwrap();
+        assert!(!result.success);
+        assert!(result.message.contains("ambiguous"));
+        assert_eq!(
+            result.candidates,
+            vec![
+                "Parser::new (function) at lines 10-15".to_string(),
+                "new (function) at lines 33-33".to_string(),
+            ]
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), RUST);
+
+        // A kind filter disambiguates `Parser` between the struct and its impls
+        let result = edit_symbol(&path, "Parser", Some("struct"), "pub struct Parser {\n    pos: u32,\n}")
+            .unwrap();
+        assert!(result.success, "{}", result.message);
+        assert!(std::fs::read_to_string(&path).unwrap().contains("    pos: u32,"));
+    }
+
+    #[test]
+    fn test_edit_python_decorated_method() {
+        let dir = TempDir::new().unwrap();
+        let path = write(&dir, "cache.py", PYTHON);
+
+        let spans = symbol_spans(Path::new("cache.py"), PYTHON);
+        let lookup = span(&spans, "Cache::lookup");
+        assert_eq!((lookup.start_line, lookup.end_line), (8, 17));
+
+        let result = edit_symbol(
+            &path,
+            "Cache.lookup",
+            None,
+            "@staticmethod\ndef lookup(key, default=None):\n    return key or default",
+        )
+        .unwrap();
+        assert!(result.success, "{}", result.message);
+        assert_eq!((result.lines_before, result.lines_after), (10, 3));
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains(
+            "        self.items = {}\n\n    @staticmethod\n    def lookup(key, default=None):\n        return key or default\n\n    def size(self):"
+        ));
+        assert!(content.ends_with("def lookup(key):\n    return key\n"));
+    }
+
+    #[test]
+    fn test_edit_typescript_arrow_consts() {
+        let dir = TempDir::new().unwrap();
+        let path = write(&dir, "math.ts", TYPESCRIPT);
+
+        let spans = symbol_spans(Path::new("math.ts"), TYPESCRIPT);
+        assert_eq!(span(&spans, "add").end_line, 4);
+        assert_eq!(span(&spans, "double").end_line, 6);
+        let increment = span(&spans, "Counter::increment");
+        assert_eq!((increment.start_line, increment.end_line), (11, 16));
+        assert_eq!(span(&spans, "Counter::reset").end_line, 20);
+
+        let result = edit_symbol(
+            &path,
+            "add",
+            Some("function"),
+            "export const add = (a: number, b: number): number => a + b;",
+        )
+        .unwrap();
+        assert!(result.success, "{}", result.message);
+        assert_eq!((result.lines_before, result.lines_after), (4, 1));
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with(
+            "export const add = (a: number, b: number): number => a + b;\n\nexport const double"
+        ));
+    }
+}
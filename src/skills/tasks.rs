@@ -0,0 +1,503 @@
+// ============================================
+// Task Tracking Skills
+// A persistent TODO list the agent keeps across iterations
+// ============================================
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::registry::{Skill, SkillDefinition};
+use crate::config::Settings;
+use crate::util::unix_now;
+
+/// Skills that read or change the task list
+pub const TASK_SKILLS: &[&str] = &["task_add", "task_update", "task_list"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Pending,
+    InProgress,
+    Done,
+    Blocked,
+}
+
+impl TaskStatus {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "pending" => Ok(Self::Pending),
+            "in_progress" => Ok(Self::InProgress),
+            "done" => Ok(Self::Done),
+            "blocked" => Ok(Self::Blocked),
+            other => anyhow::bail!(
+                "Unknown task status '{}' (expected pending, in_progress, done or blocked)",
+                other
+            ),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::InProgress => "in_progress",
+            Self::Done => "done",
+            Self::Blocked => "blocked",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl TaskPriority {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "low" => Ok(Self::Low),
+            "normal" | "medium" => Ok(Self::Normal),
+            "high" => Ok(Self::High),
+            other => anyhow::bail!("Unknown priority '{}' (expected low, normal or high)", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    /// Short id (`t1`, `t2`, ...), never reused within a run
+    pub id: String,
+    pub description: String,
+    #[serde(default)]
+    pub priority: TaskPriority,
+    pub status: TaskStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Task {
+    /// One line: `t2 [in_progress] (high) Parse config — waiting on schema`
+    pub fn compact(&self) -> String {
+        let mut line = format!("{} [{}]", self.id, self.status.as_str());
+        if self.priority != TaskPriority::Normal {
+            line.push_str(if self.priority == TaskPriority::High {
+                " (high)"
+            } else {
+                " (low)"
+            });
+        }
+        line.push(' ');
+        line.push_str(&self.description);
+        if let Some(note) = &self.note {
+            line.push_str(" — ");
+            line.push_str(note);
+        }
+        line
+    }
+}
+
+/// The tasks of one run, as stored in `tasks.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskList {
+    #[serde(default)]
+    next_id: usize,
+    #[serde(default)]
+    pub tasks: Vec<Task>,
+}
+
+impl TaskList {
+    pub fn add(&mut self, description: &str, priority: TaskPriority) -> Result<&Task> {
+        let description = description.trim();
+        if description.is_empty() {
+            anyhow::bail!("Task description must not be empty");
+        }
+        self.next_id += 1;
+        self.tasks.push(Task {
+            id: format!("t{}", self.next_id),
+            description: description.to_string(),
+            priority,
+            status: TaskStatus::Pending,
+            note: None,
+        });
+        Ok(self.tasks.last().unwrap())
+    }
+
+    pub fn update(&mut self, id: &str, status: TaskStatus, note: Option<&str>) -> Result<&Task> {
+        let task = self
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == id)
+            .with_context(|| format!("No task with id '{}' (task_list shows all ids)", id))?;
+        task.status = status;
+        if let Some(note) = note {
+            task.note = Some(note.trim().to_string()).filter(|n| !n.is_empty());
+        }
+        Ok(task)
+    }
+
+    pub fn done_count(&self) -> usize {
+        self.tasks.iter().filter(|t| t.status == TaskStatus::Done).count()
+    }
+
+    /// Tasks not done yet, highest priority and in-progress work first
+    pub fn open(&self) -> Vec<&Task> {
+        let mut open: Vec<&Task> = self
+            .tasks
+            .iter()
+            .filter(|t| t.status != TaskStatus::Done)
+            .collect();
+        open.sort_by_key(|t| (std::cmp::Reverse(t.priority), t.status != TaskStatus::InProgress));
+        open
+    }
+
+    /// True once at least one task exists and every task is done
+    pub fn all_done(&self) -> bool {
+        !self.tasks.is_empty() && self.done_count() == self.tasks.len()
+    }
+
+    /// Every task, one line each, for `task_list`
+    pub fn render(&self) -> String {
+        if self.tasks.is_empty() {
+            return "No tasks yet. Add one with task_add.".to_string();
+        }
+        let mut out = format!(
+            "{} done, {} remaining\n",
+            self.done_count(),
+            self.tasks.len() - self.done_count()
+        );
+        for task in &self.tasks {
+            out.push_str(&task.compact());
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Open tasks to remind the model of, capped at about `max_chars`.
+    /// None when there is nothing left to do.
+    pub fn open_summary(&self, max_chars: usize) -> Option<String> {
+        let open = self.open();
+        if open.is_empty() {
+            return None;
+        }
+        let mut out = format!(
+            "Open tasks ({} remaining, {} done). Update them with task_update as you go:\n",
+            open.len(),
+            self.done_count()
+        );
+        for (i, task) in open.iter().enumerate() {
+            let line = format!("- {}\n", task.compact());
+            if out.len() + line.len() > max_chars {
+                out.push_str(&format!("- ... and {} more (see task_list)\n", open.len() - i));
+                break;
+            }
+            out.push_str(&line);
+        }
+        Some(out.trim_end().to_string())
+    }
+
+    /// Done vs remaining, for the end-of-run report. None when no tasks were tracked.
+    pub fn report(&self) -> Option<String> {
+        if self.tasks.is_empty() {
+            return None;
+        }
+        let mut out = format!(
+            "Tasks: {} done, {} remaining\n",
+            self.done_count(),
+            self.tasks.len() - self.done_count()
+        );
+        for task in &self.tasks {
+            let mark = match task.status {
+                TaskStatus::Done => "[x]",
+                TaskStatus::InProgress => "[~]",
+                TaskStatus::Blocked => "[!]",
+                TaskStatus::Pending => "[ ]",
+            };
+            out.push_str(&format!("  {} {} {}", mark, task.id, task.description));
+            if let Some(note) = &task.note {
+                out.push_str(&format!(" ({})", note));
+            }
+            out.push('\n');
+        }
+        Some(out.trim_end().to_string())
+    }
+}
+
+#[derive(Debug, Default)]
+struct TaskState {
+    list: TaskList,
+    path: Option<PathBuf>,
+}
+
+/// Task list shared by the task skills. Every change happens under one lock
+/// and is written out before the lock is released, so parallel tool calls
+/// in a turn cannot lose each other's updates.
+#[derive(Debug, Default)]
+pub struct TaskStore {
+    state: Mutex<TaskState>,
+}
+
+impl TaskStore {
+    /// In-memory list until a run directory is set with `start_run`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start an empty list persisted to `<run_dir>/tasks.json`. The
+    /// directory is only created once the first task is added.
+    pub fn start_run(&self, run_dir: &Path) {
+        let mut state = self.state.lock().unwrap();
+        state.list = TaskList::default();
+        state.path = Some(run_dir.join("tasks.json"));
+    }
+
+    pub fn path(&self) -> Option<PathBuf> {
+        self.state.lock().unwrap().path.clone()
+    }
+
+    pub fn snapshot(&self) -> TaskList {
+        self.state.lock().unwrap().list.clone()
+    }
+
+    pub fn add(&self, description: &str, priority: TaskPriority) -> Result<Task> {
+        self.modify(|list| list.add(description, priority).cloned())
+    }
+
+    pub fn update(&self, id: &str, status: TaskStatus, note: Option<&str>) -> Result<Task> {
+        self.modify(|list| list.update(id, status, note).cloned())
+    }
+
+    fn modify<T>(&self, change: impl FnOnce(&mut TaskList) -> Result<T>) -> Result<T> {
+        let mut state = self.state.lock().unwrap();
+        let mut list = state.list.clone();
+        let value = change(&mut list)?;
+        if let Some(path) = &state.path {
+            save(path, &list)?;
+        }
+        state.list = list;
+        Ok(value)
+    }
+}
+
+fn save(path: &Path, list: &TaskList) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(list)?)
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// `.webrana/runs/<run_id>` under `root`
+pub fn run_dir(root: &Path, run_id: &str) -> PathBuf {
+    root.join(".webrana").join("runs").join(run_id)
+}
+
+/// Sortable, short run id: `<unix seconds>-<6 hex chars>`
+pub fn new_run_id() -> String {
+    let secs = unix_now();
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    format!("{}-{}", secs, &suffix[..6])
+}
+
+pub struct TaskAddSkill {
+    store: Arc<TaskStore>,
+}
+
+impl TaskAddSkill {
+    pub fn new(store: Arc<TaskStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Skill for TaskAddSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: "task_add".to_string(),
+            description: "Add a sub-task to your task list so you don't lose track of it. \
+                          Returns the task id."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "description": {
+                        "type": "string",
+                        "description": "What needs to be done"
+                    },
+                    "priority": {
+                        "type": "string",
+                        "enum": ["low", "normal", "high"],
+                        "description": "Defaults to normal"
+                    }
+                },
+                "required": ["description"]
+            }),
+            requires_confirmation: false,
+            parallel_safe: false,
+        }
+    }
+
+    async fn execute(&self, args: &Value, _settings: &Settings) -> Result<String> {
+        let description = args["description"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing description"))?;
+        let priority = match args["priority"].as_str() {
+            Some(p) => TaskPriority::parse(p)?,
+            None => TaskPriority::Normal,
+        };
+        let task = self.store.add(description, priority)?;
+        Ok(format!("Added {}", task.compact()))
+    }
+}
+
+pub struct TaskUpdateSkill {
+    store: Arc<TaskStore>,
+}
+
+impl TaskUpdateSkill {
+    pub fn new(store: Arc<TaskStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Skill for TaskUpdateSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: "task_update".to_string(),
+            description: "Change the status of a task, optionally with a short note \
+                          (e.g. why it is blocked)."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "id": {
+                        "type": "string",
+                        "description": "Task id from task_add or task_list (e.g. t3)"
+                    },
+                    "status": {
+                        "type": "string",
+                        "enum": ["pending", "in_progress", "done", "blocked"]
+                    },
+                    "note": {
+                        "type": "string",
+                        "description": "Short note stored with the task"
+                    }
+                },
+                "required": ["id", "status"]
+            }),
+            requires_confirmation: false,
+            parallel_safe: false,
+        }
+    }
+
+    async fn execute(&self, args: &Value, _settings: &Settings) -> Result<String> {
+        let id = args["id"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing id"))?;
+        let status = TaskStatus::parse(
+            args["status"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("Missing status"))?,
+        )?;
+        let task = self.store.update(id.trim(), status, args["note"].as_str())?;
+        Ok(format!("Updated {}", task.compact()))
+    }
+}
+
+pub struct TaskListSkill {
+    store: Arc<TaskStore>,
+}
+
+impl TaskListSkill {
+    pub fn new(store: Arc<TaskStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Skill for TaskListSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: "task_list".to_string(),
+            description: "Show your task list with ids and statuses".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {},
+                "required": []
+            }),
+            requires_confirmation: false,
+            parallel_safe: true,
+        }
+    }
+
+    async fn execute(&self, _args: &Value, _settings: &Settings) -> Result<String> {
+        Ok(self.store.snapshot().render())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_summary_and_report() {
+        let mut list = TaskList::default();
+        assert!(list.open_summary(500).is_none());
+        assert!(list.report().is_none());
+
+        list.add("Write the parser", TaskPriority::Normal).unwrap();
+        list.add("Fix the failing test", TaskPriority::High).unwrap();
+        list.add("Update docs", TaskPriority::Low).unwrap();
+        list.update("t1", TaskStatus::Done, None).unwrap();
+        list.update("t3", TaskStatus::Blocked, Some("needs API review")).unwrap();
+
+        let summary = list.open_summary(500).unwrap();
+        assert_eq!(
+            summary,
+            "Open tasks (2 remaining, 1 done). Update them with task_update as you go:\n\
+             - t2 [pending] (high) Fix the failing test\n\
+             - t3 [blocked] (low) Update docs — needs API review"
+        );
+
+        // The budget keeps the highest-priority task and counts the rest
+        let short = list.open_summary(120).unwrap();
+        assert!(short.contains("t2 [pending]"));
+        assert!(short.contains("... and 1 more"));
+        assert!(!short.contains("t3"));
+
+        assert_eq!(
+            list.report().unwrap(),
+            "Tasks: 1 done, 2 remaining\n  \
+             [x] t1 Write the parser\n  \
+             [ ] t2 Fix the failing test\n  \
+             [!] t3 Update docs (needs API review)"
+        );
+        assert!(!list.all_done());
+        list.update("t2", TaskStatus::Done, None).unwrap();
+        list.update("t3", TaskStatus::Done, Some("")).unwrap();
+        assert!(list.all_done());
+        assert!(list.open_summary(500).is_none());
+    }
+
+    #[test]
+    fn test_ids_are_stable_and_validated() {
+        let mut list = TaskList::default();
+        assert!(list.add("   ", TaskPriority::Normal).is_err());
+        assert_eq!(list.add("a", TaskPriority::Normal).unwrap().id, "t1");
+        assert_eq!(list.add("b", TaskPriority::Normal).unwrap().id, "t2");
+        assert!(list.update("t9", TaskStatus::Done, None).is_err());
+        assert!(TaskStatus::parse("finished").is_err());
+    }
+}
@@ -0,0 +1,986 @@
+// ============================================
+// Test-Impact Analysis Skill
+// Maps changed files to the tests that cover them
+// ============================================
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use super::registry::{Skill, SkillDefinition};
+use super::sandbox::resolve_working_dir;
+use super::shell::{shell_join, ExecuteCommandSkill};
+use crate::config::Settings;
+use crate::indexer::{FileWalker, ProjectDetector, ProjectType};
+use crate::util::sha256_hex;
+
+const JS_EXTENSIONS: [&str; 6] = ["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+const CACHE_FILE: &str = ".webrana/cache/import-graph.json";
+
+/// A single test target chosen by the selector, with the reason it was chosen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectedTest {
+    pub target: String,
+    pub reason: String,
+}
+
+/// Result of mapping changed files to tests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestSelection {
+    /// Commands to run (argv form)
+    pub commands: Vec<Vec<String>>,
+    /// Tests that were selected and why
+    pub selected: Vec<SelectedTest>,
+    /// True when selection fell back to the full suite
+    pub full: bool,
+}
+
+impl TestSelection {
+    fn full_suite(project_type: &ProjectType, reason: &str) -> Self {
+        let commands = project_type
+            .test_command()
+            .map(|cmd| vec![cmd.split_whitespace().map(String::from).collect()])
+            .unwrap_or_default();
+        Self {
+            commands,
+            selected: vec![SelectedTest {
+                target: "full suite".to_string(),
+                reason: reason.to_string(),
+            }],
+            full: true,
+        }
+    }
+
+    /// Human-readable report of which tests were selected and why
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        if self.full {
+            out.push_str("Running full test suite\n");
+        } else {
+            out.push_str(&format!("Selected {} test target(s):\n", self.selected.len()));
+        }
+        for test in &self.selected {
+            out.push_str(&format!("  - {} ({})\n", test.target, test.reason));
+        }
+        for cmd in &self.commands {
+            out.push_str(&format!("$ {}\n", cmd.join(" ")));
+        }
+        out
+    }
+}
+
+/// Parsed imports of a single file, keyed by content hash
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CachedImports {
+    hash: String,
+    specifiers: Vec<String>,
+}
+
+/// Per-file import cache, invalidated when a file's content hash changes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportCache {
+    entries: HashMap<String, CachedImports>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl ImportCache {
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Get the import specifiers of a file, re-parsing only if its content changed
+    fn specifiers(&mut self, file: &str, content: &str, lang: ImportLang) -> Vec<String> {
+        let hash = sha256_hex(content);
+        if let Some(cached) = self.entries.get(file) {
+            if cached.hash == hash {
+                return cached.specifiers.clone();
+            }
+        }
+
+        let specifiers = match lang {
+            ImportLang::JavaScript => parse_js_imports(content),
+            ImportLang::Python => parse_py_imports(content),
+        };
+        self.entries.insert(
+            file.to_string(),
+            CachedImports {
+                hash,
+                specifiers: specifiers.clone(),
+            },
+        );
+        self.dirty = true;
+        specifiers
+    }
+
+    /// Number of cached files
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ImportLang {
+    JavaScript,
+    Python,
+}
+
+/// Import graph over the files of one language, used to trace dependents
+pub struct ImportGraph {
+    /// file -> files it imports
+    imports: HashMap<String, Vec<String>>,
+}
+
+impl ImportGraph {
+    fn build(root: &Path, files: &[String], lang: ImportLang, cache: &mut ImportCache) -> Self {
+        let known: HashSet<&str> = files.iter().map(|f| f.as_str()).collect();
+        let mut imports = HashMap::new();
+
+        for file in files {
+            let Ok(content) = fs::read_to_string(root.join(file)) else {
+                continue;
+            };
+            let resolved: Vec<String> = cache
+                .specifiers(file, &content, lang)
+                .iter()
+                .filter_map(|spec| match lang {
+                    ImportLang::JavaScript => resolve_js_import(file, spec, &known),
+                    ImportLang::Python => resolve_py_import(file, spec, &known),
+                })
+                .collect();
+            imports.insert(file.clone(), resolved);
+        }
+
+        Self { imports }
+    }
+
+    /// Find all files that transitively import any of `changed`.
+    /// Returns each dependent with the changed file it was reached from.
+    pub fn dependents(&self, changed: &[String]) -> BTreeMap<String, String> {
+        let mut reverse: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (file, deps) in &self.imports {
+            for dep in deps {
+                reverse.entry(dep.as_str()).or_default().push(file.as_str());
+            }
+        }
+
+        let mut found = BTreeMap::new();
+        for origin in changed {
+            let mut queue = VecDeque::from([origin.as_str()]);
+            let mut seen = HashSet::from([origin.as_str()]);
+            while let Some(current) = queue.pop_front() {
+                for importer in reverse.get(current).into_iter().flatten() {
+                    if seen.insert(importer) {
+                        found
+                            .entry(importer.to_string())
+                            .or_insert_with(|| origin.clone());
+                        queue.push_back(importer);
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Extract import/require specifiers from JS/TS source
+fn parse_js_imports(content: &str) -> Vec<String> {
+    lazy_static::lazy_static! {
+        static ref JS_IMPORT: Regex = Regex::new(
+            r#"(?:import|export)\s[^'"`;]*?from\s*['"]([^'"]+)['"]|import\s*['"]([^'"]+)['"]|(?:require|import)\s*\(\s*['"]([^'"]+)['"]\s*\)"#
+        ).unwrap();
+    }
+
+    JS_IMPORT
+        .captures_iter(content)
+        .filter_map(|cap| cap.get(1).or(cap.get(2)).or(cap.get(3)))
+        .map(|m| m.as_str().to_string())
+        .collect()
+}
+
+/// Extract imported modules from Python source.
+/// Relative imports keep their leading dots; `from a import b` also yields `a.b`.
+fn parse_py_imports(content: &str) -> Vec<String> {
+    lazy_static::lazy_static! {
+        static ref PY_IMPORT: Regex = Regex::new(r"^\s*import\s+(.+)$").unwrap();
+        static ref PY_FROM: Regex = Regex::new(r"^\s*from\s+(\.*)([\w.]*)\s+import\s+\(?([^)#]+)").unwrap();
+    }
+
+    let mut modules = Vec::new();
+    for line in content.lines() {
+        if let Some(cap) = PY_FROM.captures(line) {
+            let base = format!("{}{}", &cap[1], &cap[2]);
+            modules.push(base.clone());
+            for name in cap[3].split(',') {
+                let name = name.split_whitespace().next().unwrap_or("");
+                if name.is_empty() || name == "*" {
+                    continue;
+                }
+                if base.ends_with('.') {
+                    modules.push(format!("{}{}", base, name));
+                } else {
+                    modules.push(format!("{}.{}", base, name));
+                }
+            }
+        } else if let Some(cap) = PY_IMPORT.captures(line) {
+            for module in cap[1].split(',') {
+                if let Some(name) = module.split_whitespace().next() {
+                    modules.push(name.to_string());
+                }
+            }
+        }
+    }
+    modules
+}
+
+/// Lexically normalize a relative path (resolve `.` and `..`)
+fn normalize(path: &Path) -> Option<String> {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            _ => return None,
+        }
+    }
+    Some(parts.join("/"))
+}
+
+fn resolve_js_import(from: &str, spec: &str, known: &HashSet<&str>) -> Option<String> {
+    if !spec.starts_with("./") && !spec.starts_with("../") {
+        return None; // package import
+    }
+
+    let dir = Path::new(from).parent().unwrap_or(Path::new(""));
+    let base = normalize(&dir.join(spec))?;
+
+    if known.contains(base.as_str()) {
+        return Some(base);
+    }
+    for ext in JS_EXTENSIONS {
+        let candidate = format!("{}.{}", base, ext);
+        if known.contains(candidate.as_str()) {
+            return Some(candidate);
+        }
+    }
+    for ext in JS_EXTENSIONS {
+        let candidate = format!("{}/index.{}", base, ext);
+        if known.contains(candidate.as_str()) {
+            return Some(candidate);
+        }
+    }
+    None
+}
+
+fn resolve_py_import(from: &str, module: &str, known: &HashSet<&str>) -> Option<String> {
+    let level = module.chars().take_while(|c| *c == '.').count();
+    let name = &module[level..];
+
+    let mut bases: Vec<PathBuf> = Vec::new();
+    if level > 0 {
+        let mut dir = Path::new(from).parent().unwrap_or(Path::new("")).to_path_buf();
+        for _ in 1..level {
+            dir = dir.parent()?.to_path_buf();
+        }
+        bases.push(dir);
+    } else {
+        bases.push(PathBuf::new());
+        bases.push(PathBuf::from("src"));
+    }
+
+    for base in bases {
+        let mut path = base;
+        for part in name.split('.').filter(|p| !p.is_empty()) {
+            path.push(part);
+        }
+        let path = normalize(&path)?;
+        let candidates = if path.is_empty() {
+            vec!["__init__.py".to_string()]
+        } else {
+            vec![format!("{}.py", path), format!("{}/__init__.py", path)]
+        };
+        for candidate in candidates {
+            if known.contains(candidate.as_str()) {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+fn is_js_test(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.contains(".test.") || name.contains(".spec.") || path.contains("__tests__/")
+}
+
+fn is_py_test(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.ends_with(".py") && (name.starts_with("test_") || name.ends_with("_test.py"))
+}
+
+fn extension(path: &str) -> &str {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+}
+
+/// Maps changed files to the tests that cover them
+pub struct TestSelector {
+    root: PathBuf,
+    cache_path: Option<PathBuf>,
+}
+
+impl TestSelector {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref().to_path_buf();
+        let cache_path = Some(root.join(CACHE_FILE));
+        Self { root, cache_path }
+    }
+
+    /// Disable the on-disk import cache
+    pub fn without_cache(mut self) -> Self {
+        self.cache_path = None;
+        self
+    }
+
+    /// Select the tests affected by `changed_files`
+    pub fn select_tests(&self, changed_files: &[String]) -> Result<TestSelection> {
+        let project_type = ProjectDetector::new(&self.root).detect()?.project_type;
+
+        let changed: Vec<String> = changed_files
+            .iter()
+            .filter_map(|f| self.relative(f))
+            .collect();
+        if changed.is_empty() {
+            return Ok(TestSelection::full_suite(&project_type, "no changed files given"));
+        }
+
+        let mut selection = TestSelection {
+            commands: Vec::new(),
+            selected: Vec::new(),
+            full: false,
+        };
+
+        let rust: Vec<String> = changed
+            .iter()
+            .filter(|f| extension(f) == "rs" || f.ends_with("Cargo.toml"))
+            .cloned()
+            .collect();
+        let js: Vec<String> = changed
+            .iter()
+            .filter(|f| JS_EXTENSIONS.contains(&extension(f)))
+            .cloned()
+            .collect();
+        let python: Vec<String> = changed
+            .iter()
+            .filter(|f| extension(f) == "py")
+            .cloned()
+            .collect();
+
+        if rust.is_empty() && js.is_empty() && python.is_empty() {
+            return Ok(TestSelection::full_suite(
+                &project_type,
+                "changed files are not mapped to any tests",
+            ));
+        }
+
+        let needs_graph = !js.is_empty() || !python.is_empty();
+        let files = if needs_graph { self.source_files()? } else { Vec::new() };
+        let mut cache = self
+            .cache_path
+            .as_deref()
+            .map(ImportCache::load)
+            .unwrap_or_default();
+
+        if !rust.is_empty() {
+            self.select_rust(&rust, &mut selection);
+        }
+        if !js.is_empty() {
+            self.select_js(&js, &files, &mut cache, &mut selection);
+        }
+        if !python.is_empty() {
+            self.select_python(&python, &files, &mut cache, &mut selection);
+        }
+
+        if let Some(path) = &self.cache_path {
+            if let Err(e) = cache.save(path) {
+                tracing::debug!("Failed to save import cache: {}", e);
+            }
+        }
+
+        if selection.commands.is_empty() {
+            return Ok(TestSelection::full_suite(
+                &project_type,
+                "no tests cover the changed files",
+            ));
+        }
+
+        Ok(selection)
+    }
+
+    /// Convert a path to be relative to the project root
+    fn relative(&self, path: &str) -> Option<String> {
+        let p = Path::new(path);
+        let rel = if p.is_absolute() {
+            let root = self.root.canonicalize().unwrap_or_else(|_| self.root.clone());
+            let p = p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+            p.strip_prefix(&root).ok()?.to_path_buf()
+        } else {
+            p.to_path_buf()
+        };
+        normalize(&rel).filter(|s| !s.is_empty())
+    }
+
+    fn source_files(&self) -> Result<Vec<String>> {
+        let mut walker = FileWalker::new(&self.root);
+        walker.load_gitignore()?;
+        Ok(walker
+            .walk()?
+            .into_iter()
+            .map(|entry| entry.path.replace('\\', "/"))
+            .filter(|path| {
+                let ext = extension(path);
+                ext == "py" || JS_EXTENSIONS.contains(&ext)
+            })
+            .collect())
+    }
+
+    /// Rust: map files to `cargo test -p <crate> <module>::` filters
+    fn select_rust(&self, changed: &[String], selection: &mut TestSelection) {
+        // crate name -> Some(filters) or None for the whole crate
+        let mut per_crate: BTreeMap<String, Option<Vec<RustFilter>>> = BTreeMap::new();
+
+        for file in changed {
+            let Some((crate_dir, crate_name)) = self.find_crate(file) else {
+                continue;
+            };
+            let rel = Path::new(file)
+                .strip_prefix(&crate_dir)
+                .unwrap_or(Path::new(file))
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            let target = rust_test_target(&rel);
+            let entry = per_crate.entry(crate_name.clone()).or_insert_with(|| Some(Vec::new()));
+            match target {
+                RustTarget::WholeCrate => *entry = None,
+                RustTarget::Filter(args) => {
+                    if let Some(filters) = entry {
+                        if !filters.iter().any(|(a, _)| *a == args) {
+                            filters.push((args, file.clone()));
+                        }
+                    }
+                }
+            }
+        }
+
+        for (crate_name, filters) in per_crate {
+            let base = vec![
+                "cargo".to_string(),
+                "test".to_string(),
+                "-p".to_string(),
+                crate_name.clone(),
+            ];
+            match filters {
+                None => {
+                    selection.commands.push(base);
+                    selection.selected.push(SelectedTest {
+                        target: crate_name.clone(),
+                        reason: "crate root or manifest changed".to_string(),
+                    });
+                }
+                Some(filters) => {
+                    for (args, file) in filters {
+                        let mut cmd = base.clone();
+                        cmd.extend(args.iter().cloned());
+                        selection.commands.push(cmd);
+                        selection.selected.push(SelectedTest {
+                            target: format!("{} {}", crate_name, args.join(" ")),
+                            reason: format!("{} changed", file),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Find the nearest crate (directory with a `[package]` Cargo.toml) for a file
+    fn find_crate(&self, file: &str) -> Option<(PathBuf, String)> {
+        let mut dir = Path::new(file).parent();
+        while let Some(d) = dir {
+            let manifest = self.root.join(d).join("Cargo.toml");
+            if let Ok(content) = fs::read_to_string(&manifest) {
+                if let Ok(parsed) = content.parse::<toml::Table>() {
+                    if let Some(name) = parsed
+                        .get("package")
+                        .and_then(|p| p.get("name"))
+                        .and_then(|n| n.as_str())
+                    {
+                        return Some((d.to_path_buf(), name.to_string()));
+                    }
+                }
+            }
+            dir = d.parent();
+        }
+        None
+    }
+
+    /// JS/TS: trace the import graph to test files
+    fn select_js(
+        &self,
+        changed: &[String],
+        files: &[String],
+        cache: &mut ImportCache,
+        selection: &mut TestSelection,
+    ) {
+        let js_files: Vec<String> = files
+            .iter()
+            .filter(|f| JS_EXTENSIONS.contains(&extension(f)))
+            .cloned()
+            .collect();
+        let graph = ImportGraph::build(&self.root, &js_files, ImportLang::JavaScript, cache);
+
+        let mut tests: BTreeMap<String, String> = BTreeMap::new();
+        for file in changed.iter().filter(|f| is_js_test(f)) {
+            tests.insert(file.clone(), "test file changed".to_string());
+        }
+        for (dependent, origin) in graph.dependents(changed) {
+            if is_js_test(&dependent) {
+                tests
+                    .entry(dependent)
+                    .or_insert_with(|| format!("imports {}", origin));
+            }
+        }
+
+        if tests.is_empty() {
+            return;
+        }
+
+        let mut cmd: Vec<String> = if self.uses_vitest() {
+            vec!["npx".into(), "vitest".into(), "run".into()]
+        } else {
+            vec!["npx".into(), "jest".into(), "--runTestsByPath".into()]
+        };
+        for (test, reason) in tests {
+            cmd.push(test.clone());
+            selection.selected.push(SelectedTest {
+                target: test,
+                reason,
+            });
+        }
+        selection.commands.push(cmd);
+    }
+
+    fn uses_vitest(&self) -> bool {
+        fs::read_to_string(self.root.join("package.json"))
+            .map(|content| content.contains("\"vitest\""))
+            .unwrap_or(false)
+    }
+
+    /// Python: `tests/test_<module>.py` conventions plus import tracing
+    fn select_python(
+        &self,
+        changed: &[String],
+        files: &[String],
+        cache: &mut ImportCache,
+        selection: &mut TestSelection,
+    ) {
+        let py_files: Vec<String> = files
+            .iter()
+            .filter(|f| extension(f) == "py")
+            .cloned()
+            .collect();
+        let graph = ImportGraph::build(&self.root, &py_files, ImportLang::Python, cache);
+
+        let mut tests: BTreeMap<String, String> = BTreeMap::new();
+        for file in changed {
+            if is_py_test(file) {
+                tests.insert(file.clone(), "test file changed".to_string());
+                continue;
+            }
+            let module = Path::new(file)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let conventional = [format!("test_{}.py", module), format!("{}_test.py", module)];
+            for candidate in &py_files {
+                let name = candidate.rsplit('/').next().unwrap_or(candidate);
+                if conventional.iter().any(|c| c == name) {
+                    tests
+                        .entry(candidate.clone())
+                        .or_insert_with(|| format!("naming convention for {}", file));
+                }
+            }
+        }
+        for (dependent, origin) in graph.dependents(changed) {
+            if is_py_test(&dependent) {
+                tests
+                    .entry(dependent)
+                    .or_insert_with(|| format!("imports {}", origin));
+            }
+        }
+
+        if tests.is_empty() {
+            return;
+        }
+
+        let mut cmd = vec!["pytest".to_string()];
+        for (test, reason) in tests {
+            cmd.push(test.clone());
+            selection.selected.push(SelectedTest {
+                target: test,
+                reason,
+            });
+        }
+        selection.commands.push(cmd);
+    }
+}
+
+enum RustTarget {
+    WholeCrate,
+    Filter(Vec<String>),
+}
+
+/// `cargo test` filter arguments and the changed file that produced them
+type RustFilter = (Vec<String>, String);
+
+/// Map a path (relative to its crate) to a `cargo test` filter
+fn rust_test_target(rel: &str) -> RustTarget {
+    if let Some(test) = rel.strip_prefix("tests/") {
+        let name = test.split('/').next().unwrap_or(test).trim_end_matches(".rs");
+        return RustTarget::Filter(vec!["--test".to_string(), name.to_string()]);
+    }
+
+    let Some(module_path) = rel.strip_prefix("src/") else {
+        return RustTarget::WholeCrate;
+    };
+    let module_path = module_path.trim_end_matches(".rs");
+    let mut parts: Vec<&str> = module_path.split('/').collect();
+    if parts.last() == Some(&"mod") {
+        parts.pop();
+    }
+    if parts.is_empty() || (parts.len() == 1 && matches!(parts[0], "lib" | "main")) {
+        return RustTarget::WholeCrate;
+    }
+    if parts[0] == "bin" {
+        return RustTarget::WholeCrate;
+    }
+
+    RustTarget::Filter(vec![format!("{}::", parts.join("::"))])
+}
+
+/// Run tests, optionally restricted to those affected by changed files
+pub struct RunTestsSkill;
+
+#[async_trait]
+impl Skill for RunTestsSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: "run_tests".to_string(),
+            description: "Run the project's tests. Pass changed_files to run only the tests \
+                          that cover them, or full=true to run the whole suite."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Project directory (defaults to current directory)"
+                    },
+                    "changed_files": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Files that changed; only tests covering them are run"
+                    },
+                    "full": {
+                        "type": "boolean",
+                        "description": "Run the full suite even if changed_files is given"
+                    }
+                },
+                "required": []
+            }),
+            requires_confirmation: true,
+            parallel_safe: false,
+        }
+    }
+
+    async fn execute(&self, args: &Value, settings: &Settings) -> Result<String> {
+        let path = args["path"].as_str().filter(|s| !s.is_empty());
+        let full = args["full"].as_bool().unwrap_or(false);
+        let changed: Vec<String> = args["changed_files"]
+            .as_array()
+            .map(|files| {
+                files
+                    .iter()
+                    .filter_map(|f| f.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Symlinks and `..` resolved; the project must be inside cwd
+        let cwd = std::env::current_dir()?;
+        let dir = cwd.join(resolve_working_dir(&cwd, path)?);
+        if !dir.is_dir() {
+            bail!("{} is not a directory", dir.display());
+        }
+
+        let selector = TestSelector::new(&dir);
+        let selection = if full {
+            let project_type = ProjectDetector::new(&dir).detect()?.project_type;
+            TestSelection::full_suite(&project_type, "full run requested")
+        } else {
+            selector.select_tests(&changed)?
+        };
+
+        if selection.commands.is_empty() {
+            bail!("No test command known for this project type");
+        }
+
+        // Each command goes through the shell skill's checks, sandbox and timeout
+        let shell = ExecuteCommandSkill::new();
+        let mut result = selection.report();
+        for argv in &selection.commands {
+            let command = shell_join(argv);
+            let run = shell.run(&command, path, false, settings).await?;
+            result.push_str(&format!("\n--- {} ---\n", command));
+            result.push_str(&run.output);
+            result.push('\n');
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let full = root.join(path);
+        fs::create_dir_all(full.parent().unwrap()).unwrap();
+        fs::write(full, content).unwrap();
+    }
+
+    fn targets(selection: &TestSelection) -> Vec<String> {
+        selection.selected.iter().map(|s| s.target.clone()).collect()
+    }
+
+    #[test]
+    fn test_parse_js_imports() {
+        let src = r#"
+import { a } from './a';
+import b from "../lib/b";
+import './side-effect';
+export * from './reexport';
+const c = require('./c');
+const d = await import('./d');
+import React from 'react';
+"#;
+        let imports = parse_js_imports(src);
+        assert_eq!(
+            imports,
+            vec!["./a", "../lib/b", "./side-effect", "./reexport", "./c", "./d", "react"]
+        );
+    }
+
+    #[test]
+    fn test_parse_py_imports() {
+        let src = "import os, app.utils as u\nfrom app.models import User, Group\nfrom . import helpers\nfrom ..core import thing\n";
+        let imports = parse_py_imports(src);
+        assert!(imports.contains(&"app.utils".to_string()));
+        assert!(imports.contains(&"app.models".to_string()));
+        assert!(imports.contains(&"app.models.User".to_string()));
+        assert!(imports.contains(&".helpers".to_string()));
+        assert!(imports.contains(&"..core.thing".to_string()));
+    }
+
+    #[test]
+    fn test_js_shared_utility_fans_out() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(root, "package.json", r#"{"devDependencies": {"jest": "29"}}"#);
+        write(root, "src/utils/format.ts", "export const f = 1;");
+        write(root, "src/a.ts", "import { f } from './utils/format';");
+        write(root, "src/b.ts", "const f = require('./utils/format');");
+        write(root, "src/c.ts", "export const unrelated = 1;");
+        write(root, "test/a.test.ts", "import { x } from '../src/a';");
+        write(root, "test/b.spec.ts", "import { y } from '../src/b';");
+        write(root, "test/c.test.ts", "import { z } from '../src/c';");
+        write(root, "src/__tests__/format.ts", "import { f } from '../utils/format';");
+
+        let selector = TestSelector::new(root).without_cache();
+        let selection = selector
+            .select_tests(&["src/utils/format.ts".to_string()])
+            .unwrap();
+
+        assert!(!selection.full);
+        assert_eq!(
+            targets(&selection),
+            vec!["src/__tests__/format.ts", "test/a.test.ts", "test/b.spec.ts"]
+        );
+        assert_eq!(selection.commands.len(), 1);
+        assert_eq!(&selection.commands[0][..3], &["npx", "jest", "--runTestsByPath"]);
+        assert!(selection.selected[1].reason.contains("src/utils/format.ts"));
+    }
+
+    #[test]
+    fn test_js_vitest_runner() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(root, "package.json", r#"{"devDependencies": {"vitest": "1"}}"#);
+        write(root, "src/index.js", "export default 1;");
+        write(root, "src/index.test.js", "import x from './index';");
+
+        let selection = TestSelector::new(root)
+            .without_cache()
+            .select_tests(&["src/index.js".to_string()])
+            .unwrap();
+        assert_eq!(
+            selection.commands[0],
+            vec!["npx", "vitest", "run", "src/index.test.js"]
+        );
+    }
+
+    #[test]
+    fn test_python_convention_and_imports() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(root, "pyproject.toml", "[project]\nname = \"app\"\n");
+        write(root, "app/__init__.py", "");
+        write(root, "app/utils.py", "def helper(): pass\n");
+        write(root, "app/models.py", "from app.utils import helper\n");
+        write(root, "app/views.py", "from . import models\n");
+        write(root, "tests/test_utils.py", "import os\n");
+        write(root, "tests/test_views.py", "from app.views import index\n");
+        write(root, "tests/test_other.py", "import json\n");
+
+        let selection = TestSelector::new(root)
+            .without_cache()
+            .select_tests(&["app/utils.py".to_string()])
+            .unwrap();
+
+        assert_eq!(
+            targets(&selection),
+            vec!["tests/test_utils.py", "tests/test_views.py"]
+        );
+        assert_eq!(selection.selected[0].reason, "naming convention for app/utils.py");
+        assert_eq!(selection.commands[0][0], "pytest");
+    }
+
+    #[test]
+    fn test_rust_module_filters() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(root, "Cargo.toml", "[workspace]\nmembers = [\"core\"]\n");
+        write(root, "core/Cargo.toml", "[package]\nname = \"my-core\"\n");
+        write(root, "core/src/lib.rs", "");
+        write(root, "core/src/parser/mod.rs", "");
+        write(root, "core/src/parser/lexer.rs", "");
+        write(root, "core/tests/integration.rs", "");
+
+        let selector = TestSelector::new(root).without_cache();
+        let selection = selector
+            .select_tests(&[
+                "core/src/parser/lexer.rs".to_string(),
+                "core/src/parser/mod.rs".to_string(),
+                "core/tests/integration.rs".to_string(),
+            ])
+            .unwrap();
+
+        assert_eq!(
+            selection.commands,
+            vec![
+                vec!["cargo", "test", "-p", "my-core", "parser::lexer::"],
+                vec!["cargo", "test", "-p", "my-core", "parser::"],
+                vec!["cargo", "test", "-p", "my-core", "--test", "integration"],
+            ]
+        );
+
+        let selection = selector
+            .select_tests(&["core/src/lib.rs".to_string(), "core/src/parser/lexer.rs".to_string()])
+            .unwrap();
+        assert_eq!(selection.commands, vec![vec!["cargo", "test", "-p", "my-core"]]);
+    }
+
+    #[test]
+    fn test_unmapped_changes_fall_back_to_full_suite() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(root, "Cargo.toml", "[package]\nname = \"demo\"\n");
+        write(root, "README.md", "# demo");
+
+        let selection = TestSelector::new(root)
+            .without_cache()
+            .select_tests(&["README.md".to_string()])
+            .unwrap();
+        assert!(selection.full);
+        assert_eq!(selection.commands, vec![vec!["cargo", "test"]]);
+    }
+
+    #[test]
+    fn test_import_cache_invalidated_by_hash() {
+        let mut cache = ImportCache::default();
+        let first = cache.specifiers("a.js", "import x from './x';", ImportLang::JavaScript);
+        assert_eq!(first, vec!["./x"]);
+        assert!(cache.dirty);
+
+        cache.dirty = false;
+        let again = cache.specifiers("a.js", "import x from './x';", ImportLang::JavaScript);
+        assert_eq!(again, vec!["./x"]);
+        assert!(!cache.dirty);
+
+        let changed = cache.specifiers("a.js", "import y from './y';", ImportLang::JavaScript);
+        assert_eq!(changed, vec!["./y"]);
+        assert!(cache.dirty);
+        assert_eq!(cache.len(), 1);
+        // Stable across toolchains: keyed by SHA-256, not DefaultHasher
+        assert_eq!(cache.entries["a.js"].hash, sha256_hex("import y from './y';"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commands_quoted_for_the_shell() {
+        let argv: Vec<String> = ["pytest", "tests/test_a.py", "-k", "it's a test"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(shell_join(&argv), "pytest tests/test_a.py -k 'it'\\''s a test'");
+    }
+
+    #[tokio::test]
+    async fn test_run_tests_stays_inside_cwd() {
+        let err = RunTestsSkill
+            .execute(&json!({"path": "/", "full": true}), &Settings::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("is outside"), "{}", err);
+    }
+}
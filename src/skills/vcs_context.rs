@@ -0,0 +1,141 @@
+// ============================================
+// WEBRANA CLI - Merge Request Skills
+// Read GitLab/Bitbucket merge request context and publish review comments
+// ============================================
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use super::registry::{Skill, SkillDefinition};
+use crate::config::Settings;
+use crate::vcs::{MrRef, ReviewComment};
+
+/// Title, description, branches, changed files and discussions of a
+/// merge/pull request
+pub struct ReviewContextSkill;
+
+#[async_trait]
+impl Skill for ReviewContextSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: "vcs_review_context".to_string(),
+            description: "Fetch a GitLab merge request or Bitbucket pull request: title, description, \
+                          branches, changed files and existing discussion threads"
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "mr": {
+                        "type": "string",
+                        "description": "Merge/pull request URL, or its number in this repository's origin project"
+                    }
+                },
+                "required": ["mr"]
+            }),
+            requires_confirmation: false,
+            parallel_safe: true,
+        }
+    }
+
+    async fn execute(&self, args: &Value, settings: &Settings) -> Result<String> {
+        let mr = MrRef::resolve(&mr_arg(args)?, settings).await?;
+        let context = mr.provider(settings)?.review_context(mr.number).await?;
+        Ok(context.render())
+    }
+}
+
+/// Starts a discussion on a line of a merge/pull request's diff
+pub struct PostReviewCommentSkill;
+
+#[async_trait]
+impl Skill for PostReviewCommentSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: "post_review_comment".to_string(),
+            description: "Publish a review finding as a discussion on a line of a merge/pull request \
+                          (the new version of the file)"
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "mr": {
+                        "type": "string",
+                        "description": "Merge/pull request URL, or its number in this repository's origin project"
+                    },
+                    "path": {"type": "string", "description": "File path in the merge request"},
+                    "line": {"type": "integer", "description": "Line number in the new version of the file"},
+                    "body": {"type": "string", "description": "Comment text (Markdown)"}
+                },
+                "required": ["mr", "path", "line", "body"]
+            }),
+            requires_confirmation: true,
+            parallel_safe: false,
+        }
+    }
+
+    async fn execute(&self, args: &Value, settings: &Settings) -> Result<String> {
+        if settings.shell.read_only {
+            bail!("Read-only mode: review comments are not published");
+        }
+        let comment = ReviewComment {
+            path: args["path"].as_str().unwrap_or_default().to_string(),
+            line: args["line"].as_u64().unwrap_or_default(),
+            body: args["body"].as_str().unwrap_or_default().trim().to_string(),
+        };
+        if comment.path.is_empty() || comment.line == 0 {
+            bail!("A file path and a line number (from 1) are required");
+        }
+        if comment.body.is_empty() {
+            bail!("The comment body is empty");
+        }
+
+        let mr = MrRef::resolve(&mr_arg(args)?, settings).await?;
+        let id = mr.provider(settings)?.post_comment(mr.number, &comment).await?;
+        Ok(format!(
+            "Posted a comment on {}:{} ({} discussion {})",
+            comment.path,
+            comment.line,
+            mr.kind.as_str(),
+            id
+        ))
+    }
+}
+
+/// The `mr` argument; numbers may arrive as JSON numbers
+fn mr_arg(args: &Value) -> Result<String> {
+    match &args["mr"] {
+        Value::String(text) if !text.trim().is_empty() => Ok(text.clone()),
+        Value::Number(number) => Ok(number.to_string()),
+        _ => bail!("Which merge request? Pass its URL or number as 'mr'"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skills::SkillRegistry;
+
+    #[tokio::test]
+    async fn test_post_refused_in_read_only_mode() {
+        let registry = SkillRegistry::new();
+        let mut settings = Settings::default();
+        settings.shell.read_only = true;
+        settings.vcs.gitlab_token = Some("glpat-test".to_string());
+
+        let args = json!({
+            "mr": "https://gitlab.example.com/team/app/-/merge_requests/3",
+            "path": "src/lib.rs",
+            "line": 4,
+            "body": "Typo"
+        });
+        let err = registry
+            .execute("post_review_comment", &args, &settings)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Read-only mode"), "{}", err);
+        assert!(registry.get("post_review_comment").unwrap().definition().requires_confirmation);
+        assert!(mr_arg(&json!({"mr": 12})).unwrap() == "12");
+        assert!(mr_arg(&json!({})).is_err());
+    }
+}
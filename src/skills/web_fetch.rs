@@ -0,0 +1,258 @@
+// ============================================
+// WEBRANA CLI - Web Fetch Skill
+// Read a web page as text, within safety.egress_allowlist
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use super::escalation::Escalation;
+use super::registry::{Skill, SkillDefinition};
+use crate::config::Settings;
+use crate::util::egress::EgressGuard;
+use crate::util::http;
+
+const DEFAULT_MAX_CHARS: usize = 20_000;
+const MAX_REDIRECTS: usize = 5;
+
+/// GET a URL and return its body, with HTML reduced to text. Every hop of a
+/// redirect is checked against the egress allowlist; without one, the user
+/// confirms each new host.
+pub struct WebFetchSkill {
+    escalation: Arc<Escalation>,
+}
+
+impl WebFetchSkill {
+    pub fn new(escalation: Arc<Escalation>) -> Self {
+        Self { escalation }
+    }
+
+    /// Ask before reaching a host no allowlist entry vouches for
+    fn confirm(&self, url: &url::Url, confirmed: &mut HashSet<String>) -> Result<()> {
+        let host = url.host_str().unwrap_or_default().to_string();
+        if confirmed.contains(&host) {
+            return Ok(());
+        }
+        let Some(confirmer) = self.escalation.confirmer() else {
+            bail!(
+                "web_fetch needs the user's confirmation for {}, which isn't available in auto or \
+                 non-interactive mode. Set safety.egress_allowlist to fetch unattended.",
+                host
+            );
+        };
+        if !confirmer(&format!("Fetch {}?", url)) {
+            bail!("The user declined to fetch {}", url);
+        }
+        confirmed.insert(host);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Skill for WebFetchSkill {
+    fn definition(&self) -> SkillDefinition {
+        SkillDefinition {
+            name: "web_fetch".to_string(),
+            description: "Fetch a web page (http or https) and return its text, e.g. documentation \
+                          or a changelog. Only hosts allowed by the egress allowlist, or confirmed by the \
+                          user when there is none, are reachable."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "url": {"type": "string", "description": "Absolute http(s) URL"},
+                    "max_chars": {
+                        "type": "integer",
+                        "description": "Truncate the text to this many characters (default 20000)"
+                    }
+                },
+                "required": ["url"]
+            }),
+            // Asks per host itself when there is no allowlist
+            requires_confirmation: false,
+            parallel_safe: true,
+        }
+    }
+
+    async fn execute(&self, args: &Value, settings: &Settings) -> Result<String> {
+        http::ensure_online("web_fetch")?;
+        let mut url = args["url"].as_str().unwrap_or_default().trim().to_string();
+        if url.is_empty() {
+            bail!("Which page? Pass an absolute URL as 'url'");
+        }
+        let max_chars = args["max_chars"].as_u64().map(|n| n as usize).unwrap_or(DEFAULT_MAX_CHARS);
+        let guard = EgressGuard::from_settings(settings)?;
+        let mut confirmed = HashSet::new();
+
+        for _ in 0..=MAX_REDIRECTS {
+            let permit = guard.authorize(&url, "web_fetch").await?;
+            if !guard.is_restricted() {
+                self.confirm(&permit.url, &mut confirmed)?;
+            }
+            let response = permit
+                .client()?
+                .get(permit.url.clone())
+                .send()
+                .await
+                .with_context(|| format!("Failed to fetch {}", permit.url))?;
+            let status = response.status();
+
+            if status.is_redirection() {
+                let location = response
+                    .headers()
+                    .get(reqwest::header::LOCATION)
+                    .and_then(|value| value.to_str().ok())
+                    .with_context(|| format!("{} redirected without a Location", permit.url))?;
+                url = permit.url.join(location)?.to_string();
+                continue;
+            }
+
+            let is_html = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|kind| kind.contains("html"));
+            // Markup and multibyte characters take more bytes than they leave text
+            let (body, cut) = read_capped(response, max_chars.saturating_mul(4)).await?;
+            if !status.is_success() {
+                bail!("{} returned {}", permit.url, status);
+            }
+            let body = String::from_utf8_lossy(&body).into_owned();
+            let text = if is_html { html_to_text(&body) } else { body };
+            if cut && text.chars().count() <= max_chars {
+                return Ok(format!("{}\n[page cut off after {} bytes]", text, max_chars.saturating_mul(4)));
+            }
+            return Ok(truncate(&text, max_chars));
+        }
+        bail!("{} redirected more than {} times", url, MAX_REDIRECTS)
+    }
+}
+
+/// The first `limit` bytes of the body, and whether there was more; the
+/// rest is never downloaded
+async fn read_capped(mut response: reqwest::Response, limit: usize) -> Result<(Vec<u8>, bool)> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            body.extend_from_slice(&chunk[..limit - body.len()]);
+            return Ok((body, true));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((body, false))
+}
+
+/// Drop scripts, styles and tags, decode the common entities and blank
+/// lines; enough for the model to read a page
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        let lower = tag.get(..8).unwrap_or(tag).to_ascii_lowercase();
+        let skip_to = ["<script", "<style"]
+            .iter()
+            .find(|open| lower.starts_with(*open))
+            .map(|open| format!("</{}", &open[1..]));
+        if let Some(close) = skip_to {
+            rest = match tag.to_ascii_lowercase().find(&close) {
+                Some(end) => tag[end..].split_once('>').map_or("", |(_, after)| after),
+                None => "",
+            };
+            continue;
+        }
+        rest = tag;
+        match rest.find('>') {
+            Some(end) => {
+                let name = rest[1..end].trim_start_matches('/').to_ascii_lowercase();
+                if ["p", "br", "div", "li", "h1", "h2", "h3", "h4", "tr", "pre"]
+                    .iter()
+                    .any(|block| name == *block || name.starts_with(&format!("{} ", block)))
+                {
+                    text.push('\n');
+                }
+                rest = &rest[end + 1..];
+            }
+            None => rest = "",
+        }
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    let mut out = String::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((at, _)) => format!("{}\n[truncated after {} characters]", &text[..at], max_chars),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_text() {
+        let html = "<html><head><style>p { color: red }</style><SCRIPT>alert('<p>')</SCRIPT></head>\
+                    <body><h1>Serde</h1><p>Fast &amp; generic</p>\n\n\n<p>Use <code>&lt;T&gt;</code></p></body></html>";
+        assert_eq!(html_to_text(html), "Serde\nFast & generic\nUse <T>\n");
+        assert_eq!(truncate("héllo", 2), "hé\n[truncated after 2 characters]");
+        assert_eq!(truncate("héllo", 5), "héllo");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_without_allowlist_needs_confirmation() {
+        let args = json!({ "url": "http://8.8.8.8/" });
+        let unattended = WebFetchSkill::new(Arc::new(Escalation::new()));
+        let err = unattended.execute(&args, &Settings::default()).await.unwrap_err();
+        assert!(err.to_string().contains("safety.egress_allowlist"), "{}", err);
+
+        let asked = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = asked.clone();
+        let escalation = Arc::new(Escalation::new());
+        escalation.set_confirmer(Some(Arc::new(move |q: &str| {
+            log.lock().unwrap().push(q.to_string());
+            false
+        })));
+        let err = WebFetchSkill::new(escalation).execute(&args, &Settings::default()).await.unwrap_err();
+        assert!(err.to_string().contains("declined"), "{}", err);
+        assert_eq!(*asked.lock().unwrap(), vec!["Fetch http://8.8.8.8/?".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_endless_body_is_read_up_to_the_cap() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\n");
+            // Until the client hangs up
+            while stream.write_all(&[b'a'; 8192]).is_ok() {}
+        });
+
+        let response = reqwest::get(&url).await.unwrap();
+        let (body, cut) = read_capped(response, 20_000).await.unwrap();
+        assert!(cut);
+        assert_eq!(body.len(), 20_000);
+    }
+}
@@ -1,6 +1,9 @@
 use colored::Colorize;
 
-use crate::config::Settings;
+use super::header::{self, SessionHeader};
+use super::plain::{self, plain_text};
+use crate::config::{BannerMode, Settings};
+use crate::core::Verbosity;
 use crate::skills::SkillRegistry;
 
 pub struct Console;
@@ -10,10 +13,15 @@ impl Console {
         Self
     }
 
+    /// Print the ASCII banner to stderr so stdout stays clean for piping.
+    /// Only with `ui.banner = "full"`; never in plain or accessible mode.
     pub fn banner(&self) {
+        if header::mode() != BannerMode::Full || plain::is_plain() || plain::is_a11y() {
+            return;
+        }
         let version = env!("CARGO_PKG_VERSION");
         
-        println!(
+        eprintln!(
             r#"
 {}
 {}
@@ -35,32 +43,65 @@ impl Console {
         );
     }
 
+    /// Print the session header to stderr unless `ui.banner` is off
+    pub fn session_header(&self, session: &SessionHeader) {
+        if header::mode() != BannerMode::Off {
+            self.print_session_header(session);
+        }
+    }
+
+    /// Print the REPL's warm-start block to stderr unless `ui.banner` is off
+    pub fn warm_start(&self, block: &str) {
+        if header::mode() != BannerMode::Off {
+            eprintln!("{}\n", block.dimmed());
+        }
+    }
+
+    /// Print the session header regardless of `ui.banner` (`/status`)
+    pub fn print_session_header(&self, session: &SessionHeader) {
+        let lines = session.lines();
+        if let Some((first, rest)) = lines.split_first() {
+            eprintln!("{}", first.bright_cyan());
+            for line in rest {
+                eprintln!("{}", line.dimmed());
+            }
+        }
+    }
+
+    /// Hidden under `--quiet`; warnings and errors always print
     pub fn info(&self, message: &str) {
-        println!("{} {}", "[INFO]".blue(), message);
+        if Verbosity::current() == Verbosity::Quiet {
+            return;
+        }
+        println!("{} {}", "[INFO]".blue(), plain_text(message));
     }
 
     pub fn warn(&self, message: &str) {
-        println!("{} {}", "[WARN]".yellow(), message);
+        println!("{} {}", "[WARN]".yellow(), plain_text(message));
     }
 
     pub fn error(&self, message: &str) {
-        println!("{} {}", "[ERROR]".red(), message);
+        println!("{} {}", "[ERROR]".red(), plain_text(message));
     }
 
     pub fn success(&self, message: &str) {
-        println!("{} {}", "[OK]".green(), message);
+        if Verbosity::current() == Verbosity::Quiet {
+            return;
+        }
+        println!("{} {}", "[OK]".green(), plain_text(message));
     }
 
     pub fn user_message(&self, message: &str) {
-        println!("\n{} {}", "▶".cyan().bold(), message);
+        let marker = if plain::is_a11y() { "You:" } else { "▶" };
+        println!("\n{} {}", marker.cyan().bold(), plain_text(message));
     }
 
     pub fn agent_message(&self, agent: &str, message: &str) {
         println!(
             "\n{} {}\n{}",
             format!("[{}]", agent).green().bold(),
-            "━".repeat(50).dimmed(),
-            message
+            plain::rule("━", 50).dimmed(),
+            plain_text(message)
         );
     }
 
@@ -68,18 +109,19 @@ impl Console {
         println!(
             "\n{} {}\n{}",
             format!("[TOOL:{}]", tool).magenta(),
-            "─".repeat(40).dimmed(),
+            plain::rule("─", 40).dimmed(),
             result.dimmed()
         );
     }
 
     pub fn list_agents(&self, settings: &Settings) {
         println!("\n{}", "AVAILABLE AGENTS".bold().underline());
-        println!("{}", "─".repeat(50));
+        println!("{}", plain::rule("─", 50));
+        let bullet = if plain::is_a11y() { "" } else { "•" };
         for (key, agent) in &settings.agents {
             println!(
                 "  {} {} {}",
-                "•".cyan(),
+                bullet.cyan(),
                 key.cyan().bold(),
                 format!("({})", agent.model).dimmed()
             );
@@ -92,7 +134,7 @@ impl Console {
         let skills = registry.list();
 
         println!("\n{}", "AVAILABLE SKILLS".bold().underline());
-        println!("{}", "─".repeat(50));
+        println!("{}", plain::rule("─", 50));
 
         // Group skills by category
         let file_skills: Vec<_> = skills
@@ -141,7 +183,9 @@ impl Console {
     }
 
     fn print_skill(&self, skill: &crate::skills::SkillDefinition) {
-        let confirm = if skill.requires_confirmation {
+        let confirm = if skill.requires_confirmation && (plain::is_plain() || plain::is_a11y()) {
+            " (confirm)".to_string()
+        } else if skill.requires_confirmation {
             " ⚠".yellow().to_string()
         } else {
             String::new()
@@ -156,18 +200,23 @@ impl Console {
 
     pub fn show_config(&self, settings: &Settings) {
         println!("\n{}", "CONFIGURATION".bold().underline());
-        println!("{}", "─".repeat(50));
+        println!("{}", plain::rule("─", 50));
 
         println!("\n  {}", "Models:".yellow());
         for (key, model) in &settings.models {
             let is_default = key == &settings.default_model;
-            let marker = if is_default { "→ " } else { "  " };
+            let (marker, suffix) = match (is_default, plain::is_a11y()) {
+                (true, true) => ("  ", " (default)"),
+                (true, false) => ("→ ", ""),
+                (false, _) => ("  ", ""),
+            };
             println!(
-                "  {}{} {} {}",
+                "  {}{} {} {}{}",
                 marker.green(),
                 key.cyan().bold(),
                 format!("({})", model.provider).dimmed(),
-                model.model.dimmed()
+                model.model.dimmed(),
+                suffix
             );
         }
 
@@ -0,0 +1,160 @@
+// ============================================
+// WEBRANA CLI - Session Header
+// The line or two printed when a session starts (and by /status): model,
+// crew, directory, flags and tools at a glance
+// ============================================
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::config::BannerMode;
+
+/// Off until `set_mode`, so tests and embedded use print nothing
+static MODE: AtomicU8 = AtomicU8::new(2);
+
+/// Set from `ui.banner`; `--quiet`, CI, plain and machine-readable output force `Off`
+pub fn set_mode(mode: BannerMode) {
+    let value = match mode {
+        BannerMode::Full => 0,
+        BannerMode::Compact => 1,
+        BannerMode::Off => 2,
+    };
+    MODE.store(value, Ordering::Relaxed);
+}
+
+pub fn mode() -> BannerMode {
+    match MODE.load(Ordering::Relaxed) {
+        0 => BannerMode::Full,
+        1 => BannerMode::Compact,
+        _ => BannerMode::Off,
+    }
+}
+
+/// Everything the header shows, gathered when the orchestrator is built
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionHeader {
+    pub version: String,
+    /// `provider/model` of the primary model, then its fallbacks in order
+    pub models: Vec<String>,
+    pub crew: Option<String>,
+    pub directory: String,
+    pub auto: bool,
+    pub yolo: bool,
+    pub read_only: bool,
+    pub tools: usize,
+    pub mcp_servers: usize,
+    pub plugins: usize,
+    pub semantic_index: bool,
+}
+
+impl SessionHeader {
+    /// The header text, without styling
+    pub fn lines(&self) -> Vec<String> {
+        let mut first = vec![format!("webrana v{}", self.version), self.models.join(" → ")];
+        if let Some(crew) = &self.crew {
+            first.push(format!("crew {}", crew));
+        }
+        first.push(self.directory.clone());
+
+        let flags: Vec<&str> = [
+            (self.auto, "auto"),
+            (self.yolo, "yolo"),
+            (self.read_only, "read-only"),
+        ]
+        .into_iter()
+        .filter_map(|(on, flag)| on.then_some(flag))
+        .collect();
+        let mut second = Vec::new();
+        if !flags.is_empty() {
+            second.push(flags.join(", "));
+        }
+        let mut tools = vec![count(self.tools, "tool")];
+        if self.mcp_servers > 0 {
+            tools.push(count(self.mcp_servers, "MCP server"));
+        }
+        if self.plugins > 0 {
+            tools.push(count(self.plugins, "plugin"));
+        }
+        second.push(tools.join(" + "));
+        second.push(if self.semantic_index { "semantic index" } else { "no semantic index" }.to_string());
+
+        vec![first.join(" · "), second.join(" · ")]
+    }
+}
+
+fn count(n: usize, noun: &str) -> String {
+    format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" })
+}
+
+/// `dir` with the home directory shown as `~`
+pub fn abbreviate_home(dir: &Path, home: Option<&Path>) -> String {
+    match home.and_then(|home| dir.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => dir.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> SessionHeader {
+        SessionHeader {
+            version: "0.4.0".to_string(),
+            models: vec!["anthropic/claude-sonnet-4-20250514".to_string()],
+            directory: abbreviate_home(Path::new("/home/ada/src/shop"), Some(Path::new("/home/ada"))),
+            tools: 31,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_session() {
+        assert_eq!(
+            header().lines(),
+            [
+                "webrana v0.4.0 · anthropic/claude-sonnet-4-20250514 · ~/src/shop",
+                "31 tools · no semantic index",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_crew_and_fallbacks() {
+        let header = SessionHeader {
+            models: vec![
+                "anthropic/claude-sonnet-4-20250514".to_string(),
+                "openai/gpt-4o".to_string(),
+                "ollama/llama3".to_string(),
+            ],
+            crew: Some("Code Reviewer".to_string()),
+            auto: true,
+            mcp_servers: 2,
+            plugins: 1,
+            semantic_index: true,
+            ..header()
+        };
+        assert_eq!(
+            header.lines(),
+            [
+                "webrana v0.4.0 · anthropic/claude-sonnet-4-20250514 → openai/gpt-4o → ollama/llama3 · crew Code Reviewer · ~/src/shop",
+                "auto · 31 tools + 2 MCP servers + 1 plugin · semantic index",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_only_outside_home() {
+        let header = SessionHeader {
+            directory: abbreviate_home(Path::new("/srv/app"), Some(Path::new("/home/ada"))),
+            yolo: true,
+            read_only: true,
+            tools: 1,
+            ..header()
+        };
+        assert_eq!(header.lines()[1], "yolo, read-only · 1 tool · no semantic index");
+        assert!(header.lines()[0].ends_with(" · /srv/app"));
+        assert_eq!(abbreviate_home(Path::new("/home/ada"), Some(Path::new("/home/ada"))), "~");
+    }
+}
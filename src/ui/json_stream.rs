@@ -0,0 +1,67 @@
+// ============================================
+// WEBRANA CLI - Streaming JSON Arrays
+// Large results written item by item instead of serialized at once
+// ============================================
+
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Write;
+
+/// Writes `[`, one item per line separated by commas, then `]`. The output
+/// is a complete JSON array once [`JsonArrayWriter::finish`] is called.
+pub struct JsonArrayWriter<W: Write> {
+    out: W,
+    count: usize,
+}
+
+impl<W: Write> JsonArrayWriter<W> {
+    pub fn new(mut out: W) -> Result<Self> {
+        out.write_all(b"[")?;
+        Ok(Self { out, count: 0 })
+    }
+
+    /// Write one item and flush, so readers see it right away
+    pub fn push<T: Serialize>(&mut self, item: &T) -> Result<()> {
+        let separator: &[u8] = if self.count == 0 { b"\n  " } else { b",\n  " };
+        self.out.write_all(separator)?;
+        serde_json::to_writer(&mut self.out, item)?;
+        self.out.flush()?;
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn finish(mut self) -> Result<W> {
+        let close: &[u8] = if self.count == 0 { b"]\n" } else { b"\n]\n" };
+        self.out.write_all(close)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streamed_array_matches_batch() {
+        let items = vec![
+            serde_json::json!({"file": "a.env", "line": 1}),
+            serde_json::json!({"file": "b \"quoted\".env", "line": 22}),
+        ];
+        let mut writer = JsonArrayWriter::new(Vec::new()).unwrap();
+        for item in &items {
+            writer.push(item).unwrap();
+        }
+        assert_eq!(writer.count(), 2);
+        let out = writer.finish().unwrap();
+        let parsed: Vec<serde_json::Value> = serde_json::from_slice(&out).unwrap();
+        assert_eq!(parsed, items);
+
+        let empty = JsonArrayWriter::new(Vec::new()).unwrap().finish().unwrap();
+        assert_eq!(empty, b"[]\n");
+    }
+}
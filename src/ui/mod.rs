@@ -1,3 +1,14 @@
 mod console;
+pub mod header;
+mod json_stream;
+pub mod plain;
+mod progress;
+mod template;
 
 pub use console::Console;
+pub use header::SessionHeader;
+pub use json_stream::JsonArrayWriter;
+#[allow(unused_imports)]
+pub use plain::{is_a11y, is_plain, plain_text};
+pub use progress::Progress;
+pub use template::{OutputTemplate, TemplateValues};
@@ -0,0 +1,245 @@
+// ============================================
+// WEBRANA CLI - Plain and Accessible Output Modes
+// Plain: no colors, banner, emoji or cursor movement (CI logs, --plain)
+// Accessible: text labels and sentences for screen readers (--a11y)
+// ============================================
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Variables set by common CI systems, besides the generic `CI`
+const CI_VARS: &[&str] = &[
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "BUILDKITE",
+    "CIRCLECI",
+    "TRAVIS",
+    "JENKINS_URL",
+    "TEAMCITY_VERSION",
+    "TF_BUILD",
+    "BITBUCKET_BUILD_NUMBER",
+    "DRONE",
+    "APPVEYOR",
+    "CODEBUILD_BUILD_ID",
+];
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+static A11Y: AtomicBool = AtomicBool::new(false);
+
+/// Emoji that carry meaning, and the words a screen reader should hear
+const EMOJI_LABELS: &[(&str, &str)] = &[
+    ("🔴", "critical"),
+    ("🟠", "high"),
+    ("🟡", "medium"),
+    ("🟢", "low"),
+    ("✅", "ok"),
+    ("❌", "failed"),
+    ("⚠️", "warning"),
+    ("⚠", "warning"),
+    ("🛡️", "security"),
+    ("🛡", "security"),
+];
+
+fn truthy(value: &str) -> bool {
+    let value = value.trim();
+    !value.is_empty() && !matches!(value.to_lowercase().as_str(), "0" | "false" | "no" | "off")
+}
+
+/// Whether the variables returned by `var` describe a CI run
+pub fn ci_detected_with(var: impl Fn(&str) -> Option<String>) -> bool {
+    std::iter::once("CI")
+        .chain(CI_VARS.iter().copied())
+        .any(|name| var(name).is_some_and(|v| truthy(&v)))
+}
+
+pub fn ci_detected() -> bool {
+    ci_detected_with(|name| std::env::var(name).ok())
+}
+
+/// Switch the process to plain output; also turns off `colored` styling
+pub fn set_plain(plain: bool) {
+    PLAIN.store(plain, Ordering::Relaxed);
+    if plain {
+        colored::control::set_override(false);
+    }
+}
+
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// Switch to accessible output: text labels instead of emoji and symbols,
+/// sentences instead of spinners and bars, and no column layouts. Unlike
+/// plain mode, colors stay on; they are just never the only signal.
+pub fn set_a11y(a11y: bool) {
+    A11Y.store(a11y, Ordering::Relaxed);
+}
+
+pub fn is_a11y() -> bool {
+    A11Y.load(Ordering::Relaxed)
+}
+
+/// Whether to suggest `--a11y`: `TERM=dumb` (common with screen readers'
+/// terminal modes), unless `WEBRANA_A11Y` already says either way
+pub fn suggest_a11y(term: Option<&str>, a11y_var: Option<&str>) -> bool {
+    term == Some("dumb") && a11y_var.is_none()
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2B00..=0x2BFF | 0xFE0F | 0x200D
+    )
+}
+
+/// `text` without emoji (and the space that followed each one)
+pub fn strip_emoji(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if is_emoji(c) {
+            while chars.peek().is_some_and(|&n| is_emoji(n)) {
+                chars.next();
+            }
+            if chars.peek() == Some(&' ') {
+                chars.next();
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// `text` with meaningful emoji spelled out ("❌ Build" -> "failed: Build")
+/// and the rest dropped. A label the text already says next to the emoji
+/// ("🔴 CRITICAL") isn't repeated.
+pub fn label_emoji(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while !rest.is_empty() {
+        let Some((emoji, label)) = EMOJI_LABELS.iter().find(|(emoji, _)| rest.starts_with(emoji)) else {
+            let c = rest.chars().next().unwrap();
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        rest = rest[emoji.len()..].trim_start_matches('\u{FE0F}').trim_start();
+        let next_word = rest.split(|c: char| !c.is_alphanumeric()).next().unwrap_or_default();
+        if !next_word.eq_ignore_ascii_case(label) {
+            out.push_str(label);
+            out.push_str(": ");
+        }
+    }
+    strip_emoji(&out)
+}
+
+/// `text` as it should be printed in the current mode
+pub fn plain_text(text: &str) -> Cow<'_, str> {
+    if is_a11y() {
+        Cow::Owned(label_emoji(text))
+    } else if is_plain() {
+        Cow::Owned(strip_emoji(text))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// A horizontal rule of `ch`, or nothing in accessible mode
+pub fn rule(ch: &str, width: usize) -> String {
+    Mode::current().rule(ch, width)
+}
+
+/// The output mode switches, read once so output built from them can be
+/// tested without touching the process-wide flags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Mode {
+    pub plain: bool,
+    pub a11y: bool,
+}
+
+impl Mode {
+    pub fn current() -> Self {
+        Self {
+            plain: is_plain(),
+            a11y: is_a11y(),
+        }
+    }
+
+    /// A horizontal rule of `ch`, or nothing in accessible mode
+    pub fn rule(self, ch: &str, width: usize) -> String {
+        if self.a11y {
+            String::new()
+        } else {
+            ch.repeat(width)
+        }
+    }
+
+    /// `glyph`, or `label` where symbols would be read out or mangled
+    pub fn marker<'a>(self, glyph: &'a str, label: &'a str) -> &'a str {
+        if self.plain || self.a11y {
+            label
+        } else {
+            glyph
+        }
+    }
+}
+
+/// One table row as a `title` line followed by indented `key: value`
+/// lines, for accessible mode in place of aligned columns
+pub fn record(title: &str, fields: &[(&str, String)]) -> String {
+    let mut out = format!("{}\n", title);
+    for (key, value) in fields.iter().filter(|(_, value)| !value.is_empty()) {
+        out.push_str(&format!("  {}: {}\n", key, value));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ci_detection() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert!(ci_detected_with(env(&[("CI", "true")])));
+        assert!(ci_detected_with(env(&[("GITHUB_ACTIONS", "true")])));
+        assert!(ci_detected_with(env(&[("JENKINS_URL", "http://ci")])));
+        assert!(!ci_detected_with(env(&[("CI", "false")])));
+        assert!(!ci_detected_with(env(&[("CI", "")])));
+        assert!(!ci_detected_with(env(&[("HOME", "/root")])));
+    }
+
+    #[test]
+    fn test_label_emoji() {
+        assert_eq!(label_emoji("🔴 CRITICAL: a.env:1"), "CRITICAL: a.env:1");
+        assert_eq!(label_emoji("❌ Build failed"), "failed: Build failed");
+        assert_eq!(label_emoji("⚠️ High-risk command"), "warning: High-risk command");
+        assert_eq!(label_emoji("🛡️ SECURITY: blocked"), "SECURITY: blocked");
+        assert_eq!(label_emoji("📊 Webrana API Status"), "Webrana API Status");
+        assert_eq!(label_emoji("café → done"), "café → done");
+    }
+
+    #[test]
+    fn test_record_and_suggestion() {
+        let record = record("Message 2", &[("role", "user".to_string()), ("status", String::new())]);
+        assert_eq!(record, "Message 2\n  role: user\n");
+        assert!(suggest_a11y(Some("dumb"), None));
+        assert!(!suggest_a11y(Some("dumb"), Some("0")));
+        assert!(!suggest_a11y(Some("xterm-256color"), None));
+    }
+
+    #[test]
+    fn test_strip_emoji() {
+        assert_eq!(strip_emoji("📝 Ask mode"), "Ask mode");
+        assert_eq!(strip_emoji("🔴 CRITICAL: a.env:1"), "CRITICAL: a.env:1");
+        assert_eq!(strip_emoji("⚠️  careful"), " careful");
+        assert_eq!(strip_emoji("café → done"), "café → done");
+    }
+}
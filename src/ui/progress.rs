@@ -0,0 +1,137 @@
+// ============================================
+// WEBRANA CLI - Progress Rendering
+// One renderer for indexing and scanning progress
+// ============================================
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use super::plain;
+use crate::core::Verbosity;
+
+/// Time between progress lines in plain mode
+const PLAIN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Minimum time between redraws of the live line
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+const FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Progress on stderr: a live spinner line on a terminal, or a plain
+/// `label: done/total` line every few seconds in plain mode and when piped.
+/// Accessible mode never animates and writes sentences instead of counters.
+pub struct Progress {
+    label: String,
+    live: bool,
+    accessible: bool,
+    hidden: bool,
+    started: Instant,
+    last_output: Option<Instant>,
+    frame: usize,
+}
+
+impl Progress {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            live: !plain::is_plain() && !plain::is_a11y() && atty::is(atty::Stream::Stderr),
+            accessible: plain::is_a11y(),
+            hidden: Verbosity::current() == Verbosity::Quiet,
+            started: Instant::now(),
+            last_output: None,
+            frame: 0,
+        }
+    }
+
+    pub fn update(&mut self, done: usize, total: Option<usize>) {
+        if let Some(line) = self.render(done, total, Instant::now()) {
+            let mut stderr = std::io::stderr();
+            let _ = stderr.write_all(line.as_bytes());
+            let _ = stderr.flush();
+        }
+    }
+
+    /// Clear the live line
+    pub fn finish(&mut self) {
+        if self.live && !self.hidden && self.last_output.is_some() {
+            eprint!("\r\x1b[2K");
+        }
+    }
+
+    /// Text to write for this update, if any is due
+    fn render(&mut self, done: usize, total: Option<usize>, now: Instant) -> Option<String> {
+        if self.hidden {
+            return None;
+        }
+        let status = match (total, self.accessible) {
+            (Some(total), true) => format!("{}: {} of {} files done", self.label, done, total),
+            (None, true) => format!("{}: {} files so far", self.label, done),
+            (Some(total), false) => format!("{}: {}/{}", self.label, done, total),
+            (None, false) => format!("{}: {}", self.label, done),
+        };
+
+        if self.live {
+            if self.last_output.is_some_and(|t| now - t < REDRAW_INTERVAL) {
+                return None;
+            }
+            self.last_output = Some(now);
+            self.frame = (self.frame + 1) % FRAMES.len();
+            Some(format!("\r\x1b[2K{} {}", FRAMES[self.frame], status))
+        } else {
+            let since = self.last_output.unwrap_or(self.started);
+            if now - since < PLAIN_INTERVAL {
+                return None;
+            }
+            self.last_output = Some(now);
+            Some(format!("{}\n", status))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_progress_is_periodic_lines() {
+        let mut progress = Progress::new("Indexing");
+        progress.live = false;
+        progress.hidden = false;
+        let start = progress.started;
+
+        assert_eq!(progress.render(1, Some(10), start + Duration::from_secs(1)), None);
+        assert_eq!(
+            progress.render(4, Some(10), start + Duration::from_secs(5)),
+            Some("Indexing: 4/10\n".to_string())
+        );
+        assert_eq!(progress.render(5, Some(10), start + Duration::from_secs(7)), None);
+        let line = progress.render(9, None, start + Duration::from_secs(11)).unwrap();
+        assert_eq!(line, "Indexing: 9\n");
+        assert!(!line.contains('\x1b') && !line.contains('\r'));
+    }
+
+    #[test]
+    fn test_accessible_progress_sequence() {
+        let mut progress = Progress::new("Indexing");
+        progress.live = false;
+        progress.accessible = true;
+        progress.hidden = false;
+        let start = progress.started;
+
+        let lines: String = [(100, 1), (400, 5), (500, 6), (900, 10), (1200, 15)]
+            .iter()
+            .filter_map(|(done, secs)| progress.render(*done, Some(1200), start + Duration::from_secs(*secs)))
+            .collect();
+        assert_eq!(
+            lines,
+            "Indexing: 400 of 1200 files done\nIndexing: 900 of 1200 files done\nIndexing: 1200 of 1200 files done\n"
+        );
+
+        let mut progress = Progress::new("Scanning");
+        progress.live = false;
+        progress.accessible = true;
+        progress.hidden = false;
+        let line = progress.render(42, None, progress.started + Duration::from_secs(5)).unwrap();
+        assert_eq!(line, "Scanning: 42 files so far\n");
+    }
+}
@@ -0,0 +1,139 @@
+// ============================================
+// WEBRANA CLI - Output Templates
+// `--output-template '{model}: {response}'` for shell scripting
+// ============================================
+
+use std::str::FromStr;
+
+/// Placeholders `ask --output-template` understands
+pub const PLACEHOLDERS: &[&str] = &["query", "response", "tokens", "model", "cost"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Placeholder(&'static str),
+}
+
+/// A parsed template. `{{` and `}}` are literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputTemplate {
+    parts: Vec<Part>,
+}
+
+/// Values available to a template after the call completes
+#[derive(Debug, Clone, Default)]
+pub struct TemplateValues {
+    pub query: String,
+    pub response: String,
+    /// Estimated prompt + response tokens
+    pub tokens: usize,
+    pub model: String,
+    /// USD, when the model's price is known
+    pub cost: Option<f64>,
+}
+
+impl OutputTemplate {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("Unclosed placeholder '{{{}'", name)),
+                        }
+                    }
+                    let placeholder = PLACEHOLDERS
+                        .iter()
+                        .find(|p| **p == name.trim())
+                        .ok_or_else(|| {
+                            format!(
+                                "Unknown placeholder '{{{}}}' (available: {})",
+                                name,
+                                PLACEHOLDERS
+                                    .iter()
+                                    .map(|p| format!("{{{}}}", p))
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            )
+                        })?;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Placeholder(placeholder));
+                }
+                '}' => return Err("Unmatched '}' (use '}}' for a literal brace)".to_string()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Self { parts })
+    }
+
+    pub fn render(&self, values: &TemplateValues) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.push_str(text),
+                Part::Placeholder(name) => match *name {
+                    "query" => out.push_str(&values.query),
+                    "response" => out.push_str(values.response.trim_end()),
+                    "tokens" => out.push_str(&values.tokens.to_string()),
+                    "model" => out.push_str(&values.model),
+                    "cost" => match values.cost {
+                        Some(cost) => out.push_str(&format!("${:.4}", cost)),
+                        None => out.push_str("unknown"),
+                    },
+                    _ => {}
+                },
+            }
+        }
+        out
+    }
+}
+
+impl FromStr for OutputTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            OutputTemplate::parse("{respone}").unwrap_err(),
+            "Unknown placeholder '{respone}' (available: {query}, {response}, {tokens}, {model}, {cost})"
+        );
+        assert!(OutputTemplate::parse("{response").is_err());
+        assert!(OutputTemplate::parse("a } b").is_err());
+
+        let literal = OutputTemplate::parse("{{\"text\": \"{response}\"}}").unwrap();
+        let values = TemplateValues {
+            response: "hi".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(literal.render(&values), "{\"text\": \"hi\"}");
+    }
+}
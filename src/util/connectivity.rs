@@ -0,0 +1,184 @@
+// ============================================
+// WEBRANA CLI - Connectivity
+// A quick reachability probe for the LLM provider, so a missing network
+// fails in a second or two instead of after request timeouts
+// ============================================
+
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use url::Url;
+
+use crate::config::ModelConfig;
+
+/// Longest a probe may take, DNS lookup included
+pub const PROBE_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Probe results by `host:port`, kept for the rest of the process
+static RESULTS: Mutex<Option<HashMap<String, bool>>> = Mutex::new(None);
+
+/// Replaces the TCP probe in tests
+pub type ProbeFn = fn(&str, u16) -> bool;
+
+static PROBE: RwLock<Option<ProbeFn>> = RwLock::new(None);
+
+/// Held by tests that swap the probe, since it is process-wide
+#[cfg(test)]
+pub(crate) static TEST_PROBE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Use `probe` instead of connecting (None restores the real probe) and
+/// forget earlier results
+pub fn set_probe(probe: Option<ProbeFn>) {
+    *PROBE.write().unwrap() = probe;
+    *RESULTS.lock().unwrap() = None;
+}
+
+/// Where requests for `model` go; None for providers that don't use the
+/// network (replay)
+pub fn provider_endpoint(model: &ModelConfig) -> Option<Url> {
+    let default = match model.provider.as_str() {
+        "anthropic" => "https://api.anthropic.com",
+        "openai" | "openai_compatible" => "https://api.openai.com",
+        "ollama" => "http://localhost:11434",
+        "webrana" => "https://api.webrana.id",
+        _ => return None,
+    };
+    let base = match model.provider.as_str() {
+        "webrana" => default,
+        _ => model.base_url.as_deref().unwrap_or(default),
+    };
+    Url::parse(base).ok()
+}
+
+/// Whether `url` stays on this machine
+pub fn is_loopback(url: &Url) -> bool {
+    match url.host() {
+        Some(url::Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+        None => true,
+    }
+}
+
+/// Whether `model` works without the network
+pub fn is_local_model(model: &ModelConfig) -> bool {
+    provider_endpoint(model).map_or(model.provider == "replay", |url| is_loopback(&url))
+}
+
+/// The proxy requests to `url` go through, from `HTTPS_PROXY`,
+/// `HTTP_PROXY` or `ALL_PROXY` (either case) unless `NO_PROXY` lists the host
+pub fn proxy_for(url: &Url, env: impl Fn(&str) -> Option<String>) -> Option<Url> {
+    let var = |name: &str| {
+        env(name)
+            .or_else(|| env(&name.to_ascii_lowercase()))
+            .filter(|value| !value.trim().is_empty())
+    };
+    let host = url.host_str()?.to_ascii_lowercase();
+    if let Some(no_proxy) = var("NO_PROXY") {
+        let bypassed = no_proxy.split(',').map(str::trim).filter(|e| !e.is_empty()).any(|entry| {
+            let entry = entry.trim_start_matches('.').to_ascii_lowercase();
+            entry == "*" || host == entry || host.ends_with(&format!(".{}", entry))
+        });
+        if bypassed {
+            return None;
+        }
+    }
+    let scheme_var = if url.scheme() == "https" { "HTTPS_PROXY" } else { "HTTP_PROXY" };
+    let proxy = var(scheme_var).or_else(|| var("ALL_PROXY"))?;
+    let proxy = if proxy.contains("://") {
+        proxy
+    } else {
+        format!("http://{}", proxy)
+    };
+    Url::parse(&proxy).ok()
+}
+
+/// The host and port to probe for `url`: its proxy when one is set, since
+/// direct connections may be blocked where the proxy works
+pub fn probe_target(url: &Url) -> Option<(String, u16)> {
+    let target = proxy_for(url, |name| std::env::var(name).ok()).unwrap_or_else(|| url.clone());
+    Some((target.host_str()?.to_string(), target.port_or_known_default()?))
+}
+
+/// Whether `host:port` accepts a TCP connection within `PROBE_TIMEOUT`.
+/// Each target is probed at most once per process.
+pub async fn reachable(host: &str, port: u16) -> bool {
+    let key = format!("{}:{}", host, port);
+    if let Some(known) = RESULTS.lock().unwrap().as_ref().and_then(|results| results.get(&key)) {
+        return *known;
+    }
+    let stub = *PROBE.read().unwrap();
+    let result = match stub {
+        Some(probe) => probe(host, port),
+        None => matches!(
+            tokio::time::timeout(PROBE_TIMEOUT, tokio::net::TcpStream::connect((host, port))).await,
+            Ok(Ok(_))
+        ),
+    };
+    RESULTS.lock().unwrap().get_or_insert_with(HashMap::new).insert(key, result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(provider: &str, base_url: Option<&str>) -> ModelConfig {
+        ModelConfig {
+            provider: provider.to_string(),
+            api_key: None,
+            api_key_env: None,
+            base_url: base_url.map(str::to_string),
+            model: "m".to_string(),
+            temperature: 0.7,
+            max_tokens: 1024,
+            context_window: None,
+            fallback: Vec::new(),
+            edit_format: None,
+            max_tools: None,
+            max_tool_bytes: None,
+            vision: None,
+        }
+    }
+
+    #[test]
+    fn test_local_models() {
+        assert!(is_local_model(&model("ollama", None)));
+        assert!(is_local_model(&model("openai_compatible", Some("http://127.0.0.1:8080/v1"))));
+        assert!(is_local_model(&model("replay", Some("responses.jsonl"))));
+        assert!(!is_local_model(&model("ollama", Some("http://gpu-box.lan:11434"))));
+        assert!(!is_local_model(&model("anthropic", None)));
+        // The built-in model ignores base_url
+        assert!(!is_local_model(&model("webrana", Some("http://localhost"))));
+    }
+
+    #[test]
+    fn test_proxy_from_environment() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+        };
+        let openai = Url::parse("https://api.openai.com/v1").unwrap();
+        let proxy = proxy_for(&openai, env(&[("https_proxy", "proxy.corp:3128")])).unwrap();
+        assert_eq!((proxy.host_str(), proxy.port()), (Some("proxy.corp"), Some(3128)));
+        assert!(proxy_for(&openai, env(&[("HTTP_PROXY", "http://proxy.corp:3128")])).is_none());
+        assert!(proxy_for(&openai, env(&[("ALL_PROXY", "socks5://proxy.corp:1080")])).is_some());
+        let bypassed = env(&[("HTTPS_PROXY", "http://proxy.corp:3128"), ("NO_PROXY", "localhost,.openai.com")]);
+        assert!(proxy_for(&openai, bypassed).is_none());
+    }
+
+    #[test]
+    fn test_probe_is_cached() {
+        static CALLS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        fn unreachable(_: &str, _: u16) -> bool {
+            CALLS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            false
+        }
+        let _guard = TEST_PROBE_LOCK.lock().unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        set_probe(Some(unreachable));
+        assert!(!runtime.block_on(reachable("probe-cache.example", 443)));
+        assert!(!runtime.block_on(reachable("probe-cache.example", 443)));
+        set_probe(None);
+        assert_eq!(CALLS.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+}
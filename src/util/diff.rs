@@ -0,0 +1,259 @@
+// ============================================
+// WEBRANA CLI - Diff Rendering
+// Unified and side-by-side diffs for edit previews and confirmations
+// ============================================
+
+use similar::{DiffTag, TextDiff};
+
+use crate::ui::plain;
+
+const RED: &str = "\u{1b}[31m";
+const GREEN: &str = "\u{1b}[32m";
+const CYAN: &str = "\u{1b}[36m";
+const BOLD: &str = "\u{1b}[1m";
+const RESET: &str = "\u{1b}[0m";
+
+/// How a diff is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffStyle {
+    pub color: bool,
+    /// Unchanged lines shown around each change
+    pub context: usize,
+    /// Spell out each line's role ("added: ...") instead of relying on
+    /// `+`/`-` columns and colors (`--a11y`)
+    pub accessible: bool,
+}
+
+impl Default for DiffStyle {
+    fn default() -> Self {
+        Self {
+            color: false,
+            context: 3,
+            accessible: false,
+        }
+    }
+}
+
+impl DiffStyle {
+    /// Colored only when stdout is a terminal and colors aren't turned off
+    /// (`--plain`, CI, `NO_COLOR`); accessible under `--a11y`
+    pub fn terminal() -> Self {
+        Self {
+            color: color_enabled(atty::is(atty::Stream::Stdout)),
+            accessible: plain::is_a11y(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Whether output going to a terminal (`is_tty`) should be colored
+pub fn color_enabled(is_tty: bool) -> bool {
+    is_tty && !plain::is_plain() && colored::control::SHOULD_COLORIZE.should_colorize()
+}
+
+/// Unified diff from `old` to `new` with `a/path` and `b/path` headers.
+/// Empty when the texts are equal.
+pub fn unified(old: &str, new: &str, path: &str, style: DiffStyle) -> String {
+    let diff = TextDiff::from_lines(old, new);
+    let text = diff
+        .unified_diff()
+        .context_radius(style.context)
+        .header(&format!("a/{}", path), &format!("b/{}", path))
+        .to_string();
+    let text = if style.accessible { spell_out(&text) } else { text };
+    if !style.color {
+        return text;
+    }
+
+    let mut out = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let (body, newline) = match line.strip_suffix('\n') {
+            Some(body) => (body, "\n"),
+            None => (line, ""),
+        };
+        let color = if body.starts_with("---") || body.starts_with("+++") || body.starts_with("file: ") {
+            BOLD
+        } else if body.starts_with("@@") || body.starts_with("change at ") {
+            CYAN
+        } else if body.starts_with('-') || body.starts_with("removed: ") {
+            RED
+        } else if body.starts_with('+') || body.starts_with("added: ") {
+            GREEN
+        } else {
+            ""
+        };
+        if color.is_empty() {
+            out.push_str(line);
+        } else {
+            out.push_str(&format!("{}{}{}{}", color, body, RESET, newline));
+        }
+    }
+    out
+}
+
+/// A plain unified diff with each line's role as a word: `file:`,
+/// `change at line N:`, `removed:`, `added:` and `unchanged:`
+fn spell_out(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 2);
+    for line in text.lines() {
+        if line.starts_with("+++ ") {
+            continue;
+        } else if let Some(path) = line.strip_prefix("--- a/") {
+            if !path.is_empty() {
+                out.push_str(&format!("file: {}\n", path));
+            }
+        } else if line.starts_with("@@") {
+            let start = line
+                .split_whitespace()
+                .find_map(|part| part.strip_prefix('+'))
+                .and_then(|range| range.split(',').next())
+                .unwrap_or("?");
+            out.push_str(&format!("change at line {}:\n", start));
+        } else if let Some(rest) = line.strip_prefix('-') {
+            out.push_str(&format!("removed: {}\n", rest));
+        } else if let Some(rest) = line.strip_prefix('+') {
+            out.push_str(&format!("added: {}\n", rest));
+        } else if let Some(rest) = line.strip_prefix(' ') {
+            out.push_str(&format!("unchanged: {}\n", rest));
+        } else {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Two columns, old on the left and new on the right, `width` characters
+/// wide in total. Changed rows are marked `-`, `+` or `~` in the gutter.
+/// Accessible styles get the spelled-out unified diff instead of columns.
+pub fn side_by_side(old: &str, new: &str, width: usize, style: DiffStyle) -> String {
+    if style.accessible {
+        return unified(old, new, "", style);
+    }
+    let diff = TextDiff::from_lines(old, new);
+    let old_lines: Vec<&str> = diff.old_slices().iter().map(|l| l.trim_end_matches(['\n', '\r'])).collect();
+    let new_lines: Vec<&str> = diff.new_slices().iter().map(|l| l.trim_end_matches(['\n', '\r'])).collect();
+    let column = width.saturating_sub(3).max(10) / 2;
+
+    let mut out = String::new();
+    for (i, group) in diff.grouped_ops(style.context).iter().enumerate() {
+        if i > 0 {
+            out.push_str(&format!("{}\n", "┈".repeat(column * 2 + 3)));
+        }
+        for op in group {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            let rows = old_range.len().max(new_range.len());
+            for row in 0..rows {
+                let left = old_range.clone().nth(row).map(|i| old_lines[i]);
+                let right = new_range.clone().nth(row).map(|i| new_lines[i]);
+                let marker = match (tag, left, right) {
+                    (DiffTag::Equal, _, _) => ' ',
+                    (_, Some(_), Some(_)) => '~',
+                    (_, Some(_), None) => '-',
+                    _ => '+',
+                };
+                let left = fit(left.unwrap_or(""), column);
+                let right = fit(right.unwrap_or(""), column);
+                let (left_color, right_color) = match marker {
+                    _ if !style.color => ("", ""),
+                    '~' => (RED, GREEN),
+                    '-' => (RED, ""),
+                    '+' => ("", GREEN),
+                    _ => ("", ""),
+                };
+                out.push_str(&paint(&left, left_color));
+                out.push_str(&format!(" {} ", marker));
+                out.push_str(paint(&right, right_color).trim_end());
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// Pad or cut `text` to exactly `width` characters
+fn fit(text: &str, width: usize) -> String {
+    let text = text.replace('\t', "    ");
+    let count = text.chars().count();
+    if count > width {
+        let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+        cut.push('…');
+        cut
+    } else {
+        format!("{}{}", text, " ".repeat(width - count))
+    }
+}
+
+fn paint(text: &str, color: &str) -> String {
+    if color.is_empty() {
+        text.to_string()
+    } else {
+        format!("{}{}{}", color, text, RESET)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "fn main() {\n    println!(\"hello\");\n}\n";
+    const NEW: &str = "fn main() {\n    println!(\"hello, world\");\n    std::process::exit(0);\n}\n";
+
+    #[test]
+    fn test_unified_diff_lines() {
+        let diff = unified(OLD, NEW, "src/main.rs", DiffStyle::default());
+        let lines: Vec<&str> = diff.lines().collect();
+        assert_eq!(lines[0], "--- a/src/main.rs");
+        assert_eq!(lines[1], "+++ b/src/main.rs");
+        assert!(lines.contains(&"-    println!(\"hello\");"));
+        assert!(lines.contains(&"+    println!(\"hello, world\");"));
+        assert!(lines.contains(&"+    std::process::exit(0);"));
+        assert!(lines.contains(&" fn main() {"));
+
+        assert!(unified(OLD, OLD, "src/main.rs", DiffStyle::default()).is_empty());
+    }
+
+    #[test]
+    fn test_color_only_on_tty() {
+        assert!(!color_enabled(false));
+        let plain = unified(OLD, NEW, "src/main.rs", DiffStyle::default());
+        assert!(!plain.contains('\u{1b}'));
+
+        let colored = unified(OLD, NEW, "src/main.rs", DiffStyle { color: true, ..Default::default() });
+        assert!(colored.contains(&format!("{}+    std::process::exit(0);{}\n", GREEN, RESET)));
+        assert_eq!(crate::core::scrub::scrub(&colored, false).model, plain);
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        let diff = side_by_side(OLD, NEW, 63, DiffStyle::default());
+        let lines: Vec<&str> = diff.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("fn main() {") && lines[0].contains("   fn main() {"));
+        assert!(lines[1].contains(" ~     println!(\"hello, world\");"), "{}", diff);
+        assert!(lines[2].trim_start().starts_with("+     std::process::exit(0);"), "{}", diff);
+        assert!(lines.iter().all(|l| l.chars().count() <= 63));
+        assert!(!diff.contains('\u{1b}'));
+    }
+
+    #[test]
+    fn test_accessible_diff() {
+        let style = DiffStyle {
+            accessible: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            unified(OLD, NEW, "src/main.rs", style),
+            "file: src/main.rs\n\
+             change at line 1:\n\
+             unchanged: fn main() {\n\
+             removed:     println!(\"hello\");\n\
+             added:     println!(\"hello, world\");\n\
+             added:     std::process::exit(0);\n\
+             unchanged: }\n"
+        );
+        let columns = side_by_side(OLD, NEW, 63, style);
+        assert!(columns.starts_with("change at line 1:\nunchanged: fn main() {\n"), "{}", columns);
+        assert!(!columns.contains('┈') && !columns.contains(" ~ "));
+    }
+}
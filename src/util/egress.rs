@@ -0,0 +1,453 @@
+// ============================================
+// WEBRANA CLI - Egress Allowlist
+// Which hosts agent-initiated connections (web_fetch, MCP over HTTP,
+// plugins) may reach. LLM providers are not checked here.
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::RwLock;
+use url::{Host, Url};
+
+use crate::config::Settings;
+use crate::core::audit::{AuditEvent, AuditEventType, AuditSeverity, AUDIT};
+
+/// Guard used by code without access to `Settings`, set once at startup
+static GUARD: RwLock<Option<EgressGuard>> = RwLock::new(None);
+
+/// Build the guard from `settings.safety.egress_allowlist`, failing on
+/// malformed entries so a typo can't silently open or close everything
+pub fn configure(settings: &Settings) -> Result<()> {
+    *GUARD.write().unwrap() = Some(EgressGuard::from_settings(settings)?);
+    Ok(())
+}
+
+/// The guard set by `configure`; unrestricted before that
+pub fn current() -> EgressGuard {
+    GUARD.read().unwrap().clone().unwrap_or_default()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum HostPattern {
+    /// One host, IDNA-normalized
+    Exact(String),
+    /// `*.docs.rs`: `docs.rs` and any name under it
+    Suffix(String),
+    Ip(IpAddr),
+}
+
+/// One `safety.egress_allowlist` entry: a host pattern and optional port
+#[derive(Debug, Clone, PartialEq)]
+pub struct EgressRule {
+    host: HostPattern,
+    port: Option<u16>,
+}
+
+impl EgressRule {
+    /// `wiki.example.com`, `*.docs.rs`, `bücher.example:8443`, `10.0.0.5`,
+    /// `[::1]:8080` or `::1`
+    pub fn parse(entry: &str) -> Result<Self> {
+        let entry = entry.trim();
+        if entry.contains("://") || entry.contains('/') {
+            bail!("'{}' is not a host pattern; drop the scheme and path", entry);
+        }
+        if let Ok(ip) = entry.parse::<Ipv6Addr>() {
+            return Ok(Self { host: HostPattern::Ip(IpAddr::V6(ip)), port: None });
+        }
+
+        let (host, port) = if let Some(rest) = entry.strip_prefix('[') {
+            let (ip, rest) = rest.split_once(']').with_context(|| format!("'{}' has no closing ']'", entry))?;
+            let ip: Ipv6Addr = ip.parse().with_context(|| format!("'{}' is not an IPv6 address", ip))?;
+            let port = match rest {
+                "" => None,
+                _ => Some(parse_port(rest.strip_prefix(':').unwrap_or(rest), entry)?),
+            };
+            return Ok(Self { host: HostPattern::Ip(IpAddr::V6(ip)), port });
+        } else {
+            match entry.rsplit_once(':') {
+                Some((host, port)) => (host, Some(parse_port(port, entry)?)),
+                None => (entry, None),
+            }
+        };
+
+        let (wildcard, host) = match host.strip_prefix("*.") {
+            Some(rest) => (true, rest),
+            None => (false, host),
+        };
+        if host.is_empty() || host.contains('*') {
+            bail!("'{}' is not a host pattern; a wildcard may only be a leading '*.'", entry);
+        }
+        let host = match normalize_host(host).with_context(|| format!("'{}' is not a valid host", entry))? {
+            Host::Domain(domain) if wildcard => HostPattern::Suffix(domain),
+            Host::Domain(domain) => HostPattern::Exact(domain),
+            _ if wildcard => bail!("'{}': wildcards only apply to host names", entry),
+            Host::Ipv4(ip) => HostPattern::Ip(IpAddr::V4(ip)),
+            Host::Ipv6(ip) => HostPattern::Ip(IpAddr::V6(ip)),
+        };
+        Ok(Self { host, port })
+    }
+
+    /// Whether `host:port` is covered: Some(true) for an exact host or IP
+    /// entry, Some(false) for a wildcard, None when it doesn't match
+    fn matches(&self, host: &Host<String>, port: u16) -> Option<bool> {
+        if self.port.is_some_and(|p| p != port) {
+            return None;
+        }
+        match (&self.host, host) {
+            (HostPattern::Exact(pattern), Host::Domain(domain)) => (pattern == domain).then_some(true),
+            (HostPattern::Suffix(suffix), Host::Domain(domain)) => {
+                let under = domain
+                    .strip_suffix(suffix.as_str())
+                    .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with('.'));
+                under.then_some(false)
+            }
+            (HostPattern::Ip(ip), Host::Ipv4(v4)) => (*ip == IpAddr::V4(*v4)).then_some(true),
+            (HostPattern::Ip(ip), Host::Ipv6(v6)) => (*ip == IpAddr::V6(*v6)).then_some(true),
+            _ => None,
+        }
+    }
+}
+
+fn parse_port(port: &str, entry: &str) -> Result<u16> {
+    port.parse().with_context(|| format!("'{}' has an invalid port", entry))
+}
+
+/// Lowercased, punycode (IDNA) form without a trailing dot; IPv4 in any
+/// notation URLs accept (`0x7f.1`) becomes dotted decimal
+fn normalize_host(host: &str) -> Result<Host<String>> {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    Ok(match Host::parse(host)? {
+        Host::Domain(domain) => Host::Domain(domain.trim_end_matches('.').to_string()),
+        other => other,
+    })
+}
+
+/// Why a connection was refused
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum DenyReason {
+    /// No allowlist entry covers the host and port
+    NotAllowlisted,
+    /// The host resolves to a loopback, private or otherwise internal address
+    /// without being listed by name or IP
+    PrivateAddress { address: IpAddr },
+}
+
+/// A refused connection, for the caller and the audit log
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EgressDenied {
+    pub host: String,
+    pub port: u16,
+    /// What tried to connect, e.g. `web_fetch`
+    pub consumer: String,
+    #[serde(flatten)]
+    pub reason: DenyReason,
+    /// Where the allowlist is configured
+    pub source: String,
+}
+
+impl fmt::Display for EgressDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Egress refused for {}: {}:{} ", self.consumer, self.host, self.port)?;
+        let source = match self.source.as_str() {
+            "" => String::new(),
+            source => format!(" ({})", source),
+        };
+        match &self.reason {
+            DenyReason::NotAllowlisted => write!(f, "is not in safety.egress_allowlist{}", source),
+            DenyReason::PrivateAddress { address } => write!(
+                f,
+                "resolves to non-public address {}; list the host by name or IP in \
+                 safety.egress_allowlist{} to allow it",
+                address, source
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EgressDenied {}
+
+/// A checked destination and the addresses it resolved to. Connect through
+/// `client()`, which pins those addresses so a second DNS answer can't
+/// redirect the request.
+#[derive(Debug, Clone)]
+pub struct EgressPermit {
+    pub url: Url,
+    pub addrs: Vec<SocketAddr>,
+}
+
+impl EgressPermit {
+    /// Client with the configured TLS settings, `addrs` pinned and redirects
+    /// off; follow a redirect by authorizing its target again
+    pub fn client(&self) -> Result<reqwest::Client> {
+        let mut builder = super::http::configured_builder()?.redirect(reqwest::redirect::Policy::none());
+        if let Some(Host::Domain(domain)) = self.url.host() {
+            builder = builder.resolve_to_addrs(domain, &self.addrs);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// `safety.egress_allowlist`; an empty list allows any public host
+#[derive(Debug, Clone, Default)]
+pub struct EgressGuard {
+    rules: Vec<EgressRule>,
+    /// Config file the rules came from
+    source: String,
+}
+
+impl EgressGuard {
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        let source = Settings::config_path()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|_| "global config".to_string());
+        Self::new(&settings.safety.egress_allowlist, &source)
+    }
+
+    pub fn new(entries: &[String], source: &str) -> Result<Self> {
+        let rules = entries
+            .iter()
+            .map(|entry| EgressRule::parse(entry).context("Invalid safety.egress_allowlist entry"))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            rules,
+            source: source.to_string(),
+        })
+    }
+
+    pub fn is_restricted(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    /// Host and port checks only, before any DNS lookup. Ok(true) when an
+    /// exact host or IP entry vouches for the destination.
+    pub fn check_url(&self, url: &Url, consumer: &str) -> Result<bool> {
+        let (host, port) = destination(url)?;
+        if !self.is_restricted() {
+            return Ok(false);
+        }
+        let matched = self.rules.iter().filter_map(|rule| rule.matches(&host, port)).max();
+        match matched {
+            Some(exact) => Ok(exact),
+            None => Err(self.deny(&host, port, consumer, DenyReason::NotAllowlisted)),
+        }
+    }
+
+    /// Check `url` against the allowlist by its name as written, then resolve
+    /// it and refuse internal addresses unless an exact entry lists the host
+    pub async fn authorize(&self, url: &str, consumer: &str) -> Result<EgressPermit> {
+        let url = Url::parse(url).with_context(|| format!("Invalid URL '{}'", url))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            bail!("Only http and https URLs are supported, not '{}'", url.scheme());
+        }
+        let vouched = self.check_url(&url, consumer)?;
+        let (host, port) = destination(&url)?;
+
+        let addrs: Vec<SocketAddr> = match &host {
+            Host::Ipv4(ip) => vec![SocketAddr::new(IpAddr::V4(*ip), port)],
+            Host::Ipv6(ip) => vec![SocketAddr::new(IpAddr::V6(*ip), port)],
+            Host::Domain(domain) => tokio::net::lookup_host((domain.as_str(), port))
+                .await
+                .with_context(|| format!("Could not resolve {}", domain))?
+                .collect(),
+        };
+        if !vouched {
+            if let Some(internal) = addrs.iter().map(|addr| addr.ip()).find(|ip| !is_public(*ip)) {
+                return Err(self.deny(&host, port, consumer, DenyReason::PrivateAddress { address: internal }));
+            }
+        }
+        Ok(EgressPermit { url, addrs })
+    }
+
+    /// Audit the refusal and return it as an error callers can downcast
+    fn deny(&self, host: &Host<String>, port: u16, consumer: &str, reason: DenyReason) -> anyhow::Error {
+        let denied = EgressDenied {
+            host: host.to_string(),
+            port,
+            consumer: consumer.to_string(),
+            reason,
+            source: self.source.clone(),
+        };
+        AUDIT.log(
+            AuditEvent::new(AuditEventType::EgressBlocked, AuditSeverity::Warning, denied.to_string())
+                .with_details(serde_json::to_value(&denied).unwrap_or_default()),
+        );
+        anyhow::Error::new(denied)
+    }
+}
+
+/// Normalized host and port (explicit or the scheme's default)
+fn destination(url: &Url) -> Result<(Host<String>, u16)> {
+    let host = match url.host().with_context(|| format!("'{}' has no host", url))? {
+        Host::Domain(domain) => normalize_host(domain)?,
+        Host::Ipv4(ip) => Host::Ipv4(ip),
+        Host::Ipv6(ip) => Host::Ipv6(ip),
+    };
+    let port = url
+        .port_or_known_default()
+        .with_context(|| format!("'{}' has no port", url))?;
+    Ok((host, port))
+}
+
+/// Routable on the public internet: not loopback, private, link-local,
+/// shared (CGNAT), documentation, multicast or otherwise reserved
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_v4(v4);
+            }
+            let s = ip.segments();
+            // NAT64 and 6to4 reach the IPv4 address they embed
+            if s[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                return is_public_v4(Ipv4Addr::new((s[6] >> 8) as u8, s[6] as u8, (s[7] >> 8) as u8, s[7] as u8));
+            }
+            if s[0] == 0x2002 {
+                return is_public_v4(Ipv4Addr::new((s[1] >> 8) as u8, s[1] as u8, (s[2] >> 8) as u8, s[2] as u8));
+            }
+            let first = s[0];
+            !(ip.is_loopback()
+                || (first == 0x64 && s[1] == 0xff9b && s[2] == 1) // local-use NAT64
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (first & 0xfe00) == 0xfc00 // unique local
+                || (first & 0xffc0) == 0xfe80 // link-local
+                || (first == 0x2001 && s[1] == 0x0db8)) // documentation
+        }
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || a == 0
+        || a >= 240
+        || (a == 100 && (64..128).contains(&b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(entries: &[&str]) -> EgressGuard {
+        let entries: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
+        EgressGuard::new(&entries, "/home/ada/.config/webrana/config.toml").unwrap()
+    }
+
+    fn allowed(guard: &EgressGuard, url: &str) -> bool {
+        guard.check_url(&Url::parse(url).unwrap(), "test").is_ok()
+    }
+
+    #[test]
+    fn test_rule_parsing() {
+        let rule = |entry: &str| EgressRule::parse(entry).unwrap();
+        assert_eq!(rule("Wiki.Example.COM."), EgressRule { host: HostPattern::Exact("wiki.example.com".into()), port: None });
+        assert_eq!(rule("*.docs.rs:443"), EgressRule { host: HostPattern::Suffix("docs.rs".into()), port: Some(443) });
+        assert_eq!(rule("bücher.example").host, HostPattern::Exact("xn--bcher-kva.example".into()));
+        assert_eq!(rule("10.0.0.5:8080").host, HostPattern::Ip("10.0.0.5".parse().unwrap()));
+        assert_eq!(rule("[::1]:8080"), EgressRule { host: HostPattern::Ip("::1".parse().unwrap()), port: Some(8080) });
+        assert_eq!(rule("::1").port, None);
+
+        for bad in ["", "*", "*.", "docs.*.rs", "a*.docs.rs", "https://docs.rs", "docs.rs/path", "docs.rs:http", "docs.rs:70000", "*.10.0.0.1", "[::1", "[nope]:80", "bad host"] {
+            assert!(EgressRule::parse(bad).is_err(), "{:?} parsed", bad);
+        }
+        let err = EgressGuard::new(&["ok.example".into(), "*x".into()], "cfg").unwrap_err();
+        assert!(format!("{:#}", err).starts_with("Invalid safety.egress_allowlist entry"), "{:#}", err);
+    }
+
+    #[test]
+    fn test_wildcards_and_ports() {
+        let guard = guard(&["*.docs.rs", "wiki.internal.example.com", "api.example.com:8443"]);
+        assert!(allowed(&guard, "https://docs.rs/serde"));
+        assert!(allowed(&guard, "https://serde.docs.rs/"));
+        assert!(allowed(&guard, "http://a.b.docs.rs:8080/"));
+        assert!(!allowed(&guard, "https://evildocs.rs/"));
+        assert!(!allowed(&guard, "https://docs.rs.evil.com/"));
+        assert!(allowed(&guard, "https://WIKI.internal.example.com./page"));
+        assert!(!allowed(&guard, "https://sub.wiki.internal.example.com/"));
+        assert!(allowed(&guard, "https://api.example.com:8443/v1"));
+        assert!(!allowed(&guard, "https://api.example.com/v1"));
+        // Credentials in the URL don't change the host
+        assert!(!allowed(&guard, "https://docs.rs@evil.com/"));
+        assert!(!allowed(&guard, "https://evil.com/#docs.rs"));
+    }
+
+    #[test]
+    fn test_idn_and_ip_literals() {
+        let guard = guard(&["bücher.example", "127.0.0.1:9000", "[::1]", "10.1.2.3"]);
+        assert!(allowed(&guard, "https://BÜCHER.example/"));
+        assert!(allowed(&guard, "https://xn--bcher-kva.example/"));
+        assert!(!allowed(&guard, "https://bucher.example/"));
+        assert!(allowed(&guard, "http://127.0.0.1:9000/"));
+        // Other spellings of the same address normalize to it
+        assert!(allowed(&guard, "http://0x7f.0.0.1:9000/"));
+        assert!(allowed(&guard, "http://2130706433:9000/"));
+        assert!(!allowed(&guard, "http://127.0.0.1:9001/"));
+        assert!(!allowed(&guard, "http://localhost:9000/"));
+        assert!(allowed(&guard, "http://[::1]:1234/"));
+        assert!(allowed(&guard, "http://10.1.2.3/"));
+        // A name never matches an IP entry, even if it resolves there
+        assert!(!allowed(&guard, "http://10-1-2-3.example/"));
+    }
+
+    #[test]
+    fn test_is_public() {
+        let public = |ip: &str| is_public(ip.parse().unwrap());
+        for ip in ["8.8.8.8", "1.1.1.1", "2606:4700::1111", "100.128.0.1", "64:ff9b::808:808", "2002:808:808::1"] {
+            assert!(public(ip), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1", "10.0.0.1", "172.16.5.4", "192.168.1.1", "169.254.169.254", "100.64.0.1",
+            "0.0.0.0", "255.255.255.255", "224.0.0.1", "240.0.0.1", "192.0.2.1", "::1", "::",
+            "fd00::1", "fe80::1", "ff02::1", "2001:db8::1", "::ffff:127.0.0.1", "::ffff:10.0.0.1",
+            "64:ff9b::7f00:1", "64:ff9b::a9fe:a9fe", "2002:7f00:1::1", "2002:a00:1::", "64:ff9b:1::808:808",
+        ] {
+            assert!(!public(ip), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authorize_resolves_and_refuses_internal_addresses() {
+        // Unrestricted still refuses internal addresses
+        let open = EgressGuard::default();
+        let err = open.authorize("http://127.0.0.1:8080/", "web_fetch").await.unwrap_err();
+        let denied = err.downcast_ref::<EgressDenied>().unwrap();
+        assert_eq!(denied.reason, DenyReason::PrivateAddress { address: "127.0.0.1".parse().unwrap() });
+
+        // A wildcard can't vouch for a name that resolves inside
+        let wildcard = guard(&["*.localhost"]);
+        let err = wildcard.authorize("http://localhost/", "web_fetch").await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<EgressDenied>().unwrap().reason, DenyReason::PrivateAddress { .. }));
+
+        // Listing the host itself does
+        let exact = guard(&["localhost", "127.0.0.1"]);
+        let permit = exact.authorize("http://localhost:8080/x", "web_fetch").await.unwrap();
+        assert!(permit.addrs.iter().all(|addr| addr.ip().is_loopback() && addr.port() == 8080));
+        assert!(exact.authorize("http://127.0.0.1/", "web_fetch").await.is_ok());
+
+        let err = exact.authorize("http://example.com/", "mcp").await.unwrap_err();
+        let denied = err.downcast_ref::<EgressDenied>().unwrap();
+        assert_eq!((denied.host.as_str(), denied.port, denied.reason.clone()), ("example.com", 80, DenyReason::NotAllowlisted));
+        assert_eq!(
+            err.to_string(),
+            "Egress refused for mcp: example.com:80 is not in safety.egress_allowlist (/home/ada/.config/webrana/config.toml)"
+        );
+        let json = serde_json::to_value(denied).unwrap();
+        assert_eq!(json["reason"], "not_allowlisted");
+        assert!(AUDIT
+            .events_by_type(AuditEventType::EgressBlocked)
+            .iter()
+            .any(|event| event.details.as_ref().is_some_and(|d| d["host"] == "example.com")));
+
+        assert!(exact.authorize("ftp://localhost/", "web_fetch").await.is_err());
+    }
+}
@@ -0,0 +1,45 @@
+// ============================================
+// WEBRANA CLI - Hashing
+// SHA-256 hex digests for content checks and on-disk storage keys
+// ============================================
+
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Lowercase hex of a digest
+pub fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Lowercase hex SHA-256 of `bytes`
+pub fn sha256_hex(bytes: impl AsRef<[u8]>) -> String {
+    hex(&Sha256::digest(bytes.as_ref()))
+}
+
+/// Short key naming per-directory storage (indexes, checkpoints): the first
+/// 16 hex digits of the SHA-256 of the canonical path
+pub fn dir_key(dir: &Path) -> String {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    let mut key = sha256_hex(dir.to_string_lossy().as_bytes());
+    key.truncate(16);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_hex_and_dir_key() {
+        assert_eq!(
+            sha256_hex("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let tmp = tempfile::TempDir::new().unwrap();
+        let key = dir_key(tmp.path());
+        assert_eq!(key.len(), 16);
+        // Keyed by the canonical path, so spellings of one directory agree
+        assert_eq!(key, dir_key(&tmp.path().join(".")));
+        assert_ne!(key, dir_key(&std::env::temp_dir()));
+    }
+}
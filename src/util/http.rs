@@ -0,0 +1,316 @@
+// ============================================
+// WEBRANA CLI - HTTP Clients
+// One place to build reqwest clients with the configured CA and mTLS identity,
+// and the --offline switch for non-essential network calls
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use crate::config::{HttpSettings, Settings};
+
+/// Settings every `client()` is built from, set once at startup
+static CONFIG: RwLock<Option<HttpSettings>> = RwLock::new(None);
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// `--offline`: skip update checks, Webrana status calls, registry lookups
+/// and telemetry export, and switch LLM calls to a local model (see
+/// `llm::client`).
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Fail with a clear message when `what` would need the network in offline mode
+pub fn ensure_online(what: &str) -> Result<()> {
+    check_online(is_offline(), what)
+}
+
+/// [`ensure_online`] for callers that carry the offline mode themselves
+pub fn check_online(offline: bool, what: &str) -> Result<()> {
+    if offline {
+        bail!("{} needs the network; skipped in offline mode (--offline / WEBRANA_OFFLINE)", what);
+    }
+    Ok(())
+}
+
+/// Use `settings.http` for every client built from now on. Refuses
+/// `insecure_skip_verify` under a safety policy, and warns whenever it is on.
+pub fn configure(settings: &Settings) -> Result<()> {
+    let http = &settings.http;
+    if http.insecure_skip_verify {
+        if settings.safety.is_policy() {
+            bail!(
+                "http.insecure_skip_verify is not allowed while a safety policy is set \
+                 (safety.allowed_commands or safety.blocked_paths); configure http.ca_bundle instead"
+            );
+        }
+        eprintln!(
+            "WARNING: http.insecure_skip_verify is on. TLS certificates are NOT verified; \
+             anyone on the network can read and change requests, including API keys."
+        );
+    }
+    // Fail at startup rather than on the first request
+    client_builder(http)?.build()?;
+    *CONFIG.write().unwrap() = Some(http.clone());
+    Ok(())
+}
+
+/// A client with the configured TLS settings (defaults before `configure`)
+pub fn client() -> Result<reqwest::Client> {
+    Ok(configured_builder()?.build()?)
+}
+
+/// `client()`'s builder, for callers that need more options
+pub fn configured_builder() -> Result<reqwest::ClientBuilder> {
+    let config = CONFIG.read().unwrap().clone().unwrap_or_default();
+    client_builder(&config)
+}
+
+/// Builder with `http`'s CA bundle, client identity and verification applied
+pub fn client_builder(http: &HttpSettings) -> Result<reqwest::ClientBuilder> {
+    let mut builder = reqwest::Client::builder();
+
+    if let Some(bundle) = &http.ca_bundle {
+        let path = expand_path(bundle);
+        let pem = std::fs::read(&path)
+            .with_context(|| format!("Failed to read http.ca_bundle {}", path.display()))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("http.ca_bundle {} is not a PEM certificate bundle", path.display()))?;
+        if certs.is_empty() {
+            bail!("http.ca_bundle {} contains no certificates", path.display());
+        }
+        if http.ca_replace {
+            builder = builder.tls_built_in_root_certs(false);
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    } else if http.ca_replace {
+        bail!("http.ca_replace needs http.ca_bundle");
+    }
+
+    match (&http.client_cert, &http.client_key) {
+        (Some(cert), Some(key)) => {
+            let cert_path = expand_path(cert);
+            let key_path = expand_path(key);
+            let mut pem = std::fs::read(&cert_path)
+                .with_context(|| format!("Failed to read http.client_cert {}", cert_path.display()))?;
+            let key = std::fs::read(&key_path)
+                .with_context(|| format!("Failed to read http.client_key {}", key_path.display()))?;
+            if is_encrypted_key(&key) {
+                bail!(
+                    "http.client_key {} is encrypted. Decrypt it first, e.g. \
+                     `openssl pkey -in {} -out client-decrypted.key`, and keep the result private",
+                    key_path.display(),
+                    key_path.display()
+                );
+            }
+            pem.push(b'\n');
+            pem.extend_from_slice(&key);
+            let identity = reqwest::Identity::from_pem(&pem).with_context(|| {
+                format!(
+                    "http.client_cert {} and http.client_key {} are not a usable PEM certificate and key",
+                    cert_path.display(),
+                    key_path.display()
+                )
+            })?;
+            builder = builder.identity(identity);
+        }
+        (Some(_), None) => bail!("http.client_cert needs http.client_key"),
+        (None, Some(_)) => bail!("http.client_key needs http.client_cert"),
+        (None, None) => {}
+    }
+
+    if http.insecure_skip_verify {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder)
+}
+
+fn is_encrypted_key(pem: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(pem);
+    text.contains("BEGIN ENCRYPTED PRIVATE KEY") || text.contains("Proc-Type: 4,ENCRYPTED")
+}
+
+/// Expand a leading `~` and `${VAR}` / `$VAR` references. Unset variables
+/// expand to nothing.
+pub fn expand_path(path: &str) -> PathBuf {
+    let mut out = String::new();
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+        let braced = chars.peek() == Some(&'{');
+        if braced {
+            chars.next();
+        }
+        let mut name = String::new();
+        while let Some(&next) = chars.peek() {
+            if braced && next == '}' {
+                chars.next();
+                break;
+            }
+            if !braced && !is_name_char(next) {
+                break;
+            }
+            name.push(next);
+            chars.next();
+        }
+        if name.is_empty() && !braced {
+            out.push('$');
+        } else {
+            out.push_str(&std::env::var(&name).unwrap_or_default());
+        }
+    }
+
+    match out.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\') => {
+            match dirs::home_dir() {
+                Some(home) => home.join(rest.trim_start_matches(['/', '\\'])),
+                None => PathBuf::from(out),
+            }
+        }
+        _ => PathBuf::from(out),
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::rustls;
+
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/tls/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    #[test]
+    fn test_builder_validates_files() {
+        let ok = HttpSettings {
+            ca_bundle: Some(fixture("ca.pem")),
+            client_cert: Some(fixture("client.pem")),
+            client_key: Some(fixture("client.key")),
+            ..Default::default()
+        };
+        assert!(client_builder(&ok).unwrap().build().is_ok());
+
+        let encrypted = HttpSettings {
+            client_key: Some(fixture("client-encrypted.key")),
+            ..ok.clone()
+        };
+        let err = client_builder(&encrypted).unwrap_err().to_string();
+        assert!(err.contains("is encrypted") && err.contains("openssl pkey"), "{}", err);
+
+        let not_pem = HttpSettings {
+            ca_bundle: Some(fixture("client.key")),
+            ..Default::default()
+        };
+        assert!(client_builder(&not_pem).is_err());
+
+        let missing_key = HttpSettings {
+            client_cert: Some(fixture("client.pem")),
+            ..Default::default()
+        };
+        assert!(client_builder(&missing_key).unwrap_err().to_string().contains("needs http.client_key"));
+        let replace_alone = HttpSettings {
+            ca_replace: true,
+            ..Default::default()
+        };
+        assert!(client_builder(&replace_alone).is_err());
+    }
+
+    #[test]
+    fn test_insecure_refused_under_policy() {
+        let mut settings = Settings::default();
+        settings.http.insecure_skip_verify = true;
+        settings.safety.blocked_paths = vec!["/etc".to_string()];
+        let err = configure(&settings).unwrap_err().to_string();
+        assert!(err.contains("not allowed while a safety policy is set"), "{}", err);
+    }
+
+    #[test]
+    fn test_expand_path() {
+        std::env::set_var("WEBRANA_TEST_CERTS", "/opt/certs");
+        assert_eq!(expand_path("${WEBRANA_TEST_CERTS}/ca.pem"), PathBuf::from("/opt/certs/ca.pem"));
+        assert_eq!(expand_path("$WEBRANA_TEST_CERTS/ca.pem"), PathBuf::from("/opt/certs/ca.pem"));
+        assert_eq!(expand_path("/plain/$"), PathBuf::from("/plain/$"));
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(expand_path("~/ca.pem"), home.join("ca.pem"));
+        }
+    }
+
+    /// Serve one HTTPS response with the fixture server certificate
+    async fn tls_server() -> u16 {
+        let pem = |name: &str| std::fs::read(fixture(name)).unwrap();
+        let certs = rustls_pemfile::certs(&mut &pem("server.pem")[..])
+            .unwrap()
+            .into_iter()
+            .map(rustls::Certificate)
+            .collect();
+        let key = rustls_pemfile::pkcs8_private_keys(&mut &pem("server.key")[..]).unwrap().remove(0);
+        let config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(certs, rustls::PrivateKey(key))
+            .unwrap();
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(mut tls) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let mut buf = [0u8; 1024];
+                    let _ = tls.read(&mut buf).await;
+                    let _ = tls
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok")
+                        .await;
+                    let _ = tls.shutdown().await;
+                });
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_custom_ca_against_local_tls_server() {
+        let port = tls_server().await;
+        let url = format!("https://localhost:{}/", port);
+
+        let trusted = HttpSettings {
+            ca_bundle: Some(fixture("ca.pem")),
+            ..Default::default()
+        };
+        let response = client_builder(&trusted).unwrap().build().unwrap().get(&url).send().await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+
+        let default = client_builder(&HttpSettings::default()).unwrap().build().unwrap();
+        let err = default.get(&url).send().await.unwrap_err();
+        assert!(format!("{:?}", err).to_lowercase().contains("certificate"), "{:?}", err);
+
+        let insecure = HttpSettings {
+            insecure_skip_verify: true,
+            ..Default::default()
+        };
+        let client = client_builder(&insecure).unwrap().build().unwrap();
+        assert!(client.get(&url).send().await.is_ok());
+    }
+}
@@ -0,0 +1,19 @@
+// ============================================
+// WEBRANA CLI - Shared Utilities
+// Helpers used across skills, commands and prompts
+// ============================================
+
+pub mod connectivity;
+pub mod diff;
+pub mod egress;
+pub mod hash;
+pub mod http;
+pub mod netcheck;
+pub mod time;
+
+#[allow(unused_imports)]
+pub use diff::{side_by_side, unified, DiffStyle};
+#[allow(unused_imports)]
+pub use hash::{dir_key, sha256_hex};
+#[allow(unused_imports)]
+pub use time::unix_now;
@@ -0,0 +1,12 @@
+// ============================================
+// WEBRANA CLI - Time
+// Wall-clock timestamps for records, ids and expiry checks
+// ============================================
+
+/// Seconds since the Unix epoch (0 if the clock is before it)
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
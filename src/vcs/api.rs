@@ -0,0 +1,153 @@
+// ============================================
+// WEBRANA CLI - VCS API Requests
+// Authenticated JSON requests that wait out rate limits
+// ============================================
+
+use anyhow::{bail, Context, Result};
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::util::unix_now;
+
+/// Retries of a request answered with 429
+const MAX_RETRIES: u32 = 3;
+
+/// Longest single wait for a rate limit to reset
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+pub(crate) struct Api {
+    client: reqwest::Client,
+    /// Provider name for errors
+    name: &'static str,
+    auth_header: &'static str,
+    auth_value: String,
+}
+
+/// A successful response
+pub(crate) struct Response {
+    pub body: Value,
+    pub headers: HeaderMap,
+}
+
+impl Api {
+    pub fn new(name: &'static str, auth_header: &'static str, auth_value: String) -> Result<Self> {
+        Ok(Self {
+            client: crate::util::http::client()?,
+            name,
+            auth_header,
+            auth_value,
+        })
+    }
+
+    pub async fn get(&self, url: &str) -> Result<Response> {
+        self.send(Method::GET, url, None).await
+    }
+
+    pub async fn post(&self, url: &str, body: &Value) -> Result<Response> {
+        self.send(Method::POST, url, Some(body)).await
+    }
+
+    async fn send(&self, method: Method, url: &str, body: Option<&Value>) -> Result<Response> {
+        crate::util::http::ensure_online(&format!("The {} API", self.name))?;
+        let mut retries = 0;
+        loop {
+            let mut request = self
+                .client
+                .request(method.clone(), url)
+                .header(self.auth_header, &self.auth_value)
+                .header("Accept", "application/json");
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            let response = request
+                .send()
+                .await
+                .with_context(|| format!("Failed to connect to the {} API", self.name))?;
+            let status = response.status();
+            let headers = response.headers().clone();
+
+            if status == StatusCode::TOO_MANY_REQUESTS && retries < MAX_RETRIES {
+                retries += 1;
+                let wait = rate_limit_wait(&headers, unix_now()).unwrap_or(Duration::from_secs(retries as u64));
+                tracing::warn!("{} API rate limit hit; retrying in {}s", self.name, wait.as_secs());
+                tokio::time::sleep(wait).await;
+                continue;
+            }
+
+            let text = response.text().await.unwrap_or_default();
+            if !status.is_success() {
+                let hint = match status {
+                    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => " (check the API token and its scopes)",
+                    _ => "",
+                };
+                let detail: String = text.chars().take(300).collect();
+                bail!("{} API error ({}){}: {}", self.name, status, hint, detail);
+            }
+
+            // Out of budget: wait for the reset before the next request goes out
+            if remaining(&headers) == Some(0) {
+                if let Some(wait) = rate_limit_wait(&headers, unix_now()) {
+                    tokio::time::sleep(wait).await;
+                }
+            }
+
+            let body = if text.trim().is_empty() {
+                Value::Null
+            } else {
+                serde_json::from_str(&text).with_context(|| format!("Invalid JSON from the {} API", self.name))?
+            };
+            return Ok(Response { body, headers });
+        }
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, names: &[&str]) -> Option<&'a str> {
+    names
+        .iter()
+        .find_map(|name| headers.get(*name))
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Requests left in the current window (GitLab `RateLimit-Remaining`,
+/// Bitbucket `X-RateLimit-Remaining`)
+fn remaining(headers: &HeaderMap) -> Option<u64> {
+    header(headers, &["ratelimit-remaining", "x-ratelimit-remaining"])?.trim().parse().ok()
+}
+
+/// How long to wait before retrying: `Retry-After` seconds, else until the
+/// `RateLimit-Reset` / `X-RateLimit-Reset` Unix time. Capped at a minute.
+pub(crate) fn rate_limit_wait(headers: &HeaderMap, now: u64) -> Option<Duration> {
+    let secs = if let Some(after) = header(headers, &["retry-after"]).and_then(|v| v.trim().parse::<u64>().ok()) {
+        after
+    } else {
+        let reset: u64 = header(headers, &["ratelimit-reset", "x-ratelimit-reset"])?.trim().parse().ok()?;
+        reset.saturating_sub(now)
+    };
+    Some(Duration::from_secs(secs).min(MAX_WAIT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_wait() {
+        let headers = |pairs: &[(&'static str, &str)]| {
+            let mut map = HeaderMap::new();
+            for (name, value) in pairs {
+                map.insert(*name, value.parse().unwrap());
+            }
+            map
+        };
+        assert_eq!(rate_limit_wait(&headers(&[("retry-after", "7")]), 0), Some(Duration::from_secs(7)));
+        assert_eq!(
+            rate_limit_wait(&headers(&[("ratelimit-reset", "1030"), ("ratelimit-remaining", "0")]), 1000),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(rate_limit_wait(&headers(&[("x-ratelimit-reset", "99999")]), 0), Some(MAX_WAIT));
+        assert_eq!(rate_limit_wait(&headers(&[]), 0), None);
+        assert_eq!(remaining(&headers(&[("x-ratelimit-remaining", "12")])), Some(12));
+    }
+}
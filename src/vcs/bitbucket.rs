@@ -0,0 +1,246 @@
+// ============================================
+// WEBRANA CLI - Bitbucket Pull Requests
+// Cloud REST 2.0: pull request, paginated diffstat and comments
+// ============================================
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::api::Api;
+use super::{ChangedFile, Comment, FileStatus, ReviewComment, ReviewContext, Thread, VcsKind, VcsProvider};
+
+const PAGE_LEN: u32 = 100;
+
+pub struct Bitbucket {
+    api: Api,
+    /// `<api>/repositories/<workspace>/<repo>`
+    repo_url: String,
+    project: String,
+}
+
+impl Bitbucket {
+    /// `api_url` is the REST base, e.g. `https://api.bitbucket.org/2.0`
+    pub fn new(api_url: &str, project: &str, token: &str) -> Result<Self> {
+        Ok(Self {
+            api: Api::new("Bitbucket", "Authorization", format!("Bearer {}", token))?,
+            repo_url: format!("{}/repositories/{}", api_url.trim_end_matches('/'), project),
+            project: project.to_string(),
+        })
+    }
+
+    fn pr_url(&self, number: u64) -> String {
+        format!("{}/pullrequests/{}", self.repo_url, number)
+    }
+
+    /// Every value of a paginated list, following `next`
+    async fn all_pages(&self, url: &str) -> Result<Vec<Value>> {
+        let mut items = Vec::new();
+        let mut next = format!("{}?pagelen={}", url, PAGE_LEN);
+        loop {
+            let page = self.api.get(&next).await?.body;
+            items.extend(page["values"].as_array().cloned().unwrap_or_default());
+            match page["next"].as_str() {
+                Some(url) if !url.is_empty() => next = url.to_string(),
+                _ => return Ok(items),
+            }
+        }
+    }
+}
+
+fn text(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
+
+fn changed_file(entry: &Value) -> ChangedFile {
+    let status = match entry["status"].as_str().unwrap_or_default() {
+        "added" => FileStatus::Added,
+        "removed" => FileStatus::Deleted,
+        "renamed" => FileStatus::Renamed,
+        _ => FileStatus::Modified,
+    };
+    let new_path = entry["new"]["path"].as_str();
+    let old_path = entry["old"]["path"].as_str();
+    ChangedFile {
+        path: new_path.or(old_path).unwrap_or_default().to_string(),
+        old_path: (status == FileStatus::Renamed).then(|| old_path.unwrap_or_default().to_string()),
+        status,
+    }
+}
+
+/// Comments grouped into threads by their parents, in posting order
+fn threads(comments: &[Value]) -> Vec<Thread> {
+    let mut threads: Vec<Thread> = Vec::new();
+    let mut thread_of: HashMap<u64, usize> = HashMap::new();
+    for comment in comments {
+        if comment["deleted"].as_bool() == Some(true) {
+            continue;
+        }
+        let id = comment["id"].as_u64().unwrap_or_default();
+        let entry = Comment {
+            author: comment["user"]["display_name"]
+                .as_str()
+                .or(comment["user"]["nickname"].as_str())
+                .unwrap_or_default()
+                .to_string(),
+            body: text(&comment["content"]["raw"]),
+            created_at: text(&comment["created_on"]),
+        };
+        let parent = comment["parent"]["id"].as_u64().and_then(|parent| thread_of.get(&parent).copied());
+        let index = match parent {
+            Some(index) => {
+                threads[index].comments.push(entry);
+                index
+            }
+            None => {
+                let inline = &comment["inline"];
+                threads.push(Thread {
+                    id: id.to_string(),
+                    path: inline["path"].as_str().map(str::to_string),
+                    line: inline["to"].as_u64().or(inline["from"].as_u64()),
+                    resolved: !comment["resolution"].is_null(),
+                    comments: vec![entry],
+                });
+                threads.len() - 1
+            }
+        };
+        thread_of.insert(id, index);
+    }
+    threads
+}
+
+#[async_trait]
+impl VcsProvider for Bitbucket {
+    async fn review_context(&self, number: u64) -> Result<ReviewContext> {
+        let pr = self
+            .api
+            .get(&self.pr_url(number))
+            .await
+            .with_context(|| format!("Failed to fetch pull request #{}", number))?
+            .body;
+        let diffstat = self.all_pages(&format!("{}/diffstat", self.pr_url(number))).await?;
+        let comments = self.all_pages(&format!("{}/comments", self.pr_url(number))).await?;
+
+        Ok(ReviewContext {
+            provider: VcsKind::Bitbucket,
+            project: self.project.clone(),
+            number,
+            title: text(&pr["title"]),
+            description: text(&pr["description"]),
+            source_branch: text(&pr["source"]["branch"]["name"]),
+            target_branch: text(&pr["destination"]["branch"]["name"]),
+            web_url: text(&pr["links"]["html"]["href"]),
+            changed_files: diffstat.iter().map(changed_file).collect(),
+            threads: threads(&comments),
+        })
+    }
+
+    async fn post_comment(&self, number: u64, comment: &ReviewComment) -> Result<String> {
+        let payload = json!({
+            "content": {"raw": comment.body},
+            "inline": {"path": comment.path, "to": comment.line},
+        });
+        let created = self
+            .api
+            .post(&format!("{}/comments", self.pr_url(number)), &payload)
+            .await
+            .with_context(|| format!("Failed to comment on {}:{}", comment.path, comment.line))?;
+        Ok(created.body["id"].as_u64().map(|id| id.to_string()).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::mock::MockServer;
+
+    #[tokio::test]
+    async fn test_review_context_and_comment() {
+        let server = MockServer::start().await;
+        let pr = "/repositories/acme/api/pullrequests/7";
+        server.route(
+            "GET",
+            pr,
+            &[],
+            json!({
+                "title": "Retry uploads",
+                "description": "Adds backoff",
+                "source": {"branch": {"name": "retry"}},
+                "destination": {"branch": {"name": "develop"}},
+                "links": {"html": {"href": "https://bitbucket.org/acme/api/pull-requests/7"}}
+            }),
+        );
+        server.route(
+            "GET",
+            &format!("{}/diffstat?pagelen=100", pr),
+            &[],
+            json!({
+                "values": [
+                    {"status": "modified", "old": {"path": "src/upload.rs"}, "new": {"path": "src/upload.rs"}},
+                    {"status": "removed", "old": {"path": "legacy.rs"}, "new": null}
+                ],
+                "next": format!("{}{}/diffstat?pagelen=100&page=2", server.url(), pr)
+            }),
+        );
+        server.route(
+            "GET",
+            &format!("{}/diffstat?pagelen=100&page=2", pr),
+            &[],
+            json!({"values": [
+                {"status": "renamed", "old": {"path": "a.rs"}, "new": {"path": "b.rs"}},
+                {"status": "added", "old": null, "new": {"path": "src/backoff.rs"}}
+            ]}),
+        );
+        server.route(
+            "GET",
+            &format!("{}/comments?pagelen=100", pr),
+            &[],
+            json!({"values": [
+                {"id": 1, "content": {"raw": "Cap the delay?"}, "user": {"display_name": "Alice"},
+                 "created_on": "2026-01-02T10:00:00Z", "inline": {"path": "src/upload.rs", "to": 18, "from": null}},
+                {"id": 2, "content": {"raw": "Done"}, "user": {"display_name": "Bob"},
+                 "created_on": "2026-01-02T11:00:00Z", "parent": {"id": 1}},
+                {"id": 3, "content": {"raw": "old"}, "user": {"display_name": "Bob"}, "deleted": true},
+                {"id": 4, "content": {"raw": "Ship it"}, "user": {"display_name": "Carol"},
+                 "created_on": "2026-01-03T09:00:00Z", "resolution": {"type": "resolved"}}
+            ]}),
+        );
+        server.route("POST", &format!("{}/comments", pr), &[], json!({"id": 55}));
+
+        let bitbucket = Bitbucket::new(&server.url(), "acme/api", "bb-token").unwrap();
+        let context = bitbucket.review_context(7).await.unwrap();
+        assert_eq!(context.provider, VcsKind::Bitbucket);
+        assert_eq!((context.source_branch.as_str(), context.target_branch.as_str()), ("retry", "develop"));
+        let files: Vec<(&str, FileStatus)> = context.changed_files.iter().map(|f| (f.path.as_str(), f.status)).collect();
+        assert_eq!(
+            files,
+            vec![
+                ("src/upload.rs", FileStatus::Modified),
+                ("legacy.rs", FileStatus::Deleted),
+                ("b.rs", FileStatus::Renamed),
+                ("src/backoff.rs", FileStatus::Added),
+            ]
+        );
+        assert_eq!(context.changed_files[2].old_path.as_deref(), Some("a.rs"));
+        assert_eq!(context.threads.len(), 2);
+        let first = &context.threads[0];
+        assert_eq!((first.path.as_deref(), first.line, first.resolved), (Some("src/upload.rs"), Some(18), false));
+        let replies: Vec<&str> = first.comments.iter().map(|c| c.author.as_str()).collect();
+        assert_eq!(replies, vec!["Alice", "Bob"]);
+        assert!(context.threads[1].resolved);
+        assert!(server.requests().iter().all(|r| r.header("authorization") == Some("Bearer bb-token")));
+
+        let comment = ReviewComment {
+            path: "src/upload.rs".to_string(),
+            line: 21,
+            body: "Unbounded retries".to_string(),
+        };
+        assert_eq!(bitbucket.post_comment(7, &comment).await.unwrap(), "55");
+        let post = server.requests().into_iter().find(|r| r.method == "POST").unwrap();
+        assert_eq!(
+            post.json(),
+            json!({"content": {"raw": "Unbounded retries"}, "inline": {"path": "src/upload.rs", "to": 21}})
+        );
+    }
+}
@@ -0,0 +1,270 @@
+// ============================================
+// WEBRANA CLI - GitLab Merge Requests
+// REST v4: merge request, paginated diffs and discussions
+// ============================================
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use super::api::Api;
+use super::{ChangedFile, Comment, FileStatus, ReviewComment, ReviewContext, Thread, VcsKind, VcsProvider};
+
+const PER_PAGE: u32 = 100;
+
+pub struct GitLab {
+    api: Api,
+    /// `<instance>/api/v4/projects/<url-encoded path>`
+    project_url: String,
+    project: String,
+}
+
+impl GitLab {
+    /// `base_url` is the instance root, e.g. `https://gitlab.example.com`
+    pub fn new(base_url: &str, project: &str, token: &str) -> Result<Self> {
+        Ok(Self {
+            api: Api::new("GitLab", "PRIVATE-TOKEN", token.to_string())?,
+            project_url: format!(
+                "{}/api/v4/projects/{}",
+                base_url.trim_end_matches('/'),
+                url::form_urlencoded::byte_serialize(project.as_bytes()).collect::<String>()
+            ),
+            project: project.to_string(),
+        })
+    }
+
+    fn mr_url(&self, number: u64) -> String {
+        format!("{}/merge_requests/{}", self.project_url, number)
+    }
+
+    /// Every item of a paginated list, following `X-Next-Page`
+    async fn all_pages(&self, url: &str) -> Result<Vec<Value>> {
+        let mut items = Vec::new();
+        let mut page = "1".to_string();
+        loop {
+            let response = self
+                .api
+                .get(&format!("{}?per_page={}&page={}", url, PER_PAGE, page))
+                .await?;
+            items.extend(response.body.as_array().cloned().unwrap_or_default());
+            match response.headers.get("x-next-page").and_then(|v| v.to_str().ok()) {
+                Some(next) if !next.trim().is_empty() => page = next.trim().to_string(),
+                _ => return Ok(items),
+            }
+        }
+    }
+}
+
+fn text(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
+
+fn changed_file(diff: &Value) -> ChangedFile {
+    let status = if diff["new_file"].as_bool() == Some(true) {
+        FileStatus::Added
+    } else if diff["deleted_file"].as_bool() == Some(true) {
+        FileStatus::Deleted
+    } else if diff["renamed_file"].as_bool() == Some(true) {
+        FileStatus::Renamed
+    } else {
+        FileStatus::Modified
+    };
+    let path = match status {
+        FileStatus::Deleted => text(&diff["old_path"]),
+        _ => text(&diff["new_path"]),
+    };
+    ChangedFile {
+        path,
+        old_path: (status == FileStatus::Renamed).then(|| text(&diff["old_path"])),
+        status,
+    }
+}
+
+/// A discussion, or None when it only holds system notes
+fn thread(discussion: &Value) -> Option<Thread> {
+    let notes: Vec<&Value> = discussion["notes"]
+        .as_array()?
+        .iter()
+        .filter(|note| note["system"].as_bool() != Some(true))
+        .collect();
+    let first = notes.first()?;
+    let position = &first["position"];
+    let path = position["new_path"].as_str().or(position["old_path"].as_str()).map(str::to_string);
+    Some(Thread {
+        id: text(&discussion["id"]),
+        line: position["new_line"].as_u64().or(position["old_line"].as_u64()),
+        path,
+        resolved: notes.iter().any(|note| note["resolvable"].as_bool() == Some(true))
+            && notes
+                .iter()
+                .filter(|note| note["resolvable"].as_bool() == Some(true))
+                .all(|note| note["resolved"].as_bool() == Some(true)),
+        comments: notes
+            .iter()
+            .map(|note| Comment {
+                author: text(&note["author"]["username"]),
+                body: text(&note["body"]),
+                created_at: text(&note["created_at"]),
+            })
+            .collect(),
+    })
+}
+
+#[async_trait]
+impl VcsProvider for GitLab {
+    async fn review_context(&self, number: u64) -> Result<ReviewContext> {
+        let mr = self
+            .api
+            .get(&self.mr_url(number))
+            .await
+            .with_context(|| format!("Failed to fetch merge request !{}", number))?
+            .body;
+        let diffs = self.all_pages(&format!("{}/diffs", self.mr_url(number))).await?;
+        let discussions = self.all_pages(&format!("{}/discussions", self.mr_url(number))).await?;
+
+        Ok(ReviewContext {
+            provider: VcsKind::GitLab,
+            project: self.project.clone(),
+            number,
+            title: text(&mr["title"]),
+            description: text(&mr["description"]),
+            source_branch: text(&mr["source_branch"]),
+            target_branch: text(&mr["target_branch"]),
+            web_url: text(&mr["web_url"]),
+            changed_files: diffs.iter().map(changed_file).collect(),
+            threads: discussions.iter().filter_map(thread).collect(),
+        })
+    }
+
+    async fn post_comment(&self, number: u64, comment: &ReviewComment) -> Result<String> {
+        // Diff comments are anchored to the merge request's current versions
+        let mr = self.api.get(&self.mr_url(number)).await?.body;
+        let refs = &mr["diff_refs"];
+        let payload = json!({
+            "body": comment.body,
+            "position": {
+                "position_type": "text",
+                "base_sha": refs["base_sha"],
+                "start_sha": refs["start_sha"],
+                "head_sha": refs["head_sha"],
+                "old_path": comment.path,
+                "new_path": comment.path,
+                "new_line": comment.line,
+            }
+        });
+        let created = self
+            .api
+            .post(&format!("{}/discussions", self.mr_url(number)), &payload)
+            .await
+            .with_context(|| format!("Failed to comment on {}:{}", comment.path, comment.line))?;
+        Ok(text(&created.body["id"]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::mock::MockServer;
+
+    #[tokio::test]
+    async fn test_review_context_and_comment() {
+        let server = MockServer::start().await;
+        let mr = "/api/v4/projects/team%2Fapp/merge_requests/12";
+        server.route(
+            "GET",
+            mr,
+            &[],
+            json!({
+                "title": "Add parser",
+                "description": "Parses the config",
+                "source_branch": "feature/parser",
+                "target_branch": "main",
+                "web_url": "https://gitlab.example.com/team/app/-/merge_requests/12",
+                "diff_refs": {"base_sha": "b1", "start_sha": "s1", "head_sha": "h1"}
+            }),
+        );
+        server.route(
+            "GET",
+            &format!("{}/diffs?per_page=100&page=1", mr),
+            &[("x-next-page", "2")],
+            json!([
+                {"old_path": "src/lib.rs", "new_path": "src/lib.rs", "new_file": false, "renamed_file": false, "deleted_file": false},
+                {"old_path": "src/parse.rs", "new_path": "src/parse.rs", "new_file": true, "renamed_file": false, "deleted_file": false}
+            ]),
+        );
+        server.route(
+            "GET",
+            &format!("{}/diffs?per_page=100&page=2", mr),
+            &[("x-next-page", "")],
+            json!([
+                {"old_path": "old.rs", "new_path": "new.rs", "new_file": false, "renamed_file": true, "deleted_file": false},
+                {"old_path": "gone.rs", "new_path": "gone.rs", "new_file": false, "renamed_file": false, "deleted_file": true}
+            ]),
+        );
+        server.route(
+            "GET",
+            &format!("{}/discussions?per_page=100&page=1", mr),
+            &[],
+            json!([
+                {"id": "d1", "notes": [
+                    {"body": "Why unwrap?", "author": {"username": "alice"}, "created_at": "2026-01-02T10:00:00Z",
+                     "system": false, "resolvable": true, "resolved": false,
+                     "position": {"new_path": "src/lib.rs", "new_line": 42, "old_line": null}},
+                    {"body": "Fixed", "author": {"username": "bob"}, "created_at": "2026-01-02T11:00:00Z",
+                     "system": false, "resolvable": true, "resolved": false}
+                ]},
+                {"id": "d2", "notes": [{"body": "added 1 commit", "system": true, "author": {"username": "bob"}}]},
+                {"id": "d3", "notes": [
+                    {"body": "LGTM", "author": {"username": "carol"}, "created_at": "2026-01-03T09:00:00Z",
+                     "system": false, "resolvable": false}
+                ]}
+            ]),
+        );
+        server.route("POST", &format!("{}/discussions", mr), &[], json!({"id": "d9"}));
+
+        let gitlab = GitLab::new(&server.url(), "team/app", "glpat-test").unwrap();
+        let context = gitlab.review_context(12).await.unwrap();
+        assert_eq!(context.title, "Add parser");
+        assert_eq!((context.source_branch.as_str(), context.target_branch.as_str()), ("feature/parser", "main"));
+        let files: Vec<(&str, FileStatus)> = context.changed_files.iter().map(|f| (f.path.as_str(), f.status)).collect();
+        assert_eq!(
+            files,
+            vec![
+                ("src/lib.rs", FileStatus::Modified),
+                ("src/parse.rs", FileStatus::Added),
+                ("new.rs", FileStatus::Renamed),
+                ("gone.rs", FileStatus::Deleted),
+            ]
+        );
+        assert_eq!(context.changed_files[2].old_path.as_deref(), Some("old.rs"));
+        assert_eq!(context.threads.len(), 2);
+        let first = &context.threads[0];
+        assert_eq!((first.path.as_deref(), first.line, first.resolved), (Some("src/lib.rs"), Some(42), false));
+        assert_eq!(first.comments[1].author, "bob");
+        assert_eq!((context.threads[1].path.as_deref(), context.threads[1].comments[0].body.as_str()), (None, "LGTM"));
+        assert!(server.requests().iter().all(|r| r.header("private-token") == Some("glpat-test")));
+
+        let comment = ReviewComment {
+            path: "src/lib.rs".to_string(),
+            line: 40,
+            body: "Possible panic".to_string(),
+        };
+        assert_eq!(gitlab.post_comment(12, &comment).await.unwrap(), "d9");
+        let post = server.requests().into_iter().find(|r| r.method == "POST").unwrap();
+        assert_eq!(
+            post.json(),
+            json!({
+                "body": "Possible panic",
+                "position": {
+                    "position_type": "text",
+                    "base_sha": "b1",
+                    "start_sha": "s1",
+                    "head_sha": "h1",
+                    "old_path": "src/lib.rs",
+                    "new_path": "src/lib.rs",
+                    "new_line": 40
+                }
+            })
+        );
+    }
+}
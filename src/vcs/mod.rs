@@ -0,0 +1,418 @@
+// ============================================
+// WEBRANA CLI - Merge Request Context
+// GitLab and Bitbucket Cloud merge/pull requests as one ReviewContext
+// ============================================
+
+mod api;
+mod bitbucket;
+mod gitlab;
+#[cfg(test)]
+pub(crate) mod mock;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Settings;
+
+#[allow(unused_imports)]
+pub use bitbucket::Bitbucket;
+#[allow(unused_imports)]
+pub use gitlab::GitLab;
+
+/// Bitbucket Cloud REST API, unless `vcs.bitbucket_api_url` says otherwise
+pub const BITBUCKET_API_URL: &str = "https://api.bitbucket.org/2.0";
+
+/// Token fallbacks when `vcs.gitlab_token` / `vcs.bitbucket_token` aren't set
+pub const GITLAB_TOKEN_ENV: &str = "GITLAB_TOKEN";
+pub const BITBUCKET_TOKEN_ENV: &str = "BITBUCKET_TOKEN";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VcsKind {
+    GitLab,
+    Bitbucket,
+}
+
+impl VcsKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::GitLab => "gitlab",
+            Self::Bitbucket => "bitbucket",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+impl FileStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Modified => "modified",
+            Self::Deleted => "deleted",
+            Self::Renamed => "renamed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangedFile {
+    pub path: String,
+    /// Path before a rename
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    pub status: FileStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    pub author: String,
+    pub body: String,
+    pub created_at: String,
+}
+
+/// A discussion: a top-level comment and its replies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Thread {
+    pub id: String,
+    /// Set for comments on a line of the diff
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u64>,
+    pub resolved: bool,
+    pub comments: Vec<Comment>,
+}
+
+/// Everything a reviewer needs about a merge/pull request, the same shape
+/// for every provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewContext {
+    pub provider: VcsKind,
+    pub project: String,
+    pub number: u64,
+    pub title: String,
+    pub description: String,
+    pub source_branch: String,
+    pub target_branch: String,
+    pub web_url: String,
+    pub changed_files: Vec<ChangedFile>,
+    pub threads: Vec<Thread>,
+}
+
+impl ReviewContext {
+    /// Plain-text summary for the terminal and the model
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "{} #{} in {}: {}\n{} -> {}\n{}\n",
+            self.provider.as_str(),
+            self.number,
+            self.project,
+            self.title,
+            self.source_branch,
+            self.target_branch,
+            self.web_url
+        );
+        if !self.description.trim().is_empty() {
+            out.push_str(&format!("\nDescription:\n{}\n", self.description.trim()));
+        }
+
+        out.push_str(&format!("\nChanged files ({}):\n", self.changed_files.len()));
+        for file in &self.changed_files {
+            match &file.old_path {
+                Some(old) => out.push_str(&format!("  {} {} (from {})\n", file.status.as_str(), file.path, old)),
+                None => out.push_str(&format!("  {} {}\n", file.status.as_str(), file.path)),
+            }
+        }
+
+        let unresolved = self.threads.iter().filter(|t| !t.resolved).count();
+        out.push_str(&format!("\nDiscussions ({}, {} unresolved):\n", self.threads.len(), unresolved));
+        for thread in &self.threads {
+            let state = if thread.resolved { "resolved" } else { "unresolved" };
+            match (&thread.path, thread.line) {
+                (Some(path), Some(line)) => out.push_str(&format!("  [{}] {}:{}\n", state, path, line)),
+                (Some(path), None) => out.push_str(&format!("  [{}] {}\n", state, path)),
+                _ => out.push_str(&format!("  [{}] general\n", state)),
+            }
+            for comment in &thread.comments {
+                out.push_str(&format!("    {}: {}\n", comment.author, comment.body.trim().replace('\n', "\n      ")));
+            }
+        }
+        out
+    }
+}
+
+/// A finding to publish on a line of the new version of `path`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewComment {
+    pub path: String,
+    pub line: u64,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait VcsProvider: Send + Sync {
+    async fn review_context(&self, number: u64) -> Result<ReviewContext>;
+
+    /// Start a discussion on a diff line; returns the new thread's id
+    async fn post_comment(&self, number: u64, comment: &ReviewComment) -> Result<String>;
+}
+
+/// Which merge request: provider, API base, project path and number
+#[derive(Debug, Clone, PartialEq)]
+pub struct MrRef {
+    pub kind: VcsKind,
+    /// GitLab instance root (no `/api/v4`) or the Bitbucket API base
+    pub base_url: String,
+    /// `group/subgroup/project` or `workspace/repo`
+    pub project: String,
+    pub number: u64,
+}
+
+impl MrRef {
+    /// A merge/pull request web URL. GitLab may be self-hosted, also under
+    /// a path (`vcs.gitlab_url = "https://host/gitlab"`).
+    pub fn from_url(url: &str, settings: &Settings) -> Result<Self> {
+        let Some((scheme, rest)) = url.split_once("://") else {
+            bail!("Not a merge request URL: {}", url);
+        };
+        let rest = rest.split(['?', '#']).next().unwrap_or_default().trim_end_matches('/');
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+
+        if host.ends_with("bitbucket.org") {
+            let parts: Vec<&str> = path.split('/').collect();
+            let (project, number) = match parts[..] {
+                [workspace, repo, "pull-requests", number, ..] => (format!("{}/{}", workspace, repo), number),
+                _ => bail!("Not a Bitbucket pull request URL: {}", url),
+            };
+            return Ok(Self {
+                kind: VcsKind::Bitbucket,
+                base_url: bitbucket_api_url(settings),
+                project,
+                number: parse_number(number)?,
+            });
+        }
+
+        let Some((project, number)) = path.split_once("/-/merge_requests/") else {
+            bail!("Not a GitLab merge request or Bitbucket pull request URL: {}", url);
+        };
+        let number = number.split('/').next().unwrap_or_default();
+        let mut base_url = format!("{}://{}", scheme, host);
+        let mut project = project.to_string();
+        // Instances served under a path: strip it from the project
+        if let Some(configured) = settings.vcs.gitlab_url.as_deref().map(|u| u.trim_end_matches('/')) {
+            if let Some(prefix) = configured.strip_prefix(&base_url).filter(|p| !p.is_empty()) {
+                if let Some(stripped) = format!("/{}", project).strip_prefix(&format!("{}/", prefix)) {
+                    project = stripped.to_string();
+                    base_url = configured.to_string();
+                }
+            }
+        }
+        Ok(Self {
+            kind: VcsKind::GitLab,
+            base_url,
+            project,
+            number: parse_number(number)?,
+        })
+    }
+
+    /// Merge request `number` of the repository behind `remote` (a git
+    /// remote URL, SSH or HTTPS)
+    pub fn from_remote(remote: &str, number: u64, settings: &Settings) -> Result<Self> {
+        let remote = remote.trim();
+        let (host, path) = if let Some((_, rest)) = remote.split_once("://") {
+            let rest = rest.rsplit_once('@').map(|(_, r)| r).unwrap_or(rest);
+            rest.split_once('/').unwrap_or((rest, ""))
+        } else if let Some((user_host, path)) = remote.split_once(':') {
+            (user_host.rsplit_once('@').map(|(_, h)| h).unwrap_or(user_host), path)
+        } else {
+            bail!("Can't tell the host of git remote {}", remote);
+        };
+        let host = host.split(':').next().unwrap_or(host);
+        let project = path.trim_matches('/').trim_end_matches(".git").to_string();
+        if project.is_empty() {
+            bail!("Can't tell the project of git remote {}", remote);
+        }
+
+        if host.ends_with("bitbucket.org") {
+            return Ok(Self {
+                kind: VcsKind::Bitbucket,
+                base_url: bitbucket_api_url(settings),
+                project,
+                number,
+            });
+        }
+        if host.ends_with("github.com") {
+            bail!("GitHub pull requests aren't supported; vcs context covers GitLab and Bitbucket");
+        }
+        let base_url = match &settings.vcs.gitlab_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => format!("https://{}", host),
+        };
+        Ok(Self {
+            kind: VcsKind::GitLab,
+            base_url,
+            project,
+            number,
+        })
+    }
+
+    /// A URL, or a number (`123`, `!123`, `#123`) of the origin remote's
+    /// project
+    pub async fn resolve(target: &str, settings: &Settings) -> Result<Self> {
+        let target = target.trim();
+        if target.contains("://") {
+            return Self::from_url(target, settings);
+        }
+        let number = parse_number(target.trim_start_matches(['!', '#']))?;
+        let remote = crate::skills::run_git_command(&["remote", "get-url", "origin"], None)
+            .await
+            .map_err(|_| anyhow::anyhow!("No origin remote here; pass the merge request URL instead"))?;
+        Self::from_remote(&remote, number, settings)
+    }
+
+    /// The API client for this merge request's provider, with its token
+    pub fn provider(&self, settings: &Settings) -> Result<Box<dyn VcsProvider>> {
+        let vcs = &settings.vcs;
+        Ok(match self.kind {
+            VcsKind::GitLab => Box::new(GitLab::new(
+                trusted_gitlab_url(&self.base_url, settings)?,
+                &self.project,
+                &token(vcs.gitlab_token.as_deref(), GITLAB_TOKEN_ENV, "vcs.gitlab_token")?,
+            )?),
+            VcsKind::Bitbucket => Box::new(Bitbucket::new(
+                &self.base_url,
+                &self.project,
+                &token(vcs.bitbucket_token.as_deref(), BITBUCKET_TOKEN_ENV, "vcs.bitbucket_token")?,
+            )?),
+        })
+    }
+}
+
+/// `base_url` if the GitLab token may be sent there: https, and the host of
+/// `vcs.gitlab_url` or, without one, gitlab.com
+fn trusted_gitlab_url<'a>(base_url: &'a str, settings: &Settings) -> Result<&'a str> {
+    let url = url::Url::parse(base_url).with_context(|| format!("Invalid GitLab URL {}", base_url))?;
+    let host = url.host_str().unwrap_or_default();
+    let trusted = match settings.vcs.gitlab_url.as_deref() {
+        Some(configured) => url::Url::parse(configured)
+            .with_context(|| format!("Invalid vcs.gitlab_url {}", configured))?
+            .host_str()
+            .map(str::to_string),
+        None => Some("gitlab.com".to_string()),
+    };
+    if trusted.as_deref() != Some(host) {
+        bail!(
+            "Refusing to send the GitLab token to {}: only {} is trusted (set vcs.gitlab_url for a self-hosted instance)",
+            host,
+            trusted.as_deref().unwrap_or("the configured host")
+        );
+    }
+    if url.scheme() != "https" {
+        bail!("Refusing to send the GitLab token over {}: {} must use https", url.scheme(), base_url);
+    }
+    Ok(base_url)
+}
+
+fn parse_number(text: &str) -> Result<u64> {
+    text.parse()
+        .map_err(|_| anyhow::anyhow!("'{}' is not a merge request number", text))
+}
+
+fn bitbucket_api_url(settings: &Settings) -> String {
+    settings
+        .vcs
+        .bitbucket_api_url
+        .as_deref()
+        .unwrap_or(BITBUCKET_API_URL)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// The configured token (possibly an `enc:` value, already decrypted), else `env`
+fn token(configured: Option<&str>, env: &str, key: &str) -> Result<String> {
+    if let Some(token) = configured.filter(|t| !t.is_empty()) {
+        return Ok(token.to_string());
+    }
+    match std::env::var(env) {
+        Ok(token) if !token.is_empty() => Ok(token),
+        _ => bail!(
+            "No API token: set {} (`webrana config encrypt {}` keeps it encrypted) or {}",
+            key,
+            key,
+            env
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mr_ref_from_urls() {
+        let settings = Settings::default();
+        let gitlab = MrRef::from_url("https://gitlab.com/group/sub/app/-/merge_requests/42/diffs?x=1", &settings).unwrap();
+        assert_eq!(
+            gitlab,
+            MrRef {
+                kind: VcsKind::GitLab,
+                base_url: "https://gitlab.com".to_string(),
+                project: "group/sub/app".to_string(),
+                number: 42,
+            }
+        );
+
+        let mut settings = Settings::default();
+        settings.vcs.gitlab_url = Some("https://git.corp.example/gitlab/".to_string());
+        let hosted = MrRef::from_url("https://git.corp.example/gitlab/team/app/-/merge_requests/7", &settings).unwrap();
+        assert_eq!(hosted.base_url, "https://git.corp.example/gitlab");
+        assert_eq!(hosted.project, "team/app");
+
+        let bitbucket = MrRef::from_url("https://bitbucket.org/acme/api/pull-requests/9/overview", &settings).unwrap();
+        assert_eq!(bitbucket.kind, VcsKind::Bitbucket);
+        assert_eq!((bitbucket.base_url.as_str(), bitbucket.project.as_str(), bitbucket.number), (BITBUCKET_API_URL, "acme/api", 9));
+
+        assert!(MrRef::from_url("https://gitlab.com/group/app/-/issues/3", &settings).is_err());
+    }
+
+    #[test]
+    fn test_mr_ref_from_remotes() {
+        let settings = Settings::default();
+        let ssh = MrRef::from_remote("git@gitlab.example.com:team/app.git", 5, &settings).unwrap();
+        assert_eq!((ssh.base_url.as_str(), ssh.project.as_str()), ("https://gitlab.example.com", "team/app"));
+
+        let port = MrRef::from_remote("ssh://git@gitlab.example.com:2222/team/app.git", 5, &settings).unwrap();
+        assert_eq!(port.base_url, "https://gitlab.example.com");
+
+        let bitbucket = MrRef::from_remote("https://me@bitbucket.org/acme/api.git", 3, &settings).unwrap();
+        assert_eq!((bitbucket.kind, bitbucket.project.as_str()), (VcsKind::Bitbucket, "acme/api"));
+
+        assert!(MrRef::from_remote("git@github.com:o/r.git", 1, &settings).is_err());
+    }
+
+    #[test]
+    fn test_gitlab_token_only_goes_to_trusted_hosts() {
+        let settings = Settings::default();
+        assert!(trusted_gitlab_url("https://gitlab.com", &settings).is_ok());
+        assert!(trusted_gitlab_url("http://gitlab.com", &settings).is_err());
+        assert!(trusted_gitlab_url("https://gitlab.com.evil.example", &settings).is_err());
+        let evil = MrRef::from_url("https://evil.example/group/app/-/merge_requests/1", &settings).unwrap();
+        let err = evil.provider(&settings).err().unwrap().to_string();
+        assert!(err.contains("Refusing to send the GitLab token to evil.example"), "{}", err);
+
+        let mut settings = Settings::default();
+        settings.vcs.gitlab_url = Some("https://git.corp.example/gitlab".to_string());
+        assert!(trusted_gitlab_url("https://git.corp.example/gitlab", &settings).is_ok());
+        assert!(trusted_gitlab_url("http://git.corp.example/gitlab", &settings).is_err());
+        assert!(trusted_gitlab_url("https://gitlab.com", &settings).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+// ============================================
+// WEBRANA CLI - Scripted Chat Test
+// Two-turn `chat --script` against the replay provider
+// ============================================
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn webrana(cwd: &Path, home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_webrana"))
+        .args(args)
+        .current_dir(cwd)
+        .env("HOME", home)
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("CI", "1")
+        .env_remove("OPENAI_API_KEY")
+        .output()
+        .expect("failed to run webrana")
+}
+
+/// Default config with a `replay` model answering from `responses`
+fn replay_config(project: &Path, home: &Path, responses: &Path) {
+    let out = webrana(project, home, &["config", "set", "default_model", "replay"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let path = home.join("config/webrana/config.toml");
+    let mut config = std::fs::read_to_string(&path).unwrap();
+    config.push_str(&format!(
+        "\n[models.replay]\nprovider = \"replay\"\nmodel = \"recorded\"\nbase_url = {:?}\n",
+        responses.display().to_string()
+    ));
+    std::fs::write(&path, config).unwrap();
+}
+
+const SCRIPT: &str = "\
+turns:
+  - message: Which license does this project use?
+    expect:
+      - MIT
+      - tool: read_file
+  - message: Is there any GPL code?
+    expect:
+      - not: GPL
+";
+
+#[test]
+fn test_chat_script_with_replay_provider() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let home = tmp.path().join("home");
+    let project = tmp.path().join("project");
+    std::fs::create_dir_all(&project).unwrap();
+    std::fs::write(project.join("LICENSE"), "MIT License\n").unwrap();
+    std::fs::write(project.join("script.yaml"), SCRIPT).unwrap();
+    let responses = tmp.path().join("replay.yaml");
+    std::fs::write(
+        &responses,
+        "- content: Let me read it.\n  tool_calls:\n    - name: read_file\n      arguments: {path: LICENSE}\n\
+         - The project is MIT licensed.\n\
+         - No, every dependency is permissively licensed.\n",
+    )
+    .unwrap();
+    replay_config(&project, &home, &responses);
+
+    let out = webrana(&project, &home, &["chat", "--script", "script.yaml", "--transcript", "out.md"]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(out.status.success(), "{}\n{}", stdout, String::from_utf8_lossy(&out.stderr));
+    assert!(stdout.contains("Turn 1/2") && stdout.contains("Turn 2/2"), "{}", stdout);
+
+    let transcript = std::fs::read_to_string(project.join("out.md")).unwrap();
+    assert!(transcript.starts_with("# Webrana conversation\n"), "{}", transcript);
+    assert!(transcript.contains("- Model: recorded\n"));
+    let turns = [
+        "## You\n\nWhich license does this project use?",
+        "### Tool result `replay-1-1`",
+        "MIT License",
+        "## Assistant\n\nThe project is MIT licensed.",
+        "## You\n\nIs there any GPL code?",
+        "## Assistant\n\nNo, every dependency is permissively licensed.",
+    ];
+    let mut rest = transcript.as_str();
+    for part in turns {
+        let at = rest.find(part).unwrap_or_else(|| panic!("missing {:?} in order:\n{}", part, transcript));
+        rest = &rest[at + part.len()..];
+    }
+
+    // The same script against answers that miss three expectations
+    std::fs::write(&responses, "- It is GPL licensed.\n- Yes, GPL.\n").unwrap();
+    let out = webrana(&project, &home, &["chat", "--script", "script.yaml"]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert_eq!(out.status.code(), Some(1), "{}", stdout);
+    assert!(stdout.contains("3 expectation(s) not met"), "{}", stdout);
+    assert!(stdout.contains("  - turn 1: assistant mentions \"MIT\""));
+    assert!(stdout.contains("  - turn 1: assistant calls read_file"));
+    assert!(stdout.contains("  - turn 2: assistant does not mention \"GPL\""));
+}
+
+#[test]
+fn test_chat_script_parse_error_names_turn() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let home = tmp.path().join("home");
+    std::fs::write(tmp.path().join("bad.yaml"), "- hello\n- expect: [MIT]\n").unwrap();
+
+    let out = webrana(tmp.path(), &home, &["chat", "--script", "bad.yaml"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(!out.status.success());
+    assert!(stderr.contains("Turn 2") && stderr.contains("missing 'message'"), "{}", stderr);
+}
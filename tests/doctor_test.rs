@@ -0,0 +1,132 @@
+// ============================================
+// WEBRANA CLI - Doctor Security Section Test
+// Fabricated audit files and scan stamp -> `webrana doctor [--json]`
+// ============================================
+
+use std::path::Path;
+use std::process::{Command, Output};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn webrana(cwd: &Path, home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_webrana"))
+        .args(args)
+        .current_dir(cwd)
+        .env("HOME", home)
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .output()
+        .expect("failed to run webrana")
+}
+
+#[test]
+fn test_doctor_security_section() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let home = tmp.path().join("home");
+    let project = tmp.path().join("project");
+    let audit_dir = home.join("data/webrana-cli/audit");
+    std::fs::create_dir_all(&audit_dir).unwrap();
+    std::fs::create_dir_all(project.join(".webrana")).unwrap();
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let old = now - 30 * 86400;
+    std::fs::write(
+        audit_dir.join("audit.log"),
+        format!("[{now}] CRITICAL SecurityViolation: path escape\n[{now}] WARN CommandBlocked: rm -rf /\n"),
+    )
+    .unwrap();
+    std::fs::write(
+        audit_dir.join("audit.log.1"),
+        format!("[{now}] WARN CommandBlocked: curl | sh\n[{old}] WARN CommandBlocked: too old\n"),
+    )
+    .unwrap();
+    std::fs::write(
+        project.join(".webrana/last-scan.json"),
+        format!(
+            r#"{{"time": {}, "total_secrets": 4, "files_with_secrets": 2, "by_severity": {{"Critical": 1, "High": 3}}}}"#,
+            now - 2 * 86400
+        ),
+    )
+    .unwrap();
+
+    let out = webrana(&project, &home, &["doctor", "--json"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let checks: Vec<serde_json::Value> = serde_json::from_slice(&out.stdout).unwrap();
+    let check = |name: &str| {
+        checks
+            .iter()
+            .find(|c| c["section"] == "Security" && c["name"] == name)
+            .unwrap_or_else(|| panic!("missing {}", name))
+            .clone()
+    };
+    assert_eq!(check("Audit log")["status"], "ok");
+    let events = check("Security events (7d)");
+    assert_eq!(events["status"], "warn");
+    assert_eq!(events["detail"], "1 SecurityViolation, 2 CommandBlocked (2 audit files)");
+    assert_eq!(check("Shell sandbox")["status"], "warn");
+    // The default config leaves every safety option off
+    let policy = check("Safety policy");
+    assert_eq!(policy["status"], "warn");
+    assert!(policy["detail"].as_str().unwrap().ends_with("[safety]: no restrictions"));
+    let scan = check("Last secret scan");
+    assert_eq!(scan["status"], "ok");
+    assert_eq!(scan["detail"], "2d ago: 4 findings in 2 files");
+    assert!(scan.get("fix").is_none());
+    assert_eq!(check("Critical findings")["status"], "fail");
+
+    let out = webrana(&project, &home, &["doctor"]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("\nSecurity\n"), "{}", stdout);
+    assert!(stdout.contains("  Security events (7d)... WARN (1 SecurityViolation, 2 CommandBlocked (2 audit files))"));
+    assert!(stdout.contains("  Last secret scan... OK (2d ago: 4 findings in 2 files)"));
+    assert!(stdout.contains("  Critical findings... FAIL (1 in last scan"));
+
+    // A missing stamp is fixable
+    std::fs::remove_file(project.join(".webrana/last-scan.json")).unwrap();
+    let out = webrana(&project, &home, &["doctor", "--json"]);
+    let checks: Vec<serde_json::Value> = serde_json::from_slice(&out.stdout).unwrap();
+    let scan = checks.iter().find(|c| c["name"] == "Last secret scan").unwrap();
+    assert_eq!(scan["fix"], "run_scan");
+}
+
+#[test]
+fn test_scan_writes_stamp() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let home = tmp.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    std::fs::write(tmp.path().join("config.py"), "API_KEY = \"sk-abcdefghijklmnopqrstuvwxyz123456\"\n").unwrap();
+
+    webrana(tmp.path(), &home, &["scan", "--format", "json"]);
+    let stamp: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(tmp.path().join(".webrana/last-scan.json")).unwrap()).unwrap();
+    assert!(stamp["time"].as_u64().unwrap() > 0);
+    assert!(stamp["total_secrets"].as_u64().unwrap() >= 1);
+}
+
+#[test]
+fn test_doctor_reports_missing_git() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let home = tmp.path().join("home");
+    let empty_path = tmp.path().join("bin");
+    std::fs::create_dir_all(&home).unwrap();
+    std::fs::create_dir_all(&empty_path).unwrap();
+
+    let out = Command::new(env!("CARGO_BIN_EXE_webrana"))
+        .args(["doctor", "--json"])
+        .current_dir(tmp.path())
+        .env("HOME", &home)
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("PATH", &empty_path)
+        .env_remove("WEBRANA_GIT")
+        .output()
+        .expect("failed to run webrana");
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let checks: Vec<serde_json::Value> = serde_json::from_slice(&out.stdout).unwrap();
+    let git = checks.iter().find(|c| c["name"] == "Git").unwrap();
+    assert_eq!(git["status"], "fail");
+    assert!(
+        git["detail"].as_str().unwrap().starts_with("git was not found on PATH"),
+        "{}",
+        git["detail"]
+    );
+}
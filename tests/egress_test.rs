@@ -0,0 +1,80 @@
+// ============================================
+// WEBRANA CLI - Egress Allowlist Test
+// `skills run web_fetch` against a local server, allowed and refused
+// ============================================
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn webrana(cwd: &Path, home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_webrana"))
+        .args(args)
+        .current_dir(cwd)
+        .env("HOME", home)
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env_remove("WEBRANA_OFFLINE")
+        .output()
+        .expect("failed to run webrana")
+}
+
+/// Serves one HTML page to every connection; returns the port
+fn serve_page(body: &'static str) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request);
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    port
+}
+
+fn fetch(home: &Path, url: &str) -> serde_json::Value {
+    let args = format!(r#"{{"url": "{}"}}"#, url);
+    let out = webrana(home, home, &["skills", "run", "web_fetch", "--args", &args, "--json"]);
+    serde_json::from_slice(&out.stdout)
+        .unwrap_or_else(|_| panic!("{}\n{}", String::from_utf8_lossy(&out.stdout), String::from_utf8_lossy(&out.stderr)))
+}
+
+#[test]
+fn test_web_fetch_respects_egress_allowlist() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let home = tmp.path();
+    let port = serve_page("<html><body><h1>Changelog</h1><p>1.2.0: faster &amp; smaller</p></body></html>");
+
+    let allow = format!("127.0.0.1:{}", port);
+    let out = webrana(home, home, &["config", "set", "safety.egress_allowlist", &allow]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+
+    let result = fetch(home, &format!("http://127.0.0.1:{}/changelog", port));
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["output"], "Changelog\n1.2.0: faster & smaller\n");
+
+    // Same server by another name, and another port: neither is listed
+    let config = home.join("config/webrana/config.toml");
+    for url in [format!("http://localhost:{}/", port), format!("http://127.0.0.1:{}/", port + 1)] {
+        let result = fetch(home, &url);
+        assert_eq!(result["success"], false, "{}", result);
+        let error = result["error"].as_str().unwrap();
+        assert!(error.starts_with("Egress refused for web_fetch: "), "{}", error);
+        assert!(error.contains("is not in safety.egress_allowlist"), "{}", error);
+        assert!(error.contains(&config.display().to_string()), "{}", error);
+    }
+
+    // Without an allowlist, loopback is still refused
+    let out = webrana(home, home, &["config", "unset", "safety.egress_allowlist"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let result = fetch(home, &format!("http://127.0.0.1:{}/", port));
+    assert!(result["error"].as_str().unwrap().contains("resolves to non-public address 127.0.0.1"), "{}", result);
+}
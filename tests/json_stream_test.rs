@@ -0,0 +1,111 @@
+// ============================================
+// WEBRANA CLI - JSON Stream Test
+// `ask --json-stream` against the replay provider
+// ============================================
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn webrana(cwd: &Path, home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_webrana"))
+        .args(args)
+        .current_dir(cwd)
+        .env("HOME", home)
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("CI", "1")
+        .env_remove("OPENAI_API_KEY")
+        .output()
+        .expect("failed to run webrana")
+}
+
+/// A project whose default `replay` model answers from `responses`
+fn project_with_replay(tmp: &Path, responses: &str) -> (std::path::PathBuf, std::path::PathBuf) {
+    let home = tmp.join("home");
+    let project = tmp.join("project");
+    std::fs::create_dir_all(&project).unwrap();
+    std::fs::write(project.join("LICENSE"), "MIT License\n\nPermission is hereby granted\n").unwrap();
+    let replay = tmp.join("replay.yaml");
+    std::fs::write(&replay, responses).unwrap();
+
+    let out = webrana(&project, &home, &["config", "set", "default_model", "replay"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let path = home.join("config/webrana/config.toml");
+    let mut config = std::fs::read_to_string(&path).unwrap();
+    config.push_str(&format!(
+        "\n[models.replay]\nprovider = \"replay\"\nmodel = \"recorded\"\nbase_url = {:?}\n",
+        replay.display().to_string()
+    ));
+    std::fs::write(&path, config).unwrap();
+    (project, home)
+}
+
+/// Every stdout line as JSON; panics on anything else
+fn events(out: &Output) -> Vec<serde_json::Value> {
+    String::from_utf8(out.stdout.clone())
+        .expect("stdout is not UTF-8")
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|_| panic!("not JSON: {:?}", line)))
+        .collect()
+}
+
+fn kinds(events: &[serde_json::Value]) -> Vec<&str> {
+    events.iter().map(|e| e["event"].as_str().unwrap()).collect()
+}
+
+const TOOL_THEN_ANSWER: &str = "\
+- content: \"Let me read it. \"
+  tool_calls:
+    - name: read_file
+      arguments: {path: LICENSE}
+- \"It is MIT licensed — très permissive ✓\"
+";
+
+#[test]
+fn test_ask_json_stream_events() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let (project, home) = project_with_replay(tmp.path(), TOOL_THEN_ANSWER);
+
+    let out = webrana(&project, &home, &["ask", "--json-stream", "Which license is this?"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let events = events(&out);
+    assert_eq!(kinds(&events), ["start", "attempt", "delta", "tool_call", "tool_result", "attempt", "delta", "done"]);
+
+    assert_eq!(events[0]["model"], "recorded");
+    assert_eq!(events[0]["provider"], "replay");
+    assert_eq!((events[1]["attempt"].as_u64(), events[5]["attempt"].as_u64()), (Some(1), Some(1)));
+    assert_eq!(events[1]["provider"], "replay");
+    assert_eq!(events[3]["name"], "read_file");
+    assert_eq!(events[3]["arguments"]["path"], "LICENSE");
+    assert_eq!(events[4]["name"], "read_file");
+    assert_eq!(events[4]["ok"], true);
+    assert!(events[4]["summary"].as_str().unwrap().contains("MIT License"), "{}", events[4]);
+
+    let deltas: String = events
+        .iter()
+        .filter(|e| e["event"] == "delta")
+        .map(|e| e["text"].as_str().unwrap())
+        .collect();
+    let done = events.last().unwrap();
+    assert_eq!(done["response"].as_str().unwrap(), deltas);
+    assert!(deltas.ends_with("très permissive ✓"));
+    assert!(done["usage"]["output_tokens"].as_u64().unwrap() > 0);
+    assert_eq!(done["provenance"]["provider"], "replay");
+}
+
+#[test]
+fn test_ask_json_stream_ends_with_error_event() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    // The tool call is answered, then the replay runs out mid-turn
+    let (project, home) = project_with_replay(
+        tmp.path(),
+        "- content: Reading it.\n  tool_calls:\n    - name: read_file\n      arguments: {path: LICENSE}\n",
+    );
+
+    let out = webrana(&project, &home, &["ask", "--json-stream", "Which license is this?"]);
+    assert!(!out.status.success());
+    let events = events(&out);
+    assert_eq!(kinds(&events), ["start", "attempt", "delta", "tool_call", "tool_result", "attempt", "error"]);
+    let message = events.last().unwrap()["message"].as_str().unwrap();
+    assert!(message.contains("no response for request 2"), "{}", message);
+}
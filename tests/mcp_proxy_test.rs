@@ -0,0 +1,105 @@
+// ============================================
+// WEBRANA CLI - MCP Proxy Integration Tests
+// ============================================
+
+#![cfg(unix)]
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Stdio MCP server with one `ping` tool that answers "pong"
+const MOCK_UPSTREAM: &str = r#"while IFS= read -r line; do
+  case "$line" in
+    *'"method":"initialize"'*)
+      echo '{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2024-11-05","capabilities":{"tools":{}},"serverInfo":{"name":"mock","version":"1.0"}}}' ;;
+    *'"method":"tools/list"'*)
+      echo '{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"ping","description":"Answers pong","inputSchema":{"type":"object"}}]}}' ;;
+    *'"method":"tools/call"'*'"name":"ping"'*)
+      echo '{"jsonrpc":"2.0","id":3,"result":{"content":[{"type":"text","text":"pong from mock"}]}}' ;;
+  esac
+done
+"#;
+
+struct Proxy(Child);
+
+impl Drop for Proxy {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+fn connect(port: u16) -> TcpStream {
+    let deadline = Instant::now() + Duration::from_secs(20);
+    loop {
+        match TcpStream::connect(("127.0.0.1", port)) {
+            Ok(stream) => return stream,
+            Err(_) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(100)),
+            Err(e) => panic!("proxy did not start: {}", e),
+        }
+    }
+}
+
+fn request(stream: &mut TcpStream, id: u64, method: &str, params: Value) -> Value {
+    let message = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+    writeln!(stream, "{}", message).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream.try_clone().unwrap()).read_line(&mut line).unwrap();
+    let response: Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(response["id"], id);
+    response
+}
+
+/// The proxy lists Webrana and upstream tools and routes namespaced calls
+#[test]
+fn test_mcp_proxy_merges_and_routes() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("upstream.sh");
+    std::fs::write(&script, MOCK_UPSTREAM).unwrap();
+    let config = dir.path().join("mcp.toml");
+    std::fs::write(
+        &config,
+        format!(
+            "[servers.mock]\ncommand = \"sh\"\nargs = [\"{}\"]\nenabled = true\n",
+            script.display()
+        ),
+    )
+    .unwrap();
+
+    let port = free_port();
+    let _proxy = Proxy(
+        Command::new(env!("CARGO_BIN_EXE_webrana"))
+            .args(["--no-banner", "mcp", "proxy", "--port", &port.to_string()])
+            .arg("--config")
+            .arg(&config)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+    let mut stream = connect(port);
+
+    let listed = request(&mut stream, 1, "tools/list", json!({}));
+    let names: Vec<&str> = listed["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap())
+        .collect();
+    assert!(names.contains(&"read_file"), "{:?}", names);
+    assert!(names.contains(&"mock__ping"), "{:?}", names);
+    assert!(!names.contains(&"ping"));
+
+    let called = request(&mut stream, 2, "tools/call", json!({"name": "mock__ping", "arguments": {}}));
+    assert_eq!(called["result"]["content"][0]["text"], "pong from mock");
+
+    let missing = request(&mut stream, 3, "tools/call", json!({"name": "mock__nope", "arguments": {}}));
+    assert!(missing["error"]["message"].as_str().unwrap().contains("not found"));
+}
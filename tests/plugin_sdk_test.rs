@@ -0,0 +1,86 @@
+// ============================================
+// WEBRANA CLI - Plugin SDK Scaffold Test
+// `plugin new` -> wasm32 build -> `plugin install` -> `plugin run`
+// ============================================
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn webrana(cwd: &Path, home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_webrana"))
+        .args(args)
+        .current_dir(cwd)
+        .env("HOME", home)
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .output()
+        .expect("failed to run webrana")
+}
+
+/// The test needs the wasm32-unknown-unknown standard library
+fn wasm_target_installed() -> bool {
+    Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .ok()
+        .map(|out| PathBuf::from(String::from_utf8_lossy(&out.stdout).trim()))
+        .is_some_and(|sysroot| sysroot.join("lib/rustlib/wasm32-unknown-unknown").exists())
+}
+
+#[test]
+fn test_scaffolded_plugin_builds_installs_and_runs() {
+    if !wasm_target_installed() {
+        eprintln!("skipping: rustup target add wasm32-unknown-unknown to run this test");
+        return;
+    }
+
+    let tmp = tempfile::TempDir::new().unwrap();
+    let home = tmp.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+
+    let out = webrana(tmp.path(), &home, &["plugin", "new", "hello-sdk", "--skill", "greet"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let project = tmp.path().join("hello-sdk");
+
+    // The SDK is not on crates.io in this checkout; build against the local one
+    let sdk = Path::new(env!("CARGO_MANIFEST_DIR")).join("plugin-sdk");
+    let cargo_toml = project.join("Cargo.toml");
+    let manifest = std::fs::read_to_string(&cargo_toml).unwrap();
+    std::fs::write(
+        &cargo_toml,
+        format!(
+            "{}\n[patch.crates-io]\nwebrana-plugin-sdk = {{ path = {:?} }}\n",
+            manifest,
+            sdk.display().to_string()
+        ),
+    )
+    .unwrap();
+
+    let target_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/plugin-sdk-test");
+    let build = Command::new(env!("CARGO"))
+        .args(["build", "--target", "wasm32-unknown-unknown", "--release"])
+        .current_dir(&project)
+        .env("CARGO_TARGET_DIR", &target_dir)
+        .output()
+        .unwrap();
+    assert!(build.status.success(), "{}", String::from_utf8_lossy(&build.stderr));
+    std::fs::copy(
+        target_dir.join("wasm32-unknown-unknown/release/hello_sdk.wasm"),
+        project.join("plugin.wasm"),
+    )
+    .unwrap();
+
+    let out = webrana(tmp.path(), &home, &["plugin", "install", "hello-sdk"]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("Installed hello-sdk v0.1.0"), "{}", stdout);
+
+    let out = webrana(
+        tmp.path(),
+        &home,
+        &["plugin", "run", "hello-sdk", "greet", "--params", r#"{"name": "Ada"}"#],
+    );
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let result: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(result["message"], "Hello, Ada!");
+    assert!(String::from_utf8_lossy(&out.stderr).contains("[hello-sdk] greet called for Ada"));
+}